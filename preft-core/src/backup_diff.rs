@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::models::{Category, Flow};
+
+/// Records present only in the newer side, only in the older side, or in
+/// both but with different contents. Records are matched by id.
+#[derive(Debug, Clone)]
+pub struct RecordDiff<T> {
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+    /// (before, after) pairs.
+    pub changed: Vec<(T, T)>,
+}
+
+impl<T> RecordDiff<T> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// What changed between two copies of the data -- two backups, or a backup
+/// and the live database -- so a restore or a move to another machine can be
+/// checked for anything lost along the way.
+#[derive(Debug, Clone)]
+pub struct DataDiff {
+    pub categories: RecordDiff<Category>,
+    pub flows: RecordDiff<Flow>,
+    /// Category id -> name across both sides (the newer name wins), for
+    /// labeling flows in `describe`.
+    category_names: HashMap<String, String>,
+}
+
+impl DataDiff {
+    pub fn compare(
+        before_categories: &[Category],
+        before_flows: &[Flow],
+        after_categories: &[Category],
+        after_flows: &[Flow],
+    ) -> Self {
+        Self {
+            categories: diff_records(before_categories, after_categories, |c| &c.id),
+            flows: diff_records(before_flows, after_flows, |f| &f.id),
+            category_names: before_categories.iter()
+                .chain(after_categories)
+                .map(|c| (c.id.clone(), c.name.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty() && self.flows.is_empty()
+    }
+
+    /// One line per difference, e.g. `Changed flow 2024-03-01 "Rent"
+    /// (1200.00) in Housing: amount, description`.
+    pub fn describe(&self) -> Vec<String> {
+        let flow_label = |flow: &Flow| {
+            let category = self.category_names.get(&flow.category_id).unwrap_or(&flow.category_id);
+            format!("{} \"{}\" ({:.2}) in {}", flow.date, flow.description, flow.amount, category)
+        };
+
+        let mut lines = Vec::new();
+        for category in &self.categories.added {
+            lines.push(format!("Added category \"{}\"", category.name));
+        }
+        for category in &self.categories.removed {
+            lines.push(format!("Removed category \"{}\"", category.name));
+        }
+        for (before, after) in &self.categories.changed {
+            lines.push(format!("Changed category \"{}\": {}", after.name, changed_fields(before, after).join(", ")));
+        }
+        for flow in &self.flows.added {
+            lines.push(format!("Added flow {}", flow_label(flow)));
+        }
+        for flow in &self.flows.removed {
+            lines.push(format!("Removed flow {}", flow_label(flow)));
+        }
+        for (before, after) in &self.flows.changed {
+            lines.push(format!("Changed flow {}: {}", flow_label(after), changed_fields(before, after).join(", ")));
+        }
+        lines
+    }
+}
+
+/// Matches records by id, keeping each side's order (before's for removed
+/// and changed records, after's for added ones).
+fn diff_records<T: Clone + Serialize>(before: &[T], after: &[T], id: impl Fn(&T) -> &String) -> RecordDiff<T> {
+    let after_by_id: HashMap<&str, &T> = after.iter().map(|r| (id(r).as_str(), r)).collect();
+    let before_ids: HashSet<&str> = before.iter().map(|r| id(r).as_str()).collect();
+
+    let mut diff = RecordDiff { added: Vec::new(), removed: Vec::new(), changed: Vec::new() };
+    for record in before {
+        match after_by_id.get(id(record).as_str()) {
+            None => diff.removed.push(record.clone()),
+            Some(other) if !changed_fields(record, *other).is_empty() => {
+                diff.changed.push((record.clone(), (*other).clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    diff.added = after.iter()
+        .filter(|r| !before_ids.contains(id(r).as_str()))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Names of the top-level fields that differ. Compared as JSON (like
+/// `utils::flow_discrepancies`) so map ordering doesn't count as a change.
+fn changed_fields<T: Serialize>(before: &T, after: &T) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(before)), Ok(serde_json::Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return Vec::new();
+    };
+    let mut fields: Vec<String> = before.keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{FlowType, TaxDeductionInfo};
+    use chrono::NaiveDate;
+
+    fn category(id: &str, name: &str) -> Category {
+        Category {
+            id: id.to_string(),
+            name: name.to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: Vec::new(),
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    fn flow(id: &str, amount: f64) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            amount,
+            category_id: "housing".to_string(),
+            description: "Rent".to_string(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn compare_reports_added_removed_and_changed_records_by_id() {
+        let before_categories = vec![category("housing", "Housing"), category("old", "Old")];
+        let after_categories = vec![category("housing", "Home"), category("new", "New")];
+        let before_flows = vec![flow("same", 10.0), flow("edited", 20.0), flow("gone", 30.0)];
+        let after_flows = vec![flow("same", 10.0), flow("edited", 25.0), flow("fresh", 40.0)];
+
+        let diff = DataDiff::compare(&before_categories, &before_flows, &after_categories, &after_flows);
+
+        assert_eq!(diff.categories.added.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec!["new"]);
+        assert_eq!(diff.categories.removed.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec!["old"]);
+        assert_eq!(diff.categories.changed.len(), 1);
+        assert_eq!(diff.flows.added.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), vec!["fresh"]);
+        assert_eq!(diff.flows.removed.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), vec!["gone"]);
+        assert_eq!(diff.flows.changed.iter().map(|(_, f)| f.id.as_str()).collect::<Vec<_>>(), vec!["edited"]);
+
+        let lines = diff.describe();
+        assert!(lines.contains(&"Changed category \"Home\": name".to_string()), "{:?}", lines);
+        assert!(lines.contains(&"Changed flow 2024-03-01 \"Rent\" (25.00) in Home: amount".to_string()), "{:?}", lines);
+    }
+
+    #[test]
+    fn identical_data_has_an_empty_diff() {
+        let categories = vec![category("housing", "Housing")];
+        let mut fields = HashMap::new();
+        fields.insert("a".to_string(), "1".to_string());
+        fields.insert("b".to_string(), "2".to_string());
+        let flows = vec![Flow { custom_fields: fields, ..flow("f", 10.0) }];
+
+        let diff = DataDiff::compare(&categories, &flows, &categories.clone(), &flows.clone());
+        assert!(diff.is_empty());
+        assert!(diff.describe().is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use crate::backup_diff::DataDiff;
+use crate::models::{Category, Flow, UniquenessRule};
+
+/// The change a bulk edit makes to each flow it matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkAction {
+    /// Replaces every occurrence of `find` in the description.
+    ReplaceInDescription { find: String, replace: String },
+    SetCustomField { name: String, value: String },
+    MoveToCategory(String),
+    /// Multiplies the amount, rounding the result to cents.
+    ScaleAmount(f64),
+}
+
+/// One change applied to every flow matching a filter. Nothing is written by
+/// this type: `preview` runs it against copies of the flows, and only the
+/// flows it returns are saved once the preview has been reviewed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkEdit {
+    /// Only flows in this category; `None` means every category.
+    pub category_id: Option<String>,
+    /// Only flows whose description contains this (case-insensitive);
+    /// empty matches everything.
+    pub description_contains: String,
+    pub action: BulkAction,
+}
+
+impl BulkEdit {
+    pub fn matches(&self, flow: &Flow) -> bool {
+        if let Some(category_id) = &self.category_id
+            && flow.category_id != *category_id
+        {
+            return false;
+        }
+        self.description_contains.is_empty()
+            || flow.description.to_lowercase().contains(&self.description_contains.to_lowercase())
+    }
+
+    /// A copy of `flow` with the action applied; `flow` itself is untouched.
+    pub fn apply_to(&self, flow: &Flow) -> Flow {
+        let mut edited = flow.clone();
+        match &self.action {
+            BulkAction::ReplaceInDescription { find, replace } => {
+                if !find.is_empty() {
+                    edited.description = edited.description.replace(find.as_str(), replace);
+                }
+            }
+            BulkAction::SetCustomField { name, value } => {
+                edited.custom_fields.insert(name.clone(), value.clone());
+            }
+            BulkAction::MoveToCategory(category_id) => {
+                edited.category_id = category_id.clone();
+            }
+            BulkAction::ScaleAmount(factor) => {
+                edited.amount = (edited.amount * factor * 100.0).round() / 100.0;
+            }
+        }
+        edited
+    }
+}
+
+/// What a bulk edit would do, computed without touching the database.
+#[derive(Debug, Clone)]
+pub struct BulkEditPreview {
+    /// The edited copies of every flow the edit actually changes.
+    pub edited: Vec<Flow>,
+    /// Before/after differences for `edited`, for showing before applying.
+    pub diff: DataDiff,
+    /// Matching flows left out because their year is locked.
+    pub skipped_locked: usize,
+    /// Edited copies the flow editor wouldn't save, left out of `edited`,
+    /// each with why (see `validation_errors`).
+    pub rejected: Vec<(Flow, Vec<String>)>,
+}
+
+/// Why the flow editor would refuse to save `flow`: custom field values
+/// its category doesn't accept (see `Category::field_errors`), and any of
+/// the category's `uniqueness_rules` another flow in `others` already
+/// holds the values for.
+pub fn validation_errors(
+    flow: &Flow,
+    others: &[Flow],
+    categories: &[Category],
+    uniqueness_rules: &HashMap<String, Vec<UniquenessRule>>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(category) = categories.iter().find(|c| c.id == flow.category_id) {
+        let mut field_errors: Vec<String> = category.field_errors(&flow.custom_fields).into_values().collect();
+        field_errors.sort();
+        errors.extend(field_errors);
+    }
+    for rule in uniqueness_rules.get(&flow.category_id).into_iter().flatten() {
+        if let Some(existing) = rule.conflict(flow, others) {
+            errors.push(format!(
+                "{} \"{}\" already has the same {}.",
+                existing.date,
+                existing.description,
+                rule.fields.join(", ")
+            ));
+        }
+    }
+    errors
+}
+
+/// Runs `edit` against copies of `flows`. Flows the edit matches but leaves
+/// unchanged don't appear, and flows for which `is_locked` is true are
+/// counted rather than edited (saving them would fail anyway). Edited
+/// copies that fail `validation_errors` go into `rejected`; uniqueness is
+/// checked against `flows` with the edits accepted so far applied, so of
+/// two edits that would collide only the first is kept.
+pub fn preview(
+    edit: &BulkEdit,
+    flows: &[Flow],
+    categories: &[Category],
+    uniqueness_rules: &HashMap<String, Vec<UniquenessRule>>,
+    is_locked: impl Fn(&Flow) -> bool,
+) -> BulkEditPreview {
+    let mut current = flows.to_vec();
+    let mut before = Vec::new();
+    let mut edited = Vec::new();
+    let mut rejected = Vec::new();
+    let mut skipped_locked = 0;
+    for (index, flow) in flows.iter().enumerate().filter(|(_, f)| edit.matches(f)) {
+        let after = edit.apply_to(flow);
+        if serde_json::to_value(&after).ok() == serde_json::to_value(flow).ok() {
+            continue;
+        }
+        if is_locked(flow) {
+            skipped_locked += 1;
+            continue;
+        }
+        let errors = validation_errors(&after, &current, categories, uniqueness_rules);
+        if !errors.is_empty() {
+            rejected.push((after, errors));
+            continue;
+        }
+        current[index] = after.clone();
+        before.push(flow.clone());
+        edited.push(after);
+    }
+    let diff = DataDiff::compare(categories, &before, categories, &edited);
+    BulkEditPreview { edited, diff, skipped_locked, rejected }
+}
+
+/// Each field `after` changes from `before`, as `field: old -> new`, for the
+/// preview table. Categories are shown by name when `categories` has them.
+pub fn describe_change(before: &Flow, after: &Flow, categories: &[Category]) -> Vec<String> {
+    let category_name = |id: &str| {
+        categories.iter().find(|c| c.id == id).map_or(id.to_string(), |c| c.name.clone())
+    };
+    let mut changes = Vec::new();
+    if before.description != after.description {
+        changes.push(format!("description: \"{}\" -> \"{}\"", before.description, after.description));
+    }
+    if before.amount != after.amount {
+        changes.push(format!("amount: {:.2} -> {:.2}", before.amount, after.amount));
+    }
+    if before.category_id != after.category_id {
+        changes.push(format!("category: {} -> {}", category_name(&before.category_id), category_name(&after.category_id)));
+    }
+    let mut field_names: Vec<&String> = after.custom_fields.keys().collect();
+    field_names.sort();
+    for name in field_names {
+        let new_value = &after.custom_fields[name];
+        match before.custom_fields.get(name) {
+            Some(old_value) if old_value == new_value => {}
+            Some(old_value) => changes.push(format!("{}: \"{}\" -> \"{}\"", name, old_value, new_value)),
+            None => changes.push(format!("{}: (unset) -> \"{}\"", name, new_value)),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CategoryField, FieldType, FlowType};
+    use chrono::{Datelike, NaiveDate};
+
+    fn flow(id: &str, category_id: &str, description: &str, amount: f64) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            amount,
+            category_id: category_id.to_string(),
+            description: description.to_string(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn preview_edits_copies_of_matching_flows_only() {
+        let flows = vec![
+            flow("a", "food", "ACME Grocery", 10.0),
+            flow("b", "food", "Corner Cafe", 20.0),
+            flow("c", "rent", "acme rent", 30.0),
+        ];
+        let edit = BulkEdit {
+            category_id: Some("food".to_string()),
+            description_contains: "acme".to_string(),
+            action: BulkAction::ReplaceInDescription { find: "ACME".to_string(), replace: "Acme".to_string() },
+        };
+
+        let result = preview(&edit, &flows, &[], &HashMap::new(), |_| false);
+
+        assert_eq!(result.edited.len(), 1);
+        assert_eq!(result.edited[0].id, "a");
+        assert_eq!(result.edited[0].description, "Acme Grocery");
+        assert_eq!(flows[0].description, "ACME Grocery", "the originals are untouched");
+        assert_eq!(result.diff.flows.changed.len(), 1);
+        assert!(result.diff.flows.added.is_empty() && result.diff.flows.removed.is_empty());
+        let (before, after) = &result.diff.flows.changed[0];
+        assert_eq!(describe_change(before, after, &[]), vec!["description: \"ACME Grocery\" -> \"Acme Grocery\""]);
+    }
+
+    #[test]
+    fn preview_skips_unchanged_and_locked_flows() {
+        let mut locked = flow("locked", "food", "Lunch", 10.0);
+        locked.date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let flows = vec![flow("zero", "food", "Free sample", 0.0), locked, flow("open", "food", "Dinner", 12.345)];
+        let edit = BulkEdit {
+            category_id: None,
+            description_contains: String::new(),
+            action: BulkAction::ScaleAmount(2.0),
+        };
+
+        let result = preview(&edit, &flows, &[], &HashMap::new(), |f| f.date.year() == 2020);
+
+        assert_eq!(result.skipped_locked, 1);
+        assert_eq!(result.edited.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), vec!["open"]);
+        assert_eq!(result.edited[0].amount, 24.69);
+    }
+
+    #[test]
+    fn preview_rejects_edits_the_flow_editor_would_refuse() {
+        let field = |name: &str, field_type: FieldType, required: bool| CategoryField {
+            name: name.to_string(),
+            field_type,
+            required,
+            default_value: None,
+        };
+        let taxes = Category {
+            id: "taxes".to_string(),
+            flow_type: FlowType::Expense,
+            fields: vec![field("tax_type", FieldType::Text, true), field("tax_year", FieldType::Integer, false)],
+            ..Category::new("Taxes".to_string())
+        };
+        let rules = HashMap::from([(
+            "taxes".to_string(),
+            vec![UniquenessRule { fields: vec!["tax_type".to_string(), "tax_year".to_string()] }],
+        )]);
+        let mut filed = flow("filed", "taxes", "Federal", 100.0);
+        filed.custom_fields.insert("tax_type".to_string(), "Federal".to_string());
+        filed.custom_fields.insert("tax_year".to_string(), "2023".to_string());
+        let mut other = flow("other", "taxes", "State", 50.0);
+        other.custom_fields.insert("tax_type".to_string(), "Federal".to_string());
+        let flows = vec![filed, other, flow("misc", "food", "Lunch", 10.0)];
+        let categories = vec![taxes];
+
+        let set_year = |value: &str| BulkEdit {
+            category_id: Some("taxes".to_string()),
+            description_contains: String::new(),
+            action: BulkAction::SetCustomField { name: "tax_year".to_string(), value: value.to_string() },
+        };
+        let result = preview(&set_year("twenty"), &flows, &categories, &rules, |_| false);
+        assert!(result.edited.is_empty());
+        assert_eq!(result.rejected.len(), 2);
+        assert_eq!(result.rejected[0].1, vec!["Tax Year must be a whole number.".to_string()]);
+
+        let result = preview(&set_year("2023"), &flows, &categories, &rules, |_| false);
+        assert!(result.edited.is_empty(), "\"other\" would duplicate \"filed\"");
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].0.id, "other");
+        assert!(result.rejected[0].1[0].contains("already has the same tax_type, tax_year"));
+
+        let move_lunch = BulkEdit {
+            category_id: Some("food".to_string()),
+            description_contains: String::new(),
+            action: BulkAction::MoveToCategory("taxes".to_string()),
+        };
+        let result = preview(&move_lunch, &flows, &categories, &rules, |_| false);
+        assert!(result.edited.is_empty());
+        assert_eq!(result.rejected[0].1, vec!["Tax Type is required.".to_string()]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Category schema files: the user's category definitions (fields and their
+//! defaults, tax settings, color, icon, parent) as JSON on their own, to set
+//! up the same categories in another install. No flows are included, and
+//! importing one never touches any.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::Category;
+
+const SCHEMA_FORMAT: &str = "preft-category-schema";
+const SCHEMA_VERSION: u32 = 1;
+
+pub const SCHEMA_EXTENSION: &str = "json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySchemaFile {
+    format: String,
+    version: u32,
+    pub app_version: String,
+    pub created: DateTime<Utc>,
+    pub categories: Vec<Category>,
+}
+
+impl CategorySchemaFile {
+    pub fn new(app_version: &str, categories: &[Category]) -> Self {
+        Self {
+            format: SCHEMA_FORMAT.to_string(),
+            version: SCHEMA_VERSION,
+            app_version: app_version.to_string(),
+            created: Utc::now(),
+            categories: categories.to_vec(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let file: CategorySchemaFile = serde_json::from_slice(data)
+            .map_err(|_| anyhow!("This is not a preft category schema file"))?;
+        if file.format != SCHEMA_FORMAT {
+            return Err(anyhow!("This is not a preft category schema file"));
+        }
+        if file.version > SCHEMA_VERSION {
+            return Err(anyhow!("This file was made by a newer version of preft (format version {})", file.version));
+        }
+        for category in &file.categories {
+            if let Some(error) = category.validation_errors().into_iter().next() {
+                return Err(anyhow!("Category '{}' in this file can't be imported: {}", category.name, error));
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// What importing a schema file would do to the categories already there.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaImport {
+    /// Categories that aren't here yet, parents before their subcategories.
+    pub added: Vec<Category>,
+    /// Existing categories whose definition the file changes, keeping their
+    /// own id, place in the list, and any fields the file doesn't have (so
+    /// no flow loses a value to an import).
+    pub updated: Vec<Category>,
+    /// Names of existing categories the file defines the same way.
+    pub unchanged: Vec<String>,
+}
+
+impl SchemaImport {
+    /// Lines up `imported` with `existing`, by id and then by name and flow
+    /// type (ignoring case), since categories added in another install have
+    /// ids of their own. Parents are pointed at the matching category here,
+    /// or dropped if there isn't one.
+    pub fn plan(existing: &[Category], imported: &[Category]) -> Self {
+        let matching = |category: &Category| {
+            existing.iter().find(|c| c.id == category.id).or_else(|| {
+                existing.iter().find(|c| c.flow_type == category.flow_type && c.name.eq_ignore_ascii_case(&category.name))
+            })
+        };
+        let local_ids: HashMap<&str, &str> = imported
+            .iter()
+            .map(|category| (category.id.as_str(), matching(category).map_or(category.id.as_str(), |c| c.id.as_str())))
+            .collect();
+
+        let mut plan = SchemaImport::default();
+        for category in imported {
+            let parent_id = category.parent_id.as_deref()
+                .and_then(|parent| local_ids.get(parent))
+                .map(|parent| parent.to_string());
+            match matching(category) {
+                Some(current) => {
+                    let mut fields = category.fields.clone();
+                    fields.extend(current.fields.iter()
+                        .filter(|field| !category.fields.iter().any(|f| f.name.eq_ignore_ascii_case(&field.name)))
+                        .cloned());
+                    let wanted = Category {
+                        id: current.id.clone(),
+                        parent_id,
+                        sort_order: current.sort_order,
+                        fields,
+                        ..category.clone()
+                    };
+                    if wanted == *current {
+                        plan.unchanged.push(current.name.clone());
+                    } else {
+                        plan.updated.push(wanted);
+                    }
+                }
+                None => plan.added.push(Category { parent_id, ..category.clone() }),
+            }
+        }
+        // Subcategories after any parent that's being added with them
+        let added_ids: Vec<String> = plan.added.iter().map(|c| c.id.clone()).collect();
+        plan.added.sort_by_key(|c| c.parent_id.as_ref().is_some_and(|parent| added_ids.contains(parent)));
+        plan
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CategoryField, FieldType, FlowType};
+
+    fn category(id: &str, name: &str, flow_type: FlowType) -> Category {
+        Category { id: id.to_string(), flow_type, ..Category::new(name.to_string()) }
+    }
+
+    #[test]
+    fn round_trips_and_rejects_other_files() {
+        let mut groceries = category("c1", "Groceries", FlowType::Expense);
+        groceries.fields.push(CategoryField {
+            name: "Store".to_string(),
+            field_type: FieldType::Text,
+            required: true,
+            default_value: Some("Corner shop".to_string()),
+        });
+        groceries.tax_deduction.deduction_allowed = true;
+        let file = CategorySchemaFile::new("1.2.3", std::slice::from_ref(&groceries));
+
+        let read = CategorySchemaFile::from_bytes(&file.to_bytes().unwrap()).unwrap();
+        assert_eq!(read.categories, vec![groceries]);
+        assert_eq!(read.app_version, "1.2.3");
+
+        assert!(CategorySchemaFile::from_bytes(b"{\"categories\": []}").is_err());
+        let newer = serde_json::to_vec(&CategorySchemaFile { version: SCHEMA_VERSION + 1, ..file }).unwrap();
+        assert!(CategorySchemaFile::from_bytes(&newer).unwrap_err().to_string().contains("newer version"));
+    }
+
+    #[test]
+    fn plan_matches_by_id_then_name_and_keeps_local_ids_and_fields() {
+        let mut rent = category("local-rent", "Rent", FlowType::Expense);
+        rent.sort_order = 4;
+        rent.fields.push(CategoryField { name: "Landlord".to_string(), field_type: FieldType::Text, required: false, default_value: None });
+        let salary = category("salary", "Salary", FlowType::Income);
+        let existing = vec![rent.clone(), salary.clone()];
+
+        let mut imported_rent = category("other-rent", "rent", FlowType::Expense);
+        imported_rent.icon = Some("🏠".to_string());
+        let utilities = Category { parent_id: Some("other-rent".to_string()), ..category("utilities", "Utilities", FlowType::Expense) };
+        let plan = SchemaImport::plan(&existing, &[utilities, imported_rent, salary.clone()]);
+
+        assert_eq!(plan.unchanged, ["Salary"]);
+        assert_eq!(plan.updated.len(), 1);
+        assert_eq!(plan.updated[0].id, "local-rent");
+        assert_eq!(plan.updated[0].sort_order, 4);
+        assert_eq!(plan.updated[0].icon.as_deref(), Some("🏠"));
+        assert_eq!(plan.updated[0].fields, rent.fields, "fields only here are kept");
+        assert_eq!(plan.added.len(), 1);
+        assert_eq!(plan.added[0].parent_id.as_deref(), Some("local-rent"));
+    }
+
+    #[test]
+    fn plan_adds_parents_before_their_subcategories_and_drops_unknown_parents() {
+        let child = Category { parent_id: Some("parent".to_string()), ..category("child", "Child", FlowType::Expense) };
+        let orphan = Category { parent_id: Some("missing".to_string()), ..category("orphan", "Orphan", FlowType::Expense) };
+        let parent = category("parent", "Parent", FlowType::Expense);
+        let plan = SchemaImport::plan(&[], &[child, orphan, parent]);
+
+        let ids: Vec<&str> = plan.added.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["orphan", "parent", "child"]);
+        assert_eq!(plan.added[0].parent_id, None);
+        assert!(!plan.is_empty());
+    }
+}
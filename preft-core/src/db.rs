@@ -0,0 +1,3046 @@
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, params, types::FromSql, types::ValueRef, types::FromSqlError, types::Type};
+use chrono::{Datelike, NaiveDate};
+use crate::models::{Flow, Category, FlowType, TaxDeductionInfo, CategoryField, OptionRenames, ReimbursementStatus, SqlView, Trip, get_default_categories};
+use crate::kpi::{FlowAggregate, KpiTotals};
+use crate::metrics::MetricSnapshot;
+use crate::pending_changes::PendingChanges;
+use crate::reporting::{ReportRequest, push_csv_row};
+use crate::settings::UserSettings;
+use crate::utils::{self, TripTotals};
+use crate::encryption::{is_sealed, DatabaseEncryption, KeyDerivation};
+use zeroize::Zeroizing;
+use crate::encryption_config::{EncryptionConfig, SensitiveColumn};
+use log::{info, warn, error};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+mod migrations;
+
+pub use migrations::{MigrationSummary, RollbackSummary, SCHEMA_VERSION};
+
+/// One `metric_snapshots` row: (period, taken_on, metric, value).
+type MetricSnapshotRow = (String, String, String, f64);
+
+/// One `categories` row as stored, in column order.
+type CategoryRow = (String, String, String, String, i64, i64, String, i64, Option<i64>, Option<String>);
+
+/// A category's color as stored: 0xRRGGBB.
+fn color_to_sql(color: Option<[u8; 3]>) -> Option<i64> {
+    color.map(|[r, g, b]| i64::from(r) << 16 | i64::from(g) << 8 | i64::from(b))
+}
+
+fn color_from_sql(value: Option<i64>) -> Option<[u8; 3]> {
+    value.map(|rgb| [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+}
+
+/// One `flows` row as stored, in column order.
+type FlowRow = (String, String, f64, String, String, String, String, Option<i64>, Option<String>, bool, Option<i32>, Option<String>, Option<String>, Option<String>, Option<String>);
+
+/// One `trips` row: (id, name, start_date, end_date).
+type TripRow = (String, String, String, String);
+
+/// What `Database::compact` did to the database's size, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactReport {
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+impl CompactReport {
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+/// How far a backup or restore has got, shared with whoever started it so
+/// they can show it and stop it. Stopping takes effect at the next
+/// checkpoint; a stopped restore leaves the database as it was, and a
+/// stopped backup leaves a partial file for the caller to remove.
+#[derive(Debug, Default)]
+pub struct BackupProgress {
+    /// Thousandths done.
+    done: AtomicU32,
+    cancelled: AtomicBool,
+}
+
+impl BackupProgress {
+    /// Between 0.0 and 1.0.
+    pub fn fraction(&self) -> f32 {
+        self.done.load(Ordering::Relaxed) as f32 / 1000.0
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Records that the work is done; it's past stopping by then.
+    fn finish(&self) {
+        self.done.store(1000, Ordering::Relaxed);
+    }
+
+    /// Records `done` of `total` steps, failing if the work was cancelled.
+    fn checkpoint(&self, done: usize, total: usize) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow::anyhow!("Cancelled"));
+        }
+        let thousandths = (done.min(total) * 1000).checked_div(total).unwrap_or(1000) as u32;
+        self.done.store(thousandths, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Copies every page `backup` covers, a few at a time so `progress` can
+/// follow it and stop it between steps.
+fn copy_pages(backup: &rusqlite::backup::Backup<'_, '_>, progress: &BackupProgress) -> Result<()> {
+    loop {
+        match backup.step(64)? {
+            // Past stopping now: a finished restore is already committed
+            rusqlite::backup::StepResult::Done => {
+                progress.finish();
+                return Ok(());
+            }
+            rusqlite::backup::StepResult::More => {}
+            // Another connection holds a lock; give it a moment
+            _ => std::thread::sleep(std::time::Duration::from_millis(100)),
+        }
+        let pages = backup.progress();
+        progress.checkpoint((pages.pagecount - pages.remaining) as usize, pages.pagecount as usize)?;
+    }
+}
+
+/// The `flows` columns a `Flow` is read from, in `flow_from_row`'s order.
+const FLOW_COLUMNS: &str = "id, date, amount, category_id, description, linked_flows, custom_fields, tax_deductible, refund_of, scheduled, created_utc_offset, location, trip_id, reimbursement_status, reimbursed_by";
+
+fn flow_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Flow> {
+    let date_str: String = row.get(1)?;
+    let date = NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let linked_flows_json: String = row.get(5)?;
+    let linked_flows = serde_json::from_str(&linked_flows_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let custom_fields_json: String = row.get(6)?;
+    let custom_fields = serde_json::from_str(&custom_fields_json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?;
+
+    let tax_deductible: Option<i64> = row.get(7)?;
+    let tax_deductible = tax_deductible.map(|i| i != 0);
+
+    Ok(Flow {
+        id: row.get(0)?,
+        date,
+        amount: row.get(2)?,
+        category_id: row.get(3)?,
+        description: row.get(4)?,
+        linked_flows,
+        custom_fields,
+        tax_deductible,
+        refund_of: row.get(8)?,
+        scheduled: row.get(9)?,
+        created_utc_offset: row.get(10)?,
+        location: row.get(11)?,
+        trip_id: row.get(12)?,
+        reimbursement: row.get(13)?,
+        reimbursed_by: row.get(14)?,
+    })
+}
+
+/// Which flows `Database::load_flows_in` loads; anything left `None` isn't
+/// narrowed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FlowScope {
+    pub category_id: Option<String>,
+    /// The calendar years the flows are dated in, both ends included.
+    pub years: Option<RangeInclusive<i32>>,
+    /// At most this many flows, after skipping `offset`, for paging.
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl FlowScope {
+    /// Every flow in the category, or with a year, every flow in it dated
+    /// that year.
+    pub fn category(category_id: &str, year: Option<i32>) -> Self {
+        Self {
+            category_id: Some(category_id.to_string()),
+            years: year.map(|year| year..=year),
+            ..Self::default()
+        }
+    }
+
+    /// Every flow dated in `years`, whatever its category.
+    pub fn years(years: RangeInclusive<i32>) -> Self {
+        Self { years: Some(years), ..Self::default() }
+    }
+
+    /// Whether `flow` matches the category and years. Paging isn't
+    /// considered: only the loaded page tells which flows it holds.
+    pub fn contains(&self, flow: &Flow) -> bool {
+        self.category_id.as_ref().is_none_or(|id| *id == flow.category_id)
+            && self.years.as_ref().is_none_or(|years| years.contains(&flow.date.year()))
+    }
+
+    pub fn is_paged(&self) -> bool {
+        self.limit.is_some() || self.offset > 0
+    }
+}
+
+/// One category's figures for the dashboard and category views, as summed
+/// by `Database::category_summaries`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorySummary {
+    pub last_year_total: f64,
+    /// The whole of this year, including flows dated after today.
+    pub this_year_total: f64,
+    pub current_month_total: f64,
+    /// Net totals for each of the trailing months ending with this one,
+    /// oldest first (see `utils::trailing_monthly_totals`).
+    pub monthly_trend: Vec<f64>,
+    pub tracking_ratio: Option<f64>,
+}
+
+impl CategorySummary {
+    /// The summary of a category with no flows in range.
+    pub fn empty(trend_months: usize) -> Self {
+        Self {
+            last_year_total: 0.0,
+            this_year_total: 0.0,
+            current_month_total: 0.0,
+            monthly_trend: vec![0.0; trend_months],
+            tracking_ratio: None,
+        }
+    }
+}
+
+/// `Flow::net_amount` as SQL over a `flows` row: scheduled flows count
+/// nothing and refunds count against their category.
+const NET_AMOUNT_SQL: &str = "CASE WHEN scheduled THEN 0 WHEN refund_of IS NOT NULL THEN -amount ELSE amount END";
+
+/// `utils::deductible_flows` as SQL over a `flows` row: a refund goes by
+/// the flow it refunds while that's still there, anything else by its own
+/// mark.
+const DEDUCTIBLE_SQL: &str = "CASE WHEN EXISTS (SELECT 1 FROM flows original WHERE original.id = flows.refund_of)
+    THEN (SELECT original.tax_deductible FROM flows original WHERE original.id = flows.refund_of) IS 1
+    ELSE tax_deductible IS 1 END";
+
+/// One JSON column of one row, as stored, so it can be checked without
+/// loading the row (see `integrity`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredJson {
+    /// Says whose column it is, e.g. "Flow 3f2a..." or "Category Rent".
+    pub owner: String,
+    pub column: &'static str,
+    pub json: String,
+}
+
+/// A select list for reading `table` out of a backup, in `columns`' order.
+/// Columns added after the backup was taken are read as their default
+/// (`Some`); a missing column without one is left in, so the query fails.
+fn backup_columns(conn: &Connection, table: &str, columns: &[(&str, Option<&str>)]) -> Result<String> {
+    let mut select = Vec::with_capacity(columns.len());
+    for &(column, default) in columns {
+        match default {
+            Some(default) if migrations::column_missing(conn, table, column)? => {
+                select.push(format!("{} AS {}", default, column));
+            }
+            _ => select.push(column.to_string()),
+        }
+    }
+    Ok(select.join(", "))
+}
+
+/// What running a `SqlView` returned: its column names and every row,
+/// with each value as text (NULL as empty).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SqlViewRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl SqlViewRows {
+    /// The rows as CSV with a header row, in the dialect reports use.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        push_csv_row(&mut out, &self.columns);
+        for row in &self.rows {
+            push_csv_row(&mut out, row);
+        }
+        out
+    }
+}
+
+pub struct Database {
+    conn: Connection,
+    encryption: Option<DatabaseEncryption>,
+    encryption_config: EncryptionConfig,
+    /// Counted up by every write method that changes financial records
+    /// (flows or categories -- `save_flow`, `delete_category`,
+    /// `restore_from_file`, etc.), so callers can tell whether anything worth
+    /// backing up has changed since this `Database` was constructed -- see
+    /// `is_dirty`. Deliberately *not* counted by `save_user_settings`: that
+    /// table is UI/app preferences (year filter, hidden categories, backup
+    /// directory, ...), not financial data, and gets written on routine,
+    /// entirely-expected interactions like switching a filter -- see its own
+    /// doc comment. A `Cell` because most write methods only take `&self` (SQLite itself
+    /// doesn't need `&mut` for writes; only migrations/restore, which
+    /// replace the connection's schema/content wholesale, take `&mut self`).
+    /// A count rather than a flag so caches can tell one change from the
+    /// next -- see `change_count`.
+    changes: std::cell::Cell<u64>,
+    /// What the most recent migration run changed, if anything, until the
+    /// app takes it to show the user -- see `take_migration_summary`.
+    migration_summary: Option<MigrationSummary>,
+    /// The file is encrypted (see `encrypt_file`) and hasn't been given its
+    /// key yet, so nothing can be read until `unlock_file`.
+    file_locked: bool,
+}
+
+impl Database {
+    pub fn new() -> Result<Self> {
+        // Load encryption configuration from OS keystore
+        let encryption_config = EncryptionConfig::load()
+            .unwrap_or_else(|_| EncryptionConfig::default());
+        
+        // Get the user's home directory
+        let home_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        
+        // Create the app directory if it doesn't exist
+        let app_dir = home_dir.join(".preft");
+        std::fs::create_dir_all(&app_dir)?;
+        
+        // Open or create the database file
+        let db_path = app_dir.join("preft.db");
+        let file_locked = is_file_encrypted_at(&db_path)?;
+        let conn = Connection::open(db_path)?;
+        
+        // Initialize the database
+        let mut db = Database { conn, encryption: None, encryption_config, changes: std::cell::Cell::new(0), migration_summary: None, file_locked };
+        if db.file_locked {
+            // Schema setup and migrations wait for `unlock_file`
+            log::info!("Database file is encrypted; waiting for the password");
+            return Ok(db);
+        }
+        db.initialize()?;
+
+        // Run migrations
+        let summary = migrations::run_migrations(&mut db.conn)?;
+        db.record_migration_summary(summary);
+
+        // Check if we have any categories, if not, save the defaults
+        let count: i64 = db.conn.query_row("SELECT COUNT(*) FROM categories", [], |row| row.get(0))?;
+        if count == 0 {
+            for category in get_default_categories() {
+                db.save_category(&category)?;
+            }
+        }
+
+        // Initialize user settings if they don't exist
+        let settings_count: i64 = db.conn.query_row("SELECT COUNT(*) FROM user_settings", [], |row| row.get(0))?;
+        if settings_count == 0 {
+            db.save_user_settings(&UserSettings::new())?;
+        }
+
+        // The seeding above (default categories/settings on first run) marks
+        // `changes`, but it isn't a change the *user* made -- reset so a fresh
+        // database starts clean for `is_dirty`'s purposes.
+        db.changes.set(0);
+
+        Ok(db)
+    }
+
+    /// Create a new database with minimal initialization (for error recovery)
+    pub fn new_minimal() -> Result<Self> {
+        // Load encryption configuration from OS keystore
+        let encryption_config = EncryptionConfig::load()
+            .unwrap_or_else(|_| EncryptionConfig::default());
+
+        // Get the user's home directory
+        let home_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+
+        // Create the app directory if it doesn't exist
+        let app_dir = home_dir.join(".preft");
+        std::fs::create_dir_all(&app_dir)?;
+
+        // Open or create the database file
+        let db_path = app_dir.join("preft.db");
+        let file_locked = is_file_encrypted_at(&db_path)?;
+        let conn = Connection::open(db_path)?;
+
+        // Initialize the database with just the basic tables
+        let db = Database { conn, encryption: None, encryption_config, changes: std::cell::Cell::new(0), migration_summary: None, file_locked };
+        if !db.file_locked {
+            db.initialize()?;
+        }
+
+        Ok(db)
+    }
+
+    /// Opens the user's database strictly read-only, for viewer mode (see
+    /// `PreftApp::read_only`).
+    pub fn open_read_only() -> Result<Self> {
+        let home_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        Self::open_read_only_at(&home_dir.join(".preft").join("preft.db"))
+    }
+
+    /// Opens an existing database file read-only. SQLite itself refuses
+    /// every write, so nothing the UI forgets to hide can change the file.
+    /// Schema setup and migrations are skipped since they write; a database
+    /// from an older version needs opening normally once first.
+    pub fn open_read_only_at(db_path: &Path) -> Result<Self> {
+        let encryption_config = EncryptionConfig::load()
+            .unwrap_or_else(|_| EncryptionConfig::default());
+        let file_locked = is_file_encrypted_at(db_path)?;
+        let conn = Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Database { conn, encryption: None, encryption_config, changes: std::cell::Cell::new(0), migration_summary: None, file_locked })
+    }
+
+    /// Create a database from an existing connection (for error recovery)
+    pub fn from_connection(conn: Connection) -> Self {
+        let encryption_config = EncryptionConfig::load()
+            .unwrap_or_else(|_| EncryptionConfig::default());
+        Database { conn, encryption: None, encryption_config, changes: std::cell::Cell::new(0), migration_summary: None, file_locked: false }
+    }
+
+    /// Build a fully-initialized database (schema + migrations) against an
+    /// arbitrary connection, for use in tests.
+    ///
+    /// Unlike `new()`/`new_minimal()`/`from_connection()`, this never touches
+    /// the user's real `~/.preft/preft.db` file and never reads or writes the
+    /// OS keyring (it uses `EncryptionConfig::default()` in memory instead of
+    /// `EncryptionConfig::load()`). Pass `Connection::open_in_memory()` for a
+    /// fully isolated database, or a `Connection::open(path)` pointed at a
+    /// temp-file path for tests that need a real file on disk (e.g. backup /
+    /// restore round trips).
+    ///
+    /// Default categories and default user settings are intentionally *not*
+    /// seeded here, so tests start from an empty, deterministic schema and
+    /// create exactly the fixtures they need.
+    pub fn new_for_test(conn: Connection) -> Result<Self> {
+        let mut db = Database {
+            conn,
+            encryption: None,
+            encryption_config: EncryptionConfig::default(),
+            changes: std::cell::Cell::new(0),
+            migration_summary: None,
+            file_locked: false,
+        };
+        db.initialize()?;
+        let summary = migrations::run_migrations(&mut db.conn)?;
+        db.record_migration_summary(summary);
+        Ok(db)
+    }
+
+    /// A fully-initialized database that lives only in memory, for demo
+    /// mode (see `repro`). Like `new_for_test`, it never reads or writes
+    /// the OS keyring, and nothing is seeded.
+    pub fn new_in_memory() -> Result<Self> {
+        Self::new_for_test(Connection::open_in_memory()?)
+    }
+
+    /// `new_for_test` against the file at `db_path`, except that an
+    /// encrypted file opens locked (see `is_file_locked`) as it would for
+    /// `new()`.
+    pub fn new_for_test_at(db_path: &Path) -> Result<Self> {
+        if !is_file_encrypted_at(db_path)? {
+            return Self::new_for_test(Connection::open(db_path)?);
+        }
+        Ok(Database {
+            conn: Connection::open(db_path)?,
+            encryption: None,
+            encryption_config: EncryptionConfig::default(),
+            changes: std::cell::Cell::new(0),
+            migration_summary: None,
+            file_locked: true,
+        })
+    }
+
+    fn record_migration_summary(&mut self, summary: MigrationSummary) {
+        if !summary.is_empty() {
+            self.migration_summary = Some(summary);
+        }
+    }
+
+    /// The schema version the database records (see `SCHEMA_VERSION`).
+    pub fn schema_version(&self) -> Result<i64> {
+        migrations::schema_version(&self.conn)
+    }
+
+    /// Undoes the migrations newer than `version`, e.g. to hand the file
+    /// back to an older build of Preft. The next `new()` migrates it again.
+    pub fn roll_back_schema_to(&mut self, version: i64) -> Result<RollbackSummary> {
+        migrations::roll_back_migrations(&mut self.conn, version)
+    }
+
+    /// Hands over what the last migration run changed in the user's data,
+    /// once; `None` if nothing changed or it was already taken.
+    pub fn take_migration_summary(&mut self) -> Option<MigrationSummary> {
+        self.migration_summary.take()
+    }
+
+    /// Marks the database as having financial-data changes since
+    /// construction (or the last reset). Called by every write method that
+    /// touches flows or categories -- *not* by `save_user_settings`; see
+    /// `is_dirty`.
+    fn mark_dirty(&self) {
+        self.changes.set(self.changes.get() + 1);
+    }
+
+    /// Whether any *financial* data (flows/categories) has changed since
+    /// this `Database` was constructed -- not settings/preferences, see
+    /// `changes`' doc comment. Used to skip the automatic on-exit backup when
+    /// nothing worth backing up actually changed this session -- see
+    /// `PreftApp::on_exit`.
+    pub fn is_dirty(&self) -> bool {
+        self.changes.get() > 0
+    }
+
+    /// How many financial-data writes this `Database` has made. Anything
+    /// loaded while it read N is still current while it reads N.
+    pub fn change_count(&self) -> u64 {
+        self.changes.get()
+    }
+
+    /// Mark this test database as encrypted using an explicit password/salt
+    /// pair, without touching the OS keyring. Mirrors what `initialize_encryption`
+    /// does, minus the `EncryptionConfig::save()` keyring write. Callers that
+    /// need two databases to share the same derived key (e.g. simulating a
+    /// backup restored onto a different database) must pass the same salt to
+    /// both.
+    pub fn enable_encryption_for_test(&mut self, password: &str, salt: &str) -> Result<()> {
+        let key_derivation = KeyDerivation::RECOMMENDED;
+        let password_hash = DatabaseEncryption::hash_password(password, salt, key_derivation)?;
+        self.encryption_config = EncryptionConfig {
+            enabled: true,
+            password_hash: Some(password_hash),
+            salt: Some(salt.to_string()),
+            database_encrypted: true,
+            file_encrypted: false,
+            encrypted_columns: self.encryption_config.encrypted_columns.clone(),
+            key_derivation,
+            retired_key_derivations: Vec::new(),
+        };
+        self.encryption = Some(DatabaseEncryption::new(password, salt, key_derivation)?);
+        Ok(())
+    }
+
+    /// Whether the stored user settings -- the only value `encrypt_data`
+    /// ever encrypts -- are ciphertext, by the same test as
+    /// `detect_encrypted_backup`. This is what's actually on disk, which can
+    /// disagree with the keystore's `EncryptionConfig::database_encrypted`
+    /// (see `EncryptionConfig::mismatch`).
+    pub fn detect_encryption_state(&self) -> bool {
+        match self.conn.query_row(
+            "SELECT settings_json FROM user_settings WHERE id = 1",
+            [],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(settings_json) => !settings_json.trim_start().starts_with('{'),
+            Err(_) => false, // nothing saved yet, so nothing encrypted
+        }
+    }
+
+    /// Initialize encryption for the database. With a password already set
+    /// in the config, `password` has to be that one and only its key is
+    /// derived; a new salt here would no longer match the keystore's.
+    pub fn initialize_encryption(&mut self, password: &str) -> Result<()> {
+        if self.encryption_config.is_encryption_ready() {
+            if !self.encryption_config.verify_password(password) {
+                return Err(anyhow::anyhow!("The password doesn't match the one in the encryption config"));
+            }
+        } else {
+            // Set password in encryption config (this will generate salt and hash)
+            self.encryption_config.set_password(password)?;
+        }
+        
+        // Create encryption instance
+        let salt = self.encryption_config.get_salt()
+            .ok_or_else(|| anyhow::anyhow!("Salt not found after setting password"))?;
+        let encryption = DatabaseEncryption::new(password, salt, self.encryption_config.key_derivation)?;
+        
+        // Test encryption by encrypting and decrypting a test value
+        let test_data = "encryption_test";
+        let encrypted = encryption.encrypt(test_data)?;
+        let decrypted = encryption.decrypt(&encrypted)?;
+        
+        if decrypted != test_data {
+            return Err(anyhow::anyhow!("Encryption test failed"));
+        }
+
+        self.encryption = Some(encryption);
+        
+        log::info!("Database encryption initialized successfully");
+        Ok(())
+    }
+
+    /// Set encryption state (for loading from settings)
+    pub fn set_encryption_state(&mut self, enabled: bool, password: Option<&str>, salt: Option<&str>) -> Result<()> {
+        if enabled {
+            if let (Some(pwd), Some(salt_val)) = (password, salt) {
+                let encryption = DatabaseEncryption::new(pwd, salt_val, self.encryption_config.key_derivation)?;
+                self.encryption = Some(encryption);
+            } else {
+                return Err(anyhow::anyhow!("Password and salt required for encryption"));
+            }
+        } else {
+            self.encryption = None;
+        }
+        Ok(())
+    }
+
+    /// Forgets the derived key (wiped as it's dropped), e.g. when the app
+    /// locks. Encrypted columns read back sealed until `set_encryption_state`
+    /// is given the password again.
+    pub fn clear_encryption(&mut self) {
+        self.encryption = None;
+    }
+
+    /// Replaces this database's copy of the keystore config, after the app
+    /// has changed (and saved) its own.
+    pub fn set_encryption_config(&mut self, config: EncryptionConfig) {
+        self.encryption_config = config;
+    }
+
+    /// Check if encryption is currently enabled
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption_config.is_encryption_ready()
+    }
+
+    /// Whether the database file was opened encrypted and is waiting for
+    /// `unlock_file`. Everything else fails until then.
+    pub fn is_file_locked(&self) -> bool {
+        self.file_locked
+    }
+
+    /// Whether the database file itself is encrypted with SQLCipher (see
+    /// `encrypt_file`), as opposed to only the settings.
+    pub fn is_file_encrypted(&self) -> bool {
+        self.file_path().is_ok_and(|path| is_file_encrypted_at(&path).unwrap_or(false))
+    }
+
+    /// Gives a locked database file (see `is_file_locked`) its key, derived
+    /// from the password, then runs the schema setup and migrations that
+    /// opening it skipped. The key encrypts the settings too, as
+    /// `set_encryption_state` would.
+    pub fn unlock_file(&mut self, password: &str, salt: &str) -> Result<()> {
+        if !self.file_locked {
+            return Ok(());
+        }
+        let encryption = DatabaseEncryption::new(password, salt, self.encryption_config.key_derivation)?;
+        if let Err(e) = apply_file_key(&self.conn, &encryption.file_key()) {
+            // SQLCipher keeps the first key a connection was given, so the
+            // next attempt needs a fresh one
+            let flags = if self.conn.is_readonly(rusqlite::DatabaseName::Main)? {
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
+            } else {
+                rusqlite::OpenFlags::default()
+            };
+            self.conn = Connection::open_with_flags(self.file_path()?, flags)?;
+            return Err(e);
+        }
+        self.encryption = Some(encryption);
+        self.file_locked = false;
+        if !self.conn.is_readonly(rusqlite::DatabaseName::Main)? {
+            self.initialize()?;
+            let summary = migrations::run_migrations(&mut self.conn)?;
+            self.record_migration_summary(summary);
+        }
+        log::info!("Database file unlocked");
+        Ok(())
+    }
+
+    /// Encrypts the whole database file with SQLCipher, keyed from the
+    /// current password (so after `initialize_encryption` or
+    /// `set_encryption_state`). This is the migration from the older format,
+    /// where only the settings are encrypted; they stay encrypted as well.
+    pub fn encrypt_file(&mut self) -> Result<()> {
+        let key = self.encryption.as_ref()
+            .map(|encryption| encryption.file_key())
+            .ok_or_else(|| anyhow::anyhow!("Enter the database password before encrypting the file"))?;
+        if self.is_file_encrypted() {
+            return Ok(());
+        }
+        self.export_file(&key)?;
+        log::info!("Database file encrypted");
+        Ok(())
+    }
+
+    /// Rewrites an encrypted database file (see `encrypt_file`) as plain
+    /// SQLite, e.g. before encryption is turned off. The settings keep their
+    /// own encryption until saved without it.
+    pub fn decrypt_file(&mut self) -> Result<()> {
+        if self.file_locked {
+            return Err(anyhow::anyhow!("Unlock the database file before decrypting it"));
+        }
+        if !self.is_file_encrypted() {
+            return Ok(());
+        }
+        self.export_file("")?;
+        log::info!("Database file decrypted");
+        Ok(())
+    }
+
+    /// Copies everything into a new file keyed with `key` (`""` for plain
+    /// SQLite) with `sqlcipher_export`, then swaps it in for the current
+    /// file. The current file is left as it was if the copy fails.
+    fn export_file(&mut self, key: &str) -> Result<()> {
+        if !FILE_ENCRYPTION_SUPPORTED {
+            return Err(anyhow::anyhow!(FILE_ENCRYPTION_UNSUPPORTED));
+        }
+        let path = self.file_path()?;
+        let current_key = match &self.encryption {
+            Some(encryption) if is_file_encrypted_at(&path)? => encryption.file_key(),
+            _ => Zeroizing::new(String::new()),
+        };
+        let exported = path.with_extension("db.export");
+        let _ = std::fs::remove_file(&exported);
+
+        let user_version: i64 = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        self.conn.execute("ATTACH DATABASE ?1 AS export KEY ?2", params![exported.to_string_lossy(), key])?;
+        let copied = self.conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))
+            .and_then(|_| self.conn.pragma_update(Some(rusqlite::DatabaseName::Attached("export")), "user_version", user_version));
+        self.conn.execute("DETACH DATABASE export", [])?;
+        if let Err(e) = copied {
+            let _ = std::fs::remove_file(&exported);
+            return Err(e.into());
+        }
+
+        // Close the file before replacing it
+        self.conn = Connection::open_in_memory()?;
+        let replaced = std::fs::rename(&exported, &path);
+        let conn = Connection::open(&path)?;
+        let key = if replaced.is_ok() { key } else { current_key.as_str() };
+        if !key.is_empty() {
+            apply_file_key(&conn, key)?;
+        }
+        self.conn = conn;
+        replaced?;
+        Ok(())
+    }
+
+    /// Where this database is stored; an error for an in-memory one.
+    fn file_path(&self) -> Result<std::path::PathBuf> {
+        self.conn.path()
+            .filter(|path| !path.is_empty())
+            .map(std::path::PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("This database isn't stored in a file"))
+    }
+
+    /// `value` as `column` is stored: sealed (see `DatabaseEncryption::seal`)
+    /// if the column is encrypted and the password has been given, as it
+    /// is otherwise.
+    fn seal_column(&self, column: SensitiveColumn, value: &str) -> Result<String> {
+        match &self.encryption {
+            Some(encryption) if self.encryption_config.encrypts(column) => encryption.seal(value),
+            _ => Ok(value.to_string()),
+        }
+    }
+
+    /// A stored value opened with the password. Without it, or if it won't
+    /// open, the value is kept sealed, so saving the flow again writes back
+    /// what was there rather than losing it.
+    fn open_value(&self, value: String) -> String {
+        let Some(encryption) = self.encryption.as_ref().filter(|_| is_sealed(&value)) else {
+            return value;
+        };
+        encryption.open(&value).unwrap_or_else(|e| {
+            log::warn!("Could not decrypt a stored value: {}", e);
+            value
+        })
+    }
+
+    fn open_flow(&self, flow: Flow) -> Flow {
+        Flow {
+            description: self.open_value(flow.description),
+            location: flow.location.map(|location| self.open_value(location)),
+            custom_fields: flow.custom_fields.into_iter().map(|(name, value)| (name, self.open_value(value))).collect(),
+            ..flow
+        }
+    }
+
+    /// Opens the sealed custom field values of `category_id`'s flows in
+    /// place, returning the flows that had any, for `seal_custom_fields`.
+    fn open_custom_fields(tx: &Connection, encryption: &DatabaseEncryption, category_id: &str) -> Result<Vec<String>> {
+        let stored: Vec<(String, String)> = tx
+            .prepare("SELECT id, custom_fields FROM flows WHERE category_id = ?")?
+            .query_map(params![category_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut opened = Vec::new();
+        for (id, json) in stored {
+            let fields: HashMap<String, String> = serde_json::from_str(&json)?;
+            if fields.values().any(|value| is_sealed(value)) {
+                let fields = fields.into_iter()
+                    .map(|(name, value)| Ok((name, encryption.open(&value)?)))
+                    .collect::<Result<HashMap<_, _>>>()?;
+                tx.execute("UPDATE flows SET custom_fields = ? WHERE id = ?", params![serde_json::to_string(&fields)?, id])?;
+                opened.push(id);
+            }
+        }
+        Ok(opened)
+    }
+
+    /// Seals the custom field values of `flow_ids` again after
+    /// `open_custom_fields`.
+    fn seal_custom_fields(tx: &Connection, encryption: &DatabaseEncryption, flow_ids: &[String]) -> Result<()> {
+        for id in flow_ids {
+            let json: String = tx.query_row("SELECT custom_fields FROM flows WHERE id = ?", params![id], |row| row.get(0))?;
+            let fields: HashMap<String, String> = serde_json::from_str(&json)?;
+            let fields = fields.into_iter()
+                .map(|(name, value)| Ok((name, encryption.seal(&value)?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+            tx.execute("UPDATE flows SET custom_fields = ? WHERE id = ?", params![serde_json::to_string(&fields)?, id])?;
+        }
+        Ok(())
+    }
+
+    /// Stores every flow's sensitive columns as `columns` says: sealing the
+    /// ones now encrypted and opening the ones no longer encrypted. Needs
+    /// the password (see `set_encryption_state`). Returns how many flows
+    /// were rewritten.
+    pub fn set_encrypted_columns(&mut self, columns: Vec<SensitiveColumn>) -> Result<usize> {
+        if self.encryption.is_none() {
+            return Err(anyhow::anyhow!("Enter the database password before changing which columns are encrypted"));
+        }
+        let flows = self.load_flows()?;
+        let still_sealed = |flow: &&Flow| {
+            is_sealed(&flow.description)
+                || flow.location.as_deref().is_some_and(is_sealed)
+                || flow.custom_fields.values().any(|value| is_sealed(value))
+        };
+        if let Some(flow) = flows.iter().find(still_sealed) {
+            return Err(anyhow::anyhow!("Flow {} has values the current password can't decrypt", flow.id));
+        }
+        let previous = std::mem::replace(&mut self.encryption_config.encrypted_columns, columns);
+
+        let rewritten = (|| -> Result<usize> {
+            let tx = self.conn.unchecked_transaction()?;
+            let mut rewritten = 0;
+            for flow in &flows {
+                let custom_fields = flow.custom_fields.iter()
+                    .map(|(name, value)| Ok((name, self.seal_column(SensitiveColumn::CustomFields, value)?)))
+                    .collect::<Result<HashMap<_, _>>>()?;
+                let location = flow.location.as_deref().map(|location| self.seal_column(SensitiveColumn::Location, location)).transpose()?;
+                rewritten += tx.execute(
+                    "UPDATE flows SET description = ?, custom_fields = ?, location = ? WHERE id = ?",
+                    params![self.seal_column(SensitiveColumn::Description, &flow.description)?, serde_json::to_string(&custom_fields)?, location, flow.id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(rewritten)
+        })();
+        match rewritten {
+            Ok(rewritten) => {
+                self.mark_dirty();
+                log::info!("Rewrote {} flows for encrypted columns {:?}", rewritten, self.encryption_config.encrypted_columns);
+                Ok(rewritten)
+            }
+            Err(e) => {
+                self.encryption_config.encrypted_columns = previous;
+                Err(e)
+            }
+        }
+    }
+
+    /// Re-encrypts everything the current key encrypted -- the settings, the
+    /// sealed flow values and the file itself -- under the key derived from
+    /// `password` and `salt` as `derivation` says, and uses that key from
+    /// then on; the caller records all three in the config. A value the
+    /// current key can't decrypt leaves everything as it was. Returns how
+    /// many flows were rewritten.
+    pub fn change_key(&mut self, password: &str, salt: &str, derivation: KeyDerivation) -> Result<usize> {
+        if self.file_locked {
+            return Err(anyhow::anyhow!("Unlock the database file before changing its password"));
+        }
+        let new = DatabaseEncryption::new(password, salt, derivation)?;
+        let current = self.encryption.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Enter the current password before changing it"))?;
+        let rewritten = Self::reencrypt_values(&self.conn, current, &new)?;
+
+        if self.is_file_encrypted()
+            && let Err(e) = self.export_file(&new.file_key())
+        {
+            // The file kept the current key, so the values go back to it
+            let current = self.encryption.as_ref().expect("checked above");
+            Self::reencrypt_values(&self.conn, &new, current)?;
+            return Err(e);
+        }
+        self.encryption = Some(new);
+        self.mark_dirty();
+        log::info!("Re-encrypted the settings and {} flows under the new password", rewritten);
+        Ok(rewritten)
+    }
+
+    /// Decrypts the stored settings and sealed flow values with `from` and
+    /// encrypts them again with `to`, all in one transaction. Values that
+    /// aren't encrypted are left alone.
+    fn reencrypt_values(conn: &Connection, from: &DatabaseEncryption, to: &DatabaseEncryption) -> Result<usize> {
+        let reseal = |value: &str| -> Result<String> {
+            if is_sealed(value) { to.seal(&from.open(value)?) } else { Ok(value.to_string()) }
+        };
+        let tx = conn.unchecked_transaction()?;
+
+        let settings: Option<String> = tx
+            .query_row("SELECT settings_json FROM user_settings WHERE id = 1", [], |row| row.get(0))
+            .optional()?;
+        // Plaintext settings start with '{' (see `detect_encryption_state`)
+        if let Some(settings) = settings.filter(|json| !json.trim_start().starts_with('{')) {
+            let json = from.decrypt(&settings)
+                .map_err(|_| anyhow::anyhow!("The stored settings can't be decrypted with the current password"))?;
+            tx.execute("UPDATE user_settings SET settings_json = ?1 WHERE id = 1", params![to.encrypt(&json)?])?;
+        }
+
+        let stored: Vec<(String, String, String, Option<String>)> = tx
+            .prepare("SELECT id, description, custom_fields, location FROM flows")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut rewritten = 0;
+        for (id, description, custom_fields, location) in stored {
+            let fields: HashMap<String, String> = serde_json::from_str(&custom_fields)?;
+            if !is_sealed(&description) && !location.as_deref().is_some_and(is_sealed) && !fields.values().any(|value| is_sealed(value)) {
+                continue;
+            }
+            let resealed = (|| -> Result<(String, HashMap<String, String>, Option<String>)> {
+                let fields = fields.into_iter()
+                    .map(|(name, value)| Ok((name, reseal(&value)?)))
+                    .collect::<Result<HashMap<_, _>>>()?;
+                Ok((reseal(&description)?, fields, location.as_deref().map(reseal).transpose()?))
+            })();
+            let (description, fields, location) = resealed
+                .map_err(|_| anyhow::anyhow!("Flow {} has values the current password can't decrypt", id))?;
+            rewritten += tx.execute(
+                "UPDATE flows SET description = ?, custom_fields = ?, location = ? WHERE id = ?",
+                params![description, serde_json::to_string(&fields)?, location, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(rewritten)
+    }
+
+    /// Encrypt sensitive data if encryption is enabled
+    fn encrypt_data(&self, data: &str) -> Result<String> {
+        if let Some(encryption) = &self.encryption {
+            encryption.encrypt(data)
+        } else {
+            Ok(data.to_string()) // No encryption, return as-is
+        }
+    }
+
+    /// Decrypt sensitive data if encryption is enabled
+    fn decrypt_data(&self, data: &str) -> Result<String> {
+        if let Some(encryption) = &self.encryption {
+            encryption.decrypt(data)
+        } else {
+            Ok(data.to_string()) // No encryption, return as-is
+        }
+    }
+
+    fn initialize(&self) -> Result<()> {
+        // Create tables if they don't exist
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS categories (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                flow_type TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                tax_deduction_allowed INTEGER NOT NULL,
+                tax_deduction_default INTEGER NOT NULL,
+                tax_jurisdictions TEXT NOT NULL DEFAULT '[]',
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                color INTEGER,
+                icon TEXT
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS flows (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                amount REAL NOT NULL,
+                category_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                linked_flows TEXT NOT NULL,
+                custom_fields TEXT NOT NULL,
+                tax_deductible INTEGER,
+                refund_of TEXT,
+                scheduled INTEGER NOT NULL DEFAULT 0,
+                created_utc_offset INTEGER,
+                location TEXT,
+                trip_id TEXT,
+                reimbursement_status TEXT,
+                reimbursed_by TEXT,
+                FOREIGN KEY (category_id) REFERENCES categories(id)
+            )",
+            [],
+        )?;
+
+        // Category views and totals look flows up by these
+        self.conn.execute("CREATE INDEX IF NOT EXISTS flows_category_id ON flows (category_id)", [])?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS flows_date ON flows (date)", [])?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS user_settings (
+                id INTEGER PRIMARY KEY,
+                settings_json TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS metric_snapshots (
+                period TEXT NOT NULL,
+                taken_on TEXT NOT NULL,
+                metric TEXT NOT NULL,
+                value REAL NOT NULL,
+                PRIMARY KEY (period, metric)
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS locked_years (
+                year INTEGER PRIMARY KEY
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS report_templates (
+                name TEXT PRIMARY KEY,
+                request_json TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS trips (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS sql_views (
+                name TEXT PRIMARY KEY,
+                sql TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    // Deliberately does *not* call `mark_dirty` (see its doc comment):
+    // `UserSettings` is UI/app preferences (year filter, hidden categories,
+    // backup directory, backup history, ...), not financial records, and
+    // every call site is exactly that kind of routine preference change --
+    // marking dirty here meant something as trivial as switching the year
+    // filter would trigger a full automatic backup on the next exit.
+    pub fn save_user_settings(&self, settings: &UserSettings) -> Result<()> {
+        let settings_json = serde_json::to_string(settings)?;
+
+        // Encrypt the settings if encryption is enabled
+        let encrypted_json = self.encrypt_data(&settings_json)?;
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO user_settings (id, settings_json)
+             VALUES (1, ?1)",
+            params![encrypted_json],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn load_user_settings(&self) -> Result<UserSettings> {
+        let result = self.conn.query_row(
+            "SELECT settings_json FROM user_settings WHERE id = 1",
+            [],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(encrypted_json) => {
+                // Decrypt the settings if encryption is enabled
+                let decrypted_json = self.decrypt_data(&encrypted_json)?;
+                
+                // Try to deserialize with backward compatibility
+                match serde_json::from_str::<UserSettings>(&decrypted_json) {
+                    Ok(settings) => Ok(settings),
+                    Err(e) => {
+                        // If deserialization fails, try to create default settings
+                        log::error!("Warning: Failed to deserialize user settings: {}", e);
+                        log::error!("This might be due to an old database format. Using default settings.");
+                        Ok(UserSettings::new())
+                    }
+                }
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                // No user settings exist yet, return default settings
+                Ok(UserSettings::new())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_category(conn: &Connection, category_id: &str) -> Result<Option<Category>> {
+        let mut stmt = conn.prepare("SELECT id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon FROM categories WHERE id = ?")?;
+        let result = stmt.query_row(params![category_id], |row| {
+            let id: String = row.get(0)?;
+            let name: String = row.get(1)?;
+            let flow_type_str: String = row.get(2)?;
+            let fields_json: String = row.get(3)?;
+            let tax_deduction_allowed: i64 = row.get(4)?;
+            let tax_deduction_default: i64 = row.get(5)?;
+            let jurisdictions_json: String = row.get(6)?;
+            let sort_order: i64 = row.get(7)?;
+            let color: Option<i64> = row.get(8)?;
+            let icon: Option<String> = row.get(9)?;
+            
+            let flow_type = match flow_type_str.as_str() {
+                "Income" => FlowType::Income,
+                "Expense" => FlowType::Expense,
+                _ => return Err(rusqlite::Error::InvalidParameterName(format!("Invalid flow type: {}", flow_type_str))),
+            };
+            
+            let fields: Vec<CategoryField> = serde_json::from_str(&fields_json)
+                .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+            let jurisdictions = serde_json::from_str(&jurisdictions_json)
+                .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+            
+            Ok(Category {
+                id,
+                name,
+                flow_type,
+                parent_id: None,
+                sort_order,
+                color: color_from_sql(color),
+                icon,
+                fields,
+                tax_deduction: TaxDeductionInfo {
+                    deduction_allowed: tax_deduction_allowed != 0,
+                    default_value: tax_deduction_default != 0,
+                    jurisdictions,
+                },
+            })
+        });
+
+        match result {
+            Ok(category) => Ok(Some(category)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_category(&mut self, category: &Category) -> Result<()> {
+        self.save_category_renaming_options(category, &OptionRenames::default())
+    }
+
+    /// Saves `category` like `save_category`, carrying the values of flows
+    /// whose Select option was renamed over to the new name.
+    pub fn save_category_renaming_options(&mut self, category: &Category, option_renames: &OptionRenames) -> Result<()> {
+        // Start transaction
+        let tx = self.conn.transaction()?;
+
+        // Get the old category before making any changes, if one exists.
+        // A brand-new category has no prior row, so there's nothing to
+        // diff/migrate against.
+        let old_category = Self::get_category(&tx, &category.id)?;
+
+        // Save the category
+        let fields_json = serde_json::to_string(&category.fields)?;
+        let jurisdictions_json = serde_json::to_string(&category.tax_deduction.jurisdictions)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO categories (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                category.id,
+                category.name,
+                category.flow_type.to_string(),
+                fields_json,
+                if category.tax_deduction.deduction_allowed { 1 } else { 0 },
+                if category.tax_deduction.default_value { 1 } else { 0 },
+                jurisdictions_json,
+                category.sort_order,
+                color_to_sql(category.color),
+                category.icon
+            ],
+        )?;
+
+        // Run migrations if needed (only applies when updating an existing category)
+        if let Some(old_category) = old_category {
+            if migrations::has_schema_changes(&old_category, category) {
+                // Sealed values can't be checked against the new field
+                // types, so the migration sees them opened
+                let opened = match &self.encryption {
+                    Some(encryption) => Self::open_custom_fields(&tx, encryption, &category.id)?,
+                    None => Vec::new(),
+                };
+                migrations::migrate_flows_to_new_category(&tx, &old_category, category, option_renames)?;
+                if let Some(encryption) = &self.encryption {
+                    Self::seal_custom_fields(&tx, encryption, &opened)?;
+                }
+            }
+        }
+
+        // Commit transaction
+        tx.commit()?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Refuses (with an error naming the year) to save a flow dated in a
+    /// locked year, or to move an existing flow out of one.
+    pub fn save_flow(&self, flow: &Flow) -> Result<()> {
+        self.ensure_year_unlocked(flow.date.year())?;
+        self.ensure_flow_unlocked(&flow.id)?;
+
+        let linked_flows_json = serde_json::to_string(&flow.linked_flows)?;
+        let custom_fields = flow.custom_fields.iter()
+            .map(|(name, value)| Ok((name, self.seal_column(SensitiveColumn::CustomFields, value)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let custom_fields_json = serde_json::to_string(&custom_fields)?;
+        let description = self.seal_column(SensitiveColumn::Description, &flow.description)?;
+        let location = flow.location.as_deref().map(|location| self.seal_column(SensitiveColumn::Location, location)).transpose()?;
+        
+        self.conn.execute(
+            "INSERT OR REPLACE INTO flows (id, date, amount, category_id, description, linked_flows, custom_fields, tax_deductible, refund_of, scheduled, created_utc_offset, location, trip_id, reimbursement_status, reimbursed_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                flow.id,
+                flow.date.to_string(),
+                flow.amount,
+                flow.category_id,
+                description,
+                linked_flows_json,
+                custom_fields_json,
+                flow.tax_deductible.map(|b| if b { 1 } else { 0 }),
+                flow.refund_of,
+                flow.scheduled,
+                flow.created_utc_offset,
+                location,
+                flow.trip_id,
+                flow.reimbursement.map(|status| status.to_string()),
+                flow.reimbursed_by
+            ],
+        )?;
+
+        self.mark_dirty();
+        Ok(())
+    }
+
+    pub fn load_categories(&self) -> Result<Vec<Category>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon
+             FROM categories ORDER BY sort_order, rowid"
+        )?;
+
+        let categories = stmt.query_map([], |row| {
+            let flow_type_str: String = row.get(2)?;
+            let flow_type = match flow_type_str.as_str() {
+                "Income" => FlowType::Income,
+                "Expense" => FlowType::Expense,
+                _ => return Err(rusqlite::Error::FromSqlConversionFailure(
+                    2,
+                    rusqlite::types::Type::Text,
+                    Box::new(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid flow type: {}", flow_type_str),
+                    )),
+                )),
+            };
+
+            let fields_json: String = row.get(3)?;
+            let fields = serde_json::from_str(&fields_json)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+            let tax_deduction_allowed: i64 = row.get(4)?;
+            let tax_deduction_default: i64 = row.get(5)?;
+
+            let jurisdictions_json: String = row.get(6)?;
+            let jurisdictions = serde_json::from_str(&jurisdictions_json)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?;
+
+            Ok(Category {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                flow_type,
+                parent_id: None,
+                sort_order: row.get(7)?,
+                color: color_from_sql(row.get(8)?),
+                icon: row.get(9)?,
+                fields,
+                tax_deduction: TaxDeductionInfo {
+                    deduction_allowed: tax_deduction_allowed != 0,
+                    default_value: tax_deduction_default != 0,
+                    jurisdictions,
+                },
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for category in categories {
+            result.push(category?);
+        }
+
+        Ok(result)
+    }
+
+    pub fn load_flows(&self) -> Result<Vec<Flow>> {
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM flows", FLOW_COLUMNS))?;
+        let flows = stmt.query_map([], flow_from_row)?;
+        Ok(flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The flows in `scope`, newest first, so a view can load only what it
+    /// shows rather than every flow there is.
+    pub fn load_flows_in(&self, scope: &FlowScope) -> Result<Vec<Flow>> {
+        let mut conditions = vec!["1 = 1"];
+        let mut values: Vec<String> = Vec::new();
+        if let Some(category_id) = &scope.category_id {
+            conditions.push("category_id = ?");
+            values.push(category_id.clone());
+        }
+        if let Some(years) = &scope.years {
+            // A range rather than substr() so the date index is used
+            conditions.push("date BETWEEN ? AND ?");
+            values.push(format!("{:04}-01-01", years.start()));
+            values.push(format!("{:04}-12-31", years.end()));
+        }
+        let limit = scope.limit.map_or(-1, |limit| limit as i64);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM flows WHERE {} ORDER BY date DESC, id LIMIT {} OFFSET {}",
+            FLOW_COLUMNS,
+            conditions.join(" AND "),
+            limit,
+            scope.offset,
+        ))?;
+        let flows = stmt.query_map(rusqlite::params_from_iter(&values), flow_from_row)?;
+        Ok(flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The flows that refund or were reimbursed by any of `flow_ids`, i.e.
+    /// what deleting them would unlink.
+    pub fn load_flows_linked_to(&self, flow_ids: &[String]) -> Result<Vec<Flow>> {
+        if flow_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; flow_ids.len()].join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM flows WHERE refund_of IN ({1}) OR reimbursed_by IN ({1})",
+            FLOW_COLUMNS,
+            placeholders,
+        ))?;
+        let flows = stmt.query_map(rusqlite::params_from_iter(flow_ids.iter().chain(flow_ids)), flow_from_row)?;
+        Ok(flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// The flows with any of `flow_ids` that are stored, in no particular
+    /// order.
+    pub fn load_flows_with_ids(&self, flow_ids: &[String]) -> Result<Vec<Flow>> {
+        if flow_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; flow_ids.len()].join(", ");
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM flows WHERE id IN ({})", FLOW_COLUMNS, placeholders))?;
+        let flows = stmt.query_map(rusqlite::params_from_iter(flow_ids), flow_from_row)?;
+        Ok(flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// What a report for `request` needs as of `today`, with `pending`
+    /// replayed over it: the flows its period, category and trip filters
+    /// cover, or the ones picked when any are, plus whatever flows refunds
+    /// among them refund, since a refund's deductibility follows them.
+    /// `ReportGenerator` applies the rest of the request.
+    pub fn load_report_flows(&self, request: &ReportRequest, today: NaiveDate, pending: &PendingChanges) -> Result<Vec<Flow>> {
+        let mut conditions = vec!["NOT scheduled".to_string()];
+        let mut values: Vec<String> = Vec::new();
+        if request.selected_flows.is_empty() {
+            let (start, end) = request.time_period.bounds(today);
+            conditions.push("date BETWEEN ? AND ?".to_string());
+            values.extend([start.to_string(), end.to_string()]);
+            if let Some(category_ids) = &request.selected_categories {
+                conditions.push(format!("category_id IN ({})", vec!["?"; category_ids.len()].join(", ")));
+                values.extend(category_ids.iter().cloned());
+            }
+            if let Some(trip_id) = &request.trip_id {
+                conditions.push("trip_id = ?".to_string());
+                values.push(trip_id.clone());
+            }
+        } else {
+            conditions.push(format!("id IN ({})", vec!["?"; request.selected_flows.len()].join(", ")));
+            values.extend(request.selected_flows.iter().cloned());
+        }
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM flows WHERE {}", FLOW_COLUMNS, conditions.join(" AND ")))?;
+        let flows = stmt.query_map(rusqlite::params_from_iter(&values), flow_from_row)?;
+        let mut flows: Vec<Flow> = flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?;
+        pending.apply_to(&mut flows);
+
+        // Deleted originals are pending too, and stay gone
+        let mut originals: Vec<String> = flows.iter()
+            .filter_map(|f| f.refund_of.clone())
+            .filter(|id| !flows.iter().any(|f| &f.id == id) && !pending.changes().iter().any(|change| &change.flow().id == id))
+            .collect();
+        originals.sort();
+        originals.dedup();
+        flows.extend(self.load_flows_with_ids(&originals)?);
+        Ok(flows)
+    }
+
+    /// Every scheduled flow, however far off, for confirming the ones that
+    /// have come due without loading the rest.
+    pub fn load_scheduled_flows(&self) -> Result<Vec<Flow>> {
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM flows WHERE scheduled ORDER BY date, id", FLOW_COLUMNS))?;
+        let flows = stmt.query_map([], flow_from_row)?;
+        Ok(flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Every reimbursable expense not yet paid back, however old, oldest
+    /// first (see `Flow::is_outstanding_reimbursement`).
+    pub fn load_outstanding_reimbursements(&self) -> Result<Vec<Flow>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM flows WHERE NOT scheduled AND reimbursement_status IN (?, ?) ORDER BY date, id",
+            FLOW_COLUMNS,
+        ))?;
+        let flows = stmt.query_map(
+            params![ReimbursementStatus::Outstanding.to_string(), ReimbursementStatus::Submitted.to_string()],
+            flow_from_row,
+        )?;
+        Ok(flows.map(|flow| flow.map(|flow| self.open_flow(flow))).collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Each category's net total over the flows dated `start..=end`, summed
+    /// in SQL (see `NET_AMOUNT_SQL`). Categories with no flows in range are
+    /// left out.
+    pub fn category_totals_between(&self, start: NaiveDate, end: NaiveDate) -> Result<HashMap<String, f64>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT category_id, SUM({}) FROM flows WHERE date BETWEEN ? AND ? GROUP BY category_id",
+            NET_AMOUNT_SQL,
+        ))?;
+        let totals = stmt.query_map(params![start.to_string(), end.to_string()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(totals.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Every category's summary as of `as_of`, with a trend over the
+    /// `trend_months` months ending with its month, from one query grouped
+    /// by category and month. Categories with no flows in range are left
+    /// out; they summarize to `CategorySummary::empty`.
+    pub fn category_summaries(&self, as_of: NaiveDate, trend_months: usize) -> Result<HashMap<String, CategorySummary>> {
+        let month_index = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+        let this_month = month_index(as_of);
+        let trend_start = this_month - trend_months as i32 + 1;
+        let last_year_start = (as_of.year() - 1) * 12;
+        let first = trend_start.min(last_year_start);
+        let start = NaiveDate::from_ymd_opt(first.div_euclid(12), first.rem_euclid(12) as u32 + 1, 1)
+            .ok_or_else(|| anyhow::anyhow!("No summaries before year {}", first.div_euclid(12)))?;
+        let end = NaiveDate::from_ymd_opt(as_of.year(), 12, 31).expect("every year has a last day");
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT category_id,
+                    CAST(substr(date, 1, 4) AS INTEGER) * 12 + CAST(substr(date, 6, 2) AS INTEGER) - 1 AS month,
+                    SUM({})
+             FROM flows WHERE date BETWEEN ? AND ? GROUP BY category_id, month",
+            NET_AMOUNT_SQL,
+        ))?;
+        let rows = stmt.query_map(params![start.to_string(), end.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?, row.get::<_, f64>(2)?))
+        })?;
+
+        let mut summaries: HashMap<String, CategorySummary> = HashMap::new();
+        for row in rows {
+            let (category_id, month, total) = row?;
+            let summary = summaries.entry(category_id).or_insert_with(|| CategorySummary::empty(trend_months));
+            match month.div_euclid(12) - as_of.year() {
+                -1 => summary.last_year_total += total,
+                0 => summary.this_year_total += total,
+                _ => {}
+            }
+            if month == this_month {
+                summary.current_month_total += total;
+            }
+            if (trend_start..=this_month).contains(&month) {
+                summary.monthly_trend[(month - trend_start) as usize] += total;
+            }
+        }
+        for summary in summaries.values_mut() {
+            summary.tracking_ratio = crate::utils::tracking_ratio_from_totals(summary.last_year_total, summary.this_year_total, as_of);
+        }
+        Ok(summaries)
+    }
+
+    /// Each category's `KpiTotals` over every flow dated up to `as_of`,
+    /// however old, summed in SQL, with `pending` counted in as though
+    /// saved.
+    pub fn kpi_totals(&self, as_of: NaiveDate, pending: &PendingChanges) -> Result<KpiTotals> {
+        let (excluded, pending_flows) = self.pending_flows(pending)?;
+        let placeholders = vec!["?"; excluded.len()].join(", ");
+        let mut totals = KpiTotals::default();
+        for (only_deductible, by_category) in [(false, &mut totals.by_category), (true, &mut totals.deductible_by_category)] {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT category_id, SUM({}), COUNT(*), MIN(date), MAX(date) FROM flows
+                 WHERE NOT scheduled AND date <= ? AND id NOT IN ({}) AND ({})
+                 GROUP BY category_id",
+                NET_AMOUNT_SQL,
+                placeholders,
+                if only_deductible { DEDUCTIBLE_SQL } else { "1 = 1" },
+            ))?;
+            let values = std::iter::once(as_of.to_string()).chain(excluded.iter().cloned());
+            let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, usize>(2)?, row.get::<_, String>(3)?, row.get::<_, String>(4)?))
+            })?;
+            for row in rows {
+                let (category_id, net_total, count, first, last) = row?;
+                by_category.insert(category_id, FlowAggregate {
+                    net_total,
+                    count,
+                    first: Some(NaiveDate::parse_from_str(&first, "%Y-%m-%d")?),
+                    last: Some(NaiveDate::parse_from_str(&last, "%Y-%m-%d")?),
+                });
+            }
+        }
+
+        // Pending refunds go by their originals, which may be stored
+        let mut with_originals = pending_flows.clone();
+        let missing: Vec<String> = pending_flows.iter()
+            .filter_map(|f| f.refund_of.clone())
+            .filter(|id| !excluded.contains(id))
+            .collect();
+        with_originals.extend(self.load_flows_with_ids(&missing)?);
+        totals.merge(&KpiTotals::from_flows(&with_originals, |f| {
+            f.date <= as_of && pending_flows.iter().any(|pending| pending.id == f.id)
+        }));
+        Ok(totals)
+    }
+
+    /// Each trip's totals over every flow assigned to it (see
+    /// `utils::totals_by_trip`), summed in SQL, with `pending` counted in
+    /// as though saved.
+    pub fn trip_totals(&self, categories: &[Category], pending: &PendingChanges) -> Result<HashMap<String, TripTotals>> {
+        let (excluded, pending_flows) = self.pending_flows(pending)?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT trip_id, category_id, SUM({}), COUNT(*), MIN(date), MAX(date) FROM flows
+             WHERE trip_id IS NOT NULL AND id NOT IN ({})
+             GROUP BY trip_id, category_id",
+            NET_AMOUNT_SQL,
+            vec!["?"; excluded.len()].join(", "),
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(&excluded), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, usize>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut totals = utils::totals_by_trip(&pending_flows, categories);
+        for row in rows {
+            let (trip_id, category_id, total, flow_count, first, last) = row?;
+            let (income, expenses) = match categories.iter().find(|c| c.id == category_id).map(|c| &c.flow_type) {
+                Some(FlowType::Income) => (total, 0.0),
+                Some(FlowType::Expense) => (0.0, total),
+                None => (0.0, 0.0),
+            };
+            totals.entry(trip_id).or_default().merge(&TripTotals {
+                income,
+                expenses,
+                flow_count,
+                first: Some(NaiveDate::parse_from_str(&first, "%Y-%m-%d")?),
+                last: Some(NaiveDate::parse_from_str(&last, "%Y-%m-%d")?),
+            });
+        }
+        Ok(totals)
+    }
+
+    /// The ids `pending` leaves out of the totals above, and the flows to
+    /// add in their place: its saves, and the stored refunds of whatever
+    /// it touches, since their deductibility follows the flow refunded.
+    fn pending_flows(&self, pending: &PendingChanges) -> Result<(Vec<String>, Vec<Flow>)> {
+        let touched: Vec<String> = pending.changes().iter().map(|change| change.flow().id.clone()).collect();
+        let mut flows: Vec<Flow> = self.load_flows_linked_to(&touched)?
+            .into_iter()
+            .filter(|f| f.refund_of.as_ref().is_some_and(|id| touched.contains(id)))
+            .collect();
+        let mut excluded = touched;
+        excluded.extend(flows.iter().map(|f| f.id.clone()));
+        pending.apply_to(&mut flows);
+        Ok((excluded, flows))
+    }
+
+    /// Renumbers `sort_order` to follow `category_ids`, first to last.
+    pub fn save_category_order(&mut self, category_ids: &[String]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (position, id) in category_ids.iter().enumerate() {
+            tx.execute("UPDATE categories SET sort_order = ? WHERE id = ?", params![position as i64, id])?;
+        }
+        tx.commit()?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    pub fn delete_category(&self, category_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Delete the category
+        self.conn.execute(
+            "DELETE FROM categories WHERE id = ?",
+            params![category_id],
+        )?;
+
+        self.mark_dirty();
+        Ok(())
+    }
+
+    pub fn delete_flows_by_category(&self, category_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let locked_year: Option<i32> = self.conn.query_row(
+            "SELECT MIN(CAST(substr(date, 1, 4) AS INTEGER)) FROM flows
+             WHERE category_id = ? AND CAST(substr(date, 1, 4) AS INTEGER) IN (SELECT year FROM locked_years)",
+            params![category_id],
+            |row| row.get(0),
+        )?;
+        if let Some(year) = locked_year {
+            return Err(locked_year_error(year).into());
+        }
+
+        // Delete all flows for this category
+        self.conn.execute(
+            "DELETE FROM flows WHERE category_id = ?",
+            params![category_id],
+        )?;
+
+        self.mark_dirty();
+        Ok(())
+    }
+
+    pub fn delete_flow(&self, flow_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.ensure_flow_unlocked(flow_id)?;
+
+        // Delete the flow
+        self.conn.execute(
+            "DELETE FROM flows WHERE id = ?",
+            params![flow_id],
+        )?;
+
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Deletes every flow in `flow_ids` in one transaction, or none of them
+    /// if any is in a locked year. Refunds and reimbursed expenses pointing
+    /// at a deleted flow lose the link; those in a locked year can't
+    /// change, so they keep it.
+    pub fn delete_flows(&self, flow_ids: &[String]) -> Result<()> {
+        for flow_id in flow_ids {
+            self.ensure_flow_unlocked(flow_id)?;
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        for flow_id in flow_ids {
+            tx.execute("DELETE FROM flows WHERE id = ?", params![flow_id])?;
+            tx.execute(
+                "UPDATE flows SET refund_of = NULL
+                 WHERE refund_of = ? AND CAST(substr(date, 1, 4) AS INTEGER) NOT IN (SELECT year FROM locked_years)",
+                params![flow_id],
+            )?;
+            tx.execute(
+                "UPDATE flows SET reimbursed_by = NULL
+                 WHERE reimbursed_by = ? AND CAST(substr(date, 1, 4) AS INTEGER) NOT IN (SELECT year FROM locked_years)",
+                params![flow_id],
+            )?;
+        }
+        tx.commit()?;
+
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Years whose flows can't be saved or deleted until unlocked, oldest
+    /// first.
+    pub fn load_locked_years(&self) -> Result<Vec<i32>> {
+        let mut stmt = self.conn.prepare("SELECT year FROM locked_years ORDER BY year")?;
+        let years = stmt.query_map([], |row| row.get(0))?;
+        let mut result = Vec::new();
+        for year in years {
+            result.push(year?);
+        }
+        Ok(result)
+    }
+
+    // Locking changes no financial records, so like `save_user_settings`
+    // this doesn't `mark_dirty`.
+    pub fn lock_year(&self, year: i32) -> Result<()> {
+        self.conn.execute("INSERT OR IGNORE INTO locked_years (year) VALUES (?)", params![year])?;
+        Ok(())
+    }
+
+    pub fn unlock_year(&self, year: i32) -> Result<()> {
+        self.conn.execute("DELETE FROM locked_years WHERE year = ?", params![year])?;
+        Ok(())
+    }
+
+    fn ensure_year_unlocked(&self, year: i32) -> Result<()> {
+        let locked: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM locked_years WHERE year = ?",
+            params![year],
+            |row| row.get(0),
+        )?;
+        if locked {
+            return Err(locked_year_error(year));
+        }
+        Ok(())
+    }
+
+    /// Checks the year of the flow as currently stored (if it is), since
+    /// that's the record a save or delete would change.
+    fn ensure_flow_unlocked(&self, flow_id: &str) -> Result<()> {
+        let stored_date: Option<String> = match self.conn.query_row(
+            "SELECT date FROM flows WHERE id = ?",
+            params![flow_id],
+            |row| row.get(0),
+        ) {
+            Ok(date) => Some(date),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(date) = stored_date {
+            let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+            self.ensure_year_unlocked(date.year())?;
+        }
+        Ok(())
+    }
+
+    // Saving under an existing name replaces that template. Templates are
+    // report settings rather than financial records, so no `mark_dirty`.
+    pub fn save_report_template(&self, name: &str, request: &ReportRequest) -> Result<()> {
+        let request_json = serde_json::to_string(request)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO report_templates (name, request_json) VALUES (?, ?)",
+            params![name, request_json],
+        )?;
+        Ok(())
+    }
+
+    /// Every saved report template, by name. A template that no longer
+    /// parses is logged and skipped rather than hiding all the others.
+    pub fn load_report_templates(&self) -> Result<Vec<(String, ReportRequest)>> {
+        let mut stmt = self.conn.prepare("SELECT name, request_json FROM report_templates ORDER BY name")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut result = Vec::new();
+        for row in rows {
+            let (name, request_json) = row?;
+            match serde_json::from_str(&request_json) {
+                Ok(request) => result.push((name, request)),
+                Err(e) => warn!("Skipping unreadable report template '{}': {}", name, e),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn delete_report_template(&self, name: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM report_templates WHERE name = ?", params![name])?;
+        Ok(())
+    }
+
+    pub fn save_trip(&self, trip: &Trip) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO trips (id, name, start_date, end_date) VALUES (?, ?, ?, ?)",
+            params![trip.id, trip.name, trip.start_date.to_string(), trip.end_date.to_string()],
+        )?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Every trip, earliest first.
+    pub fn load_trips(&self) -> Result<Vec<Trip>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, start_date, end_date FROM trips ORDER BY start_date, name")?;
+        let rows = stmt.query_map([], |row| {
+            let date = |i: usize| -> rusqlite::Result<NaiveDate> {
+                let date_str: String = row.get(i)?;
+                NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+                    .map_err(|e| rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, Box::new(e)))
+            };
+            Ok(Trip { id: row.get(0)?, name: row.get(1)?, start_date: date(2)?, end_date: date(3)? })
+        })?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Deletes the trip and unassigns its flows. Flows in a locked year
+    /// can't change, so they keep pointing at the deleted trip, which then
+    /// reads as no trip at all.
+    pub fn delete_trip(&self, trip_id: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE flows SET trip_id = NULL
+             WHERE trip_id = ? AND CAST(substr(date, 1, 4) AS INTEGER) NOT IN (SELECT year FROM locked_years)",
+            params![trip_id],
+        )?;
+        tx.execute("DELETE FROM trips WHERE id = ?", params![trip_id])?;
+        tx.commit()?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    // Saving under an existing name replaces that view. Like report
+    // templates, views aren't financial records, so no `mark_dirty`.
+    pub fn save_sql_view(&self, view: &SqlView) -> Result<()> {
+        self.prepare_sql_view(&view.sql)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sql_views (name, sql) VALUES (?, ?)",
+            params![view.name, view.sql],
+        )?;
+        Ok(())
+    }
+
+    /// Every saved SQL view, by name.
+    pub fn load_sql_views(&self) -> Result<Vec<SqlView>> {
+        let mut stmt = self.conn.prepare("SELECT name, sql FROM sql_views ORDER BY name")?;
+        let rows = stmt.query_map([], |row| Ok(SqlView { name: row.get(0)?, sql: row.get(1)? }))?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    pub fn delete_sql_view(&self, name: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM sql_views WHERE name = ?", params![name])?;
+        Ok(())
+    }
+
+    /// Every category's `fields` and `jurisdictions` JSON.
+    pub fn category_json(&self) -> Result<Vec<StoredJson>> {
+        let mut stmt = self.conn.prepare("SELECT name, fields, tax_jurisdictions FROM categories ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+        let mut result = Vec::new();
+        for row in rows {
+            let (name, fields, jurisdictions) = row?;
+            let owner = format!("Category {}", name);
+            result.push(StoredJson { owner: owner.clone(), column: "fields", json: fields });
+            result.push(StoredJson { owner, column: "tax_jurisdictions", json: jurisdictions });
+        }
+        Ok(result)
+    }
+
+    /// The `linked_flows` and `custom_fields` JSON of up to `limit` flows
+    /// stored after `after_rowid`, and the last rowid read (`None` once
+    /// there are no more), so a scan can work through them in batches.
+    pub fn flow_json_after(&self, after_rowid: i64, limit: usize) -> Result<(Vec<StoredJson>, Option<i64>)> {
+        let mut stmt = self.conn.prepare(
+            "SELECT rowid, id, linked_flows, custom_fields FROM flows WHERE rowid > ? ORDER BY rowid LIMIT ?"
+        )?;
+        let rows = stmt.query_map(params![after_rowid, limit as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?;
+        let mut result = Vec::new();
+        let mut last_rowid = None;
+        for row in rows {
+            let (rowid, id, linked_flows, custom_fields) = row?;
+            let owner = format!("Flow {}", id);
+            result.push(StoredJson { owner: owner.clone(), column: "linked_flows", json: linked_flows });
+            result.push(StoredJson { owner, column: "custom_fields", json: custom_fields });
+            last_rowid = Some(rowid);
+        }
+        Ok((result, last_rowid))
+    }
+
+    /// Runs a view's query, after the same checks `save_sql_view` makes, so
+    /// a view edited outside preft still can't write.
+    pub fn run_sql_view(&self, sql: &str) -> Result<SqlViewRows> {
+        let mut stmt = self.prepare_sql_view(sql)?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+        let mut rows = stmt.query([])?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut values = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                values.push(match row.get_ref(i)? {
+                    ValueRef::Null => String::new(),
+                    ValueRef::Integer(n) => n.to_string(),
+                    ValueRef::Real(x) => x.to_string(),
+                    ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+                    ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+                });
+            }
+            result.push(values);
+        }
+        Ok(SqlViewRows { columns, rows: result })
+    }
+
+    /// Prepares `sql` if it's a single statement that only reads: it must
+    /// start with SELECT, WITH or VALUES (after any `--` comments), and
+    /// SQLite must agree it doesn't write. The keyword check keeps out
+    /// statements SQLite counts as read-only but that aren't queries, such
+    /// as BEGIN or ATTACH.
+    fn prepare_sql_view(&self, sql: &str) -> Result<rusqlite::Statement<'_>> {
+        let keyword = sql.lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("--"))
+            .and_then(|line| line.split(|c: char| !c.is_ascii_alphabetic()).next())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !matches!(keyword.as_str(), "select" | "with" | "values") {
+            return Err(anyhow::anyhow!("A view must be a SELECT statement"));
+        }
+        let mut batch = rusqlite::Batch::new(&self.conn, sql);
+        let stmt = batch.next()?
+            .ok_or_else(|| anyhow::anyhow!("A view must be a SELECT statement"))?;
+        if batch.next()?.is_some() {
+            return Err(anyhow::anyhow!("A view must be a single statement"));
+        }
+        if !stmt.readonly() || stmt.column_count() == 0 {
+            return Err(anyhow::anyhow!("A view can only read data"));
+        }
+        Ok(stmt)
+    }
+
+    // Replaces any earlier snapshot for the same period. Like
+    // `save_user_settings`, deliberately does *not* call `mark_dirty`:
+    // snapshots are derived from flows, so re-taking one on every startup
+    // isn't a change worth an automatic backup by itself.
+    pub fn save_metric_snapshot(&self, snapshot: &MetricSnapshot) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM metric_snapshots WHERE period = ?", params![snapshot.period])?;
+        for (metric, value) in &snapshot.metrics {
+            tx.execute(
+                "INSERT INTO metric_snapshots (period, taken_on, metric, value) VALUES (?, ?, ?, ?)",
+                params![snapshot.period, snapshot.taken_on.to_string(), metric, value],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every saved snapshot, ordered by period.
+    pub fn load_metric_snapshots(&self) -> Result<Vec<MetricSnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT period, taken_on, metric, value FROM metric_snapshots ORDER BY period, metric"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let taken_on_str: String = row.get(1)?;
+            let taken_on = NaiveDate::parse_from_str(&taken_on_str, "%Y-%m-%d")
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?;
+            Ok((row.get::<_, String>(0)?, taken_on, row.get::<_, String>(2)?, row.get::<_, f64>(3)?))
+        })?;
+
+        let mut result: Vec<MetricSnapshot> = Vec::new();
+        for row in rows {
+            let (period, taken_on, metric, value) = row?;
+            match result.last_mut() {
+                Some(snapshot) if snapshot.period == period => snapshot.metrics.push((metric, value)),
+                _ => result.push(MetricSnapshot { period, taken_on, metrics: vec![(metric, value)] }),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Create a backup of the database to the specified path
+    /// 
+    /// # Arguments
+    /// * `backup_path` - Path where the backup file will be created
+    /// * `encrypted_backup` - If true, creates an encrypted backup (requires password)
+    ///                        If false, creates an unencrypted backup (for portability)
+    pub fn backup_to_file(&self, backup_path: &Path, encrypted_backup: bool) -> Result<()> {
+        self.backup_to_file_with_progress(backup_path, encrypted_backup, &BackupProgress::default())
+    }
+
+    /// `backup_to_file`, reporting to `progress` and stopping if it's
+    /// cancelled.
+    pub fn backup_to_file_with_progress(&self, backup_path: &Path, encrypted_backup: bool, progress: &BackupProgress) -> Result<()> {
+        if encrypted_backup && !self.is_encrypted() {
+            return Err(anyhow::anyhow!("Cannot create encrypted backup: database is not encrypted"));
+        }
+
+        if encrypted_backup {
+            // Create encrypted backup - this preserves the encryption
+            self.backup_encrypted(backup_path, progress)
+        } else {
+            // Create unencrypted backup - decrypt data before backing up
+            self.backup_unencrypted(backup_path, progress)
+        }
+    }
+
+    /// Create an encrypted backup (preserves encryption)
+    fn backup_encrypted(&self, backup_path: &Path, progress: &BackupProgress) -> Result<()> {
+        // Create a new connection to the backup file
+        let mut backup_conn = Connection::open(backup_path)?;
+
+        // SQLCipher only copies pages into a file with the same key
+        if self.is_file_encrypted()
+            && let Some(encryption) = &self.encryption
+        {
+            apply_file_key(&backup_conn, &encryption.file_key())?;
+        }
+        
+        // Create a backup object
+        let backup = rusqlite::backup::Backup::new(&self.conn, &mut backup_conn)?;
+        
+        // Perform the backup
+        copy_pages(&backup, progress)?;
+        
+        log::info!("Encrypted database backup completed to: {:?}", backup_path);
+        log::info!("Note: This backup requires the same password as the original database");
+        Ok(())
+    }
+
+    /// Create an unencrypted backup (decrypts data for portability)
+    fn backup_unencrypted(&self, backup_path: &Path, progress: &BackupProgress) -> Result<()> {
+        // Create a new connection to the backup file
+        let mut backup_conn = Connection::open(backup_path)?;
+        
+        // Start a transaction and disable foreign key constraints
+        let tx = backup_conn.transaction()?;
+        tx.execute("PRAGMA foreign_keys = OFF", [])?;
+        
+        // Initialize the backup database with the same schema
+        self.initialize_backup_database_transaction(&tx)?;
+        
+        // Copy all data, decrypting as we go
+        self.copy_data_unencrypted_transaction(&tx, progress)?;
+        
+        // Re-enable foreign key constraints
+        tx.execute("PRAGMA foreign_keys = ON", [])?;
+        
+        // Commit the transaction
+        tx.commit()?;
+        
+        log::info!("Unencrypted database backup completed to: {:?}", backup_path);
+        log::info!("Note: This backup is unencrypted and should be stored securely");
+        Ok(())
+    }
+
+    /// Initialize the backup database with the same schema within a transaction
+    fn initialize_backup_database_transaction(&self, tx: &Connection) -> Result<()> {
+        // Create tables with the same schema
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS categories (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                flow_type TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                tax_deduction_allowed INTEGER NOT NULL,
+                tax_deduction_default INTEGER NOT NULL,
+                tax_jurisdictions TEXT NOT NULL DEFAULT '[]',
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                color INTEGER,
+                icon TEXT
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS flows (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                amount REAL NOT NULL,
+                category_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                linked_flows TEXT NOT NULL,
+                custom_fields TEXT NOT NULL,
+                tax_deductible INTEGER,
+                refund_of TEXT,
+                scheduled INTEGER NOT NULL DEFAULT 0,
+                created_utc_offset INTEGER,
+                location TEXT,
+                trip_id TEXT,
+                reimbursement_status TEXT,
+                reimbursed_by TEXT,
+                FOREIGN KEY (category_id) REFERENCES categories(id)
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS user_settings (
+                id INTEGER PRIMARY KEY,
+                settings_json TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS metric_snapshots (
+                period TEXT NOT NULL,
+                taken_on TEXT NOT NULL,
+                metric TEXT NOT NULL,
+                value REAL NOT NULL,
+                PRIMARY KEY (period, metric)
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS locked_years (
+                year INTEGER PRIMARY KEY
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS report_templates (
+                name TEXT PRIMARY KEY,
+                request_json TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS trips (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS sql_views (
+                name TEXT PRIMARY KEY,
+                sql TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Copy all data from the encrypted database to the unencrypted backup within a transaction
+    fn copy_data_unencrypted_transaction(&self, tx: &Connection, progress: &BackupProgress) -> Result<()> {
+        // One checkpoint per table copied
+        const TABLES: usize = 8;
+
+        // Copy categories
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon
+             FROM categories",
+        )?;
+        let categories = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?, // id
+                row.get::<_, String>(1)?, // name
+                row.get::<_, String>(2)?, // flow_type
+                row.get::<_, String>(3)?, // fields
+                row.get::<_, i64>(4)?,    // tax_deduction_allowed
+                row.get::<_, i64>(5)?,    // tax_deduction_default
+                row.get::<_, String>(6)?, // tax_jurisdictions
+                row.get::<_, i64>(7)?,    // sort_order
+                row.get::<_, Option<i64>>(8)?, // color
+                row.get::<_, Option<String>>(9)?, // icon
+            ))
+        })?;
+
+        for category in categories {
+            let (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon) = category?;
+            tx.execute(
+                "INSERT INTO categories (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon],
+            )?;
+        }
+
+        progress.checkpoint(1, TABLES)?;
+
+        // Copy flows, opened (see `open_flow`) so sealed columns come out
+        // readable without this database's key
+        for flow in self.load_flows()? {
+            tx.execute(
+                "INSERT INTO flows (id, date, amount, category_id, description, linked_flows, custom_fields, tax_deductible, refund_of, scheduled, created_utc_offset, location, trip_id, reimbursement_status, reimbursed_by)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    flow.id,
+                    flow.date.to_string(),
+                    flow.amount,
+                    flow.category_id,
+                    flow.description,
+                    serde_json::to_string(&flow.linked_flows)?,
+                    serde_json::to_string(&flow.custom_fields)?,
+                    flow.tax_deductible.map(|b| if b { 1 } else { 0 }),
+                    flow.refund_of,
+                    flow.scheduled,
+                    flow.created_utc_offset,
+                    flow.location,
+                    flow.trip_id,
+                    flow.reimbursement.map(|status| status.to_string()),
+                    flow.reimbursed_by
+                ],
+            )?;
+        }
+
+        progress.checkpoint(2, TABLES)?;
+
+        // Copy metric snapshots
+        for snapshot in self.load_metric_snapshots()? {
+            for (metric, value) in &snapshot.metrics {
+                tx.execute(
+                    "INSERT INTO metric_snapshots (period, taken_on, metric, value) VALUES (?, ?, ?, ?)",
+                    params![snapshot.period, snapshot.taken_on.to_string(), metric, value],
+                )?;
+            }
+        }
+
+        progress.checkpoint(3, TABLES)?;
+
+        // Copy locked years
+        for year in self.load_locked_years()? {
+            tx.execute("INSERT INTO locked_years (year) VALUES (?)", params![year])?;
+        }
+        progress.checkpoint(4, TABLES)?;
+
+        // Copy report templates as stored, including any that don't parse
+        let mut stmt = self.conn.prepare("SELECT name, request_json FROM report_templates")?;
+        let templates = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for template in templates {
+            let (name, request_json) = template?;
+            tx.execute(
+                "INSERT INTO report_templates (name, request_json) VALUES (?, ?)",
+                params![name, request_json],
+            )?;
+        }
+
+        progress.checkpoint(5, TABLES)?;
+
+        // Copy trips
+        for trip in self.load_trips()? {
+            tx.execute(
+                "INSERT INTO trips (id, name, start_date, end_date) VALUES (?, ?, ?, ?)",
+                params![trip.id, trip.name, trip.start_date.to_string(), trip.end_date.to_string()],
+            )?;
+        }
+
+        progress.checkpoint(6, TABLES)?;
+
+        // Copy SQL views
+        for view in self.load_sql_views()? {
+            tx.execute("INSERT INTO sql_views (name, sql) VALUES (?, ?)", params![view.name, view.sql])?;
+        }
+        progress.checkpoint(7, TABLES)?;
+
+        // Copy user settings (decrypt if necessary)
+        let mut stmt = self.conn.prepare("SELECT settings_json FROM user_settings WHERE id = 1")?;
+        if let Ok(encrypted_json) = stmt.query_row([], |row| row.get::<_, String>(0)) {
+            // Decrypt the settings if encryption is enabled
+            let decrypted_json = self.decrypt_data(&encrypted_json)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO user_settings (id, settings_json) VALUES (1, ?)",
+                params![decrypted_json],
+            )?;
+        }
+
+        progress.checkpoint(TABLES, TABLES)
+    }
+
+    /// Rebuilds the file without the free pages deleted rows leave behind
+    /// (SQLite never shrinks it otherwise), then refreshes the statistics
+    /// the query planner picks indexes by. `progress` can stop it between
+    /// the two.
+    pub fn compact(&self, progress: &BackupProgress) -> Result<CompactReport> {
+        let size_before = self.size_in_bytes()?;
+        progress.checkpoint(0, 2)?;
+        self.conn.execute_batch("VACUUM")?;
+        progress.checkpoint(1, 2)?;
+        self.conn.execute_batch("ANALYZE")?;
+        progress.finish();
+        Ok(CompactReport { size_before, size_after: self.size_in_bytes()? })
+    }
+
+    fn size_in_bytes(&self) -> Result<u64> {
+        let page_count: u64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: u64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok(page_count * page_size)
+    }
+
+    /// Restore the database from a backup file
+    /// 
+    /// # Arguments
+    /// * `backup_path` - Path to the backup file
+    /// * `password` - Password for encrypted backups (None for unencrypted backups)
+    /// * `force_unencrypted_restore` - If true, forces restoration as unencrypted (for data recovery)
+    pub fn restore_from_file(&mut self, backup_path: &Path, password: Option<&str>, force_unencrypted_restore: bool) -> Result<()> {
+        self.restore_from_file_with_progress(backup_path, password, force_unencrypted_restore, &BackupProgress::default())
+    }
+
+    /// `restore_from_file`, reporting to `progress`. If it's cancelled the
+    /// database keeps its current contents.
+    pub fn restore_from_file_with_progress(
+        &mut self,
+        backup_path: &Path,
+        password: Option<&str>,
+        force_unencrypted_restore: bool,
+        progress: &BackupProgress,
+    ) -> Result<()> {
+        log::info!("Starting restore from file: {:?}", backup_path);
+        log::info!("Password provided: {}", password.is_some());
+        log::info!("Force unencrypted restore: {}", force_unencrypted_restore);
+        
+        // Verify the backup file exists
+        if !backup_path.exists() {
+            return Err(anyhow::anyhow!("Backup file does not exist: {:?}", backup_path));
+        }
+        log::info!("Backup file exists");
+
+        // Try to detect if the backup is encrypted by attempting to read it
+        log::info!("Detecting backup encryption...");
+        let is_encrypted_backup = self.detect_encrypted_backup(backup_path)?;
+        log::info!("Backup encryption detected: {}", is_encrypted_backup);
+
+        if is_encrypted_backup && password.is_none() && !force_unencrypted_restore {
+            return Err(anyhow::anyhow!("Encrypted backup detected but no password provided. Use force_unencrypted_restore=true for data recovery (this will result in an unencrypted database)"));
+        }
+
+        let result = if is_encrypted_backup && let Some(password) = password {
+            log::info!("Using encrypted restore path");
+            // Restore encrypted backup
+            self.restore_encrypted(backup_path, password, progress)
+        } else {
+            log::info!("Using unencrypted restore path");
+            // Restore as unencrypted (either it's unencrypted or we're forcing unencrypted restore)
+            self.restore_unencrypted(backup_path, progress).map(|()| None)
+        };
+
+        let backup_key = result?;
+        // An encrypted restore copies the backup's schema wholesale, so
+        // a backup taken before a schema migration needs it re-applied.
+        let summary = migrations::run_migrations(&mut self.conn)?;
+        self.record_migration_summary(summary);
+        if let (Some(backup_key), Some(password)) = (backup_key, password) {
+            let salt = self.encryption_config.get_salt()
+                .ok_or_else(|| anyhow::anyhow!("Salt not found"))?;
+            let current = DatabaseEncryption::new(password, salt, self.encryption_config.key_derivation)?;
+            let rewritten = Self::reencrypt_values(&self.conn, &backup_key, &current)?;
+            log::info!("Re-encrypted the restored settings and {} flows under the current key", rewritten);
+        }
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Reads the categories and flows out of a backup file without
+    /// restoring it, e.g. to compare it against another backup. Works on a
+    /// scratch in-memory copy brought up to the current schema, so backups
+    /// taken by older versions read the same as new ones and the file itself
+    /// is never modified.
+    pub fn read_backup_contents(backup_path: &Path) -> Result<(Vec<Category>, Vec<Flow>)> {
+        let backup_conn = Connection::open_with_flags(backup_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut conn = Connection::open_in_memory()?;
+        rusqlite::backup::Backup::new(&backup_conn, &mut conn)?
+            .run_to_completion(100, std::time::Duration::ZERO, None)?;
+
+        let mut scratch = Database {
+            conn,
+            encryption: None,
+            encryption_config: EncryptionConfig::default(),
+            changes: std::cell::Cell::new(0),
+            migration_summary: None,
+            file_locked: false,
+        };
+        scratch.initialize()?;
+        migrations::run_migrations(&mut scratch.conn)?;
+        Ok((scratch.load_categories()?, scratch.load_flows()?))
+    }
+
+    /// Detect if a backup file is encrypted
+    pub fn detect_encrypted_backup(&self, backup_path: &Path) -> Result<bool> {
+        // Only the *value* of user_settings.settings_json is ever encrypted
+        // (AES-GCM, then base64-encoded) -- the database file itself is a
+        // normal, fully readable SQLite file either way. So merely being able
+        // to query the user_settings table (the old check here) can't tell
+        // encrypted and unencrypted backups apart; both are always readable.
+        //
+        // Instead, inspect the actual value: unencrypted settings are always
+        // `serde_json::to_string`'d from a struct, so they always start with
+        // '{'. Base64 (the standard alphabet used by `encrypt_data`) never
+        // produces '{' as a character, so an encrypted value never starts
+        // with '{' either. This distinguishes the two reliably.
+        match Connection::open(backup_path) {
+            Ok(conn) => {
+                match conn.query_row(
+                    "SELECT settings_json FROM user_settings WHERE id = 1",
+                    [],
+                    |row| row.get::<_, String>(0),
+                ) {
+                    Ok(settings_json) => Ok(!settings_json.trim_start().starts_with('{')),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false), // no settings saved yet
+                    Err(_) => Ok(true), // table/file unreadable or malformed
+                }
+            }
+            Err(_) => Ok(true), // Can't open, assume encrypted/corrupt
+        }
+    }
+
+    /// Restore from an encrypted backup. A backup keyed with a derivation
+    /// the password has since been upgraded from (see
+    /// `EncryptionConfig::retired_key_derivations`) is restored as it is,
+    /// and that key is returned so the caller can re-encrypt its values
+    /// under the current one once the schema is up to date.
+    fn restore_encrypted(&mut self, backup_path: &Path, password: &str, progress: &BackupProgress) -> Result<Option<DatabaseEncryption>> {
+        log::info!("Starting encrypted restore from: {:?}", backup_path);
+        
+        // Verify password matches our current encryption config
+        log::info!("Verifying password...");
+        if !self.encryption_config.verify_password(password) {
+            return Err(anyhow::anyhow!("Password does not match current encryption configuration"));
+        }
+        log::info!("Password verified successfully");
+
+        // Create a connection to the backup file
+        let (backup_conn, derivation, backup_key) = self.open_encrypted_backup(backup_path, password)?;
+        let backup_file_encrypted = is_file_encrypted_at(backup_path)?;
+        let backup_key = (derivation != self.encryption_config.key_derivation).then_some(backup_key);
+        log::info!("Successfully opened encrypted backup connection");
+
+        // Pages only copy between files encrypted the same way, with the
+        // same key; otherwise copy the rows, which keeps the settings' own
+        // encryption as it is
+        if backup_file_encrypted != self.is_file_encrypted() || (backup_file_encrypted && backup_key.is_some()) {
+            log::info!("Backup file and database file differ in encryption; copying rows");
+            self.restore_rows(&backup_conn, progress)?;
+            return Ok(backup_key);
+        }
+        
+        // Create a backup object (backup -> current)
+        log::info!("Creating backup object for encrypted restore...");
+        let backup = rusqlite::backup::Backup::new(&backup_conn, &mut self.conn)?;
+        
+        // Perform the restore; stopping part way rolls the copy back
+        log::info!("Performing encrypted restore...");
+        copy_pages(&backup, progress)?;
+        
+        log::info!("Encrypted database restore completed from: {:?}", backup_path);
+        Ok(backup_key)
+    }
+
+    /// Opens an encrypted backup with the first of the password's current
+    /// and retired key derivations (`EncryptionConfig::key_derivations`)
+    /// that unlocks its file or, for a plain file, decrypts its settings.
+    /// Returns the connection, keyed if need be, with the derivation and
+    /// key that worked.
+    fn open_encrypted_backup(&self, backup_path: &Path, password: &str) -> Result<(Connection, KeyDerivation, DatabaseEncryption)> {
+        let salt = self.encryption_config.get_salt()
+            .ok_or_else(|| anyhow::anyhow!("Salt not found"))?;
+        let file_encrypted = is_file_encrypted_at(backup_path)?;
+        let mut last_error = None;
+        for derivation in self.encryption_config.key_derivations() {
+            let encryption = DatabaseEncryption::new(password, salt, derivation)?;
+            // SQLCipher keeps the first key a connection was given
+            let conn = Connection::open(backup_path)?;
+            if file_encrypted {
+                match apply_file_key(&conn, &encryption.file_key()) {
+                    Ok(()) => return Ok((conn, derivation, encryption)),
+                    Err(e) => last_error = Some(e),
+                }
+                continue;
+            }
+            let settings: Option<String> = conn
+                .query_row("SELECT settings_json FROM user_settings WHERE id = 1", [], |row| row.get(0))
+                .optional()?;
+            let opens = settings
+                .filter(|json| !json.trim_start().starts_with('{'))
+                .is_none_or(|json| encryption.decrypt(&json).is_ok());
+            if opens {
+                return Ok((conn, derivation, encryption));
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("The password doesn't decrypt this backup")))
+    }
+
+    /// Restore from an unencrypted backup
+    fn restore_unencrypted(&mut self, backup_path: &Path, progress: &BackupProgress) -> Result<()> {
+        log::info!("Starting unencrypted restore from: {:?}", backup_path);
+        
+        // Create a connection to the backup file
+        let backup_conn = Connection::open(backup_path)?;
+        log::info!("Successfully opened backup connection");
+        self.restore_rows(&backup_conn, progress)
+    }
+
+    /// Replaces this database's rows with those in `backup_conn`. Nothing is
+    /// committed until the last checkpoint passes.
+    fn restore_rows(&mut self, backup_conn: &Connection, progress: &BackupProgress) -> Result<()> {
+        const STAGES: usize = 4;
+
+        // Collect data from backup
+        log::info!("Collecting data from backup...");
+        let categories_data = self.collect_categories_from_backup(backup_conn)?;
+        log::info!("Collected {} categories from backup", categories_data.len());
+        
+        let flows_data = self.collect_flows_from_backup(backup_conn)?;
+        log::info!("Collected {} flows from backup", flows_data.len());
+        
+        let user_settings_data = self.collect_user_settings_from_backup(backup_conn)?;
+        log::info!("User settings collected: {}", user_settings_data.is_some());
+
+        let metric_snapshots_data = Self::collect_metric_snapshots_from_backup(backup_conn)?;
+        log::info!("Metric snapshots collected: {}", metric_snapshots_data.as_ref().map_or(0, |rows| rows.len()));
+
+        let locked_years_data = Self::collect_locked_years_from_backup(backup_conn)?;
+        log::info!("Locked years collected: {:?}", locked_years_data);
+
+        let report_templates_data = Self::collect_report_templates_from_backup(backup_conn)?;
+        log::info!("Report templates collected: {}", report_templates_data.as_ref().map_or(0, |rows| rows.len()));
+
+        let trips_data = Self::collect_trips_from_backup(backup_conn)?;
+        log::info!("Trips collected: {}", trips_data.as_ref().map_or(0, |rows| rows.len()));
+
+        let sql_views_data = Self::collect_sql_views_from_backup(backup_conn)?;
+        log::info!("SQL views collected: {}", sql_views_data.as_ref().map_or(0, |rows| rows.len()));
+        progress.checkpoint(1, STAGES)?;
+        
+        // Start a transaction and disable foreign key constraints
+        log::info!("Starting transaction and disabling foreign key constraints...");
+        let tx = self.conn.transaction()?;
+        tx.execute("PRAGMA foreign_keys = OFF", [])?;
+        
+        // Verify foreign key constraints are actually disabled
+        let fk_enabled: i64 = tx.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        log::info!("Foreign key constraints status after disabling: {}", fk_enabled);
+        
+        // Check if there are any foreign key constraints in the database
+        let fk_count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM pragma_foreign_key_list('flows')", 
+            [], 
+            |row| row.get(0)
+        ).unwrap_or(0);
+        log::info!("Number of foreign key constraints on flows table: {}", fk_count);
+        
+        // Clear current database
+        log::info!("Clearing current database...");
+        match tx.execute("DELETE FROM flows", []) {
+            Ok(_) => log::info!("Flows cleared"),
+            Err(e) => {
+                error!("Failed to clear flows: {}", e);
+                return Err(e.into());
+            }
+        }
+        match tx.execute("DELETE FROM categories", []) {
+            Ok(_) => log::info!("Categories cleared"),
+            Err(e) => {
+                error!("Failed to clear categories: {}", e);
+                return Err(e.into());
+            }
+        }
+        match tx.execute("DELETE FROM user_settings", []) {
+            Ok(_) => log::info!("User settings cleared"),
+            Err(e) => {
+                error!("Failed to clear user settings: {}", e);
+                return Err(e.into());
+            }
+        }
+        
+        // Insert collected data
+        log::info!("Inserting categories...");
+        Self::insert_categories_transaction(&categories_data, &tx)?;
+        log::info!("Categories inserted successfully");
+        progress.checkpoint(2, STAGES)?;
+        
+        log::info!("Inserting flows...");
+        Self::insert_flows_transaction(&flows_data, &tx)?;
+        log::info!("Flows inserted successfully");
+        progress.checkpoint(3, STAGES)?;
+        
+        log::info!("Inserting user settings...");
+        Self::insert_user_settings_transaction(&user_settings_data, &tx)?;
+        log::info!("User settings inserted successfully");
+
+        // Backups taken before snapshots existed have no table at all; keep
+        // the current snapshots rather than wiping the trend history.
+        if let Some(metric_snapshots_data) = &metric_snapshots_data {
+            tx.execute("DELETE FROM metric_snapshots", [])?;
+            for (period, taken_on, metric, value) in metric_snapshots_data {
+                tx.execute(
+                    "INSERT INTO metric_snapshots (period, taken_on, metric, value) VALUES (?, ?, ?, ?)",
+                    params![period, taken_on, metric, value],
+                )?;
+            }
+            log::info!("Metric snapshots inserted successfully");
+        }
+
+        // Likewise, a backup from before year locking keeps the current locks.
+        if let Some(locked_years_data) = &locked_years_data {
+            tx.execute("DELETE FROM locked_years", [])?;
+            for year in locked_years_data {
+                tx.execute("INSERT INTO locked_years (year) VALUES (?)", params![year])?;
+            }
+            log::info!("Locked years inserted successfully");
+        }
+
+        if let Some(report_templates_data) = &report_templates_data {
+            tx.execute("DELETE FROM report_templates", [])?;
+            for (name, request_json) in report_templates_data {
+                tx.execute(
+                    "INSERT INTO report_templates (name, request_json) VALUES (?, ?)",
+                    params![name, request_json],
+                )?;
+            }
+            log::info!("Report templates inserted successfully");
+        }
+
+        if let Some(trips_data) = &trips_data {
+            tx.execute("DELETE FROM trips", [])?;
+            for (id, name, start_date, end_date) in trips_data {
+                tx.execute(
+                    "INSERT INTO trips (id, name, start_date, end_date) VALUES (?, ?, ?, ?)",
+                    params![id, name, start_date, end_date],
+                )?;
+            }
+            log::info!("Trips inserted successfully");
+        }
+
+        if let Some(sql_views_data) = &sql_views_data {
+            tx.execute("DELETE FROM sql_views", [])?;
+            for (name, sql) in sql_views_data {
+                tx.execute("INSERT INTO sql_views (name, sql) VALUES (?, ?)", params![name, sql])?;
+            }
+            log::info!("SQL views inserted successfully");
+        }
+        
+        progress.checkpoint(STAGES, STAGES)?;
+
+        // Re-enable foreign key constraints
+        log::info!("Re-enabling foreign key constraints...");
+        tx.execute("PRAGMA foreign_keys = ON", [])?;
+        log::info!("Foreign key constraints re-enabled");
+        
+        // Commit the transaction
+        log::info!("Committing transaction...");
+        tx.commit()?;
+        log::info!("Transaction committed successfully");
+        
+        log::info!("Database restore from backup rows completed");
+        if self.is_encrypted() {
+            log::info!("Note: Database is now unencrypted. Consider re-enabling encryption for security.");
+        }
+        Ok(())
+    }
+
+    /// Collect categories data from backup
+    fn collect_categories_from_backup(&self, backup_conn: &Connection) -> Result<Vec<CategoryRow>> {
+        let columns = backup_columns(backup_conn, "categories", &[
+            ("id", None),
+            ("name", None),
+            ("flow_type", None),
+            ("fields", None),
+            ("tax_deduction_allowed", None),
+            ("tax_deduction_default", None),
+            ("tax_jurisdictions", Some("'[]'")),
+            ("sort_order", Some("0")),
+            ("color", Some("NULL")),
+            ("icon", Some("NULL")),
+        ])?;
+        let mut stmt = backup_conn.prepare(&format!("SELECT {} FROM categories", columns))?;
+        let categories = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?, // id
+                row.get::<_, String>(1)?, // name
+                row.get::<_, String>(2)?, // flow_type
+                row.get::<_, String>(3)?, // fields
+                row.get::<_, i64>(4)?,    // tax_deduction_allowed
+                row.get::<_, i64>(5)?,    // tax_deduction_default
+                row.get::<_, String>(6)?, // tax_jurisdictions
+                row.get::<_, i64>(7)?,    // sort_order
+                row.get::<_, Option<i64>>(8)?, // color
+                row.get::<_, Option<String>>(9)?, // icon
+            ))
+        })?;
+
+        let mut result = Vec::new();
+        for category in categories {
+            result.push(category?);
+        }
+        Ok(result)
+    }
+
+    /// Collect flows data from backup
+    fn collect_flows_from_backup(&self, backup_conn: &Connection) -> Result<Vec<FlowRow>> {
+        let columns = backup_columns(backup_conn, "flows", &[
+            ("id", None),
+            ("date", None),
+            ("amount", None),
+            ("category_id", None),
+            ("description", None),
+            ("linked_flows", None),
+            ("custom_fields", None),
+            ("tax_deductible", None),
+            ("refund_of", Some("NULL")),
+            ("scheduled", Some("0")),
+            ("created_utc_offset", Some("NULL")),
+            ("location", Some("NULL")),
+            ("trip_id", Some("NULL")),
+            ("reimbursement_status", Some("NULL")),
+            ("reimbursed_by", Some("NULL")),
+        ])?;
+        let mut stmt = backup_conn.prepare(&format!("SELECT {} FROM flows", columns))?;
+        let flows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?, // id
+                row.get::<_, String>(1)?, // date
+                row.get::<_, f64>(2)?,    // amount
+                row.get::<_, String>(3)?, // category_id
+                row.get::<_, String>(4)?, // description
+                row.get::<_, String>(5)?, // linked_flows
+                row.get::<_, String>(6)?, // custom_fields
+                row.get::<_, Option<i64>>(7)?, // tax_deductible
+                row.get::<_, Option<String>>(8)?, // refund_of
+                row.get::<_, bool>(9)?, // scheduled
+                row.get::<_, Option<i32>>(10)?, // created_utc_offset
+                row.get::<_, Option<String>>(11)?, // location
+                row.get::<_, Option<String>>(12)?, // trip_id
+                row.get::<_, Option<String>>(13)?, // reimbursement_status
+                row.get::<_, Option<String>>(14)?, // reimbursed_by
+            ))
+        })?;
+
+        let mut result = Vec::new();
+        for flow in flows {
+            result.push(flow?);
+        }
+        Ok(result)
+    }
+
+    /// Collect metric snapshot rows from backup, or `None` if the backup
+    /// predates the table
+    fn collect_metric_snapshots_from_backup(backup_conn: &Connection) -> Result<Option<Vec<MetricSnapshotRow>>> {
+        let has_table: bool = backup_conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'metric_snapshots'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(None);
+        }
+
+        let mut stmt = backup_conn.prepare("SELECT period, taken_on, metric, value FROM metric_snapshots")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(Some(result))
+    }
+
+    /// Collect locked years from backup, or `None` if the backup predates
+    /// the table
+    fn collect_locked_years_from_backup(backup_conn: &Connection) -> Result<Option<Vec<i32>>> {
+        let has_table: bool = backup_conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'locked_years'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(None);
+        }
+
+        let mut stmt = backup_conn.prepare("SELECT year FROM locked_years")?;
+        let years = stmt.query_map([], |row| row.get(0))?;
+        let mut result = Vec::new();
+        for year in years {
+            result.push(year?);
+        }
+        Ok(Some(result))
+    }
+
+    /// Collect (name, request JSON) report template rows from backup, or
+    /// `None` if the backup predates the table
+    fn collect_report_templates_from_backup(backup_conn: &Connection) -> Result<Option<Vec<(String, String)>>> {
+        let has_table: bool = backup_conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'report_templates'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(None);
+        }
+
+        let mut stmt = backup_conn.prepare("SELECT name, request_json FROM report_templates")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(Some(result))
+    }
+
+    /// Collect (name, sql) view rows from backup, or `None` if the backup
+    /// predates the table
+    fn collect_sql_views_from_backup(backup_conn: &Connection) -> Result<Option<Vec<(String, String)>>> {
+        let has_table: bool = backup_conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'sql_views'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(None);
+        }
+
+        let mut stmt = backup_conn.prepare("SELECT name, sql FROM sql_views")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(Some(result))
+    }
+
+    /// Collect trip rows from backup, or `None` if the backup predates the
+    /// table
+    fn collect_trips_from_backup(backup_conn: &Connection) -> Result<Option<Vec<TripRow>>> {
+        let has_table: bool = backup_conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'trips'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(None);
+        }
+
+        let mut stmt = backup_conn.prepare("SELECT id, name, start_date, end_date FROM trips")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(Some(result))
+    }
+
+    /// Collect user settings data from backup
+    fn collect_user_settings_from_backup(&self, backup_conn: &Connection) -> Result<Option<String>> {
+        let mut stmt = backup_conn.prepare("SELECT settings_json FROM user_settings WHERE id = 1")?;
+        match stmt.query_row([], |row| row.get::<_, String>(0)) {
+            // Settings that are already encrypted (see
+            // `detect_encryption_state`) are kept as they are
+            Ok(settings_json) if !settings_json.trim_start().starts_with('{') => Ok(Some(settings_json)),
+            Ok(settings_json) => {
+                // Encrypt the settings if encryption is enabled
+                let encrypted_json = self.encrypt_data(&settings_json)?;
+                Ok(Some(encrypted_json))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Insert categories data into transaction
+    fn insert_categories_transaction(categories_data: &[CategoryRow], tx: &Connection) -> Result<()> {
+        log::info!("Inserting {} categories into transaction", categories_data.len());
+        for (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon) in categories_data {
+            tx.execute(
+                "INSERT INTO categories (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default, tax_jurisdictions, sort_order, color, icon],
+            )?;
+        }
+        log::info!("All categories inserted successfully");
+        Ok(())
+    }
+
+    /// Insert flows data into transaction
+    fn insert_flows_transaction(flows_data: &[FlowRow], tx: &Connection) -> Result<()> {
+        log::info!("Inserting {} flows into transaction", flows_data.len());
+        for (id, date, amount, category_id, description, linked_flows, custom_fields, tax_deductible, refund_of, scheduled, created_utc_offset, location, trip_id, reimbursement_status, reimbursed_by) in flows_data {
+            tx.execute(
+                "INSERT INTO flows (id, date, amount, category_id, description, linked_flows, custom_fields, tax_deductible, refund_of, scheduled, created_utc_offset, location, trip_id, reimbursement_status, reimbursed_by)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![id, date, amount, category_id, description, linked_flows, custom_fields, tax_deductible, refund_of, scheduled, created_utc_offset, location, trip_id, reimbursement_status, reimbursed_by],
+            )?;
+        }
+        log::info!("All flows inserted successfully");
+        Ok(())
+    }
+
+    /// Insert user settings data into transaction
+    fn insert_user_settings_transaction(user_settings_data: &Option<String>, tx: &Connection) -> Result<()> {
+        if let Some(encrypted_json) = user_settings_data {
+            tx.execute(
+                "INSERT OR REPLACE INTO user_settings (id, settings_json) VALUES (1, ?)",
+                params![encrypted_json],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Create a SQL dump of the database to a text file
+    pub fn dump_to_sql_file(&self, dump_path: &Path) -> Result<()> {
+        // Get all tables. `migrations` is excluded: it's internal bookkeeping
+        // for this specific database instance, not user data -- each database
+        // determines its own migration state via `run_migrations()`, the same
+        // way the binary backup/restore path never touches this table either.
+        let mut tables = self.conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name != 'migrations'"
+        )?;
+
+        let mut dump_content = String::new();
+
+        // Add schema for each table
+        for table_row in tables.query_map([], |row| row.get::<_, String>(0))? {
+            let table_name = table_row?;
+            let schema: String = self.conn.query_row(
+                "SELECT sql FROM sqlite_master WHERE type='table' AND name = ?",
+                params![table_name],
+                |row| row.get::<_, String>(0)
+            )?;
+            // `sqlite_master.sql` doesn't include a trailing semicolon, and
+            // doesn't preserve "IF NOT EXISTS" even if the original CREATE
+            // TABLE statement used it. Without a semicolon, restore_from_sql_file's
+            // `split(';')` would glue all table schemas together into a single
+            // statement; without "IF NOT EXISTS", restoring into an
+            // already-initialized database (the normal case) would fail.
+            let schema = if schema.starts_with("CREATE TABLE IF NOT EXISTS") {
+                schema
+            } else {
+                schema.replacen("CREATE TABLE ", "CREATE TABLE IF NOT EXISTS ", 1)
+            };
+            dump_content.push_str(&format!("{};\n\n", schema));
+        }
+        
+        // Add data for each table
+        for table_row in tables.query_map([], |row| row.get::<_, String>(0))? {
+            let table_name = table_row?;
+            let mut data_stmt = self.conn.prepare(&format!("SELECT * FROM {}", table_name))?;
+            let column_count = data_stmt.column_count();
+            
+            for row in data_stmt.query_map([], |row| {
+                let mut values = Vec::new();
+                for i in 0..column_count {
+                    let value = row.get_ref(i)?;
+                    match value.data_type() {
+                        rusqlite::types::Type::Null => values.push("NULL".to_string()),
+                        rusqlite::types::Type::Integer => {
+                            let val: i64 = row.get(i)?;
+                            values.push(val.to_string());
+                        },
+                        rusqlite::types::Type::Real => {
+                            let val: f64 = row.get(i)?;
+                            values.push(val.to_string());
+                        },
+                        rusqlite::types::Type::Text => {
+                            let val: String = row.get(i)?;
+                            values.push(format!("'{}'", val.replace("'", "''")));
+                        },
+                        rusqlite::types::Type::Blob => {
+                            values.push("X''".to_string()); // Empty blob for simplicity
+                        },
+                    }
+                }
+                Ok(values.join(", "))
+            })? {
+                let values = row?;
+                dump_content.push_str(&format!("INSERT INTO {} VALUES ({});\n", table_name, values));
+            }
+            dump_content.push('\n');
+        }
+        
+        // Write to file
+        std::fs::write(dump_path, dump_content)?;
+        log::info!("SQL dump completed to: {:?}", dump_path);
+        Ok(())
+    }
+
+    /// Restore the database from a SQL dump file
+    pub fn restore_from_sql_file(&mut self, dump_path: &Path) -> Result<()> {
+        // Verify the dump file exists
+        if !dump_path.exists() {
+            return Err(anyhow::anyhow!("Dump file does not exist: {:?}", dump_path));
+        }
+
+        // Read the dump file
+        let dump_content = std::fs::read_to_string(dump_path)?;
+        
+        // Start a transaction
+        let tx = self.conn.transaction()?;
+        
+        // Split by semicolon and execute each statement
+        for statement in dump_content.split(';') {
+            let statement = statement.trim();
+            if !statement.is_empty() && !statement.starts_with("--") {
+                tx.execute(statement, [])?;
+            }
+        }
+        
+        // Commit the transaction
+        tx.commit()?;
+        self.mark_dirty();
+
+        log::info!("Database restore from SQL dump completed from: {:?}", dump_path);
+        Ok(())
+    }
+
+    /// Get the database file path
+    pub fn get_database_path(&self) -> Result<std::path::PathBuf> {
+        let home_dir = dirs::home_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        Ok(home_dir.join(".preft").join("preft.db"))
+    }
+}
+
+/// Whether this build can encrypt the database file (the `sqlcipher`
+/// feature). Without it, SQLite ignores `PRAGMA key`.
+pub const FILE_ENCRYPTION_SUPPORTED: bool = cfg!(feature = "sqlcipher");
+
+const FILE_ENCRYPTION_UNSUPPORTED: &str = "This build of preft can't encrypt the database file (it was built without the sqlcipher feature)";
+
+/// The first bytes of every plain SQLite file.
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Whether the file at `db_path` is an encrypted (SQLCipher) database
+/// rather than plain SQLite. A missing or empty file isn't; SQLite creates
+/// it on open.
+pub fn is_file_encrypted_at(db_path: &Path) -> Result<bool> {
+    use std::io::Read;
+    let mut header = Vec::new();
+    match std::fs::File::open(db_path) {
+        Ok(file) => file.take(SQLITE_HEADER.len() as u64).read_to_end(&mut header)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(!header.is_empty() && header != SQLITE_HEADER)
+}
+
+/// Keys `conn` with a SQLCipher key (`DatabaseEncryption::file_key`),
+/// checking it against the file: a wrong key only shows on the first read.
+fn apply_file_key(conn: &Connection, key: &str) -> Result<()> {
+    if !FILE_ENCRYPTION_SUPPORTED {
+        return Err(anyhow::anyhow!(FILE_ENCRYPTION_UNSUPPORTED));
+    }
+    conn.pragma_update(None, "key", key)?;
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .map_err(|_| anyhow::anyhow!("The password doesn't unlock this database file"))?;
+    Ok(())
+}
+
+/// The error returned for any write to a flow in a locked year.
+pub fn locked_year_error(year: i32) -> anyhow::Error {
+    anyhow::anyhow!("{} is locked; unlock it before changing its flows", year)
+}
+
+impl FromSql for FlowType {
+    fn column_result(value: ValueRef<'_>) -> Result<Self, FromSqlError> {
+        let text = value.as_str().map_err(|e| FromSqlError::Other(Box::new(e)))?;
+        match text {
+            "Income" => Ok(FlowType::Income),
+            "Expense" => Ok(FlowType::Expense),
+            _ => Err(FromSqlError::Other(Box::new(rusqlite::Error::InvalidColumnType(0, "text".to_string(), Type::Text)))),
+        }
+    }
+}
+
+impl FromSql for ReimbursementStatus {
+    fn column_result(value: ValueRef<'_>) -> Result<Self, FromSqlError> {
+        let text = value.as_str().map_err(|e| FromSqlError::Other(Box::new(e)))?;
+        ReimbursementStatus::ALL.into_iter()
+            .find(|status| status.to_string() == text)
+            .ok_or_else(|| FromSqlError::Other(Box::new(rusqlite::Error::InvalidColumnType(0, "text".to_string(), Type::Text))))
+    }
+} 
\ No newline at end of file
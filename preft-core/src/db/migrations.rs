@@ -0,0 +1,1440 @@
+use anyhow::Result;
+use rusqlite::{Connection, params};
+use serde_json::Value;
+use log::{info, warn, error};
+use crate::models::{Category, FieldType, CategoryField, FlowType, OptionRenames, TaxDeductionInfo, get_default_categories};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Subfolder, next to the database file, holding the copies taken right
+/// before migrations (or a rollback) change anything.
+pub const PRE_MIGRATION_BACKUP_DIR: &str = "pre_migration_backups";
+
+/// What a migration changes, and so how `apply` and `undo` carry it out.
+enum Change {
+    /// Converts the deprecated Number field type to Float in every category
+    /// (see `convert_number_to_float`). Can't be undone: Number is no
+    /// longer offered.
+    NumberFieldsToFloat,
+    /// Adds a column to an existing table; undone by dropping it.
+    AddColumn { table: &'static str, column: &'static str, column_type: &'static str },
+}
+
+impl Change {
+    fn can_undo(&self) -> bool {
+        !matches!(self, Change::NumberFieldsToFloat)
+    }
+}
+
+/// One schema change, recorded by name and version in the `migrations`
+/// table once applied.
+struct Migration {
+    name: &'static str,
+    version: i64,
+    change: Change,
+    /// What the change means for the user's data, for the summary.
+    description: &'static str,
+}
+
+/// Every migration, in the order they apply, with strictly increasing
+/// versions. A new schema change goes at the end.
+const MIGRATIONS: [Migration; 12] = [
+    Migration {
+        name: "convert_number_to_float",
+        version: 1,
+        change: Change::NumberFieldsToFloat,
+        description: "Number fields are now Float fields.",
+    },
+    Migration {
+        name: "add_flow_refund_of",
+        version: 2,
+        change: Change::AddColumn { table: "flows", column: "refund_of", column_type: "TEXT" },
+        description: "Flows can now be linked to the expense they refund.",
+    },
+    Migration {
+        name: "add_flow_scheduled",
+        version: 3,
+        change: Change::AddColumn { table: "flows", column: "scheduled", column_type: "INTEGER NOT NULL DEFAULT 0" },
+        description: "Flows can now be scheduled ahead of time; existing flows were left as confirmed.",
+    },
+    Migration {
+        name: "add_category_tax_jurisdictions",
+        version: 4,
+        change: Change::AddColumn { table: "categories", column: "tax_jurisdictions", column_type: "TEXT NOT NULL DEFAULT '[]'" },
+        description: "Categories can now list the tax jurisdictions a deduction applies to; existing categories have none.",
+    },
+    Migration {
+        name: "add_flow_created_utc_offset",
+        version: 5,
+        change: Change::AddColumn { table: "flows", column: "created_utc_offset", column_type: "INTEGER" },
+        description: "Flows now record the time zone they were entered in; existing flows have none.",
+    },
+    Migration {
+        name: "add_flow_location",
+        version: 6,
+        change: Change::AddColumn { table: "flows", column: "location", column_type: "TEXT" },
+        description: "Flows can now record a location or venue; existing flows have none.",
+    },
+    Migration {
+        name: "add_flow_trip_id",
+        version: 7,
+        change: Change::AddColumn { table: "flows", column: "trip_id", column_type: "TEXT" },
+        description: "Flows can now be assigned to a trip; existing flows aren't on any trip.",
+    },
+    Migration {
+        name: "add_flow_reimbursement_status",
+        version: 8,
+        change: Change::AddColumn { table: "flows", column: "reimbursement_status", column_type: "TEXT" },
+        description: "Expenses can now be marked reimbursable and tracked until paid back; existing flows aren't reimbursable.",
+    },
+    Migration {
+        name: "add_flow_reimbursed_by",
+        version: 9,
+        change: Change::AddColumn { table: "flows", column: "reimbursed_by", column_type: "TEXT" },
+        description: "Reimbursed expenses can now link to the income flow that paid them back.",
+    },
+    Migration {
+        name: "add_category_sort_order",
+        version: 10,
+        change: Change::AddColumn { table: "categories", column: "sort_order", column_type: "INTEGER NOT NULL DEFAULT 0" },
+        description: "Categories can now be put in any order; existing categories keep the order they were added in.",
+    },
+    Migration {
+        name: "add_category_color",
+        version: 11,
+        change: Change::AddColumn { table: "categories", column: "color", column_type: "INTEGER" },
+        description: "Categories can now have a color; existing categories have none.",
+    },
+    Migration {
+        name: "add_category_icon",
+        version: 12,
+        change: Change::AddColumn { table: "categories", column: "icon", column_type: "TEXT" },
+        description: "Categories can now have an icon shown before their name; existing categories have none.",
+    },
+];
+
+/// The version a fully migrated database records in `PRAGMA user_version`.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// What a `run_migrations` call actually changed in an existing database,
+/// shown to the user as "What Changed in Your Data" instead of only being
+/// logged. Empty for a fresh database (whose tables are created in their
+/// current shape) and for runs where everything was already applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationSummary {
+    /// One plain-language line per schema change applied by this run.
+    pub schema_changes: Vec<String>,
+    /// (category name, field name) for each Number field converted to Float.
+    pub converted_fields: Vec<(String, String)>,
+    /// Default categories the user doesn't have, offered after an upgrade
+    /// rather than added silently (they may have deleted them on purpose).
+    pub offered_categories: Vec<Category>,
+    /// The copy of the database taken before this run changed anything
+    /// (also recorded against each migration in the `migrations` table).
+    pub backup_path: Option<PathBuf>,
+}
+
+impl MigrationSummary {
+    /// True when no migration changed anything; `offered_categories` alone
+    /// doesn't count, since it is only filled in alongside a real change.
+    pub fn is_empty(&self) -> bool {
+        self.schema_changes.is_empty() && self.converted_fields.is_empty()
+    }
+}
+
+/// What `roll_back_migrations` undid.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RollbackSummary {
+    /// Names of the migrations undone, newest first.
+    pub undone: Vec<String>,
+    /// The copy of the database taken before anything was undone.
+    pub backup_path: Option<PathBuf>,
+}
+
+/// The schema version recorded in the database; 0 for one that predates
+/// the record (or has never been migrated).
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+pub fn run_migrations(conn: &mut Connection) -> Result<MigrationSummary> {
+    log::info!("Starting database migrations...");
+    create_migrations_table(conn)?;
+
+    let found_version = schema_version(conn)?;
+    if found_version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "The database is at schema version {}, but this version of Preft only knows up to {}; update Preft to open it",
+            found_version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    // Get list of applied migrations
+    let applied_migrations: Vec<(String, i64)> = {
+        let mut stmt = conn.prepare("SELECT name, version FROM migrations ORDER BY version")?;
+        stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<Vec<(String, i64)>, _>>()?
+    };
+    
+    log::info!("Previously applied migrations: {:?}", applied_migrations);
+
+    let mut summary = MigrationSummary::default();
+    let last_recorded_id: i64 = conn.query_row("SELECT COALESCE(MAX(id), 0) FROM migrations", [], |row| row.get(0))?;
+    if has_pending_changes(conn)? {
+        summary.backup_path = backup_before_migrating(conn)?;
+    }
+
+    for migration in &MIGRATIONS {
+        if let Err(e) = apply(conn, migration, &mut summary) {
+            log::error!("Failed to run migration {} (version {}): {:#}", migration.name, migration.version, e);
+            return Err(e.context(failure_message("Migration", migration, &summary.backup_path)));
+        }
+    }
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+    if !summary.is_empty() {
+        summary.offered_categories = missing_default_categories(conn)?;
+    }
+    if let Some(backup_path) = &summary.backup_path {
+        conn.execute(
+            "UPDATE migrations SET backup_path = ? WHERE id > ?",
+            params![backup_path.to_string_lossy(), last_recorded_id],
+        )?;
+    }
+
+    log::info!("Database migrations completed successfully (schema version {})", SCHEMA_VERSION);
+    Ok(summary)
+}
+
+/// Undoes every applied migration newer than `version`, newest first,
+/// after taking a copy of the database, so a build that only knows
+/// `version` can open it. Refuses before changing anything if one of them
+/// can't be undone.
+pub fn roll_back_migrations(conn: &mut Connection, version: i64) -> Result<RollbackSummary> {
+    create_migrations_table(conn)?;
+    let mut newer = Vec::new();
+    for migration in MIGRATIONS.iter().rev().filter(|m| m.version > version) {
+        if is_recorded(conn, migration)? {
+            newer.push(migration);
+        }
+    }
+    if let Some(stuck) = newer.iter().find(|m| !m.change.can_undo()) {
+        return Err(anyhow::anyhow!(
+            "Can't roll back to schema version {}: migration {} (version {}) can't be undone",
+            version,
+            stuck.name,
+            stuck.version
+        ));
+    }
+
+    let mut summary = RollbackSummary::default();
+    if newer.is_empty() {
+        return Ok(summary);
+    }
+    summary.backup_path = backup_before_migrating(conn)?;
+    for migration in newer {
+        let previous_version = MIGRATIONS.iter().map(|m| m.version).filter(|v| *v < migration.version).max().unwrap_or(0);
+        if let Err(e) = undo(conn, migration, previous_version) {
+            log::error!("Failed to roll back migration {} (version {}): {:#}", migration.name, migration.version, e);
+            return Err(e.context(failure_message("Rolling back migration", migration, &summary.backup_path)));
+        }
+        summary.undone.push(migration.name.to_string());
+    }
+    log::info!("Rolled back to schema version {}: {:?}", version, summary.undone);
+    Ok(summary)
+}
+
+/// Says which migration failed, that its own changes were rolled back, and
+/// where the copy from before the run is.
+fn failure_message(what: &str, migration: &Migration, backup_path: &Option<PathBuf>) -> String {
+    let mut message = format!(
+        "{} {} (version {}) failed, so none of its changes were made",
+        what, migration.name, migration.version
+    );
+    if let Some(backup_path) = backup_path {
+        message.push_str(&format!("; the database as it was before is saved at {}", backup_path.display()));
+    }
+    message
+}
+
+fn create_migrations_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS migrations (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            version INTEGER NOT NULL,
+            applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            backup_path TEXT
+        )",
+        [],
+    )?;
+    if column_missing(conn, "migrations", "backup_path")? {
+        conn.execute("ALTER TABLE migrations ADD COLUMN backup_path TEXT", [])?;
+    }
+    log::info!("Migrations table verified/created");
+    Ok(())
+}
+
+/// Whether running the migrations would change any data, as opposed to
+/// only recording migrations a fresh database never needed.
+fn has_pending_changes(conn: &Connection) -> Result<bool> {
+    for migration in &MIGRATIONS {
+        let pending = match migration.change {
+            // Fields that don't even parse count too: applying it will say
+            // so, and there should be a backup first
+            Change::NumberFieldsToFloat => !is_recorded(conn, migration)? && !validate_migration(conn).unwrap_or(false),
+            Change::AddColumn { table, column, .. } => column_missing(conn, table, column)?,
+        };
+        if pending {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn is_recorded(conn: &Connection, migration: &Migration) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) > 0 FROM migrations WHERE name = ? AND version = ?",
+        params![migration.name, migration.version],
+        |row| row.get(0),
+    )?)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?",
+        params![table],
+        |row| row.get(0),
+    )?)
+}
+
+/// Applies `migration` in its own transaction, once, and records it.
+/// Column migrations check the table's actual columns rather than trusting
+/// the migrations table alone, since `initialize()` creates fresh databases
+/// with the column already in place (and a database restored from an older
+/// backup may have the migration recorded but not the column, or vice
+/// versa).
+fn apply(conn: &mut Connection, migration: &Migration, summary: &mut MigrationSummary) -> Result<()> {
+    let Migration { name, version, .. } = *migration;
+    let tx = conn.transaction()?;
+    let recorded = is_recorded(&tx, migration)?;
+
+    match migration.change {
+        Change::NumberFieldsToFloat => {
+            if recorded {
+                log::info!("Migration {} (version {}) already applied, skipping", name, version);
+                return Ok(());
+            }
+            log::info!("Running migration: {} (version {})", name, version);
+            summary.converted_fields = convert_number_to_float(&tx)?;
+            if !validate_migration(&tx)? {
+                return Err(anyhow::anyhow!("Number fields were still left after converting them to Float"));
+            }
+        }
+        Change::AddColumn { table, column, column_type } => {
+            // Nothing to alter yet; `initialize()` creates the table with
+            // the column already in place.
+            if !table_exists(&tx, table)? {
+                return Ok(());
+            }
+            if column_missing(&tx, table, column)? {
+                log::info!("Running migration: {} (version {})", name, version);
+                tx.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_type), [])?;
+                summary.schema_changes.push(migration.description.to_string());
+            }
+        }
+    }
+
+    if !recorded {
+        tx.execute("INSERT INTO migrations (name, version) VALUES (?, ?)", params![name, version])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Undoes `migration` in its own transaction, forgets it was applied and
+/// records `previous_version` as the schema version.
+fn undo(conn: &mut Connection, migration: &Migration, previous_version: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+    match migration.change {
+        Change::NumberFieldsToFloat => {
+            return Err(anyhow::anyhow!("converting Number fields to Float can't be undone"));
+        }
+        Change::AddColumn { table, column, .. } => {
+            if table_exists(&tx, table)? && !column_missing(&tx, table, column)? {
+                log::info!("Rolling back migration: {} (version {})", migration.name, migration.version);
+                tx.execute(&format!("ALTER TABLE {} DROP COLUMN {}", table, column), [])?;
+            }
+        }
+    }
+    tx.execute(
+        "DELETE FROM migrations WHERE name = ? AND version = ?",
+        params![migration.name, migration.version],
+    )?;
+    tx.pragma_update(None, "user_version", previous_version)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Copies the database file into `PRE_MIGRATION_BACKUP_DIR` beside it,
+/// returning the copy's path. In-memory databases have no file to protect
+/// and are skipped.
+fn backup_before_migrating(conn: &Connection) -> Result<Option<PathBuf>> {
+    let Some(db_path) = conn.path().filter(|path| !path.is_empty()) else {
+        return Ok(None);
+    };
+    let dir = Path::new(db_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(PRE_MIGRATION_BACKUP_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let backup_path = dir.join(format!("preft_pre_migration_{}.db", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+    if super::is_file_encrypted_at(Path::new(db_path))? {
+        // SQLCipher won't copy pages into an unkeyed file, and nothing has
+        // been written yet, so the file itself is the backup
+        std::fs::copy(db_path, &backup_path)?;
+    } else {
+        conn.backup(rusqlite::DatabaseName::Main, &backup_path, None)?;
+    }
+    log::info!("Saved pre-migration backup to {:?}", backup_path);
+    Ok(Some(backup_path))
+}
+
+/// Whether `table` exists but lacks `column`. A missing table counts as
+/// nothing to add: `initialize()` creates it with the column in place.
+pub(super) fn column_missing(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    if !table_exists(conn, table)? {
+        return Ok(false);
+    }
+    let has_column: bool = conn.query_row(
+        &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?", table),
+        params![column],
+        |row| row.get(0),
+    )?;
+    Ok(!has_column)
+}
+
+/// Default categories whose ids aren't in the `categories` table. Returns
+/// nothing for an empty table, which gets every default seeded anyway.
+fn missing_default_categories(conn: &Connection) -> Result<Vec<Category>> {
+    let existing: HashSet<String> = {
+        let mut stmt = conn.prepare("SELECT id FROM categories")?;
+        stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?
+    };
+    if existing.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(get_default_categories()
+        .into_iter()
+        .filter(|category| !existing.contains(&category.id))
+        .collect())
+}
+
+/// Returns (category name, field name) for every field converted.
+fn convert_number_to_float(conn: &Connection) -> Result<Vec<(String, String)>> {
+    log::info!("Starting conversion of Number fields to Float...");
+
+    // Get all categories
+    let mut stmt = conn.prepare("SELECT id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default FROM categories")?;
+    let categories = stmt.query_map([], |row| {
+        let id: String = row.get(0)?;
+        let name: String = row.get(1)?;
+        let flow_type_str: String = row.get(2)?;
+        let fields_json: String = row.get(3)?;
+        let tax_deduction_allowed: i64 = row.get(4)?;
+        let tax_deduction_default: i64 = row.get(5)?;
+        
+        let flow_type = match flow_type_str.as_str() {
+            "Income" => FlowType::Income,
+            "Expense" => FlowType::Expense,
+            _ => return Err(rusqlite::Error::InvalidParameterName(format!("Invalid flow type: {}", flow_type_str))),
+        };
+        
+        let fields: Vec<CategoryField> = serde_json::from_str(&fields_json)
+            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+        
+        Ok(Category {
+            id,
+            name,
+            flow_type,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields,
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: tax_deduction_allowed != 0,
+                default_value: tax_deduction_default != 0,
+                jurisdictions: Vec::new(),
+            },
+        })
+    })?;
+
+    let mut total_categories = 0;
+    let mut modified_categories = 0;
+    let mut total_fields_converted = 0;
+    let mut converted = Vec::new();
+
+    // Convert each category's Number fields to Float
+    for category_result in categories {
+        total_categories += 1;
+        let mut category = category_result?;
+        let mut modified = false;
+        let mut fields_converted = 0;
+
+        // Update field types from Number to Float
+        for field in &mut category.fields {
+            #[allow(deprecated)]
+            if field.field_type == FieldType::Number {
+                field.field_type = FieldType::Float;
+                modified = true;
+                fields_converted += 1;
+                converted.push((category.name.clone(), field.name.clone()));
+                log::info!("Converting field '{}' in category '{}' from Number to Float", 
+                    field.name, category.name);
+            }
+        }
+
+        // If any fields were modified, update the category in the database
+        if modified {
+            let fields_json = serde_json::to_string(&category.fields)?;
+            conn.execute(
+                "UPDATE categories SET fields = ? WHERE id = ?",
+                params![fields_json, category.id],
+            )?;
+            modified_categories += 1;
+            total_fields_converted += fields_converted;
+            log::info!("Updated category '{}' with {} converted fields", 
+                category.name, fields_converted);
+        }
+    }
+
+    log::info!("Migration summary:");
+    log::info!("- Total categories processed: {}", total_categories);
+    log::info!("- Categories modified: {}", modified_categories);
+    log::info!("- Total fields converted: {}", total_fields_converted);
+
+    Ok(converted)
+}
+
+fn validate_migration(conn: &Connection) -> Result<bool> {
+    log::info!("Validating migration...");
+    
+    // Check if any Number fields still exist
+    let mut stmt = conn.prepare("SELECT fields FROM categories")?;
+    let categories = stmt.query_map([], |row| {
+        let fields_json: String = row.get(0)?;
+        let fields: Vec<CategoryField> = serde_json::from_str(&fields_json)
+            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+        Ok(fields)
+    })?;
+
+    for fields_result in categories {
+        let fields = fields_result?;
+        for field in fields {
+            #[allow(deprecated)]
+            if field.field_type == FieldType::Number {
+                log::error!("Validation failed: Found unconverted Number field '{}'", field.name);
+                return Ok(false);
+            }
+        }
+    }
+
+    log::info!("Migration validation successful");
+    Ok(true)
+}
+
+/// Compares two category field schemas to determine if a migration is needed
+pub fn has_schema_changes(old_category: &Category, new_category: &Category) -> bool {
+    log::info!("Comparing schemas for category '{}':", new_category.name);
+    log::info!("Old category fields: {:?}", old_category.fields);
+    log::info!("New category fields: {:?}", new_category.fields);
+
+    // Create maps of field names to their types for easy comparison
+    let old_fields: HashMap<&str, &FieldType> = old_category.fields
+        .iter()
+        .map(|f| (f.name.as_str(), &f.field_type))
+        .collect();
+    
+    let new_fields: HashMap<&str, &FieldType> = new_category.fields
+        .iter()
+        .map(|f| (f.name.as_str(), &f.field_type))
+        .collect();
+
+    log::info!("Old field types: {:?}", old_fields);
+    log::info!("New field types: {:?}", new_fields);
+
+    let mut has_changes = false;
+    let mut changes = Vec::new();
+
+    // Check for removed fields
+    for field_name in old_fields.keys() {
+        if !new_fields.contains_key(field_name) {
+            changes.push(format!("Field '{}' was removed", field_name));
+            has_changes = true;
+            log::info!("Field '{}' exists in old schema but not in new", field_name);
+        }
+    }
+
+    // Check for added fields
+    for field_name in new_fields.keys() {
+        if !old_fields.contains_key(field_name) {
+            changes.push(format!("Field '{}' was added", field_name));
+            has_changes = true;
+            log::info!("Field '{}' exists in new schema but not in old", field_name);
+        }
+    }
+
+    // Check for type changes
+    for (field_name, new_type) in &new_fields {
+        if let Some(old_type) = old_fields.get(field_name) {
+            if old_type != new_type {
+                changes.push(format!("Field '{}' type changed from {:?} to {:?}", 
+                    field_name, old_type, new_type));
+                has_changes = true;
+                log::info!("Field '{}' type changed: {:?} -> {:?}", field_name, old_type, new_type);
+            } else {
+                log::info!("Field '{}' type unchanged: {:?}", field_name, old_type);
+            }
+        }
+    }
+
+    if has_changes {
+        log::info!("Schema changes detected for category '{}':", new_category.name);
+        for change in changes {
+            log::info!("- {}", change);
+        }
+    } else {
+        log::info!("No schema changes detected for category '{}'", new_category.name);
+    }
+
+    has_changes
+}
+
+/// Migrates flows to match a new category structure. Select values whose
+/// option is in `option_renames` take the new name, values that match an
+/// option only ignoring case take the option's spelling, and any other
+/// value of a Select field is removed, like any other value that doesn't
+/// fit its field's type.
+pub fn migrate_flows_to_new_category(
+    conn: &Connection,
+    old_category: &Category,
+    new_category: &Category,
+    option_renames: &OptionRenames,
+) -> Result<()> {
+    // Check if we actually need to migrate
+    if !has_schema_changes(old_category, new_category) {
+        log::info!("No schema changes detected for category '{}', skipping flow migration", new_category.name);
+        return Ok(());
+    }
+
+    log::info!("Starting flow migration for category '{}'", new_category.name);
+    
+    // Get all flows for this category
+    let mut stmt = conn.prepare(
+        "SELECT id, custom_fields FROM flows WHERE category_id = ?"
+    )?;
+    
+    let flows = stmt.query_map(params![new_category.id], |row| {
+        let id: String = row.get(0)?;
+        let custom_fields_json: String = row.get(1)?;
+        let custom_fields: HashMap<String, String> = serde_json::from_str(&custom_fields_json)
+            .map_err(|e| rusqlite::Error::InvalidParameterName(e.to_string()))?;
+        Ok((id, custom_fields))
+    })?;
+
+    let mut total_flows = 0;
+    let mut migrated_flows = 0;
+    let mut skipped_fields = 0;
+
+    // Create a map of old field names to new field types
+    let field_type_map: HashMap<String, FieldType> = new_category.fields
+        .iter()
+        .map(|f| (f.name.clone(), f.field_type.clone()))
+        .collect();
+
+    // Process each flow
+    for flow_result in flows {
+        total_flows += 1;
+        let (flow_id, mut custom_fields) = flow_result?;
+        let mut modified = false;
+
+        // Check each field in the flow
+        let fields_to_remove: Vec<String> = custom_fields.keys()
+            .filter(|field_name| !field_type_map.contains_key(*field_name))
+            .cloned()
+            .collect();
+
+        // Remove fields that no longer exist in the category
+        for field_name in fields_to_remove {
+            custom_fields.remove(&field_name);
+            modified = true;
+            skipped_fields += 1;
+            log::info!("Removed field '{}' from flow {}", field_name, flow_id);
+        }
+
+        // Validate and convert field values based on new types
+        for (field_name, field_type) in &field_type_map {
+            if let Some(value) = custom_fields.get(field_name) {
+                // Skip empty values, and encrypted ones, which can't be
+                // checked without the password
+                if value.trim().is_empty() || crate::encryption::is_sealed(value) {
+                    continue;
+                }
+
+                // Clone the value to avoid borrow checker issues
+                let value = value.clone();
+
+                match field_type {
+                    FieldType::Integer => {
+                        if let Ok(_) = value.parse::<i64>() {
+                            // Value is already valid
+                        } else if let Ok(float_val) = value.parse::<f64>() {
+                            // Convert float to integer
+                            custom_fields.insert(field_name.clone(), (float_val as i64).to_string());
+                            modified = true;
+                            log::info!("Converted field '{}' to integer in flow {}", field_name, flow_id);
+                        } else {
+                            // Invalid value, remove it
+                            custom_fields.remove(field_name);
+                            modified = true;
+                            skipped_fields += 1;
+                            log::warn!("Invalid integer value '{}' for field '{}' in category '{}'", 
+                                value, field_name, new_category.name);
+                        }
+                    },
+                    FieldType::Float => {
+                        if let Ok(_) = value.parse::<f64>() {
+                            // Value is already valid
+                        } else if let Ok(int_val) = value.parse::<i64>() {
+                            // Convert integer to float
+                            custom_fields.insert(field_name.clone(), (int_val as f64).to_string());
+                            modified = true;
+                            log::info!("Converted field '{}' to float in flow {}", field_name, flow_id);
+                        } else {
+                            // Invalid value, remove it
+                            custom_fields.remove(field_name);
+                            modified = true;
+                            skipped_fields += 1;
+                            log::warn!("Invalid float value '{}' for field '{}' in category '{}'", 
+                                value, field_name, new_category.name);
+                        }
+                    },
+                    FieldType::Currency => {
+                        // Remove currency symbols and commas, then validate
+                        let clean_value = value.replace(['$', ','], "");
+                        if let Ok(_) = clean_value.parse::<f64>() {
+                            // Value is valid, update with cleaned version
+                            custom_fields.insert(field_name.clone(), clean_value);
+                            modified = true;
+                            log::info!("Cleaned currency field '{}' in flow {}", field_name, flow_id);
+                        } else {
+                            // Invalid value, remove it
+                            custom_fields.remove(field_name);
+                            modified = true;
+                            skipped_fields += 1;
+                            log::warn!("Invalid currency value '{}' for field '{}' in category '{}'", 
+                                value, field_name, new_category.name);
+                        }
+                    },
+                    FieldType::Boolean => {
+                        match value.to_lowercase().as_str() {
+                            "true" | "1" | "yes" | "y" => {
+                                custom_fields.insert(field_name.clone(), "true".to_string());
+                                modified = true;
+                            },
+                            "false" | "0" | "no" | "n" => {
+                                custom_fields.insert(field_name.clone(), "false".to_string());
+                                modified = true;
+                            },
+                            _ => {
+                                // Invalid value, remove it
+                                custom_fields.remove(field_name);
+                                modified = true;
+                                skipped_fields += 1;
+                                log::warn!("Invalid boolean value '{}' for field '{}' in category '{}'", 
+                                    value, field_name, new_category.name);
+                            }
+                        }
+                    },
+                    FieldType::Date => {
+                        // Try to parse the date in various formats
+                        if chrono::NaiveDate::parse_from_str(&value, "%Y-%m-%d").is_ok() {
+                            // Already in correct format
+                        } else if let Ok(date) = chrono::NaiveDate::parse_from_str(&value, "%m/%d/%Y") {
+                            // Convert to standard format
+                            custom_fields.insert(field_name.clone(), date.format("%Y-%m-%d").to_string());
+                            modified = true;
+                            log::info!("Converted date field '{}' to standard format in flow {}", field_name, flow_id);
+                        } else {
+                            // Invalid value, remove it
+                            custom_fields.remove(field_name);
+                            modified = true;
+                            skipped_fields += 1;
+                            log::warn!("Invalid date value '{}' for field '{}' in category '{}'", 
+                                value, field_name, new_category.name);
+                        }
+                    },
+                    FieldType::Select(options) => {
+                        let renamed = option_renames.get(field_name, &value)
+                            .or_else(|| options.iter().find(|option| option.eq_ignore_ascii_case(&value)).map(String::as_str));
+                        match renamed {
+                            Some(option) if option == value => {
+                                // Already one of the options
+                            }
+                            Some(option) => {
+                                custom_fields.insert(field_name.clone(), option.to_string());
+                                modified = true;
+                                log::info!("Renamed option of field '{}' in flow {}", field_name, flow_id);
+                            }
+                            None => {
+                                custom_fields.remove(field_name);
+                                modified = true;
+                                skipped_fields += 1;
+                                log::warn!("Value '{}' is no longer an option of field '{}' in category '{}'",
+                                    value, field_name, new_category.name);
+                            }
+                        }
+                    },
+                    _ => {
+                        // Text fields don't need validation
+                    }
+                }
+            }
+        }
+
+        // Update the flow if any changes were made
+        if modified {
+            let custom_fields_json = serde_json::to_string(&custom_fields)?;
+            conn.execute(
+                "UPDATE flows SET custom_fields = ? WHERE id = ?",
+                params![custom_fields_json, flow_id],
+            )?;
+            migrated_flows += 1;
+        }
+    }
+
+    log::info!("Flow migration summary for category '{}':", new_category.name);
+    log::info!("- Total flows processed: {}", total_flows);
+    log::info!("- Flows modified: {}", migrated_flows);
+    log::info!("- Fields skipped/removed: {}", skipped_fields);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, field_type: FieldType) -> CategoryField {
+        CategoryField { name: name.to_string(), field_type, required: false, default_value: None }
+    }
+
+    fn category(id: &str, fields: Vec<CategoryField>) -> Category {
+        Category {
+            id: id.to_string(),
+            name: format!("Category {}", id),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields,
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    // --- has_schema_changes ---
+
+    #[test]
+    fn has_schema_changes_false_for_identical_schemas() {
+        let fields = vec![field("amount", FieldType::Currency)];
+        let old = category("cat-1", fields.clone());
+        let new = category("cat-1", fields);
+        assert!(!has_schema_changes(&old, &new));
+    }
+
+    #[test]
+    fn has_schema_changes_false_for_two_empty_schemas() {
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![]);
+        assert!(!has_schema_changes(&old, &new));
+    }
+
+    #[test]
+    fn has_schema_changes_true_when_field_added() {
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("amount", FieldType::Currency)]);
+        assert!(has_schema_changes(&old, &new));
+    }
+
+    #[test]
+    fn has_schema_changes_true_when_field_removed() {
+        let old = category("cat-1", vec![field("amount", FieldType::Currency)]);
+        let new = category("cat-1", vec![]);
+        assert!(has_schema_changes(&old, &new));
+    }
+
+    #[test]
+    fn has_schema_changes_true_when_field_type_changed() {
+        let old = category("cat-1", vec![field("amount", FieldType::Integer)]);
+        let new = category("cat-1", vec![field("amount", FieldType::Float)]);
+        assert!(has_schema_changes(&old, &new));
+    }
+
+    // --- migrate_flows_to_new_category ---
+    //
+    // These use a minimal standalone `flows` table (just the columns the
+    // function touches) rather than going through `Database`, since
+    // `has_schema_changes`/`migrate_flows_to_new_category` are private to this
+    // module and only reachable from in-module tests.
+
+    fn conn_with_flows_table() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE flows (
+                id TEXT PRIMARY KEY,
+                category_id TEXT NOT NULL,
+                custom_fields TEXT NOT NULL
+            )",
+            [],
+        ).unwrap();
+        conn
+    }
+
+    fn insert_flow(conn: &Connection, id: &str, category_id: &str, custom_fields: &HashMap<String, String>) {
+        let json = serde_json::to_string(custom_fields).unwrap();
+        conn.execute(
+            "INSERT INTO flows (id, category_id, custom_fields) VALUES (?, ?, ?)",
+            params![id, category_id, json],
+        ).unwrap();
+    }
+
+    fn read_custom_fields(conn: &Connection, id: &str) -> HashMap<String, String> {
+        let json: String = conn.query_row(
+            "SELECT custom_fields FROM flows WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        ).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn migrate_skips_when_no_schema_changes() {
+        let conn = conn_with_flows_table();
+        let fields_schema = vec![field("amount", FieldType::Currency)];
+        let old = category("cat-1", fields_schema.clone());
+        let new = category("cat-1", fields_schema);
+
+        let mut fields = HashMap::new();
+        fields.insert("amount".to_string(), "$10.00".to_string()); // would be normalized if migration ran
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(
+            read_custom_fields(&conn, "flow-1").get("amount"),
+            Some(&"$10.00".to_string()),
+            "value should be untouched when no schema change occurred"
+        );
+    }
+
+    #[test]
+    fn migrate_removes_fields_no_longer_in_schema() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![field("amount", FieldType::Currency), field("notes", FieldType::Text)]);
+        let new = category("cat-1", vec![field("amount", FieldType::Currency)]);
+
+        let mut fields = HashMap::new();
+        fields.insert("amount".to_string(), "10.00".to_string());
+        fields.insert("notes".to_string(), "hello".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        let result = read_custom_fields(&conn, "flow-1");
+        assert!(!result.contains_key("notes"));
+        assert_eq!(result.get("amount"), Some(&"10.00".to_string()));
+    }
+
+    #[test]
+    fn migrate_integer_field_valid_value_unchanged() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("count", FieldType::Integer)]);
+        let mut fields = HashMap::new();
+        fields.insert("count".to_string(), "5".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("count"), Some(&"5".to_string()));
+    }
+
+    #[test]
+    fn migrate_integer_field_converts_float_string() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("count", FieldType::Integer)]);
+        let mut fields = HashMap::new();
+        fields.insert("count".to_string(), "5.9".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(
+            read_custom_fields(&conn, "flow-1").get("count"),
+            Some(&"5".to_string()),
+            "float-looking strings truncate toward zero via `as i64`"
+        );
+    }
+
+    #[test]
+    fn migrate_integer_field_removes_invalid_value() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("count", FieldType::Integer)]);
+        let mut fields = HashMap::new();
+        fields.insert("count".to_string(), "not-a-number".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert!(!read_custom_fields(&conn, "flow-1").contains_key("count"));
+    }
+
+    #[test]
+    fn migrate_float_field_converts_integer_string() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("ratio", FieldType::Float)]);
+        let mut fields = HashMap::new();
+        fields.insert("ratio".to_string(), "3".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("ratio"), Some(&"3".to_string()));
+    }
+
+    #[test]
+    fn migrate_float_field_removes_invalid_value() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("ratio", FieldType::Float)]);
+        let mut fields = HashMap::new();
+        fields.insert("ratio".to_string(), "not-a-number".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert!(!read_custom_fields(&conn, "flow-1").contains_key("ratio"));
+    }
+
+    #[test]
+    fn migrate_currency_field_strips_symbols_and_commas() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("cost", FieldType::Currency)]);
+        let mut fields = HashMap::new();
+        fields.insert("cost".to_string(), "$1,234.56".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("cost"), Some(&"1234.56".to_string()));
+    }
+
+    #[test]
+    fn migrate_currency_field_removes_invalid_value() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("cost", FieldType::Currency)]);
+        let mut fields = HashMap::new();
+        fields.insert("cost".to_string(), "free".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert!(!read_custom_fields(&conn, "flow-1").contains_key("cost"));
+    }
+
+    #[test]
+    fn migrate_boolean_field_normalizes_truthy_and_falsy_values() {
+        for (input, expected) in [("YES", "true"), ("1", "true"), ("n", "false"), ("False", "false")] {
+            let conn = conn_with_flows_table();
+            let old = category("cat-1", vec![]);
+            let new = category("cat-1", vec![field("covered", FieldType::Boolean)]);
+
+            let mut fields = HashMap::new();
+            fields.insert("covered".to_string(), input.to_string());
+            insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+            migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+            assert_eq!(
+                read_custom_fields(&conn, "flow-1").get("covered"),
+                Some(&expected.to_string()),
+                "input {:?} should normalize to {:?}", input, expected
+            );
+        }
+    }
+
+    #[test]
+    fn migrate_boolean_field_removes_invalid_value() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("covered", FieldType::Boolean)]);
+        let mut fields = HashMap::new();
+        fields.insert("covered".to_string(), "maybe".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert!(!read_custom_fields(&conn, "flow-1").contains_key("covered"));
+    }
+
+    #[test]
+    fn migrate_date_field_reformats_us_style_date() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("when", FieldType::Date)]);
+        let mut fields = HashMap::new();
+        fields.insert("when".to_string(), "03/14/2024".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("when"), Some(&"2024-03-14".to_string()));
+    }
+
+    #[test]
+    fn migrate_date_field_leaves_iso_format_unchanged() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("when", FieldType::Date)]);
+        let mut fields = HashMap::new();
+        fields.insert("when".to_string(), "2024-03-14".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("when"), Some(&"2024-03-14".to_string()));
+    }
+
+    #[test]
+    fn migrate_date_field_removes_unparseable_value() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("when", FieldType::Date)]);
+        let mut fields = HashMap::new();
+        fields.insert("when".to_string(), "not-a-date".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        assert!(!read_custom_fields(&conn, "flow-1").contains_key("when"));
+    }
+
+    #[test]
+    fn migrate_leaves_empty_values_untouched_and_unvalidated() {
+        let conn = conn_with_flows_table();
+        let old = category("cat-1", vec![]);
+        let new = category("cat-1", vec![field("count", FieldType::Integer)]);
+        let mut fields = HashMap::new();
+        fields.insert("count".to_string(), "   ".to_string());
+        insert_flow(&conn, "flow-1", "cat-1", &fields);
+
+        migrate_flows_to_new_category(&conn, &old, &new, &OptionRenames::default()).unwrap();
+
+        // Empty/whitespace-only values are skipped entirely: neither validated nor stripped.
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("count"), Some(&"   ".to_string()));
+    }
+
+    #[test]
+    fn migrate_select_field_follows_renamed_options_and_drops_removed_ones() {
+        let conn = conn_with_flows_table();
+        let options = |names: &[&str]| FieldType::Select(names.iter().map(|n| n.to_string()).collect());
+        let old = category("cat-1", vec![field("kind", options(&["Solo", "Couples", "Group"]))]);
+        let new = category("cat-1", vec![field("kind", options(&["Individual", "Couples"]))]);
+        for (id, kind) in [("flow-1", "Solo"), ("flow-2", "couples"), ("flow-3", "Group")] {
+            insert_flow(&conn, id, "cat-1", &HashMap::from([("kind".to_string(), kind.to_string())]));
+        }
+        let mut renames = OptionRenames::default();
+        renames.rename("kind", "Solo", "Individual");
+
+        migrate_flows_to_new_category(&conn, &old, &new, &renames).unwrap();
+
+        assert_eq!(read_custom_fields(&conn, "flow-1").get("kind"), Some(&"Individual".to_string()));
+        assert_eq!(read_custom_fields(&conn, "flow-2").get("kind"), Some(&"Couples".to_string()));
+        assert!(!read_custom_fields(&conn, "flow-3").contains_key("kind"));
+    }
+
+    // --- run_migrations ---
+
+    fn conn_with_categories_table() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE categories (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                flow_type TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                tax_deduction_allowed INTEGER NOT NULL,
+                tax_deduction_default INTEGER NOT NULL
+            )",
+            [],
+        ).unwrap();
+        conn
+    }
+
+    #[test]
+    fn run_migrations_converts_legacy_number_fields_and_is_idempotent() {
+        let mut conn = conn_with_categories_table();
+
+        #[allow(deprecated)]
+        let legacy_fields = vec![CategoryField {
+            name: "legacy_amount".to_string(),
+            field_type: FieldType::Number,
+            required: false,
+            default_value: None,
+        }];
+        let fields_json = serde_json::to_string(&legacy_fields).unwrap();
+        conn.execute(
+            "INSERT INTO categories (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params!["cat-1", "Legacy", "Expense", fields_json, 0, 0],
+        ).unwrap();
+
+        let summary = run_migrations(&mut conn).expect("first run should succeed");
+        assert_eq!(summary.converted_fields, vec![("Legacy".to_string(), "legacy_amount".to_string())]);
+
+        let stored_json: String = conn.query_row(
+            "SELECT fields FROM categories WHERE id = 'cat-1'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        let stored_fields: Vec<CategoryField> = serde_json::from_str(&stored_json).unwrap();
+        assert_eq!(stored_fields[0].field_type, FieldType::Float, "Number fields should be converted to Float");
+
+        let applied_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM migrations WHERE name = 'convert_number_to_float'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(applied_count, 1);
+
+        // Running again should be a no-op: no error, no duplicate migration record.
+        let rerun_summary = run_migrations(&mut conn).expect("second run should also succeed");
+        assert!(rerun_summary.is_empty());
+
+        let applied_count_after_rerun: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM migrations WHERE name = 'convert_number_to_float'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(applied_count_after_rerun, 1, "migration should not be reapplied");
+    }
+
+    #[test]
+    fn run_migrations_adds_refund_of_to_a_legacy_flows_table() {
+        let mut conn = conn_with_categories_table();
+        conn.execute(
+            "CREATE TABLE flows (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                amount REAL NOT NULL,
+                category_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                linked_flows TEXT NOT NULL,
+                custom_fields TEXT NOT NULL,
+                tax_deductible INTEGER
+            )",
+            [],
+        ).unwrap();
+
+        run_migrations(&mut conn).expect("first run should succeed");
+        run_migrations(&mut conn).expect("second run should be a no-op");
+
+        let has_column: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('flows') WHERE name = 'refund_of'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert!(has_column);
+    }
+
+    #[test]
+    fn run_migrations_summarizes_added_columns_and_offers_missing_defaults() {
+        let mut conn = conn_with_categories_table();
+        conn.execute(
+            "CREATE TABLE flows (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                amount REAL NOT NULL,
+                category_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                linked_flows TEXT NOT NULL,
+                custom_fields TEXT NOT NULL,
+                tax_deductible INTEGER
+            )",
+            [],
+        ).unwrap();
+        let kept = get_default_categories().remove(0);
+        conn.execute(
+            "INSERT INTO categories (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default)
+             VALUES (?1, ?2, ?3, '[]', 0, 0)",
+            params![kept.id, kept.name, kept.flow_type.to_string()],
+        ).unwrap();
+
+        let summary = run_migrations(&mut conn).unwrap();
+        assert_eq!(summary.schema_changes.len(), 11, "one line per added column");
+        assert!(summary.converted_fields.is_empty());
+        assert_eq!(summary.offered_categories.len(), get_default_categories().len() - 1);
+        assert!(summary.offered_categories.iter().all(|c| c.id != kept.id));
+
+        assert_eq!(run_migrations(&mut conn).unwrap(), MigrationSummary::default());
+    }
+
+    #[test]
+    fn run_migrations_backs_up_a_file_database_before_changing_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("preft.db");
+        let mut conn = Connection::open(&db_path).unwrap();
+        conn.execute(
+            "CREATE TABLE categories (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                flow_type TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                tax_deduction_allowed INTEGER NOT NULL,
+                tax_deduction_default INTEGER NOT NULL
+            )",
+            [],
+        ).unwrap();
+
+        let summary = run_migrations(&mut conn).unwrap();
+        let backup_path = summary.backup_path.expect("adding a column should take a backup first");
+        assert!(backup_path.starts_with(dir.path().join(PRE_MIGRATION_BACKUP_DIR)));
+
+        // The copy is the database as it was: still without the new column.
+        let backup = Connection::open(&backup_path).unwrap();
+        let backup_has_column: bool = backup.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('categories') WHERE name = 'tax_jurisdictions'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert!(!backup_has_column);
+
+        let recorded: String = conn.query_row(
+            "SELECT backup_path FROM migrations WHERE name = 'add_category_tax_jurisdictions'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(recorded, backup_path.to_string_lossy());
+
+        assert_eq!(run_migrations(&mut conn).unwrap().backup_path, None, "nothing left to migrate, so no new backup");
+    }
+
+    #[test]
+    fn run_migrations_takes_no_backup_for_a_fresh_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = Connection::open(dir.path().join("preft.db")).unwrap();
+        conn.execute(
+            "CREATE TABLE categories (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                flow_type TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                tax_deduction_allowed INTEGER NOT NULL,
+                tax_deduction_default INTEGER NOT NULL,
+                tax_jurisdictions TEXT NOT NULL DEFAULT '[]',
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                color INTEGER,
+                icon TEXT
+            )",
+            [],
+        ).unwrap();
+
+        assert_eq!(run_migrations(&mut conn).unwrap().backup_path, None);
+        assert!(!dir.path().join(PRE_MIGRATION_BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn migrations_are_listed_in_version_order() {
+        assert!(MIGRATIONS.windows(2).all(|pair| pair[0].version < pair[1].version));
+        let mut conn = conn_with_categories_table();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        run_migrations(&mut conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn run_migrations_refuses_a_database_from_a_newer_version() {
+        let mut conn = conn_with_categories_table();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        let err = run_migrations(&mut conn).unwrap_err();
+        assert!(err.to_string().contains("update Preft"), "{}", err);
+    }
+
+    #[test]
+    fn run_migrations_names_the_migration_that_failed() {
+        let mut conn = conn_with_categories_table();
+        conn.execute(
+            "INSERT INTO categories (id, name, flow_type, fields, tax_deduction_allowed, tax_deduction_default)
+             VALUES ('cat-1', 'Broken', 'Expense', 'not json', 0, 0)",
+            [],
+        ).unwrap();
+
+        let err = run_migrations(&mut conn).unwrap_err();
+        assert!(err.to_string().starts_with("Migration convert_number_to_float (version 1) failed"), "{}", err);
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        let recorded: i64 = conn.query_row("SELECT COUNT(*) FROM migrations", [], |row| row.get(0)).unwrap();
+        assert_eq!(recorded, 0);
+    }
+
+    #[test]
+    fn roll_back_migrations_drops_newer_columns_until_migrated_again() {
+        let mut conn = conn_with_categories_table();
+        conn.execute(
+            "CREATE TABLE flows (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                amount REAL NOT NULL,
+                category_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                linked_flows TEXT NOT NULL,
+                custom_fields TEXT NOT NULL,
+                tax_deductible INTEGER
+            )",
+            [],
+        ).unwrap();
+        run_migrations(&mut conn).unwrap();
+
+        let summary = roll_back_migrations(&mut conn, 9).unwrap();
+        assert_eq!(summary.undone, ["add_category_icon", "add_category_color", "add_category_sort_order"]);
+        assert_eq!(schema_version(&conn).unwrap(), 9);
+        assert!(column_missing(&conn, "categories", "sort_order").unwrap());
+        assert!(!column_missing(&conn, "flows", "reimbursed_by").unwrap());
+        assert_eq!(roll_back_migrations(&mut conn, 9).unwrap(), RollbackSummary::default());
+
+        let summary = run_migrations(&mut conn).unwrap();
+        assert_eq!(summary.schema_changes.len(), 3);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn roll_back_migrations_refuses_a_step_that_cannot_be_undone() {
+        let mut conn = conn_with_categories_table();
+        run_migrations(&mut conn).unwrap();
+
+        let err = roll_back_migrations(&mut conn, 0).unwrap_err();
+        assert!(err.to_string().contains("convert_number_to_float"), "{}", err);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION, "nothing was undone");
+        assert!(!column_missing(&conn, "categories", "icon").unwrap());
+    }
+}
@@ -0,0 +1,245 @@
+//! Runs a [`Database`] on its own thread, so a frontend's UI thread never
+//! waits on SQLite for writes it doesn't need the result of. Work goes in
+//! as closures over the database and runs in the order it was sent, so a
+//! read always sees the writes queued before it.
+//!
+//! - [`DbWorker::run`] queues a write and returns at once; a failure comes
+//!   back as a [`DbEvent`] from [`DbWorker::take_events`].
+//! - [`DbWorker::query`] queues a read and hands back a [`Pending`] result
+//!   to check on each frame.
+//! - [`DbWorker::call`] waits for its result, for the few places that
+//!   can't go on without it, such as shutting down.
+//!
+//! The `wake` callback given to [`DbWorker::start`] runs whenever a result
+//! or event is ready, e.g. to ask the UI to repaint.
+
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use anyhow::Result;
+
+use crate::db::Database;
+
+type Job = Box<dyn FnOnce(&mut Database) + Send>;
+
+/// Something the worker reports without being asked.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbEvent {
+    /// A write queued with `run` failed. `action` is what it was doing,
+    /// e.g. "save the settings".
+    Failed { action: String, error: String },
+}
+
+/// A job sent to the worker never answered: its thread has stopped, or the
+/// job itself panicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerGone;
+
+impl std::fmt::Display for WorkerGone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The database worker stopped before answering")
+    }
+}
+
+impl std::error::Error for WorkerGone {}
+
+/// The result of a `query`, once the worker gets to it.
+pub struct Pending<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Pending<T> {
+    /// The result if it's ready, `None` while the query is still queued or
+    /// running. Only returns it once.
+    pub fn try_take(&self) -> Option<Result<T>> {
+        match self.receiver.try_recv() {
+            Ok(value) => Some(Ok(value)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(WorkerGone.into())),
+        }
+    }
+}
+
+pub struct DbWorker {
+    jobs: Option<Sender<Job>>,
+    events: Receiver<DbEvent>,
+    event_sender: Sender<DbEvent>,
+    /// Jobs sent but not yet finished, for `is_busy`.
+    queued: Arc<AtomicUsize>,
+    /// The database's `change_count` after the last finished job.
+    changes: Arc<AtomicU64>,
+    wake: Arc<dyn Fn() + Send + Sync>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DbWorker {
+    /// Moves `db` onto a new thread that runs the work sent to it.
+    pub fn start(db: Database, wake: impl Fn() + Send + Sync + 'static) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (event_sender, events) = mpsc::channel();
+        let changes = Arc::new(AtomicU64::new(db.change_count()));
+        let thread = std::thread::Builder::new()
+            .name("preft-db".to_string())
+            .spawn(move || {
+                let mut db = db;
+                for job in receiver {
+                    job(&mut db);
+                }
+            })
+            .expect("failed to start the database thread");
+        Self {
+            jobs: Some(jobs),
+            events,
+            event_sender,
+            queued: Arc::new(AtomicUsize::new(0)),
+            changes,
+            wake: Arc::new(wake),
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, job: impl FnOnce(&mut Database) + Send + 'static) {
+        let queued = Arc::clone(&self.queued);
+        let changes = Arc::clone(&self.changes);
+        let wake = Arc::clone(&self.wake);
+        queued.fetch_add(1, Ordering::SeqCst);
+        let job: Job = Box::new(move |db| {
+            // One failed job mustn't take the rest of the session's writes
+            // down with it; its caller sees the result dropped.
+            if std::panic::catch_unwind(AssertUnwindSafe(|| job(db))).is_err() {
+                log::error!("A database job panicked");
+            }
+            // Before the job counts as finished, so an idle worker's count
+            // is final
+            changes.store(db.change_count(), Ordering::SeqCst);
+            queued.fetch_sub(1, Ordering::SeqCst);
+            wake();
+        });
+        if let Some(jobs) = &self.jobs
+            && jobs.send(job).is_err()
+        {
+            log::error!("The database thread has stopped; a job was dropped");
+        }
+    }
+
+    /// Queues a write without waiting for it. If it fails, a
+    /// `DbEvent::Failed` naming `action` follows.
+    pub fn run(&self, action: impl Into<String>, job: impl FnOnce(&mut Database) -> Result<()> + Send + 'static) {
+        let action = action.into();
+        let events = self.event_sender.clone();
+        self.send(move |db| {
+            if let Err(e) = job(db) {
+                let _ = events.send(DbEvent::Failed { action, error: e.to_string() });
+            }
+        });
+    }
+
+    /// Queues a read whose result is picked up later from the `Pending`.
+    pub fn query<T: Send + 'static>(&self, job: impl FnOnce(&mut Database) -> T + Send + 'static) -> Pending<T> {
+        let (sender, receiver) = mpsc::channel();
+        self.send(move |db| {
+            let _ = sender.send(job(db));
+        });
+        Pending { receiver }
+    }
+
+    /// Runs `job` after everything already queued and waits for its result.
+    /// Blocks the caller for as long as the queue takes, so a frontend's UI
+    /// thread should `query` instead.
+    pub fn call<T: Send + 'static>(&self, job: impl FnOnce(&mut Database) -> T + Send + 'static) -> Result<T, WorkerGone> {
+        self.query(job).receiver.recv().map_err(|_| WorkerGone)
+    }
+
+    /// Events reported since the last call, oldest first.
+    pub fn take_events(&self) -> Vec<DbEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// Whether any work is queued or running.
+    pub fn is_busy(&self) -> bool {
+        self.queued.load(Ordering::SeqCst) > 0
+    }
+
+    /// The database's `change_count` as of the last finished job, read
+    /// without waiting on the queue. Only final while the worker isn't
+    /// busy: a write still queued hasn't counted yet.
+    pub fn change_count(&self) -> u64 {
+        self.changes.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for DbWorker {
+    /// Finishes whatever is still queued, so writes sent just before exit
+    /// aren't lost.
+    fn drop(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::UserSettings;
+    use rusqlite::Connection;
+
+    fn worker() -> DbWorker {
+        let db = Database::new_for_test(Connection::open_in_memory().unwrap()).unwrap();
+        DbWorker::start(db, || {})
+    }
+
+    #[test]
+    fn work_runs_in_the_order_it_was_sent() {
+        let worker = worker();
+        let mut settings = UserSettings::new();
+        settings.set_year_filter(Some(2021));
+        worker.run("save the settings", move |db| db.save_user_settings(&settings));
+
+        let pending = worker.query(|db| db.load_user_settings().unwrap().get_year_filter());
+        assert_eq!(worker.call(|db| db.load_user_settings().unwrap().get_year_filter()), Ok(Some(2021)));
+        assert_eq!(pending.try_take().unwrap().unwrap(), Some(2021), "answered before the later call");
+        assert!(pending.try_take().unwrap().is_err(), "only answered once");
+        assert!(worker.take_events().is_empty());
+    }
+
+    #[test]
+    fn failed_writes_come_back_as_events() {
+        let worker = worker();
+        worker.run("delete the flows", |_| Err(anyhow::anyhow!("refused")));
+        worker.call(|_| ()).unwrap();
+        assert_eq!(
+            worker.take_events(),
+            vec![DbEvent::Failed { action: "delete the flows".to_string(), error: "refused".to_string() }]
+        );
+    }
+
+    #[test]
+    fn a_panicking_job_leaves_the_worker_running() {
+        let worker = worker();
+        let pending = worker.query(|_| -> i32 { panic!("bad job") });
+        assert_eq!(worker.call(|_| 7), Ok(7));
+        assert!(pending.try_take().unwrap().is_err());
+    }
+
+    #[test]
+    fn the_change_count_is_published_once_a_write_finishes() {
+        let worker = worker();
+        let before = worker.change_count();
+        worker.run("reorder the categories", |db| db.save_category_order(&[]));
+        worker.call(|_| ()).unwrap();
+        assert_eq!(worker.change_count(), worker.call(|db| db.change_count()).unwrap());
+        assert!(worker.change_count() > before);
+    }
+
+    #[test]
+    fn a_call_whose_job_panics_comes_back_as_an_error() {
+        let worker = worker();
+        assert_eq!(worker.call(|_| -> i32 { panic!("bad job") }), Err(WorkerGone));
+        assert_eq!(worker.call(|_| 7), Ok(7));
+    }
+}
@@ -0,0 +1,371 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use printpdf::*;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::locale::NumberFormat;
+use crate::models::{Category, Flow, FlowType};
+use crate::reporting::{max_chars_for_width, save_pdf, wrap_text};
+use crate::settings::UserSettings;
+
+/// A payment has to show up in at least this many different calendar
+/// months of the past year to be listed as a recurring obligation.
+pub const RECURRING_MIN_MONTHS: usize = 3;
+
+/// A payment or income that keeps coming back: the same description in the
+/// same category, seen in several months of the past year.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecurringObligation {
+    pub category_id: String,
+    pub category: String,
+    pub flow_type: FlowType,
+    /// As written on the most recent occurrence.
+    pub description: String,
+    /// The median amount, so one unusual month doesn't skew it.
+    pub typical_amount: f64,
+    pub months_seen: usize,
+    pub last_date: NaiveDate,
+}
+
+/// A scheduled flow that hasn't happened yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpcomingFlow {
+    pub date: NaiveDate,
+    pub category: String,
+    pub description: String,
+    pub amount: f64,
+}
+
+/// Where a copy of the data can be found, e.g. ("Automatic backups", dir).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupLocation {
+    pub label: String,
+    pub path: String,
+}
+
+/// Everything the "in case of emergency" document says, assembled from
+/// flows and settings. There are no accounts in preft, so categories (what
+/// money comes from and goes to) stand in for them. Nothing secret is
+/// included: no passwords or keys, and no custom field values, which may
+/// be encrypted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmergencyDocument {
+    pub generated_on: NaiveDate,
+    /// Categories with at least one flow, and the date of the latest.
+    pub categories: Vec<(String, FlowType, NaiveDate)>,
+    pub recurring: Vec<RecurringObligation>,
+    pub upcoming: Vec<UpcomingFlow>,
+    pub backup_locations: Vec<BackupLocation>,
+    /// Whether opening the data needs a password (which is not included).
+    pub password_protected: bool,
+}
+
+impl EmergencyDocument {
+    pub fn build(
+        flows: &[Flow],
+        categories: &[Category],
+        settings: &UserSettings,
+        database_path: Option<String>,
+        password_protected: bool,
+        today: NaiveDate,
+    ) -> Self {
+        let by_id: HashMap<&str, &Category> = categories.iter().map(|c| (c.id.as_str(), c)).collect();
+
+        let mut latest: HashMap<&str, NaiveDate> = HashMap::new();
+        for flow in flows.iter().filter(|f| !f.scheduled) {
+            let date = latest.entry(flow.category_id.as_str()).or_insert(flow.date);
+            *date = (*date).max(flow.date);
+        }
+        let mut category_rows: Vec<(String, FlowType, NaiveDate)> = categories.iter()
+            .filter_map(|c| latest.get(c.id.as_str()).map(|date| (c.name.clone(), c.flow_type.clone(), *date)))
+            .collect();
+        category_rows.sort_by_key(|row| row.0.to_lowercase());
+
+        let mut upcoming: Vec<UpcomingFlow> = flows.iter()
+            .filter(|f| f.scheduled)
+            .filter_map(|f| by_id.get(f.category_id.as_str()).map(|c| UpcomingFlow {
+                date: f.date,
+                category: c.name.clone(),
+                description: f.description.clone(),
+                amount: f.amount,
+            }))
+            .collect();
+        upcoming.sort_by_key(|u| u.date);
+
+        Self {
+            generated_on: today,
+            categories: category_rows,
+            recurring: detect_recurring(flows, categories, today),
+            upcoming,
+            backup_locations: backup_locations(settings, database_path),
+            password_protected,
+        }
+    }
+
+    /// Renders the document as an A4 PDF meant to be printed and filed.
+    pub fn to_pdf(&self, format: &NumberFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let (doc, page, layer) = PdfDocument::new("In Case of Emergency", Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer 1");
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let mut writer = DocumentWriter { doc: &doc, layer: doc.get_page(page).get_layer(layer), y: TOP_MM };
+
+        writer.line("In Case of Emergency", 22.0, &bold, 0.0);
+        writer.line(&format!("Financial records summary, prepared {}", self.generated_on.format("%B %-d, %Y")), 11.0, &regular, 0.0);
+        writer.gap();
+        writer.paragraph(
+            "This document lists where the financial records kept in preft are stored, which payments recur, and \
+             what is scheduled. It deliberately contains no passwords or account numbers.",
+            10.0, &regular, 0.0,
+        );
+        if self.password_protected {
+            writer.paragraph(
+                "The records are password protected. The password is not in this document and must be passed on separately.",
+                10.0, &bold, 0.0,
+            );
+        }
+
+        writer.heading("Where the Records Are", &bold);
+        if self.backup_locations.is_empty() {
+            writer.line("No locations are recorded.", 10.0, &regular, 4.0);
+        }
+        for location in &self.backup_locations {
+            writer.line(&location.label, 10.0, &bold, 4.0);
+            writer.paragraph(&location.path, 10.0, &regular, 8.0);
+        }
+
+        writer.heading("Recurring Payments and Income", &bold);
+        if self.recurring.is_empty() {
+            writer.line("None found in the past year.", 10.0, &regular, 4.0);
+        }
+        for item in &self.recurring {
+            writer.paragraph(
+                &format!(
+                    "{} ({}, {}): about {}, seen in {} of the last 12 months, most recently {}",
+                    item.description,
+                    item.category,
+                    item.flow_type,
+                    format.format_currency(item.typical_amount),
+                    item.months_seen,
+                    item.last_date.format("%Y-%m-%d"),
+                ),
+                10.0, &regular, 4.0,
+            );
+        }
+
+        writer.heading("Scheduled", &bold);
+        if self.upcoming.is_empty() {
+            writer.line("Nothing is scheduled.", 10.0, &regular, 4.0);
+        }
+        for item in &self.upcoming {
+            writer.paragraph(
+                &format!("{}  {}: {} ({})", item.date.format("%Y-%m-%d"), item.category, item.description, format.format_currency(item.amount)),
+                10.0, &regular, 4.0,
+            );
+        }
+
+        writer.heading("Categories", &bold);
+        if self.categories.is_empty() {
+            writer.line("No flows have been recorded.", 10.0, &regular, 4.0);
+        }
+        for (name, flow_type, last_date) in &self.categories {
+            writer.line(&format!("{} ({}), last entry {}", name, flow_type, last_date.format("%Y-%m-%d")), 10.0, &regular, 4.0);
+        }
+
+        save_pdf(doc)
+    }
+}
+
+/// Groups the past year's flows (ending `today`) by category and
+/// description, and keeps the groups seen in at least
+/// `RECURRING_MIN_MONTHS` calendar months. Flows without a description
+/// can't be told apart, so they're never grouped.
+pub fn detect_recurring(flows: &[Flow], categories: &[Category], today: NaiveDate) -> Vec<RecurringObligation> {
+    let since = today - Duration::days(365);
+    let mut groups: HashMap<(&str, String), Vec<&Flow>> = HashMap::new();
+    for flow in flows {
+        let description = flow.description.trim().to_lowercase();
+        if flow.scheduled || flow.is_refund() || description.is_empty() || flow.date <= since || flow.date > today {
+            continue;
+        }
+        groups.entry((flow.category_id.as_str(), description)).or_default().push(flow);
+    }
+
+    let mut recurring: Vec<RecurringObligation> = groups.into_values()
+        .filter_map(|group| {
+            let months: BTreeSet<(i32, u32)> = group.iter().map(|f| (f.date.year(), f.date.month())).collect();
+            if months.len() < RECURRING_MIN_MONTHS {
+                return None;
+            }
+            let category = categories.iter().find(|c| c.id == group[0].category_id)?;
+            let latest = group.iter().max_by_key(|f| f.date)?;
+            let mut amounts: Vec<f64> = group.iter().map(|f| f.amount).collect();
+            amounts.sort_by(|a, b| a.total_cmp(b));
+            Some(RecurringObligation {
+                category_id: category.id.clone(),
+                category: category.name.clone(),
+                flow_type: category.flow_type.clone(),
+                description: latest.description.trim().to_string(),
+                typical_amount: amounts[amounts.len() / 2],
+                months_seen: months.len(),
+                last_date: latest.date,
+            })
+        })
+        .collect();
+    recurring.sort_by(|a, b| (&a.category, &a.description).cmp(&(&b.category, &b.description)));
+    recurring
+}
+
+/// The live database, the automatic backups folder, and the most recent
+/// backup. A path is only listed once, since the last backup is usually
+/// recorded both in the history and as `last_backup_path`.
+fn backup_locations(settings: &UserSettings, database_path: Option<String>) -> Vec<BackupLocation> {
+    let mut locations = Vec::new();
+    let mut add = |label: &str, path: Option<String>| {
+        if let Some(path) = path
+            && !locations.iter().any(|l: &BackupLocation| l.path == path)
+        {
+            locations.push(BackupLocation { label: label.to_string(), path });
+        }
+    };
+    add("Database (on this computer)", database_path);
+    if settings.is_auto_backup_enabled() {
+        add("Automatic backups folder", settings.get_auto_backup_directory().cloned());
+    }
+    if let Some(entry) = settings.get_last_successful_backup() {
+        add(&format!("Most recent backup ({})", entry.timestamp.format("%Y-%m-%d")), Some(entry.file_path.clone()));
+    }
+    add("Last backup saved", settings.last_backup_path.clone());
+    locations
+}
+
+const PAGE_WIDTH_MM: f64 = 210.0;
+const PAGE_HEIGHT_MM: f64 = 297.0;
+const TOP_MM: f64 = 275.0;
+const LEFT_MM: f64 = 20.0;
+const BOTTOM_MM: f64 = 20.0;
+
+/// Writes lines top to bottom, starting a new page when one is full.
+struct DocumentWriter<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    y: f64,
+}
+
+impl DocumentWriter<'_> {
+    fn line(&mut self, text: &str, size: f64, font: &IndirectFontRef, indent_mm: f64) {
+        let height = size * 0.3528 * 1.5;
+        if self.y - height < BOTTOM_MM {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = TOP_MM;
+        }
+        self.layer.use_text(text, size, Mm(LEFT_MM + indent_mm), Mm(self.y), font);
+        self.y -= height;
+    }
+
+    fn paragraph(&mut self, text: &str, size: f64, font: &IndirectFontRef, indent_mm: f64) {
+        let width = PAGE_WIDTH_MM - 2.0 * LEFT_MM - indent_mm;
+        for line in wrap_text(text, max_chars_for_width(width, size)) {
+            self.line(&line, size, font, indent_mm);
+        }
+    }
+
+    fn heading(&mut self, text: &str, font: &IndirectFontRef) {
+        self.gap();
+        self.line(text, 14.0, font, 0.0);
+    }
+
+    fn gap(&mut self) {
+        self.y -= 4.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::get_default_categories;
+    use crate::settings::BackupEntry;
+
+    fn flow(category_id: &str, date: NaiveDate, description: &str, amount: f64) -> Flow {
+        Flow {
+            id: uuid::Uuid::new_v4().to_string(),
+            date,
+            amount,
+            category_id: category_id.to_string(),
+            description: description.to_string(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn detect_recurring_needs_the_same_description_in_several_months() {
+        let categories = get_default_categories();
+        let category_id = categories[0].id.clone();
+        let flows = vec![
+            flow(&category_id, date(2024, 1, 3), "Netflix", 15.0),
+            flow(&category_id, date(2024, 2, 3), "NETFLIX ", 15.0),
+            flow(&category_id, date(2024, 3, 3), "netflix", 18.0),
+            flow(&category_id, date(2024, 3, 4), "netflix", 15.0),
+            // Twice in one month isn't recurring.
+            flow(&category_id, date(2024, 2, 1), "Hardware store", 40.0),
+            flow(&category_id, date(2024, 2, 20), "Hardware store", 60.0),
+            flow(&category_id, date(2024, 3, 1), "Hardware store", 10.0),
+            // Outside the past year.
+            flow(&category_id, date(2022, 1, 1), "Gym", 30.0),
+            flow(&category_id, date(2022, 2, 1), "Gym", 30.0),
+            flow(&category_id, date(2022, 3, 1), "Gym", 30.0),
+        ];
+
+        let recurring = detect_recurring(&flows, &categories, date(2024, 3, 31));
+
+        assert_eq!(recurring.len(), 1);
+        assert_eq!(recurring[0].description, "netflix");
+        assert_eq!(recurring[0].months_seen, 3);
+        assert_eq!(recurring[0].typical_amount, 15.0);
+        assert_eq!(recurring[0].last_date, date(2024, 3, 4));
+    }
+
+    #[test]
+    fn build_lists_backup_locations_once_and_scheduled_flows_in_date_order() {
+        let categories = get_default_categories();
+        let category_id = categories[0].id.clone();
+        let mut later = flow(&category_id, date(2024, 6, 1), "Property tax", 900.0);
+        later.scheduled = true;
+        let mut sooner = flow(&category_id, date(2024, 5, 1), "Insurance", 300.0);
+        sooner.scheduled = true;
+        let flows = vec![later, sooner, flow(&category_id, date(2024, 4, 1), "Paid", 1.0)];
+
+        let mut settings = UserSettings::new();
+        settings.set_auto_backup_enabled(true);
+        settings.set_auto_backup_directory(Some("/backups".to_string()));
+        settings.set_last_backup_path("/backups/preft_1.db".to_string());
+        settings.add_backup_entry(BackupEntry {
+            timestamp: chrono::Utc::now(),
+            file_path: "/backups/preft_1.db".to_string(),
+            file_size: None,
+            success: true,
+            error_message: None,
+        });
+
+        let doc = EmergencyDocument::build(&flows, &categories, &settings, Some("/home/me/.preft/preft.db".to_string()), true, date(2024, 4, 15));
+
+        let paths: Vec<&str> = doc.backup_locations.iter().map(|l| l.path.as_str()).collect();
+        assert_eq!(paths, vec!["/home/me/.preft/preft.db", "/backups", "/backups/preft_1.db"]);
+        let upcoming: Vec<&str> = doc.upcoming.iter().map(|u| u.description.as_str()).collect();
+        assert_eq!(upcoming, vec!["Insurance", "Property tax"]);
+        assert_eq!(doc.categories, vec![(categories[0].name.clone(), categories[0].flow_type.clone(), date(2024, 4, 1))]);
+        assert!(doc.to_pdf(&NumberFormat::default()).unwrap().starts_with(b"%PDF"));
+    }
+}
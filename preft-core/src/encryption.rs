@@ -0,0 +1,289 @@
+use anyhow::Result;
+use aes_gcm::{Aes256Gcm, Key, Nonce, KeyInit};
+use aes_gcm::aead::Aead;
+use base64::{Engine as _, engine::general_purpose};
+use rand::Rng;
+use sha2::{Sha256, Digest};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use zeroize::Zeroizing;
+
+/// Marks a value `DatabaseEncryption::seal` encrypted, so encrypted and
+/// plain values can sit side by side in one column.
+const SEALED_PREFIX: &str = "enc1:";
+
+/// Mixed into the key before hashing it for the keystore, so the hash is
+/// never a value the key is used for elsewhere.
+const PASSWORD_CHECK_CONTEXT: &[u8] = b"preft password check";
+
+/// Whether `value` was stored by `DatabaseEncryption::seal`.
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+/// An empty string to type a password into, wiped when dropped or
+/// zeroized. It starts with room for any reasonable password, so typing
+/// one never reallocates and leaves part of it behind in a freed buffer.
+pub fn secret_buffer() -> Zeroizing<String> {
+    Zeroizing::new(String::with_capacity(256))
+}
+
+/// How a password is stretched into the encryption key and the keystore's
+/// password hash. Recorded with the salt, so the parameters can be raised
+/// later without losing track of how existing data was keyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyDerivation {
+    /// 10,000 rounds of SHA-256, from before Argon2id. Only kept to open
+    /// data keyed this way until the password is next entered (see
+    /// `KeyDerivation::is_outdated`).
+    LegacySha256,
+    Argon2id { memory_kib: u32, iterations: u32, parallelism: u32 },
+}
+
+impl KeyDerivation {
+    /// OWASP's baseline for Argon2id: 19 MiB, two passes, one lane.
+    pub const RECOMMENDED: KeyDerivation = KeyDerivation::Argon2id { memory_kib: 19 * 1024, iterations: 2, parallelism: 1 };
+
+    /// Whether a password keyed this way should be re-keyed with
+    /// `RECOMMENDED` the next time it's entered.
+    pub fn is_outdated(self) -> bool {
+        match (self, KeyDerivation::RECOMMENDED) {
+            (
+                KeyDerivation::Argon2id { memory_kib, iterations, .. },
+                KeyDerivation::Argon2id { memory_kib: recommended_memory, iterations: recommended_iterations, .. },
+            ) => memory_kib < recommended_memory || iterations < recommended_iterations,
+            _ => true,
+        }
+    }
+
+    fn derive(self, password: &str, salt: &str) -> Result<Zeroizing<[u8; 32]>> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        match self {
+            KeyDerivation::LegacySha256 => {
+                let mut hasher = Sha256::new();
+                hasher.update(password.as_bytes());
+                hasher.update(salt.as_bytes());
+                for _ in 0..10000 {
+                    let result = hasher.finalize_reset();
+                    hasher.update(result);
+                }
+                hasher.finalize_into((&mut key[..]).into());
+            }
+            KeyDerivation::Argon2id { memory_kib, iterations, parallelism } => {
+                let params = Params::new(memory_kib, iterations, parallelism, Some(key.len()))
+                    .map_err(|e| anyhow::anyhow!("Invalid Argon2id parameters: {}", e))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(password.as_bytes(), salt.as_bytes(), &mut *key)
+                    .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// Enhanced encryption wrapper for sensitive data with proper key derivation.
+/// The key is wiped from memory when this is dropped.
+pub struct DatabaseEncryption {
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl DatabaseEncryption {
+    /// Create encryption instance from a password, derived as `derivation` says
+    pub fn new(password: &str, salt: &str, derivation: KeyDerivation) -> Result<Self> {
+        Ok(DatabaseEncryption { key: derivation.derive(password, salt)? })
+    }
+
+    /// Generate a random salt for password hashing
+    pub fn generate_salt() -> String {
+        let mut salt_bytes = [0u8; 32];
+        rand::thread_rng().fill(&mut salt_bytes);
+        general_purpose::STANDARD.encode(salt_bytes)
+    }
+
+    /// Hash a password with a salt (for storing password hashes). The
+    /// legacy hash is the key itself; an Argon2id one is a SHA-256 of the
+    /// key, so the stored hash can't stand in for it.
+    pub fn hash_password(password: &str, salt: &str, derivation: KeyDerivation) -> Result<String> {
+        let key = derivation.derive(password, salt)?;
+        Ok(match derivation {
+            KeyDerivation::LegacySha256 => general_purpose::STANDARD.encode(key.as_slice()),
+            KeyDerivation::Argon2id { .. } => {
+                let mut hasher = Sha256::new();
+                hasher.update(PASSWORD_CHECK_CONTEXT);
+                hasher.update(key.as_slice());
+                general_purpose::STANDARD.encode(hasher.finalize())
+            }
+        })
+    }
+
+    /// Verify a password against a stored hash
+    pub fn verify_password(password: &str, salt: &str, stored_hash: &str, derivation: KeyDerivation) -> bool {
+        Self::hash_password(password, salt, derivation).is_ok_and(|computed_hash| computed_hash == stored_hash)
+    }
+
+    /// The derived key as a SQLCipher raw key (`x'…'`), so the database
+    /// file can be keyed without SQLCipher deriving a second key from the
+    /// password.
+    pub fn file_key(&self) -> Zeroizing<String> {
+        // Sized up front so no partial copy is left behind by a reallocation
+        let mut file_key = Zeroizing::new(String::with_capacity(3 + 2 * self.key.len()));
+        file_key.push_str("x'");
+        for byte in self.key.iter() {
+            let _ = write!(file_key, "{:02x}", byte);
+        }
+        file_key.push('\'');
+        file_key
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.key.as_slice()))
+    }
+
+    pub fn encrypt(&self, data: &str) -> Result<String> {
+        let cipher = self.cipher();
+        
+        // Generate a random nonce
+        let mut nonce_bytes = [0u8; 12];
+        rand::thread_rng().fill(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        
+        // Encrypt the data
+        let ciphertext = cipher.encrypt(nonce, data.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+        
+        // Combine nonce and ciphertext and encode as base64
+        let mut combined = Vec::new();
+        combined.extend_from_slice(&nonce_bytes);
+        combined.extend_from_slice(&ciphertext);
+        
+        Ok(general_purpose::STANDARD.encode(combined))
+    }
+
+    /// `encrypt`, marked so `open` can tell the result from plaintext.
+    /// Empty and already sealed values are left as they are.
+    pub fn seal(&self, value: &str) -> Result<String> {
+        if value.is_empty() || is_sealed(value) {
+            return Ok(value.to_string());
+        }
+        Ok(format!("{}{}", SEALED_PREFIX, self.encrypt(value)?))
+    }
+
+    /// Decrypts a value `seal` stored; anything else is returned as it is.
+    pub fn open(&self, value: &str) -> Result<String> {
+        match value.strip_prefix(SEALED_PREFIX) {
+            Some(encrypted) => self.decrypt(encrypted),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    pub fn decrypt(&self, encrypted_data: &str) -> Result<String> {
+        let cipher = self.cipher();
+        
+        // Decode from base64
+        let combined = general_purpose::STANDARD.decode(encrypted_data)
+            .map_err(|e| anyhow::anyhow!("Base64 decode failed: {}", e))?;
+        
+        if combined.len() < 12 {
+            return Err(anyhow::anyhow!("Invalid encrypted data"));
+        }
+        
+        // Extract nonce and ciphertext
+        let nonce_bytes = &combined[..12];
+        let ciphertext = &combined[12..];
+        
+        let nonce = Nonce::from_slice(nonce_bytes);
+        
+        // Decrypt the data
+        let plaintext = cipher.decrypt(nonce, ciphertext)
+            .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))?;
+        
+        String::from_utf8(plaintext)
+            .map_err(|e| anyhow::anyhow!("Invalid UTF-8: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encryption_decryption() {
+        let salt = DatabaseEncryption::generate_salt();
+        let encryption = DatabaseEncryption::new("test_password", &salt, KeyDerivation::RECOMMENDED).unwrap();
+        let original_data = "sensitive financial data";
+        
+        let encrypted = encryption.encrypt(original_data).unwrap();
+        let decrypted = encryption.decrypt(&encrypted).unwrap();
+        
+        assert_eq!(original_data, decrypted);
+    }
+
+    #[test]
+    fn seal_marks_values_so_open_can_tell_them_from_plaintext() {
+        let encryption = DatabaseEncryption::new("test_password", &DatabaseEncryption::generate_salt(), KeyDerivation::RECOMMENDED).unwrap();
+        let sealed = encryption.seal("Dentist").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(encryption.seal(&sealed).unwrap(), sealed, "sealing twice changes nothing");
+        assert_eq!(encryption.open(&sealed).unwrap(), "Dentist");
+        assert_eq!(encryption.open("plain").unwrap(), "plain");
+        assert_eq!(encryption.seal("").unwrap(), "");
+    }
+
+    #[test]
+    fn secret_buffer_is_wiped_in_place_and_never_reallocates_for_a_password() {
+        use zeroize::Zeroize;
+        let mut buffer = secret_buffer();
+        let (address, capacity) = (buffer.as_ptr(), buffer.capacity());
+        buffer.push_str(&"x".repeat(100));
+        assert_eq!(buffer.as_ptr(), address);
+        buffer.zeroize();
+        assert!(buffer.is_empty());
+        assert_eq!((buffer.as_ptr(), buffer.capacity()), (address, capacity), "wiping keeps the buffer for the next password");
+    }
+
+    #[test]
+    fn test_password_hashing() {
+        let password = "my_secure_password";
+        let salt = DatabaseEncryption::generate_salt();
+        let derivation = KeyDerivation::RECOMMENDED;
+        
+        let hash1 = DatabaseEncryption::hash_password(password, &salt, derivation).unwrap();
+        let hash2 = DatabaseEncryption::hash_password(password, &salt, derivation).unwrap();
+        
+        // Same password and salt should produce same hash
+        assert_eq!(hash1, hash2);
+        
+        // Different salt should produce different hash
+        let different_salt = DatabaseEncryption::generate_salt();
+        let hash3 = DatabaseEncryption::hash_password(password, &different_salt, derivation).unwrap();
+        assert_ne!(hash1, hash3);
+        
+        // Verify password should work
+        assert!(DatabaseEncryption::verify_password(password, &salt, &hash1, derivation));
+        assert!(!DatabaseEncryption::verify_password("wrong_password", &salt, &hash1, derivation));
+    }
+
+    #[test]
+    fn each_derivation_gives_its_own_key_and_the_hash_is_not_the_key() {
+        let salt = DatabaseEncryption::generate_salt();
+        let legacy = DatabaseEncryption::new("password", &salt, KeyDerivation::LegacySha256).unwrap();
+        let argon2 = DatabaseEncryption::new("password", &salt, KeyDerivation::RECOMMENDED).unwrap();
+        assert!(argon2.decrypt(&legacy.encrypt("data").unwrap()).is_err());
+        assert_eq!(legacy.decrypt(&legacy.encrypt("data").unwrap()).unwrap(), "data");
+
+        // Legacy hashes, and so existing keystores, still verify
+        let legacy_hash = DatabaseEncryption::hash_password("password", &salt, KeyDerivation::LegacySha256).unwrap();
+        assert!(DatabaseEncryption::verify_password("password", &salt, &legacy_hash, KeyDerivation::LegacySha256));
+        assert!(!DatabaseEncryption::verify_password("password", &salt, &legacy_hash, KeyDerivation::RECOMMENDED));
+
+        let hash = DatabaseEncryption::hash_password("password", &salt, KeyDerivation::RECOMMENDED).unwrap();
+        let key_hex: String = general_purpose::STANDARD.decode(&hash).unwrap().iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(!argon2.file_key().contains(&key_hex));
+
+        assert!(KeyDerivation::LegacySha256.is_outdated());
+        assert!(!KeyDerivation::RECOMMENDED.is_outdated());
+        assert!(KeyDerivation::Argon2id { memory_kib: 1024, iterations: 2, parallelism: 1 }.is_outdated());
+        assert!(DatabaseEncryption::new("password", &salt, KeyDerivation::Argon2id { memory_kib: 0, iterations: 0, parallelism: 0 }).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::Result;
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use crate::encryption::{DatabaseEncryption, KeyDerivation};
+use log::{info, warn, error};
+
+const KEYRING_SERVICE: &str = "MyPersonalApplicationsService";
+const KEYRING_USER: &str = "preft";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    pub password_hash: Option<String>,
+    pub salt: Option<String>,
+    pub database_encrypted: bool,
+    /// Whether the whole database file is encrypted with SQLCipher, not
+    /// just the settings (see `Database::encrypt_file`).
+    #[serde(default)]
+    pub file_encrypted: bool,
+    /// Flow columns stored encrypted, value by value, once a password is
+    /// set (see `Database::set_encrypted_columns`).
+    #[serde(default)]
+    pub encrypted_columns: Vec<SensitiveColumn>,
+    /// How `password_hash` and the key were derived from the password.
+    /// Configs from before this was recorded used the legacy derivation.
+    #[serde(default = "legacy_key_derivation")]
+    pub key_derivation: KeyDerivation,
+    /// Derivations the same password and salt were keyed with before
+    /// `set_key_derivation` upgraded them, newest first, so backups taken
+    /// before an upgrade can still be opened. A new password clears them.
+    #[serde(default)]
+    pub retired_key_derivations: Vec<KeyDerivation>,
+}
+
+fn legacy_key_derivation() -> KeyDerivation {
+    KeyDerivation::LegacySha256
+}
+
+/// Flow columns that can be stored encrypted, at the cost of no longer
+/// being searchable or readable in SQL (e.g. in SQL views). Dates, amounts
+/// and categories never are, so totals and date ranges can always be
+/// worked out in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SensitiveColumn {
+    Description,
+    /// The values of a flow's custom fields; the field names stay readable.
+    CustomFields,
+    Location,
+}
+
+impl SensitiveColumn {
+    pub const ALL: [SensitiveColumn; 3] = [SensitiveColumn::Description, SensitiveColumn::CustomFields, SensitiveColumn::Location];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SensitiveColumn::Description => "Descriptions",
+            SensitiveColumn::CustomFields => "Custom field values",
+            SensitiveColumn::Location => "Locations",
+        }
+    }
+}
+
+/// The keystore and the database disagree about whether the data is
+/// encrypted. See `EncryptionConfig::mismatch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionMismatch {
+    /// `database_encrypted` is set, but the stored data is plaintext.
+    DataNotEncrypted,
+    /// `database_encrypted` is clear, but the stored data is ciphertext.
+    DataEncrypted,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true, // Default to encryption enabled
+            password_hash: None,
+            salt: None,
+            database_encrypted: false,
+            file_encrypted: false,
+            encrypted_columns: Vec::new(),
+            key_derivation: KeyDerivation::RECOMMENDED,
+            retired_key_derivations: Vec::new(),
+        }
+    }
+}
+
+impl EncryptionConfig {
+    /// Load encryption configuration from OS keystore
+    pub fn load() -> Result<Self> {
+        let entry = Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        
+        match entry.get_password() {
+            Ok(encrypted_config) => {
+                // The config itself is stored in plain text in the keystore
+                // since the keystore is already secure
+                let config: EncryptionConfig = serde_json::from_str(&encrypted_config)?;
+                Ok(config)
+            }
+            Err(keyring::Error::NoEntry) => {
+                // No configuration exists yet, return default
+                Ok(EncryptionConfig::default())
+            }
+            Err(e) => {
+                // Other error, return default but log the issue
+                log::error!("Warning: Could not load encryption config from keystore: {}", e);
+                Ok(EncryptionConfig::default())
+            }
+        }
+    }
+
+    /// Save encryption configuration to OS keystore
+    pub fn save(&self) -> Result<()> {
+        let entry = Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+        let config_json = serde_json::to_string(self)?;
+        
+        entry.set_password(&config_json)
+            .map_err(|e| anyhow::anyhow!("Failed to save encryption config to keystore: {}", e))?;
+        
+        Ok(())
+    }
+
+    /// Set password and update configuration
+    pub fn set_password(&mut self, password: &str) -> Result<()> {
+        self.set_password_with_salt(password, DatabaseEncryption::generate_salt(), KeyDerivation::RECOMMENDED)
+    }
+
+    /// `set_password` with a salt and derivation chosen by the caller, e.g.
+    /// ones the data was already re-encrypted with (see
+    /// `Database::change_key`).
+    pub fn set_password_with_salt(&mut self, password: &str, salt: String, key_derivation: KeyDerivation) -> Result<()> {
+        let password_hash = DatabaseEncryption::hash_password(password, &salt, key_derivation)?;
+        
+        self.password_hash = Some(password_hash);
+        self.salt = Some(salt);
+        self.key_derivation = key_derivation;
+        self.retired_key_derivations.clear();
+        self.enabled = true;
+        self.database_encrypted = true;
+        
+        self.save()?;
+        Ok(())
+    }
+
+    /// Re-hashes the current `password` with `key_derivation`, keeping the
+    /// salt, after the data was re-encrypted that way. The derivation it
+    /// replaces is kept in `retired_key_derivations`.
+    pub fn set_key_derivation(&mut self, password: &str, key_derivation: KeyDerivation) -> Result<()> {
+        let salt = self.salt.clone().ok_or_else(|| anyhow::anyhow!("Salt not found"))?;
+        self.password_hash = Some(DatabaseEncryption::hash_password(password, &salt, key_derivation)?);
+        let retired = std::mem::replace(&mut self.key_derivation, key_derivation);
+        if retired != key_derivation && !self.retired_key_derivations.contains(&retired) {
+            self.retired_key_derivations.insert(0, retired);
+        }
+        self.save()?;
+        Ok(())
+    }
+
+    /// The current derivation followed by the retired ones, in the order
+    /// to try them on data of unknown age such as a backup.
+    pub fn key_derivations(&self) -> Vec<KeyDerivation> {
+        std::iter::once(self.key_derivation)
+            .chain(self.retired_key_derivations.iter().copied())
+            .collect()
+    }
+
+    /// Verify a password against stored hash
+    pub fn verify_password(&self, password: &str) -> bool {
+        if let (Some(stored_hash), Some(salt)) = (&self.password_hash, &self.salt) {
+            DatabaseEncryption::verify_password(password, salt, stored_hash, self.key_derivation)
+        } else {
+            false
+        }
+    }
+
+    /// Check if encryption is enabled and password is set
+    pub fn is_encryption_ready(&self) -> bool {
+        self.enabled && self.password_hash.is_some() && self.salt.is_some()
+    }
+
+    /// Check if database is currently encrypted
+    pub fn is_database_encrypted(&self) -> bool {
+        self.database_encrypted
+    }
+
+    /// Compares `database_encrypted` with whether the stored data actually
+    /// is encrypted (`Database::detect_encryption_state`).
+    pub fn mismatch(&self, data_encrypted: bool) -> Option<EncryptionMismatch> {
+        match (self.database_encrypted, data_encrypted) {
+            (true, false) => Some(EncryptionMismatch::DataNotEncrypted),
+            (false, true) => Some(EncryptionMismatch::DataEncrypted),
+            _ => None,
+        }
+    }
+
+    /// Whether the keystore still has a password hash and salt, without
+    /// which encrypted data can't be read or written.
+    pub fn has_password(&self) -> bool {
+        self.password_hash.is_some() && self.salt.is_some()
+    }
+
+    /// Record whether the database is encrypted, keeping the password
+    pub fn set_database_encrypted(&mut self, encrypted: bool) -> Result<()> {
+        self.database_encrypted = encrypted;
+        if encrypted {
+            self.enabled = true;
+        }
+
+        self.save()?;
+        Ok(())
+    }
+
+    /// Record whether the database file itself is encrypted
+    pub fn set_file_encrypted(&mut self, encrypted: bool) -> Result<()> {
+        self.file_encrypted = encrypted;
+        self.save()?;
+        Ok(())
+    }
+
+    /// Whether `column` is stored encrypted once there's a password.
+    pub fn encrypts(&self, column: SensitiveColumn) -> bool {
+        self.encrypted_columns.contains(&column)
+    }
+
+    /// Record which flow columns are stored encrypted
+    pub fn set_encrypted_columns(&mut self, columns: Vec<SensitiveColumn>) -> Result<()> {
+        self.encrypted_columns = columns;
+        self.save()?;
+        Ok(())
+    }
+
+    /// Disable encryption (for migration from encrypted to unencrypted)
+    pub fn disable_encryption(&mut self) -> Result<()> {
+        self.enabled = false;
+        self.password_hash = None;
+        self.salt = None;
+        self.database_encrypted = false;
+        self.file_encrypted = false;
+        self.encrypted_columns.clear();
+        self.retired_key_derivations.clear();
+        
+        self.save()?;
+        Ok(())
+    }
+
+    /// Re-enable encryption configuration (without setting password)
+    /// This puts the system back into the "configured but no password" state
+    pub fn re_enable_encryption(&mut self) -> Result<()> {
+        self.enabled = true;
+        self.password_hash = None;
+        self.salt = None;
+        self.database_encrypted = false;
+        self.file_encrypted = false;
+        self.encrypted_columns.clear();
+        self.retired_key_derivations.clear();
+        
+        self.save()?;
+        Ok(())
+    }
+
+    /// Get salt for database encryption initialization
+    pub fn get_salt(&self) -> Option<&String> {
+        self.salt.as_ref()
+    }
+
+    /// Get password hash for verification
+    pub fn get_password_hash(&self) -> Option<&String> {
+        self.password_hash.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encryption_config_default() {
+        let config = EncryptionConfig::default();
+        assert!(config.enabled);
+        assert!(!config.is_encryption_ready());
+        assert!(!config.is_database_encrypted());
+    }
+
+    #[test]
+    fn mismatch_compares_the_keystore_flag_with_the_stored_data() {
+        let mut config = EncryptionConfig::default();
+        assert_eq!(config.mismatch(false), None);
+        assert_eq!(config.mismatch(true), Some(EncryptionMismatch::DataEncrypted));
+
+        config.database_encrypted = true;
+        assert_eq!(config.mismatch(true), None);
+        assert_eq!(config.mismatch(false), Some(EncryptionMismatch::DataNotEncrypted));
+    }
+
+    #[test]
+    fn configs_saved_before_argon2id_keep_the_legacy_derivation() {
+        let salt = DatabaseEncryption::generate_salt();
+        let hash = DatabaseEncryption::hash_password("password", &salt, KeyDerivation::LegacySha256).unwrap();
+        let saved = format!(r#"{{"enabled":true,"password_hash":"{}","salt":"{}","database_encrypted":true}}"#, hash, salt);
+        let config: EncryptionConfig = serde_json::from_str(&saved).unwrap();
+        assert_eq!(config.key_derivation, KeyDerivation::LegacySha256);
+        assert!(config.verify_password("password"));
+
+        let round_tripped: EncryptionConfig = serde_json::from_str(&serde_json::to_string(&EncryptionConfig::default()).unwrap()).unwrap();
+        assert_eq!(round_tripped.key_derivation, KeyDerivation::RECOMMENDED);
+    }
+
+    #[test]
+    fn test_password_setting_and_verification() {
+        let mut config = EncryptionConfig::default();
+        let password = "test_password_123";
+        
+        // Set password
+        config.set_password(password).unwrap();
+        assert!(config.is_encryption_ready());
+        assert!(config.is_database_encrypted());
+        
+        // Verify password
+        assert!(config.verify_password(password));
+        assert!(!config.verify_password("wrong_password"));
+    }
+}
\ No newline at end of file
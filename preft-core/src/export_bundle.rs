@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::encryption::{DatabaseEncryption, KeyDerivation};
+
+const BUNDLE_FORMAT: &str = "preft-export-bundle";
+/// 2 added `key_derivation`; version 1 bundles all used the legacy one.
+const BUNDLE_VERSION: u32 = 2;
+
+/// One file inside an export bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleFile {
+    /// A plain file name; bundles have no directories.
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+/// What's written to disk: enough to derive the key again, plus the
+/// encrypted file list. Everything but the format marker and salt is
+/// inside `payload`, so not even the file names are readable without the
+/// password.
+#[derive(Serialize, Deserialize)]
+struct SealedBundle {
+    format: String,
+    version: u32,
+    salt: String,
+    #[serde(default = "legacy_key_derivation")]
+    key_derivation: KeyDerivation,
+    payload: String,
+}
+
+fn legacy_key_derivation() -> KeyDerivation {
+    KeyDerivation::LegacySha256
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredFile {
+    name: String,
+    /// Base64, since the database backup is binary.
+    contents: String,
+}
+
+/// Encrypts `files` into a single bundle with a key derived from
+/// `password` (the same AES-256-GCM scheme as the database's encrypted
+/// fields, with a fresh salt per bundle).
+pub fn seal(files: &[BundleFile], password: &str) -> Result<Vec<u8>> {
+    let stored: Vec<StoredFile> = files.iter()
+        .map(|file| StoredFile {
+            name: file.name.clone(),
+            contents: general_purpose::STANDARD.encode(&file.contents),
+        })
+        .collect();
+    let salt = DatabaseEncryption::generate_salt();
+    let key_derivation = KeyDerivation::RECOMMENDED;
+    let payload = DatabaseEncryption::new(password, &salt, key_derivation)?.encrypt(&serde_json::to_string(&stored)?)?;
+    let sealed = SealedBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        salt,
+        key_derivation,
+        payload,
+    };
+    Ok(serde_json::to_vec_pretty(&sealed)?)
+}
+
+/// Decrypts a bundle written by `seal`. A wrong password fails here rather
+/// than producing garbage, since AES-GCM authenticates what it decrypts.
+pub fn open(data: &[u8], password: &str) -> Result<Vec<BundleFile>> {
+    let sealed: SealedBundle = serde_json::from_slice(data)
+        .map_err(|_| anyhow!("This is not a preft export bundle"))?;
+    if sealed.format != BUNDLE_FORMAT {
+        return Err(anyhow!("This is not a preft export bundle"));
+    }
+    if sealed.version > BUNDLE_VERSION {
+        return Err(anyhow!("This bundle was made by a newer version of preft (format version {})", sealed.version));
+    }
+    let json = DatabaseEncryption::new(password, &sealed.salt, sealed.key_derivation)?
+        .decrypt(&sealed.payload)
+        .map_err(|_| anyhow!("Wrong password, or the bundle is damaged"))?;
+    let stored: Vec<StoredFile> = serde_json::from_str(&json)?;
+    stored.into_iter()
+        .map(|file| Ok(BundleFile {
+            contents: general_purpose::STANDARD.decode(&file.contents)?,
+            name: file.name,
+        }))
+        .collect()
+}
+
+/// Writes each file into `dir`, refusing to overwrite anything already
+/// there. Names are reduced to their final component so a crafted bundle
+/// can't write outside `dir`. Returns the paths written.
+pub fn extract_to(files: &[BundleFile], dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut targets = Vec::new();
+    for file in files {
+        let name = Path::new(&file.name).file_name()
+            .ok_or_else(|| anyhow!("Bundle contains an invalid file name: {:?}", file.name))?;
+        let target = dir.join(name);
+        if target.exists() {
+            return Err(anyhow!("{} already exists; choose an empty folder", target.display()));
+        }
+        targets.push(target);
+    }
+    std::fs::create_dir_all(dir)?;
+    for (file, target) in files.iter().zip(&targets) {
+        std::fs::write(target, &file.contents)?;
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<BundleFile> {
+        vec![
+            BundleFile { name: "preft.db".to_string(), contents: vec![0, 159, 146, 150, 255] },
+            BundleFile { name: "settings.json".to_string(), contents: b"{}".to_vec() },
+        ]
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let sealed = seal(&files(), "correct horse").unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("settings.json"), "file names are encrypted too");
+        assert_eq!(open(&sealed, "correct horse").unwrap(), files());
+    }
+
+    #[test]
+    fn open_rejects_a_wrong_password_and_other_files() {
+        let sealed = seal(&files(), "correct horse").unwrap();
+        assert!(open(&sealed, "battery staple").is_err());
+        assert!(open(b"SQLite format 3\0", "correct horse").is_err());
+    }
+
+    #[test]
+    fn version_1_bundles_open_with_the_legacy_derivation() {
+        let salt = DatabaseEncryption::generate_salt();
+        let stored = vec![StoredFile { name: "settings.json".to_string(), contents: general_purpose::STANDARD.encode(b"{}") }];
+        let payload = DatabaseEncryption::new("correct horse", &salt, KeyDerivation::LegacySha256).unwrap()
+            .encrypt(&serde_json::to_string(&stored).unwrap()).unwrap();
+        let old = serde_json::json!({ "format": BUNDLE_FORMAT, "version": 1, "salt": salt, "payload": payload });
+        let opened = open(&serde_json::to_vec(&old).unwrap(), "correct horse").unwrap();
+        assert_eq!(opened, vec![BundleFile { name: "settings.json".to_string(), contents: b"{}".to_vec() }]);
+    }
+
+    #[test]
+    fn extract_strips_directories_and_never_overwrites() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let sneaky = vec![BundleFile { name: "../../escape.txt".to_string(), contents: b"x".to_vec() }];
+        let written = extract_to(&sneaky, dir.path()).unwrap();
+        assert_eq!(written, vec![dir.path().join("escape.txt")]);
+
+        assert!(extract_to(&sneaky, dir.path()).is_err(), "an existing file is not overwritten");
+    }
+}
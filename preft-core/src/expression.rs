@@ -0,0 +1,152 @@
+//! Simple arithmetic in amount fields, e.g. `"12.50*3+4"` when adding up
+//! the line items of a receipt: `+ - * /`, parentheses and unary minus,
+//! with the numbers in the user's number format.
+
+use crate::locale::NumberFormat;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Operator(char),
+}
+
+/// Evaluates `text` as an amount, either a plain number (see
+/// `NumberFormat::parse_amount`) or arithmetic on numbers. `None` if it
+/// doesn't parse or the result isn't finite (e.g. divides by zero).
+pub fn evaluate_amount(text: &str, number_format: &NumberFormat) -> Option<f64> {
+    let tokens = tokenize(text, number_format)?;
+    let mut parser = Parser { tokens: &tokens, pos: 0 };
+    let value = parser.expression()?;
+    (parser.pos == tokens.len() && value.is_finite()).then_some(value)
+}
+
+/// Whether `text` has any arithmetic in it, rather than being a number.
+pub fn is_expression(text: &str, number_format: &NumberFormat) -> bool {
+    tokenize(text, number_format).is_some_and(|tokens| tokens.len() > 1)
+}
+
+fn tokenize(text: &str, number_format: &NumberFormat) -> Option<Vec<Token>> {
+    // Spaces may be thousands separators, and the symbol can't be an
+    // operator, so both go before splitting.
+    let symbol = number_format.currency_symbol.trim();
+    let text: String = if symbol.is_empty() { text.to_string() } else { text.replace(symbol, "") };
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let is_number_char = |c: char| {
+        c.is_ascii_digit()
+            || c == '.'
+            || c == number_format.decimal_separator
+            || number_format.thousands_separator.contains(c)
+    };
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if matches!(c, '+' | '-' | '*' | '/' | '(' | ')') {
+            tokens.push(Token::Operator(c));
+        } else if is_number_char(c) {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek().filter(|(_, c)| is_number_char(*c)) {
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = &text[start..end];
+            // As in the flow editor, a plain "12.50" reads the same in
+            // every format.
+            let value = number.parse::<f64>().ok().or_else(|| number_format.parse_amount(number))?;
+            tokens.push(Token::Number(value));
+        } else {
+            return None;
+        }
+    }
+    Some(tokens)
+}
+
+/// Recursive descent over the usual precedence: `*` and `/` before `+` and
+/// `-`, left to right.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn next_operator(&mut self, operators: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Operator(op)) if operators.contains(op) => {
+                self.pos += 1;
+                Some(*op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(op) = self.next_operator(&['+', '-']) {
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        while let Some(op) = self.next_operator(&['*', '/']) {
+            let rhs = self.factor()?;
+            value = if op == '*' { value * rhs } else { value / rhs };
+        }
+        Some(value)
+    }
+
+    fn factor(&mut self) -> Option<f64> {
+        if self.next_operator(&['-']).is_some() {
+            return self.factor().map(|value| -value);
+        }
+        if self.next_operator(&['(']).is_some() {
+            let value = self.expression()?;
+            self.next_operator(&[')'])?;
+            return Some(value);
+        }
+        match self.tokens.get(self.pos) {
+            Some(Token::Number(value)) => {
+                self.pos += 1;
+                Some(*value)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn german() -> NumberFormat {
+        NumberFormat::presets().into_iter().find(|(name, _)| name.contains("German")).unwrap().1
+    }
+
+    #[test]
+    fn evaluates_arithmetic_with_precedence_and_parentheses() {
+        let us = NumberFormat::default();
+        assert_eq!(evaluate_amount("12.50*3+4", &us), Some(41.5));
+        assert_eq!(evaluate_amount("(1 + 2) * 3", &us), Some(9.0));
+        assert_eq!(evaluate_amount("10 - 2 - 3", &us), Some(5.0));
+        assert_eq!(evaluate_amount("-4 + $1,000", &us), Some(996.0));
+        assert_eq!(evaluate_amount("42", &us), Some(42.0));
+        assert!(is_expression("1+2", &us));
+        assert!(!is_expression("$1,200.00", &us));
+    }
+
+    #[test]
+    fn reads_numbers_in_the_users_format() {
+        assert_eq!(evaluate_amount("1.234,5 € + 0,5", &german()), Some(1235.0));
+        assert_eq!(evaluate_amount("12.50*2", &german()), Some(25.0), "plain numbers still read as typed");
+    }
+
+    #[test]
+    fn rejects_what_isnt_arithmetic() {
+        let us = NumberFormat::default();
+        for text in ["", "1+", "(1+2", "1+2)", "2*/3", "abc", "1/0"] {
+            assert_eq!(evaluate_amount(text, &us), None, "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Flows loaded for the views that show them, by `FlowScope`, rather than
+//! every flow there is. Each scope's flows are loaded once (see
+//! `Database::load_flows_in`) and kept, newest first, until the cache
+//! grows past its capacity and the least recently used scopes are let go.
+//! Saves and deletes are applied to every scope they touch, so a view
+//! doesn't reload after each edit.
+
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+use crate::db::FlowScope;
+use crate::models::Flow;
+
+/// How many flows the cache holds across all its scopes before letting
+/// the least recently used go.
+pub const DEFAULT_CAPACITY: usize = 20_000;
+
+#[derive(Debug, Clone)]
+pub struct FlowCache {
+    /// Least recently used first.
+    entries: Vec<(FlowScope, Arc<Vec<Flow>>)>,
+    capacity: usize,
+    /// Counts the saves, deletes and clears so far, so a load that was
+    /// under way during one can tell it may have missed it.
+    generation: u64,
+}
+
+impl Default for FlowCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl FlowCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: Vec::new(), capacity, generation: 0 }
+    }
+
+    /// The scope's flows, newest first, if they're loaded.
+    pub fn get(&mut self, scope: &FlowScope) -> Option<Arc<Vec<Flow>>> {
+        let index = self.entries.iter().position(|(cached, _)| cached == scope)?;
+        let entry = self.entries.remove(index);
+        let flows = entry.1.clone();
+        self.entries.push(entry);
+        Some(flows)
+    }
+
+    /// Keeps `flows` as the scope's, replacing what it had. Scopes used
+    /// least recently are let go until the cache is back within capacity,
+    /// though never the one just added, so a scope bigger than the whole
+    /// cache still stays until the next one comes in.
+    pub fn insert(&mut self, scope: FlowScope, mut flows: Vec<Flow>) -> Arc<Vec<Flow>> {
+        sort_newest_first(&mut flows);
+        let flows = Arc::new(flows);
+        self.entries.retain(|(cached, _)| *cached != scope);
+        self.entries.push((scope, flows.clone()));
+        while self.entries.len() > 1 && self.len() > self.capacity {
+            self.entries.remove(0);
+        }
+        flows
+    }
+
+    /// Every loaded scope with its flows, least recently used first.
+    pub fn iter(&self) -> impl Iterator<Item = (&FlowScope, &[Flow])> {
+        self.entries.iter().map(|(scope, flows)| (scope, flows.as_slice()))
+    }
+
+    /// The flow with this id in any loaded scope.
+    pub fn find(&self, flow_id: &str) -> Option<&Flow> {
+        self.entries.iter().rev().find_map(|(_, flows)| flows.iter().find(|f| f.id == flow_id))
+    }
+
+    /// Brings every scope in line with `flow` having been saved: it's put
+    /// in (or moved to its place in) each scope it falls in and taken out
+    /// of the rest. A page it touches is let go instead, since the flows
+    /// either side of it have shifted.
+    pub fn saved(&mut self, flow: &Flow) {
+        self.generation += 1;
+        self.entries.retain(|(scope, flows)| !scope.is_paged() || !(scope.contains(flow) || flows.iter().any(|f| f.id == flow.id)));
+        for (scope, flows) in &mut self.entries {
+            let had = flows.iter().any(|f| f.id == flow.id);
+            let wanted = scope.contains(flow);
+            if !had && !wanted {
+                continue;
+            }
+            let flows = Arc::make_mut(flows);
+            flows.retain(|f| f.id != flow.id);
+            if wanted {
+                let key = newest_first(flow);
+                let at = flows.partition_point(|f| newest_first(f) < key);
+                flows.insert(at, flow.clone());
+            }
+        }
+    }
+
+    /// Takes the flow out of every scope, letting go of pages that held it.
+    pub fn deleted(&mut self, flow_id: &str) {
+        self.generation += 1;
+        self.entries.retain(|(scope, flows)| !scope.is_paged() || !flows.iter().any(|f| f.id == flow_id));
+        for (_, flows) in &mut self.entries {
+            if flows.iter().any(|f| f.id == flow_id) {
+                Arc::make_mut(flows).retain(|f| f.id != flow_id);
+            }
+        }
+    }
+
+    /// Runs `edit` over every loaded flow, e.g. to follow a change the
+    /// database made to many flows at once. It mustn't move a flow to
+    /// another category or date, since that would take it out of scope.
+    pub fn edit_all(&mut self, mut edit: impl FnMut(&mut Flow)) {
+        self.generation += 1;
+        for (_, flows) in &mut self.entries {
+            let edited: Vec<(usize, Flow)> = flows.iter().enumerate()
+                .filter_map(|(index, flow)| {
+                    let mut edited = flow.clone();
+                    edit(&mut edited);
+                    (edited != *flow).then_some((index, edited))
+                })
+                .collect();
+            if edited.is_empty() {
+                continue;
+            }
+            let flows = Arc::make_mut(flows);
+            for (index, flow) in edited {
+                flows[index] = flow;
+            }
+        }
+    }
+
+    /// Lets go of every scope, e.g. once the database has changed in ways
+    /// the cache can't follow.
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.entries.clear();
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// How many flows are held, counting a flow once for each scope it's in.
+    pub fn len(&self) -> usize {
+        self.entries.iter().map(|(_, flows)| flows.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The order `Database::load_flows_in` returns flows in.
+fn newest_first(flow: &Flow) -> (Reverse<chrono::NaiveDate>, &str) {
+    (Reverse(flow.date), flow.id.as_str())
+}
+
+fn sort_newest_first(flows: &mut [Flow]) {
+    flows.sort_by(|a, b| newest_first(a).cmp(&newest_first(b)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, NaiveDate};
+    use std::collections::HashMap;
+
+    fn flow(id: &str, category_id: &str, year: i32, day: u32) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date: NaiveDate::from_ymd_opt(year, 3, day).unwrap(),
+            amount: 10.0,
+            category_id: category_id.to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    fn ids(flows: &[Flow]) -> Vec<&str> {
+        flows.iter().map(|f| f.id.as_str()).collect()
+    }
+
+    #[test]
+    fn least_recently_used_scopes_go_once_past_capacity() {
+        let mut cache = FlowCache::new(3);
+        let food = FlowScope::category("food", None);
+        let rent = FlowScope::category("rent", None);
+        cache.insert(food.clone(), vec![flow("a", "food", 2024, 1), flow("b", "food", 2024, 2)]);
+        cache.insert(rent.clone(), vec![flow("c", "rent", 2024, 1)]);
+        assert!(cache.get(&food).is_some(), "food is now the most recently used");
+
+        let this_year = FlowScope::years(2024..=2024);
+        cache.insert(this_year.clone(), vec![flow("a", "food", 2024, 1)]);
+        assert!(cache.get(&rent).is_none(), "rent was used least recently");
+        assert_eq!(cache.len(), 3);
+
+        let everything = FlowScope::default();
+        cache.insert(everything.clone(), (1..=5).map(|day| flow(&day.to_string(), "food", 2024, day)).collect());
+        assert!(cache.get(&everything).is_some(), "kept although bigger than the cache");
+        assert_eq!(cache.len(), 5, "everything else went");
+    }
+
+    #[test]
+    fn saves_move_flows_between_scopes_in_date_order() {
+        let mut cache = FlowCache::default();
+        let food_2024 = FlowScope::category("food", Some(2024));
+        let rent = FlowScope::category("rent", None);
+        cache.insert(food_2024.clone(), vec![flow("a", "food", 2024, 1), flow("b", "food", 2024, 9)]);
+        cache.insert(rent.clone(), Vec::new());
+        assert_eq!(ids(&cache.get(&food_2024).unwrap()), vec!["b", "a"], "newest first");
+
+        cache.saved(&flow("c", "food", 2024, 5));
+        assert_eq!(ids(&cache.get(&food_2024).unwrap()), vec!["b", "c", "a"]);
+        assert!(cache.get(&rent).unwrap().is_empty());
+
+        cache.saved(&flow("c", "rent", 2023, 5));
+        assert_eq!(ids(&cache.get(&food_2024).unwrap()), vec!["b", "a"]);
+        assert_eq!(ids(&cache.get(&rent).unwrap()), vec!["c"]);
+        assert_eq!(cache.find("c").map(|f| f.date.year()), Some(2023));
+
+        cache.deleted("b");
+        assert_eq!(ids(&cache.get(&food_2024).unwrap()), vec!["a"]);
+        assert!(cache.find("b").is_none());
+    }
+
+    #[test]
+    fn pages_an_edit_touches_are_let_go() {
+        let mut cache = FlowCache::default();
+        let first_page = FlowScope { limit: Some(2), ..FlowScope::category("food", None) };
+        let whole = FlowScope::category("food", None);
+        let flows = vec![flow("a", "food", 2024, 1), flow("b", "food", 2024, 2), flow("c", "food", 2024, 3)];
+        cache.insert(first_page.clone(), flows[1..].to_vec());
+        cache.insert(whole.clone(), flows.clone());
+
+        cache.saved(&flow("x", "rent", 2024, 4));
+        assert!(cache.get(&first_page).is_some(), "nothing on the page moved");
+
+        let generation = cache.generation();
+        cache.saved(&flow("d", "food", 2024, 4));
+        assert!(cache.get(&first_page).is_none());
+        assert_eq!(ids(&cache.get(&whole).unwrap()), vec!["d", "c", "b", "a"]);
+        assert!(cache.generation() > generation, "a load under way can tell it may be stale");
+    }
+
+    #[test]
+    fn edits_to_every_flow_leave_shared_copies_alone() {
+        let mut cache = FlowCache::default();
+        let scope = FlowScope::default();
+        let mut on_trip = flow("a", "food", 2024, 1);
+        on_trip.trip_id = Some("lisbon".to_string());
+        cache.insert(scope.clone(), vec![on_trip, flow("b", "food", 2024, 2)]);
+        let shown = cache.get(&scope).unwrap();
+
+        cache.edit_all(|flow| if flow.trip_id.as_deref() == Some("lisbon") { flow.trip_id = None });
+        assert_eq!(cache.find("a").unwrap().trip_id, None);
+        assert_eq!(shown.iter().find(|f| f.id == "a").unwrap().trip_id.as_deref(), Some("lisbon"));
+    }
+}
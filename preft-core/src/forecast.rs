@@ -0,0 +1,178 @@
+use chrono::{Duration, Months, NaiveDate};
+
+use crate::emergency;
+use crate::models::{Category, Flow, FlowType};
+
+/// How far back average spending is measured.
+const HISTORY_DAYS: i64 = 365;
+
+/// Projected net (income minus expenses) from the start of a period to its
+/// end, split by where each part comes from. Positive means more came in
+/// than went out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    pub period_end: NaiveDate,
+    /// What has actually happened so far this period.
+    pub actual: f64,
+    /// Scheduled flows still to come before `period_end`.
+    pub scheduled: f64,
+    /// Expected repeats of recurring flows (see
+    /// `emergency::detect_recurring`) not already scheduled.
+    pub recurring: f64,
+    /// Everything else, at each category's average daily rate over the past
+    /// year.
+    pub estimated: f64,
+}
+
+impl Forecast {
+    pub fn projected_net(&self) -> f64 {
+        self.actual + self.scheduled + self.recurring + self.estimated
+    }
+}
+
+/// Forecasts the period from `period_start` to `period_end` (inclusive)
+/// as of `today`: flows up to `today` count as they are, and the rest of the
+/// period is projected.
+pub fn forecast(flows: &[Flow], categories: &[Category], period_start: NaiveDate, period_end: NaiveDate, today: NaiveDate) -> Forecast {
+    let sign = |flow: &Flow| match categories.iter().find(|c| c.id == flow.category_id).map(|c| &c.flow_type) {
+        Some(FlowType::Income) => 1.0,
+        Some(FlowType::Expense) => -1.0,
+        None => 0.0,
+    };
+    let key = |category_id: &str, description: &str| (category_id.to_string(), description.trim().to_lowercase());
+
+    let actual = flows.iter()
+        .filter(|f| !f.scheduled && f.date >= period_start && f.date <= today)
+        .map(|f| sign(f) * f.net_amount())
+        .sum();
+
+    let upcoming: Vec<&Flow> = flows.iter()
+        .filter(|f| f.scheduled && f.date > today && f.date <= period_end)
+        .collect();
+    let scheduled = upcoming.iter().map(|f| sign(f) * f.projected_amount()).sum();
+
+    // A recurring flow that's also been scheduled is already counted above.
+    let recurring_items = emergency::detect_recurring(flows, categories, today);
+    let mut recurring = 0.0;
+    for item in &recurring_items {
+        let already_scheduled = upcoming.iter()
+            .any(|f| key(&f.category_id, &f.description) == key(&item.category_id, &item.description));
+        if already_scheduled {
+            continue;
+        }
+        let sign = if item.flow_type == FlowType::Income { 1.0 } else { -1.0 };
+        let mut next = item.last_date + Months::new(1);
+        while next <= period_end {
+            if next > today {
+                recurring += sign * item.typical_amount;
+            }
+            next = next + Months::new(1);
+        }
+    }
+
+    // Recurring flows are projected on their own above, so leave them out
+    // of the averages to avoid counting them twice.
+    let recurring_keys: Vec<(String, String)> = recurring_items.iter()
+        .map(|item| key(&item.category_id, &item.description))
+        .collect();
+    let history_start = today - Duration::days(HISTORY_DAYS);
+    let history_total: f64 = flows.iter()
+        .filter(|f| !f.scheduled && f.date > history_start && f.date <= today)
+        .filter(|f| !recurring_keys.contains(&key(&f.category_id, &f.description)))
+        .map(|f| sign(f) * f.net_amount())
+        .sum();
+    let remaining_days = (period_end - today).num_days().max(0);
+    let estimated = history_total / HISTORY_DAYS as f64 * remaining_days as f64;
+
+    Forecast { period_end, actual, scheduled, recurring, estimated }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaxDeductionInfo;
+    use std::collections::HashMap;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn category(id: &str, flow_type: FlowType) -> Category {
+        Category {
+            id: id.to_string(),
+            name: format!("Category {}", id),
+            flow_type,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: Vec::new(),
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    fn flow(category_id: &str, date: NaiveDate, amount: f64, description: &str) -> Flow {
+        Flow {
+            id: uuid::Uuid::new_v4().to_string(),
+            date,
+            amount,
+            category_id: category_id.to_string(),
+            description: description.to_string(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn forecast_adds_scheduled_and_recurring_flows_to_what_happened_so_far() {
+        let categories = vec![category("salary", FlowType::Income), category("bills", FlowType::Expense)];
+        let today = date(2024, 6, 10);
+        let mut bonus = flow("salary", date(2024, 6, 25), 500.0, "bonus");
+        bonus.scheduled = true;
+        let flows = vec![
+            flow("salary", date(2024, 6, 1), 3000.0, ""),
+            // Rent on the 15th for three months, so one more is due this month.
+            flow("bills", date(2024, 3, 15), 1000.0, "Rent"),
+            flow("bills", date(2024, 4, 15), 1000.0, "Rent"),
+            flow("bills", date(2024, 5, 15), 1000.0, "Rent"),
+            bonus,
+        ];
+
+        let result = forecast(&flows, &categories, date(2024, 6, 1), date(2024, 6, 30), today);
+
+        assert_eq!(result.actual, 3000.0);
+        assert_eq!(result.scheduled, 500.0);
+        assert_eq!(result.recurring, -1000.0);
+        // The only non-recurring history is this month's salary.
+        assert!((result.estimated - 3000.0 / 365.0 * 20.0).abs() < 1e-9);
+        assert!((result.projected_net() - (2500.0 + result.estimated)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scheduled_repeats_of_a_recurring_flow_are_not_counted_twice() {
+        let categories = vec![category("bills", FlowType::Expense)];
+        let today = date(2024, 6, 10);
+        let mut next_rent = flow("bills", date(2024, 6, 15), 1200.0, "rent");
+        next_rent.scheduled = true;
+        let flows = vec![
+            flow("bills", date(2024, 3, 15), 1000.0, "Rent"),
+            flow("bills", date(2024, 4, 15), 1000.0, "Rent"),
+            flow("bills", date(2024, 5, 15), 1000.0, "Rent"),
+            next_rent,
+        ];
+
+        let result = forecast(&flows, &categories, date(2024, 6, 1), date(2024, 6, 30), today);
+
+        assert_eq!(result.scheduled, -1200.0);
+        assert_eq!(result.recurring, 0.0);
+        assert_eq!(result.estimated, 0.0);
+    }
+}
@@ -0,0 +1,650 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::models::{Category, Flow, FlowType};
+
+/// How a preset's CSV export encodes the amount and whether a row is money
+/// in or money out. Flows themselves always store a positive `amount`
+/// (direction comes from the category's `FlowType`), so every convention
+/// below resolves to an absolute amount plus a `FlowType`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignConvention {
+    /// A single signed amount column: negative is an expense, positive is
+    /// income (most bank exports).
+    NegativeIsExpense {
+        amount_column: String,
+    },
+    /// Amounts are always positive and a separate column says which way the
+    /// money moved (Mint's "Transaction Type" of `debit`/`credit`).
+    TypeColumn {
+        amount_column: String,
+        column: String,
+        expense_value: String,
+        income_value: String,
+    },
+    /// Separate, always-positive columns for money out and money in, with
+    /// the unused one left blank or zero (YNAB's "Outflow"/"Inflow").
+    InflowOutflow {
+        inflow_column: String,
+        outflow_column: String,
+    },
+}
+
+/// A built-in description of one application's CSV export format: which
+/// columns hold what, how dates are written, how the sign is encoded, and
+/// which of the source application's category names map onto which preft
+/// category (by id). Category mappings are only *suggestions* -- the import
+/// dialog shows them pre-selected and lets each row be reassigned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportPreset {
+    pub name: String,
+    pub date_column: String,
+    pub date_format: String,
+    pub description_column: String,
+    pub sign_convention: SignConvention,
+    pub category_column: Option<String>,
+    pub notes_column: Option<String>,
+    /// Source category name (compared case-insensitively) -> preft category id.
+    pub category_mappings: Vec<(String, String)>,
+}
+
+impl ImportPreset {
+    /// Plain `Date,Description,Amount` with ISO dates and a signed amount --
+    /// the fallback for anything that doesn't have a dedicated preset.
+    pub fn generic() -> Self {
+        Self {
+            name: "Generic (Date, Description, Amount)".to_string(),
+            date_column: "Date".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            description_column: "Description".to_string(),
+            sign_convention: SignConvention::NegativeIsExpense {
+                amount_column: "Amount".to_string(),
+            },
+            category_column: None,
+            notes_column: None,
+            category_mappings: Vec::new(),
+        }
+    }
+
+    /// Mint's "Export all transactions" CSV:
+    /// `Date,Description,Original Description,Amount,Transaction Type,Category,Account Name,Labels,Notes`,
+    /// with US-style dates and always-positive amounts.
+    pub fn mint() -> Self {
+        let mappings = [
+            ("Paycheck", "salary"),
+            ("Bonus", "salary"),
+            ("Income", "other_income"),
+            ("Reimbursement", "other_income"),
+            ("Interest Income", "passive_income"),
+            ("Dividend & Cap Gains", "passive_income"),
+            ("Rental Income", "passive_income"),
+            ("Federal Tax", "taxes_paid"),
+            ("State Tax", "taxes_paid"),
+            ("Local Tax", "taxes_paid"),
+            ("Property Tax", "taxes_paid"),
+            ("Taxes", "taxes_paid"),
+            ("Charity", "cash_donations"),
+            ("Gifts & Donations", "cash_donations"),
+            ("Doctor", "medical"),
+            ("Pharmacy", "medical"),
+            ("Health Insurance", "medical"),
+            ("Eyecare", "medical"),
+            ("Dentist", "dental"),
+        ];
+
+        Self {
+            name: "Mint".to_string(),
+            date_column: "Date".to_string(),
+            date_format: "%m/%d/%Y".to_string(),
+            description_column: "Description".to_string(),
+            sign_convention: SignConvention::TypeColumn {
+                amount_column: "Amount".to_string(),
+                column: "Transaction Type".to_string(),
+                expense_value: "debit".to_string(),
+                income_value: "credit".to_string(),
+            },
+            category_column: Some("Category".to_string()),
+            notes_column: Some("Notes".to_string()),
+            category_mappings: mappings.iter()
+                .map(|(source, id)| (source.to_string(), id.to_string()))
+                .collect(),
+        }
+    }
+
+    /// YNAB's register export:
+    /// `Account,Flag,Date,Payee,Category Group/Category,Category Group,Category,Memo,Outflow,Inflow,Cleared`,
+    /// with separate outflow/inflow columns. Income lands in YNAB's
+    /// "Inflow: Ready to Assign" (formerly "To be Budgeted") category rather
+    /// than anything income-specific, so that's mapped to Other Income.
+    pub fn ynab() -> Self {
+        let mappings = [
+            ("Inflow: Ready to Assign", "other_income"),
+            ("Inflow: To be Budgeted", "other_income"),
+            ("Ready to Assign", "other_income"),
+            ("To be Budgeted", "other_income"),
+            ("Interest", "passive_income"),
+            ("Taxes", "taxes_paid"),
+            ("Income Tax", "taxes_paid"),
+            ("Property Tax", "taxes_paid"),
+            ("Charitable Giving", "cash_donations"),
+            ("Giving", "cash_donations"),
+            ("Charity", "cash_donations"),
+            ("Medical", "medical"),
+            ("Medical Expenses", "medical"),
+            ("Health", "medical"),
+            ("Dental", "dental"),
+        ];
+
+        Self {
+            name: "YNAB".to_string(),
+            date_column: "Date".to_string(),
+            date_format: "%m/%d/%Y".to_string(),
+            description_column: "Payee".to_string(),
+            sign_convention: SignConvention::InflowOutflow {
+                inflow_column: "Inflow".to_string(),
+                outflow_column: "Outflow".to_string(),
+            },
+            category_column: Some("Category".to_string()),
+            notes_column: Some("Memo".to_string()),
+            category_mappings: mappings.iter()
+                .map(|(source, id)| (source.to_string(), id.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn builtin_presets() -> Vec<ImportPreset> {
+        vec![Self::generic(), Self::mint(), Self::ynab()]
+    }
+
+    /// The suggested preft category id for a source category name, if any.
+    pub fn suggest_category(&self, source_category: &str) -> Option<&str> {
+        let source_category = source_category.trim();
+        self.category_mappings.iter()
+            .find(|(source, _)| source.eq_ignore_ascii_case(source_category))
+            .map(|(_, id)| id.as_str())
+    }
+}
+
+/// One successfully parsed row, not yet assigned to a category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedRow {
+    pub date: NaiveDate,
+    /// Always positive; see `SignConvention`.
+    pub amount: f64,
+    pub flow_type: FlowType,
+    pub description: String,
+    pub source_category: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl ImportedRow {
+    /// Picks a category for this row: the preset's suggestion when it exists
+    /// among `categories` and has the right flow type, otherwise the first
+    /// category with a matching flow type, preferring the catch-all
+    /// "Other Income"/"Other Expense" defaults.
+    pub fn suggested_category_id(&self, preset: &ImportPreset, categories: &[Category]) -> Option<String> {
+        let matches_type = |id: &str| categories.iter()
+            .any(|c| c.id == id && c.flow_type == self.flow_type);
+
+        if let Some(id) = self.source_category.as_deref().and_then(|s| preset.suggest_category(s))
+            && matches_type(id)
+        {
+            return Some(id.to_string());
+        }
+
+        let fallback = match self.flow_type {
+            FlowType::Income => "other_income",
+            FlowType::Expense => "other_expense",
+        };
+        if matches_type(fallback) {
+            return Some(fallback.to_string());
+        }
+
+        categories.iter()
+            .find(|c| c.flow_type == self.flow_type)
+            .map(|c| c.id.clone())
+    }
+
+    pub fn into_flow(self, category_id: String) -> Flow {
+        let mut custom_fields = HashMap::new();
+        if let Some(notes) = self.notes.filter(|n| !n.is_empty()) {
+            custom_fields.insert("notes".to_string(), notes);
+        }
+
+        Flow {
+            id: Uuid::new_v4().to_string(),
+            date: self.date,
+            amount: self.amount,
+            category_id,
+            description: self.description,
+            linked_flows: Vec::new(),
+            custom_fields,
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+}
+
+/// Result of running a preset over a CSV file. Rows that can't be parsed
+/// don't abort the whole import; they're reported back (with their 1-based
+/// line number in the file) so the dialog can show what was skipped.
+#[derive(Debug, Default)]
+pub struct ImportResult {
+    pub rows: Vec<ImportedRow>,
+    pub errors: Vec<String>,
+}
+
+/// Splits CSV text into records of fields. Handles quoted fields containing
+/// commas, doubled quotes (`""`) and embedded newlines, and both `\n` and
+/// `\r\n` line endings. Blank lines are skipped.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+/// Parses an amount as written in typical exports: optional currency symbol,
+/// thousands separators, and either a leading minus or accounting-style
+/// parentheses for negatives.
+pub fn parse_amount(text: &str) -> Option<f64> {
+    let trimmed = text.trim();
+    let (negative, inner) = if trimmed.starts_with('(') && trimmed.ends_with(')') {
+        (true, &trimmed[1..trimmed.len() - 1])
+    } else {
+        (false, trimmed)
+    };
+
+    let cleaned: String = inner.chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+    let value: f64 = cleaned.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// A preset's `SignConvention` resolved against an actual header row.
+enum AmountColumns<'a> {
+    Signed(usize),
+    Typed {
+        amount: usize,
+        flow_type: usize,
+        expense_value: &'a str,
+        income_value: &'a str,
+    },
+    Split {
+        inflow: usize,
+        outflow: usize,
+    },
+}
+
+impl AmountColumns<'_> {
+    /// Reads one record's absolute amount and direction, or describes why
+    /// it can't be read.
+    fn resolve<'r>(&self, get: &impl Fn(usize) -> &'r str) -> Result<(f64, FlowType), String> {
+        let amount = |idx: usize| parse_amount(get(idx))
+            .ok_or_else(|| format!("invalid amount \"{}\"", get(idx)));
+
+        match self {
+            AmountColumns::Signed(idx) => {
+                let value = amount(*idx)?;
+                let flow_type = if value < 0.0 { FlowType::Expense } else { FlowType::Income };
+                Ok((value.abs(), flow_type))
+            }
+            AmountColumns::Typed { amount: amount_idx, flow_type, expense_value, income_value } => {
+                let value = amount(*amount_idx)?.abs();
+                let kind = get(*flow_type);
+                if kind.eq_ignore_ascii_case(expense_value) {
+                    Ok((value, FlowType::Expense))
+                } else if kind.eq_ignore_ascii_case(income_value) {
+                    Ok((value, FlowType::Income))
+                } else {
+                    Err(format!("unknown transaction type \"{}\"", kind))
+                }
+            }
+            AmountColumns::Split { inflow, outflow } => {
+                // The unused side is normally blank (or "$0.00").
+                let side = |idx: usize| if get(idx).is_empty() { Ok(0.0) } else { amount(idx) };
+                let net = side(*inflow)? - side(*outflow)?;
+                let flow_type = if net < 0.0 { FlowType::Expense } else { FlowType::Income };
+                Ok((net.abs(), flow_type))
+            }
+        }
+    }
+}
+
+/// Runs `preset` over the CSV `text`. Fails outright only if the header row
+/// is missing or doesn't contain the columns the preset needs; individual
+/// bad rows end up in `ImportResult::errors`.
+pub fn import_csv(text: &str, preset: &ImportPreset) -> Result<ImportResult> {
+    let mut records = parse_csv(text).into_iter();
+    let header = records.next()
+        .ok_or_else(|| anyhow::anyhow!("CSV file is empty"))?;
+
+    let column_index = |name: &str| header.iter()
+        .position(|h| h.trim().eq_ignore_ascii_case(name));
+    let required = |name: &str| column_index(name)
+        .ok_or_else(|| anyhow::anyhow!("CSV file has no \"{}\" column (expected by the {} preset)", name, preset.name));
+
+    let date_idx = required(&preset.date_column)?;
+    let description_idx = required(&preset.description_column)?;
+    let amount_columns = match &preset.sign_convention {
+        SignConvention::NegativeIsExpense { amount_column } => AmountColumns::Signed(required(amount_column)?),
+        SignConvention::TypeColumn { amount_column, column, expense_value, income_value } => AmountColumns::Typed {
+            amount: required(amount_column)?,
+            flow_type: required(column)?,
+            expense_value,
+            income_value,
+        },
+        SignConvention::InflowOutflow { inflow_column, outflow_column } => AmountColumns::Split {
+            inflow: required(inflow_column)?,
+            outflow: required(outflow_column)?,
+        },
+    };
+    let category_idx = preset.category_column.as_deref().and_then(column_index);
+    let notes_idx = preset.notes_column.as_deref().and_then(column_index);
+
+    let mut result = ImportResult::default();
+    for (i, record) in records.enumerate() {
+        let line = i + 2; // 1-based, after the header
+        let get = |idx: usize| record.get(idx).map(|s| s.trim()).unwrap_or("");
+
+        let date = match NaiveDate::parse_from_str(get(date_idx), &preset.date_format) {
+            Ok(date) => date,
+            Err(_) => {
+                result.errors.push(format!("Line {}: invalid date \"{}\"", line, get(date_idx)));
+                continue;
+            }
+        };
+
+        let (raw_amount, flow_type) = match amount_columns.resolve(&get) {
+            Ok(resolved) => resolved,
+            Err(message) => {
+                result.errors.push(format!("Line {}: {}", line, message));
+                continue;
+            }
+        };
+
+        let optional = |idx: Option<usize>| idx
+            .map(get)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+
+        result.rows.push(ImportedRow {
+            date,
+            amount: raw_amount,
+            flow_type,
+            description: get(description_idx).to_string(),
+            source_category: optional(category_idx),
+            notes: optional(notes_idx),
+        });
+    }
+
+    Ok(result)
+}
+
+/// Value of the first `<tag>` in an OFX fragment. Works for both OFX 1.x
+/// (SGML, where leaf elements have no closing tag) and 2.x (XML), since the
+/// value runs up to the next `<` either way.
+fn ofx_value(fragment: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = fragment.find(&open)? + open.len();
+    let rest = &fragment[start..];
+    let value = rest[..rest.find('<').unwrap_or(rest.len())].trim();
+    (!value.is_empty()).then(|| value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&"))
+}
+
+/// Reads the `<STMTTRN>` transactions of an OFX (or QFX) bank statement.
+/// `TRNAMT` is signed like `SignConvention::NegativeIsExpense`; the
+/// description is the payee `NAME`, falling back to `MEMO`, and a memo that
+/// adds to the name is kept as notes. There's no source category in OFX, so
+/// rows get the catch-all suggestions. As with `import_csv`, bad
+/// transactions end up in `ImportResult::errors`.
+pub fn import_ofx(text: &str) -> Result<ImportResult> {
+    let blocks: Vec<&str> = text.split("<STMTTRN>").skip(1).collect();
+    if blocks.is_empty() {
+        return Err(anyhow::anyhow!("OFX file has no transactions"));
+    }
+
+    let mut result = ImportResult::default();
+    for (i, block) in blocks.into_iter().enumerate() {
+        let number = i + 1;
+        let block = &block[..block.find("</STMTTRN>").unwrap_or(block.len())];
+
+        // DTPOSTED is YYYYMMDD, optionally followed by a time and zone.
+        let posted = ofx_value(block, "DTPOSTED").unwrap_or_default();
+        let date = match posted.get(..8).and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok()) {
+            Some(date) => date,
+            None => {
+                result.errors.push(format!("Transaction {}: invalid date \"{}\"", number, posted));
+                continue;
+            }
+        };
+
+        let raw_amount = ofx_value(block, "TRNAMT").unwrap_or_default();
+        let Some(amount) = parse_amount(&raw_amount) else {
+            result.errors.push(format!("Transaction {}: invalid amount \"{}\"", number, raw_amount));
+            continue;
+        };
+
+        let name = ofx_value(block, "NAME");
+        let memo = ofx_value(block, "MEMO");
+        let (description, notes) = match name {
+            Some(name) => {
+                let notes = memo.filter(|m| *m != name);
+                (name, notes)
+            }
+            None => (memo.unwrap_or_default(), None),
+        };
+
+        result.rows.push(ImportedRow {
+            date,
+            amount: amount.abs(),
+            flow_type: if amount < 0.0 { FlowType::Expense } else { FlowType::Income },
+            description,
+            source_category: None,
+            notes,
+        });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::get_default_categories;
+
+    const MINT_CSV: &str = "\"Date\",\"Description\",\"Original Description\",\"Amount\",\"Transaction Type\",\"Category\",\"Account Name\",\"Labels\",\"Notes\"\r\n\
+\"1/15/2024\",\"Acme Corp\",\"ACME CORP PAYROLL\",\"2500.00\",\"credit\",\"Paycheck\",\"Checking\",\"\",\"\"\r\n\
+\"1/20/2024\",\"CVS\",\"CVS/PHARMACY #123\",\"42.10\",\"debit\",\"Pharmacy\",\"Visa\",\"\",\"refill, 90 days\"\r\n\
+\"1/22/2024\",\"Coffee Shop\",\"COFFEE\",\"4.50\",\"debit\",\"Coffee Shops\",\"Visa\",\"\",\"\"\r\n";
+
+    #[test]
+    fn parse_csv_handles_quotes_commas_and_escaped_quotes() {
+        let records = parse_csv("a,\"b,c\",\"say \"\"hi\"\"\"\n1,2,3\n");
+        assert_eq!(records, vec![
+            vec!["a".to_string(), "b,c".to_string(), "say \"hi\"".to_string()],
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+        ]);
+    }
+
+    #[test]
+    fn parse_csv_handles_crlf_missing_trailing_newline_and_blank_lines() {
+        let records = parse_csv("a,b\r\n\r\n1,2");
+        assert_eq!(records, vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["1".to_string(), "2".to_string()],
+        ]);
+    }
+
+    #[test]
+    fn parse_amount_accepts_currency_symbols_separators_and_parentheses() {
+        assert_eq!(parse_amount("$1,234.50"), Some(1234.5));
+        assert_eq!(parse_amount("-12.00"), Some(-12.0));
+        assert_eq!(parse_amount("(7.25)"), Some(-7.25));
+        assert_eq!(parse_amount("abc"), None);
+    }
+
+    #[test]
+    fn mint_preset_parses_dates_types_and_notes() {
+        let result = import_csv(MINT_CSV, &ImportPreset::mint()).unwrap();
+        assert!(result.errors.is_empty(), "unexpected errors: {:?}", result.errors);
+        assert_eq!(result.rows.len(), 3);
+
+        let paycheck = &result.rows[0];
+        assert_eq!(paycheck.date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(paycheck.amount, 2500.0);
+        assert_eq!(paycheck.flow_type, FlowType::Income);
+        assert_eq!(paycheck.source_category.as_deref(), Some("Paycheck"));
+
+        let pharmacy = &result.rows[1];
+        assert_eq!(pharmacy.flow_type, FlowType::Expense);
+        assert_eq!(pharmacy.notes.as_deref(), Some("refill, 90 days"));
+    }
+
+    #[test]
+    fn mint_preset_suggests_categories_with_type_aware_fallback() {
+        let preset = ImportPreset::mint();
+        let categories = get_default_categories();
+        let result = import_csv(MINT_CSV, &preset).unwrap();
+
+        let suggestions: Vec<Option<String>> = result.rows.iter()
+            .map(|row| row.suggested_category_id(&preset, &categories))
+            .collect();
+        assert_eq!(suggestions, vec![
+            Some("salary".to_string()),
+            Some("medical".to_string()),
+            Some("other_expense".to_string()), // no mapping for "Coffee Shops"
+        ]);
+    }
+
+    #[test]
+    fn rows_with_bad_values_are_reported_not_fatal() {
+        let csv = "Date,Description,Amount\n2024-01-01,Ok,-5\nnot-a-date,Bad,1\n2024-01-02,Bad amount,xyz\n";
+        let result = import_csv(csv, &ImportPreset::generic()).unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].flow_type, FlowType::Expense);
+        assert_eq!(result.rows[0].amount, 5.0);
+        assert_eq!(result.errors.len(), 2);
+        assert!(result.errors[0].starts_with("Line 3"));
+    }
+
+    const YNAB_CSV: &str = "\"Account\",\"Flag\",\"Date\",\"Payee\",\"Category Group/Category\",\"Category Group\",\"Category\",\"Memo\",\"Outflow\",\"Inflow\",\"Cleared\"\n\
+\"Checking\",\"\",\"02/01/2024\",\"Employer\",\"Inflow: Ready to Assign\",\"Inflow\",\"Ready to Assign\",\"\",\"$0.00\",\"$3,100.00\",\"Cleared\"\n\
+\"Checking\",\"\",\"02/03/2024\",\"Red Cross\",\"Giving: Charitable Giving\",\"Giving\",\"Charitable Giving\",\"annual\",\"$100.00\",\"$0.00\",\"Cleared\"\n\
+\"Checking\",\"\",\"02/04/2024\",\"Dr. Smith\",\"Health: Dental\",\"Health\",\"Dental\",\"\",\"$85.50\",\"\",\"Uncleared\"\n";
+
+    #[test]
+    fn ynab_preset_reads_split_inflow_outflow_columns() {
+        let result = import_csv(YNAB_CSV, &ImportPreset::ynab()).unwrap();
+        assert!(result.errors.is_empty(), "unexpected errors: {:?}", result.errors);
+
+        let amounts: Vec<(f64, FlowType)> = result.rows.iter()
+            .map(|row| (row.amount, row.flow_type.clone()))
+            .collect();
+        assert_eq!(amounts, vec![
+            (3100.0, FlowType::Income),
+            (100.0, FlowType::Expense),
+            (85.5, FlowType::Expense),
+        ]);
+        assert_eq!(result.rows[0].description, "Employer");
+        assert_eq!(result.rows[1].notes.as_deref(), Some("annual"));
+    }
+
+    #[test]
+    fn ynab_preset_maps_budget_categories() {
+        let preset = ImportPreset::ynab();
+        let categories = get_default_categories();
+        let result = import_csv(YNAB_CSV, &preset).unwrap();
+
+        let suggestions: Vec<Option<String>> = result.rows.iter()
+            .map(|row| row.suggested_category_id(&preset, &categories))
+            .collect();
+        assert_eq!(suggestions, vec![
+            Some("other_income".to_string()),
+            Some("cash_donations".to_string()),
+            Some("dental".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn ofx_statements_read_sgml_and_xml_transactions() {
+        let sgml = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST>\n\
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240115120000[-5:EST]<TRNAMT>-42.10<FITID>1<NAME>CVS &amp; Co<MEMO>refill\n\
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240131<TRNAMT>2500.00<FITID>2<NAME>ACME PAYROLL\n\
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>2024XX01<TRNAMT>-1.00<FITID>3<NAME>Bad\n\
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+        let result = import_ofx(sgml).unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.rows[0].date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!((result.rows[0].amount, result.rows[0].flow_type.clone()), (42.10, FlowType::Expense));
+        assert_eq!(result.rows[0].description, "CVS & Co");
+        assert_eq!(result.rows[0].notes.as_deref(), Some("refill"));
+        assert_eq!(result.rows[1].flow_type, FlowType::Income);
+
+        let xml = "<OFX><STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20240201</DTPOSTED>\
+<TRNAMT>-4.50</TRNAMT><MEMO>COFFEE</MEMO></STMTTRN></OFX>";
+        let result = import_ofx(xml).unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].description, "COFFEE");
+        assert_eq!(result.rows[0].notes, None);
+
+        assert!(import_ofx("Date,Description,Amount\n").is_err());
+    }
+
+    #[test]
+    fn missing_required_column_is_an_error() {
+        let csv = "Date,Description,Amount\n1/1/2024,x,1\n";
+        assert!(import_csv(csv, &ImportPreset::mint()).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Checks that the JSON stored alongside flows and categories still parses,
+//! run a batch at a time while the app is idle. A row whose JSON doesn't
+//! parse can't be loaded, so these catch damage (e.g. from editing the
+//! database by hand) before the next start fails on it.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::db::{Database, StoredJson};
+use crate::models::{CategoryField, JurisdictionTreatment};
+
+/// How many flows each step of a scan reads.
+pub const BATCH_SIZE: usize = 200;
+
+/// How long the app must go without input before a scan runs.
+pub const IDLE_AFTER: Duration = Duration::from_secs(30);
+
+/// How long after one scan finishes the next one starts.
+pub const SCAN_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// What's wrong with `stored`, if its JSON doesn't parse as the type its
+/// column holds.
+pub fn json_problem(stored: &StoredJson) -> Option<String> {
+    let parsed = match stored.column {
+        "linked_flows" => serde_json::from_str::<Vec<String>>(&stored.json).map(drop),
+        "custom_fields" => serde_json::from_str::<HashMap<String, String>>(&stored.json).map(drop),
+        "fields" => serde_json::from_str::<Vec<CategoryField>>(&stored.json).map(drop),
+        "tax_jurisdictions" => serde_json::from_str::<Vec<JurisdictionTreatment>>(&stored.json).map(drop),
+        _ => serde_json::from_str::<serde_json::Value>(&stored.json).map(drop),
+    };
+    parsed.err().map(|e| format!("{}: {} doesn't parse: {}", stored.owner, stored.column, e))
+}
+
+/// A scan in progress: categories first (there are few), then flows
+/// `BATCH_SIZE` at a time so no single step holds up the UI.
+#[derive(Debug, Default)]
+pub struct IntegrityScan {
+    categories_checked: bool,
+    /// The rowid of the last flow checked.
+    last_flow_rowid: i64,
+    finished: bool,
+    pub problems: Vec<String>,
+}
+
+impl IntegrityScan {
+    /// Checks the next batch. Returns whether the scan has finished.
+    pub fn step(&mut self, db: &Database) -> Result<bool> {
+        if !self.categories_checked {
+            self.problems.extend(db.category_json()?.iter().filter_map(json_problem));
+            self.categories_checked = true;
+            return Ok(false);
+        }
+        let (stored, last_rowid) = db.flow_json_after(self.last_flow_rowid, BATCH_SIZE)?;
+        self.problems.extend(stored.iter().filter_map(json_problem));
+        match last_rowid {
+            Some(rowid) => self.last_flow_rowid = rowid,
+            None => self.finished = true,
+        }
+        Ok(self.finished)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(column: &'static str, json: &str) -> StoredJson {
+        StoredJson { owner: "Flow a".to_string(), column, json: json.to_string() }
+    }
+
+    #[test]
+    fn json_problem_checks_each_column_against_its_own_type() {
+        assert_eq!(json_problem(&stored("custom_fields", r#"{"Vendor":"Acme"}"#)), None);
+        assert_eq!(json_problem(&stored("linked_flows", "[]")), None);
+        let problem = json_problem(&stored("custom_fields", r#"["Acme"]"#)).unwrap();
+        assert!(problem.starts_with("Flow a: custom_fields doesn't parse"), "{}", problem);
+        assert!(json_problem(&stored("linked_flows", "[\"a\"")).is_some(), "truncated JSON");
+    }
+}
@@ -0,0 +1,384 @@
+//! User-defined KPI cards for the top of the dashboard. Each card is a
+//! small metric built from a measure, the flows it covers and a period,
+//! e.g. "total of deductible flows, year to date".
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::locale::NumberFormat;
+use crate::models::{Category, Flow, FlowType};
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KpiMeasure {
+    Total,
+    /// The total spread over the calendar months the period has covered so
+    /// far, including the current one.
+    MonthlyAverage,
+    Count,
+    DaysSinceLast,
+}
+
+impl KpiMeasure {
+    pub const ALL: [KpiMeasure; 4] = [
+        KpiMeasure::Total,
+        KpiMeasure::MonthlyAverage,
+        KpiMeasure::Count,
+        KpiMeasure::DaysSinceLast,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            KpiMeasure::Total => "Total",
+            KpiMeasure::MonthlyAverage => "Monthly average",
+            KpiMeasure::Count => "Number of flows",
+            KpiMeasure::DaysSinceLast => "Days since last flow",
+        }
+    }
+}
+
+/// Which flows a card looks at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum KpiScope {
+    /// Every flow. Totals are net: income minus expenses.
+    AllFlows,
+    Income,
+    Expenses,
+    /// Flows marked tax deductible, net of their refunds (see
+    /// `utils::deductible_flows`).
+    Deductible,
+    Category(String),
+}
+
+impl KpiScope {
+    pub fn get_display_name(&self, categories: &[Category]) -> String {
+        match self {
+            KpiScope::AllFlows => "All flows".to_string(),
+            KpiScope::Income => "Income".to_string(),
+            KpiScope::Expenses => "Expenses".to_string(),
+            KpiScope::Deductible => "Deductible flows".to_string(),
+            KpiScope::Category(id) => categories.iter()
+                .find(|c| &c.id == id)
+                .map_or_else(|| id.clone(), |c| c.name.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KpiPeriod {
+    ThisMonth,
+    YearToDate,
+    /// The current month and the eleven before it.
+    Last12Months,
+    AllTime,
+}
+
+impl KpiPeriod {
+    pub const ALL: [KpiPeriod; 4] = [
+        KpiPeriod::ThisMonth,
+        KpiPeriod::YearToDate,
+        KpiPeriod::Last12Months,
+        KpiPeriod::AllTime,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            KpiPeriod::ThisMonth => "This month",
+            KpiPeriod::YearToDate => "Year to date",
+            KpiPeriod::Last12Months => "Last 12 months",
+            KpiPeriod::AllTime => "All time",
+        }
+    }
+
+    /// First day of the period as of `today`; `None` for all time.
+    fn start(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let month_start = today.with_day(1).unwrap();
+        match self {
+            KpiPeriod::ThisMonth => Some(month_start),
+            KpiPeriod::YearToDate => NaiveDate::from_ymd_opt(today.year(), 1, 1),
+            KpiPeriod::Last12Months => Some(month_start - Months::new(11)),
+            KpiPeriod::AllTime => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KpiCard {
+    pub label: String,
+    pub measure: KpiMeasure,
+    pub scope: KpiScope,
+    pub period: KpiPeriod,
+}
+
+impl Default for KpiCard {
+    fn default() -> Self {
+        Self {
+            label: "Spending this month".to_string(),
+            measure: KpiMeasure::Total,
+            scope: KpiScope::Expenses,
+            period: KpiPeriod::ThisMonth,
+        }
+    }
+}
+
+impl KpiCard {
+    /// Ready-made cards offered in the card editor.
+    pub fn examples() -> Vec<KpiCard> {
+        vec![
+            KpiCard {
+                label: "Deductible YTD".to_string(),
+                measure: KpiMeasure::Total,
+                scope: KpiScope::Deductible,
+                period: KpiPeriod::YearToDate,
+            },
+            KpiCard {
+                label: "Average monthly medical spend".to_string(),
+                measure: KpiMeasure::MonthlyAverage,
+                scope: KpiScope::Category("medical".to_string()),
+                period: KpiPeriod::Last12Months,
+            },
+            KpiCard {
+                label: "Days since last entry".to_string(),
+                measure: KpiMeasure::DaysSinceLast,
+                scope: KpiScope::AllFlows,
+                period: KpiPeriod::AllTime,
+            },
+        ]
+    }
+
+    /// The card's value as of `today`, ignoring flows dated later and
+    /// scheduled flows. `None` when there's nothing to measure, e.g. days
+    /// since the last flow when there are none.
+    pub fn evaluate(&self, flows: &[Flow], categories: &[Category], today: NaiveDate) -> Option<f64> {
+        let start = self.period.start(today);
+        let totals = KpiTotals::from_flows(flows, |f| f.date <= today && start.is_none_or(|start| f.date >= start));
+        self.evaluate_totals(&totals, categories, today)
+    }
+
+    /// The card's value from `totals` over its period up to `today`, e.g.
+    /// as summed by `Database::kpi_totals` for an "All time" card, rather
+    /// than from the flows themselves.
+    pub fn evaluate_totals(&self, totals: &KpiTotals, categories: &[Category], today: NaiveDate) -> Option<f64> {
+        let flow_type = |category_id: &str| categories.iter()
+            .find(|c| c.id == category_id)
+            .map(|c| c.flow_type.clone());
+        let by_category = match &self.scope {
+            KpiScope::Deductible => &totals.deductible_by_category,
+            _ => &totals.by_category,
+        };
+        let matching: Vec<(&String, &FlowAggregate)> = by_category.iter()
+            .filter(|(category_id, _)| match &self.scope {
+                KpiScope::AllFlows | KpiScope::Deductible => true,
+                KpiScope::Income => flow_type(category_id) == Some(FlowType::Income),
+                KpiScope::Expenses => flow_type(category_id) == Some(FlowType::Expense),
+                KpiScope::Category(id) => *category_id == id,
+            })
+            .collect();
+
+        let total = || -> f64 {
+            matching.iter()
+                .map(|(category_id, aggregate)| match (&self.scope, flow_type(category_id)) {
+                    (KpiScope::AllFlows, Some(FlowType::Expense)) => -aggregate.net_total,
+                    (KpiScope::AllFlows, None) => 0.0,
+                    _ => aggregate.net_total,
+                })
+                .sum()
+        };
+
+        match self.measure {
+            KpiMeasure::Total => Some(total()),
+            KpiMeasure::MonthlyAverage => {
+                // All time starts with the first matching flow.
+                let first = self.period.start(today).or_else(|| matching.iter().filter_map(|(_, a)| a.first).min())?;
+                let months = (today.year() - first.year()) * 12 + today.month() as i32 - first.month() as i32 + 1;
+                Some(total() / months.max(1) as f64)
+            }
+            KpiMeasure::Count => Some(matching.iter().map(|(_, a)| a.count).sum::<usize>() as f64),
+            KpiMeasure::DaysSinceLast => matching.iter()
+                .filter_map(|(_, a)| a.last)
+                .max()
+                .map(|last| (today - last).num_days() as f64),
+        }
+    }
+
+    /// `value` (from `evaluate`) as shown on the card.
+    pub fn format_value(&self, value: Option<f64>, number_format: &NumberFormat) -> String {
+        let Some(value) = value else {
+            return "—".to_string();
+        };
+        match self.measure {
+            KpiMeasure::Total | KpiMeasure::MonthlyAverage => number_format.format_currency(value),
+            KpiMeasure::Count => format!("{}", value as i64),
+            KpiMeasure::DaysSinceLast if value == 1.0 => "1 day".to_string(),
+            KpiMeasure::DaysSinceLast => format!("{} days", value as i64),
+        }
+    }
+}
+
+/// What a card measures over some flows, kept per category.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FlowAggregate {
+    /// The sum of `Flow::net_amount`.
+    pub net_total: f64,
+    pub count: usize,
+    pub first: Option<NaiveDate>,
+    pub last: Option<NaiveDate>,
+}
+
+impl FlowAggregate {
+    pub fn add(&mut self, flow: &Flow) {
+        self.merge(&FlowAggregate { net_total: flow.net_amount(), count: 1, first: Some(flow.date), last: Some(flow.date) });
+    }
+
+    pub fn merge(&mut self, other: &FlowAggregate) {
+        self.net_total += other.net_total;
+        self.count += other.count;
+        self.first = self.first.into_iter().chain(other.first).min();
+        self.last = self.last.into_iter().chain(other.last).max();
+    }
+}
+
+/// Everything a card needs from the flows in its period, so it can be
+/// worked out without them (see `KpiCard::evaluate_totals`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KpiTotals {
+    /// Every flow that isn't scheduled, by category id.
+    pub by_category: HashMap<String, FlowAggregate>,
+    /// Just the deductible ones (see `utils::deductible_flows`).
+    pub deductible_by_category: HashMap<String, FlowAggregate>,
+}
+
+impl KpiTotals {
+    /// Sums the flows `in_period` accepts. Whether a refund is deductible
+    /// is looked up among all of `flows`.
+    pub fn from_flows(flows: &[Flow], in_period: impl Fn(&Flow) -> bool) -> Self {
+        let mut totals = KpiTotals::default();
+        for flow in flows.iter().filter(|f| !f.scheduled && in_period(f)) {
+            totals.by_category.entry(flow.category_id.clone()).or_default().add(flow);
+        }
+        for flow in utils::deductible_flows(flows).into_iter().filter(|f| !f.scheduled && in_period(f)) {
+            totals.deductible_by_category.entry(flow.category_id.clone()).or_default().add(flow);
+        }
+        totals
+    }
+
+    /// Adds in totals over other flows.
+    pub fn merge(&mut self, other: &KpiTotals) {
+        for (category_id, aggregate) in &other.by_category {
+            self.by_category.entry(category_id.clone()).or_default().merge(aggregate);
+        }
+        for (category_id, aggregate) in &other.deductible_by_category {
+            self.deductible_by_category.entry(category_id.clone()).or_default().merge(aggregate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaxDeductionInfo;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn category(id: &str, flow_type: FlowType) -> Category {
+        Category {
+            id: id.to_string(),
+            name: id.to_string(),
+            flow_type,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: Vec::new(),
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    fn flow(category_id: &str, date: NaiveDate, amount: f64) -> Flow {
+        Flow {
+            id: uuid::Uuid::new_v4().to_string(),
+            date,
+            amount,
+            category_id: category_id.to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn example_cards_measure_deductions_averages_and_days_since_last_entry() {
+        let categories = vec![category("salary", FlowType::Income), category("medical", FlowType::Expense)];
+        let today = date(2024, 3, 20);
+        let mut deductible = flow("medical", date(2024, 2, 5), 300.0);
+        deductible.tax_deductible = Some(true);
+        let flows = vec![
+            flow("salary", date(2024, 3, 1), 4000.0),
+            flow("medical", date(2023, 12, 10), 150.0),
+            flow("medical", date(2024, 3, 15), 90.0),
+            // Not yet happened, so counts toward nothing.
+            flow("medical", date(2024, 3, 25), 1000.0),
+            deductible,
+        ];
+
+        let [deductible_ytd, medical_average, days_since] = KpiCard::examples().try_into().unwrap();
+        assert_eq!(deductible_ytd.evaluate(&flows, &categories, today), Some(300.0));
+        // Twelve months back from March 2024.
+        assert_eq!(medical_average.evaluate(&flows, &categories, today), Some(540.0 / 12.0));
+        assert_eq!(days_since.evaluate(&flows, &categories, today), Some(5.0));
+        assert_eq!(days_since.evaluate(&[], &categories, today), None);
+    }
+
+    #[test]
+    fn all_flows_total_is_net_and_all_time_average_starts_at_the_first_flow() {
+        let categories = vec![category("salary", FlowType::Income), category("rent", FlowType::Expense)];
+        let today = date(2024, 3, 20);
+        let flows = vec![
+            flow("salary", date(2024, 1, 1), 3000.0),
+            flow("rent", date(2024, 1, 2), 1000.0),
+            flow("rent", date(2024, 2, 2), 1000.0),
+        ];
+        let card = |measure, scope| KpiCard { label: String::new(), measure, scope, period: KpiPeriod::AllTime };
+
+        assert_eq!(card(KpiMeasure::Total, KpiScope::AllFlows).evaluate(&flows, &categories, today), Some(1000.0));
+        assert_eq!(card(KpiMeasure::Count, KpiScope::Expenses).evaluate(&flows, &categories, today), Some(2.0));
+        assert_eq!(card(KpiMeasure::MonthlyAverage, KpiScope::Expenses).evaluate(&flows, &categories, today), Some(2000.0 / 3.0));
+    }
+
+    #[test]
+    fn totals_summed_in_parts_give_the_same_values_as_the_flows() {
+        let categories = vec![category("salary", FlowType::Income), category("medical", FlowType::Expense)];
+        let today = date(2024, 3, 20);
+        let mut deductible = flow("medical", date(2019, 2, 5), 300.0);
+        deductible.tax_deductible = Some(true);
+        let mut refund = flow("medical", date(2020, 1, 5), 100.0);
+        refund.refund_of = Some(deductible.id.clone());
+        let flows = vec![
+            deductible,
+            refund,
+            flow("salary", date(2024, 3, 1), 4000.0),
+            flow("medical", date(2023, 12, 10), 150.0),
+        ];
+
+        let in_period = |f: &Flow| f.date <= today;
+        let mut totals = KpiTotals::from_flows(&flows[..2], in_period);
+        totals.merge(&KpiTotals::from_flows(&flows[2..], in_period));
+        for measure in KpiMeasure::ALL {
+            for scope in [KpiScope::AllFlows, KpiScope::Expenses, KpiScope::Deductible, KpiScope::Category("salary".to_string())] {
+                let card = KpiCard { label: String::new(), measure, scope, period: KpiPeriod::AllTime };
+                assert_eq!(card.evaluate_totals(&totals, &categories, today), card.evaluate(&flows, &categories, today), "{:?}", card);
+            }
+        }
+    }
+}
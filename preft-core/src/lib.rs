@@ -0,0 +1,60 @@
+//! Everything Preft does that isn't drawing windows: the data model, the
+//! SQLite database (with optional encryption), reporting, and import and
+//! export. The desktop app (`preft`) is a frontend over this crate, and
+//! command-line tools and tests can use it the same way.
+//!
+//! The usual entry point is [`db::Database`]: open the user's database
+//! with [`Database::new`](db::Database::new) (or wrap any connection with
+//! `new_for_test`), then load and save [`models::Category`] and
+//! [`models::Flow`] values through it. Everything else works on those
+//! loaded values rather than on the database:
+//!
+//! - [`reporting::ReportGenerator`] turns a [`reporting::ReportRequest`]
+//!   into PDF, CSV or spreadsheet bytes.
+//! - [`import`] parses bank CSV exports into flows, and [`export_bundle`]
+//!   writes and reads password-protected bundles of a whole dataset.
+//!   [`repro`] writes anonymized bundles for bug reports.
+//! - [`settings::UserSettings`] is stored in the database alongside the
+//!   data it describes.
+//!
+//! Writes to a flow in a locked year fail with [`db::locked_year_error`];
+//! `Database::load_locked_years` lists them up front.
+//!
+//! A frontend with a UI thread can hand the database to a
+//! [`db_worker::DbWorker`] so its queries and writes run on a thread of
+//! their own.
+
+// Data model and storage
+pub mod db;
+pub mod db_worker;
+pub mod encryption;
+pub mod encryption_config;
+pub mod flow_cache;
+pub mod integrity;
+pub mod models;
+pub mod pending_changes;
+pub mod settings;
+pub mod undo;
+
+// Reporting and analysis over loaded flows
+pub mod backup_diff;
+pub mod emergency;
+pub mod forecast;
+pub mod kpi;
+pub mod metrics;
+pub mod reporting;
+pub mod utils;
+pub mod year_grid;
+
+// Import, export and bulk changes
+pub mod bulk_edit;
+pub mod category_schema;
+pub mod export_bundle;
+pub mod import;
+pub mod repro;
+pub mod watch_folder;
+
+// Shared by the above and by frontends
+pub mod expression;
+pub mod locale;
+pub mod shortcuts;
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+/// How amounts are written: currency symbol and where it goes, and the
+/// thousands and decimal separators. Stored in `UserSettings` and used by
+/// both the flow tables and `ReportGenerator`. Amounts are always shown to
+/// two decimal places; stored values (including `Currency` custom fields)
+/// keep their US-style form regardless of this setting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumberFormat {
+    pub currency_symbol: String,
+    /// `1,234.56 €` rather than `€1,234.56`. A space separates a trailing
+    /// symbol from the number.
+    pub symbol_after: bool,
+    /// May be empty for no grouping.
+    pub thousands_separator: String,
+    pub decimal_separator: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            currency_symbol: "$".to_string(),
+            symbol_after: false,
+            thousands_separator: ",".to_string(),
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Common formats offered in the settings, by name.
+    pub fn presets() -> Vec<(&'static str, NumberFormat)> {
+        let preset = |symbol: &str, symbol_after: bool, thousands: &str, decimal: char| NumberFormat {
+            currency_symbol: symbol.to_string(),
+            symbol_after,
+            thousands_separator: thousands.to_string(),
+            decimal_separator: decimal,
+        };
+        vec![
+            ("United States ($1,234.56)", NumberFormat::default()),
+            ("United Kingdom (£1,234.56)", preset("£", false, ",", '.')),
+            ("Euro, German (1.234,56 €)", preset("€", true, ".", ',')),
+            ("Euro, French (1 234,56 €)", preset("€", true, "\u{a0}", ',')),
+            ("Euro, Irish (€1,234.56)", preset("€", false, ",", '.')),
+            ("Swiss (CHF 1'234.56)", preset("CHF ", false, "'", '.')),
+            ("India (₹1,234.56)", preset("₹", false, ",", '.')),
+        ]
+    }
+
+    /// Inserts thousands separators into a string of ASCII digits (no sign,
+    /// no decimal point), e.g. `"1234567"` -> `"1,234,567"`.
+    pub fn group_thousands(&self, digits: &str) -> String {
+        let mut grouped = String::new();
+        for (i, c) in digits.chars().rev().enumerate() {
+            if i > 0 && i % 3 == 0 {
+                grouped.extend(self.thousands_separator.chars().rev());
+            }
+            grouped.push(c);
+        }
+        grouped.chars().rev().collect()
+    }
+
+    /// A non-negative amount to two decimal places with grouped thousands,
+    /// e.g. `1234567.5` -> `"1,234,567.50"`. Rounds to the nearest cent the
+    /// same way `{:.2}` would.
+    pub fn format_grouped(&self, amount: f64) -> String {
+        let cents = (amount * 100.0).round() as i64;
+        format!("{}{}{:02}", self.group_thousands(&(cents / 100).to_string()), self.decimal_separator, cents % 100)
+    }
+
+    fn with_symbol(&self, number: &str) -> String {
+        if self.symbol_after {
+            format!("{} {}", number, self.currency_symbol)
+        } else {
+            format!("{}{}", self.currency_symbol, number)
+        }
+    }
+
+    /// e.g. `"$1,234.50"`, `"-$12.00"`, `"1.234,50 €"`.
+    pub fn format_currency(&self, amount: f64) -> String {
+        let sign = if amount < 0.0 { "-" } else { "" };
+        format!("{}{}", sign, self.with_symbol(&self.format_grouped(amount.abs())))
+    }
+
+    /// Like `format_currency`, but negatives in parentheses (accounting
+    /// style, e.g. `"($1,234.00)"`) instead of with a minus sign.
+    pub fn format_accounting(&self, amount: f64) -> String {
+        if amount < 0.0 {
+            format!("({})", self.with_symbol(&self.format_grouped(-amount)))
+        } else {
+            self.with_symbol(&self.format_grouped(amount))
+        }
+    }
+
+    /// Reads an amount typed in this format, with or without the currency
+    /// symbol and thousands separators, e.g. `"1.234,5 €"` -> `1234.5`.
+    pub fn parse_amount(&self, text: &str) -> Option<f64> {
+        let mut number = text.trim().replace(self.currency_symbol.trim(), "");
+        if !self.thousands_separator.is_empty() {
+            number = number.replace(self.thousands_separator.as_str(), "");
+        }
+        let number: String = number.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| if c == self.decimal_separator { '.' } else { c })
+            .collect();
+        number.parse::<f64>().ok().filter(|amount| amount.is_finite())
+    }
+
+    /// Whether amounts in both formats are in the same currency, and so can
+    /// be added up: the same symbol, ignoring surrounding spaces.
+    pub fn same_currency(&self, other: &NumberFormat) -> bool {
+        self.currency_symbol.trim() == other.currency_symbol.trim()
+    }
+
+    /// The equivalent Excel number format, with negatives in parentheses,
+    /// e.g. `"$"#,##0.00;("$"#,##0.00)`. Excel applies its own locale's
+    /// separators, so only the symbol and its placement carry over.
+    pub fn excel_currency_format(&self) -> String {
+        let symbol = format!("\"{}\"", self.currency_symbol.replace('"', ""));
+        let positive = if self.symbol_after {
+            format!("#,##0.00 {}", symbol)
+        } else {
+            format!("{}#,##0.00", symbol)
+        };
+        format!("{};({})", positive, positive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn german() -> NumberFormat {
+        NumberFormat::presets().into_iter().find(|(name, _)| name.contains("German")).unwrap().1
+    }
+
+    #[test]
+    fn default_is_us_style() {
+        let format = NumberFormat::default();
+        assert_eq!(format.format_currency(1234567.5), "$1,234,567.50");
+        assert_eq!(format.format_currency(-12.0), "-$12.00");
+        assert_eq!(format.format_accounting(-1234.0), "($1,234.00)");
+    }
+
+    #[test]
+    fn symbol_placement_and_separators_follow_the_format() {
+        let format = german();
+        assert_eq!(format.format_currency(1234.5), "1.234,50 €");
+        assert_eq!(format.format_currency(-0.5), "-0,50 €");
+        assert_eq!(format.format_accounting(-1234.5), "(1.234,50 €)");
+    }
+
+    #[test]
+    fn excel_format_keeps_symbol_placement() {
+        assert_eq!(NumberFormat::default().excel_currency_format(), "\"$\"#,##0.00;(\"$\"#,##0.00)");
+        assert_eq!(german().excel_currency_format(), "#,##0.00 \"€\";(#,##0.00 \"€\")");
+    }
+
+    #[test]
+    fn empty_or_multi_character_separators() {
+        let format = NumberFormat { thousands_separator: String::new(), ..NumberFormat::default() };
+        assert_eq!(format.format_grouped(1234567.0), "1234567.00");
+        let format = NumberFormat { thousands_separator: ", ".to_string(), ..NumberFormat::default() };
+        assert_eq!(format.group_thousands("1234567"), "1, 234, 567");
+    }
+
+    #[test]
+    fn parse_amount_reads_what_format_currency_writes() {
+        for format in NumberFormat::presets().into_iter().map(|(_, format)| format) {
+            assert_eq!(format.parse_amount(&format.format_currency(1234567.5)), Some(1234567.5));
+            assert_eq!(format.parse_amount(&format.format_currency(-12.0)), Some(-12.0));
+        }
+        assert_eq!(german().parse_amount("1.234,5"), Some(1234.5));
+        assert_eq!(NumberFormat::default().parse_amount("1250.5"), Some(1250.5));
+        assert_eq!(NumberFormat::default().parse_amount("twelve"), None);
+        assert_eq!(NumberFormat::default().parse_amount("inf"), None);
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::models::{Category, Flow, FlowType};
+
+/// All-time income minus expenses through the snapshot date. There are no
+/// account balances to sum, so this is the closest thing to net worth the
+/// flows themselves can give.
+pub const NET_WORTH: &str = "net_worth";
+pub const YTD_INCOME: &str = "ytd_income";
+pub const YTD_EXPENSE: &str = "ytd_expense";
+/// Prefix for per-category year-to-date totals, followed by the category id.
+pub const CATEGORY_PREFIX: &str = "category:";
+
+/// A point-in-time copy of key totals, persisted so long-term trends stay
+/// accurate even if the flows they were computed from are later archived or
+/// pruned. `period` names the week (`"2024-W07"`) or month (`"2024-02"`)
+/// the snapshot stands for; the snapshot for the current period is
+/// overwritten as it's re-taken, and stops changing once the period ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSnapshot {
+    pub period: String,
+    pub taken_on: NaiveDate,
+    pub metrics: Vec<(String, f64)>,
+}
+
+impl MetricSnapshot {
+    pub fn get(&self, metric: &str) -> Option<f64> {
+        self.metrics.iter().find(|(name, _)| name == metric).map(|(_, value)| *value)
+    }
+}
+
+/// The weekly and monthly periods `as_of` falls in, e.g.
+/// `["2024-W07", "2024-02"]`. Weeks are ISO weeks, so the week-based year
+/// can differ from the calendar year around New Year.
+pub fn snapshot_periods(as_of: NaiveDate) -> Vec<String> {
+    let week = as_of.iso_week();
+    vec![
+        format!("{}-W{:02}", week.year(), week.week()),
+        format!("{}-{:02}", as_of.year(), as_of.month()),
+    ]
+}
+
+/// Computes every snapshot metric as of `as_of`. Flows dated after `as_of`
+/// (and scheduled flows, via `Flow::net_amount`) don't count.
+pub fn compute_metrics_as_of(flows: &[Flow], categories: &[Category], as_of: NaiveDate) -> Vec<(String, f64)> {
+    let mut net_worth = 0.0;
+    let mut ytd_income = 0.0;
+    let mut ytd_expense = 0.0;
+    let mut category_totals: Vec<(String, f64)> = categories.iter()
+        .map(|c| (format!("{}{}", CATEGORY_PREFIX, c.id), 0.0))
+        .collect();
+
+    for flow in flows.iter().filter(|f| f.date <= as_of) {
+        // Flows whose category no longer exists have no sign to apply.
+        let Some((idx, category)) = categories.iter().enumerate().find(|(_, c)| c.id == flow.category_id) else {
+            continue;
+        };
+        let amount = flow.net_amount();
+        let this_year = flow.date.year() == as_of.year();
+
+        match category.flow_type {
+            FlowType::Income => {
+                net_worth += amount;
+                if this_year {
+                    ytd_income += amount;
+                }
+            }
+            FlowType::Expense => {
+                net_worth -= amount;
+                if this_year {
+                    ytd_expense += amount;
+                }
+            }
+        }
+        if this_year {
+            category_totals[idx].1 += amount;
+        }
+    }
+
+    let mut metrics = vec![
+        (NET_WORTH.to_string(), net_worth),
+        (YTD_INCOME.to_string(), ytd_income),
+        (YTD_EXPENSE.to_string(), ytd_expense),
+    ];
+    metrics.extend(category_totals);
+    metrics
+}
+
+/// Snapshots for every period `as_of` falls in, all sharing the same metrics.
+pub fn snapshots_as_of(flows: &[Flow], categories: &[Category], as_of: NaiveDate) -> Vec<MetricSnapshot> {
+    let metrics = compute_metrics_as_of(flows, categories, as_of);
+    snapshot_periods(as_of).into_iter()
+        .map(|period| MetricSnapshot { period, taken_on: as_of, metrics: metrics.clone() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaxDeductionInfo;
+    use std::collections::HashMap;
+
+    fn category(id: &str, flow_type: FlowType) -> Category {
+        Category {
+            id: id.to_string(),
+            name: id.to_string(),
+            flow_type,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: Vec::new(),
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    fn flow(category_id: &str, date: NaiveDate, amount: f64) -> Flow {
+        Flow {
+            id: uuid::Uuid::new_v4().to_string(),
+            date,
+            amount,
+            category_id: category_id.to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn snapshot_periods_names_the_iso_week_and_calendar_month() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 2, 14).unwrap();
+        assert_eq!(snapshot_periods(as_of), vec!["2024-W07", "2024-02"]);
+
+        // Dec 30, 2024 is in ISO week 1 of 2025.
+        let as_of = NaiveDate::from_ymd_opt(2024, 12, 30).unwrap();
+        assert_eq!(snapshot_periods(as_of), vec!["2025-W01", "2024-12"]);
+    }
+
+    #[test]
+    fn metrics_split_all_time_net_from_year_to_date_totals() {
+        let categories = vec![category("salary", FlowType::Income), category("rent", FlowType::Expense)];
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let flows = vec![
+            flow("salary", NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 1000.0),
+            flow("rent", NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 400.0),
+            flow("salary", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 2000.0),
+            flow("rent", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 500.0),
+            flow("salary", NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(), 9999.0), // after as_of
+        ];
+
+        let snapshot = &snapshots_as_of(&flows, &categories, as_of)[0];
+        assert_eq!(snapshot.get(NET_WORTH), Some(2100.0));
+        assert_eq!(snapshot.get(YTD_INCOME), Some(2000.0));
+        assert_eq!(snapshot.get(YTD_EXPENSE), Some(500.0));
+        assert_eq!(snapshot.get("category:rent"), Some(500.0));
+        assert_eq!(snapshot.get("category:salary"), Some(2000.0));
+    }
+}
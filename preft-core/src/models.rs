@@ -0,0 +1,976 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FlowType {
+    Income,
+    Expense,
+}
+
+impl std::fmt::Display for FlowType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlowType::Income => write!(f, "Income"),
+            FlowType::Expense => write!(f, "Expense"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaxDeductionInfo {
+    pub deduction_allowed: bool,
+    pub default_value: bool,
+    /// Per-jurisdiction overrides, for categories whose deductibility
+    /// differs between returns (e.g. deductible federally but not on the
+    /// state return). A jurisdiction with no entry here follows the flow's
+    /// own `tax_deductible` flag.
+    #[serde(default)]
+    pub jurisdictions: Vec<JurisdictionTreatment>,
+}
+
+impl TaxDeductionInfo {
+    /// Whether a deductible flow in this category counts toward
+    /// `jurisdiction`'s deductions.
+    pub fn deductible_in(&self, jurisdiction: &str) -> bool {
+        self.jurisdictions.iter()
+            .find(|t| t.jurisdiction == jurisdiction)
+            .is_none_or(|t| t.deductible)
+    }
+}
+
+/// How one tax jurisdiction (e.g. "Federal", "CA") treats a category.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JurisdictionTreatment {
+    pub jurisdiction: String,
+    pub deductible: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    pub flow_type: FlowType,
+    pub parent_id: Option<String>,
+    /// Position in category lists, lowest first; ties keep the order the
+    /// categories were added in.
+    #[serde(default)]
+    pub sort_order: i64,
+    /// Used for the category's name wherever it's listed, to tell
+    /// categories apart at a glance.
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+    /// An emoji or symbol shown before the name (see `label`).
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub fields: Vec<CategoryField>,
+    pub tax_deduction: TaxDeductionInfo,
+}
+
+impl Category {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            flow_type: FlowType::Income,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: Vec::new(),
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: false,
+                default_value: false,
+                jurisdictions: Vec::new(),
+            },
+        }
+    }
+
+    /// The name with the icon in front, if there is one.
+    pub fn label(&self) -> String {
+        match self.icon.as_deref().map(str::trim).filter(|icon| !icon.is_empty()) {
+            Some(icon) => format!("{} {}", icon, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Problems that should block saving this category: a blank name,
+    /// two fields with the same name (ignoring case), and anything wrong
+    /// with an individual field (see `CategoryField::validation_errors`).
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push("The category needs a name.".to_string());
+        }
+        let mut seen = std::collections::HashSet::new();
+        for field in &self.fields {
+            let key = field.name.trim().to_lowercase();
+            if !key.is_empty() && !seen.insert(key) {
+                errors.push(format!("More than one field is named \"{}\".", field.name.trim()));
+            }
+            errors.extend(field.validation_errors());
+        }
+        errors
+    }
+
+    /// The fields whose value in `custom_fields` a flow can't be saved
+    /// with, by field name, with why (see `CategoryField::value_error`).
+    pub fn field_errors(&self, custom_fields: &HashMap<String, String>) -> HashMap<String, String> {
+        self.fields.iter()
+            .filter_map(|field| {
+                field.value_error(custom_fields.get(&field.name).map(String::as_str))
+                    .map(|error| (field.name.clone(), error))
+            })
+            .collect()
+    }
+}
+
+/// Field names that would be confused with a flow's own columns (in the
+/// flow table and in CSV/XLSX exports), compared ignoring case.
+/// "description" isn't among them: the default "Other Expense" category
+/// has always had a field by that name.
+pub const RESERVED_FIELD_NAMES: [&str; 5] = ["id", "date", "amount", "category", "tax_deductible"];
+
+/// Custom field names that say who a flow was paid to or received from,
+/// compared ignoring case and in this order. Flows have no payee of their
+/// own; these are the names the default categories (and most users) use.
+pub const COUNTERPARTY_FIELD_NAMES: [&str; 6] = ["payee", "recipient", "provider", "employer", "payer", "source"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CategoryField {
+    pub name: String,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub default_value: Option<String>,
+}
+
+impl CategoryField {
+    /// A blank or reserved name, or a default value that isn't valid for
+    /// the field's type.
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let name = self.name.trim();
+        if name.is_empty() {
+            errors.push("Every field needs a name.".to_string());
+        } else if RESERVED_FIELD_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(name)) {
+            errors.push(format!("\"{}\" is reserved for a flow's own column; choose another field name.", name));
+        }
+        if let FieldType::Select(options) = &self.field_type {
+            if options.is_empty() {
+                errors.push(format!("\"{}\" needs at least one option.", name));
+            }
+            if options.iter().any(|option| option.trim().is_empty()) {
+                errors.push(format!("Every option of \"{}\" needs a name.", name));
+            }
+            let mut seen = std::collections::HashSet::new();
+            for option in options.iter().map(|option| option.trim()).filter(|option| !option.is_empty()) {
+                if !seen.insert(option.to_lowercase()) {
+                    errors.push(format!("\"{}\" has more than one option named \"{}\".", name, option));
+                }
+            }
+        }
+        if let Some(error) = self.default_value_error() {
+            errors.push(error);
+        }
+        errors
+    }
+
+    /// Why a flow can't be saved with `value` (its value for this field, if
+    /// it has one): a required field left blank, or a value that doesn't
+    /// fit the field's type, in the form the flow editor stores it. A
+    /// Boolean field always has a value, unticked being false.
+    pub fn value_error(&self, value: Option<&str>) -> Option<String> {
+        let value = value.map(str::trim).unwrap_or("");
+        if value.is_empty() {
+            return (self.required && self.field_type != FieldType::Boolean)
+                .then(|| format!("{} is required.", self.display_name()));
+        }
+        let expected = match &self.field_type {
+            FieldType::Text => return None,
+            FieldType::Integer => value.parse::<i64>().is_err().then_some("a whole number".to_string()),
+            #[allow(deprecated)]
+            FieldType::Float | FieldType::Number => value.parse::<f64>().is_err().then_some("a number".to_string()),
+            FieldType::Currency => value.replace(['$', ','], "").parse::<f64>().is_err().then_some("an amount".to_string()),
+            FieldType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_err().then_some("a date like 2024-01-31".to_string()),
+            FieldType::Boolean => value.parse::<bool>().is_err().then_some("true or false".to_string()),
+            FieldType::Select(options) => (!options.iter().any(|option| option == value))
+                .then(|| format!("one of: {}", options.join(", "))),
+        };
+        expected.map(|expected| format!("{} must be {}.", self.display_name(), expected))
+    }
+
+    /// Why the default value can't be used for this field's type, if it
+    /// can't. No default (or a blank one) is always fine.
+    pub fn default_value_error(&self) -> Option<String> {
+        let value = self.default_value.as_deref().map(str::trim).filter(|v| !v.is_empty())?;
+        let valid = match &self.field_type {
+            FieldType::Text => true,
+            FieldType::Integer => value.parse::<i64>().is_ok(),
+            #[allow(deprecated)]
+            FieldType::Float | FieldType::Number => value.parse::<f64>().is_ok(),
+            FieldType::Currency => value.replace(['$', ','], "").parse::<f64>().is_ok(),
+            FieldType::Boolean => matches!(value.to_lowercase().as_str(), "true" | "false"),
+            FieldType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            FieldType::Select(options) => options.iter().any(|option| option == value),
+        };
+        (!valid).then(|| format!(
+            "The default for \"{}\" isn't a valid {}: \"{}\"",
+            self.name.trim(),
+            self.field_type.get_display_name().to_lowercase(),
+            value,
+        ))
+    }
+
+    pub fn display_name(&self) -> String {
+        let first_char = self.name.chars().next();
+        let needs_formatting = match first_char {
+            Some(c) if c.is_alphabetic() => {
+                // Format if first char is lowercase or if there are underscores
+                !c.is_uppercase() || self.name.contains('_')
+            },
+            _ => false,
+        };
+
+        if needs_formatting {
+            self.name.split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        None => String::new(),
+                        Some(first) => first.to_uppercase().chain(chars).collect(),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// Select options renamed while a category is edited, per field, so that
+/// saving it can carry the flows' values over to the new names (see
+/// `Database::save_category_renaming_options`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptionRenames(HashMap<String, HashMap<String, String>>);
+
+impl OptionRenames {
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(HashMap::is_empty)
+    }
+
+    /// Records that `field`'s option `from` is now called `to`. Renaming an
+    /// option that was already renamed updates that rename instead, so
+    /// flows go straight from their stored value to the latest name.
+    pub fn rename(&mut self, field: &str, from: &str, to: &str) {
+        let renames = self.0.entry(field.to_string()).or_default();
+        match renames.values_mut().find(|renamed| *renamed == from) {
+            Some(renamed) => *renamed = to.to_string(),
+            None => {
+                renames.insert(from.to_string(), to.to_string());
+            }
+        }
+        renames.retain(|from, to| from != to);
+    }
+
+    /// The new name of the option a flow stored as `value`, if it was renamed.
+    pub fn get(&self, field: &str, value: &str) -> Option<&str> {
+        self.0.get(field)?.get(value).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FieldType {
+    Text,
+    #[deprecated(note = "Use Integer or Float instead. Kept for migration purposes only.")]
+    Number,
+    Integer,
+    Float,
+    Currency,
+    Date,
+    Boolean,
+    Select(Vec<String>),
+}
+
+impl FieldType {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            FieldType::Text => "Text",
+            FieldType::Integer => "Whole Number",
+            FieldType::Float => "Decimal Number",
+            FieldType::Currency => "Currency",
+            FieldType::Boolean => "Boolean",
+            FieldType::Date => "Date",
+            #[allow(deprecated)]
+            FieldType::Number => "Decimal Number",
+            FieldType::Select(_) => "Select",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Flow {
+    pub id: String,
+    pub date: NaiveDate,
+    pub amount: f64,
+    pub category_id: String,
+    pub description: String,
+    pub linked_flows: Vec<String>, // IDs of linked flows
+    pub custom_fields: HashMap<String, String>,
+    pub tax_deductible: Option<bool>, // Optional because not all flows are tax-deductible
+    #[serde(default)]
+    pub refund_of: Option<String>, // ID of the flow (same category) this flow refunds
+    #[serde(default)]
+    pub scheduled: bool, // Planned future flow, not yet counted in actual totals
+    /// The system's UTC offset, in seconds east of UTC, when the flow was
+    /// entered. `date` is a local date, so this says which timezone's
+    /// "today" it was taken from. `None` for flows entered before this was
+    /// recorded, and for imported flows, whose dates come from the file.
+    #[serde(default)]
+    pub created_utc_offset: Option<i32>,
+    /// Where the flow happened, e.g. a city or venue, as free text.
+    #[serde(default)]
+    pub location: Option<String>,
+    /// The `Trip` this flow belongs to, if any.
+    #[serde(default)]
+    pub trip_id: Option<String>,
+    /// Set on expenses that are to be paid back; `None` for everything else.
+    #[serde(default)]
+    pub reimbursement: Option<ReimbursementStatus>,
+    /// The income flow that paid this expense back, once it has been.
+    #[serde(default)]
+    pub reimbursed_by: Option<String>,
+}
+
+impl Flow {
+    pub fn is_refund(&self) -> bool {
+        self.refund_of.is_some()
+    }
+
+    /// The amount this flow contributes to its category's actual totals.
+    /// Amounts are stored as unsigned magnitudes, so a refund -- money
+    /// coming back against an earlier flow in the same category -- nets
+    /// against that category instead of adding to it. Scheduled flows
+    /// haven't happened yet and contribute nothing until confirmed.
+    pub fn net_amount(&self) -> f64 {
+        if self.scheduled { 0.0 } else { self.projected_amount() }
+    }
+
+    /// Like `net_amount`, but counting scheduled flows as if they'd already
+    /// happened -- for forecasts rather than actual totals.
+    pub fn projected_amount(&self) -> f64 {
+        if self.is_refund() { -self.amount } else { self.amount }
+    }
+
+    /// Whether this is a reimbursable expense that hasn't been paid back
+    /// yet, whether or not it's been submitted.
+    pub fn is_outstanding_reimbursement(&self) -> bool {
+        !self.scheduled && matches!(self.reimbursement, Some(ReimbursementStatus::Outstanding | ReimbursementStatus::Submitted))
+    }
+
+    /// Whether this is a scheduled flow whose date has arrived, so it should
+    /// now be treated as a real one.
+    pub fn is_due_as_of(&self, today: NaiveDate) -> bool {
+        self.scheduled && self.date <= today
+    }
+
+    /// Who this flow was paid to or received from, taken from the first
+    /// non-blank custom field named in `COUNTERPARTY_FIELD_NAMES`.
+    pub fn counterparty(&self) -> Option<&str> {
+        COUNTERPARTY_FIELD_NAMES.iter().find_map(|wanted| {
+            self.custom_fields.iter()
+                .find(|(name, value)| name.eq_ignore_ascii_case(wanted) && !value.trim().is_empty())
+                .map(|(_, value)| value.trim())
+        })
+    }
+}
+
+/// Custom fields whose values, taken together, may appear on only one flow
+/// in a category -- e.g. `tax_type` + `tax_year` in Taxes Paid.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UniquenessRule {
+    pub fields: Vec<String>,
+}
+
+impl UniquenessRule {
+    /// The first flow in `others` (other than `flow` itself) in the same
+    /// category with the same values for every field in the rule. Values
+    /// are compared trimmed and ignoring case, and a flow missing any of
+    /// the values can't conflict, so the rule only bites once it's filled in.
+    pub fn conflict<'a>(&self, flow: &Flow, others: &'a [Flow]) -> Option<&'a Flow> {
+        let values = |f: &Flow| -> Option<Vec<String>> {
+            self.fields.iter()
+                .map(|name| f.custom_fields.get(name)
+                    .map(|value| value.trim().to_lowercase())
+                    .filter(|value| !value.is_empty()))
+                .collect()
+        };
+        if self.fields.is_empty() {
+            return None;
+        }
+        let wanted = values(flow)?;
+        others.iter()
+            .filter(|other| other.id != flow.id && other.category_id == flow.category_id)
+            .find(|other| values(other).as_ref() == Some(&wanted))
+    }
+}
+
+/// How far a reimbursable expense (e.g. a work expense paid personally) has
+/// got in being paid back.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReimbursementStatus {
+    /// Not yet claimed.
+    Outstanding,
+    /// Claimed, awaiting payment.
+    Submitted,
+    Reimbursed,
+}
+
+impl ReimbursementStatus {
+    pub const ALL: [ReimbursementStatus; 3] = [
+        ReimbursementStatus::Outstanding,
+        ReimbursementStatus::Submitted,
+        ReimbursementStatus::Reimbursed,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            ReimbursementStatus::Outstanding => "Not submitted",
+            ReimbursementStatus::Submitted => "Submitted",
+            ReimbursementStatus::Reimbursed => "Reimbursed",
+        }
+    }
+}
+
+impl std::fmt::Display for ReimbursementStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReimbursementStatus::Outstanding => write!(f, "Outstanding"),
+            ReimbursementStatus::Submitted => write!(f, "Submitted"),
+            ReimbursementStatus::Reimbursed => write!(f, "Reimbursed"),
+        }
+    }
+}
+
+/// A named stretch of time, such as a vacation or a work trip, that flows
+/// can be assigned to for per-trip totals and expense reports.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Trip {
+    pub id: String,
+    pub name: String,
+    pub start_date: NaiveDate,
+    /// Inclusive.
+    pub end_date: NaiveDate,
+}
+
+impl Trip {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        (self.start_date..=self.end_date).contains(&date)
+    }
+}
+
+/// A named, read-only query over the database for slices the built-in
+/// reports don't offer. Run with `Database::run_sql_view`; saving one
+/// validates that it only reads.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SqlView {
+    pub name: String,
+    pub sql: String,
+}
+
+// Default categories that will be pre-defined
+pub fn get_default_categories() -> Vec<Category> {
+    vec![
+        Category {
+            id: "salary".to_string(),
+            name: "Salary".to_string(),
+            flow_type: FlowType::Income,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "employer".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "pay_period".to_string(),
+                    field_type: FieldType::Select(vec!["Monthly".to_string(), "Bi-weekly".to_string(), "Weekly".to_string()]),
+                    required: true,
+                    default_value: Some("Monthly".to_string()),
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: false,
+                default_value: false,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "passive_income".to_string(),
+            name: "Passive Income".to_string(),
+            flow_type: FlowType::Income,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "source".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "type".to_string(),
+                    field_type: FieldType::Select(vec!["Investment".to_string(), "Rental".to_string(), "Royalty".to_string(), "Other".to_string()]),
+                    required: true,
+                    default_value: None,
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: false,
+                default_value: false,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "taxes_paid".to_string(),
+            name: "Taxes Paid".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "tax_type".to_string(),
+                    field_type: FieldType::Select(vec!["Federal".to_string(), "State".to_string(), "Local".to_string(), "Property".to_string(), "Other".to_string()]),
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "tax_year".to_string(),
+                    field_type: FieldType::Integer,
+                    required: true,
+                    default_value: None,
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: true,
+                default_value: true,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "cash_donations".to_string(),
+            name: "Cash Donations".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "recipient".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: true,
+                default_value: true,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "in_kind_donations".to_string(),
+            name: "In-Kind Donations".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "recipient".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "item_description".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: true,
+                default_value: true,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "medical".to_string(),
+            name: "Medical".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "provider".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "type".to_string(),
+                    field_type: FieldType::Select(vec!["Doctor Visit".to_string(), "Prescription".to_string(), "Procedure".to_string(), "Equipment".to_string(), "Other".to_string()]),
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "insurance_covered".to_string(),
+                    field_type: FieldType::Boolean,
+                    required: true,
+                    default_value: Some("false".to_string()),
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: true,
+                default_value: true,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "dental".to_string(),
+            name: "Dental".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "provider".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "type".to_string(),
+                    field_type: FieldType::Select(vec!["Cleaning".to_string(), "Checkup".to_string(), "Procedure".to_string(), "Orthodontics".to_string(), "Other".to_string()]),
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "insurance_covered".to_string(),
+                    field_type: FieldType::Boolean,
+                    required: true,
+                    default_value: Some("false".to_string()),
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: true,
+                default_value: true,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "other_expense".to_string(),
+            name: "Other Expense".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "description".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "recurring".to_string(),
+                    field_type: FieldType::Boolean,
+                    required: true,
+                    default_value: Some("false".to_string()),
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: true,
+                default_value: false,
+                jurisdictions: Vec::new(),
+            },
+        },
+        Category {
+            id: "other_income".to_string(),
+            name: "Other Income".to_string(),
+            flow_type: FlowType::Income,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: vec![
+                CategoryField {
+                    name: "source".to_string(),
+                    field_type: FieldType::Text,
+                    required: true,
+                    default_value: None,
+                },
+                CategoryField {
+                    name: "recurring".to_string(),
+                    field_type: FieldType::Boolean,
+                    required: true,
+                    default_value: Some("false".to_string()),
+                },
+            ],
+            tax_deduction: TaxDeductionInfo {
+                deduction_allowed: false,
+                default_value: false,
+                jurisdictions: Vec::new(),
+            },
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn field(name: &str) -> CategoryField {
+        CategoryField {
+            name: name.to_string(),
+            field_type: FieldType::Text,
+            required: false,
+            default_value: None,
+        }
+    }
+
+    #[test]
+    fn display_name_leaves_capitalized_single_word_unchanged() {
+        assert_eq!(field("Employer").display_name(), "Employer");
+    }
+
+    #[test]
+    fn display_name_capitalizes_lowercase_single_word() {
+        assert_eq!(field("employer").display_name(), "Employer");
+    }
+
+    #[test]
+    fn display_name_formats_snake_case_to_title_case() {
+        assert_eq!(field("pay_period").display_name(), "Pay Period");
+    }
+
+    #[test]
+    fn display_name_formats_capitalized_snake_case() {
+        // Starts uppercase but still contains an underscore, so it still
+        // needs splitting/reformatting per the "contains '_'" rule.
+        assert_eq!(field("Tax_Year").display_name(), "Tax Year");
+    }
+
+    #[test]
+    fn display_name_leaves_non_alphabetic_leading_char_unchanged() {
+        assert_eq!(field("123abc").display_name(), "123abc");
+    }
+
+    #[test]
+    fn display_name_handles_empty_name() {
+        assert_eq!(field("").display_name(), "");
+    }
+
+    #[test]
+    fn default_categories_have_unique_non_empty_ids() {
+        let categories = get_default_categories();
+        assert!(!categories.is_empty());
+
+        let ids: HashSet<&str> = categories.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids.len(), categories.len(), "default category ids must be unique");
+        assert!(categories.iter().all(|c| !c.id.is_empty() && !c.name.is_empty()));
+    }
+
+    #[test]
+    fn default_categories_pass_validation() {
+        for category in get_default_categories() {
+            assert_eq!(category.validation_errors(), Vec::<String>::new(), "{}", category.name);
+        }
+    }
+
+    #[test]
+    fn select_fields_need_distinct_named_options() {
+        let select = |options: &[&str]| CategoryField {
+            field_type: FieldType::Select(options.iter().map(|o| o.to_string()).collect()),
+            ..field("kind")
+        };
+        assert!(select(&["Solo", "Couples"]).validation_errors().is_empty());
+        assert_eq!(select(&[]).validation_errors().len(), 1);
+        assert_eq!(select(&["Solo", " "]).validation_errors().len(), 1);
+        assert_eq!(select(&["Solo", "solo"]).validation_errors().len(), 1);
+    }
+
+    #[test]
+    fn required_fields_need_a_value_except_booleans() {
+        let required = |field_type| CategoryField { field_type, required: true, ..field("payee") };
+        assert_eq!(required(FieldType::Text).value_error(None).as_deref(), Some("Payee is required."));
+        assert!(required(FieldType::Text).value_error(Some("  ")).is_some());
+        assert_eq!(required(FieldType::Text).value_error(Some("Dr. Smith")), None);
+        assert_eq!(required(FieldType::Boolean).value_error(None), None);
+        assert_eq!(field("payee").value_error(None), None, "optional");
+    }
+
+    #[test]
+    fn values_must_fit_their_field_type() {
+        let typed = |field_type| CategoryField { field_type, ..field("count") };
+        assert_eq!(typed(FieldType::Integer).value_error(Some("2.5")).as_deref(), Some("Count must be a whole number."));
+        assert_eq!(typed(FieldType::Integer).value_error(Some("3")), None);
+        assert_eq!(typed(FieldType::Currency).value_error(Some("$1,250.50")), None);
+        assert!(typed(FieldType::Currency).value_error(Some("lots")).is_some());
+        assert!(typed(FieldType::Date).value_error(Some("03/01/2024")).is_some());
+        assert_eq!(
+            typed(FieldType::Select(vec!["Small".to_string(), "Large".to_string()])).value_error(Some("Medium")).as_deref(),
+            Some("Count must be one of: Small, Large."),
+        );
+    }
+
+    #[test]
+    fn option_renames_follow_an_option_through_several_renames() {
+        let mut renames = OptionRenames::default();
+        renames.rename("kind", "Solo", "Single");
+        renames.rename("kind", "Single", "Individual");
+        assert_eq!(renames.get("kind", "Solo"), Some("Individual"));
+        assert_eq!(renames.get("kind", "Single"), None);
+        renames.rename("kind", "Individual", "Solo");
+        assert!(renames.is_empty(), "renamed back to what it was");
+    }
+
+    #[test]
+    fn default_categories_include_both_flow_types() {
+        let categories = get_default_categories();
+        assert!(categories.iter().any(|c| c.flow_type == FlowType::Income));
+        assert!(categories.iter().any(|c| c.flow_type == FlowType::Expense));
+    }
+
+    fn flow(amount: f64, refund_of: Option<&str>) -> Flow {
+        Flow {
+            id: Uuid::new_v4().to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            amount,
+            category_id: "medical".to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: refund_of.map(|s| s.to_string()),
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn net_amount_is_negative_for_refunds_only() {
+        assert_eq!(flow(40.0, None).net_amount(), 40.0);
+        assert_eq!(flow(40.0, Some("original")).net_amount(), -40.0);
+    }
+
+    #[test]
+    fn scheduled_flows_count_toward_projections_but_not_actual_totals() {
+        let planned = Flow { scheduled: true, ..flow(40.0, None) };
+        assert_eq!(planned.net_amount(), 0.0);
+        assert_eq!(planned.projected_amount(), 40.0);
+    }
+
+    #[test]
+    fn scheduled_flow_is_due_once_its_date_arrives() {
+        let planned = Flow { scheduled: true, ..flow(40.0, None) };
+        assert!(!planned.is_due_as_of(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()));
+        assert!(planned.is_due_as_of(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()));
+        assert!(!flow(40.0, None).is_due_as_of(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()), "only scheduled flows become due");
+    }
+
+    #[test]
+    fn flow_without_refund_of_in_json_deserializes_as_not_a_refund() {
+        let json = r#"{"id":"f","date":"2024-01-01","amount":1.0,"category_id":"c","description":"","linked_flows":[],"custom_fields":{},"tax_deductible":null}"#;
+        let flow: Flow = serde_json::from_str(json).unwrap();
+        assert!(!flow.is_refund());
+    }
+
+    #[test]
+    fn category_new_defaults_to_income_with_no_tax_deduction() {
+        let category = Category::new("Freelance".to_string());
+        assert_eq!(category.name, "Freelance");
+        assert_eq!(category.flow_type, FlowType::Income);
+        assert!(category.fields.is_empty());
+        assert!(!category.tax_deduction.deduction_allowed);
+    }
+
+    #[test]
+    fn validation_errors_catch_duplicate_reserved_and_blank_names() {
+        let mut category = Category::new(" ".to_string());
+        category.fields = vec![field("vendor"), field("Vendor "), field("Amount"), field("")];
+
+        let errors = category.validation_errors();
+
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[0].contains("needs a name"));
+        assert!(errors[1].contains("More than one field is named \"Vendor\""));
+        assert!(errors[2].contains("\"Amount\" is reserved"));
+        assert!(errors[3].contains("Every field needs a name"));
+    }
+
+    #[test]
+    fn default_value_error_checks_the_default_against_the_field_type() {
+        let with_default = |field_type: FieldType, default: &str| CategoryField {
+            field_type,
+            default_value: Some(default.to_string()),
+            ..field("f")
+        };
+        assert!(with_default(FieldType::Integer, "12").default_value_error().is_none());
+        assert!(with_default(FieldType::Integer, "1.5").default_value_error().is_some());
+        assert!(with_default(FieldType::Currency, "$1,200.50").default_value_error().is_none());
+        assert!(with_default(FieldType::Date, "03/01/2024").default_value_error().is_some());
+        assert!(with_default(FieldType::Boolean, "TRUE").default_value_error().is_none());
+        assert!(with_default(FieldType::Select(vec!["A".to_string()]), "B").default_value_error().is_some());
+        assert!(with_default(FieldType::Date, "  ").default_value_error().is_none(), "a blank default means no default");
+    }
+
+    #[test]
+    fn deductible_in_follows_jurisdiction_overrides_and_defaults_to_deductible() {
+        let mut info = TaxDeductionInfo { deduction_allowed: true, default_value: true, jurisdictions: Vec::new() };
+        assert!(info.deductible_in("Federal"));
+
+        info.jurisdictions.push(JurisdictionTreatment { jurisdiction: "State".to_string(), deductible: false });
+        assert!(!info.deductible_in("State"));
+        assert!(info.deductible_in("Federal"));
+    }
+
+    #[test]
+    fn uniqueness_rule_conflicts_only_when_every_value_matches() {
+        let rule = UniquenessRule { fields: vec!["tax_type".to_string(), "tax_year".to_string()] };
+        let with_fields = |values: &[(&str, &str)]| Flow {
+            custom_fields: values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..flow(100.0, None)
+        };
+        let existing = with_fields(&[("tax_type", "Property"), ("tax_year", "2024")]);
+        let others = vec![existing.clone(), with_fields(&[("tax_type", "Income"), ("tax_year", "2024")])];
+
+        let duplicate = with_fields(&[("tax_type", " property "), ("tax_year", "2024")]);
+        assert_eq!(rule.conflict(&duplicate, &others).map(|f| &f.id), Some(&existing.id));
+        assert!(rule.conflict(&with_fields(&[("tax_type", "Property"), ("tax_year", "2023")]), &others).is_none());
+        assert!(rule.conflict(&with_fields(&[("tax_type", "Property")]), &others).is_none(), "incomplete values never conflict");
+        assert!(rule.conflict(&existing, &others).is_none(), "a flow doesn't conflict with itself");
+    }
+}
@@ -0,0 +1,244 @@
+//! Deferred writes for users who'd rather review their edits before they
+//! reach the database. With `UserSettings::deferred_writes` on, flow saves
+//! and deletes are queued here (and applied to the in-memory flows right
+//! away, so the tables and dashboard already show them) until the user
+//! picks Save All or Discard in the pending changes panel.
+
+use anyhow::Result;
+
+use crate::db::Database;
+use crate::models::Flow;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingChange {
+    SaveFlow(Flow),
+    /// Keeps the whole flow, not just its id, so the review panel can still
+    /// say what is being deleted.
+    DeleteFlow(Flow),
+}
+
+impl PendingChange {
+    pub fn flow(&self) -> &Flow {
+        match self {
+            PendingChange::SaveFlow(flow) | PendingChange::DeleteFlow(flow) => flow,
+        }
+    }
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            PendingChange::SaveFlow(_) => "Save",
+            PendingChange::DeleteFlow(_) => "Delete",
+        }
+    }
+}
+
+/// The changes waiting for Save All, in the order they were made.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingChanges {
+    changes: Vec<PendingChange>,
+}
+
+impl PendingChanges {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn changes(&self) -> &[PendingChange] {
+        &self.changes
+    }
+
+    /// Queues a save. A flow saved more than once keeps a single entry with
+    /// its latest contents.
+    pub fn save_flow(&mut self, flow: Flow) {
+        let existing = self.changes.iter_mut().find_map(|change| match change {
+            PendingChange::SaveFlow(queued) if queued.id == flow.id => Some(queued),
+            _ => None,
+        });
+        match existing {
+            Some(queued) => *queued = flow,
+            None => self.changes.push(PendingChange::SaveFlow(flow)),
+        }
+    }
+
+    /// Queues a delete, dropping any pending save of the same flow.
+    pub fn delete_flow(&mut self, flow: Flow) {
+        self.changes.retain(|change| change.flow().id != flow.id);
+        self.changes.push(PendingChange::DeleteFlow(flow));
+    }
+
+    /// Writes the changes in order. Stops at the first one the database
+    /// refuses (e.g. a flow in a locked year), leaving it and everything
+    /// after it pending. Returns how many were written.
+    pub fn save_all(&mut self, db: &Database) -> Result<usize> {
+        let mut written = 0;
+        while let Some(change) = self.changes.first() {
+            match change {
+                PendingChange::SaveFlow(flow) => db.save_flow(flow)?,
+                PendingChange::DeleteFlow(flow) => db.delete_flow(&flow.id)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?,
+            }
+            self.changes.remove(0);
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Drops the changes a copy of this queue managed to write, once its
+    /// `save_all` comes back. A change edited again in the meantime no
+    /// longer matches what was written, so it stays pending.
+    pub fn forget_written(&mut self, written: &[PendingChange]) {
+        self.changes.retain(|change| !written.contains(change));
+    }
+
+    /// Replays the changes over `flows`, e.g. ones just reloaded from the
+    /// database, so they show the pending edits again: a queued save
+    /// replaces the flow with the same id (or is added), a delete removes it.
+    pub fn apply_to(&self, flows: &mut Vec<Flow>) {
+        for change in &self.changes {
+            match change {
+                PendingChange::SaveFlow(flow) => match flows.iter_mut().find(|f| f.id == flow.id) {
+                    Some(existing) => *existing = flow.clone(),
+                    None => flows.push(flow.clone()),
+                },
+                PendingChange::DeleteFlow(flow) => flows.retain(|f| f.id != flow.id),
+            }
+        }
+    }
+
+    /// Forgets every pending change. The caller reloads its flows from the
+    /// database to undo them in memory.
+    pub fn discard(&mut self) {
+        self.changes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::get_default_categories;
+    use chrono::NaiveDate;
+    use rusqlite::Connection;
+    use std::collections::HashMap;
+
+    fn flow(id: &str, amount: f64) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            amount,
+            category_id: "other_expense".to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    fn test_db() -> Database {
+        let mut db = Database::new_for_test(Connection::open_in_memory().unwrap()).unwrap();
+        let category = get_default_categories().into_iter().find(|c| c.id == "other_expense").unwrap();
+        db.save_category(&category).unwrap();
+        db
+    }
+
+    fn summarize(pending: &PendingChanges) -> Vec<(&'static str, String, f64)> {
+        pending.changes().iter()
+            .map(|change| (change.get_display_name(), change.flow().id.clone(), change.flow().amount))
+            .collect()
+    }
+
+    #[test]
+    fn repeated_saves_collapse_and_deletes_drop_pending_saves() {
+        let mut pending = PendingChanges::default();
+        pending.save_flow(flow("a", 10.0));
+        pending.save_flow(flow("b", 20.0));
+        pending.save_flow(flow("a", 15.0));
+        assert_eq!(summarize(&pending), vec![
+            ("Save", "a".to_string(), 15.0),
+            ("Save", "b".to_string(), 20.0),
+        ]);
+
+        pending.delete_flow(flow("b", 20.0));
+        assert_eq!(summarize(&pending), vec![
+            ("Save", "a".to_string(), 15.0),
+            ("Delete", "b".to_string(), 20.0),
+        ]);
+    }
+
+    #[test]
+    fn nothing_is_written_until_save_all() {
+        let db = test_db();
+        db.save_flow(&flow("old", 5.0)).unwrap();
+
+        let mut pending = PendingChanges::default();
+        pending.save_flow(flow("new", 10.0));
+        pending.delete_flow(flow("old", 5.0));
+        let ids = |db: &Database| db.load_flows().unwrap().into_iter().map(|f| f.id).collect::<Vec<_>>();
+        assert_eq!(ids(&db), vec!["old".to_string()]);
+
+        assert_eq!(pending.save_all(&db).unwrap(), 2);
+        assert!(pending.is_empty());
+        assert_eq!(ids(&db), vec!["new".to_string()]);
+    }
+
+    #[test]
+    fn save_all_stops_at_a_refused_change_and_keeps_the_rest() {
+        let db = test_db();
+        db.lock_year(2024).unwrap();
+
+        let mut pending = PendingChanges::default();
+        let mut unlocked = flow("unlocked", 1.0);
+        unlocked.date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        pending.save_flow(unlocked);
+        pending.save_flow(flow("locked", 2.0));
+        pending.save_flow(flow("after", 3.0));
+
+        assert!(pending.save_all(&db).is_err());
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending.changes()[0].flow().id, "locked");
+        assert_eq!(db.load_flows().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn forgetting_written_changes_keeps_ones_edited_since() {
+        let mut pending = PendingChanges::default();
+        pending.save_flow(flow("a", 1.0));
+        pending.save_flow(flow("b", 2.0));
+        let written = pending.changes().to_vec();
+
+        pending.save_flow(flow("b", 3.0));
+        pending.save_flow(flow("c", 4.0));
+        pending.forget_written(&written);
+        assert_eq!(summarize(&pending), vec![
+            ("Save", "b".to_string(), 3.0),
+            ("Save", "c".to_string(), 4.0),
+        ]);
+    }
+
+    #[test]
+    fn applying_replays_saves_and_deletes_over_stored_flows() {
+        let mut pending = PendingChanges::default();
+        pending.save_flow(flow("edited", 2.0));
+        pending.save_flow(flow("new", 3.0));
+        pending.delete_flow(flow("deleted", 4.0));
+
+        let mut flows = vec![flow("edited", 1.0), flow("deleted", 4.0), flow("untouched", 5.0)];
+        pending.apply_to(&mut flows);
+        let summary: Vec<(String, f64)> = flows.iter().map(|f| (f.id.clone(), f.amount)).collect();
+        assert_eq!(summary, vec![
+            ("edited".to_string(), 2.0),
+            ("untouched".to_string(), 5.0),
+            ("new".to_string(), 3.0),
+        ]);
+    }
+}
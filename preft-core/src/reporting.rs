@@ -0,0 +1,3361 @@
+use chrono::{NaiveDate, Datelike};
+use std::collections::{HashMap, HashSet};
+use crate::locale::NumberFormat;
+use crate::models::{Category, CategoryField, FieldType, Flow, FlowType, TaxDeductionInfo};
+use crate::utils;
+use crate::year_grid::{MONTH_LABELS, YearGrid};
+use serde::{Deserialize, Serialize};
+use printpdf::*;
+use printpdf::indices::{PdfPageIndex, PdfLayerIndex};
+use rust_xlsxwriter::{ExcelDateTime, Format as XlsxFormat, Workbook, Worksheet, XlsxError};
+use std::io::{Cursor, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimePeriod {
+    LastYear,
+    ThisYear,
+    Custom(NaiveDate, NaiveDate),
+}
+
+impl TimePeriod {
+    /// Core of `Default::default`, parameterized on "today" so it's testable
+    /// without depending on the wall clock.
+    fn default_for(today: NaiveDate) -> Self {
+        // If we're significantly after tax time (after April), default to this year
+        // Otherwise default to last year
+        if today.month() > 4 {
+            TimePeriod::ThisYear
+        } else {
+            TimePeriod::LastYear
+        }
+    }
+
+    /// The first and last day of this period as of `today`, both included:
+    /// all of last year for `LastYear`, Jan 1 to today for `ThisYear`.
+    pub fn bounds(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            TimePeriod::LastYear => {
+                let start = NaiveDate::from_ymd_opt(today.year() - 1, 1, 1).unwrap();
+                (start, NaiveDate::from_ymd_opt(today.year() - 1, 12, 31).unwrap())
+            },
+            TimePeriod::ThisYear => (today.with_month(1).unwrap().with_day(1).unwrap(), today),
+            TimePeriod::Custom(start, end) => (*start, *end),
+        }
+    }
+
+    /// Whether `date` falls within this period, as of `today` (see
+    /// `bounds`).
+    fn contains(&self, date: NaiveDate, today: NaiveDate) -> bool {
+        let (start, end) = self.bounds(today);
+        date >= start && date <= end
+    }
+}
+
+impl Default for TimePeriod {
+    fn default() -> Self {
+        Self::default_for(chrono::Local::now().naive_local().date())
+    }
+}
+
+/// Label used to group flows that have no value set for the field being
+/// grouped by (either the field is missing entirely, or its value is empty/
+/// whitespace-only), so they still appear -- and count toward totals --
+/// instead of silently vanishing from the report.
+const UNSET_GROUP_LABEL: &str = "(Value not set)";
+
+/// Groups flows by the value of a custom field. Every flow passed in ends up
+/// in exactly one group -- flows with no meaningful value for the field are
+/// grouped together under `UNSET_GROUP_LABEL` rather than dropped, so the
+/// displayed group totals always sum to the category total shown on the
+/// same page.
+fn group_flows_by_field<'a>(flows: &[&'a Flow], field_name: &str) -> HashMap<String, Vec<&'a Flow>> {
+    let mut grouped: HashMap<String, Vec<&'a Flow>> = HashMap::new();
+    for flow in flows {
+        let key = match flow.custom_fields.get(field_name) {
+            Some(value) if !value.trim().is_empty() => value.clone(),
+            _ => UNSET_GROUP_LABEL.to_string(),
+        };
+        grouped.entry(key).or_default().push(flow);
+    }
+    grouped
+}
+
+/// Orders the keys of `present` (category ids with actual data to show)
+/// according to `order` (the category dropdown's order), appending any keys
+/// not found in `order` -- e.g. a category deleted after a flow referencing
+/// it was saved -- at the end, so nothing is silently dropped from the
+/// report just because it's no longer in the ordered list.
+fn ordered_category_ids<T>(order: &[String], present: &HashMap<String, T>) -> Vec<String> {
+    let mut ids: Vec<String> = order.iter()
+        .filter(|id| present.contains_key(*id))
+        .cloned()
+        .collect();
+    for id in present.keys() {
+        if !order.contains(id) {
+            ids.push(id.clone());
+        }
+    }
+    ids
+}
+
+/// Per-category info a report needs: display name and flow type (for netting
+/// the overall total), plus the category's custom field definitions (for
+/// rendering per-flow custom field columns, formatted according to each
+/// field's type).
+#[derive(Debug, Clone)]
+pub struct ReportCategoryInfo {
+    pub name: String,
+    pub flow_type: FlowType,
+    pub fields: Vec<CategoryField>,
+    pub tax_deduction: TaxDeductionInfo,
+    /// The category's own number format (see
+    /// `UserSettings::category_number_formats`); `None` uses the report's.
+    pub number_format: Option<NumberFormat>,
+    /// The category's color, used for its heading in the PDF and preview.
+    pub color: Option<[u8; 3]>,
+    /// The category's icon, shown before its name in the preview (the PDF's
+    /// builtin fonts can't draw emoji, so it's left out there).
+    pub icon: Option<String>,
+}
+
+impl From<&Category> for ReportCategoryInfo {
+    fn from(category: &Category) -> Self {
+        Self {
+            name: category.name.clone(),
+            flow_type: category.flow_type.clone(),
+            fields: category.fields.clone(),
+            tax_deduction: category.tax_deduction.clone(),
+            number_format: None,
+            color: category.color,
+            icon: category.icon.clone(),
+        }
+    }
+}
+
+impl ReportCategoryInfo {
+    pub fn with_number_format(mut self, number_format: Option<NumberFormat>) -> Self {
+        self.number_format = number_format;
+        self
+    }
+}
+
+/// Category totals in one currency, which can be added up together.
+struct CurrencyTotals<'a> {
+    format: &'a NumberFormat,
+    /// Appended to each total's label, e.g. " (€)", so totals in different
+    /// currencies are told apart; empty when the report has only one.
+    suffix: String,
+    totals: HashMap<String, f64>,
+}
+
+/// Nets per-category totals into a single overall total: Income category
+/// totals add, Expense category totals subtract. Flow amounts are stored as
+/// unsigned magnitudes (sign comes from the category's `FlowType`, the same
+/// convention `Dashboard::update_financial_summary_as_of` uses), so a plain
+/// sum of all category totals would overstate the result by counting
+/// expenses as if they were income. A category id with no matching entry in
+/// `categories` (e.g. the category was deleted after flows referencing it
+/// were saved) contributes nothing, consistent with how the dashboard skips
+/// such flows rather than guessing a sign for them.
+fn net_total(category_totals: &HashMap<String, f64>, categories: &HashMap<String, ReportCategoryInfo>) -> f64 {
+    category_totals.iter()
+        .map(|(category_id, total)| match categories.get(category_id) {
+            Some(info) if info.flow_type == FlowType::Income => *total,
+            Some(info) if info.flow_type == FlowType::Expense => -*total,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+/// Reverses the sign a category's raw (unsigned) total displays as in the
+/// report summary: Expense totals show as positive (their raw magnitude),
+/// Income totals show as negative. This is the opposite of standard
+/// accounting sign convention, deliberately: since the primary use case for
+/// this app is expense tracking, a net loss reading as a plain positive
+/// number (rather than a negative one) is the more useful default -- see the
+/// note printed alongside the summary table.
+fn summary_display_value(raw_total: f64, flow_type: &FlowType) -> f64 {
+    match flow_type {
+        FlowType::Income => -raw_total,
+        FlowType::Expense => raw_total,
+    }
+}
+
+/// Custom fields to show as report columns for a category: those in
+/// `selected_fields` (all of them when it's `None`, see
+/// `ReportRequest::selected_fields`), except the one currently selected as
+/// "Group By" (already shown as each group's section header, so repeating it
+/// per row would be redundant).
+fn visible_custom_fields<'a>(category_fields: &'a [CategoryField], group_by: &Option<String>, selected_fields: Option<&[String]>) -> Vec<&'a CategoryField> {
+    category_fields.iter()
+        .filter(|f| group_by.as_deref() != Some(f.name.as_str()))
+        .filter(|f| selected_fields.is_none_or(|selected| selected.contains(&f.name)))
+        .collect()
+}
+
+/// Whether the report's selected "Group By" field name is actually one of
+/// this category's own fields. A single "Group By" selection is shared
+/// across the whole report (it can span many categories with different
+/// field sets), so this decides, per category, whether to group at all --
+/// otherwise `group_flows_by_field` would silently drop every flow that
+/// doesn't have the field, which is *every* flow for a category that never
+/// had it, making that category's section show a total with zero detail
+/// rows to back it up. Categories where this is false render their flows
+/// normally (ungrouped), the same as when no "Group By" is selected.
+fn group_by_applies_to_category(group_by: &Option<String>, category_fields: &[CategoryField]) -> bool {
+    match group_by {
+        Some(name) => category_fields.iter().any(|f| &f.name == name),
+        None => false,
+    }
+}
+
+/// Formats a flow's custom field value for display, applying the same
+/// per-type formatting used elsewhere in the app (currency symbols,
+/// Yes/No for booleans, etc.) rather than printing the raw stored string.
+fn format_field_value(field: &CategoryField, flow: &Flow, format: &NumberFormat) -> String {
+    let Some(value) = flow.custom_fields.get(&field.name) else {
+        return String::new();
+    };
+    match field.field_type {
+        FieldType::Boolean => {
+            if value.parse::<bool>().unwrap_or(false) { "Yes".to_string() } else { "No".to_string() }
+        },
+        FieldType::Currency => {
+            match utils::parse_stored_currency(value) {
+                Some(num) => format.format_accounting(num),
+                None => value.clone(),
+            }
+        },
+        FieldType::Integer => value.parse::<i64>().map(|n| {
+            let sign = if n < 0 { "-" } else { "" };
+            format!("{}{}", sign, format.group_thousands(&n.unsigned_abs().to_string()))
+        }).unwrap_or_else(|_| value.clone()),
+        FieldType::Float => value.parse::<f64>().map(|n| {
+            let sign = if n < 0.0 { "-" } else { "" };
+            format!("{}{}", sign, format.format_grouped(n.abs()))
+        }).unwrap_or_else(|_| value.clone()),
+        _ => value.clone(),
+    }
+}
+
+/// Writes a custom field's stored value as the cell type matching the
+/// field: numbers for Integer/Float/Currency, a date for Date, a boolean
+/// for Boolean. Values that don't parse as their type are written as text
+/// rather than dropped.
+fn write_typed_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    field: &CategoryField,
+    value: &str,
+    currency: &XlsxFormat,
+    date_format: &XlsxFormat,
+) -> Result<(), XlsxError> {
+    match field.field_type {
+        FieldType::Currency => {
+            if let Some(num) = utils::parse_stored_currency(value) {
+                sheet.write_number_with_format(row, col, num, currency)?;
+                return Ok(());
+            }
+        }
+        FieldType::Integer | FieldType::Float => {
+            if let Ok(num) = value.parse::<f64>() {
+                sheet.write_number(row, col, num)?;
+                return Ok(());
+            }
+        }
+        FieldType::Boolean => {
+            if let Ok(flag) = value.parse::<bool>() {
+                sheet.write_boolean(row, col, flag)?;
+                return Ok(());
+            }
+        }
+        FieldType::Date => {
+            if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                && let Ok(date) = ExcelDateTime::from_ymd(date.year() as u16, date.month() as u8, date.day() as u8)
+            {
+                sheet.write_datetime_with_format(row, col, &date, date_format)?;
+                return Ok(());
+            }
+        }
+        _ => {}
+    }
+    sheet.write_string(row, col, value)?;
+    Ok(())
+}
+
+/// Appends one CSV record to `out`, quoting any field that contains a comma,
+/// quote, or line break (the same dialect `import::parse_csv` reads).
+pub(crate) fn push_csv_row(out: &mut String, fields: &[String]) {
+    let escaped: Vec<String> = fields.iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    out.push_str(&escaped.join(","));
+    out.push_str("\r\n");
+}
+
+/// Greedily wraps `text` into lines of at most `max_chars_per_line`
+/// (Unicode scalar count, not accounting for variable glyph widths -- an
+/// approximation, since exact text-width measurement isn't readily
+/// available from `printpdf` for externally-loaded fonts). A word longer
+/// than the limit gets its own line rather than being split mid-word.
+/// Always returns at least one (possibly empty) line.
+pub(crate) fn wrap_text(text: &str, max_chars_per_line: usize) -> Vec<String> {
+    let max_chars_per_line = max_chars_per_line.max(1);
+    let mut lines = Vec::new();
+    let mut current_line = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate_len = if current_line.is_empty() {
+            word.chars().count()
+        } else {
+            current_line.chars().count() + 1 + word.chars().count()
+        };
+
+        if current_line.is_empty() || candidate_len <= max_chars_per_line {
+            if !current_line.is_empty() {
+                current_line.push(' ');
+            }
+            current_line.push_str(word);
+        } else {
+            lines.push(std::mem::take(&mut current_line));
+            current_line.push_str(word);
+        }
+    }
+
+    if !current_line.is_empty() || lines.is_empty() {
+        lines.push(current_line);
+    }
+    lines
+}
+
+/// Rough character budget for a column of the given width, at the given
+/// font size, using an approximate average glyph width (~half the font
+/// size, a common heuristic for proportional fonts) rather than exact font
+/// metrics.
+pub(crate) fn max_chars_for_width(width_mm: f64, font_size_pt: f64) -> usize {
+    const PT_TO_MM: f64 = 0.3528;
+    let avg_char_width_mm = (font_size_pt * PT_TO_MM * 0.5).max(0.1);
+    ((width_mm / avg_char_width_mm).floor() as usize).max(4)
+}
+
+/// Estimated on-page width of `text` at `font_size_pt`, using the same
+/// average-glyph-width heuristic as `max_chars_for_width` -- `printpdf`
+/// doesn't expose font metrics for externally-loaded fonts, so there's no
+/// way to measure exact text width. Amount columns are mostly digits and a
+/// handful of narrow, fairly uniform-width symbols (`$`, `.`, `,`, `(`, `)`),
+/// so this estimate is close enough to right-align them without the visible
+/// jitter it would cause on free-form text like Description.
+fn estimate_text_width_mm(text: &str, font_size_pt: f64) -> f64 {
+    const PT_TO_MM: f64 = 0.3528;
+    let avg_char_width_mm = (font_size_pt * PT_TO_MM * 0.5).max(0.1);
+    text.chars().count() as f64 * avg_char_width_mm
+}
+
+/// X position (mm from the page's left edge) to draw `text` at so its right
+/// edge lands approximately at `right_edge_mm` -- see `estimate_text_width_mm`
+/// for why this is an estimate, not an exact measurement.
+fn right_align_x(text: &str, right_edge_mm: f64, font_size_pt: f64) -> f64 {
+    right_edge_mm - estimate_text_width_mm(text, font_size_pt)
+}
+
+/// Like `right_align_x`, but never returns a position left of `left_edge_mm`.
+/// The width estimate it's built on is a heuristic (`estimate_text_width_mm`),
+/// not an exact measurement -- for an unusually wide value (or just an
+/// underestimate), a plain `right_align_x` could push text left into the
+/// previous column entirely. This floors it at the column's own left edge
+/// instead: for an amount too wide to right-align cleanly, drawing it flush
+/// against the column boundary is a better failure mode than overlapping
+/// the Date/Category column to its left.
+fn right_align_x_clamped(text: &str, right_edge_mm: f64, left_edge_mm: f64, font_size_pt: f64) -> f64 {
+    right_align_x(text, right_edge_mm, font_size_pt).max(left_edge_mm)
+}
+
+/// X position to center `text` between `left_edge_mm` and `right_edge_mm`,
+/// floored at `left_edge_mm` the same way `right_align_x_clamped` is. Used
+/// for column *header* labels rather than `right_align_x_clamped`: headers
+/// are drawn in the bold header font, and `estimate_text_width_mm`'s
+/// character-width heuristic was tuned against amount values (mostly
+/// digits/currency symbols in the regular body font, narrower on average
+/// than bold word text), so it systematically underestimates a bold header
+/// label's actual width. Centering makes that underestimate much less
+/// visible -- the error splits across both sides of the column instead of
+/// being dumped entirely into the neighboring column the way a hard
+/// right-align would.
+fn center_align_x(text: &str, left_edge_mm: f64, right_edge_mm: f64, font_size_pt: f64) -> f64 {
+    let width = estimate_text_width_mm(text, font_size_pt);
+    let center = (left_edge_mm + right_edge_mm) / 2.0;
+    (center - width / 2.0).max(left_edge_mm)
+}
+
+/// Body text shrinks as more custom-field columns need to fit alongside
+/// Date/Amount/Description, since `printpdf` lays out text at fixed
+/// coordinates with no automatic column sizing. Grouped sections shrink a
+/// bit further still (regardless of extra-column count), since group
+/// headers and group totals add extra vertical density on top of the rows
+/// themselves.
+fn body_font_size_for_extra_columns(extra_column_count: usize, is_grouped: bool) -> f64 {
+    let base: f64 = match extra_column_count {
+        0 => 12.0,
+        1..=2 => 10.0,
+        3..=4 => 8.5,
+        _ => 7.0,
+    };
+    if is_grouped {
+        (base - 1.5).max(6.0)
+    } else {
+        base
+    }
+}
+
+/// X positions (mm from the page's left edge) and widths for the flow table's
+/// columns: fixed-width Date/Amount, then Description and any extra custom
+/// field columns sharing the remaining page width equally.
+struct ColumnLayout {
+    date_x: f64,
+    amount_x: f64,
+    /// Right edge to align amount text to within the Amount column (a few mm
+    /// before `description_x`, so a right-aligned amount doesn't butt right
+    /// up against the Description column).
+    amount_right_edge_x: f64,
+    description_x: f64,
+    column_width: f64,
+    extra_field_x: Vec<f64>,
+}
+
+fn compute_column_layout(extra_field_count: usize, page_width_mm: f64) -> ColumnLayout {
+    const LEFT_MARGIN_MM: f64 = 20.0;
+    const RIGHT_MARGIN_MM: f64 = 10.0;
+    const DATE_WIDTH_MM: f64 = 35.0;
+    const AMOUNT_WIDTH_MM: f64 = 25.0;
+    const AMOUNT_COLUMN_GAP_MM: f64 = 3.0;
+    const MIN_REMAINING_WIDTH_MM: f64 = 20.0;
+
+    let date_x = LEFT_MARGIN_MM;
+    let amount_x = date_x + DATE_WIDTH_MM;
+    let description_x = amount_x + AMOUNT_WIDTH_MM;
+    let amount_right_edge_x = description_x - AMOUNT_COLUMN_GAP_MM;
+    let remaining_width = (page_width_mm - RIGHT_MARGIN_MM - description_x).max(MIN_REMAINING_WIDTH_MM);
+    let column_count = (extra_field_count + 1) as f64; // Description + extras
+    let column_width = remaining_width / column_count;
+
+    let extra_field_x = (0..extra_field_count)
+        .map(|i| description_x + column_width * (i as f64 + 1.0))
+        .collect();
+
+    ColumnLayout {
+        date_x,
+        amount_x,
+        amount_right_edge_x,
+        description_x,
+        column_width,
+        extra_field_x,
+    }
+}
+
+/// Line height in mm at the given font size, and the per-line character
+/// budget for the flow table's shared column width. Shared by `row_height_mm`
+/// (checking page space before drawing a row) and `render_flow_row` (actually
+/// drawing it), so both agree on how a row will be laid out.
+fn row_wrap_metrics(layout: &ColumnLayout, body_size: f64) -> (f64, usize) {
+    const PT_TO_MM: f64 = 0.3528;
+    let line_height = body_size * PT_TO_MM * 1.3;
+    let max_chars = max_chars_for_width(layout.column_width, body_size);
+    (line_height, max_chars)
+}
+
+/// Height in mm a flow's row will need once word-wrapped -- how much
+/// vertical space to check for (via `PageCursor::ensure_space`) before drawing it.
+fn row_height_mm(flow: &Flow, visible_fields: &[&CategoryField], layout: &ColumnLayout, body_size: f64, format: &NumberFormat) -> f64 {
+    let (line_height, max_chars) = row_wrap_metrics(layout, body_size);
+
+    let description_line_count = wrap_text(&flow.description, max_chars).len();
+    let field_line_count = visible_fields.iter()
+        .map(|field| wrap_text(&format_field_value(field, flow, format), max_chars).len())
+        .max()
+        .unwrap_or(1);
+
+    let row_line_count = description_line_count.max(field_line_count).max(1);
+    line_height * row_line_count as f64 + 2.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            PaperSize::A4 => "A4",
+            PaperSize::Letter => "US Letter",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PageOrientation {
+    #[default]
+    Portrait,
+    /// Wider pages, so tables with many custom field columns get more room
+    /// per column.
+    Landscape,
+}
+
+impl PageOrientation {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            PageOrientation::Portrait => "Portrait",
+            PageOrientation::Landscape => "Landscape",
+        }
+    }
+}
+
+/// Page dimensions (mm) for a report, and the positions that depend on
+/// them. Horizontal positions are measured from the left edge and vertical
+/// ones from the bottom (as in `printpdf`), so only things anchored to the
+/// right or top edge move when the size changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PageGeometry {
+    width_mm: f64,
+    height_mm: f64,
+}
+
+impl Default for PageGeometry {
+    fn default() -> Self {
+        Self::new(PaperSize::A4, PageOrientation::Portrait)
+    }
+}
+
+impl PageGeometry {
+    fn new(paper: PaperSize, orientation: PageOrientation) -> Self {
+        let (short, long) = match paper {
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::Letter => (215.9, 279.4),
+        };
+        match orientation {
+            PageOrientation::Portrait => Self { width_mm: short, height_mm: long },
+            PageOrientation::Landscape => Self { width_mm: long, height_mm: short },
+        }
+    }
+
+    /// Where content starts on a freshly created page. Kept close to the
+    /// page edge (a ~22mm top margin) with a small gap below the
+    /// period-label chrome drawn at `header_chrome_y`.
+    fn content_top(&self) -> f64 {
+        self.height_mm - 22.0
+    }
+
+    fn header_chrome_y(&self) -> f64 {
+        self.height_mm - 9.0
+    }
+
+    /// The cover page was laid out for A4 portrait; its fixed heights are
+    /// scaled to other page heights so it keeps its proportions.
+    fn cover_y(&self, a4_portrait_y: f64) -> Mm {
+        Mm(a4_portrait_y * self.height_mm / 297.0)
+    }
+}
+
+/// Content may not be drawn below this y position (an ~18mm bottom margin,
+/// leaving room above the page-number chrome at `FOOTER_CHROME_Y_MM`).
+const BOTTOM_MARGIN_MM: f64 = 18.0;
+const CHROME_FONT_SIZE: f64 = 8.0;
+const FOOTER_CHROME_Y_MM: f64 = 12.0;
+
+/// Draws the small-font period label (top margin) and page number (bottom
+/// margin) that appear on every page of the report -- the cover page, every
+/// category page, and any page created mid-content by pagination.
+fn draw_page_chrome(layer: &PdfLayerReference, geometry: &PageGeometry, page_number: usize, time_period_text: &str, chrome_font: &IndirectFontRef) {
+    layer.use_text(time_period_text, CHROME_FONT_SIZE, Mm(20.0), Mm(geometry.header_chrome_y()), chrome_font);
+    layer.use_text(&format!("Page {}", page_number), CHROME_FONT_SIZE, Mm(20.0), Mm(FOOTER_CHROME_Y_MM), chrome_font);
+}
+
+/// A table's column headings, redrawn at the top of every page the table
+/// runs onto so a continuation page can be read without flipping back.
+struct TableHeader {
+    /// Shown above the headings on continuation pages only, e.g.
+    /// "Category: Rent (continued)".
+    continued_title: Option<String>,
+    /// Each heading and the x position (mm) it's drawn at.
+    columns: Vec<(String, f64)>,
+    font_size: f64,
+    /// Space below the headings before the separator line.
+    spacing_mm: f64,
+    font: IndirectFontRef,
+}
+
+/// Tracks the current page/layer/y-position while rendering the report body,
+/// and centralizes page creation so every new page (whether forced, e.g. one
+/// category per page, or triggered by running out of room) gets the same
+/// period/page-number chrome and content-top position -- plus the current
+/// table's headings, if a table is in progress.
+struct PageCursor<'a> {
+    doc: &'a PdfDocumentReference,
+    page: PdfPageIndex,
+    layer_idx: PdfLayerIndex,
+    geometry: PageGeometry,
+    y_pos: Mm,
+    page_number: usize,
+    time_period_text: &'a str,
+    chrome_font: &'a IndirectFontRef,
+    table_header: Option<TableHeader>,
+}
+
+impl<'a> PageCursor<'a> {
+    fn layer(&self) -> PdfLayerReference {
+        self.doc.get_page(self.page).get_layer(self.layer_idx)
+    }
+
+    /// Unconditionally starts a fresh page, e.g. so each category begins on
+    /// its own page rather than possibly sharing one with the previous
+    /// category's tail end. Ends any table in progress.
+    fn start_new_page(&mut self) -> PdfLayerReference {
+        self.table_header = None;
+        self.add_page()
+    }
+
+    fn add_page(&mut self) -> PdfLayerReference {
+        let (page, layer_idx) = self.doc.add_page(Mm(self.geometry.width_mm), Mm(self.geometry.height_mm), "Layer 1");
+        self.page = page;
+        self.layer_idx = layer_idx;
+        self.y_pos = Mm(self.geometry.content_top());
+        self.page_number += 1;
+        let layer = self.layer();
+        draw_page_chrome(&layer, &self.geometry, self.page_number, self.time_period_text, self.chrome_font);
+        layer
+    }
+
+    /// Starts a fresh page only if `needed_height_mm` more content wouldn't
+    /// fit above the bottom margin; otherwise returns the current layer
+    /// unchanged. A page started mid-table repeats the table's headings.
+    fn ensure_space(&mut self, needed_height_mm: f64) -> PdfLayerReference {
+        if self.y_pos.0 - needed_height_mm < BOTTOM_MARGIN_MM {
+            let layer = self.add_page();
+            if let Some(header) = self.table_header.take() {
+                if let Some(title) = &header.continued_title {
+                    layer.use_text(title, 12.0, Mm(20.0), self.y_pos, &header.font);
+                    self.y_pos -= Mm(10.0);
+                }
+                self.draw_headings(&layer, &header);
+                self.table_header = Some(header);
+            }
+            layer
+        } else {
+            self.layer()
+        }
+    }
+
+    /// Draws `header`'s headings at the current position, and keeps them
+    /// for any page the table runs onto until `end_table` or the next
+    /// `start_new_page`.
+    fn begin_table(&mut self, header: TableHeader) {
+        let layer = self.layer();
+        self.draw_headings(&layer, &header);
+        self.table_header = Some(header);
+    }
+
+    /// Stops repeating the current table's headings, for content that
+    /// follows a table on the same page (e.g. the summary's closing totals).
+    fn end_table(&mut self) {
+        self.table_header = None;
+    }
+
+    fn draw_headings(&mut self, layer: &PdfLayerReference, header: &TableHeader) {
+        for (text, x) in &header.columns {
+            layer.use_text(text, header.font_size, Mm(*x), self.y_pos, &header.font);
+        }
+        self.y_pos -= Mm(header.spacing_mm);
+        layer.add_line_break();
+        self.y_pos -= Mm(5.0);
+    }
+}
+
+/// How many of the monthly breakdown's 13 amount columns (12 months plus
+/// Total) fit across a page `page_width_mm` wide -- 7 on a portrait page;
+/// the rest continue on the next.
+fn monthly_columns_per_page(page_width_mm: f64) -> usize {
+    const MIN_COLUMN_WIDTH_MM: f64 = 18.0;
+    let available = page_width_mm - MONTH_TABLE_RIGHT_MARGIN_MM - MONTH_TABLE_FIRST_AMOUNT_X;
+    ((available / MIN_COLUMN_WIDTH_MM).floor() as usize).clamp(1, MONTH_LABELS.len() + 1)
+}
+
+const MONTH_TABLE_FIRST_AMOUNT_X: f64 = 62.0;
+const MONTH_TABLE_RIGHT_MARGIN_MM: f64 = 20.0;
+
+/// Per-calendar-month totals of `flows`, January first, followed by the
+/// overall total (so 13 values). A period spanning more than one year folds
+/// every January into one column, and so on. The overall total is computed
+/// the same way as a category total, so the two always agree.
+fn monthly_totals(flows: &[&Flow], rounding: &RoundingRule) -> Vec<f64> {
+    let mut months: Vec<Vec<f64>> = vec![Vec::new(); 12];
+    for flow in flows {
+        months[flow.date.month0() as usize].push(flow.net_amount());
+    }
+    let mut totals: Vec<f64> = months.into_iter().map(|amounts| rounding.total(amounts)).collect();
+    totals.push(rounding.total(flows.iter().map(|f| f.net_amount())));
+    totals
+}
+
+pub(crate) fn save_pdf(doc: PdfDocumentReference) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut buffer = Vec::new();
+    {
+        let mut writer = BufWriter::new(&mut buffer);
+        doc.save(&mut writer)?;
+    }
+    Ok(buffer)
+}
+
+/// Draws one flow's row: Date and Amount (always one line), then Description
+/// and each visible custom field, word-wrapped to fit their column width.
+/// Advances `y_pos` past however many wrapped lines the tallest column in
+/// this row needed. Caller is responsible for calling `PageCursor::ensure_space`
+/// (with `row_height_mm`'s result) first, and passing in whatever `layer`
+/// that returns.
+#[allow(clippy::too_many_arguments)]
+fn render_flow_row(
+    layer: &PdfLayerReference,
+    flow: &Flow,
+    visible_fields: &[&CategoryField],
+    layout: &ColumnLayout,
+    body_size: f64,
+    body_font: &IndirectFontRef,
+    rounding: &RoundingRule,
+    format: &NumberFormat,
+    y_pos: &mut Mm,
+) {
+    let (line_height, max_chars) = row_wrap_metrics(layout, body_size);
+
+    let description_lines = wrap_text(&flow.description, max_chars);
+    let field_lines: Vec<Vec<String>> = visible_fields.iter()
+        .map(|field| wrap_text(&format_field_value(field, flow, format), max_chars))
+        .collect();
+
+    let row_line_count = std::iter::once(description_lines.len())
+        .chain(field_lines.iter().map(|lines| lines.len()))
+        .max()
+        .unwrap_or(1)
+        .max(1);
+
+    layer.use_text(&flow.date.format("%B %d, %Y").to_string(), body_size, Mm(layout.date_x), *y_pos, body_font);
+    let amount_text = format.format_accounting(rounding.item(flow.net_amount()));
+    layer.use_text(&amount_text, body_size, Mm(right_align_x_clamped(&amount_text, layout.amount_right_edge_x, layout.amount_x, body_size)), *y_pos, body_font);
+
+    let mut line_y = *y_pos;
+    for line in &description_lines {
+        layer.use_text(line, body_size, Mm(layout.description_x), line_y, body_font);
+        line_y -= Mm(line_height);
+    }
+
+    for (field_idx, lines) in field_lines.iter().enumerate() {
+        let mut line_y = *y_pos;
+        for line in lines {
+            layer.use_text(line, body_size, Mm(layout.extra_field_x[field_idx]), line_y, body_font);
+            line_y -= Mm(line_height);
+        }
+    }
+
+    *y_pos -= Mm(line_height * row_line_count as f64 + 2.0);
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum FontVariant {
+    RobotoRegular,
+    RobotoBold,
+    RobotoItalic,
+    RobotoBoldItalic,
+    TimesRegular,
+    TimesBold,
+    TimesItalic,
+    TimesBoldItalic,
+}
+
+impl FontVariant {
+    pub fn get_font_path(&self) -> &'static str {
+        match self {
+            FontVariant::RobotoRegular => "assets/fonts/Roboto-Regular.ttf",
+            FontVariant::RobotoBold => "assets/fonts/Roboto-Bold.ttf",
+            FontVariant::RobotoItalic => "assets/fonts/Roboto-Italic.ttf",
+            FontVariant::RobotoBoldItalic => "assets/fonts/Roboto-BoldItalic.ttf",
+            _ => "", // Built-in fonts don't need paths
+        }
+    }
+
+    pub fn get_builtin_font(&self) -> Option<BuiltinFont> {
+        match self {
+            FontVariant::TimesRegular => Some(BuiltinFont::TimesRoman),
+            FontVariant::TimesBold => Some(BuiltinFont::TimesBold),
+            FontVariant::TimesItalic => Some(BuiltinFont::TimesItalic),
+            FontVariant::TimesBoldItalic => Some(BuiltinFont::TimesBoldItalic),
+            _ => None,
+        }
+    }
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            FontVariant::RobotoRegular => "Roboto Regular",
+            FontVariant::RobotoBold => "Roboto Bold",
+            FontVariant::RobotoItalic => "Roboto Italic",
+            FontVariant::RobotoBoldItalic => "Roboto Bold Italic",
+            FontVariant::TimesRegular => "Times New Roman Regular",
+            FontVariant::TimesBold => "Times New Roman Bold",
+            FontVariant::TimesItalic => "Times New Roman Italic",
+            FontVariant::TimesBoldItalic => "Times New Roman Bold Italic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FontSettings {
+    pub title_font: FontVariant,
+    pub subtitle_font: FontVariant,
+    pub header_font: FontVariant,
+    pub body_font: FontVariant,
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        Self {
+            title_font: FontVariant::RobotoBold,
+            subtitle_font: FontVariant::RobotoRegular,
+            header_font: FontVariant::RobotoBold,
+            body_font: FontVariant::RobotoRegular,
+        }
+    }
+}
+
+/// What a report's amounts are rounded to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RoundingPrecision {
+    Cent,
+    /// Whole dollars, as many tax forms require (50 cents rounds up).
+    Dollar,
+}
+
+/// Where rounding happens: on every line item before it's summed, or only
+/// on the totals (items are shown and summed unrounded). Jurisdictions
+/// differ, and with whole-dollar precision the two can disagree by a few
+/// dollars on a long report.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RoundingStage {
+    EachItem,
+    Totals,
+}
+
+/// A report's rounding convention, applied to every amount the report
+/// shows: line items, group/category totals, and the summary.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RoundingRule {
+    pub precision: RoundingPrecision,
+    pub stage: RoundingStage,
+}
+
+impl Default for RoundingRule {
+    /// Cents, rounding only totals -- what reports did before rounding was
+    /// configurable (items were summed unrounded and displayed to the cent).
+    fn default() -> Self {
+        Self {
+            precision: RoundingPrecision::Cent,
+            stage: RoundingStage::Totals,
+        }
+    }
+}
+
+impl RoundingRule {
+    /// Rounds `amount` to this rule's precision, half away from zero.
+    pub fn round(&self, amount: f64) -> f64 {
+        match self.precision {
+            RoundingPrecision::Cent => (amount * 100.0).round() / 100.0,
+            RoundingPrecision::Dollar => amount.round(),
+        }
+    }
+
+    /// A line item's amount as it should be shown and summed.
+    pub fn item(&self, amount: f64) -> f64 {
+        match self.stage {
+            RoundingStage::EachItem => self.round(amount),
+            RoundingStage::Totals => amount,
+        }
+    }
+
+    /// Totals line-item amounts under this rule.
+    pub fn total(&self, amounts: impl IntoIterator<Item = f64>) -> f64 {
+        self.round(amounts.into_iter().map(|amount| self.item(amount)).sum())
+    }
+}
+
+/// Which flows a report covers and how it summarizes them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReportKind {
+    /// Every flow in the period, summarized as income/expense/net.
+    Flows,
+    /// Only tax-deductible flows (and refunds of them), summarized as
+    /// per-category and overall deductible totals.
+    TaxDeductions,
+    /// Only reimbursable expenses not yet paid back (see
+    /// `Flow::is_outstanding_reimbursement`), summarized like `Flows`.
+    OutstandingReimbursements,
+}
+
+impl ReportKind {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            ReportKind::Flows => "Financial Flows",
+            ReportKind::TaxDeductions => "Tax Deduction Summary",
+            ReportKind::OutstandingReimbursements => "Outstanding Reimbursements",
+        }
+    }
+
+    /// Title a fresh report of this kind starts with.
+    pub fn default_title(&self) -> &'static str {
+        match self {
+            ReportKind::Flows => "Financial Flows Report",
+            ReportKind::TaxDeductions => "Tax Deduction Summary",
+            ReportKind::OutstandingReimbursements => "Outstanding Reimbursements",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReportFormat {
+    Pdf,
+    Csv,
+    Xlsx,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            ReportFormat::Pdf => "PDF",
+            ReportFormat::Csv => "CSV",
+            ReportFormat::Xlsx => "Excel (XLSX)",
+            ReportFormat::Markdown => "Markdown",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Pdf => "pdf",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Xlsx => "xlsx",
+            ReportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// The "Time Period: ..." line shown on the cover page and every page's
+/// footer.
+fn time_period_text(time_period: &TimePeriod) -> String {
+    match time_period {
+        TimePeriod::LastYear => {
+            let now = chrono::Local::now().date_naive();
+            let start = now.with_month(1).unwrap().with_day(1).unwrap();
+            let end = start.with_year(start.year() - 1).unwrap();
+            format!("Time Period: {} to {}", end.format("%B %d, %Y"), start.format("%B %d, %Y"))
+        },
+        TimePeriod::ThisYear => {
+            let now = chrono::Local::now().date_naive();
+            let start = now.with_month(1).unwrap().with_day(1).unwrap();
+            format!("Time Period: {} to {}", start.format("%B %d, %Y"), now.format("%B %d, %Y"))
+        },
+        TimePeriod::Custom(start, end) => {
+            format!("Time Period: {} to {}", start.format("%B %d, %Y"), end.format("%B %d, %Y"))
+        },
+    }
+}
+
+/// The text content of a report -- titles, each category's table, and the
+/// summary -- as the PDF would show it, for checking in the report dialog
+/// before anything is written to disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportPreview {
+    pub title: String,
+    pub subtitle: String,
+    pub time_period_text: String,
+    pub categories: Vec<PreviewCategory>,
+    /// (label, amount) rows of the summary page, including its totals.
+    pub summary: Vec<(String, String)>,
+}
+
+impl ReportPreview {
+    /// The same content as GitHub-style Markdown: a heading per category
+    /// with its table(s), then the summary as a two-column table. Plain
+    /// text with stable ordering, so successive exports diff cleanly.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        if !self.subtitle.is_empty() {
+            out += &format!("{}\n\n", self.subtitle);
+        }
+        out += &format!("{}\n", self.time_period_text);
+
+        for category in &self.categories {
+            out += &format!("\n## {}\n", category.name);
+            for group in &category.groups {
+                if let Some(heading) = &group.heading {
+                    out += &format!("\n### {}\n", heading);
+                }
+                out.push('\n');
+                push_markdown_table(&mut out, &category.columns, &group.rows);
+                if let Some(total) = &group.total {
+                    out += &format!("\nGroup Total: **{}**\n", total);
+                }
+                for (label, value) in &group.aggregates {
+                    out += &format!("{}: {}\n", label, value);
+                }
+            }
+            out += &format!("\nCategory Total: **{}**\n", category.total);
+        }
+
+        out += "\n## Summary\n\n";
+        let rows: Vec<Vec<String>> = self.summary.iter()
+            .map(|(label, amount)| vec![label.clone(), amount.clone()])
+            .collect();
+        push_markdown_table(&mut out, &["Category".to_string(), "Total".to_string()], &rows);
+        out
+    }
+}
+
+/// Appends a Markdown table, escaping `|` and flattening line breaks so
+/// cell text can't break the table's structure.
+fn push_markdown_table(out: &mut String, header: &[String], rows: &[Vec<String>]) {
+    let cell = |text: &str| text.replace('|', "\\|").replace(['\r', '\n'], " ");
+    let line = |cells: &[String]| format!("| {} |\n", cells.iter().map(|c| cell(c)).collect::<Vec<_>>().join(" | "));
+    out.push_str(&line(header));
+    out.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+    for row in rows {
+        out.push_str(&line(row));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewCategory {
+    /// The category's name, with its icon in front if it has one.
+    pub name: String,
+    pub color: Option<[u8; 3]>,
+    pub columns: Vec<String>,
+    /// A category that isn't grouped is a single group with no heading.
+    pub groups: Vec<PreviewGroup>,
+    pub total: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewGroup {
+    /// e.g. `"vendor: Acme"`, as the PDF's group heading.
+    pub heading: Option<String>,
+    pub rows: Vec<Vec<String>>,
+    pub total: Option<String>,
+    /// (label, value) for each of the request's `group_aggregates`.
+    pub aggregates: Vec<(String, String)>,
+}
+
+/// A statistic shown under each group's total in a grouped report.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GroupAggregate {
+    Count,
+    Average,
+    Minimum,
+    Maximum,
+    /// Total of just the group's deductible flows (and refunds of them).
+    DeductibleSubtotal,
+}
+
+impl GroupAggregate {
+    pub const ALL: [GroupAggregate; 5] = [
+        GroupAggregate::Count,
+        GroupAggregate::Average,
+        GroupAggregate::Minimum,
+        GroupAggregate::Maximum,
+        GroupAggregate::DeductibleSubtotal,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            GroupAggregate::Count => "Count",
+            GroupAggregate::Average => "Average",
+            GroupAggregate::Minimum => "Minimum",
+            GroupAggregate::Maximum => "Maximum",
+            GroupAggregate::DeductibleSubtotal => "Deductible Subtotal",
+        }
+    }
+}
+
+/// Which category types a report covers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReportFlowTypes {
+    Both,
+    IncomeOnly,
+    ExpenseOnly,
+}
+
+impl ReportFlowTypes {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            ReportFlowTypes::Both => "Income and Expense",
+            ReportFlowTypes::IncomeOnly => "Income Only",
+            ReportFlowTypes::ExpenseOnly => "Expense Only",
+        }
+    }
+
+    /// Flows whose category no longer exists (`None`) have no type, so
+    /// they're only included when both types are.
+    pub fn includes(&self, flow_type: Option<&FlowType>) -> bool {
+        match self {
+            ReportFlowTypes::Both => true,
+            ReportFlowTypes::IncomeOnly => flow_type == Some(&FlowType::Income),
+            ReportFlowTypes::ExpenseOnly => flow_type == Some(&FlowType::Expense),
+        }
+    }
+}
+
+/// Everything that determines a report. Serializable so it can be saved as
+/// a named template (see `Database::save_report_template`); fields added
+/// later fall back to their defaults when an older template is loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportRequest {
+    pub kind: ReportKind,
+    pub time_period: TimePeriod,
+    /// Flow ids picked one by one. When any are picked the report covers
+    /// exactly those flows, and the period, category, trip and type
+    /// filters are ignored. Empty means the filters decide.
+    pub selected_flows: Vec<String>,
+    /// Category ids to include. `None` means every category, so a fresh
+    /// request (and any category added later) is included by default;
+    /// an empty list means none.
+    pub selected_categories: Option<Vec<String>>,
+    pub group_by: Option<String>, // Field name to group by
+    /// Statistics listed under each group's total when grouping, in this
+    /// order.
+    pub group_aggregates: Vec<GroupAggregate>,
+    /// Custom field names to include as extra columns in flow tables.
+    /// `None` means every field, like `selected_categories`.
+    pub selected_fields: Option<Vec<String>>,
+    pub title: String,
+    pub subtitle: String,
+    pub font_settings: FontSettings,
+    pub output_format: ReportFormat,
+    pub rounding: RoundingRule,
+    pub paper_size: PaperSize,
+    pub orientation: PageOrientation,
+    /// Income categories, expense categories, or both.
+    pub flow_types: ReportFlowTypes,
+    /// Adds a month-by-month table of each category's totals (PDF only).
+    pub include_monthly_breakdown: bool,
+    /// Adds each category's `YearGrid` (monthly total, count, and average)
+    /// over the report period (PDF only).
+    pub include_year_grid: bool,
+    /// Only flows assigned to this trip, for a trip expense report.
+    pub trip_id: Option<String>,
+}
+
+impl Default for ReportRequest {
+    fn default() -> Self {
+        Self {
+            kind: ReportKind::Flows,
+            time_period: TimePeriod::default(),
+            selected_flows: Vec::new(),
+            selected_categories: None,
+            group_by: None,
+            group_aggregates: Vec::new(),
+            selected_fields: None,
+            title: ReportKind::Flows.default_title().to_string(),
+            subtitle: String::new(),
+            font_settings: FontSettings::default(),
+            output_format: ReportFormat::Pdf,
+            rounding: RoundingRule::default(),
+            paper_size: PaperSize::A4,
+            orientation: PageOrientation::Portrait,
+            flow_types: ReportFlowTypes::Both,
+            include_monthly_breakdown: false,
+            include_year_grid: false,
+            trip_id: None,
+        }
+    }
+}
+
+impl ReportRequest {
+    pub fn includes_category(&self, category_id: &str) -> bool {
+        self.selected_categories.as_ref()
+            .is_none_or(|selected| selected.iter().any(|id| id == category_id))
+    }
+
+    /// Whether `flow` passes the period, category, trip and type filters,
+    /// regardless of the report kind or any flows picked one by one.
+    pub fn matches_filters(&self, flow: &Flow, flow_type: Option<&FlowType>, today: NaiveDate) -> bool {
+        self.time_period.contains(flow.date, today)
+            && self.includes_category(&flow.category_id)
+            && (self.trip_id.is_none() || flow.trip_id == self.trip_id)
+            && self.flow_types.includes(flow_type)
+    }
+}
+
+pub struct ReportGenerator {
+    flows: Vec<Flow>,
+    categories: HashMap<String, ReportCategoryInfo>, // category_id -> info
+    /// Category ids in the same order they appear in the category selection
+    /// dropdown, so the report's category/page order is deterministic
+    /// instead of following `categories`' arbitrary `HashMap` iteration
+    /// order. Any category id referenced by a flow but missing here (e.g. a
+    /// deleted category) is still shown, just appended after the ordered
+    /// ones -- see `ordered_category_ids` in `generate_report`.
+    category_order: Vec<String>,
+    /// How PDF amounts are written. CSV output always uses plain
+    /// `1234.50`-style numbers so spreadsheets can read them back.
+    number_format: NumberFormat,
+    title_font: Option<IndirectFontRef>,
+    subtitle_font: Option<IndirectFontRef>,
+    header_font: Option<IndirectFontRef>,
+    body_font: Option<IndirectFontRef>,
+}
+
+impl ReportGenerator {
+    pub fn new(flows: Vec<Flow>, categories: HashMap<String, ReportCategoryInfo>, category_order: Vec<String>) -> Self {
+        Self {
+            flows,
+            categories,
+            category_order,
+            number_format: NumberFormat::default(),
+            title_font: None,
+            subtitle_font: None,
+            header_font: None,
+            body_font: None,
+        }
+    }
+
+    pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// How amounts in `category_id` are written: the category's own format
+    /// if it has one, otherwise the report's.
+    fn format_for(&self, category_id: &str) -> &NumberFormat {
+        self.categories.get(category_id)
+            .and_then(|info| info.number_format.as_ref())
+            .unwrap_or(&self.number_format)
+    }
+
+    /// Splits category totals by currency so amounts are never added across
+    /// currencies: the report's own currency first (present even when empty,
+    /// unless other currencies have totals), then any others by symbol.
+    fn totals_by_currency(&self, category_totals: &HashMap<String, f64>) -> Vec<CurrencyTotals<'_>> {
+        let mut groups = vec![CurrencyTotals { format: &self.number_format, suffix: String::new(), totals: HashMap::new() }];
+        for (category_id, total) in category_totals {
+            let format = self.format_for(category_id);
+            match groups.iter_mut().find(|group| group.format.same_currency(format)) {
+                Some(group) => {
+                    group.totals.insert(category_id.clone(), *total);
+                }
+                None => groups.push(CurrencyTotals {
+                    format,
+                    suffix: String::new(),
+                    totals: HashMap::from([(category_id.clone(), *total)]),
+                }),
+            }
+        }
+        groups[1..].sort_by(|a, b| a.format.currency_symbol.cmp(&b.format.currency_symbol));
+        if groups.len() > 1 && groups[0].totals.is_empty() {
+            groups.remove(0);
+        }
+        if groups.len() > 1 {
+            for group in &mut groups {
+                group.suffix = format!(" ({})", group.format.currency_symbol.trim());
+            }
+        }
+        groups
+    }
+
+    /// Writes the report in `request.output_format`.
+    pub fn generate_report(&self, request: &ReportRequest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match request.output_format {
+            ReportFormat::Pdf => self.generate_pdf(request),
+            ReportFormat::Csv => self.generate_csv(request),
+            ReportFormat::Xlsx => self.generate_xlsx(request),
+            ReportFormat::Markdown => Ok(self.preview(request).to_markdown().into_bytes()),
+        }
+    }
+
+    /// The flows a report covers, grouped by category, plus the order those
+    /// categories appear in. Shared by every output format, so the same
+    /// filters produce the same rows whether written to PDF or CSV.
+    fn report_flows(&self, request: &ReportRequest) -> (HashMap<String, Vec<&Flow>>, Vec<String>) {
+        // A deduction summary only covers deductible flows (and refunds of
+        // them, which reduce the deductible total).
+        let deductible_ids: Option<HashSet<&str>> = (request.kind == ReportKind::TaxDeductions)
+            .then(|| utils::deductible_flows(&self.flows).iter().map(|f| f.id.as_str()).collect());
+
+        // Filter flows based on time period and the selected categories,
+        // unless flows were picked one by one. Scheduled flows haven't
+        // happened yet, so they're not reported.
+        let today = chrono::Local::now().date_naive();
+        let filtered_flows: Vec<&Flow> = self.flows.iter()
+            .filter(|flow| !flow.scheduled)
+            .filter(|flow| deductible_ids.as_ref().is_none_or(|ids| ids.contains(flow.id.as_str())))
+            .filter(|flow| request.kind != ReportKind::OutstandingReimbursements || flow.is_outstanding_reimbursement())
+            .filter(|flow| if request.selected_flows.is_empty() {
+                request.matches_filters(flow, self.categories.get(&flow.category_id).map(|info| &info.flow_type), today)
+            } else {
+                request.selected_flows.contains(&flow.id)
+            })
+            .collect();
+
+        // Sort flows by date (TODO: Add support for sorting by amount with higher priority)
+        let mut sorted_flows = filtered_flows;
+        sorted_flows.sort_by(|a, b| a.date.cmp(&b.date));
+
+        // Group flows by category
+        let mut category_flows: HashMap<String, Vec<&Flow>> = HashMap::new();
+        for flow in sorted_flows {
+            category_flows.entry(flow.category_id.clone())
+                .or_default()
+                .push(flow);
+        }
+
+        // Categories are rendered in the same order as the category
+        // selection dropdown (`self.category_order`), not `category_flows`'
+        // arbitrary `HashMap` iteration order -- otherwise which category
+        // shows up first/second/etc. changes randomly between report runs.
+        // Computed once, up front, so both the cover page's table of
+        // contents and the detail-page loop agree on the order.
+        let category_display_order = ordered_category_ids(&self.category_order, &category_flows);
+        (category_flows, category_display_order)
+    }
+
+    /// Columns shared by the CSV and XLSX exports after Category, Date,
+    /// Amount and Description: the group-by field's name (when grouping),
+    /// then every custom field shown by any included category.
+    fn export_columns(&self, request: &ReportRequest, category_display_order: &[String]) -> (Option<String>, Vec<&CategoryField>) {
+        let mut fields: Vec<&CategoryField> = Vec::new();
+        for category_id in category_display_order {
+            let category_fields = self.categories.get(category_id)
+                .map(|info| info.fields.as_slice())
+                .unwrap_or(&[]);
+            for field in visible_custom_fields(category_fields, &request.group_by, request.selected_fields.as_deref()) {
+                if !fields.iter().any(|f| f.name == field.name) {
+                    fields.push(field);
+                }
+            }
+        }
+
+        let group_header = request.group_by.as_ref().map(|group_by| {
+            let group_field = self.categories.values()
+                .flat_map(|info| info.fields.iter())
+                .find(|f| &f.name == group_by);
+            group_field.map(|f| f.display_name()).unwrap_or_else(|| group_by.clone())
+        });
+        (group_header, fields)
+    }
+
+    /// (category name, group value, flow) for every exported row, in
+    /// order; grouped categories list their rows group by group, and
+    /// ungrouped ones have an empty group value.
+    fn export_rows<'f>(
+        &self,
+        request: &ReportRequest,
+        category_flows: &HashMap<String, Vec<&'f Flow>>,
+        category_display_order: &[String],
+    ) -> Vec<(String, String, &'f Flow)> {
+        let mut rows = Vec::new();
+        for category_id in category_display_order {
+            let flows = &category_flows[category_id];
+            let category_name = self.category_name(category_id);
+            let category_fields = self.categories.get(category_id)
+                .map(|info| info.fields.as_slice())
+                .unwrap_or(&[]);
+
+            let groups: Vec<(String, Vec<&Flow>)> = match &request.group_by {
+                Some(group_by) if group_by_applies_to_category(&request.group_by, category_fields) => {
+                    let mut groups: Vec<_> = group_flows_by_field(flows, group_by).into_iter().collect();
+                    groups.sort_by(|a, b| a.0.cmp(&b.0));
+                    groups
+                }
+                _ => vec![(String::new(), flows.clone())],
+            };
+
+            for (group_value, group_flows) in groups {
+                for flow in group_flows {
+                    rows.push((category_name.to_string(), group_value.clone(), flow));
+                }
+            }
+        }
+        rows
+    }
+
+    /// One row per flow: Category, Date, Amount, Description, then the
+    /// `export_columns`. Amounts are plain signed numbers (refunds negative)
+    /// rather than the PDF's currency formatting, so spreadsheets read them
+    /// as numbers.
+    fn generate_csv(&self, request: &ReportRequest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let (category_flows, category_display_order) = self.report_flows(request);
+        let (group_header, fields) = self.export_columns(request, &category_display_order);
+
+        let mut header = vec!["Category".to_string(), "Date".to_string(), "Amount".to_string(), "Description".to_string()];
+        header.extend(group_header);
+        header.extend(fields.iter().map(|f| f.display_name()));
+
+        let mut out = String::new();
+        push_csv_row(&mut out, &header);
+
+        for (category_name, group_value, flow) in self.export_rows(request, &category_flows, &category_display_order) {
+            let mut row = vec![
+                category_name,
+                flow.date.format("%Y-%m-%d").to_string(),
+                format!("{:.2}", request.rounding.item(flow.net_amount())),
+                flow.description.clone(),
+            ];
+            if request.group_by.is_some() {
+                row.push(group_value);
+            }
+            for field in &fields {
+                row.push(flow.custom_fields.get(&field.name).cloned().unwrap_or_default());
+            }
+            push_csv_row(&mut out, &row);
+        }
+
+        Ok(out.into_bytes())
+    }
+
+    /// A "Flows" sheet with the same rows and columns as the CSV, but typed:
+    /// real dates, amounts and currency fields in their category's currency
+    /// format, numbers and Yes/No fields as numbers and booleans. The
+    /// header row is frozen and has an autofilter. A "Summary" sheet follows
+    /// with each category's total and the report's overall totals, one set
+    /// per currency.
+    fn generate_xlsx(&self, request: &ReportRequest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let (category_flows, category_display_order) = self.report_flows(request);
+        let (group_header, fields) = self.export_columns(request, &category_display_order);
+
+        let bold = XlsxFormat::new().set_bold();
+        let date_format = XlsxFormat::new().set_num_format("yyyy-mm-dd");
+        let currency = |format: &NumberFormat| XlsxFormat::new().set_num_format(format.excel_currency_format());
+
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Flows")?;
+
+        let mut header = vec!["Category".to_string(), "Date".to_string(), "Amount".to_string(), "Description".to_string()];
+        header.extend(group_header);
+        header.extend(fields.iter().map(|f| f.display_name()));
+        for (col, title) in header.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, title, &bold)?;
+        }
+
+        let rows = self.export_rows(request, &category_flows, &category_display_order);
+        for (i, (category_name, group_value, flow)) in rows.iter().enumerate() {
+            let row = i as u32 + 1;
+            let currency = currency(self.format_for(&flow.category_id));
+            sheet.write_string(row, 0, category_name)?;
+            let date = ExcelDateTime::from_ymd(flow.date.year() as u16, flow.date.month() as u8, flow.date.day() as u8)?;
+            sheet.write_datetime_with_format(row, 1, &date, &date_format)?;
+            sheet.write_number_with_format(row, 2, request.rounding.item(flow.net_amount()), &currency)?;
+            sheet.write_string(row, 3, &flow.description)?;
+            let mut col = 4;
+            if request.group_by.is_some() {
+                sheet.write_string(row, col, group_value)?;
+                col += 1;
+            }
+            for field in &fields {
+                if let Some(value) = flow.custom_fields.get(&field.name) {
+                    write_typed_cell(sheet, row, col, field, value, &currency, &date_format)?;
+                }
+                col += 1;
+            }
+        }
+        sheet.autofilter(0, 0, rows.len() as u32, header.len() as u16 - 1)?;
+        sheet.set_freeze_panes(1, 0)?;
+        sheet.autofit();
+
+        let summary = workbook.add_worksheet();
+        summary.set_name("Summary")?;
+        summary.write_string_with_format(0, 0, "Category", &bold)?;
+        summary.write_string_with_format(0, 1, "Type", &bold)?;
+        summary.write_string_with_format(0, 2, "Total", &bold)?;
+        let mut category_totals: HashMap<String, f64> = HashMap::new();
+        let mut row = 1;
+        for category_id in &category_display_order {
+            let total = request.rounding.total(category_flows[category_id].iter().map(|f| f.net_amount()));
+            category_totals.insert(category_id.clone(), total);
+            summary.write_string(row, 0, self.category_name(category_id))?;
+            let flow_type = self.categories.get(category_id).map_or("", |info| match info.flow_type {
+                FlowType::Income => "Income",
+                FlowType::Expense => "Expense",
+            });
+            summary.write_string(row, 1, flow_type)?;
+            summary.write_number_with_format(row, 2, total, &currency(self.format_for(category_id)))?;
+            row += 1;
+        }
+
+        // Plain signs here (income positive, expenses as positive costs),
+        // unlike the PDF summary's reversed convention: spreadsheet users
+        // total these columns themselves.
+        row += 1;
+        for group in self.totals_by_currency(&category_totals) {
+            let group_totals = &group.totals;
+            let mut totals: Vec<(String, f64)> = Vec::new();
+            if request.kind == ReportKind::TaxDeductions {
+                totals.push((format!("Total Deductible{}", group.suffix), group_totals.values().sum()));
+                totals.extend(self.jurisdiction_totals(group_totals).into_iter()
+                    .map(|(jurisdiction, total)| (format!("{} Deductible{}", jurisdiction, group.suffix), total)));
+            } else {
+                let type_total = |flow_type: FlowType| group_totals.iter()
+                    .filter(|(id, _)| self.categories.get(*id).is_some_and(|info| info.flow_type == flow_type))
+                    .map(|(_, total)| total)
+                    .sum::<f64>();
+                if request.flow_types != ReportFlowTypes::ExpenseOnly {
+                    totals.push((format!("Total Income{}", group.suffix), type_total(FlowType::Income)));
+                }
+                if request.flow_types != ReportFlowTypes::IncomeOnly {
+                    totals.push((format!("Total Expense{}", group.suffix), type_total(FlowType::Expense)));
+                }
+                if request.flow_types == ReportFlowTypes::Both {
+                    totals.push((format!("Net Total{}", group.suffix), net_total(group_totals, &self.categories)));
+                }
+            }
+            let bold_currency = currency(group.format).set_bold();
+            for (label, total) in totals {
+                summary.write_string_with_format(row, 0, label, &bold)?;
+                summary.write_number_with_format(row, 2, request.rounding.round(total), &bold_currency)?;
+                row += 1;
+            }
+        }
+        summary.autofit();
+
+        Ok(workbook.save_to_buffer()?)
+    }
+
+    /// What `generate_report` would put in a PDF for `request`, without
+    /// laying out any pages. Uses the same filters, grouping, rounding and
+    /// number format as the PDF; the monthly breakdown and year grids are
+    /// left out.
+    pub fn preview(&self, request: &ReportRequest) -> ReportPreview {
+        let (category_flows, category_display_order) = self.report_flows(request);
+        let mut category_totals: HashMap<String, f64> = HashMap::new();
+        let mut categories = Vec::new();
+
+        for category_id in &category_display_order {
+            let flows = &category_flows[category_id];
+            let category_fields = self.categories.get(category_id)
+                .map(|info| info.fields.as_slice())
+                .unwrap_or(&[]);
+            let visible_fields = visible_custom_fields(category_fields, &request.group_by, request.selected_fields.as_deref());
+            let mut columns = vec!["Date".to_string(), "Amount".to_string(), "Description".to_string()];
+            columns.extend(visible_fields.iter().map(|field| field.display_name()));
+
+            let number_format = self.format_for(category_id);
+            let row = |flow: &Flow| {
+                let mut cells = vec![
+                    flow.date.format("%B %d, %Y").to_string(),
+                    number_format.format_accounting(request.rounding.item(flow.net_amount())),
+                    flow.description.clone(),
+                ];
+                cells.extend(visible_fields.iter().map(|field| format_field_value(field, flow, number_format)));
+                cells
+            };
+
+            let groups = match &request.group_by {
+                Some(group_by) if group_by_applies_to_category(&request.group_by, category_fields) => {
+                    let mut grouped: Vec<_> = group_flows_by_field(flows, group_by).into_iter().collect();
+                    grouped.sort_by(|a, b| a.0.cmp(&b.0));
+                    grouped.into_iter()
+                        .map(|(value, group_flows)| PreviewGroup {
+                            heading: Some(format!("{}: {}", group_by, value)),
+                            rows: group_flows.iter().map(|flow| row(flow)).collect(),
+                            total: Some(number_format.format_accounting(
+                                request.rounding.total(group_flows.iter().map(|f| f.net_amount()))
+                            )),
+                            aggregates: self.group_aggregates(request, &group_flows, number_format),
+                        })
+                        .collect()
+                }
+                _ => vec![PreviewGroup { heading: None, rows: flows.iter().map(|flow| row(flow)).collect(), total: None, aggregates: Vec::new() }],
+            };
+
+            let category_total = request.rounding.total(flows.iter().map(|f| f.net_amount()));
+            category_totals.insert(category_id.clone(), category_total);
+            let info = self.categories.get(category_id);
+            categories.push(PreviewCategory {
+                name: match info.and_then(|info| info.icon.as_deref()).map(str::trim).filter(|icon| !icon.is_empty()) {
+                    Some(icon) => format!("{} {}", icon, self.category_name(category_id)),
+                    None => self.category_name(category_id).to_string(),
+                },
+                color: info.and_then(|info| info.color),
+                columns,
+                groups,
+                total: number_format.format_accounting(category_total),
+            });
+        }
+
+        let mut summary = Vec::new();
+        for group in self.totals_by_currency(&category_totals) {
+            let format = |amount: f64| group.format.format_accounting(amount);
+            let group_totals = &group.totals;
+            let ordered_ids = ordered_category_ids(&self.category_order, group_totals);
+            if request.kind == ReportKind::TaxDeductions {
+                for category_id in &ordered_ids {
+                    summary.push((self.category_name(category_id).to_string(), format(group_totals[category_id])));
+                }
+                summary.push((format!("Total Deductible{}:", group.suffix), format(request.rounding.round(group_totals.values().sum()))));
+                for (jurisdiction, total) in self.jurisdiction_totals(group_totals) {
+                    summary.push((format!("{} Deductible{}:", jurisdiction, group.suffix), format(request.rounding.round(total))));
+                }
+            } else {
+                for (flow_type, heading) in [(FlowType::Income, "Income"), (FlowType::Expense, "Expense")] {
+                    let section_ids: Vec<&String> = ordered_ids.iter()
+                        .filter(|id| self.categories.get(*id).is_some_and(|info| info.flow_type == flow_type))
+                        .collect();
+                    if section_ids.is_empty() {
+                        continue;
+                    }
+                    let mut section_total = 0.0;
+                    for category_id in section_ids {
+                        section_total += group_totals[category_id];
+                        let displayed = summary_display_value(group_totals[category_id], &flow_type);
+                        summary.push((self.category_name(category_id).to_string(), format(displayed)));
+                    }
+                    summary.push((format!("Total {}{}:", heading, group.suffix), format(request.rounding.round(section_total))));
+                }
+                for category_id in ordered_ids.iter().filter(|id| !self.categories.contains_key(*id)) {
+                    summary.push((category_id.clone(), format(group_totals[category_id])));
+                }
+                if request.flow_types == ReportFlowTypes::Both {
+                    let overall_total = -request.rounding.round(net_total(group_totals, &self.categories));
+                    summary.push((format!("Net Total{}:", group.suffix), format(overall_total)));
+                }
+            }
+        }
+
+        ReportPreview {
+            title: request.title.clone(),
+            subtitle: request.subtitle.clone(),
+            time_period_text: time_period_text(&request.time_period),
+            categories,
+            summary,
+        }
+    }
+
+    /// (label, formatted value) for each of `request.group_aggregates` over
+    /// one group's flows. Amounts are rounded like the group total.
+    fn group_aggregates(&self, request: &ReportRequest, flows: &[&Flow], number_format: &NumberFormat) -> Vec<(String, String)> {
+        let amounts: Vec<f64> = flows.iter().map(|f| request.rounding.item(f.net_amount())).collect();
+        let format = |amount: f64| number_format.format_accounting(request.rounding.round(amount));
+        request.group_aggregates.iter()
+            .map(|aggregate| {
+                let value = match aggregate {
+                    GroupAggregate::Count => flows.len().to_string(),
+                    GroupAggregate::Average if amounts.is_empty() => "-".to_string(),
+                    GroupAggregate::Average => format(amounts.iter().sum::<f64>() / amounts.len() as f64),
+                    GroupAggregate::Minimum => amounts.iter().copied().reduce(f64::min).map_or("-".to_string(), format),
+                    GroupAggregate::Maximum => amounts.iter().copied().reduce(f64::max).map_or("-".to_string(), format),
+                    GroupAggregate::DeductibleSubtotal => {
+                        let deductible: HashSet<&str> = utils::deductible_flows(&self.flows).iter().map(|f| f.id.as_str()).collect();
+                        format(request.rounding.total(flows.iter()
+                            .filter(|f| deductible.contains(f.id.as_str()))
+                            .map(|f| f.net_amount())))
+                    }
+                };
+                (aggregate.get_display_name().to_string(), value)
+            })
+            .collect()
+    }
+
+    fn generate_pdf(&self, request: &ReportRequest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let (category_flows, category_display_order) = self.report_flows(request);
+
+        // Create a new document -- page1/layer1 becomes the cover page below.
+        let geometry = PageGeometry::new(request.paper_size, request.orientation);
+        let (doc, page1, layer1) = PdfDocument::new("Financial Report", Mm(geometry.width_mm), Mm(geometry.height_mm), "Layer 1");
+
+        // Load fonts
+        let title_font = self.load_font(&doc, &request.font_settings.title_font)?;
+        let subtitle_font = self.load_font(&doc, &request.font_settings.subtitle_font)?;
+        let header_font = self.load_font(&doc, &request.font_settings.header_font)?;
+        let body_font = self.load_font(&doc, &request.font_settings.body_font)?;
+
+        let time_period_text = time_period_text(&request.time_period);
+
+        // Cover page: title, subtitle, time period, and a mini table of
+        // contents -- the categories that appear (in the same order as their
+        // detail pages) plus a pointer to the summary at the end. No page
+        // numbers here: what page each category lands on isn't known until
+        // it's actually rendered below.
+        let cover_layer = doc.get_page(page1).get_layer(layer1);
+        cover_layer.use_text(&request.title, 26.0, Mm(20.0), geometry.cover_y(180.0), &title_font);
+        if !request.subtitle.is_empty() {
+            cover_layer.use_text(&request.subtitle, 16.0, Mm(20.0), geometry.cover_y(163.0), &subtitle_font);
+        }
+        cover_layer.use_text(&time_period_text, 14.0, Mm(20.0), geometry.cover_y(148.0), &subtitle_font);
+        // No page-number/period chrome on the cover page itself -- it already
+        // shows the period as part of its own content, and numbering starts
+        // on the first page after it (see `page_number: 0` below).
+
+        let mut cover_y = geometry.cover_y(130.0);
+        if !category_display_order.is_empty() {
+            cover_layer.use_text("Categories in this report:", 13.0, Mm(20.0), cover_y, &header_font);
+            cover_y -= Mm(8.0);
+            for category_id in &category_display_order {
+                let category_name = self.categories.get(category_id)
+                    .map(|info| info.name.as_str())
+                    .unwrap_or(category_id);
+                cover_layer.use_text(&format!("- {}", category_name), 11.0, Mm(24.0), cover_y, &subtitle_font);
+                cover_y -= Mm(6.0);
+            }
+            cover_y -= Mm(6.0);
+        }
+        let summary_pointer = match request.kind {
+            ReportKind::Flows | ReportKind::OutstandingReimbursements => "A financial summary appears at the end of this report.",
+            ReportKind::TaxDeductions => "Deductible totals appear at the end of this report.",
+        };
+        cover_layer.use_text(summary_pointer, 11.0, Mm(20.0), cover_y, &subtitle_font);
+
+        let mut cursor = PageCursor {
+            doc: &doc,
+            page: page1,
+            layer_idx: layer1,
+            geometry,
+            y_pos: Mm(geometry.content_top()),
+            // Starts at 0 (not 1) so the first page created after the cover
+            // page -- the first category's page -- becomes "Page 1", not
+            // "Page 2". The cover page itself is never numbered.
+            page_number: 0,
+            time_period_text: &time_period_text,
+            chrome_font: &body_font,
+            table_header: None,
+        };
+
+        // Store category totals for later use
+        let mut category_totals: HashMap<String, f64> = HashMap::new();
+
+        for category_id in &category_display_order {
+            let flows = &category_flows[category_id];
+
+            // Each category always starts on its own fresh page.
+            let mut layer = cursor.start_new_page();
+
+            // Add category header
+            let category_name = self.categories.get(category_id)
+                .map(|info| info.name.as_str())
+                .unwrap_or(category_id);
+            let category_color = self.categories.get(category_id).and_then(|info| info.color);
+            if let Some([r, g, b]) = category_color {
+                layer.set_fill_color(Color::Rgb(Rgb::new(f64::from(r) / 255.0, f64::from(g) / 255.0, f64::from(b) / 255.0, None)));
+            }
+            layer.use_text(&format!("Category: {}", category_name), 16.0, Mm(20.0), cursor.y_pos, &header_font);
+            if category_color.is_some() {
+                layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+            }
+            cursor.y_pos -= Mm(15.0);
+
+            // Custom fields (other than the active group-by field, which is
+            // already shown as each group's section header) get their own
+            // column, sharing the remaining page width with Description.
+            let category_fields = self.categories.get(category_id)
+                .map(|info| info.fields.as_slice())
+                .unwrap_or(&[]);
+            let visible_fields = visible_custom_fields(category_fields, &request.group_by, request.selected_fields.as_deref());
+            let layout = compute_column_layout(visible_fields.len(), geometry.width_mm);
+            let is_grouped = group_by_applies_to_category(&request.group_by, category_fields);
+            let number_format = self.format_for(category_id);
+            let body_size = body_font_size_for_extra_columns(visible_fields.len(), is_grouped);
+            let header_size = (body_size + 1.0).min(12.0);
+
+            // Add table headers (repeated on every page the category runs onto)
+            let mut columns = vec![
+                ("Date".to_string(), layout.date_x),
+                ("Amount".to_string(), center_align_x("Amount", layout.amount_x, layout.amount_right_edge_x, header_size)),
+                ("Description".to_string(), layout.description_x),
+            ];
+            columns.extend(visible_fields.iter().zip(&layout.extra_field_x).map(|(field, x)| (field.display_name(), *x)));
+            cursor.begin_table(TableHeader {
+                continued_title: Some(format!("Category: {} (continued)", category_name)),
+                columns,
+                font_size: header_size,
+                spacing_mm: 10.0,
+                font: header_font.clone(),
+            });
+
+            // Group flows if requested and this category actually has the
+            // field being grouped by -- otherwise render normally below.
+            if is_grouped {
+                let group_by = request.group_by.as_ref().unwrap();
+                // Sorted by value, as in the CSV and the preview, rather than
+                // the map's arbitrary order.
+                let mut grouped_flows: Vec<_> = group_flows_by_field(flows, group_by).into_iter().collect();
+                grouped_flows.sort_by(|a, b| a.0.cmp(&b.0));
+
+                // Add each group
+                for (group_value, group_flows) in &grouped_flows {
+                    layer = cursor.ensure_space(12.0);
+                    layer.use_text(&format!("{}: {}", group_by, group_value), 14.0, Mm(20.0), cursor.y_pos, &header_font);
+                    cursor.y_pos -= Mm(10.0);
+
+                    // Add flows in this group -- checking (and paginating for)
+                    // space before each row, since previously nothing did,
+                    // and content past the bottom margin is simply invisible.
+                    for flow in group_flows {
+                        let needed = row_height_mm(flow, &visible_fields, &layout, body_size, number_format);
+                        layer = cursor.ensure_space(needed);
+                        render_flow_row(&layer, flow, &visible_fields, &layout, body_size, &body_font, &request.rounding, number_format, &mut cursor.y_pos);
+                    }
+
+                    // Add group total -- in the same column as individual
+                    // flow amounts, not the old hardcoded Mm(80.0), which
+                    // landed under Description once column positions became
+                    // dynamic (variable custom-field columns).
+                    layer = cursor.ensure_space(15.0);
+                    let group_total = request.rounding.total(group_flows.iter().map(|f| f.net_amount()));
+                    let group_total_text = number_format.format_accounting(group_total);
+                    layer.use_text("Group Total:", 12.0, Mm(20.0), cursor.y_pos, &body_font);
+                    layer.use_text(&group_total_text, 12.0, Mm(right_align_x_clamped(&group_total_text, layout.amount_right_edge_x, layout.amount_x, 12.0)), cursor.y_pos, &body_font);
+                    for (label, value) in self.group_aggregates(request, group_flows, number_format) {
+                        cursor.y_pos -= Mm(6.0);
+                        layer = cursor.ensure_space(6.0);
+                        layer.use_text(format!("{}:", label), 10.0, Mm(24.0), cursor.y_pos, &body_font);
+                        layer.use_text(&value, 10.0, Mm(right_align_x_clamped(&value, layout.amount_right_edge_x, layout.amount_x, 10.0)), cursor.y_pos, &body_font);
+                    }
+                    cursor.y_pos -= Mm(15.0);
+                }
+            } else {
+                // Add all flows without grouping
+                for flow in flows {
+                    let needed = row_height_mm(flow, &visible_fields, &layout, body_size, number_format);
+                    layer = cursor.ensure_space(needed);
+                    render_flow_row(&layer, flow, &visible_fields, &layout, body_size, &body_font, &request.rounding, number_format, &mut cursor.y_pos);
+                }
+            }
+
+            // Add category total, with a bit of breathing room above it.
+            layer = cursor.ensure_space(28.0);
+            cursor.y_pos -= Mm(8.0);
+            let category_total = request.rounding.total(flows.iter().map(|f| f.net_amount()));
+            category_totals.insert(category_id.clone(), category_total);
+            let category_total_text = number_format.format_accounting(category_total);
+            layer.use_text("Category Total:", 14.0, Mm(20.0), cursor.y_pos, &header_font);
+            layer.use_text(&category_total_text, 14.0, Mm(right_align_x_clamped(&category_total_text, layout.amount_right_edge_x, layout.amount_x, 14.0)), cursor.y_pos, &header_font);
+        }
+
+        if request.include_monthly_breakdown {
+            self.render_monthly_breakdown(&mut cursor, &category_flows, &category_display_order, request, &header_font, &body_font);
+        }
+        if request.include_year_grid {
+            self.render_year_grids(&mut cursor, &category_flows, &category_display_order, request, &header_font, &body_font);
+        }
+
+        // Add summary page
+        let mut layer = cursor.start_new_page();
+        layer.use_text("Summary", 20.0, Mm(20.0), cursor.y_pos, &header_font);
+        cursor.y_pos -= Mm(15.0);
+
+        if request.kind == ReportKind::TaxDeductions {
+            self.render_deduction_summary(&mut cursor, &category_totals, request, &header_font, &body_font);
+            return save_pdf(doc);
+        }
+
+        // Clarifying note: everything below uses a reversed sign convention
+        // from standard accounting (see `summary_display_value`).
+        const SUMMARY_SIGN_NOTE: &str = "Note: amounts below use a reversed sign convention, since this app is primarily used for expense tracking. Expense totals and a net loss are shown as positive; Income totals and a net gain are shown as negative, in parentheses.";
+        let note_size = 9.0;
+        let note_lines = wrap_text(SUMMARY_SIGN_NOTE, max_chars_for_width(170.0, note_size));
+        layer = cursor.ensure_space(note_lines.len() as f64 * 4.5 + 8.0);
+        for line in &note_lines {
+            layer.use_text(line, note_size, Mm(20.0), cursor.y_pos, &body_font);
+            cursor.y_pos -= Mm(4.5);
+        }
+        cursor.y_pos -= Mm(8.0);
+
+        // Table header
+        const SUMMARY_AMOUNT_X: f64 = 120.0;
+        const SUMMARY_AMOUNT_RIGHT_EDGE_MM: f64 = 170.0;
+        cursor.ensure_space(13.0);
+        cursor.begin_table(TableHeader {
+            continued_title: Some("Summary (continued)".to_string()),
+            columns: vec![
+                ("Category".to_string(), 20.0),
+                ("Total".to_string(), center_align_x("Total", SUMMARY_AMOUNT_X, SUMMARY_AMOUNT_RIGHT_EDGE_MM, 12.0)),
+            ],
+            font_size: 12.0,
+            spacing_mm: 8.0,
+            font: header_font.clone(),
+        });
+
+        // Per-category totals (reversed-sign display), in an Income section
+        // then an Expense section, each closed by its own total. Same
+        // dropdown-derived ordering as the detail pages, for consistency.
+        // Categories deleted after flows referencing them were saved are
+        // shown for transparency in a final section, but excluded from the
+        // type totals and net total, same as `net_total` already does.
+        // Categories in another currency get their own sections and totals
+        // after these, labeled with that currency.
+        let currency_groups = self.totals_by_currency(&category_totals);
+        for group in &currency_groups {
+            let group_totals = &group.totals;
+            let ordered_ids = ordered_category_ids(&self.category_order, group_totals);
+            let sections = [(Some(FlowType::Income), "Income"), (Some(FlowType::Expense), "Expense"), (None, "Other")];
+            for (flow_type, heading) in sections {
+                let section_ids: Vec<&String> = ordered_ids.iter()
+                    .filter(|id| self.categories.get(*id).map(|info| info.flow_type.clone()) == flow_type)
+                    .collect();
+                if section_ids.is_empty() {
+                    continue;
+                }
+
+                // Keep the heading with at least its first row.
+                layer = cursor.ensure_space(22.0);
+                layer.use_text(format!("{}{}", heading, group.suffix), 13.0, Mm(20.0), cursor.y_pos, &header_font);
+                cursor.y_pos -= Mm(10.0);
+
+                let mut section_total = 0.0;
+                for category_id in section_ids {
+                    let raw_total = group_totals[category_id];
+                    section_total += raw_total;
+                    let displayed = match &flow_type {
+                        Some(flow_type) => summary_display_value(raw_total, flow_type),
+                        None => raw_total,
+                    };
+
+                    layer = cursor.ensure_space(12.0);
+                    layer.use_text(self.category_name(category_id), 12.0, Mm(20.0), cursor.y_pos, &body_font);
+                    let displayed_text = group.format.format_accounting(displayed);
+                    layer.use_text(&displayed_text, 12.0, Mm(right_align_x_clamped(&displayed_text, SUMMARY_AMOUNT_RIGHT_EDGE_MM, SUMMARY_AMOUNT_X, 12.0)), cursor.y_pos, &body_font);
+                    cursor.y_pos -= Mm(12.0);
+                }
+
+                if flow_type.is_some() {
+                    // Category totals are already rounded, so this only clears
+                    // float noise from summing them.
+                    let section_total_text = group.format.format_accounting(request.rounding.round(section_total));
+                    layer = cursor.ensure_space(16.0);
+                    layer.use_text(format!("Total {}{}:", heading, group.suffix), 12.0, Mm(20.0), cursor.y_pos, &header_font);
+                    layer.use_text(&section_total_text, 12.0, Mm(right_align_x_clamped(&section_total_text, SUMMARY_AMOUNT_RIGHT_EDGE_MM, SUMMARY_AMOUNT_X, 12.0)), cursor.y_pos, &header_font);
+                    cursor.y_pos -= Mm(16.0);
+                }
+            }
+        }
+        cursor.end_table();
+
+        // A net total only means something when both types are included.
+        if request.flow_types == ReportFlowTypes::Both {
+            layer = cursor.ensure_space(30.0);
+            cursor.y_pos -= Mm(6.0);
+            layer.add_line_break();
+            cursor.y_pos -= Mm(10.0);
+
+            // Net total, same reversed convention: a net loss (expenses
+            // exceeded income) displays as positive, a net gain as negative.
+            for group in &currency_groups {
+                layer = cursor.ensure_space(12.0);
+                let overall_total = -request.rounding.round(net_total(&group.totals, &self.categories));
+                let overall_total_text = group.format.format_accounting(overall_total);
+                layer.use_text(format!("Net Total{}:", group.suffix), 16.0, Mm(20.0), cursor.y_pos, &header_font);
+                layer.use_text(&overall_total_text, 16.0, Mm(right_align_x_clamped(&overall_total_text, SUMMARY_AMOUNT_RIGHT_EDGE_MM, SUMMARY_AMOUNT_X, 16.0)), cursor.y_pos, &header_font);
+                cursor.y_pos -= Mm(12.0);
+            }
+        }
+
+        save_pdf(doc)
+    }
+
+    /// One row per category with its monthly totals and overall total.
+    fn render_monthly_breakdown(
+        &self,
+        cursor: &mut PageCursor,
+        category_flows: &HashMap<String, Vec<&Flow>>,
+        category_display_order: &[String],
+        request: &ReportRequest,
+        header_font: &IndirectFontRef,
+        body_font: &IndirectFontRef,
+    ) {
+        let rows: Vec<(String, Vec<String>)> = category_display_order.iter()
+            .map(|category_id| {
+                let totals = monthly_totals(&category_flows[category_id], &request.rounding);
+                let number_format = self.format_for(category_id);
+                (self.category_name(category_id).to_string(), totals.iter().map(|t| number_format.format_accounting(*t)).collect())
+            })
+            .collect();
+        self.render_month_table(cursor, "Monthly Breakdown", "Total", &rows, header_font, body_font);
+    }
+
+    /// Each category's `YearGrid` as three rows: its total (labeled with the
+    /// category name), then its count and average.
+    fn render_year_grids(
+        &self,
+        cursor: &mut PageCursor,
+        category_flows: &HashMap<String, Vec<&Flow>>,
+        category_display_order: &[String],
+        request: &ReportRequest,
+        header_font: &IndirectFontRef,
+        body_font: &IndirectFontRef,
+    ) {
+        let mut rows: Vec<(String, Vec<String>)> = Vec::new();
+        for category_id in category_display_order {
+            let grid = YearGrid::from_flows(category_flows[category_id].iter().copied());
+            let number_format = self.format_for(category_id);
+            let amounts = |values: Vec<f64>| values.into_iter()
+                .map(|v| number_format.format_accounting(request.rounding.round(v)))
+                .collect();
+            rows.push((self.category_name(category_id).to_string(), amounts(grid.totals.iter().copied().chain([grid.year_total()]).collect())));
+            rows.push(("  Count".to_string(), grid.counts.iter().copied().chain([grid.year_count()]).map(|c| c.to_string()).collect()));
+            rows.push(("  Average".to_string(), amounts(grid.averages())));
+        }
+        self.render_month_table(cursor, "Year Grid", "Year", &rows, header_font, body_font);
+    }
+
+    fn category_name<'a>(&'a self, category_id: &'a str) -> &'a str {
+        self.categories.get(category_id)
+            .map(|info| info.name.as_str())
+            .unwrap_or(category_id)
+    }
+
+    /// A table of (label, 13 values) rows under month headings plus
+    /// `last_column` (e.g. "Total"). Split across pages
+    /// (`monthly_columns_per_page` value columns each) since all 13 don't
+    /// fit across one page.
+    fn render_month_table(
+        &self,
+        cursor: &mut PageCursor,
+        title: &str,
+        last_column: &str,
+        rows: &[(String, Vec<String>)],
+        header_font: &IndirectFontRef,
+        body_font: &IndirectFontRef,
+    ) {
+        const FONT_SIZE: f64 = 9.0;
+        const CATEGORY_X: f64 = 20.0;
+        const FIRST_AMOUNT_X: f64 = MONTH_TABLE_FIRST_AMOUNT_X;
+        const ROW_HEIGHT_MM: f64 = 8.0;
+
+        let right_edge = cursor.geometry.width_mm - MONTH_TABLE_RIGHT_MARGIN_MM;
+        let columns_per_page = monthly_columns_per_page(cursor.geometry.width_mm);
+        let labels: Vec<&str> = MONTH_LABELS.iter().copied().chain(std::iter::once(last_column)).collect();
+        let column_width = (right_edge - FIRST_AMOUNT_X) / columns_per_page as f64;
+        let name_chars = max_chars_for_width(FIRST_AMOUNT_X - CATEGORY_X - 2.0, FONT_SIZE);
+        let continued_title = format!("{} (continued)", title);
+
+        for (chunk_index, columns) in (0..labels.len()).collect::<Vec<_>>().chunks(columns_per_page).enumerate() {
+            let layer = cursor.start_new_page();
+            let heading = if chunk_index == 0 { title } else { continued_title.as_str() };
+            layer.use_text(heading, 16.0, Mm(CATEGORY_X), cursor.y_pos, header_font);
+            cursor.y_pos -= Mm(15.0);
+
+            let mut headings = vec![("Category".to_string(), CATEGORY_X)];
+            headings.extend(columns.iter().enumerate().map(|(i, &column)| {
+                let left = FIRST_AMOUNT_X + i as f64 * column_width;
+                let label = labels[column];
+                (label.to_string(), center_align_x(label, left, left + column_width, FONT_SIZE))
+            }));
+            cursor.begin_table(TableHeader {
+                continued_title: Some(continued_title.clone()),
+                columns: headings,
+                font_size: FONT_SIZE,
+                spacing_mm: 8.0,
+                font: header_font.clone(),
+            });
+
+            for (name, values) in rows {
+                let layer = cursor.ensure_space(ROW_HEIGHT_MM);
+                let name_line = wrap_text(name, name_chars).swap_remove(0);
+                layer.use_text(&name_line, FONT_SIZE, Mm(CATEGORY_X), cursor.y_pos, body_font);
+                for (i, &column) in columns.iter().enumerate() {
+                    let left = FIRST_AMOUNT_X + i as f64 * column_width;
+                    let text = &values[column];
+                    layer.use_text(text, FONT_SIZE, Mm(right_align_x_clamped(text, left + column_width - 1.0, left, FONT_SIZE)), cursor.y_pos, body_font);
+                }
+                cursor.y_pos -= Mm(ROW_HEIGHT_MM);
+            }
+        }
+    }
+
+    /// The summary page of a deduction report: each category's deductible
+    /// total, then the overall total. Unlike the flows summary there's no
+    /// income/expense netting or reversed sign convention -- every amount
+    /// here is simply "how much is deductible".
+    fn render_deduction_summary(
+        &self,
+        cursor: &mut PageCursor,
+        category_totals: &HashMap<String, f64>,
+        request: &ReportRequest,
+        header_font: &IndirectFontRef,
+        body_font: &IndirectFontRef,
+    ) {
+        const SUMMARY_AMOUNT_X: f64 = 120.0;
+        const SUMMARY_AMOUNT_RIGHT_EDGE_MM: f64 = 170.0;
+
+        cursor.ensure_space(13.0);
+        cursor.begin_table(TableHeader {
+            continued_title: Some("Summary (continued)".to_string()),
+            columns: vec![
+                ("Category".to_string(), 20.0),
+                ("Deductible".to_string(), center_align_x("Deductible", SUMMARY_AMOUNT_X, SUMMARY_AMOUNT_RIGHT_EDGE_MM, 12.0)),
+            ],
+            font_size: 12.0,
+            spacing_mm: 8.0,
+            font: header_font.clone(),
+        });
+
+        for category_id in ordered_category_ids(&self.category_order, category_totals) {
+            let category_name = self.categories.get(&category_id)
+                .map(|info| info.name.as_str())
+                .unwrap_or(&category_id);
+            let total_text = self.format_for(&category_id).format_accounting(category_totals[&category_id]);
+            let layer = cursor.ensure_space(12.0);
+            layer.use_text(category_name, 12.0, Mm(20.0), cursor.y_pos, body_font);
+            layer.use_text(&total_text, 12.0, Mm(right_align_x_clamped(&total_text, SUMMARY_AMOUNT_RIGHT_EDGE_MM, SUMMARY_AMOUNT_X, 12.0)), cursor.y_pos, body_font);
+            cursor.y_pos -= Mm(12.0);
+        }
+
+        cursor.end_table();
+        let mut layer = cursor.ensure_space(30.0);
+        cursor.y_pos -= Mm(6.0);
+        layer.add_line_break();
+        cursor.y_pos -= Mm(10.0);
+
+        for group in self.totals_by_currency(category_totals) {
+            layer = cursor.ensure_space(12.0);
+            let overall_total = request.rounding.round(group.totals.values().sum());
+            let overall_total_text = group.format.format_accounting(overall_total);
+            layer.use_text(format!("Total Deductible{}:", group.suffix), 16.0, Mm(20.0), cursor.y_pos, header_font);
+            layer.use_text(&overall_total_text, 16.0, Mm(right_align_x_clamped(&overall_total_text, SUMMARY_AMOUNT_RIGHT_EDGE_MM, SUMMARY_AMOUNT_X, 16.0)), cursor.y_pos, header_font);
+            cursor.y_pos -= Mm(12.0);
+
+            for (jurisdiction, total) in self.jurisdiction_totals(&group.totals) {
+                let label = format!("{} Deductible{}:", jurisdiction, group.suffix);
+                let total_text = group.format.format_accounting(request.rounding.round(total));
+                layer = cursor.ensure_space(12.0);
+                layer.use_text(&label, 12.0, Mm(20.0), cursor.y_pos, body_font);
+                layer.use_text(&total_text, 12.0, Mm(right_align_x_clamped(&total_text, SUMMARY_AMOUNT_RIGHT_EDGE_MM, SUMMARY_AMOUNT_X, 12.0)), cursor.y_pos, body_font);
+                cursor.y_pos -= Mm(12.0);
+            }
+        }
+    }
+
+    /// Deductible totals for each jurisdiction any reported category has a
+    /// treatment for, sorted by jurisdiction name. Each is the sum of the
+    /// category totals `TaxDeductionInfo::deductible_in` that jurisdiction;
+    /// with no jurisdictions configured there's nothing beyond the overall
+    /// total to show, so this is empty.
+    fn jurisdiction_totals(&self, category_totals: &HashMap<String, f64>) -> Vec<(String, f64)> {
+        let mut jurisdictions: Vec<&str> = self.categories.values()
+            .flat_map(|info| info.tax_deduction.jurisdictions.iter().map(|t| t.jurisdiction.as_str()))
+            .collect();
+        jurisdictions.sort();
+        jurisdictions.dedup();
+
+        jurisdictions.into_iter()
+            .map(|jurisdiction| {
+                let total = category_totals.iter()
+                    .filter(|(category_id, _)| self.categories.get(*category_id)
+                        .is_none_or(|info| info.tax_deduction.deductible_in(jurisdiction)))
+                    .map(|(_, total)| total)
+                    .sum();
+                (jurisdiction.to_string(), total)
+            })
+            .collect()
+    }
+
+    fn load_font(&self, doc: &PdfDocumentReference, variant: &FontVariant) -> Result<IndirectFontRef, Box<dyn std::error::Error>> {
+        if let Some(builtin) = variant.get_builtin_font() {
+            Ok(doc.add_builtin_font(builtin)?)
+        } else {
+            match variant {
+                FontVariant::RobotoRegular => {
+                    Ok(doc.add_external_font(&include_bytes!("../assets/fonts/Roboto-Regular.ttf")[..])?)
+                },
+                FontVariant::RobotoBold => {
+                    Ok(doc.add_external_font(&include_bytes!("../assets/fonts/Roboto-Bold.ttf")[..])?)
+                },
+                FontVariant::RobotoItalic => {
+                    Ok(doc.add_external_font(&include_bytes!("../assets/fonts/Roboto-Italic.ttf")[..])?)
+                },
+                FontVariant::RobotoBoldItalic => {
+                    Ok(doc.add_external_font(&include_bytes!("../assets/fonts/Roboto-BoldItalic.ttf")[..])?)
+                },
+                _ => Ok(doc.add_builtin_font(BuiltinFont::TimesRoman)?),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ReimbursementStatus;
+
+    fn flow(id: &str, date: NaiveDate, custom_fields: HashMap<String, String>) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date,
+            amount: 10.0,
+            category_id: "cat-1".to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields,
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    // --- TimePeriod::default_for ---
+
+    #[test]
+    fn default_for_is_last_year_on_or_before_april() {
+        for month in 1..=4 {
+            let today = NaiveDate::from_ymd_opt(2024, month, 15).unwrap();
+            assert_eq!(TimePeriod::default_for(today), TimePeriod::LastYear, "month {}", month);
+        }
+    }
+
+    #[test]
+    fn default_for_is_this_year_after_april() {
+        for month in 5..=12 {
+            let today = NaiveDate::from_ymd_opt(2024, month, 15).unwrap();
+            assert_eq!(TimePeriod::default_for(today), TimePeriod::ThisYear, "month {}", month);
+        }
+    }
+
+    // --- TimePeriod::contains ---
+
+    #[test]
+    fn last_year_covers_the_full_previous_calendar_year() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let period = TimePeriod::LastYear;
+
+        assert!(period.contains(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), today));
+        assert!(period.contains(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(), today));
+        assert!(!period.contains(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), today), "current year's Jan 1 is excluded");
+        assert!(!period.contains(NaiveDate::from_ymd_opt(2022, 12, 31).unwrap(), today), "the year before last is excluded");
+    }
+
+    #[test]
+    fn this_year_covers_january_first_through_today_inclusive() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let period = TimePeriod::ThisYear;
+
+        assert!(period.contains(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), today));
+        assert!(period.contains(today, today));
+        assert!(!period.contains(NaiveDate::from_ymd_opt(2024, 6, 16).unwrap(), today), "dates after today are excluded");
+        assert!(!period.contains(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(), today), "last year is excluded");
+    }
+
+    #[test]
+    fn custom_range_is_inclusive_on_both_ends() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let period = TimePeriod::Custom(
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+        );
+
+        assert!(period.contains(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), today));
+        assert!(period.contains(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(), today));
+        assert!(!period.contains(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(), today));
+        assert!(!period.contains(NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(), today));
+    }
+
+    // --- ReportRequest::includes_category ---
+
+    #[test]
+    fn no_category_selection_includes_every_category_and_an_empty_one_none() {
+        let request = ReportRequest::default();
+        assert!(request.includes_category("cat-1"));
+        assert!(request.includes_category("anything"));
+
+        let request = ReportRequest { selected_categories: Some(Vec::new()), ..ReportRequest::default() };
+        assert!(!request.includes_category("cat-1"));
+    }
+
+    #[test]
+    fn category_selection_limits_the_report_to_those_categories() {
+        let request = ReportRequest {
+            selected_categories: Some(vec!["cash_donations".to_string(), "goods_donations".to_string()]),
+            ..ReportRequest::default()
+        };
+        assert!(request.includes_category("cash_donations"));
+        assert!(request.includes_category("goods_donations"));
+        assert!(!request.includes_category("medical"));
+    }
+
+    // --- ReportKind::TaxDeductions ---
+
+    #[test]
+    fn deduction_report_covers_only_deductible_flows_and_their_refunds() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let deductible = Flow { tax_deductible: Some(true), ..flow("deductible", date, HashMap::new()) };
+        let refund = Flow { refund_of: Some("deductible".to_string()), ..flow("refund", date, HashMap::new()) };
+        let not_deductible = Flow { tax_deductible: Some(false), ..flow("not-deductible", date, HashMap::new()) };
+        let unset = flow("unset", date, HashMap::new());
+
+        let request = ReportRequest { kind: ReportKind::TaxDeductions, ..csv_request() };
+        let generator = csv_generator(vec![deductible, refund, not_deductible, unset]);
+        let (category_flows, _) = generator.report_flows(&request);
+
+        let mut ids: Vec<&str> = category_flows["cat-1"].iter().map(|f| f.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["deductible", "refund"]);
+    }
+
+    // --- ReportKind::OutstandingReimbursements ---
+
+    #[test]
+    fn outstanding_reimbursement_report_skips_paid_back_and_non_reimbursable_flows() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let with_status = |id: &str, status: ReimbursementStatus| Flow { reimbursement: Some(status), ..flow(id, date, HashMap::new()) };
+        let flows = vec![
+            with_status("outstanding", ReimbursementStatus::Outstanding),
+            with_status("submitted", ReimbursementStatus::Submitted),
+            with_status("reimbursed", ReimbursementStatus::Reimbursed),
+            flow("ordinary", date, HashMap::new()),
+        ];
+
+        let request = ReportRequest { kind: ReportKind::OutstandingReimbursements, ..csv_request() };
+        let generator = csv_generator(flows);
+        let (category_flows, _) = generator.report_flows(&request);
+
+        let mut ids: Vec<&str> = category_flows["cat-1"].iter().map(|f| f.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["outstanding", "submitted"]);
+    }
+
+    #[test]
+    fn picked_flows_are_reported_whatever_the_other_filters_say() {
+        let in_period = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let out_of_period = NaiveDate::from_ymd_opt(2019, 6, 1).unwrap();
+        let flows = vec![
+            flow("picked", in_period, HashMap::new()),
+            flow("picked-old", out_of_period, HashMap::new()),
+            flow("not-picked", in_period, HashMap::new()),
+        ];
+
+        let request = ReportRequest {
+            selected_flows: vec!["picked".to_string(), "picked-old".to_string()],
+            ..csv_request()
+        };
+        let generator = csv_generator(flows);
+        let (category_flows, _) = generator.report_flows(&request);
+
+        let mut ids: Vec<&str> = category_flows["cat-1"].iter().map(|f| f.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["picked", "picked-old"]);
+    }
+
+    #[test]
+    fn flow_type_filter_limits_the_report_to_income_or_expense_categories() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let salary = Flow { category_id: "salary".to_string(), ..flow("salary", date, HashMap::new()) };
+        let orphan = Flow { category_id: "deleted".to_string(), ..flow("orphan", date, HashMap::new()) };
+        let mut generator = csv_generator(vec![flow("donation", date, HashMap::new()), salary, orphan]);
+        generator.categories.insert("salary".to_string(), ReportCategoryInfo {
+            name: "Salary".to_string(),
+            flow_type: FlowType::Income,
+            fields: Vec::new(),
+            tax_deduction: no_tax_deduction(),
+            number_format: None,
+            color: None,
+            icon: None,
+        });
+
+        let categories_for = |flow_types| {
+            let request = ReportRequest { flow_types, ..csv_request() };
+            let (category_flows, _) = generator.report_flows(&request);
+            let mut ids: Vec<String> = category_flows.into_keys().collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(categories_for(ReportFlowTypes::Both), vec!["cat-1", "deleted", "salary"]);
+        assert_eq!(categories_for(ReportFlowTypes::IncomeOnly), vec!["salary"]);
+        assert_eq!(categories_for(ReportFlowTypes::ExpenseOnly), vec!["cat-1"]);
+    }
+
+    #[test]
+    fn preview_shows_grouped_rows_and_summary_totals() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let with_charity = |id: &str, charity: &str| {
+            let mut fields = HashMap::new();
+            fields.insert("charity".to_string(), charity.to_string());
+            flow(id, date, fields)
+        };
+        let generator = csv_generator(vec![with_charity("a", "Red Cross"), with_charity("b", "Oxfam"), with_charity("c", "Red Cross")]);
+        let request = ReportRequest { group_by: Some("charity".to_string()), title: "Gifts".to_string(), ..csv_request() };
+
+        let preview = generator.preview(&request);
+
+        assert_eq!(preview.title, "Gifts");
+        assert_eq!(preview.categories.len(), 1);
+        let category = &preview.categories[0];
+        assert_eq!(category.name, "Donations");
+        assert_eq!(category.columns, vec!["Date", "Amount", "Description"], "the group-by field isn't a column");
+        let headings: Vec<_> = category.groups.iter().map(|g| g.heading.clone().unwrap()).collect();
+        assert_eq!(headings, vec!["charity: Oxfam", "charity: Red Cross"]);
+        assert_eq!(category.groups[1].rows.len(), 2);
+        assert_eq!(category.groups[1].total.as_deref(), Some("$20.00"));
+        assert_eq!(category.total, "$30.00");
+        assert_eq!(preview.summary, vec![
+            ("Donations".to_string(), "$30.00".to_string()),
+            ("Total Expense:".to_string(), "$30.00".to_string()),
+            ("Net Total:".to_string(), "$30.00".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn categories_in_another_currency_get_their_own_summary_totals() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let rent = Flow { category_id: "rent".to_string(), amount: 500.0, ..flow("rent", date, HashMap::new()) };
+        let mut generator = csv_generator(vec![flow("donation", date, HashMap::new()), rent]);
+        let euros = NumberFormat { currency_symbol: "€".to_string(), ..NumberFormat::default() };
+        generator.categories.insert("rent".to_string(), category_info("Rental Upkeep", FlowType::Expense).with_number_format(Some(euros)));
+
+        let preview = generator.preview(&csv_request());
+
+        let rent = preview.categories.iter().find(|c| c.name == "Rental Upkeep").unwrap();
+        assert_eq!(rent.groups[0].rows[0][1], "€500.00");
+        assert_eq!(rent.total, "€500.00");
+        assert_eq!(preview.summary, vec![
+            ("Donations".to_string(), "$10.00".to_string()),
+            ("Total Expense ($):".to_string(), "$10.00".to_string()),
+            ("Net Total ($):".to_string(), "$10.00".to_string()),
+            ("Rental Upkeep".to_string(), "€500.00".to_string()),
+            ("Total Expense (€):".to_string(), "€500.00".to_string()),
+            ("Net Total (€):".to_string(), "€500.00".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn group_aggregates_are_listed_per_group_in_the_requested_order() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let with_charity = |id: &str, charity: &str, amount: f64, deductible: Option<bool>| {
+            let mut fields = HashMap::new();
+            fields.insert("charity".to_string(), charity.to_string());
+            Flow { amount, tax_deductible: deductible, ..flow(id, date, fields) }
+        };
+        let generator = csv_generator(vec![
+            with_charity("a", "Red Cross", 10.0, Some(true)),
+            with_charity("b", "Red Cross", 25.0, None),
+            with_charity("c", "Oxfam", 5.0, None),
+        ]);
+        let request = ReportRequest {
+            group_by: Some("charity".to_string()),
+            group_aggregates: vec![GroupAggregate::Count, GroupAggregate::Average, GroupAggregate::Maximum, GroupAggregate::DeductibleSubtotal],
+            ..csv_request()
+        };
+
+        let preview = generator.preview(&request);
+
+        let red_cross = &preview.categories[0].groups[1];
+        assert_eq!(red_cross.aggregates, vec![
+            ("Count".to_string(), "2".to_string()),
+            ("Average".to_string(), "$17.50".to_string()),
+            ("Maximum".to_string(), "$25.00".to_string()),
+            ("Deductible Subtotal".to_string(), "$10.00".to_string()),
+        ]);
+        assert!(preview.to_markdown().contains("Average: $17.50"));
+    }
+
+    #[test]
+    fn xlsx_report_is_a_workbook_even_with_unparseable_typed_fields() {
+        let mut fields = HashMap::new();
+        fields.insert("charity".to_string(), "Red Cross".to_string());
+        fields.insert("pledged".to_string(), "not a number".to_string());
+        let mut generator = csv_generator(vec![flow("a", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(), fields)]);
+        let pledged = CategoryField { field_type: FieldType::Currency, ..text_field("pledged") };
+        generator.categories.get_mut("cat-1").unwrap().fields.push(pledged);
+
+        let request = ReportRequest { output_format: ReportFormat::Xlsx, ..csv_request() };
+        let data = generator.generate_report(&request).expect("xlsx generates");
+
+        assert!(data.starts_with(b"PK"), "an XLSX file is a zip archive");
+    }
+
+    #[test]
+    fn markdown_report_has_a_table_per_category_and_escapes_pipes() {
+        let mut piped = flow("a", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(), HashMap::new());
+        piped.description = "Coat | scarf\nand hat".to_string();
+        let request = ReportRequest { output_format: ReportFormat::Markdown, title: "Gifts".to_string(), ..csv_request() };
+
+        let data = csv_generator(vec![piped]).generate_report(&request).unwrap();
+        let markdown = String::from_utf8(data).unwrap();
+
+        assert!(markdown.starts_with("# Gifts\n"));
+        assert!(markdown.contains("\n## Donations\n\n| Date | Amount | Description | Charity |\n|---|---|---|---|\n"));
+        assert!(markdown.contains("| January 05, 2024 | $10.00 | Coat \\| scarf and hat |  |\n"));
+        assert!(markdown.contains("Category Total: **$10.00**"));
+        assert!(markdown.contains("\n## Summary\n"));
+        assert!(markdown.contains("| Net Total: | $10.00 |"));
+    }
+
+    fn no_tax_deduction() -> TaxDeductionInfo {
+        TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() }
+    }
+
+    #[test]
+    fn jurisdiction_totals_exclude_categories_a_jurisdiction_does_not_allow() {
+        use crate::models::JurisdictionTreatment;
+
+        let treatment = |jurisdiction: &str, deductible: bool| JurisdictionTreatment {
+            jurisdiction: jurisdiction.to_string(),
+            deductible,
+        };
+        let mut medical = category_info("Medical", FlowType::Expense);
+        medical.tax_deduction.jurisdictions = vec![treatment("Federal", true), treatment("State", false)];
+        let donations = category_info("Donations", FlowType::Expense);
+
+        let mut categories = HashMap::new();
+        categories.insert("medical".to_string(), medical);
+        categories.insert("donations".to_string(), donations);
+        let generator = ReportGenerator::new(Vec::new(), categories, Vec::new());
+
+        let mut category_totals = HashMap::new();
+        category_totals.insert("medical".to_string(), 300.0);
+        category_totals.insert("donations".to_string(), 50.0);
+
+        // Donations has no treatments, so every jurisdiction follows the
+        // flows' own deductible flags and counts it.
+        assert_eq!(
+            generator.jurisdiction_totals(&category_totals),
+            vec![("Federal".to_string(), 350.0), ("State".to_string(), 50.0)]
+        );
+    }
+
+    // --- Monthly breakdown ---
+
+    #[test]
+    fn monthly_totals_fold_each_calendar_month_and_end_with_the_overall_total() {
+        let flows = [
+            flow("jan-2024", NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(), HashMap::new()),
+            flow("jan-2025", NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(), HashMap::new()),
+            flow("dec", NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(), HashMap::new()),
+        ];
+        let refs: Vec<&Flow> = flows.iter().collect();
+        let amount = flows[0].amount;
+
+        let totals = monthly_totals(&refs, &RoundingRule::default());
+        assert_eq!(totals.len(), 13);
+        assert_eq!(totals[0], 2.0 * amount);
+        assert_eq!(totals[1], 0.0);
+        assert_eq!(totals[11], amount);
+        assert_eq!(totals[12], 3.0 * amount);
+    }
+
+    // --- RoundingRule ---
+
+    #[test]
+    fn rounding_each_item_and_rounding_totals_can_disagree() {
+        let amounts = [10.40, 10.40, 10.40];
+        let each_item = RoundingRule { precision: RoundingPrecision::Dollar, stage: RoundingStage::EachItem };
+        let totals = RoundingRule { precision: RoundingPrecision::Dollar, stage: RoundingStage::Totals };
+
+        assert_eq!(each_item.total(amounts), 30.0);
+        assert_eq!(totals.total(amounts), 31.0);
+        assert_eq!(each_item.item(10.40), 10.0);
+        assert_eq!(totals.item(10.40), 10.40, "items stay unrounded when only totals are rounded");
+    }
+
+    #[test]
+    fn rounding_rounds_half_away_from_zero() {
+        let dollars = RoundingRule { precision: RoundingPrecision::Dollar, stage: RoundingStage::EachItem };
+        assert_eq!(dollars.round(12.50), 13.0);
+        assert_eq!(dollars.round(-12.50), -13.0);
+
+        let cents = RoundingRule::default();
+        assert_eq!(cents.round(0.125), 0.13);
+        assert_eq!(cents.total([0.333, 0.333, 0.333]), 1.0);
+    }
+
+    // --- CSV output ---
+
+    fn csv_generator(flows: Vec<Flow>) -> ReportGenerator {
+        let mut categories = HashMap::new();
+        categories.insert("cat-1".to_string(), ReportCategoryInfo {
+            name: "Donations".to_string(),
+            flow_type: FlowType::Expense,
+            fields: vec![text_field("charity")],
+            tax_deduction: no_tax_deduction(),
+            number_format: None,
+            color: None,
+            icon: None,
+        });
+        ReportGenerator::new(flows, categories, vec!["cat-1".to_string()])
+    }
+
+    fn csv_request() -> ReportRequest {
+        ReportRequest {
+            time_period: TimePeriod::Custom(
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            ),
+            output_format: ReportFormat::Csv,
+            ..ReportRequest::default()
+        }
+    }
+
+    #[test]
+    fn csv_report_has_one_row_per_flow_with_custom_field_columns() {
+        let mut fields = HashMap::new();
+        fields.insert("charity".to_string(), "Red Cross".to_string());
+        let mut quoted = flow("b", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), HashMap::new());
+        quoted.description = "Coat, \"wool\"".to_string();
+        let flows = vec![
+            flow("a", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(), fields),
+            quoted,
+            flow("old", NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), HashMap::new()),
+        ];
+
+        let csv = String::from_utf8(csv_generator(flows).generate_report(&csv_request()).unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines, vec![
+            "Category,Date,Amount,Description,Charity",
+            "Donations,2024-01-05,10.00,,Red Cross",
+            "Donations,2024-02-01,10.00,\"Coat, \"\"wool\"\"\",",
+        ]);
+    }
+
+    #[test]
+    fn csv_report_leaves_out_unselected_field_columns() {
+        let mut fields = HashMap::new();
+        fields.insert("charity".to_string(), "Red Cross".to_string());
+        let flows = vec![flow("a", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(), fields)];
+        // The dialog's "None" placeholder: a selection no field matches.
+        let request = ReportRequest { selected_fields: Some(Vec::new()), ..csv_request() };
+
+        let csv = String::from_utf8(csv_generator(flows).generate_report(&request).unwrap()).unwrap();
+        assert_eq!(csv.lines().collect::<Vec<_>>(), vec![
+            "Category,Date,Amount,Description",
+            "Donations,2024-01-05,10.00,",
+        ]);
+    }
+
+    #[test]
+    fn csv_report_adds_a_group_column_and_orders_rows_by_group() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let with_charity = |id: &str, charity: &str| {
+            let mut fields = HashMap::new();
+            fields.insert("charity".to_string(), charity.to_string());
+            flow(id, date, fields)
+        };
+        let flows = vec![with_charity("a", "Zoo"), with_charity("b", "Animal Shelter"), with_charity("c", "Zoo")];
+        let request = ReportRequest { group_by: Some("charity".to_string()), ..csv_request() };
+
+        let csv = String::from_utf8(csv_generator(flows).generate_report(&request).unwrap()).unwrap();
+        let groups: Vec<&str> = csv.lines().skip(1)
+            .map(|line| line.rsplit(',').next().unwrap())
+            .collect();
+        assert_eq!(csv.lines().next().unwrap(), "Category,Date,Amount,Description,Charity");
+        assert_eq!(groups, vec!["Animal Shelter", "Zoo", "Zoo"]);
+    }
+
+    // --- group_flows_by_field ---
+
+    #[test]
+    fn group_flows_by_field_groups_matching_values_together() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut fields_a = HashMap::new();
+        fields_a.insert("vendor".to_string(), "Acme".to_string());
+        let mut fields_b = HashMap::new();
+        fields_b.insert("vendor".to_string(), "Acme".to_string());
+        let mut fields_c = HashMap::new();
+        fields_c.insert("vendor".to_string(), "Other".to_string());
+
+        let flow_a = flow("a", date, fields_a);
+        let flow_b = flow("b", date, fields_b);
+        let flow_c = flow("c", date, fields_c);
+        let flows: Vec<&Flow> = vec![&flow_a, &flow_b, &flow_c];
+
+        let grouped = group_flows_by_field(&flows, "vendor");
+
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped.get("Acme").unwrap().len(), 2);
+        assert_eq!(grouped.get("Other").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn group_flows_by_field_buckets_missing_values_instead_of_dropping_them() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut fields_a = HashMap::new();
+        fields_a.insert("vendor".to_string(), "Acme".to_string());
+        let fields_b = HashMap::new(); // no "vendor" field at all
+
+        let flow_a = flow("a", date, fields_a);
+        let flow_b = flow("b", date, fields_b);
+        let flows: Vec<&Flow> = vec![&flow_a, &flow_b];
+
+        let grouped = group_flows_by_field(&flows, "vendor");
+
+        let total_grouped_flows: usize = grouped.values().map(|v| v.len()).sum();
+        assert_eq!(total_grouped_flows, 2, "every flow should end up in some group, so group totals sum to the category total");
+        assert_eq!(grouped.get("Acme").unwrap().len(), 1);
+        assert_eq!(grouped.get(UNSET_GROUP_LABEL).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn group_flows_by_field_treats_empty_and_whitespace_values_as_unset() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut fields_a = HashMap::new();
+        fields_a.insert("vendor".to_string(), "".to_string());
+        let mut fields_b = HashMap::new();
+        fields_b.insert("vendor".to_string(), "   ".to_string());
+
+        let flow_a = flow("a", date, fields_a);
+        let flow_b = flow("b", date, fields_b);
+        let flows: Vec<&Flow> = vec![&flow_a, &flow_b];
+
+        let grouped = group_flows_by_field(&flows, "vendor");
+
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped.get(UNSET_GROUP_LABEL).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn group_flows_by_field_empty_input_yields_empty_map() {
+        let flows: Vec<&Flow> = Vec::new();
+        let grouped = group_flows_by_field(&flows, "vendor");
+        assert!(grouped.is_empty());
+    }
+
+    fn category_info(name: &str, flow_type: FlowType) -> ReportCategoryInfo {
+        ReportCategoryInfo { name: name.to_string(), flow_type, fields: Vec::new(), tax_deduction: no_tax_deduction(), number_format: None, color: None, icon: None }
+    }
+
+    // --- net_total ---
+
+    #[test]
+    fn net_total_subtracts_expenses_from_income() {
+        let mut category_totals = HashMap::new();
+        category_totals.insert("salary".to_string(), 5000.0);
+        category_totals.insert("rent".to_string(), 2000.0);
+
+        let mut categories = HashMap::new();
+        categories.insert("salary".to_string(), category_info("Salary", FlowType::Income));
+        categories.insert("rent".to_string(), category_info("Rent", FlowType::Expense));
+
+        assert_eq!(net_total(&category_totals, &categories), 3000.0);
+    }
+
+    #[test]
+    fn net_total_all_income_sums_directly() {
+        let mut category_totals = HashMap::new();
+        category_totals.insert("salary".to_string(), 1000.0);
+        category_totals.insert("bonus".to_string(), 500.0);
+
+        let mut categories = HashMap::new();
+        categories.insert("salary".to_string(), category_info("Salary", FlowType::Income));
+        categories.insert("bonus".to_string(), category_info("Bonus", FlowType::Income));
+
+        assert_eq!(net_total(&category_totals, &categories), 1500.0);
+    }
+
+    #[test]
+    fn net_total_all_expenses_is_negative() {
+        let mut category_totals = HashMap::new();
+        category_totals.insert("rent".to_string(), 2000.0);
+
+        let mut categories = HashMap::new();
+        categories.insert("rent".to_string(), category_info("Rent", FlowType::Expense));
+
+        assert_eq!(net_total(&category_totals, &categories), -2000.0);
+    }
+
+    #[test]
+    fn net_total_excludes_categories_with_no_matching_entry() {
+        let mut category_totals = HashMap::new();
+        category_totals.insert("salary".to_string(), 1000.0);
+        category_totals.insert("deleted-category".to_string(), 9999.0);
+
+        let mut categories = HashMap::new();
+        categories.insert("salary".to_string(), category_info("Salary", FlowType::Income));
+        // "deleted-category" intentionally has no entry in `categories`.
+
+        assert_eq!(
+            net_total(&category_totals, &categories),
+            1000.0,
+            "a category with no matching entry should contribute nothing, not be guessed as income"
+        );
+    }
+
+    #[test]
+    fn net_total_empty_input_is_zero() {
+        assert_eq!(net_total(&HashMap::new(), &HashMap::new()), 0.0);
+    }
+
+    // --- format_currency ---
+
+    #[test]
+    fn format_currency_positive_has_no_parentheses() {
+        assert_eq!(NumberFormat::default().format_accounting(100.0), "$100.00");
+    }
+
+    #[test]
+    fn format_currency_negative_uses_parentheses_not_a_minus_sign() {
+        assert_eq!(NumberFormat::default().format_accounting(-100.0), "($100.00)");
+    }
+
+    #[test]
+    fn format_currency_zero_has_no_parentheses() {
+        assert_eq!(NumberFormat::default().format_accounting(0.0), "$0.00");
+    }
+
+    #[test]
+    fn format_currency_thousands_get_a_separator() {
+        assert_eq!(NumberFormat::default().format_accounting(1234.5), "$1,234.50");
+    }
+
+    #[test]
+    fn format_currency_millions_get_two_separators() {
+        assert_eq!(NumberFormat::default().format_accounting(12345678.9), "$12,345,678.90");
+    }
+
+    #[test]
+    fn format_currency_negative_thousands_use_parentheses_with_separator() {
+        assert_eq!(NumberFormat::default().format_accounting(-1234.5), "($1,234.50)");
+    }
+
+    #[test]
+    fn format_currency_under_a_thousand_has_no_separator() {
+        assert_eq!(NumberFormat::default().format_accounting(999.99), "$999.99");
+    }
+
+    // --- summary_display_value ---
+
+    #[test]
+    fn summary_display_value_reverses_income_to_negative() {
+        // Deliberately the opposite of standard accounting sign convention --
+        // see the doc comment on `summary_display_value` for why.
+        assert_eq!(summary_display_value(500.0, &FlowType::Income), -500.0);
+    }
+
+    #[test]
+    fn summary_display_value_reverses_expense_to_positive() {
+        assert_eq!(summary_display_value(500.0, &FlowType::Expense), 500.0);
+    }
+
+    #[test]
+    fn summary_display_value_and_format_currency_together_show_income_in_parens() {
+        // The combination is what actually appears on the page: an Income
+        // category's raw (positive, unsigned) total should render inside
+        // parentheses, not as a plain positive number.
+        let displayed = summary_display_value(500.0, &FlowType::Income);
+        assert_eq!(NumberFormat::default().format_accounting(displayed), "($500.00)");
+    }
+
+    // --- visible_custom_fields ---
+
+    fn text_field(name: &str) -> CategoryField {
+        CategoryField { name: name.to_string(), field_type: FieldType::Text, required: false, default_value: None }
+    }
+
+    #[test]
+    fn visible_custom_fields_returns_all_when_not_grouping() {
+        let fields = vec![text_field("recipient"), text_field("notes")];
+        let visible = visible_custom_fields(&fields, &None, None);
+        assert_eq!(visible.len(), 2);
+    }
+
+    #[test]
+    fn visible_custom_fields_excludes_the_active_group_by_field() {
+        let fields = vec![text_field("recipient"), text_field("notes")];
+        let visible = visible_custom_fields(&fields, &Some("recipient".to_string()), None);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].name, "notes");
+    }
+
+    #[test]
+    fn visible_custom_fields_unaffected_by_unrelated_group_by_value() {
+        // Grouping by a field name that isn't one of this category's fields
+        // (e.g. another category's field) shouldn't hide anything here.
+        let fields = vec![text_field("recipient")];
+        let visible = visible_custom_fields(&fields, &Some("some_other_field".to_string()), None);
+        assert_eq!(visible.len(), 1);
+    }
+
+    #[test]
+    fn visible_custom_fields_limits_columns_to_the_selected_fields() {
+        let fields = vec![text_field("recipient"), text_field("notes"), text_field("provider")];
+        let selected = vec!["provider".to_string(), "recipient".to_string()];
+        let visible: Vec<&str> = visible_custom_fields(&fields, &None, Some(&selected)).iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(visible, vec!["recipient", "provider"]);
+
+        let visible = visible_custom_fields(&fields, &Some("recipient".to_string()), Some(&selected));
+        assert_eq!(visible.len(), 1, "the group-by field is still left out");
+    }
+
+    // --- group_by_applies_to_category ---
+
+    #[test]
+    fn group_by_applies_when_category_has_the_field() {
+        let fields = vec![text_field("recipient")];
+        assert!(group_by_applies_to_category(&Some("recipient".to_string()), &fields));
+    }
+
+    #[test]
+    fn group_by_does_not_apply_when_category_lacks_the_field() {
+        // This is the bug this function fixes: a report spans many
+        // categories, and a "Group By" selection picked for one category
+        // (e.g. Donations' "recipient") shouldn't cause a category that never
+        // had that field (e.g. Salary) to render zero detail rows.
+        let fields = vec![text_field("some_other_field")];
+        assert!(!group_by_applies_to_category(&Some("recipient".to_string()), &fields));
+    }
+
+    #[test]
+    fn group_by_does_not_apply_when_nothing_is_selected() {
+        let fields = vec![text_field("recipient")];
+        assert!(!group_by_applies_to_category(&None, &fields));
+    }
+
+    #[test]
+    fn group_by_does_not_apply_to_a_category_with_no_fields_at_all() {
+        assert!(!group_by_applies_to_category(&Some("recipient".to_string()), &[]));
+    }
+
+    // --- format_field_value ---
+
+    fn flow_with_custom_field(name: &str, value: &str) -> Flow {
+        let mut custom_fields = HashMap::new();
+        custom_fields.insert(name.to_string(), value.to_string());
+        flow("f", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), custom_fields)
+    }
+
+    #[test]
+    fn format_field_value_missing_value_is_empty_string() {
+        let field = text_field("recipient");
+        let f = flow("f", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), HashMap::new());
+        assert_eq!(format_field_value(&field, &f, &NumberFormat::default()), "");
+    }
+
+    #[test]
+    fn format_field_value_text_passes_through_unchanged() {
+        let field = text_field("recipient");
+        let f = flow_with_custom_field("recipient", "Goodwill");
+        assert_eq!(format_field_value(&field, &f, &NumberFormat::default()), "Goodwill");
+    }
+
+    #[test]
+    fn format_field_value_boolean_renders_yes_no() {
+        let field = CategoryField { name: "covered".to_string(), field_type: FieldType::Boolean, required: false, default_value: None };
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("covered", "true"), &NumberFormat::default()), "Yes");
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("covered", "false"), &NumberFormat::default()), "No");
+    }
+
+    #[test]
+    fn format_field_value_currency_normalizes_symbols() {
+        let field = CategoryField { name: "cost".to_string(), field_type: FieldType::Currency, required: false, default_value: None };
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("cost", "$1,234.5"), &NumberFormat::default()), "$1,234.50");
+    }
+
+    #[test]
+    fn format_field_value_invalid_number_falls_back_to_raw_value() {
+        let field = CategoryField { name: "count".to_string(), field_type: FieldType::Integer, required: false, default_value: None };
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("count", "not-a-number"), &NumberFormat::default()), "not-a-number");
+    }
+
+    #[test]
+    fn format_field_value_integer_gets_a_thousands_separator() {
+        let field = CategoryField { name: "count".to_string(), field_type: FieldType::Integer, required: false, default_value: None };
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("count", "1234567"), &NumberFormat::default()), "1,234,567");
+    }
+
+    #[test]
+    fn format_field_value_negative_integer_keeps_the_minus_sign() {
+        let field = CategoryField { name: "count".to_string(), field_type: FieldType::Integer, required: false, default_value: None };
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("count", "-1234"), &NumberFormat::default()), "-1,234");
+    }
+
+    #[test]
+    fn format_field_value_float_gets_a_thousands_separator() {
+        let field = CategoryField { name: "amount".to_string(), field_type: FieldType::Float, required: false, default_value: None };
+        assert_eq!(format_field_value(&field, &flow_with_custom_field("amount", "1234567.5"), &NumberFormat::default()), "1,234,567.50");
+    }
+
+    // --- wrap_text ---
+
+    #[test]
+    fn wrap_text_fits_within_char_budget() {
+        let lines = wrap_text("Hello world this is a test", 10);
+        for line in &lines {
+            assert!(line.chars().count() <= 10, "line {:?} exceeds the budget", line);
+        }
+        // Rejoining should reproduce the original words in order.
+        assert_eq!(lines.join(" "), "Hello world this is a test");
+    }
+
+    #[test]
+    fn wrap_text_empty_string_yields_one_empty_line() {
+        assert_eq!(wrap_text("", 10), vec!["".to_string()]);
+    }
+
+    #[test]
+    fn wrap_text_word_longer_than_budget_gets_its_own_line() {
+        let lines = wrap_text("supercalifragilisticexpialidocious short", 10);
+        assert_eq!(lines[0], "supercalifragilisticexpialidocious");
+        assert_eq!(lines[1], "short");
+    }
+
+    #[test]
+    fn wrap_text_short_text_is_a_single_line() {
+        assert_eq!(wrap_text("short", 50), vec!["short".to_string()]);
+    }
+
+    // --- max_chars_for_width / body_font_size_for_extra_columns ---
+
+    #[test]
+    fn max_chars_for_width_increases_with_more_room() {
+        let narrow = max_chars_for_width(20.0, 10.0);
+        let wide = max_chars_for_width(60.0, 10.0);
+        assert!(wide > narrow);
+    }
+
+    #[test]
+    fn max_chars_for_width_has_a_floor() {
+        assert!(max_chars_for_width(0.1, 12.0) >= 4);
+    }
+
+    // --- right_align_x ---
+
+    #[test]
+    fn right_align_x_places_longer_text_further_left() {
+        let short_x = right_align_x("$5.00", 100.0, 12.0);
+        let long_x = right_align_x("$5,000,000.00", 100.0, 12.0);
+        assert!(long_x < short_x, "wider text should start further left to keep the same right edge");
+    }
+
+    #[test]
+    fn right_align_x_estimated_right_edge_matches_the_target() {
+        let text = "$1,234.00";
+        let right_edge = 100.0;
+        let x = right_align_x(text, right_edge, 12.0);
+        assert_eq!(x + estimate_text_width_mm(text, 12.0), right_edge);
+    }
+
+    // --- right_align_x_clamped ---
+
+    #[test]
+    fn right_align_x_clamped_floors_at_the_left_edge_for_text_too_wide_to_fit() {
+        // A right edge close to the left edge, with text wide enough that a
+        // plain `right_align_x` would push it left of the column entirely.
+        let x = right_align_x_clamped("$999,999,999.99", 40.0, 20.0, 12.0);
+        assert_eq!(x, 20.0);
+    }
+
+    #[test]
+    fn right_align_x_clamped_matches_right_align_x_when_text_fits() {
+        let text = "$5.00";
+        let right_edge = 100.0;
+        let left_edge = 20.0;
+        assert_eq!(right_align_x_clamped(text, right_edge, left_edge, 12.0), right_align_x(text, right_edge, 12.0));
+    }
+
+    // --- center_align_x ---
+
+    #[test]
+    fn center_align_x_centers_text_within_the_bounds() {
+        let x = center_align_x("Amount", 55.0, 77.0, 12.0);
+        let width = estimate_text_width_mm("Amount", 12.0);
+        // Left and right gaps around the text should be equal.
+        assert!((x - 55.0 - (77.0 - 55.0 - width) / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn center_align_x_floors_at_the_left_edge_for_text_too_wide_to_fit() {
+        let x = center_align_x("A Very Long Header Label Indeed", 55.0, 77.0, 12.0);
+        assert_eq!(x, 55.0);
+    }
+
+    #[test]
+    fn body_font_size_shrinks_as_extra_columns_grow() {
+        let zero = body_font_size_for_extra_columns(0, false);
+        let two = body_font_size_for_extra_columns(2, false);
+        let five = body_font_size_for_extra_columns(5, false);
+        assert!(zero > two);
+        assert!(two > five);
+    }
+
+    #[test]
+    fn body_font_size_shrinks_further_when_grouped() {
+        for extra_columns in [0, 2, 5] {
+            let ungrouped = body_font_size_for_extra_columns(extra_columns, false);
+            let grouped = body_font_size_for_extra_columns(extra_columns, true);
+            assert!(
+                grouped < ungrouped,
+                "grouped ({}) should be smaller than ungrouped ({}) at {} extra columns",
+                grouped, ungrouped, extra_columns
+            );
+        }
+    }
+
+    #[test]
+    fn body_font_size_has_a_floor_even_when_grouped() {
+        assert!(body_font_size_for_extra_columns(10, true) >= 6.0);
+    }
+
+    // --- compute_column_layout ---
+
+    #[test]
+    fn compute_column_layout_with_no_extra_fields_gives_description_the_full_remainder() {
+        let layout = compute_column_layout(0, PageGeometry::default().width_mm);
+        assert!(layout.extra_field_x.is_empty());
+        assert!(layout.amount_x > layout.date_x);
+        assert!(layout.description_x > layout.amount_x);
+        assert!(layout.column_width > 0.0);
+    }
+
+    #[test]
+    fn compute_column_layout_amount_right_edge_stays_within_the_amount_column() {
+        let layout = compute_column_layout(0, PageGeometry::default().width_mm);
+        assert!(layout.amount_right_edge_x > layout.amount_x, "right edge should be to the right of where the column starts");
+        assert!(layout.amount_right_edge_x < layout.description_x, "right edge should leave a gap before the next column");
+    }
+
+    #[test]
+    fn compute_column_layout_extra_columns_are_ordered_after_description() {
+        let layout = compute_column_layout(2, PageGeometry::default().width_mm);
+        assert_eq!(layout.extra_field_x.len(), 2);
+        assert!(layout.extra_field_x[0] > layout.description_x);
+        assert!(layout.extra_field_x[1] > layout.extra_field_x[0]);
+    }
+
+    #[test]
+    fn compute_column_layout_column_width_shrinks_as_extra_fields_increase() {
+        let no_extras = compute_column_layout(0, PageGeometry::default().width_mm);
+        let with_extras = compute_column_layout(3, PageGeometry::default().width_mm);
+        assert!(with_extras.column_width < no_extras.column_width);
+    }
+
+    // --- row_height_mm ---
+
+    fn flow_with_description(description: &str) -> Flow {
+        Flow {
+            description: description.to_string(),
+            ..flow("f", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), HashMap::new())
+        }
+    }
+
+    #[test]
+    fn row_height_mm_increases_with_more_wrapped_lines() {
+        let layout = compute_column_layout(0, PageGeometry::default().width_mm);
+        let short = flow_with_description("Short");
+        let long = flow_with_description(&"word ".repeat(50));
+
+        let short_height = row_height_mm(&short, &[], &layout, 12.0, &NumberFormat::default());
+        let long_height = row_height_mm(&long, &[], &layout, 12.0, &NumberFormat::default());
+
+        assert!(long_height > short_height);
+    }
+
+    #[test]
+    fn row_height_mm_is_never_less_than_one_line() {
+        let layout = compute_column_layout(0, PageGeometry::default().width_mm);
+        let empty = flow_with_description("");
+
+        assert!(row_height_mm(&empty, &[], &layout, 12.0, &NumberFormat::default()) > 0.0);
+    }
+
+    // --- PageCursor::ensure_space ---
+
+    #[test]
+    fn ensure_page_space_resets_y_pos_when_not_enough_room() {
+        let (doc, page1, layer1) = PdfDocument::new("Test", Mm(210.0), Mm(297.0), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::TimesRoman).unwrap();
+        let period = "Time Period: Jan 01, 2024 to Dec 31, 2024";
+        let mut cursor = PageCursor {
+            doc: &doc,
+            page: page1,
+            layer_idx: layer1,
+            geometry: PageGeometry::default(),
+            y_pos: Mm(30.0), // close to the bottom margin already
+            page_number: 1,
+            time_period_text: period,
+            chrome_font: &font,
+            table_header: None,
+        };
+
+        cursor.ensure_space(20.0);
+
+        assert!(cursor.y_pos.0 > 100.0, "y_pos should reset near the top of a new page, got {}", cursor.y_pos.0);
+        assert_eq!(cursor.page_number, 2, "starting a new page should advance the page number");
+    }
+
+    #[test]
+    fn ensure_page_space_leaves_y_pos_alone_when_room_remains() {
+        let (doc, page1, layer1) = PdfDocument::new("Test", Mm(210.0), Mm(297.0), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::TimesRoman).unwrap();
+        let period = "Time Period: Jan 01, 2024 to Dec 31, 2024";
+        let mut cursor = PageCursor {
+            doc: &doc,
+            page: page1,
+            layer_idx: layer1,
+            geometry: PageGeometry::default(),
+            y_pos: Mm(200.0),
+            page_number: 1,
+            time_period_text: period,
+            chrome_font: &font,
+            table_header: None,
+        };
+
+        cursor.ensure_space(20.0);
+
+        assert_eq!(cursor.y_pos.0, 200.0, "y_pos shouldn't change when there's already enough room");
+        assert_eq!(cursor.page_number, 1, "page number shouldn't advance when there's already enough room");
+    }
+
+    fn test_header(font: &IndirectFontRef) -> TableHeader {
+        TableHeader {
+            continued_title: Some("Category: Test (continued)".to_string()),
+            columns: vec![("Date".to_string(), 20.0), ("Amount".to_string(), 60.0)],
+            font_size: 12.0,
+            spacing_mm: 10.0,
+            font: font.clone(),
+        }
+    }
+
+    #[test]
+    fn ensure_space_repeats_table_headings_on_continuation_pages() {
+        let (doc, page1, layer1) = PdfDocument::new("Test", Mm(210.0), Mm(297.0), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::TimesRoman).unwrap();
+        let mut cursor = PageCursor {
+            doc: &doc,
+            page: page1,
+            layer_idx: layer1,
+            geometry: PageGeometry::default(),
+            y_pos: Mm(40.0),
+            page_number: 1,
+            time_period_text: "",
+            chrome_font: &font,
+            table_header: None,
+        };
+
+        cursor.begin_table(test_header(&font));
+        assert_eq!(cursor.y_pos.0, 40.0 - 10.0 - 5.0, "headings are drawn where the table begins");
+
+        cursor.ensure_space(20.0);
+        assert_eq!(cursor.page_number, 2);
+        // Continued title, then the headings and separator again.
+        assert_eq!(cursor.y_pos.0, PageGeometry::default().content_top() - 10.0 - 10.0 - 5.0);
+
+        cursor.end_table();
+        cursor.y_pos = Mm(30.0);
+        cursor.ensure_space(20.0);
+        assert_eq!(cursor.y_pos.0, PageGeometry::default().content_top(), "no headings once the table has ended");
+    }
+
+    #[test]
+    fn start_new_page_ends_the_current_table() {
+        let (doc, page1, layer1) = PdfDocument::new("Test", Mm(210.0), Mm(297.0), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::TimesRoman).unwrap();
+        let mut cursor = PageCursor {
+            doc: &doc,
+            page: page1,
+            layer_idx: layer1,
+            geometry: PageGeometry::default(),
+            y_pos: Mm(200.0),
+            page_number: 1,
+            time_period_text: "",
+            chrome_font: &font,
+            table_header: None,
+        };
+
+        cursor.begin_table(test_header(&font));
+        cursor.start_new_page();
+        assert_eq!(cursor.y_pos.0, PageGeometry::default().content_top());
+        assert!(cursor.table_header.is_none());
+    }
+
+    /// Reads the page count out of a generated PDF's page tree
+    /// (`/Type/Pages/Count N`), which `printpdf` leaves uncompressed.
+    fn pdf_page_count(pdf: &[u8]) -> usize {
+        let text = String::from_utf8_lossy(pdf);
+        let start = text.find("/Type/Pages/Count ").expect("page tree") + "/Type/Pages/Count ".len();
+        text[start..].chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap()
+    }
+
+    #[test]
+    fn hundreds_of_flows_paginate_across_as_many_pages_as_they_need() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let flows: Vec<Flow> = (0..500).map(|i| flow(&format!("flow-{}", i), date, HashMap::new())).collect();
+        let font_settings = FontSettings {
+            title_font: FontVariant::TimesBold,
+            subtitle_font: FontVariant::TimesRegular,
+            header_font: FontVariant::TimesBold,
+            body_font: FontVariant::TimesRegular,
+        };
+        let request = ReportRequest { output_format: ReportFormat::Pdf, font_settings, ..csv_request() };
+
+        let layout = compute_column_layout(1, PageGeometry::default().width_mm);
+        let body_size = body_font_size_for_extra_columns(1, false);
+        let row_height = row_height_mm(&flows[0], &[&text_field("charity")], &layout, body_size, &NumberFormat::default());
+        let rows_per_page = ((PageGeometry::default().content_top() - BOTTOM_MARGIN_MM) / row_height).floor() as usize;
+        // Cover and summary pages, plus however many the rows fill.
+        let min_pages = 2 + flows.len().div_ceil(rows_per_page);
+
+        let pdf = csv_generator(flows).generate_report(&request).unwrap();
+        assert!(pdf_page_count(&pdf) >= min_pages, "{} pages, expected at least {}", pdf_page_count(&pdf), min_pages);
+    }
+
+    #[test]
+    fn landscape_swaps_the_page_dimensions_and_fits_more_month_columns() {
+        let portrait = PageGeometry::new(PaperSize::Letter, PageOrientation::Portrait);
+        let landscape = PageGeometry::new(PaperSize::Letter, PageOrientation::Landscape);
+        assert_eq!((landscape.width_mm, landscape.height_mm), (portrait.height_mm, portrait.width_mm));
+        assert_eq!(landscape.content_top(), landscape.height_mm - 22.0);
+
+        assert_eq!(monthly_columns_per_page(PageGeometry::default().width_mm), 7);
+        assert!(monthly_columns_per_page(landscape.width_mm) > 7);
+        let wide = compute_column_layout(3, landscape.width_mm);
+        assert!(wide.column_width > compute_column_layout(3, portrait.width_mm).column_width);
+    }
+
+    #[test]
+    fn year_grid_adds_its_own_pages_to_the_pdf() {
+        let flows = || vec![flow("a", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), HashMap::new())];
+        let request = ReportRequest { output_format: ReportFormat::Pdf, ..csv_request() };
+        let without = csv_generator(flows()).generate_report(&request).unwrap();
+        let with = csv_generator(flows()).generate_report(&ReportRequest { include_year_grid: true, ..request }).unwrap();
+        // 13 columns at `monthly_columns_per_page` (7 on A4 portrait) per page.
+        assert_eq!(pdf_page_count(&with), pdf_page_count(&without) + 2);
+    }
+
+    #[test]
+    fn ordered_category_ids_follows_the_given_order() {
+        let mut present: HashMap<String, i32> = HashMap::new();
+        present.insert("b".to_string(), 1);
+        present.insert("a".to_string(), 2);
+        present.insert("c".to_string(), 3);
+
+        let order = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        assert_eq!(ordered_category_ids(&order, &present), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn ordered_category_ids_appends_ids_missing_from_order() {
+        let mut present: HashMap<String, i32> = HashMap::new();
+        present.insert("known".to_string(), 1);
+        present.insert("orphaned".to_string(), 2);
+
+        let order = vec!["known".to_string()];
+        let result = ordered_category_ids(&order, &present);
+
+        assert_eq!(result[0], "known");
+        assert!(result.contains(&"orphaned".to_string()), "an id missing from `order` must still be included, not dropped");
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn ordered_category_ids_skips_order_entries_with_no_data() {
+        let mut present: HashMap<String, i32> = HashMap::new();
+        present.insert("has_data".to_string(), 1);
+
+        let order = vec!["no_data".to_string(), "has_data".to_string()];
+        assert_eq!(ordered_category_ids(&order, &present), vec!["has_data"]);
+    }
+} 
\ No newline at end of file
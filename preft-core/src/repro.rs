@@ -0,0 +1,282 @@
+//! Repro bundles: an anonymized copy of the user's categories, flows and
+//! trips, their settings, recent logs and version details in one file, to
+//! attach to a bug report. The desktop app opens one in demo mode with
+//! `--load-repro <file>`, in memory, without touching the user's own data.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::{Category, FieldType, Flow, Trip};
+use crate::settings::UserSettings;
+
+const REPRO_FORMAT: &str = "preft-repro-bundle";
+const REPRO_VERSION: u32 = 1;
+
+/// How much of the end of each log file is kept.
+pub const MAX_LOG_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReproLog {
+    pub name: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproBundle {
+    format: String,
+    version: u32,
+    pub app_version: String,
+    /// e.g. `"linux x86_64"`.
+    pub platform: String,
+    pub created: DateTime<Utc>,
+    pub categories: Vec<Category>,
+    pub flows: Vec<Flow>,
+    pub trips: Vec<Trip>,
+    pub settings: UserSettings,
+    pub logs: Vec<ReproLog>,
+}
+
+impl ReproBundle {
+    /// Anonymizes the data (see `anonymize`) and keeps only the end of each
+    /// log (see `MAX_LOG_BYTES`). Logs are otherwise included as they are.
+    pub fn new(
+        app_version: &str,
+        categories: &[Category],
+        flows: &[Flow],
+        trips: &[Trip],
+        settings: &UserSettings,
+        logs: Vec<ReproLog>,
+    ) -> Self {
+        let (categories, flows, trips, settings) = anonymize(categories, flows, trips, settings);
+        Self {
+            format: REPRO_FORMAT.to_string(),
+            version: REPRO_VERSION,
+            app_version: app_version.to_string(),
+            platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            created: Utc::now(),
+            categories,
+            flows,
+            trips,
+            settings,
+            logs: logs.into_iter().map(|log| ReproLog { contents: tail(&log.contents, MAX_LOG_BYTES).to_string(), ..log }).collect(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let bundle: ReproBundle = serde_json::from_slice(data)
+            .map_err(|_| anyhow!("This is not a preft repro bundle"))?;
+        if bundle.format != REPRO_FORMAT {
+            return Err(anyhow!("This is not a preft repro bundle"));
+        }
+        if bundle.version > REPRO_VERSION {
+            return Err(anyhow!("This bundle was made by a newer version of preft (format version {})", bundle.version));
+        }
+        Ok(bundle)
+    }
+}
+
+/// The last `max_bytes` of `text` or a little less, starting on a character
+/// boundary.
+fn tail(text: &str, max_bytes: usize) -> &str {
+    let mut start = text.len().saturating_sub(max_bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// Stands in for each distinct value with a numbered placeholder, so values
+/// that were equal stay equal (and grouping still behaves the same).
+struct Pseudonyms {
+    prefix: &'static str,
+    seen: HashMap<String, String>,
+}
+
+impl Pseudonyms {
+    fn new(prefix: &'static str) -> Self {
+        Self { prefix, seen: HashMap::new() }
+    }
+
+    fn get(&mut self, value: &str) -> String {
+        let next = self.seen.len() + 1;
+        self.seen.entry(value.to_string())
+            .or_insert_with(|| format!("{} {}", self.prefix, next))
+            .clone()
+    }
+}
+
+/// Copies of the data with everything written by the user replaced:
+/// category and trip names, Select options, descriptions, locations (in
+/// the settings too) and Text fields. Amounts, dates, numbers and the shape
+/// of the data (ids, links, field definitions) are kept so problems still
+/// reproduce. Paths and backup history are left out of the settings, and
+/// automatic backups and the watch folder are turned off.
+pub fn anonymize(
+    categories: &[Category],
+    flows: &[Flow],
+    trips: &[Trip],
+    settings: &UserSettings,
+) -> (Vec<Category>, Vec<Flow>, Vec<Trip>, UserSettings) {
+    // Select options per (category, field), old -> new.
+    let mut options: HashMap<(String, String), HashMap<String, String>> = HashMap::new();
+    let categories: Vec<Category> = categories.iter().enumerate()
+        .map(|(i, category)| {
+            let mut category = category.clone();
+            category.name = format!("Category {}", i + 1);
+            category.icon = None;
+            for field in &mut category.fields {
+                if let FieldType::Select(values) = &mut field.field_type {
+                    let renamed: HashMap<String, String> = values.iter().enumerate()
+                        .map(|(j, value)| (value.clone(), format!("Option {}", j + 1)))
+                        .collect();
+                    for value in values.iter_mut() {
+                        *value = renamed[value].clone();
+                    }
+                    field.default_value = field.default_value.as_ref().and_then(|value| renamed.get(value).cloned());
+                    options.insert((category.id.clone(), field.name.clone()), renamed);
+                } else if field.field_type == FieldType::Text {
+                    field.default_value = None;
+                }
+            }
+            category
+        })
+        .collect();
+
+    let mut descriptions = Pseudonyms::new("Flow");
+    let mut locations = Pseudonyms::new("Location");
+    let mut texts = Pseudonyms::new("Text");
+    let flows = flows.iter()
+        .map(|flow| {
+            let mut flow = flow.clone();
+            if !flow.description.is_empty() {
+                flow.description = descriptions.get(&flow.description);
+            }
+            flow.location = flow.location.as_deref().map(|location| locations.get(location));
+            let fields = categories.iter().find(|c| c.id == flow.category_id).map(|c| c.fields.as_slice()).unwrap_or(&[]);
+            for (name, value) in flow.custom_fields.iter_mut() {
+                let field_type = fields.iter().find(|f| f.name == *name).map(|f| &f.field_type);
+                match field_type {
+                    Some(FieldType::Select(_)) => {
+                        *value = options.get(&(flow.category_id.clone(), name.clone()))
+                            .and_then(|renamed| renamed.get(value).cloned())
+                            .unwrap_or_else(|| texts.get(value));
+                    }
+                    // Values of fields the category no longer defines are
+                    // treated as text, since nothing says what they hold.
+                    Some(FieldType::Text) | None => *value = texts.get(value),
+                    Some(_) => {}
+                }
+            }
+            flow
+        })
+        .collect();
+
+    let trips = trips.iter().enumerate()
+        .map(|(i, trip)| Trip { name: format!("Trip {}", i + 1), ..trip.clone() })
+        .collect();
+
+    let mut settings = settings.clone();
+    settings.backup_history.clear();
+    settings.last_backup_path = None;
+    settings.auto_backup_enabled = false;
+    settings.auto_backup_directory = None;
+    settings.watch_folder = None;
+    for view in settings.category_views.values_mut() {
+        view.location = view.location.as_deref().map(|location| locations.get(location));
+    }
+
+    (categories, flows, trips, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CategoryField, FlowType, TaxDeductionInfo};
+    use chrono::NaiveDate;
+
+    fn category() -> Category {
+        Category {
+            id: "cat-1".to_string(),
+            name: "Therapy".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: Some([1, 2, 3]),
+            icon: Some("🧠".to_string()),
+            fields: vec![
+                CategoryField { name: "provider".to_string(), field_type: FieldType::Text, required: false, default_value: Some("Dr. Smith".to_string()) },
+                CategoryField { name: "kind".to_string(), field_type: FieldType::Select(vec!["Couples".to_string(), "Solo".to_string()]), required: false, default_value: Some("Solo".to_string()) },
+                CategoryField { name: "copay".to_string(), field_type: FieldType::Currency, required: false, default_value: None },
+            ],
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    fn flow(id: &str, provider: &str, kind: &str) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            amount: 120.0,
+            category_id: "cat-1".to_string(),
+            description: format!("Session with {}", provider),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::from([
+                ("provider".to_string(), provider.to_string()),
+                ("kind".to_string(), kind.to_string()),
+                ("copay".to_string(), "$20.00".to_string()),
+            ]),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: Some("Springfield".to_string()),
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn anonymize_replaces_what_the_user_wrote_and_keeps_the_rest() {
+        let flows = vec![flow("a", "Dr. Smith", "Solo"), flow("b", "Dr. Jones", "Couples"), flow("c", "Dr. Smith", "Solo")];
+        let mut settings = UserSettings::new();
+        settings.watch_folder = Some("/home/me/Downloads".into());
+        let (categories, flows, _, settings) = anonymize(&[category()], &flows, &[], &settings);
+
+        assert_eq!(categories[0].name, "Category 1");
+        assert_eq!(categories[0].icon, None);
+        assert_eq!(categories[0].fields[0].default_value, None);
+        assert_eq!(categories[0].fields[1].field_type, FieldType::Select(vec!["Option 1".to_string(), "Option 2".to_string()]));
+        assert_eq!(categories[0].fields[1].default_value.as_deref(), Some("Option 2"));
+
+        let json = serde_json::to_string(&flows).unwrap();
+        for private in ["Dr. Smith", "Dr. Jones", "Springfield", "Session", "Solo", "Couples"] {
+            assert!(!json.contains(private), "{} should be gone", private);
+        }
+        assert_eq!(flows[0].custom_fields["provider"], flows[2].custom_fields["provider"], "equal values stay equal");
+        assert_ne!(flows[0].custom_fields["provider"], flows[1].custom_fields["provider"]);
+        assert_eq!(flows[1].custom_fields["kind"], "Option 1");
+        assert_eq!(flows[0].custom_fields["copay"], "$20.00");
+        assert_eq!((flows[0].amount, flows[0].date), (120.0, NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()));
+        assert_eq!(settings.watch_folder, None);
+    }
+
+    #[test]
+    fn bundle_round_trips_and_rejects_other_files() {
+        let logs = vec![ReproLog { name: "preft.log".to_string(), contents: "é".repeat(MAX_LOG_BYTES) }];
+        let bundle = ReproBundle::new("1.2.3", &[category()], &[flow("a", "Dr. Smith", "Solo")], &[], &UserSettings::new(), logs);
+        assert!(bundle.logs[0].contents.len() <= MAX_LOG_BYTES, "only the end of the log is kept");
+
+        let opened = ReproBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(opened.app_version, "1.2.3");
+        assert_eq!(opened.flows[0].description, bundle.flows[0].description);
+        assert!(ReproBundle::from_bytes(b"{}").is_err());
+        assert!(ReproBundle::from_bytes(b"SQLite format 3\0").is_err());
+    }
+}
@@ -0,0 +1,684 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use chrono::{self, Datelike, DateTime, Utc};
+
+use crate::kpi::KpiCard;
+use crate::shortcuts::ShortcutAction;
+use crate::locale::NumberFormat;
+use crate::models::{Flow, FlowType, UniquenessRule};
+
+/// How many recent flows the dashboard lists before "View All".
+pub const DEFAULT_RECENT_FLOWS_COUNT: usize = 5;
+
+/// The smallest and largest UI scale offered (see `UserSettings::ui_scale`).
+pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+/// Minutes of inactivity offered when auto-lock is first turned on (see
+/// `UserSettings::auto_lock_minutes`).
+pub const DEFAULT_AUTO_LOCK_MINUTES: u32 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub timestamp: DateTime<Utc>,
+    pub file_path: String,
+    pub file_size: Option<u64>,
+    pub success: bool,
+    pub error_message: Option<String>,
+}
+
+/// The app's colour scheme. `System` follows the OS light/dark setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    System,
+    Dark,
+    Light,
+    SolarizedDark,
+    SolarizedLight,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 6] = [
+        Theme::System,
+        Theme::Dark,
+        Theme::Light,
+        Theme::SolarizedDark,
+        Theme::SolarizedLight,
+        Theme::HighContrast,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::SolarizedDark => "Solarized Dark",
+            Theme::SolarizedLight => "Solarized Light",
+            Theme::HighContrast => "High Contrast",
+        }
+    }
+}
+
+/// What preft shows when it opens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StartupView {
+    #[default]
+    Dashboard,
+    /// The category that was last open (see `UserSettings::last_category`).
+    LastCategory,
+    /// The last category, with the flow editor open on a new flow.
+    NewFlow,
+}
+
+impl StartupView {
+    pub const ALL: [StartupView; 3] = [
+        StartupView::Dashboard,
+        StartupView::LastCategory,
+        StartupView::NewFlow,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            StartupView::Dashboard => "Dashboard",
+            StartupView::LastCategory => "Last-used category",
+            StartupView::NewFlow => "New flow in last-used category",
+        }
+    }
+}
+
+/// The column a category's flows table is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortColumn {
+    Date,
+    Amount,
+    Description,
+}
+
+impl SortColumn {
+    /// Direction a column starts in the first time it's selected.
+    pub fn default_ascending(self) -> bool {
+        match self {
+            SortColumn::Date => false,        // newest first
+            SortColumn::Amount => false,      // largest first
+            SortColumn::Description => true,  // A-Z
+        }
+    }
+}
+
+/// The years a category opens showing, through the year filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum YearView {
+    /// Whichever year it is when the category is opened.
+    CurrentYear,
+    AllYears,
+}
+
+impl YearView {
+    /// The year filter this view sets in `current_year`.
+    pub fn year_filter(self, current_year: i32) -> Option<i32> {
+        match self {
+            YearView::CurrentYear => Some(current_year),
+            YearView::AllYears => None,
+        }
+    }
+}
+
+/// How a category's flows table is set up each time the category is
+/// opened (see `UserSettings::category_views`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryView {
+    pub sort_column: SortColumn,
+    pub sort_ascending: bool,
+    /// `None` leaves the year filter as it is.
+    #[serde(default)]
+    pub years: Option<YearView>,
+    /// Only flows at this location, like the table's location filter.
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// Where the main window was when preft last closed, in logical pixels
+/// (before the UI scale). Position and size are the window's last while it
+/// wasn't maximized, so un-maximizing after a restart goes back there.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// The outer top-left corner, including the title bar.
+    pub x: f32,
+    pub y: f32,
+    /// The inner size, without the title bar and borders.
+    pub width: f32,
+    pub height: f32,
+    pub maximized: bool,
+}
+
+/// Which destructive actions ask before going ahead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmationSettings {
+    pub delete_flow: bool,
+    pub delete_selected_flows: bool,
+    pub hide_category: bool,
+    pub delete_category: bool,
+    /// Deleting a category asks for its name to be typed, not just a click.
+    pub type_category_name_to_delete: bool,
+}
+
+impl Default for ConfirmationSettings {
+    fn default() -> Self {
+        Self {
+            delete_flow: false,
+            delete_selected_flows: true,
+            hide_category: true,
+            delete_category: true,
+            type_category_name_to_delete: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AmountComparison {
+    Above,
+    Below,
+}
+
+impl AmountComparison {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            AmountComparison::Above => "over",
+            AmountComparison::Below => "under",
+        }
+    }
+}
+
+/// Conditional formatting for flow tables, e.g. "expenses over $500 in bold
+/// red". Compared against the flow's amount as entered (amounts are stored
+/// unsigned, so refunds compare by their size too).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmountHighlightRule {
+    /// Only flows in categories of this type; `None` applies to both.
+    pub flow_type: Option<FlowType>,
+    pub comparison: AmountComparison,
+    pub threshold: f64,
+    /// sRGB.
+    pub color: [u8; 3],
+    pub bold: bool,
+}
+
+impl AmountHighlightRule {
+    pub fn matches(&self, flow: &Flow, flow_type: &FlowType) -> bool {
+        if self.flow_type.as_ref().is_some_and(|t| t != flow_type) {
+            return false;
+        }
+        match self.comparison {
+            AmountComparison::Above => flow.amount > self.threshold,
+            AmountComparison::Below => flow.amount < self.threshold,
+        }
+    }
+}
+
+impl Default for AmountHighlightRule {
+    fn default() -> Self {
+        Self {
+            flow_type: Some(FlowType::Expense),
+            comparison: AmountComparison::Above,
+            threshold: 500.0,
+            color: [220, 50, 50],
+            bold: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UserSettings {
+    #[serde(default)]
+    pub hidden_categories: HashSet<String>,  // Set of category IDs that are hidden
+    #[serde(default)]
+    pub year_filter: Option<i32>,  // Optional year to filter flows by, None means show all years
+    #[serde(default)]
+    pub backup_history: Vec<BackupEntry>,  // History of backup operations
+    #[serde(default)]
+    pub last_backup_path: Option<String>,  // Path of the last successful backup
+    #[serde(default)]
+    pub auto_backup_enabled: bool,  // Whether automatic backups are enabled
+    #[serde(default)]
+    pub auto_backup_directory: Option<String>,  // Directory for automatic backups
+    #[serde(default)]
+    pub auto_backup_encrypted: Option<bool>,  // Whether automatic backups should be encrypted (None = use default)
+    /// UTC offset (seconds east of UTC) that flow dates are meant in.
+    /// Recorded on first run; the app warns when the system timezone no
+    /// longer matches it, since "today" may then be another date at home.
+    #[serde(default)]
+    pub home_utc_offset: Option<i32>,
+    /// Evaluated in order for each flow table row; the first match wins.
+    #[serde(default)]
+    pub highlight_rules: Vec<AmountHighlightRule>,
+    /// How amounts are shown in tables and PDF reports.
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    /// Per category id, a format that replaces `number_format` for that
+    /// category's amounts, e.g. a rental property kept in euros. Amounts
+    /// aren't converted; totals only add up categories in one currency.
+    #[serde(default)]
+    pub category_number_formats: HashMap<String, NumberFormat>,
+    /// Folder whose CSV files are imported automatically (see
+    /// `watch_folder`). `None` means no folder is watched.
+    #[serde(default)]
+    pub watch_folder: Option<String>,
+    /// Uniqueness rules per category id, checked whenever a flow is saved
+    /// from the flow editor.
+    #[serde(default)]
+    pub uniqueness_rules: HashMap<String, Vec<UniquenessRule>>,
+    /// Keyboard shortcut bindings that differ from the defaults (see
+    /// `ShortcutAction::default_binding`).
+    #[serde(default)]
+    pub shortcuts: BTreeMap<ShortcutAction, String>,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Queue flow edits for review and an explicit Save All instead of
+    /// writing each one immediately (see `pending_changes`).
+    #[serde(default)]
+    pub deferred_writes: bool,
+    /// Cards shown at the top of the dashboard, in order (see `kpi`).
+    #[serde(default)]
+    pub kpi_cards: Vec<KpiCard>,
+    /// How many recent flows the dashboard lists; `None` means
+    /// `DEFAULT_RECENT_FLOWS_COUNT`.
+    #[serde(default)]
+    pub recent_flows_count: Option<usize>,
+    #[serde(default)]
+    pub startup_view: StartupView,
+    /// The category most recently opened, for `StartupView::LastCategory`.
+    #[serde(default)]
+    pub last_category: Option<String>,
+    /// Ask for the database password again before deleting a category,
+    /// deleting several flows at once or restoring a backup. Has no effect
+    /// until a password is set.
+    #[serde(default)]
+    pub strict_mode: bool,
+    /// Lock the app after this many minutes without input, until the
+    /// database password is entered again; `None` never locks. Has no
+    /// effect until a password is set.
+    #[serde(default)]
+    pub auto_lock_minutes: Option<u32>,
+    /// How much larger than normal text and widgets are drawn, on top of the
+    /// display's own scaling; `None` means 1.0.
+    #[serde(default)]
+    pub ui_scale: Option<f32>,
+    #[serde(default)]
+    pub confirmations: ConfirmationSettings,
+    /// Restored at startup; `None` until preft has closed once.
+    #[serde(default)]
+    pub window: Option<WindowGeometry>,
+    /// Per category id, how its flows table is set up when it's opened.
+    /// Categories without an entry keep however they were last left.
+    #[serde(default)]
+    pub category_views: HashMap<String, CategoryView>,
+    // Future settings can be added here, such as:
+    // - preferred date format
+    // - default currency
+    // - theme preferences
+    // - notification settings
+    // - etc.
+}
+
+impl UserSettings {
+    pub fn new() -> Self {
+        Self {
+            hidden_categories: HashSet::new(),
+            year_filter: Some(chrono::Local::now().year()),  // Default to current year
+            backup_history: Vec::new(),
+            last_backup_path: None,
+            auto_backup_enabled: false,
+            auto_backup_directory: None,
+            auto_backup_encrypted: None,
+            home_utc_offset: None,
+            highlight_rules: Vec::new(),
+            number_format: NumberFormat::default(),
+            category_number_formats: HashMap::new(),
+            watch_folder: None,
+            uniqueness_rules: HashMap::new(),
+            shortcuts: BTreeMap::new(),
+            theme: Theme::default(),
+            deferred_writes: false,
+            kpi_cards: Vec::new(),
+            recent_flows_count: None,
+            startup_view: StartupView::default(),
+            last_category: None,
+            strict_mode: false,
+            auto_lock_minutes: None,
+            ui_scale: None,
+            confirmations: ConfirmationSettings::default(),
+            window: None,
+            category_views: HashMap::new(),
+        }
+    }
+
+    pub fn is_category_hidden(&self, category_id: &str) -> bool {
+        self.hidden_categories.contains(category_id)
+    }
+
+    pub fn toggle_category_visibility(&mut self, category_id: String) {
+        if self.hidden_categories.contains(&category_id) {
+            self.hidden_categories.remove(&category_id);
+        } else {
+            self.hidden_categories.insert(category_id);
+        }
+    }
+
+    pub fn set_year_filter(&mut self, year: Option<i32>) {
+        self.year_filter = year;
+    }
+
+    pub fn get_year_filter(&self) -> Option<i32> {
+        self.year_filter
+    }
+
+    /// The category to open at startup: the last one, if `startup_view`
+    /// asks for it and it still exists and isn't hidden. `None` opens the
+    /// dashboard.
+    pub fn startup_category(&self, exists: impl Fn(&str) -> bool) -> Option<&str> {
+        if self.startup_view == StartupView::Dashboard {
+            return None;
+        }
+        self.last_category.as_deref()
+            .filter(|id| exists(id) && !self.is_category_hidden(id))
+    }
+
+    pub fn get_recent_flows_count(&self) -> usize {
+        self.recent_flows_count.unwrap_or(DEFAULT_RECENT_FLOWS_COUNT)
+    }
+
+    pub fn get_ui_scale(&self) -> f32 {
+        self.ui_scale
+            .filter(|scale| scale.is_finite())
+            .map_or(1.0, |scale| scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end()))
+    }
+
+    pub fn add_backup_entry(&mut self, entry: BackupEntry) {
+        // Keep only the last 100 backup entries
+        if self.backup_history.len() >= 100 {
+            self.backup_history.remove(0);
+        }
+        self.backup_history.push(entry);
+    }
+
+    pub fn get_last_successful_backup(&self) -> Option<&BackupEntry> {
+        self.backup_history.iter().rev().find(|entry| entry.success)
+    }
+
+    pub fn set_last_backup_path(&mut self, path: String) {
+        self.last_backup_path = Some(path);
+    }
+
+    pub fn set_auto_backup_enabled(&mut self, enabled: bool) {
+        self.auto_backup_enabled = enabled;
+    }
+
+    pub fn is_auto_backup_enabled(&self) -> bool {
+        self.auto_backup_enabled
+    }
+
+    pub fn set_auto_backup_directory(&mut self, directory: Option<String>) {
+        self.auto_backup_directory = directory;
+    }
+
+    pub fn get_auto_backup_directory(&self) -> Option<&String> {
+        self.auto_backup_directory.as_ref()
+    }
+
+    pub fn set_auto_backup_encrypted(&mut self, encrypted: Option<bool>) {
+        self.auto_backup_encrypted = encrypted;
+    }
+
+    pub fn get_auto_backup_encrypted(&self) -> Option<bool> {
+        self.auto_backup_encrypted
+    }
+
+    /// How amounts in `category_id` are written: its own format if it has
+    /// one, otherwise `number_format`.
+    pub fn number_format_for(&self, category_id: &str) -> &NumberFormat {
+        self.category_number_formats.get(category_id).unwrap_or(&self.number_format)
+    }
+
+    /// `None` (or a format identical to `number_format`) removes the
+    /// category's own format.
+    pub fn set_category_number_format(&mut self, category_id: &str, format: Option<NumberFormat>) {
+        match format.filter(|format| *format != self.number_format) {
+            Some(format) => self.category_number_formats.insert(category_id.to_string(), format),
+            None => self.category_number_formats.remove(category_id),
+        };
+    }
+
+    pub fn get_uniqueness_rules(&self, category_id: &str) -> &[UniquenessRule] {
+        self.uniqueness_rules.get(category_id).map_or(&[], |rules| rules.as_slice())
+    }
+
+    /// Rules with no fields are dropped; an empty list removes the entry.
+    pub fn set_uniqueness_rules(&mut self, category_id: &str, mut rules: Vec<UniquenessRule>) {
+        rules.retain(|rule| !rule.fields.is_empty());
+        if rules.is_empty() {
+            self.uniqueness_rules.remove(category_id);
+        } else {
+            self.uniqueness_rules.insert(category_id.to_string(), rules);
+        }
+    }
+
+    pub fn get_category_view(&self, category_id: &str) -> Option<&CategoryView> {
+        self.category_views.get(category_id)
+    }
+
+    /// `None` forgets the category's view.
+    pub fn set_category_view(&mut self, category_id: &str, view: Option<CategoryView>) {
+        match view {
+            Some(view) => self.category_views.insert(category_id.to_string(), view),
+            None => self.category_views.remove(category_id),
+        };
+    }
+
+    pub fn get_shortcut(&self, action: ShortcutAction) -> &str {
+        self.shortcuts.get(&action).map_or(action.default_binding(), String::as_str)
+    }
+
+    /// Binding `action` back to its default removes the override.
+    pub fn set_shortcut(&mut self, action: ShortcutAction, binding: String) {
+        if binding == action.default_binding() {
+            self.shortcuts.remove(&action);
+        } else {
+            self.shortcuts.insert(action, binding);
+        }
+    }
+
+    /// The first highlight rule that applies to `flow`, if any.
+    pub fn highlight_for(&self, flow: &Flow, flow_type: &FlowType) -> Option<&AmountHighlightRule> {
+        self.highlight_rules.iter().find(|rule| rule.matches(flow, flow_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup_entry(file_path: &str, success: bool) -> BackupEntry {
+        BackupEntry {
+            timestamp: Utc::now(),
+            file_path: file_path.to_string(),
+            file_size: None,
+            success,
+            error_message: None,
+        }
+    }
+
+    #[test]
+    fn new_defaults_to_current_year_filter_and_empty_state() {
+        let settings = UserSettings::new();
+        assert_eq!(settings.get_year_filter(), Some(chrono::Local::now().year()));
+        assert!(settings.backup_history.is_empty());
+        assert!(!settings.is_auto_backup_enabled());
+        assert_eq!(settings.get_auto_backup_directory(), None);
+    }
+
+    #[test]
+    fn toggle_category_visibility_round_trips() {
+        let mut settings = UserSettings::new();
+        assert!(!settings.is_category_hidden("groceries"));
+
+        settings.toggle_category_visibility("groceries".to_string());
+        assert!(settings.is_category_hidden("groceries"));
+
+        settings.toggle_category_visibility("groceries".to_string());
+        assert!(!settings.is_category_hidden("groceries"));
+    }
+
+    #[test]
+    fn category_number_format_falls_back_to_the_app_format() {
+        let mut settings = UserSettings::new();
+        let euros = NumberFormat { currency_symbol: "€".to_string(), symbol_after: true, ..NumberFormat::default() };
+
+        settings.set_category_number_format("rental", Some(euros.clone()));
+        assert_eq!(settings.number_format_for("rental"), &euros);
+        assert_eq!(settings.number_format_for("groceries"), &settings.number_format);
+
+        settings.set_category_number_format("rental", Some(NumberFormat::default()));
+        assert!(settings.category_number_formats.is_empty(), "the app format isn't an override");
+    }
+
+    #[test]
+    fn startup_category_needs_a_category_view_and_a_visible_category() {
+        let mut settings = UserSettings::new();
+        settings.last_category = Some("groceries".to_string());
+        let exists = |id: &str| id == "groceries";
+
+        assert_eq!(settings.startup_category(exists), None, "the dashboard is the default");
+        settings.startup_view = StartupView::NewFlow;
+        assert_eq!(settings.startup_category(exists), Some("groceries"));
+        assert_eq!(settings.startup_category(|_| false), None, "a deleted category opens the dashboard");
+        settings.toggle_category_visibility("groceries".to_string());
+        assert_eq!(settings.startup_category(exists), None);
+    }
+
+    #[test]
+    fn year_filter_round_trips() {
+        let mut settings = UserSettings::new();
+        settings.set_year_filter(Some(2020));
+        assert_eq!(settings.get_year_filter(), Some(2020));
+
+        settings.set_year_filter(None);
+        assert_eq!(settings.get_year_filter(), None);
+    }
+
+    #[test]
+    fn backup_history_caps_at_100_entries_evicting_oldest_first() {
+        let mut settings = UserSettings::new();
+        for i in 0..101 {
+            settings.add_backup_entry(backup_entry(&format!("backup_{}", i), true));
+        }
+
+        assert_eq!(settings.backup_history.len(), 100);
+        assert_eq!(settings.backup_history.first().unwrap().file_path, "backup_1");
+        assert_eq!(settings.backup_history.last().unwrap().file_path, "backup_100");
+    }
+
+    #[test]
+    fn get_last_successful_backup_skips_trailing_failures() {
+        let mut settings = UserSettings::new();
+        settings.add_backup_entry(backup_entry("ok_1", true));
+        settings.add_backup_entry(backup_entry("fail_1", false));
+        settings.add_backup_entry(backup_entry("ok_2", true));
+        settings.add_backup_entry(backup_entry("fail_2", false));
+
+        let last_success = settings.get_last_successful_backup().unwrap();
+        assert_eq!(last_success.file_path, "ok_2");
+    }
+
+    #[test]
+    fn get_last_successful_backup_none_when_no_successes() {
+        let mut settings = UserSettings::new();
+        settings.add_backup_entry(backup_entry("fail_1", false));
+        assert!(settings.get_last_successful_backup().is_none());
+    }
+
+    fn flow_with_amount(amount: f64) -> Flow {
+        Flow {
+            id: "f".to_string(),
+            date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            amount,
+            category_id: "cat".to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: std::collections::HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn highlight_for_uses_the_first_matching_rule() {
+        let mut settings = UserSettings::new();
+        let big_expense = AmountHighlightRule::default();
+        let small_anything = AmountHighlightRule {
+            flow_type: None,
+            comparison: AmountComparison::Below,
+            threshold: 10.0,
+            color: [128, 128, 128],
+            bold: false,
+        };
+        settings.highlight_rules = vec![big_expense.clone(), small_anything.clone()];
+
+        assert_eq!(settings.highlight_for(&flow_with_amount(600.0), &FlowType::Expense), Some(&big_expense));
+        assert_eq!(settings.highlight_for(&flow_with_amount(600.0), &FlowType::Income), None, "the first rule is expenses only");
+        assert_eq!(settings.highlight_for(&flow_with_amount(500.0), &FlowType::Expense), None, "over means strictly over");
+        assert_eq!(settings.highlight_for(&flow_with_amount(5.0), &FlowType::Income), Some(&small_anything));
+    }
+
+    #[test]
+    fn ui_scale_defaults_to_one_and_stays_in_range() {
+        let mut settings = UserSettings::new();
+        assert_eq!(settings.get_ui_scale(), 1.0);
+        settings.ui_scale = Some(1.5);
+        assert_eq!(settings.get_ui_scale(), 1.5);
+        settings.ui_scale = Some(40.0);
+        assert_eq!(settings.get_ui_scale(), *UI_SCALE_RANGE.end());
+        settings.ui_scale = Some(f32::NAN);
+        assert_eq!(settings.get_ui_scale(), 1.0);
+    }
+
+    #[test]
+    fn confirmations_missing_from_saved_settings_use_the_defaults() {
+        let mut json = serde_json::to_value(UserSettings::new()).unwrap();
+        json.as_object_mut().unwrap().remove("confirmations");
+        let settings: UserSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(settings.confirmations, ConfirmationSettings::default());
+        assert!(!settings.confirmations.delete_flow && settings.confirmations.delete_category);
+
+        let partial: ConfirmationSettings = serde_json::from_str(r#"{"delete_flow": true}"#).unwrap();
+        assert!(partial.delete_flow && partial.delete_selected_flows);
+    }
+
+    #[test]
+    fn category_views_are_kept_per_category_and_survive_a_save() {
+        let mut settings = UserSettings::new();
+        let view = CategoryView {
+            sort_column: SortColumn::Amount,
+            sort_ascending: false,
+            years: Some(YearView::CurrentYear),
+            location: Some("Springfield".to_string()),
+        };
+        settings.set_category_view("cat-1", Some(view.clone()));
+
+        let saved: UserSettings = serde_json::from_str(&serde_json::to_string(&settings).unwrap()).unwrap();
+        assert_eq!(saved.get_category_view("cat-1"), Some(&view));
+        assert_eq!(saved.get_category_view("cat-2"), None);
+        assert_eq!(view.years.unwrap().year_filter(2024), Some(2024));
+        assert_eq!(YearView::AllYears.year_filter(2024), None);
+
+        settings.set_category_view("cat-1", None);
+        assert!(settings.category_views.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+//! The actions that can be bound to a keyboard shortcut. Bindings are kept
+//! in `UserSettings` as text like "Ctrl+N"; frontends parse them into
+//! their own key types.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ShortcutAction {
+    NewFlow,
+    /// Saves the flow open in the flow editor.
+    Save,
+    /// Focuses the search bar above the selected category's flows.
+    Search,
+    OpenReport,
+    /// Reverses the last flow or category edit (see `undo`).
+    Undo,
+    Redo,
+    /// Bound to modifiers only: they're combined with 1-9 to switch to
+    /// that category in the category list.
+    SwitchCategory,
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 7] = [
+        ShortcutAction::NewFlow,
+        ShortcutAction::Save,
+        ShortcutAction::Search,
+        ShortcutAction::OpenReport,
+        ShortcutAction::Undo,
+        ShortcutAction::Redo,
+        ShortcutAction::SwitchCategory,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            ShortcutAction::NewFlow => "New flow",
+            ShortcutAction::Save => "Save flow",
+            ShortcutAction::Search => "Search flows",
+            ShortcutAction::OpenReport => "Open report",
+            ShortcutAction::Undo => "Undo",
+            ShortcutAction::Redo => "Redo",
+            ShortcutAction::SwitchCategory => "Switch category (+ 1-9)",
+        }
+    }
+
+    pub fn default_binding(&self) -> &'static str {
+        match self {
+            ShortcutAction::NewFlow => "Ctrl+N",
+            ShortcutAction::Save => "Ctrl+S",
+            ShortcutAction::Search => "Ctrl+F",
+            ShortcutAction::OpenReport => "Ctrl+R",
+            ShortcutAction::Undo => "Ctrl+Z",
+            ShortcutAction::Redo => "Ctrl+Y",
+            ShortcutAction::SwitchCategory => "Ctrl",
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Undo and redo for edits to flows and categories. An `Edit` records the
+//! whole record before and after for everything it touched, so undoing it
+//! means writing the "before" side back and redoing it means writing the
+//! "after" side again. Absent records (`None`) are deleted.
+
+use crate::models::{Category, Flow};
+
+/// How many edits can be undone; older ones are forgotten.
+pub const UNDO_LIMIT: usize = 100;
+
+/// One record's state on either side of an edit. `None` means it didn't
+/// exist (before a create, or after a delete).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<T> {
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+impl<T> Change<T> {
+    /// The state to write back: `before` when undoing, `after` when redoing.
+    pub fn target(&self, undo: bool) -> Option<&T> {
+        if undo { self.before.as_ref() } else { self.after.as_ref() }
+    }
+}
+
+/// Everything one user action changed, e.g. a deleted flow together with
+/// the refunds that lost their link to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    /// What the user did, for "Undo Delete flow" and the like.
+    pub label: String,
+    pub flows: Vec<Change<Flow>>,
+    pub categories: Vec<Change<Category>>,
+}
+
+impl Edit {
+    pub fn new(label: &str) -> Self {
+        Self { label: label.to_string(), flows: Vec::new(), categories: Vec::new() }
+    }
+
+    pub fn flow(mut self, before: Option<Flow>, after: Option<Flow>) -> Self {
+        self.flows.push(Change { before, after });
+        self
+    }
+
+    pub fn category(mut self, before: Option<Category>, after: Option<Category>) -> Self {
+        self.categories.push(Change { before, after });
+        self
+    }
+}
+
+/// Edits that can be undone, most recent last, and edits that were undone
+/// and can be redone. Making a new edit forgets the redo side.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl UndoStack {
+    pub fn record(&mut self, edit: Edit) {
+        self.undo.push(edit);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// The edit an undo would reverse. Once its "before" side has been
+    /// written, call `undone`.
+    pub fn next_undo(&self) -> Option<&Edit> {
+        self.undo.last()
+    }
+
+    /// The edit a redo would make again. Once its "after" side has been
+    /// written, call `redone`.
+    pub fn next_redo(&self) -> Option<&Edit> {
+        self.redo.last()
+    }
+
+    pub fn undone(&mut self) {
+        if let Some(edit) = self.undo.pop() {
+            self.redo.push(edit);
+        }
+    }
+
+    pub fn redone(&mut self) {
+        if let Some(edit) = self.redo.pop() {
+            self.undo.push(edit);
+        }
+    }
+
+    /// Forgets everything, for when the data is replaced wholesale (e.g. a
+    /// restored backup) and old edits no longer describe it.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undone_edits_can_be_redone_until_a_new_edit_is_recorded() {
+        let mut stack = UndoStack::default();
+        stack.record(Edit::new("first"));
+        stack.record(Edit::new("second"));
+
+        assert_eq!(stack.next_undo().map(|e| e.label.as_str()), Some("second"));
+        stack.undone();
+        assert_eq!(stack.next_undo().map(|e| e.label.as_str()), Some("first"));
+        assert_eq!(stack.next_redo().map(|e| e.label.as_str()), Some("second"));
+
+        stack.redone();
+        assert_eq!(stack.next_undo().map(|e| e.label.as_str()), Some("second"));
+        assert!(stack.next_redo().is_none());
+
+        stack.undone();
+        stack.record(Edit::new("third"));
+        assert!(stack.next_redo().is_none(), "a new edit forgets the redo side");
+        assert_eq!(stack.next_undo().map(|e| e.label.as_str()), Some("third"));
+    }
+
+    #[test]
+    fn only_the_most_recent_edits_are_kept() {
+        let mut stack = UndoStack::default();
+        for i in 0..UNDO_LIMIT + 5 {
+            stack.record(Edit::new(&i.to_string()));
+        }
+        let mut count = 0;
+        while stack.next_undo().is_some() {
+            stack.undone();
+            count += 1;
+        }
+        assert_eq!(count, UNDO_LIMIT);
+        assert_eq!(stack.next_redo().map(|e| e.label.as_str()), Some("5"));
+    }
+}
@@ -0,0 +1,580 @@
+use chrono::{Datelike, NaiveDate};
+use crate::locale::NumberFormat;
+use crate::models::{Flow, Category, FieldType, FlowType};
+use std::collections::HashMap;
+
+pub fn calculate_tracking_ratio(flows: &[Flow], category: &Category) -> Option<f64> {
+    calculate_tracking_ratio_as_of(flows, category, chrono::Local::now().naive_local().date())
+}
+
+/// Core of `calculate_tracking_ratio`, parameterized on "today" so it can be
+/// tested deterministically instead of depending on the wall clock. Public so
+/// frontends (the app's `Dashboard` and `CategoryFlowsState`) can compute a tracking ratio consistent with an
+/// explicit `as_of` date of their own, rather than re-reading the wall clock
+/// independently.
+pub fn calculate_tracking_ratio_as_of(flows: &[Flow], category: &Category, as_of: NaiveDate) -> Option<f64> {
+    let current_year = as_of.year();
+
+    // Get flows for this category
+    let category_flows: Vec<_> = flows.iter()
+        .filter(|f| f.category_id == category.id)
+        .collect();
+
+    // Calculate last year's total
+    let last_year_total: f64 = category_flows.iter()
+        .filter(|f| f.date.year() == current_year - 1)
+        .map(|f| f.net_amount())
+        .sum();
+
+    // Calculate this year's total
+    let this_year_total: f64 = category_flows.iter()
+        .filter(|f| f.date.year() == current_year)
+        .map(|f| f.net_amount())
+        .sum();
+
+    tracking_ratio_from_totals(last_year_total, this_year_total, as_of)
+}
+
+/// The tracking ratio for a category whose net totals last year and so far
+/// this year (as of `as_of`) are already known, e.g. summed in SQL by
+/// `Database::category_summaries`.
+pub fn tracking_ratio_from_totals(last_year_total: f64, this_year_total: f64, as_of: NaiveDate) -> Option<f64> {
+    let current_year = as_of.year();
+
+    // If there was no data last year, return 9999.0
+    if last_year_total == 0.0 {
+        if this_year_total == 0.0 {
+            return None;
+        } else {
+            return Some(9999.0);
+        }
+    }
+
+    // Calculate the proportion of the year that has passed
+    let current_day = as_of.ordinal() as f64;
+    let days_in_year = if NaiveDate::from_ymd_opt(current_year, 12, 31).unwrap().leap_year() {
+        366.0
+    } else {
+        365.0
+    };
+    let year_progress = current_day / days_in_year;
+
+    // Calculate what proportion of last year's total we should have by now
+    let expected_this_year = last_year_total * year_progress;
+
+    // Calculate the tracking ratio (actual vs expected)
+    let ratio = this_year_total / expected_this_year;
+
+    // If ratio exceeds 9999.0, return 9999.0
+    if ratio > 9999.0 {
+        Some(9999.0)
+    } else {
+        Some(ratio)
+    }
+}
+
+/// The flows among `flows` that count toward deductions: those marked
+/// deductible, plus refunds of them. A refund counts when the flow it
+/// refunds does, regardless of its own flag, so returning part of a
+/// deductible expense always reduces the deductible total.
+pub fn deductible_flows(flows: &[Flow]) -> Vec<&Flow> {
+    let deductible: HashMap<&str, bool> = flows.iter()
+        .map(|f| (f.id.as_str(), f.tax_deductible == Some(true)))
+        .collect();
+
+    flows.iter()
+        .filter(|f| match &f.refund_of {
+            Some(original_id) => deductible.get(original_id.as_str()).copied()
+                .unwrap_or(f.tax_deductible == Some(true)),
+            None => f.tax_deductible == Some(true),
+        })
+        .collect()
+}
+
+/// Net totals for `category_id` in each of the `months` calendar months
+/// ending with `as_of`'s, oldest first -- the data behind trend sparklines.
+pub fn trailing_monthly_totals(flows: &[Flow], category_id: &str, as_of: NaiveDate, months: usize) -> Vec<f64> {
+    let month_index = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+    let last = month_index(as_of);
+    let first = last - months as i32 + 1;
+
+    let mut totals = vec![0.0; months];
+    for flow in flows.iter().filter(|f| f.category_id == category_id) {
+        let index = month_index(flow.date);
+        if (first..=last).contains(&index) {
+            totals[(index - first) as usize] += flow.net_amount();
+        }
+    }
+    totals
+}
+
+/// Groups `flows` by location, ignoring case and surrounding spaces, as
+/// (first spelling seen, number of flows, net total). Flows without a
+/// location are left out. Most used first.
+fn group_by_location<'a>(flows: impl IntoIterator<Item = &'a Flow>) -> Vec<(String, usize, f64)> {
+    let mut groups: Vec<(String, usize, f64)> = Vec::new();
+    for flow in flows {
+        let Some(location) = flow.location.as_deref().map(str::trim).filter(|l| !l.is_empty()) else { continue };
+        match groups.iter_mut().find(|(seen, _, _)| seen.eq_ignore_ascii_case(location)) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += flow.net_amount();
+            }
+            None => groups.push((location.to_string(), 1, flow.net_amount())),
+        }
+    }
+    groups.sort_by_key(|(_, count, _)| std::cmp::Reverse(*count));
+    groups
+}
+
+/// Past locations containing `typed` (ignoring case), most used first, for
+/// autocompleting the location field. A location typed in full isn't
+/// suggested back.
+pub fn location_suggestions(flows: &[Flow], typed: &str, limit: usize) -> Vec<String> {
+    let typed = typed.trim().to_lowercase();
+    group_by_location(flows).into_iter()
+        .map(|(location, _, _)| location)
+        .filter(|location| location.to_lowercase().contains(&typed) && location.to_lowercase() != typed)
+        .take(limit)
+        .collect()
+}
+
+/// Net total of `flows` per location, largest first (see
+/// `group_by_location` for how locations are matched).
+pub fn totals_by_location<'a>(flows: impl IntoIterator<Item = &'a Flow>) -> Vec<(String, f64)> {
+    let mut totals: Vec<(String, f64)> = group_by_location(flows).into_iter()
+        .map(|(location, _, total)| (location, total))
+        .collect();
+    totals.sort_by(|a, b| b.1.total_cmp(&a.1));
+    totals
+}
+
+/// What a trip brought in and cost, from the flows assigned to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TripTotals {
+    pub income: f64,
+    pub expenses: f64,
+    pub flow_count: usize,
+    /// The span of the flows' dates, which can run outside the trip's own.
+    pub first: Option<NaiveDate>,
+    pub last: Option<NaiveDate>,
+}
+
+impl TripTotals {
+    /// Takes in the totals of more of the trip's flows.
+    pub fn merge(&mut self, other: &TripTotals) {
+        self.income += other.income;
+        self.expenses += other.expenses;
+        self.flow_count += other.flow_count;
+        self.first = self.first.into_iter().chain(other.first).min();
+        self.last = self.last.into_iter().chain(other.last).max();
+    }
+}
+
+/// Totals per trip id over every flow assigned to a trip. Scheduled flows
+/// are counted but, like everywhere else, add nothing until confirmed.
+pub fn totals_by_trip(flows: &[Flow], categories: &[Category]) -> HashMap<String, TripTotals> {
+    let mut totals: HashMap<String, TripTotals> = HashMap::new();
+    for flow in flows {
+        let Some(trip_id) = &flow.trip_id else {
+            continue;
+        };
+        let (income, expenses) = match categories.iter().find(|c| c.id == flow.category_id).map(|c| &c.flow_type) {
+            Some(FlowType::Income) => (flow.net_amount(), 0.0),
+            Some(FlowType::Expense) => (0.0, flow.net_amount()),
+            None => (0.0, 0.0),
+        };
+        totals.entry(trip_id.clone()).or_default().merge(&TripTotals {
+            income,
+            expenses,
+            flow_count: 1,
+            first: Some(flow.date),
+            last: Some(flow.date),
+        });
+    }
+    totals
+}
+
+/// Reads a `Currency` custom field value as stored, e.g. `"$1,234.50"`.
+pub fn parse_stored_currency(value: &str) -> Option<f64> {
+    value.replace(['$', ','], "").trim().parse().ok()
+}
+
+/// The stored form of an amount typed into a `Currency` custom field, which
+/// is always US-style whatever the user's number format: `"1.234,5 €"`
+/// (German format) -> `"$1234.50"`. Text that's already in the stored form
+/// is kept. `None` if it isn't an amount.
+pub fn stored_currency(typed: &str, number_format: &NumberFormat) -> Option<String> {
+    number_format.parse_amount(typed)
+        .or_else(|| parse_stored_currency(typed))
+        .map(|amount| format!("${:.2}", amount))
+}
+
+/// `flows` as tab-separated rows under a header, with the same columns as
+/// the category's flows table, so they paste straight into a spreadsheet.
+/// Amounts are plain `1234.50`-style numbers, refunds negative, as are
+/// `Currency` field values; tabs and line breaks in text become spaces.
+pub fn flows_to_tsv(flows: &[&Flow], category: &Category) -> String {
+    let clean = |text: &str| text.replace(['\t', '\n', '\r'], " ");
+    let show_tax = category.tax_deduction.deduction_allowed;
+
+    let mut header = vec!["Date".to_string(), "Amount".to_string(), "Description".to_string(), "Location".to_string()];
+    if show_tax {
+        header.push("Tax Deductible".to_string());
+    }
+    header.extend(category.fields.iter().map(|f| clean(&f.display_name())));
+    let mut out = header.join("\t");
+    out.push('\n');
+
+    for flow in flows {
+        let mut row = vec![
+            flow.date.format("%Y-%m-%d").to_string(),
+            format!("{:.2}", flow.projected_amount()),
+            clean(&flow.description),
+            clean(flow.location.as_deref().unwrap_or("")),
+        ];
+        if show_tax {
+            row.push(if flow.tax_deductible == Some(true) { "TRUE" } else { "FALSE" }.to_string());
+        }
+        for field in &category.fields {
+            let value = flow.custom_fields.get(&field.name).map(String::as_str).unwrap_or("");
+            row.push(match field.field_type {
+                FieldType::Currency => parse_stored_currency(value).map_or_else(|| clean(value), |amount| format!("{:.2}", amount)),
+                _ => clean(value),
+            });
+        }
+        out += &row.join("\t");
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether a cached total has drifted from a freshly computed one by more
+/// than rounding noise (half a cent).
+pub fn totals_differ(cached: f64, expected: f64) -> bool {
+    (cached - expected).abs() > 0.005
+}
+
+/// Describes every way the in-memory `cached` flows differ from the `stored`
+/// ones (as loaded back from the database): flows missing on either side, and
+/// flows whose contents no longer match.
+pub fn flow_discrepancies(cached: &[Flow], stored: &[Flow]) -> Vec<String> {
+    let stored_by_id: HashMap<&str, &Flow> = stored.iter().map(|f| (f.id.as_str(), f)).collect();
+    let cached_ids: std::collections::HashSet<&str> = cached.iter().map(|f| f.id.as_str()).collect();
+
+    let mut found = Vec::new();
+    for flow in cached {
+        match stored_by_id.get(flow.id.as_str()) {
+            None => found.push(format!("Flow {} ({} {}) is in memory but not in the database", flow.id, flow.date, flow.description)),
+            // Compared as JSON so `custom_fields` ordering doesn't matter.
+            Some(stored_flow) if serde_json::to_value(flow).ok() != serde_json::to_value(stored_flow).ok() => {
+                found.push(format!("Flow {} ({} {}) differs from the database copy", flow.id, flow.date, flow.description));
+            }
+            Some(_) => {}
+        }
+    }
+    for flow in stored.iter().filter(|f| !cached_ids.contains(f.id.as_str())) {
+        found.push(format!("Flow {} ({} {}) is in the database but not in memory", flow.id, flow.date, flow.description));
+    }
+    found
+}
+
+/// The system's current UTC offset, in seconds east of UTC.
+pub fn local_utc_offset() -> i32 {
+    chrono::Local::now().offset().local_minus_utc()
+}
+
+/// e.g. `UTC+05:30`, `UTC-08:00`.
+pub fn format_utc_offset(offset_seconds: i32) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let minutes = offset_seconds.unsigned_abs() / 60;
+    format!("UTC{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// The calendar date at `now` for a clock `offset_seconds` east of UTC.
+pub fn date_at_offset(now: chrono::DateTime<chrono::Utc>, offset_seconds: i32) -> NaiveDate {
+    (now + chrono::Duration::seconds(offset_seconds as i64)).date_naive()
+}
+
+/// When the system clock's "today" is a different date than it is in the
+/// home timezone -- e.g. while travelling, late in the evening -- returns
+/// (local today, home today). A flow dated with the local date then lands
+/// on another day than it would at home, which at a month or year boundary
+/// moves it into another period.
+pub fn ambiguous_today(now: chrono::DateTime<chrono::Utc>, local_offset: i32, home_offset: i32) -> Option<(NaiveDate, NaiveDate)> {
+    let local = date_at_offset(now, local_offset);
+    let home = date_at_offset(now, home_offset);
+    (local != home).then_some((local, home))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Category, CategoryField, TaxDeductionInfo};
+
+    fn category() -> Category {
+        Category {
+            id: "cat-1".to_string(),
+            name: "Test Category".to_string(),
+            flow_type: FlowType::Expense,
+            parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
+            fields: Vec::new(),
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+        }
+    }
+
+    fn flow(category_id: &str, date: NaiveDate, amount: f64) -> Flow {
+        Flow {
+            id: uuid::Uuid::new_v4().to_string(),
+            date,
+            amount,
+            category_id: category_id.to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn no_flows_for_category_returns_none() {
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        assert_eq!(calculate_tracking_ratio_as_of(&[], &cat, as_of), None);
+    }
+
+    #[test]
+    fn no_data_either_year_returns_none() {
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let flows = vec![flow(&cat.id, NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(), 100.0)];
+        assert_eq!(calculate_tracking_ratio_as_of(&flows, &cat, as_of), None);
+    }
+
+    #[test]
+    fn no_last_year_data_but_this_year_data_returns_9999() {
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let flows = vec![flow(&cat.id, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 50.0)];
+        assert_eq!(calculate_tracking_ratio_as_of(&flows, &cat, as_of), Some(9999.0));
+    }
+
+    #[test]
+    fn on_pace_ratio_is_one_in_leap_year() {
+        // 2024 is a leap year (366 days). July 1 is ordinal day 183 -> exactly
+        // half the year has passed (183 / 366 = 0.5), so a clean fraction of
+        // last year's total lets us assert an exact expected ratio.
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        assert_eq!(as_of.ordinal(), 183);
+
+        let flows = vec![
+            flow(&cat.id, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 1000.0), // last year total
+            flow(&cat.id, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(), 500.0),  // this year total
+        ];
+        let ratio = calculate_tracking_ratio_as_of(&flows, &cat, as_of).unwrap();
+        assert!((ratio - 1.0).abs() < 1e-9, "expected ratio ~1.0, got {}", ratio);
+    }
+
+    #[test]
+    fn on_pace_ratio_is_one_in_non_leap_year() {
+        // 2023 is not a leap year (365 days). March 14 is ordinal day 73 ->
+        // 73 / 365 = 0.2 exactly, another clean fraction to assert against.
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2023, 3, 14).unwrap();
+        assert_eq!(as_of.ordinal(), 73);
+
+        let flows = vec![
+            flow(&cat.id, NaiveDate::from_ymd_opt(2022, 6, 1).unwrap(), 1000.0), // last year total
+            flow(&cat.id, NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(), 200.0),  // this year total
+        ];
+        let ratio = calculate_tracking_ratio_as_of(&flows, &cat, as_of).unwrap();
+        assert!((ratio - 1.0).abs() < 1e-9, "expected ratio ~1.0, got {}", ratio);
+    }
+
+    #[test]
+    fn behind_pace_gives_ratio_below_one() {
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(); // year_progress = 0.5
+        let flows = vec![
+            flow(&cat.id, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 1_000_000.0),
+            flow(&cat.id, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(), 1.0),
+        ];
+        let ratio = calculate_tracking_ratio_as_of(&flows, &cat, as_of).unwrap();
+        assert!(ratio < 1.0, "expected ratio below 1.0, got {}", ratio);
+    }
+
+    #[test]
+    fn ratio_clamps_at_9999() {
+        let cat = category();
+        // Regardless of where in the year `as_of` falls, a this-year total this
+        // far above last year's total will always produce a ratio > 9999.
+        let as_of = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let flows = vec![
+            flow(&cat.id, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 1.0),
+            flow(&cat.id, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 1_000_000.0),
+        ];
+        assert_eq!(calculate_tracking_ratio_as_of(&flows, &cat, as_of), Some(9999.0));
+    }
+
+    #[test]
+    fn flows_from_other_categories_are_ignored() {
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let flows = vec![flow("other-category", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 500.0)];
+        assert_eq!(calculate_tracking_ratio_as_of(&flows, &cat, as_of), None);
+    }
+
+    #[test]
+    fn refunds_net_against_this_years_total() {
+        let cat = category();
+        let as_of = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let original = flow(&cat.id, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 500.0);
+        let refund = Flow {
+            refund_of: Some(original.id.clone()),
+            ..flow(&cat.id, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(), 500.0)
+        };
+        let flows = vec![
+            flow(&cat.id, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 1000.0),
+            original,
+            refund,
+        ];
+        let ratio = calculate_tracking_ratio_as_of(&flows, &cat, as_of).unwrap();
+        assert_eq!(ratio, 0.0, "a fully refunded expense should leave nothing spent this year");
+    }
+
+    #[test]
+    fn flow_discrepancies_reports_missing_and_changed_flows() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let unchanged = flow("cat-1", date, 10.0);
+        let changed = flow("cat-1", date, 20.0);
+        let memory_only = flow("cat-1", date, 30.0);
+        let db_only = flow("cat-1", date, 40.0);
+
+        let cached = vec![unchanged.clone(), changed.clone(), memory_only.clone()];
+        let stored = vec![unchanged, Flow { amount: 25.0, ..changed.clone() }, db_only.clone()];
+
+        let found = flow_discrepancies(&cached, &stored);
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert!(found.iter().any(|d| d.contains(&changed.id) && d.contains("differs")));
+        assert!(found.iter().any(|d| d.contains(&memory_only.id) && d.contains("not in the database")));
+        assert!(found.iter().any(|d| d.contains(&db_only.id) && d.contains("not in memory")));
+    }
+
+    #[test]
+    fn format_utc_offset_handles_half_hours_and_negatives() {
+        assert_eq!(format_utc_offset(0), "UTC+00:00");
+        assert_eq!(format_utc_offset(5 * 3600 + 1800), "UTC+05:30");
+        assert_eq!(format_utc_offset(-8 * 3600), "UTC-08:00");
+    }
+
+    #[test]
+    fn ambiguous_today_only_when_the_dates_differ() {
+        use chrono::TimeZone;
+        // 2024-12-31 23:30 in New York is already Jan 1 in London.
+        let now = chrono::Utc.with_ymd_and_hms(2025, 1, 1, 4, 30, 0).unwrap();
+        let new_york = -5 * 3600;
+        let london = 0;
+        assert_eq!(
+            ambiguous_today(now, new_york, london),
+            Some((NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()))
+        );
+        assert_eq!(ambiguous_today(now, london, 3600), None);
+        assert_eq!(ambiguous_today(now, new_york, new_york), None);
+    }
+
+    #[test]
+    fn trailing_monthly_totals_cover_the_months_up_to_as_of_across_a_year_boundary() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 2, 10).unwrap();
+        let flows = vec![
+            flow("cat-1", NaiveDate::from_ymd_opt(2023, 11, 30).unwrap(), 5.0), // too old
+            flow("cat-1", NaiveDate::from_ymd_opt(2023, 12, 1).unwrap(), 10.0),
+            flow("cat-1", NaiveDate::from_ymd_opt(2024, 2, 28).unwrap(), 20.0),
+            flow("cat-1", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 1.0),
+            flow("cat-2", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 99.0),
+        ];
+        assert_eq!(trailing_monthly_totals(&flows, "cat-1", as_of, 3), vec![10.0, 0.0, 21.0]);
+    }
+
+    #[test]
+    fn locations_group_ignoring_case_for_totals_and_suggestions() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let at = |location: &str, amount: f64| Flow { location: Some(location.to_string()), ..flow("cat-1", date, amount) };
+        let flows = vec![
+            at("Lisbon", 40.0),
+            at(" lisbon ", 60.0),
+            at("Porto", 150.0),
+            at("Lisbon Airport", 10.0),
+            at("", 99.0),
+            flow("cat-1", date, 99.0),
+        ];
+
+        assert_eq!(totals_by_location(&flows), vec![
+            ("Porto".to_string(), 150.0),
+            ("Lisbon".to_string(), 100.0),
+            ("Lisbon Airport".to_string(), 10.0),
+        ]);
+        assert_eq!(location_suggestions(&flows, "lis", 5), vec!["Lisbon".to_string(), "Lisbon Airport".to_string()]);
+        assert_eq!(location_suggestions(&flows, "LISBON", 5), vec!["Lisbon Airport".to_string()]);
+    }
+
+    #[test]
+    fn trip_totals_split_income_and_expenses_and_net_refunds() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let income = Category { id: "cat-2".to_string(), flow_type: FlowType::Income, ..category() };
+        let on = |trip: &str, flow: Flow| Flow { trip_id: Some(trip.to_string()), ..flow };
+        let hotel = on("lisbon", flow("cat-1", date, 300.0));
+        let refund = Flow { refund_of: Some(hotel.id.clone()), ..on("lisbon", flow("cat-1", date, 50.0)) };
+        let flows = vec![
+            hotel,
+            refund,
+            on("lisbon", flow("cat-2", date, 120.0)),
+            on("porto", flow("cat-1", date, 80.0)),
+            flow("cat-1", date, 999.0),
+        ];
+
+        let totals = totals_by_trip(&flows, &[category(), income]);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals["lisbon"], TripTotals { income: 120.0, expenses: 250.0, flow_count: 3, first: Some(date), last: Some(date) });
+        assert_eq!(totals["porto"], TripTotals { income: 0.0, expenses: 80.0, flow_count: 1, first: Some(date), last: Some(date) });
+    }
+
+    #[test]
+    fn flows_to_tsv_has_a_header_and_the_table_columns() {
+        let mut cat = category();
+        cat.fields = vec![CategoryField { name: "copay".to_string(), field_type: FieldType::Currency, required: false, default_value: None }];
+        let mut visit = flow("cat-1", NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(), 1234.5);
+        visit.description = "Checkup\twith\nnotes".to_string();
+        visit.custom_fields.insert("copay".to_string(), "$1,020.00".to_string());
+        let mut refund = flow("cat-1", NaiveDate::from_ymd_opt(2024, 3, 9).unwrap(), 20.0);
+        refund.refund_of = Some(visit.id.clone());
+        refund.location = Some("Clinic".to_string());
+
+        assert_eq!(
+            flows_to_tsv(&[&visit, &refund], &cat),
+            "Date\tAmount\tDescription\tLocation\tCopay\n\
+             2024-03-05\t1234.50\tCheckup with notes\t\t1020.00\n\
+             2024-03-09\t-20.00\t\tClinic\t\n"
+        );
+    }
+
+    #[test]
+        fn stored_currency_is_us_style_whatever_the_number_format() {
+        let german = NumberFormat::presets().into_iter().find(|(name, _)| name.contains("German")).unwrap().1;
+        assert_eq!(stored_currency("1.234,5 €", &german).as_deref(), Some("$1234.50"));
+        assert_eq!(stored_currency("$12.50", &german).as_deref(), Some("$12.50"), "an untouched stored value");
+        assert_eq!(stored_currency("$1,250.5", &NumberFormat::default()).as_deref(), Some("$1250.50"));
+        assert_eq!(stored_currency("a lot", &NumberFormat::default()), None);
+        assert_eq!(parse_stored_currency("$1,234.50"), Some(1234.5));
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::import::{import_csv, import_ofx, ImportPreset, ImportResult};
+use crate::models::{Category, Flow};
+
+/// Subfolders of the watch folder that processed files are moved into, so
+/// each file is imported exactly once and nothing is ever deleted.
+pub const IMPORTED_DIR: &str = "imported";
+pub const FAILED_DIR: &str = "failed";
+
+/// How often the watch folder is rescanned while the app is running.
+pub const SCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Files modified more recently than this are left for the next scan, in
+/// case they're still being written (e.g. a download in progress).
+pub const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Extensions (compared case-insensitively) of the files that are
+/// imported: CSV through the import presets, OFX and its Quicken flavour
+/// QFX through `import_ofx`.
+pub const EXTENSIONS: [&str; 3] = ["csv", "ofx", "qfx"];
+
+/// Importable files (see `EXTENSIONS`) directly inside `folder` that
+/// haven't been modified for at least `settled_for`, sorted by name.
+/// Subfolders (including `imported/` and `failed/`) and other file types
+/// are ignored.
+pub fn pending_files(folder: &Path, settled_for: Duration) -> std::io::Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(folder)? {
+        let entry = entry?;
+        let path = entry.path();
+        let importable = path.extension()
+            .is_some_and(|ext| EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)));
+        let metadata = entry.metadata()?;
+        if !importable || !metadata.is_file() {
+            continue;
+        }
+        let settled = metadata.modified().ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_none_or(|age| age >= settled_for);
+        if settled {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// A watched file, parsed and ready to save.
+#[derive(Debug)]
+pub struct FileImport {
+    pub preset_name: String,
+    pub flows: Vec<Flow>,
+    /// Rows that couldn't be parsed, or had no category of the right type.
+    pub skipped: usize,
+}
+
+/// Parses `text` with the first of `presets` whose columns it has, trying
+/// them last to first so the generic fallback (first in
+/// `ImportPreset::builtin_presets`) is tried last. Unlike the import
+/// dialog there's no one to review the rows, so each goes into its
+/// suggested category and rows without one are skipped.
+pub fn prepare_import(text: &str, presets: &[ImportPreset], categories: &[Category]) -> Result<FileImport, String> {
+    let mut last_error = "no import formats are available".to_string();
+    for preset in presets.iter().rev() {
+        let result = match import_csv(text, preset) {
+            Ok(result) => result,
+            Err(e) => {
+                last_error = e.to_string();
+                continue;
+            }
+        };
+        // Every row failing (e.g. a Mint export read as generic, whose dates
+        // don't parse) means this isn't the file's format after all.
+        if result.rows.is_empty() && !result.errors.is_empty() {
+            last_error = format!("no rows could be read as {}", preset.name);
+            continue;
+        }
+
+        return Ok(assign_categories(result, preset, categories));
+    }
+    Err(last_error)
+}
+
+/// Parses an OFX statement (see `import_ofx`). Rows carry no source
+/// category, so each goes into the catch-all category for its direction.
+pub fn prepare_ofx_import(text: &str, categories: &[Category]) -> Result<FileImport, String> {
+    let result = import_ofx(text).map_err(|e| e.to_string())?;
+    if result.rows.is_empty() && !result.errors.is_empty() {
+        return Err("no transactions could be read".to_string());
+    }
+    let mut import = assign_categories(result, &ImportPreset::generic(), categories);
+    import.preset_name = "OFX".to_string();
+    Ok(import)
+}
+
+/// Parses the file at `path` with the importer for its extension.
+pub fn prepare_file(path: &Path, presets: &[ImportPreset], categories: &[Category]) -> Result<FileImport, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let is_csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        prepare_import(&text, presets, categories)
+    } else {
+        prepare_ofx_import(&text, categories)
+    }
+}
+
+/// Puts each row into its suggested category, counting rows without one
+/// (and rows that failed to parse) as skipped.
+fn assign_categories(result: ImportResult, preset: &ImportPreset, categories: &[Category]) -> FileImport {
+    let mut skipped = result.errors.len();
+    let mut flows = Vec::new();
+    for row in result.rows {
+        match row.suggested_category_id(preset, categories) {
+            Some(category_id) => flows.push(row.into_flow(category_id)),
+            None => skipped += 1,
+        }
+    }
+    FileImport { preset_name: preset.name.clone(), flows, skipped }
+}
+
+/// Moves `path` into the `subfolder` of its own folder, creating it if
+/// needed. A file of the same name already there is kept by prefixing the
+/// new one with a timestamp. Returns the new path.
+pub fn archive(path: &Path, subfolder: &str) -> std::io::Result<PathBuf> {
+    let folder = path.parent().unwrap_or(Path::new(".")).join(subfolder);
+    std::fs::create_dir_all(&folder)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut destination = folder.join(&file_name);
+    if destination.exists() {
+        let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        destination = folder.join(format!("{}_{}", stamp, file_name));
+    }
+    std::fs::rename(path, &destination)?;
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::get_default_categories;
+
+    #[test]
+    fn pending_files_lists_settled_importable_files_only() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        std::fs::write(dir.path().join("b.csv"), "x").unwrap();
+        std::fs::write(dir.path().join("a.CSV"), "x").unwrap();
+        std::fs::write(dir.path().join("statement.ofx"), "x").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        std::fs::create_dir(dir.path().join(IMPORTED_DIR)).unwrap();
+        std::fs::write(dir.path().join(IMPORTED_DIR).join("old.csv"), "x").unwrap();
+
+        let names: Vec<String> = pending_files(dir.path(), Duration::ZERO).unwrap().iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["a.CSV", "b.csv", "statement.ofx"]);
+
+        assert!(pending_files(dir.path(), Duration::from_secs(3600)).unwrap().is_empty(), "just-written files wait");
+    }
+
+    #[test]
+    fn prepare_import_picks_the_format_the_file_matches() {
+        let mint = "Date,Description,Original Description,Amount,Transaction Type,Category,Account Name,Labels,Notes\n\
+                    1/15/2024,Paycheck,ACME PAYROLL,2500.00,credit,Paycheck,Checking,,\n";
+        let import = prepare_import(mint, &ImportPreset::builtin_presets(), &get_default_categories()).unwrap();
+        assert_eq!(import.preset_name, ImportPreset::mint().name);
+        assert_eq!(import.flows.len(), 1);
+        assert_eq!(import.flows[0].category_id, "salary");
+
+        let generic = "Date,Description,Amount\n2024-01-01,Coffee,-4.50\n2024-01-02,Bad,xyz\n";
+        let import = prepare_import(generic, &ImportPreset::builtin_presets(), &get_default_categories()).unwrap();
+        assert_eq!(import.preset_name, ImportPreset::generic().name);
+        assert_eq!((import.flows.len(), import.skipped), (1, 1));
+
+        assert!(prepare_import("Foo,Bar\n1,2\n", &ImportPreset::builtin_presets(), &[]).is_err());
+    }
+
+    #[test]
+    fn prepare_file_routes_ofx_statements_to_the_ofx_importer() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("statement.ofx");
+        std::fs::write(&path, "<OFX><STMTTRN><DTPOSTED>20240115<TRNAMT>-4.50<NAME>Coffee\n\
+<STMTTRN><DTPOSTED>20240131<TRNAMT>2500.00<NAME>Payroll\n</OFX>").unwrap();
+
+        let import = prepare_file(&path, &ImportPreset::builtin_presets(), &get_default_categories()).unwrap();
+        assert_eq!(import.preset_name, "OFX");
+        let categories: Vec<&str> = import.flows.iter().map(|f| f.category_id.as_str()).collect();
+        assert_eq!(categories, vec!["other_expense", "other_income"]);
+
+        std::fs::write(&path, "not a statement").unwrap();
+        assert!(prepare_file(&path, &ImportPreset::builtin_presets(), &get_default_categories()).is_err());
+    }
+
+    #[test]
+    fn archive_moves_without_overwriting() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("jan.csv");
+        std::fs::write(&path, "first").unwrap();
+        let first = archive(&path, IMPORTED_DIR).unwrap();
+        std::fs::write(&path, "second").unwrap();
+        let second = archive(&path, IMPORTED_DIR).unwrap();
+
+        assert!(!path.exists());
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(first).unwrap(), "first");
+        assert_eq!(std::fs::read_to_string(second).unwrap(), "second");
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::Datelike;
+
+use crate::locale::NumberFormat;
+use crate::models::Flow;
+
+pub const MONTH_LABELS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// A category's year at a glance: total, flow count, and average flow per
+/// calendar month, plus the same for the whole year -- the summary people
+/// otherwise rebuild by hand in a spreadsheet. Scheduled flows haven't
+/// happened yet, so they're left out of every metric.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearGrid {
+    pub totals: [f64; 12],
+    pub counts: [usize; 12],
+}
+
+impl YearGrid {
+    /// Folds `flows` by calendar month regardless of year; callers filter to
+    /// the year (or report period) they want first.
+    pub fn from_flows<'a>(flows: impl IntoIterator<Item = &'a Flow>) -> Self {
+        let mut grid = YearGrid { totals: [0.0; 12], counts: [0; 12] };
+        for flow in flows.into_iter().filter(|f| !f.scheduled) {
+            let month = flow.date.month0() as usize;
+            grid.totals[month] += flow.net_amount();
+            grid.counts[month] += 1;
+        }
+        grid
+    }
+
+    pub fn for_category(flows: &[Flow], category_id: &str, year: i32) -> Self {
+        Self::from_flows(flows.iter().filter(|f| f.category_id == category_id && f.date.year() == year))
+    }
+
+    pub fn year_total(&self) -> f64 {
+        self.totals.iter().sum()
+    }
+
+    pub fn year_count(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Average flow amount, or 0 when there were none.
+    fn average(total: f64, count: usize) -> f64 {
+        if count == 0 { 0.0 } else { total / count as f64 }
+    }
+
+    /// (metric, 12 monthly values then the year's) rows, formatted for
+    /// display.
+    pub fn rows(&self, format: &NumberFormat) -> Vec<(&'static str, Vec<String>)> {
+        let amounts = |values: Vec<f64>| values.into_iter().map(|v| format.format_currency(v)).collect();
+        vec![
+            ("Total", amounts(self.totals.iter().copied().chain([self.year_total()]).collect())),
+            ("Count", self.counts.iter().copied().chain([self.year_count()]).map(|c| c.to_string()).collect()),
+            ("Average", amounts(self.averages())),
+        ]
+    }
+
+    /// Monthly averages followed by the year's.
+    pub fn averages(&self) -> Vec<f64> {
+        (0..12)
+            .map(|m| Self::average(self.totals[m], self.counts[m]))
+            .chain([Self::average(self.year_total(), self.year_count())])
+            .collect()
+    }
+
+    /// Tab-separated, with plain `1234.50`-style numbers, so it pastes
+    /// straight into a spreadsheet.
+    pub fn to_tsv(&self) -> String {
+        let mut out = std::iter::once("Metric")
+            .chain(MONTH_LABELS)
+            .chain(["Year"])
+            .collect::<Vec<_>>()
+            .join("\t");
+        out.push('\n');
+        let row = |name: &str, values: Vec<String>| format!("{}\t{}\n", name, values.join("\t"));
+        out += &row("Total", self.totals.iter().chain([&self.year_total()]).map(|v| format!("{:.2}", v)).collect());
+        out += &row("Count", self.counts.iter().chain([&self.year_count()]).map(|c| c.to_string()).collect());
+        out += &row("Average", self.averages().iter().map(|v| format!("{:.2}", v)).collect());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    fn flow(category_id: &str, date: NaiveDate, amount: f64) -> Flow {
+        Flow {
+            id: uuid::Uuid::new_v4().to_string(),
+            date,
+            amount,
+            category_id: category_id.to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn for_category_totals_counts_and_averages_by_month() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let mut planned = flow("food", date(3, 1), 99.0);
+        planned.scheduled = true;
+        let flows = vec![
+            flow("food", date(1, 5), 10.0),
+            flow("food", date(1, 20), 30.0),
+            flow("food", date(3, 1), 5.0),
+            planned,
+            flow("rent", date(1, 1), 1000.0),
+            flow("food", NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), 7.0),
+        ];
+
+        let grid = YearGrid::for_category(&flows, "food", 2024);
+
+        assert_eq!(grid.totals[0], 40.0);
+        assert_eq!(grid.counts[0], 2);
+        assert_eq!(grid.totals[2], 5.0);
+        assert_eq!(grid.counts[2], 1, "scheduled flows don't count");
+        assert_eq!(grid.year_total(), 45.0);
+        let averages = grid.averages();
+        assert_eq!(averages[0], 20.0);
+        assert_eq!(averages[1], 0.0, "no flows means a zero average, not NaN");
+        assert_eq!(averages[12], 15.0);
+    }
+
+    #[test]
+    fn to_tsv_has_a_header_and_one_line_per_metric() {
+        let grid = YearGrid::from_flows(&[flow("food", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 1234.5)]);
+        let tsv = grid.to_tsv();
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Metric\tJan\tFeb"));
+        assert!(lines[0].ends_with("Dec\tYear"));
+        assert_eq!(lines[1], "Total\t0.00\t1234.50\t0.00\t0.00\t0.00\t0.00\t0.00\t0.00\t0.00\t0.00\t0.00\t0.00\t1234.50");
+        assert!(lines[2].starts_with("Count\t0\t1\t"));
+    }
+}
@@ -0,0 +1,540 @@
+//! Backup/restore/dump round trips for `Database`, using `tempfile` so nothing
+//! touches the user's real `~/.preft` directory.
+//!
+//! Three tests here (`detect_encrypted_backup_true_for_encrypted_backup`,
+//! `restore_from_file_on_encrypted_backup_correctly_restores_settings`, and
+//! `restore_from_sql_file_succeeds_against_an_already_initialized_database`)
+//! are regression tests for three bugs found while writing this file (broken
+//! encrypted-backup detection/restore, and a `restore_from_sql_file` schema
+//! bug) and since fixed in `src/db.rs`. See each test's comment for the root
+//! cause that was fixed.
+
+use preft_core::db::{is_file_encrypted_at, BackupProgress, Database};
+use preft_core::encryption::{DatabaseEncryption, KeyDerivation};
+use preft_core::encryption_config::EncryptionConfig;
+use preft_core::metrics::MetricSnapshot;
+use preft_core::models::{Category, CategoryField, FlowType, TaxDeductionInfo};
+use preft_core::settings::UserSettings;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::io::Write;
+
+fn test_db() -> Database {
+    Database::new_for_test(Connection::open_in_memory().expect("open in-memory db"))
+        .expect("initialize test db")
+}
+
+fn category_with_fields(id: &str, fields: Vec<CategoryField>) -> Category {
+    Category {
+        id: id.to_string(),
+        name: format!("Category {}", id),
+        flow_type: FlowType::Expense,
+        parent_id: None,
+        sort_order: 0,
+        color: None,
+        icon: None,
+        fields,
+        tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+    }
+}
+
+// --- encrypt_data/decrypt_data (indirectly, via the public save/load API) ---
+
+#[test]
+fn user_settings_round_trip_through_encryption() {
+    let salt = DatabaseEncryption::generate_salt();
+    let mut db = test_db();
+    db.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+    assert!(db.is_encrypted());
+
+    let mut settings = UserSettings::new();
+    settings.set_year_filter(Some(2019));
+    db.save_user_settings(&settings).expect("save settings");
+
+    let loaded = db.load_user_settings().expect("load settings");
+    assert_eq!(loaded.get_year_filter(), Some(2019));
+}
+
+#[test]
+fn detect_encryption_state_reflects_the_stored_settings() {
+    let mut db = test_db();
+    assert!(!db.detect_encryption_state(), "nothing saved yet");
+    db.save_user_settings(&UserSettings::new()).expect("save plaintext settings");
+    assert!(!db.detect_encryption_state());
+
+    db.enable_encryption_for_test("s3cret", &DatabaseEncryption::generate_salt()).expect("set up encryption");
+    db.save_user_settings(&UserSettings::new()).expect("save encrypted settings");
+    assert!(db.detect_encryption_state());
+}
+
+// --- backup_to_file / restore_from_file (binary rusqlite backup) ---
+
+#[test]
+fn unencrypted_backup_and_restore_round_trip() {
+    let mut db1 = test_db();
+    db1.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let mut custom_fields = HashMap::new();
+    custom_fields.insert("note".to_string(), "hello".to_string());
+    let flow = preft_core::models::Flow {
+        id: "flow-1".to_string(),
+        date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        amount: 42.0,
+        category_id: "cat-1".to_string(),
+        description: "Test flow".to_string(),
+        linked_flows: Vec::new(),
+        custom_fields,
+        tax_deductible: Some(true),
+        refund_of: None,
+        scheduled: false,
+        created_utc_offset: None,
+        location: None,
+        trip_id: None,
+        reimbursement: None,
+        reimbursed_by: None,
+    };
+    db1.save_flow(&flow).expect("save flow");
+
+    let mut settings = UserSettings::new();
+    settings.set_year_filter(Some(2022));
+    db1.save_user_settings(&settings).expect("save settings");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, false).expect("unencrypted backup should succeed");
+
+    let mut db2 = test_db(); // fresh, empty
+    db2.restore_from_file(&backup_path, None, false).expect("restore should succeed");
+
+    let categories = db2.load_categories().expect("load categories");
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].id, "cat-1");
+
+    let flows = db2.load_flows().expect("load flows");
+    assert_eq!(flows.len(), 1);
+    assert_eq!(flows[0].custom_fields.get("note"), Some(&"hello".to_string()));
+    assert_eq!(flows[0].tax_deductible, Some(true));
+
+    let loaded_settings = db2.load_user_settings().expect("load settings");
+    assert_eq!(loaded_settings.get_year_filter(), Some(2022));
+}
+
+#[test]
+fn unencrypted_backup_and_restore_carries_metric_snapshots() {
+    let db1 = test_db();
+    let snapshot = MetricSnapshot {
+        period: "2024-02".to_string(),
+        taken_on: chrono::NaiveDate::from_ymd_opt(2024, 2, 14).unwrap(),
+        metrics: vec![("net_worth".to_string(), 1234.5)],
+    };
+    db1.save_metric_snapshot(&snapshot).expect("save snapshot");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, false).expect("unencrypted backup should succeed");
+
+    let mut db2 = test_db();
+    db2.restore_from_file(&backup_path, None, false).expect("restore should succeed");
+
+    assert_eq!(db2.load_metric_snapshots().expect("load snapshots"), vec![snapshot]);
+}
+
+#[test]
+fn unencrypted_backup_and_restore_carries_locked_years() {
+    let db1 = test_db();
+    db1.lock_year(2023).expect("lock year");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, false).expect("unencrypted backup should succeed");
+
+    let mut db2 = test_db();
+    db2.lock_year(2022).expect("lock year");
+    db2.restore_from_file(&backup_path, None, false).expect("restore should succeed");
+
+    assert_eq!(db2.load_locked_years().expect("load locked years"), vec![2023]);
+}
+
+#[test]
+fn restore_defaults_columns_an_older_backup_predates() {
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("old.db");
+    let old = Connection::open(&backup_path).expect("create old backup");
+    old.execute_batch(
+        "CREATE TABLE categories (id TEXT PRIMARY KEY, name TEXT NOT NULL, flow_type TEXT NOT NULL, fields TEXT NOT NULL,
+             tax_deduction_allowed INTEGER NOT NULL, tax_deduction_default INTEGER NOT NULL);
+         CREATE TABLE flows (id TEXT PRIMARY KEY, date TEXT NOT NULL, amount REAL NOT NULL, category_id TEXT NOT NULL,
+             description TEXT NOT NULL, linked_flows TEXT NOT NULL, custom_fields TEXT NOT NULL, tax_deductible INTEGER);
+         CREATE TABLE user_settings (id INTEGER PRIMARY KEY, settings_json TEXT NOT NULL);
+         INSERT INTO categories VALUES ('cat-1', 'Old', 'Expense', '[]', 0, 0);
+         INSERT INTO flows VALUES ('flow-1', '2019-05-01', 12.5, 'cat-1', 'Old flow', '[]', '{}', NULL);",
+    )
+    .expect("fill old backup");
+    drop(old);
+
+    let mut db = test_db();
+    db.restore_from_file(&backup_path, None, false).expect("restore should succeed");
+
+    let categories = db.load_categories().expect("load categories");
+    assert_eq!(categories.len(), 1);
+    assert!(categories[0].tax_deduction.jurisdictions.is_empty());
+    assert_eq!(categories[0].color, None);
+
+    let flows = db.load_flows().expect("load flows");
+    assert_eq!(flows.len(), 1);
+    assert_eq!(flows[0].description, "Old flow");
+    assert_eq!(flows[0].refund_of, None);
+    assert!(!flows[0].scheduled);
+    assert_eq!(flows[0].reimbursement, None);
+}
+
+#[test]
+fn backup_with_progress_reaches_the_end() {
+    let db = test_db();
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let progress = BackupProgress::default();
+    db.backup_to_file_with_progress(&backup_dir.path().join("backup.db"), false, &progress)
+        .expect("backup should succeed");
+    assert_eq!(progress.fraction(), 1.0);
+}
+
+#[test]
+fn cancelled_restore_leaves_the_database_as_it_was() {
+    let db1 = test_db();
+    db1.lock_year(2023).expect("lock year");
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, false).expect("unencrypted backup should succeed");
+
+    let mut db2 = test_db();
+    db2.lock_year(2022).expect("lock year");
+    let progress = BackupProgress::default();
+    progress.cancel();
+    assert!(db2.restore_from_file_with_progress(&backup_path, None, false, &progress).is_err());
+
+    assert_eq!(db2.load_locked_years().expect("load locked years"), vec![2022]);
+}
+
+#[test]
+fn backup_to_file_encrypted_errors_when_database_not_encrypted() {
+    let db = test_db();
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+
+    let result = db.backup_to_file(&backup_path, true);
+    assert!(result.is_err());
+}
+
+#[test]
+fn backup_to_file_encrypted_succeeds_when_database_is_encrypted() {
+    let salt = DatabaseEncryption::generate_salt();
+    let mut db = test_db();
+    db.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+
+    db.backup_to_file(&backup_path, true).expect("encrypted backup should succeed");
+    assert!(backup_path.exists());
+    assert!(std::fs::metadata(&backup_path).unwrap().len() > 0);
+}
+
+#[test]
+fn detect_encrypted_backup_false_for_valid_unencrypted_backup() {
+    let db1 = test_db();
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, false).expect("backup should succeed");
+
+    assert_eq!(db1.detect_encrypted_backup(&backup_path).unwrap(), false);
+}
+
+#[test]
+fn detect_encrypted_backup_true_for_malformed_file() {
+    let db = test_db();
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let garbage_path = backup_dir.path().join("garbage.db");
+    let mut file = std::fs::File::create(&garbage_path).expect("create garbage file");
+    file.write_all(b"this is not a sqlite database").expect("write garbage bytes");
+    drop(file);
+
+    assert_eq!(db.detect_encrypted_backup(&garbage_path).unwrap(), true);
+}
+
+#[test]
+fn detect_encrypted_backup_true_for_encrypted_backup() {
+    // Regression test: `detect_encrypted_backup` used to decide "encrypted or
+    // not" by checking whether `user_settings` was queryable at all, which is
+    // true either way -- only the column *value* is encrypted, not the file.
+    // It now inspects the value itself (ciphertext never starts with '{',
+    // unlike plaintext JSON), so this requires an actual saved settings row
+    // to have anything to detect.
+    let salt = DatabaseEncryption::generate_salt();
+    let mut db = test_db();
+    db.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+    db.save_user_settings(&UserSettings::new()).expect("save settings");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db.backup_to_file(&backup_path, true).expect("encrypted backup should succeed");
+
+    assert_eq!(
+        db.detect_encrypted_backup(&backup_path).unwrap(),
+        true,
+        "a backup created via the encrypted path should be detected as encrypted"
+    );
+}
+
+#[test]
+fn restore_from_file_on_encrypted_backup_correctly_restores_settings() {
+    // Regression test for the same `detect_encrypted_backup` bug fixed in
+    // `detect_encrypted_backup_true_for_encrypted_backup` above: before the
+    // fix, detection misreported this backup as unencrypted, so
+    // `restore_from_file` took the unencrypted path and silently reset
+    // settings to defaults instead of decrypting them. The restore target
+    // must already have matching encryption configured (same password/salt)
+    // for `restore_encrypted`'s password check to succeed, mirroring needing
+    // to unlock/configure encryption before restoring in real usage.
+    let salt = DatabaseEncryption::generate_salt();
+    let mut db1 = test_db();
+    db1.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+
+    let mut settings = UserSettings::new();
+    settings.set_year_filter(Some(2021));
+    db1.save_user_settings(&settings).expect("save settings");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, true).expect("encrypted backup should succeed");
+
+    let mut db2 = test_db();
+    db2.enable_encryption_for_test("s3cret", &salt)
+        .expect("set up matching encryption on the restore target");
+    db2.restore_from_file(&backup_path, Some("s3cret"), false)
+        .expect("restore should succeed");
+
+    let restored = db2.load_user_settings().expect("load settings");
+    assert_eq!(
+        restored.get_year_filter(),
+        Some(2021),
+        "settings should be correctly decrypted after restoring an encrypted backup with the right password"
+    );
+}
+
+#[test]
+fn encrypted_backup_restores_after_the_key_derivation_is_upgraded() {
+    // The silent upgrade at login keeps the password and salt but not the
+    // derivation, so a backup taken before it is keyed with the retired one
+    let salt = DatabaseEncryption::generate_salt();
+    let config = |derivation: KeyDerivation, retired: Vec<KeyDerivation>| EncryptionConfig {
+        password_hash: Some(DatabaseEncryption::hash_password("s3cret", &salt, derivation).expect("hash password")),
+        salt: Some(salt.clone()),
+        database_encrypted: true,
+        key_derivation: derivation,
+        retired_key_derivations: retired,
+        ..EncryptionConfig::default()
+    };
+    let mut db = test_db();
+    db.set_encryption_config(config(KeyDerivation::LegacySha256, Vec::new()));
+    db.set_encryption_state(true, Some("s3cret"), Some(&salt)).expect("set up encryption");
+    let mut settings = UserSettings::new();
+    settings.set_year_filter(Some(2021));
+    db.save_user_settings(&settings).expect("save settings");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db.backup_to_file(&backup_path, true).expect("encrypted backup should succeed");
+
+    db.change_key("s3cret", &salt, KeyDerivation::RECOMMENDED).expect("upgrade derivation");
+    settings.set_year_filter(Some(2024));
+    db.save_user_settings(&settings).expect("save settings");
+
+    db.set_encryption_config(config(KeyDerivation::RECOMMENDED, Vec::new()));
+    assert!(db.restore_from_file(&backup_path, Some("s3cret"), false).is_err(), "the current key can't open it");
+    assert_eq!(db.load_user_settings().expect("load settings").get_year_filter(), Some(2024));
+
+    db.set_encryption_config(config(KeyDerivation::RECOMMENDED, vec![KeyDerivation::LegacySha256]));
+    db.restore_from_file(&backup_path, Some("s3cret"), false).expect("restore with the retired derivation");
+    assert_eq!(
+        db.load_user_settings().expect("settings re-encrypted under the current key").get_year_filter(),
+        Some(2021)
+    );
+}
+
+// --- dump_to_sql_file / restore_from_sql_file ---
+
+#[test]
+fn dump_to_sql_file_produces_expected_inserts_with_escaped_quotes() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let flow = preft_core::models::Flow {
+        id: "flow-1".to_string(),
+        date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        amount: 12.5,
+        category_id: "cat-1".to_string(),
+        description: "Tom's Coffee".to_string(),
+        linked_flows: Vec::new(),
+        custom_fields: HashMap::new(),
+        tax_deductible: None,
+        refund_of: None,
+        scheduled: false,
+        created_utc_offset: None,
+        location: None,
+        trip_id: None,
+        reimbursement: None,
+        reimbursed_by: None,
+    };
+    db.save_flow(&flow).expect("save flow");
+
+    let dump_dir = tempfile::tempdir().expect("create tempdir");
+    let dump_path = dump_dir.path().join("dump.sql");
+    db.dump_to_sql_file(&dump_path).expect("dump should succeed");
+
+    let dump_content = std::fs::read_to_string(&dump_path).expect("read dump file");
+    assert!(dump_content.contains("CREATE TABLE"), "dump should contain table schemas");
+    assert!(dump_content.contains("INSERT INTO categories"));
+    assert!(dump_content.contains("INSERT INTO flows"));
+    assert!(
+        dump_content.contains("'Tom''s Coffee'"),
+        "embedded single quotes should be escaped by doubling, per the dump's own escaping rule"
+    );
+}
+
+#[test]
+fn restore_from_sql_file_succeeds_against_an_already_initialized_database() {
+    // Regression test: `dump_to_sql_file` used to omit "IF NOT EXISTS" and
+    // the trailing semicolon SQLite strips from `sqlite_master.sql`'s stored
+    // CREATE TABLE text, so `restore_from_sql_file`'s `split(';')` glued all
+    // table schemas into one statement, and replaying it against an
+    // already-initialized database (the normal case) failed outright.
+    let mut db1 = test_db();
+    db1.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let dump_dir = tempfile::tempdir().expect("create tempdir");
+    let dump_path = dump_dir.path().join("dump.sql");
+    db1.dump_to_sql_file(&dump_path).expect("dump should succeed");
+
+    let mut db2 = test_db(); // already has its own migrations bookkeeping row
+    db2.restore_from_sql_file(&dump_path)
+        .expect("restore should succeed against an already-initialized database");
+
+    let categories = db2.load_categories().expect("load categories");
+    assert!(
+        categories.iter().any(|c| c.id == "cat-1"),
+        "restored database should contain the dumped category"
+    );
+}
+
+#[test]
+fn read_backup_contents_reads_a_backup_without_restoring_it() {
+    let mut db1 = test_db();
+    db1.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let backup_dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = backup_dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, false).expect("unencrypted backup should succeed");
+
+    let (categories, flows) = Database::read_backup_contents(&backup_path).expect("read backup");
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].id, "cat-1");
+    assert!(flows.is_empty());
+}
+
+// --- encrypt_file / unlock_file (SQLCipher, whole-file encryption) ---
+
+#[test]
+fn is_file_encrypted_at_tells_sqlite_files_from_others() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db_path = dir.path().join("preft.db");
+    assert!(!is_file_encrypted_at(&db_path).unwrap(), "a missing file is created plain");
+
+    let mut db = Database::new_for_test_at(&db_path).expect("open file db");
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    assert!(!is_file_encrypted_at(&db_path).unwrap());
+    assert!(!db.is_file_encrypted());
+
+    let garbage_path = dir.path().join("garbage.db");
+    std::fs::write(&garbage_path, b"this is not a sqlite database").expect("write garbage bytes");
+    assert!(is_file_encrypted_at(&garbage_path).unwrap());
+}
+
+#[test]
+fn encrypt_file_needs_the_password_first() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let mut db = Database::new_for_test_at(&dir.path().join("preft.db")).expect("open file db");
+    assert!(db.encrypt_file().is_err());
+}
+
+#[cfg(feature = "sqlcipher")]
+#[test]
+fn encrypt_file_round_trips_through_unlock_and_decrypt() {
+    let salt = DatabaseEncryption::generate_salt();
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db_path = dir.path().join("preft.db");
+    {
+        let mut db = Database::new_for_test_at(&db_path).expect("open file db");
+        db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+        db.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+        db.encrypt_file().expect("encrypt file");
+        assert!(db.is_file_encrypted());
+        assert_eq!(db.load_categories().expect("still readable").len(), 1);
+    }
+    let contents = std::fs::read(&db_path).unwrap();
+    assert!(!contents.windows(b"Category cat-1".len()).any(|w| w == b"Category cat-1"), "names are not stored in plain text");
+
+    let mut db = Database::new_for_test_at(&db_path).expect("open encrypted file");
+    assert!(db.is_file_locked());
+    assert!(db.unlock_file("wrong", &salt).is_err());
+    assert!(db.is_file_locked());
+    db.unlock_file("s3cret", &salt).expect("unlock with the password");
+    assert_eq!(db.load_categories().expect("load categories")[0].id, "cat-1");
+
+    db.decrypt_file().expect("decrypt file");
+    assert!(!is_file_encrypted_at(&db_path).unwrap());
+    drop(db);
+    let db = Database::new_for_test_at(&db_path).expect("open plain file");
+    assert_eq!(db.load_categories().expect("load categories").len(), 1);
+}
+
+#[cfg(feature = "sqlcipher")]
+#[test]
+fn encrypted_backup_of_an_encrypted_file_restores_with_the_password() {
+    let salt = DatabaseEncryption::generate_salt();
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let mut db1 = Database::new_for_test_at(&dir.path().join("one.db")).expect("open file db");
+    db1.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    db1.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+    db1.encrypt_file().expect("encrypt file");
+
+    let backup_path = dir.path().join("backup.db");
+    db1.backup_to_file(&backup_path, true).expect("encrypted backup should succeed");
+    assert!(is_file_encrypted_at(&backup_path).unwrap());
+
+    // A plain database restores the rows rather than the encrypted pages
+    let mut db2 = test_db();
+    db2.enable_encryption_for_test("s3cret", &salt).expect("set up encryption");
+    db2.restore_from_file(&backup_path, Some("s3cret"), false).expect("restore");
+    assert_eq!(db2.load_categories().expect("load categories")[0].id, "cat-1");
+}
+
+#[cfg(feature = "sqlcipher")]
+#[test]
+fn changing_the_key_rekeys_an_encrypted_file() {
+    let (old_salt, new_salt) = (DatabaseEncryption::generate_salt(), DatabaseEncryption::generate_salt());
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let db_path = dir.path().join("preft.db");
+    {
+        let mut db = Database::new_for_test_at(&db_path).expect("open file db");
+        db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+        db.enable_encryption_for_test("s3cret", &old_salt).expect("set up encryption");
+        db.encrypt_file().expect("encrypt file");
+        db.change_key("n3w s3cret", &new_salt, preft_core::encryption::KeyDerivation::RECOMMENDED).expect("change key");
+        assert_eq!(db.load_categories().expect("still readable").len(), 1);
+    }
+
+    let mut db = Database::new_for_test_at(&db_path).expect("open encrypted file");
+    assert!(db.unlock_file("s3cret", &old_salt).is_err());
+    db.unlock_file("n3w s3cret", &new_salt).expect("unlock with the new password");
+    assert_eq!(db.load_categories().expect("load categories")[0].id, "cat-1");
+}
@@ -0,0 +1,958 @@
+//! Category CRUD round trips against an isolated `Database::new_for_test` instance.
+//!
+//! These specifically cover the scenarios that were previously blocked by a bug
+//! in `Database::get_category`/`save_category`: saving a *brand-new* category
+//! (id not already in the table) used to error out unconditionally, so none of
+//! this was testable until that was fixed.
+
+use chrono::{Datelike, NaiveDate};
+use preft_core::db::{BackupProgress, Database, FlowScope};
+use preft_core::encryption::{DatabaseEncryption, KeyDerivation};
+use preft_core::encryption_config::SensitiveColumn;
+use preft_core::integrity::IntegrityScan;
+use preft_core::kpi::KpiTotals;
+use preft_core::metrics::MetricSnapshot;
+use preft_core::models::{Category, CategoryField, FieldType, Flow, FlowType, JurisdictionTreatment, ReimbursementStatus, SqlView, TaxDeductionInfo, Trip};
+use preft_core::pending_changes::PendingChanges;
+use preft_core::reporting::{ReportKind, ReportRequest, TimePeriod};
+use preft_core::settings::UserSettings;
+use preft_core::utils;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+fn test_db() -> Database {
+    Database::new_for_test(Connection::open_in_memory().expect("open in-memory db"))
+        .expect("initialize test db")
+}
+
+fn category_with_fields(id: &str, fields: Vec<CategoryField>) -> Category {
+    Category {
+        id: id.to_string(),
+        name: format!("Category {}", id),
+        flow_type: FlowType::Expense,
+        parent_id: None,
+        sort_order: 0,
+        color: None,
+        icon: None,
+        fields,
+        tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
+    }
+}
+
+fn flow_with_custom_fields(id: &str, category_id: &str, custom_fields: HashMap<String, String>) -> Flow {
+    Flow {
+        id: id.to_string(),
+        date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        amount: 10.0,
+        category_id: category_id.to_string(),
+        description: String::new(),
+        linked_flows: Vec::new(),
+        custom_fields,
+        tax_deductible: None,
+        refund_of: None,
+        scheduled: false,
+        created_utc_offset: None,
+        location: None,
+        trip_id: None,
+        reimbursement: None,
+        reimbursed_by: None,
+    }
+}
+
+#[test]
+fn save_category_inserts_a_brand_new_category() {
+    let mut db = test_db();
+    let category = category_with_fields("new-cat", vec![]);
+
+    db.save_category(&category)
+        .expect("saving a brand-new category should succeed");
+
+    let loaded = db.load_categories().expect("load categories");
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].id, "new-cat");
+    assert_eq!(loaded[0].name, "Category new-cat");
+}
+
+#[test]
+fn save_category_round_trips_all_field_types() {
+    let mut db = test_db();
+    #[allow(deprecated)]
+    let fields = vec![
+        CategoryField { name: "notes".to_string(), field_type: FieldType::Text, required: true, default_value: None },
+        CategoryField { name: "cost".to_string(), field_type: FieldType::Currency, required: true, default_value: None },
+        CategoryField { name: "covered".to_string(), field_type: FieldType::Boolean, required: false, default_value: Some("false".to_string()) },
+        CategoryField { name: "kind".to_string(), field_type: FieldType::Select(vec!["A".to_string(), "B".to_string()]), required: true, default_value: None },
+        CategoryField { name: "legacy".to_string(), field_type: FieldType::Number, required: false, default_value: None },
+        CategoryField { name: "count".to_string(), field_type: FieldType::Integer, required: false, default_value: None },
+        CategoryField { name: "ratio".to_string(), field_type: FieldType::Float, required: false, default_value: None },
+        CategoryField { name: "when".to_string(), field_type: FieldType::Date, required: false, default_value: None },
+    ];
+    let category = category_with_fields("field-types", fields.clone());
+    db.save_category(&category).expect("save category");
+
+    let loaded = db.load_categories().expect("load categories");
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].fields, fields);
+}
+
+#[test]
+fn save_category_round_trips_tax_jurisdictions() {
+    let mut db = test_db();
+    let mut category = category_with_fields("medical", vec![]);
+    category.tax_deduction.deduction_allowed = true;
+    category.tax_deduction.jurisdictions = vec![
+        JurisdictionTreatment { jurisdiction: "Federal".to_string(), deductible: true },
+        JurisdictionTreatment { jurisdiction: "State".to_string(), deductible: false },
+    ];
+    db.save_category(&category).expect("save category");
+
+    let loaded = db.load_categories().expect("load categories");
+    assert_eq!(loaded[0].tax_deduction, category.tax_deduction);
+}
+
+#[test]
+fn load_categories_follows_the_saved_order() {
+    let mut db = test_db();
+    for id in ["a", "b", "c"] {
+        db.save_category(&category_with_fields(id, vec![])).expect("save category");
+    }
+    let ids = |db: &Database| -> Vec<String> {
+        db.load_categories().expect("load categories").into_iter().map(|c| c.id).collect()
+    };
+    assert_eq!(ids(&db), ["a", "b", "c"], "ties keep the order they were added in");
+
+    db.save_category_order(&["c".to_string(), "a".to_string(), "b".to_string()]).expect("save order");
+    assert_eq!(ids(&db), ["c", "a", "b"]);
+
+    // Editing a category keeps its place.
+    let mut a = db.load_categories().unwrap().remove(1);
+    a.name = "Renamed".to_string();
+    db.save_category(&a).expect("save category");
+    assert_eq!(ids(&db), ["c", "a", "b"]);
+}
+
+#[test]
+fn save_category_round_trips_color_and_icon() {
+    let mut db = test_db();
+    let mut category = category_with_fields("groceries", vec![]);
+    category.color = Some([0x12, 0xab, 0xff]);
+    category.icon = Some("🛒".to_string());
+    db.save_category(&category).expect("save category");
+
+    let loaded = db.load_categories().expect("load categories");
+    assert_eq!(loaded[0].color, Some([0x12, 0xab, 0xff]));
+    assert_eq!(loaded[0].icon.as_deref(), Some("🛒"));
+    assert_eq!(loaded[0].label(), "🛒 Category groceries");
+
+    category.color = None;
+    category.icon = None;
+    db.save_category(&category).expect("clear color and icon");
+    let loaded = db.load_categories().expect("load categories");
+    assert_eq!((loaded[0].color, loaded[0].icon.clone()), (None, None));
+    assert_eq!(loaded[0].label(), "Category groceries");
+}
+
+#[test]
+fn save_category_update_preserves_existing_id_and_changes_name() {
+    let mut db = test_db();
+    let category = category_with_fields("stable-cat", vec![]);
+    db.save_category(&category).expect("initial save");
+
+    let mut renamed = category.clone();
+    renamed.name = "Renamed".to_string();
+    db.save_category(&renamed).expect("update save");
+
+    let loaded = db.load_categories().expect("load categories");
+    assert_eq!(loaded.len(), 1, "update should replace, not duplicate, the category");
+    assert_eq!(loaded[0].name, "Renamed");
+}
+
+#[test]
+fn save_category_update_without_schema_change_leaves_flows_untouched() {
+    let mut db = test_db();
+    let fields = vec![CategoryField {
+        name: "amount".to_string(),
+        field_type: FieldType::Currency,
+        required: true,
+        default_value: None,
+    }];
+    let category = category_with_fields("stable-fields-cat", fields);
+    db.save_category(&category).expect("initial save");
+
+    let mut custom_fields = HashMap::new();
+    custom_fields.insert("amount".to_string(), "$10.00".to_string());
+    let flow = flow_with_custom_fields("flow-1", "stable-fields-cat", custom_fields);
+    db.save_flow(&flow).expect("save flow");
+
+    // Re-save the same category with only the name changed -- no field schema change.
+    let mut renamed = category.clone();
+    renamed.name = "Renamed".to_string();
+    db.save_category(&renamed).expect("update save");
+
+    let flows = db.load_flows().expect("load flows");
+    assert_eq!(
+        flows[0].custom_fields.get("amount"),
+        Some(&"$10.00".to_string()),
+        "flow custom fields should be untouched when the category schema didn't change"
+    );
+}
+
+#[test]
+fn save_category_schema_change_migrates_existing_flows() {
+    let mut db = test_db();
+    let original_fields = vec![
+        CategoryField { name: "amount".to_string(), field_type: FieldType::Currency, required: true, default_value: None },
+        CategoryField { name: "old_field".to_string(), field_type: FieldType::Text, required: false, default_value: None },
+    ];
+    let category = category_with_fields("evolving-cat", original_fields);
+    db.save_category(&category).expect("initial save");
+
+    let mut custom_fields = HashMap::new();
+    custom_fields.insert("amount".to_string(), "$10.00".to_string());
+    custom_fields.insert("old_field".to_string(), "some text".to_string());
+    let flow = flow_with_custom_fields("flow-1", "evolving-cat", custom_fields);
+    db.save_flow(&flow).expect("save flow");
+
+    // Remove `old_field` from the category schema -- this is a schema change.
+    let mut updated = category.clone();
+    updated.fields.retain(|f| f.name != "old_field");
+    db.save_category(&updated).expect("schema-changing save");
+
+    let flows = db.load_flows().expect("load flows");
+    let migrated_flow = flows.iter().find(|f| f.id == "flow-1").unwrap();
+    assert!(
+        !migrated_flow.custom_fields.contains_key("old_field"),
+        "field removed from the category schema should be stripped from existing flows"
+    );
+    assert_eq!(
+        migrated_flow.custom_fields.get("amount"),
+        Some(&"10.00".to_string()),
+        "surviving Currency fields are normalized (symbols/commas stripped) whenever a schema-changing save runs a migration"
+    );
+}
+
+#[test]
+fn delete_category_removes_it_from_load_categories() {
+    let mut db = test_db();
+    let category = category_with_fields("to-delete", vec![]);
+    db.save_category(&category).expect("save category");
+    assert_eq!(db.load_categories().unwrap().len(), 1);
+
+    db.delete_category("to-delete").expect("delete category");
+    assert!(db.load_categories().unwrap().is_empty());
+}
+
+#[test]
+fn multiple_new_categories_can_be_saved_independently() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-a", vec![])).expect("save cat-a");
+    db.save_category(&category_with_fields("cat-b", vec![])).expect("save cat-b");
+
+    let mut loaded_ids: Vec<String> = db.load_categories().unwrap().into_iter().map(|c| c.id).collect();
+    loaded_ids.sort();
+    assert_eq!(loaded_ids, vec!["cat-a".to_string(), "cat-b".to_string()]);
+}
+
+#[test]
+fn save_flow_round_trips_refund_link() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let original = flow_with_custom_fields("original", "cat-1", HashMap::new());
+    let refund = Flow {
+        refund_of: Some("original".to_string()),
+        ..flow_with_custom_fields("refund", "cat-1", HashMap::new())
+    };
+    db.save_flow(&original).expect("save original");
+    db.save_flow(&refund).expect("save refund");
+
+    let loaded = db.load_flows().expect("load flows");
+    let loaded_refund = loaded.iter().find(|f| f.id == "refund").expect("refund loaded");
+    assert_eq!(loaded_refund.refund_of.as_deref(), Some("original"));
+    assert_eq!(loaded_refund.net_amount(), -10.0);
+    assert!(!loaded.iter().find(|f| f.id == "original").unwrap().is_refund());
+}
+
+#[test]
+fn save_flow_round_trips_scheduled_flag() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let planned = Flow {
+        scheduled: true,
+        ..flow_with_custom_fields("planned", "cat-1", HashMap::new())
+    };
+    db.save_flow(&planned).expect("save planned");
+
+    let loaded = db.load_flows().expect("load flows");
+    assert!(loaded[0].scheduled);
+    assert_eq!(loaded[0].net_amount(), 0.0);
+}
+
+#[test]
+fn load_flows_in_narrows_by_category_and_years_newest_first() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    db.save_category(&category_with_fields("cat-2", vec![])).expect("save category");
+    let flow = |id: &str, category_id: &str, year: i32, month: u32| Flow {
+        date: NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+        ..flow_with_custom_fields(id, category_id, HashMap::new())
+    };
+    for saved in [
+        flow("a", "cat-1", 2023, 6),
+        flow("b", "cat-1", 2024, 2),
+        flow("c", "cat-1", 2024, 9),
+        flow("d", "cat-2", 2024, 5),
+        flow("e", "cat-2", 2025, 1),
+    ] {
+        db.save_flow(&saved).expect("save flow");
+    }
+    let ids = |scope: FlowScope| -> Vec<String> {
+        db.load_flows_in(&scope).expect("load flows").into_iter().map(|f| f.id).collect()
+    };
+
+    assert_eq!(ids(FlowScope::default()), vec!["e", "c", "d", "b", "a"]);
+    assert_eq!(ids(FlowScope::category("cat-1", None)), vec!["c", "b", "a"]);
+    assert_eq!(ids(FlowScope::category("cat-1", Some(2024))), vec!["c", "b"]);
+    assert_eq!(ids(FlowScope::category("cat-2", Some(2023))), Vec::<String>::new());
+    assert_eq!(ids(FlowScope::years(2024..=2025)), vec!["e", "c", "d", "b"]);
+    assert!(FlowScope::category("cat-1", Some(2024)).contains(&flow("x", "cat-1", 2024, 12)));
+    assert!(!FlowScope::category("cat-1", Some(2024)).contains(&flow("x", "cat-1", 2025, 1)));
+}
+
+#[test]
+fn load_flows_in_pages_with_limit_and_offset() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    for day in 1..=5 {
+        let flow = Flow {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            ..flow_with_custom_fields(&format!("f{}", day), "cat-1", HashMap::new())
+        };
+        db.save_flow(&flow).expect("save flow");
+    }
+    let page = |limit: Option<usize>, offset: usize| -> Vec<String> {
+        let scope = FlowScope { limit, offset, ..FlowScope::category("cat-1", Some(2024)) };
+        db.load_flows_in(&scope).expect("load flows").into_iter().map(|f| f.id).collect()
+    };
+
+    assert_eq!(page(Some(2), 0), vec!["f5", "f4"]);
+    assert_eq!(page(Some(2), 2), vec!["f3", "f2"]);
+    assert_eq!(page(Some(2), 4), vec!["f1"]);
+    assert_eq!(page(None, 3), vec!["f2", "f1"], "an offset without a limit runs to the end");
+    assert!(page(Some(2), 5).is_empty());
+}
+
+#[test]
+fn linked_and_scheduled_flows_load_without_the_rest() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    let flow = |id: &str| flow_with_custom_fields(id, "cat-1", HashMap::new());
+    db.save_flow(&flow("original")).expect("save original");
+    db.save_flow(&Flow { refund_of: Some("original".to_string()), ..flow("refund") }).expect("save refund");
+    db.save_flow(&Flow { reimbursed_by: Some("payback".to_string()), ..flow("expense") }).expect("save expense");
+    db.save_flow(&Flow { scheduled: true, ..flow("planned") }).expect("save scheduled flow");
+
+    let mut linked: Vec<String> = db.load_flows_linked_to(&["original".to_string(), "payback".to_string()])
+        .expect("load linked flows")
+        .into_iter()
+        .map(|f| f.id)
+        .collect();
+    linked.sort();
+    assert_eq!(linked, vec!["expense", "refund"]);
+    assert!(db.load_flows_linked_to(&[]).expect("load linked flows").is_empty());
+
+    let scheduled: Vec<String> = db.load_scheduled_flows().expect("load scheduled flows").into_iter().map(|f| f.id).collect();
+    assert_eq!(scheduled, vec!["planned"]);
+}
+
+#[test]
+fn outstanding_reimbursements_load_oldest_first_whatever_their_year() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    let claim = |id: &str, year: i32, status: Option<ReimbursementStatus>| Flow {
+        date: NaiveDate::from_ymd_opt(year, 6, 1).unwrap(),
+        reimbursement: status,
+        ..flow_with_custom_fields(id, "cat-1", HashMap::new())
+    };
+    db.save_flow(&claim("submitted", 2024, Some(ReimbursementStatus::Submitted))).expect("save flow");
+    db.save_flow(&claim("old", 2015, Some(ReimbursementStatus::Outstanding))).expect("save flow");
+    db.save_flow(&claim("paid", 2020, Some(ReimbursementStatus::Reimbursed))).expect("save flow");
+    db.save_flow(&claim("plain", 2021, None)).expect("save flow");
+    db.save_flow(&Flow { scheduled: true, ..claim("planned", 2016, Some(ReimbursementStatus::Outstanding)) }).expect("save flow");
+
+    let outstanding: Vec<String> = db.load_outstanding_reimbursements()
+        .expect("load outstanding reimbursements")
+        .into_iter()
+        .map(|f| f.id)
+        .collect();
+    assert_eq!(outstanding, vec!["old", "submitted"]);
+}
+
+#[test]
+fn category_summaries_and_totals_sum_net_amounts_by_month_and_date_range() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    db.save_category(&category_with_fields("cat-2", vec![])).expect("save category");
+
+    let dated = |id: &str, category_id: &str, date: NaiveDate, amount: f64| Flow {
+        date,
+        amount,
+        ..flow_with_custom_fields(id, category_id, HashMap::new())
+    };
+    let jan = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+    let flows = [
+        dated("a", "cat-1", jan, 100.0),
+        Flow { refund_of: Some("a".to_string()), ..dated("refund", "cat-1", jan, 30.0) },
+        Flow { scheduled: true, ..dated("planned", "cat-1", jan, 500.0) },
+        dated("b", "cat-1", NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), 40.0),
+        dated("c", "cat-2", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 5.0),
+    ];
+    for flow in &flows {
+        db.save_flow(flow).expect("save flow");
+    }
+
+    let as_of = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+    let summaries = db.category_summaries(as_of, 3).expect("summaries");
+    let cat_1 = &summaries["cat-1"];
+    assert_eq!(cat_1.this_year_total, 70.0, "refund subtracted, scheduled left out");
+    assert_eq!(cat_1.last_year_total, 40.0);
+    assert_eq!(cat_1.current_month_total, 0.0);
+    assert_eq!(cat_1.monthly_trend, vec![70.0, 0.0, 0.0]);
+    assert_eq!(
+        cat_1.tracking_ratio,
+        utils::calculate_tracking_ratio_as_of(&flows, &category_with_fields("cat-1", vec![]), as_of),
+    );
+    let cat_2 = &summaries["cat-2"];
+    assert_eq!((cat_2.this_year_total, cat_2.current_month_total), (5.0, 5.0));
+    assert_eq!(cat_2.monthly_trend, vec![0.0, 0.0, 5.0]);
+    assert_eq!(cat_2.tracking_ratio, Some(9999.0));
+
+    let january = db.category_totals_between(jan.with_day(1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())
+        .expect("range totals");
+    assert_eq!(january, HashMap::from([("cat-1".to_string(), 70.0)]));
+    let expected: f64 = flows.iter().filter(|f| f.category_id == "cat-1" && f.date == jan).map(|f| f.net_amount()).sum();
+    assert_eq!(january["cat-1"], expected);
+}
+
+#[test]
+fn report_flows_are_the_filtered_ones_and_the_flows_they_refund() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    db.save_category(&category_with_fields("cat-2", vec![])).expect("save category");
+    let dated = |id: &str, category_id: &str, year: i32| Flow {
+        date: NaiveDate::from_ymd_opt(year, 6, 1).unwrap(),
+        ..flow_with_custom_fields(id, category_id, HashMap::new())
+    };
+    for flow in [
+        Flow { tax_deductible: Some(true), ..dated("original", "cat-1", 2023) },
+        Flow { refund_of: Some("original".to_string()), ..dated("refund", "cat-1", 2024) },
+        dated("other-category", "cat-2", 2024),
+        dated("last-year", "cat-1", 2023),
+        Flow { scheduled: true, ..dated("planned", "cat-1", 2024) },
+    ] {
+        db.save_flow(&flow).expect("save flow");
+    }
+
+    let today = NaiveDate::from_ymd_opt(2024, 12, 1).unwrap();
+    let ids = |request: &ReportRequest, pending: &PendingChanges| -> Vec<String> {
+        let mut ids: Vec<String> = db.load_report_flows(request, today, pending)
+            .expect("load report flows")
+            .into_iter()
+            .map(|f| f.id)
+            .collect();
+        ids.sort();
+        ids
+    };
+    let request = ReportRequest {
+        time_period: TimePeriod::ThisYear,
+        selected_categories: Some(vec!["cat-1".to_string()]),
+        ..ReportRequest::default()
+    };
+    assert_eq!(ids(&request, &PendingChanges::default()), vec!["original", "refund"]);
+
+    let mut pending = PendingChanges::default();
+    pending.save_flow(dated("added", "cat-1", 2024));
+    assert_eq!(ids(&request, &pending), vec!["added", "original", "refund"]);
+
+    let picked = ReportRequest { selected_flows: vec!["last-year".to_string()], ..request };
+    assert_eq!(ids(&picked, &PendingChanges::default()), vec!["last-year"]);
+}
+
+#[test]
+fn kpi_and_trip_totals_match_the_flows_with_pending_changes_counted_in() {
+    let mut db = test_db();
+    let categories = vec![
+        category_with_fields("cat-1", vec![]),
+        Category { flow_type: FlowType::Income, ..category_with_fields("pay", vec![]) },
+    ];
+    for category in &categories {
+        db.save_category(category).expect("save category");
+    }
+
+    let dated = |id: &str, category_id: &str, date: (i32, u32, u32), amount: f64| Flow {
+        date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+        amount,
+        ..flow_with_custom_fields(id, category_id, HashMap::new())
+    };
+    let on_trip = |flow: Flow| Flow { trip_id: Some("lisbon".to_string()), ..flow };
+    let stored = vec![
+        Flow { tax_deductible: Some(true), ..dated("old", "cat-1", (2012, 5, 1), 100.0) },
+        // Deductible because what it refunds is
+        Flow { refund_of: Some("old".to_string()), ..dated("refund", "cat-1", (2013, 1, 1), 30.0) },
+        on_trip(dated("hotel", "cat-1", (2024, 2, 1), 200.0)),
+        on_trip(dated("per-diem", "pay", (2024, 2, 2), 50.0)),
+        dated("future", "cat-1", (2024, 12, 1), 70.0),
+        Flow { scheduled: true, ..dated("planned", "cat-1", (2024, 1, 1), 500.0) },
+    ];
+    for flow in &stored {
+        db.save_flow(flow).expect("save flow");
+    }
+
+    let mut pending = PendingChanges::default();
+    // No longer deductible, and neither is its stored refund
+    pending.save_flow(Flow { tax_deductible: Some(false), ..stored[0].clone() });
+    pending.delete_flow(stored[2].clone());
+    pending.save_flow(on_trip(dated("taxi", "cat-1", (2024, 2, 3), 20.0)));
+
+    let as_of = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+    let kpi_totals = db.kpi_totals(as_of, &PendingChanges::default()).expect("kpi totals");
+    assert_eq!(kpi_totals.deductible_by_category["cat-1"].net_total, 70.0);
+    assert_eq!(kpi_totals.by_category["cat-1"].first, NaiveDate::from_ymd_opt(2012, 5, 1));
+    for pending in [PendingChanges::default(), pending] {
+        let mut flows = stored.clone();
+        pending.apply_to(&mut flows);
+        assert_eq!(db.kpi_totals(as_of, &pending).expect("kpi totals"), KpiTotals::from_flows(&flows, |f| f.date <= as_of));
+        assert_eq!(db.trip_totals(&categories, &pending).expect("trip totals"), utils::totals_by_trip(&flows, &categories));
+    }
+}
+
+#[test]
+fn save_flow_round_trips_created_utc_offset() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let entered_abroad = Flow {
+        created_utc_offset: Some(-8 * 3600),
+        ..flow_with_custom_fields("abroad", "cat-1", HashMap::new())
+    };
+    db.save_flow(&entered_abroad).expect("save flow");
+    db.save_flow(&flow_with_custom_fields("legacy", "cat-1", HashMap::new())).expect("save flow");
+
+    let loaded = db.load_flows().expect("load flows");
+    let offset_of = |id: &str| loaded.iter().find(|f| f.id == id).expect("flow").created_utc_offset;
+    assert_eq!(offset_of("abroad"), Some(-8 * 3600));
+    assert_eq!(offset_of("legacy"), None);
+}
+
+#[test]
+fn save_flow_round_trips_location() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let in_lisbon = Flow {
+        location: Some("Lisbon".to_string()),
+        ..flow_with_custom_fields("lisbon", "cat-1", HashMap::new())
+    };
+    db.save_flow(&in_lisbon).expect("save flow");
+    db.save_flow(&flow_with_custom_fields("nowhere", "cat-1", HashMap::new())).expect("save flow");
+
+    let loaded = db.load_flows().expect("load flows");
+    let location_of = |id: &str| loaded.iter().find(|f| f.id == id).expect("flow").location.clone();
+    assert_eq!(location_of("lisbon"), Some("Lisbon".to_string()));
+    assert_eq!(location_of("nowhere"), None);
+}
+
+#[test]
+fn save_flow_round_trips_reimbursement_status_and_link() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+
+    let expense = Flow {
+        reimbursement: Some(ReimbursementStatus::Reimbursed),
+        reimbursed_by: Some("payback".to_string()),
+        ..flow_with_custom_fields("expense", "cat-1", HashMap::new())
+    };
+    db.save_flow(&expense).expect("save flow");
+    db.save_flow(&flow_with_custom_fields("ordinary", "cat-1", HashMap::new())).expect("save flow");
+
+    let loaded = db.load_flows().expect("load flows");
+    let reimbursement_of = |id: &str| {
+        let flow = loaded.iter().find(|f| f.id == id).expect("flow");
+        (flow.reimbursement, flow.reimbursed_by.clone())
+    };
+    assert_eq!(reimbursement_of("expense"), (Some(ReimbursementStatus::Reimbursed), Some("payback".to_string())));
+    assert_eq!(reimbursement_of("ordinary"), (None, None));
+}
+
+#[test]
+fn trips_round_trip_and_deleting_one_unassigns_its_flows() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    let trip = |id: &str, name: &str, month: u32| Trip {
+        id: id.to_string(),
+        name: name.to_string(),
+        start_date: NaiveDate::from_ymd_opt(2024, month, 1).unwrap(),
+        end_date: NaiveDate::from_ymd_opt(2024, month, 10).unwrap(),
+    };
+    db.save_trip(&trip("porto", "Porto", 6)).expect("save trip");
+    db.save_trip(&trip("lisbon", "Lisbon", 5)).expect("save trip");
+    db.save_trip(&trip("lisbon", "Lisbon and Sintra", 5)).expect("re-save trip");
+    assert_eq!(db.load_trips().expect("load trips"), vec![trip("lisbon", "Lisbon and Sintra", 5), trip("porto", "Porto", 6)]);
+
+    let on_trip = Flow { trip_id: Some("lisbon".to_string()), ..flow_with_custom_fields("f1", "cat-1", HashMap::new()) };
+    db.save_flow(&on_trip).expect("save flow");
+    assert_eq!(db.load_flows().expect("load flows")[0].trip_id, Some("lisbon".to_string()));
+
+    db.delete_trip("lisbon").expect("delete trip");
+    assert_eq!(db.load_trips().expect("load trips"), vec![trip("porto", "Porto", 6)]);
+    assert_eq!(db.load_flows().expect("load flows")[0].trip_id, None);
+}
+
+#[test]
+fn delete_flows_removes_them_together_and_unlinks_refunds() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    let flow = |id: &str| flow_with_custom_fields(id, "cat-1", HashMap::new());
+    db.save_flow(&flow("a")).expect("save a");
+    db.save_flow(&flow("b")).expect("save b");
+    db.save_flow(&Flow { refund_of: Some("a".to_string()), ..flow("refund") }).expect("save refund");
+    db.save_flow(&Flow { date: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(), ..flow("old") }).expect("save old");
+
+    db.lock_year(2023).expect("lock year");
+    let err = db.delete_flows(&["a".to_string(), "old".to_string()]).expect_err("old is locked");
+    assert!(err.to_string().contains("2023"), "{}", err);
+    assert_eq!(db.load_flows().expect("load flows").len(), 4, "nothing deleted");
+
+    db.delete_flows(&["a".to_string(), "b".to_string()]).expect("delete flows");
+    let mut remaining = db.load_flows().expect("load flows");
+    remaining.sort_by(|x, y| x.id.cmp(&y.id));
+    let ids: Vec<&str> = remaining.iter().map(|f| f.id.as_str()).collect();
+    assert_eq!(ids, vec!["old", "refund"]);
+    assert_eq!(remaining[1].refund_of, None);
+}
+
+#[test]
+fn deleting_a_reimbursement_leaves_a_locked_years_expense_linked() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    let flow = |id: &str, year: i32| Flow {
+        date: NaiveDate::from_ymd_opt(year, 3, 1).unwrap(),
+        ..flow_with_custom_fields(id, "cat-1", HashMap::new())
+    };
+    db.save_flow(&Flow { reimbursed_by: Some("payback".to_string()), ..flow("expense", 2024) }).expect("save expense");
+    db.save_flow(&flow("payback", 2025)).expect("save reimbursement");
+
+    db.lock_year(2024).expect("lock year");
+    db.delete_flows(&["payback".to_string()]).expect("the reimbursement isn't locked");
+    let remaining = db.load_flows().expect("load flows");
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].reimbursed_by, Some("payback".to_string()));
+}
+
+#[test]
+fn compact_gives_back_the_space_deleted_flows_used() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("preft.db");
+    let mut db = Database::new_for_test(Connection::open(&path).expect("open db")).expect("initialize db");
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    let ids: Vec<String> = (0..200).map(|i| format!("flow-{}", i)).collect();
+    for id in &ids {
+        let flow = Flow { description: "x".repeat(2000), ..flow_with_custom_fields(id, "cat-1", HashMap::new()) };
+        db.save_flow(&flow).expect("save flow");
+    }
+    db.delete_flows(&ids[1..]).expect("delete flows");
+    let size_on_disk = || std::fs::metadata(&path).expect("stat db").len();
+    let before = size_on_disk();
+
+    let progress = BackupProgress::default();
+    let report = db.compact(&progress).expect("compact");
+    assert_eq!(report.size_before, before);
+    assert!(report.reclaimed() > 100_000, "{:?}", report);
+    assert_eq!(size_on_disk(), report.size_after);
+    assert_eq!(progress.fraction(), 1.0);
+    assert_eq!(db.load_flows().expect("load flows").len(), 1);
+
+    let cancelled = BackupProgress::default();
+    cancelled.cancel();
+    assert!(db.compact(&cancelled).is_err());
+}
+
+#[test]
+fn encrypted_columns_are_sealed_on_disk_but_totals_still_add_up() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("preft.db");
+    let mut db = Database::new_for_test(Connection::open(&path).expect("open db")).expect("initialize db");
+    let visits = CategoryField { name: "Visits".to_string(), field_type: FieldType::Integer, required: false, default_value: None };
+    let category = category_with_fields("cat-1", vec![visits.clone()]);
+    db.save_category(&category).expect("save category");
+    let flow = Flow {
+        description: "Dentist".to_string(),
+        location: Some("Main St".to_string()),
+        ..flow_with_custom_fields("f1", "cat-1", HashMap::from([("Visits".to_string(), "2".to_string())]))
+    };
+    db.save_flow(&flow).expect("save flow");
+
+    db.enable_encryption_for_test("password", &DatabaseEncryption::generate_salt()).expect("enable encryption");
+    assert_eq!(db.set_encrypted_columns(vec![SensitiveColumn::Description, SensitiveColumn::CustomFields]).expect("encrypt columns"), 1);
+
+    let raw = || -> (String, String, Option<String>) {
+        Connection::open(&path).expect("open second connection")
+            .query_row("SELECT description, custom_fields, location FROM flows WHERE id = 'f1'", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .expect("read raw flow")
+    };
+    let (description, custom_fields, location) = raw();
+    assert!(description.starts_with("enc1:"), "{}", description);
+    assert!(custom_fields.contains("\"Visits\":\"enc1:"), "{}", custom_fields);
+    assert_eq!(location.as_deref(), Some("Main St"), "locations weren't chosen");
+    let totals = db.category_totals_between(flow.date, flow.date).expect("total flows");
+    assert_eq!(totals.get("cat-1"), Some(&10.0));
+    assert_eq!(db.load_flows().expect("load flows")[0].description, "Dentist");
+
+    // Changing the field's type migrates the value through the seal
+    let float_visits = CategoryField { field_type: FieldType::Float, ..visits };
+    db.save_category(&category_with_fields("cat-1", vec![float_visits])).expect("change field type");
+    assert_eq!(db.load_flows().expect("load flows")[0].custom_fields["Visits"], "2");
+    assert!(raw().1.contains("enc1:"));
+
+    // Without the password, values stay sealed and save back unchanged
+    let locked = Database::new_for_test(Connection::open(&path).expect("open db")).expect("open without password");
+    let sealed = locked.load_flows().expect("load flows").remove(0);
+    assert_eq!(sealed.description, description);
+    locked.save_flow(&sealed).expect("save sealed flow");
+    assert_eq!(raw().0, description);
+
+    db.set_encrypted_columns(Vec::new()).expect("decrypt columns");
+    assert_eq!(raw().0, "Dentist");
+    assert!(!raw().1.contains("enc1:"));
+}
+
+#[test]
+fn unencrypted_backups_hold_sealed_columns_opened() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    db.enable_encryption_for_test("password", &DatabaseEncryption::generate_salt()).expect("enable encryption");
+    db.set_encrypted_columns(SensitiveColumn::ALL.to_vec()).expect("encrypt columns");
+    let flow = Flow {
+        description: "Dentist".to_string(),
+        location: Some("Main St".to_string()),
+        ..flow_with_custom_fields("f1", "cat-1", HashMap::from([("Visits".to_string(), "2".to_string())]))
+    };
+    db.save_flow(&flow).expect("save flow");
+
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let backup_path = dir.path().join("backup.db");
+    db.backup_to_file(&backup_path, false).expect("unencrypted backup");
+
+    let (description, custom_fields, location): (String, String, Option<String>) = Connection::open(&backup_path).expect("open backup")
+        .query_row("SELECT description, custom_fields, location FROM flows WHERE id = 'f1'", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .expect("read backed up flow");
+    for value in [&description, &custom_fields, location.as_ref().expect("location")] {
+        assert!(!value.contains("enc1:"), "{}", value);
+    }
+    assert_eq!(description, "Dentist");
+    assert_eq!(location.as_deref(), Some("Main St"));
+}
+
+#[test]
+fn clearing_the_encryption_forgets_the_key_and_leaves_values_sealed() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    db.enable_encryption_for_test("password", &DatabaseEncryption::generate_salt()).expect("enable encryption");
+    db.set_encrypted_columns(vec![SensitiveColumn::Description]).expect("encrypt columns");
+    let flow = Flow { description: "Dentist".to_string(), ..flow_with_custom_fields("f1", "cat-1", HashMap::new()) };
+    db.save_flow(&flow).expect("save flow");
+
+    db.clear_encryption();
+    let description = db.load_flows().expect("load flows").remove(0).description;
+    assert!(description.starts_with("enc1:"), "{}", description);
+}
+
+#[test]
+fn changing_the_key_re_encrypts_settings_and_sealed_values_or_nothing() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("preft.db");
+    let mut db = Database::new_for_test(Connection::open(&path).expect("open db")).expect("initialize db");
+    db.save_category(&category_with_fields("cat-1", Vec::new())).expect("save category");
+    db.save_flow(&Flow { description: "Dentist".to_string(), ..flow_with_custom_fields("f1", "cat-1", HashMap::new()) }).expect("save flow");
+    db.save_flow(&flow_with_custom_fields("f2", "cat-1", HashMap::new())).expect("save flow");
+    let old_salt = DatabaseEncryption::generate_salt();
+    db.enable_encryption_for_test("old password", &old_salt).expect("enable encryption");
+    db.set_encrypted_columns(vec![SensitiveColumn::Description]).expect("encrypt columns");
+    db.save_user_settings(&UserSettings::default()).expect("save settings");
+
+    let raw_description = || -> String {
+        Connection::open(&path).expect("open second connection")
+            .query_row("SELECT description FROM flows WHERE id = 'f1'", [], |row| row.get(0))
+            .expect("read raw flow")
+    };
+    let before = raw_description();
+    let new_salt = DatabaseEncryption::generate_salt();
+    assert_eq!(db.change_key("new password", &new_salt, KeyDerivation::RECOMMENDED).expect("change key"), 1, "only f1 had a sealed value");
+    assert!(raw_description().starts_with("enc1:"));
+    assert_ne!(raw_description(), before);
+    let description = |db: &Database| db.load_flows().expect("load flows").into_iter().find(|f| f.id == "f1").unwrap().description;
+    assert_eq!(description(&db), "Dentist");
+    db.load_user_settings().expect("settings open with the new key");
+
+    let mut reopened = Database::new_for_test(Connection::open(&path).expect("open db")).expect("open db");
+    reopened.set_encryption_state(true, Some("old password"), Some(&old_salt)).expect("old key");
+    assert!(reopened.load_user_settings().is_err(), "the old key no longer opens anything");
+    reopened.set_encryption_state(true, Some("new password"), Some(&new_salt)).expect("new key");
+    reopened.load_user_settings().expect("settings open with the new key");
+    assert_eq!(description(&reopened), "Dentist");
+
+    // A value under some other key stops the whole change
+    let foreign = DatabaseEncryption::new("someone else", &old_salt, KeyDerivation::RECOMMENDED).expect("key").seal("Pharmacy").expect("seal");
+    Connection::open(&path).expect("open second connection")
+        .execute("UPDATE flows SET description = ?1 WHERE id = 'f2'", [foreign])
+        .expect("write foreign value");
+    let before = raw_description();
+    let error = db.change_key("third password", &DatabaseEncryption::generate_salt(), KeyDerivation::RECOMMENDED).unwrap_err();
+    assert!(error.to_string().contains("f2"), "{}", error);
+    assert_eq!(raw_description(), before);
+    db.load_user_settings().expect("settings still open with the new key");
+}
+
+#[test]
+fn metric_snapshots_round_trip_and_replace_by_period() {
+    let db = test_db();
+    let date = NaiveDate::from_ymd_opt(2024, 2, 14).unwrap();
+    let snapshot = |period: &str, value: f64| MetricSnapshot {
+        period: period.to_string(),
+        taken_on: date,
+        metrics: vec![("net_worth".to_string(), value), ("ytd_income".to_string(), value * 2.0)],
+    };
+
+    db.save_metric_snapshot(&snapshot("2024-02", 100.0)).expect("save snapshot");
+    db.save_metric_snapshot(&snapshot("2024-W07", 100.0)).expect("save snapshot");
+    db.save_metric_snapshot(&snapshot("2024-02", 150.0)).expect("re-save snapshot");
+
+    let loaded = db.load_metric_snapshots().expect("load snapshots");
+    assert_eq!(loaded, vec![snapshot("2024-02", 150.0), snapshot("2024-W07", 100.0)]);
+}
+
+#[test]
+fn locked_year_blocks_saving_moving_and_deleting_its_flows() {
+    let mut db = test_db();
+    db.save_category(&category_with_fields("cat-1", vec![])).expect("save category");
+    // flow_with_custom_fields dates flows 2024-01-01.
+    let flow = flow_with_custom_fields("f1", "cat-1", HashMap::new());
+    db.save_flow(&flow).expect("save before locking");
+
+    db.lock_year(2024).expect("lock year");
+    assert_eq!(db.load_locked_years().expect("load locked years"), vec![2024]);
+
+    let edited = Flow { amount: 99.0, ..flow.clone() };
+    assert!(db.save_flow(&edited).is_err(), "editing a flow in a locked year should fail");
+    let moved = Flow { date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), ..flow.clone() };
+    assert!(db.save_flow(&moved).is_err(), "moving a flow out of a locked year should fail");
+    let new_flow = flow_with_custom_fields("f2", "cat-1", HashMap::new());
+    assert!(db.save_flow(&new_flow).is_err(), "adding a flow to a locked year should fail");
+    assert!(db.delete_flow("f1").is_err());
+    assert!(db.delete_flows_by_category("cat-1").is_err());
+    let stored = db.load_flows().expect("load flows");
+    assert_eq!(stored.len(), 1, "nothing added or deleted");
+    assert_eq!((stored[0].amount, stored[0].date), (flow.amount, flow.date), "nothing changed");
+
+    db.unlock_year(2024).expect("unlock year");
+    db.save_flow(&edited).expect("save after unlocking");
+    db.delete_flow("f1").expect("delete after unlocking");
+    assert!(db.load_flows().expect("load flows").is_empty());
+}
+
+#[test]
+fn report_templates_round_trip_and_replace_by_name() {
+    let db = test_db();
+    let mut request = ReportRequest {
+        kind: ReportKind::TaxDeductions,
+        time_period: TimePeriod::LastYear,
+        selected_categories: Some(vec!["cat-1".to_string()]),
+        group_by: Some("Vendor".to_string()),
+        ..ReportRequest::default()
+    };
+
+    db.save_report_template("Yearly Taxes", &request).expect("save template");
+    db.save_report_template("All Flows", &ReportRequest::default()).expect("save template");
+    request.subtitle = "Updated".to_string();
+    db.save_report_template("Yearly Taxes", &request).expect("re-save template");
+
+    let loaded = db.load_report_templates().expect("load templates");
+    let names: Vec<&str> = loaded.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["All Flows", "Yearly Taxes"]);
+    let taxes = &loaded[1].1;
+    assert_eq!(taxes.kind, ReportKind::TaxDeductions);
+    assert_eq!(taxes.time_period, TimePeriod::LastYear);
+    assert_eq!(taxes.selected_categories, Some(vec!["cat-1".to_string()]));
+    assert_eq!(taxes.group_by.as_deref(), Some("Vendor"));
+    assert_eq!(taxes.subtitle, "Updated");
+
+    db.delete_report_template("All Flows").expect("delete template");
+    assert_eq!(db.load_report_templates().expect("load templates").len(), 1);
+}
+
+#[test]
+fn sql_views_round_trip_and_only_read() {
+    let db = test_db();
+    db.save_trip(&Trip {
+        id: "t1".to_string(),
+        name: "Lisbon, spring".to_string(),
+        start_date: NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+        end_date: NaiveDate::from_ymd_opt(2024, 4, 8).unwrap(),
+    }).expect("save trip");
+
+    let view = SqlView {
+        name: "Trips".to_string(),
+        sql: "-- every trip\nSELECT name, start_date, NULL AS note FROM trips;".to_string(),
+    };
+    db.save_sql_view(&view).expect("save view");
+    assert_eq!(db.load_sql_views().expect("load views"), vec![view.clone()]);
+
+    let result = db.run_sql_view(&view.sql).expect("run view");
+    assert_eq!(result.columns, vec!["name", "start_date", "note"]);
+    assert_eq!(result.rows, vec![vec!["Lisbon, spring".to_string(), "2024-04-01".to_string(), String::new()]]);
+    assert_eq!(result.to_csv(), "name,start_date,note\r\n\"Lisbon, spring\",2024-04-01,\r\n");
+
+    for sql in [
+        "DELETE FROM trips",
+        "WITH t AS (SELECT id FROM trips) DELETE FROM trips WHERE id IN t",
+        "SELECT 1; DELETE FROM trips",
+        "BEGIN",
+        "",
+    ] {
+        let bad = SqlView { name: "Bad".to_string(), sql: sql.to_string() };
+        assert!(db.save_sql_view(&bad).is_err(), "{:?} should be refused", sql);
+        assert!(db.run_sql_view(sql).is_err(), "{:?} should not run", sql);
+    }
+    assert_eq!(db.load_trips().expect("load trips").len(), 1);
+    assert_eq!(db.load_sql_views().expect("load views").len(), 1);
+
+    db.delete_sql_view("Trips").expect("delete view");
+    assert!(db.load_sql_views().expect("load views").is_empty());
+}
+
+#[test]
+fn integrity_scan_reports_json_that_no_longer_parses() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("preft.db");
+    let mut db = Database::new_for_test(Connection::open(&path).expect("open db")).expect("initialize db");
+    db.save_category(&category_with_fields("c1", Vec::new())).expect("save category");
+    for id in ["f1", "f2", "f3"] {
+        db.save_flow(&flow_with_custom_fields(id, "c1", HashMap::new())).expect("save flow");
+    }
+
+    // Damage one flow the way a hand edit might.
+    Connection::open(&path).expect("open second connection")
+        .execute("UPDATE flows SET custom_fields = '{\"Vendor\":' WHERE id = 'f2'", [])
+        .expect("corrupt flow");
+
+    let mut scan = IntegrityScan::default();
+    let mut steps = 0;
+    while !scan.step(&db).expect("scan step") {
+        steps += 1;
+        assert!(steps < 10, "the scan should finish");
+    }
+    assert!(scan.is_finished());
+    assert_eq!(scan.problems.len(), 1, "{:?}", scan.problems);
+    assert!(scan.problems[0].starts_with("Flow f2: custom_fields doesn't parse"), "{}", scan.problems[0]);
+}
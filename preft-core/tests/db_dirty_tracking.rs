@@ -5,9 +5,9 @@
 //! backup bookkeeping, ...) -- see `save_user_settings_does_not_mark_dirty`.
 
 use chrono::NaiveDate;
-use preft::db::Database;
-use preft::models::{Category, FlowType, Flow, TaxDeductionInfo};
-use preft::settings::UserSettings;
+use preft_core::db::Database;
+use preft_core::models::{Category, FlowType, Flow, TaxDeductionInfo};
+use preft_core::settings::UserSettings;
 use rusqlite::Connection;
 use std::collections::HashMap;
 
@@ -22,8 +22,11 @@ fn category(id: &str) -> Category {
         name: format!("Category {}", id),
         flow_type: FlowType::Expense,
         parent_id: None,
+        sort_order: 0,
+        color: None,
+        icon: None,
         fields: vec![],
-        tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false },
+        tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
     }
 }
 
@@ -37,6 +40,13 @@ fn flow(id: &str, category_id: &str) -> Flow {
         linked_flows: Vec::new(),
         custom_fields: HashMap::new(),
         tax_deductible: None,
+        refund_of: None,
+        scheduled: false,
+        created_utc_offset: None,
+        location: None,
+        trip_id: None,
+        reimbursement: None,
+        reimbursed_by: None,
     }
 }
 
@@ -1,12 +1,12 @@
 //! Smoke test for the `Database::new_for_test` seam: proves an isolated,
 //! in-memory database can be built and used from an integration test
-//! (a `tests/` binary that only sees `preft`'s public API) without touching
+//! (a `tests/` binary that only sees `preft_core`'s public API) without touching
 //! the user's real `~/.preft/preft.db` file or the OS keyring.
 
 use chrono::NaiveDate;
-use preft::db::Database;
-use preft::models::Flow;
-use preft::settings::UserSettings;
+use preft_core::db::Database;
+use preft_core::models::Flow;
+use preft_core::settings::UserSettings;
 use rusqlite::Connection;
 use std::collections::HashMap;
 
@@ -44,6 +44,13 @@ fn flow_round_trip_on_isolated_db() {
         linked_flows: Vec::new(),
         custom_fields: HashMap::new(),
         tax_deductible: Some(true),
+        refund_of: None,
+        scheduled: false,
+        created_utc_offset: None,
+        location: None,
+        trip_id: None,
+        reimbursement: None,
+        reimbursed_by: None,
     };
     db.save_flow(&flow).expect("save flow");
 
@@ -70,3 +77,21 @@ fn two_test_databases_are_independent() {
     let loaded_b = db_b.load_user_settings().expect("load settings from db b");
     assert_eq!(loaded_b.get_year_filter(), UserSettings::new().get_year_filter());
 }
+
+#[test]
+fn read_only_database_loads_but_refuses_writes() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("preft.db");
+    {
+        let conn = Connection::open(&path).expect("open db file");
+        let db = Database::new_for_test(conn).expect("initialize test db");
+        let mut settings = UserSettings::new();
+        settings.set_year_filter(Some(2023));
+        db.save_user_settings(&settings).expect("save settings");
+    }
+
+    let db = Database::open_read_only_at(&path).expect("open read-only");
+    let loaded = db.load_user_settings().expect("load settings");
+    assert_eq!(loaded.get_year_filter(), Some(2023));
+    assert!(db.save_user_settings(&UserSettings::new()).is_err());
+}
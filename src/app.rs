@@ -1,57 +1,248 @@
 use anyhow::Result;
 use eframe::egui;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
-use log::{info, warn, error};
+use chrono::Datelike;
+use log::{info, warn};
 
-use crate::models::{Flow, Category, CategoryField, get_default_categories};
+use crate::models::{Flow, Category, CategoryField, OptionRenames, SqlView, Trip, UniquenessRule, get_default_categories};
 use crate::ui::{show_main_panel, FlowEditorState};
-use crate::db::Database;
-use crate::settings::UserSettings;
+use crate::db::{BackupProgress, CategorySummary, CompactReport, Database, FlowScope, MigrationSummary};
+use crate::db_worker::{DbEvent, DbWorker, Pending};
+use crate::flow_cache::FlowCache;
+use crate::pending_changes::PendingChanges;
+use crate::settings::{StartupView, UserSettings, WindowGeometry};
+use crate::shortcuts::{self, ShortcutAction};
+use crate::theme;
+use crate::utils::TripTotals;
+use crate::undo::{Edit, UndoStack};
+use crate::integrity::IntegrityScan;
+use crate::kpi::KpiTotals;
+use crate::locale::NumberFormat;
 use crate::reporting::ReportRequest;
+use crate::category_schema::{CategorySchemaFile, SchemaImport, SCHEMA_EXTENSION};
+use crate::repro::{ReproBundle, ReproLog};
 use crate::ui::dashboard::Dashboard;
+use crate::ui::category_editor::{CategoryEditorTab, SelectOptionDraft};
 use crate::ui::category_flows::CategoryFlowsState;
+use crate::ui::import_dialog::ImportDialogState;
+use crate::ui::backup_compare_dialog::BackupCompareState;
+use crate::ui::bulk_edit_dialog::BulkEditState;
+use crate::ui::highlight_rules_dialog::HighlightRulesState;
+use crate::ui::kpi_cards_dialog::KpiCardsState;
+use crate::ui::trips_dialog::TripsState;
+use crate::ui::report_dialog::ReportDialogState;
+use crate::ui::sql_views_dialog::SqlViewsState;
+use crate::ui::uniqueness_conflict_dialog::UniquenessConflict;
+use crate::ui::encryption_repair_dialog::EncryptionRepairState;
+use crate::ui::export_bundle_dialog::ExportBundleState;
 use rusqlite::Connection;
-use crate::encryption_config::EncryptionConfig;
+use crate::encryption::{secret_buffer, DatabaseEncryption, KeyDerivation};
+use zeroize::{Zeroize, Zeroizing};
+use crate::encryption_config::{EncryptionConfig, SensitiveColumn};
+
+/// Extension for repro bundles (see `save_repro_bundle`).
+const REPRO_EXTENSION: &str = "preftrepro";
 
 pub struct PreftApp {
     pub categories: Vec<Category>,
-    pub flows: Vec<Flow>,
+    /// The flows the views have asked for (see `flows_in`), with pending
+    /// changes replayed over them, rather than every flow there is.
+    pub flow_cache: FlowCache,
+    /// Scopes being loaded for `flow_cache`.
+    loading_scopes: HashSet<FlowScope>,
+    /// Scopes that failed to load, with the database's change count at the
+    /// time, so they're retried once it changes rather than every frame.
+    failed_scopes: HashMap<FlowScope, u64>,
+    /// Every trip, earliest first.
+    pub trips: Vec<Trip>,
+    /// Saved SQL views, by name.
+    pub sql_views: Vec<SqlView>,
     pub selected_category: Option<String>,
+    /// The category whose saved view was last applied (see
+    /// `open_category_view`), to tell when another one is opened.
+    opened_category: Option<String>,
     pub show_category_editor: bool,
     pub show_hidden_categories: bool,
     pub new_flow: Option<Flow>,
     pub editing_flow: Option<Flow>,
     pub custom_field_values: HashMap<String, String>,
     pub user_settings: UserSettings,
+    /// Years whose flows are read-only until unlocked (mirrors the
+    /// database's `locked_years`, which is what actually enforces it).
+    pub locked_years: BTreeSet<i32>,
     flow_editor_state: FlowEditorState,
-    pub db: Database,
+    /// The database, on its own thread (see `db_worker`).
+    pub db: DbWorker,
+    /// Database work whose result is still to come back, applied in the
+    /// order it was queued (see `when_done`).
+    awaiting: Vec<ResultHandler>,
     pub hide_category_confirmation: Option<String>,  // Track which category is being confirmed for hiding
     pub delete_category_confirmation: Option<String>,
+    /// What's been typed into the delete confirmation, when
+    /// `ConfirmationSettings::type_category_name_to_delete` asks for it.
+    pub delete_category_typed_name: String,
     pub new_category: Option<Category>,  // This will now track all fields being added
     pub show_field_editor: bool,  // Track if field editor is open
     pub editing_field: Option<CategoryField>,  // Track the field being edited
+    /// The options of `editing_field`, while it's a Select field.
+    pub select_option_drafts: Vec<SelectOptionDraft>,
     pub report_request: ReportRequest,
+    /// Saved (name, request) pairs offered by the report dialog, by name.
+    pub report_templates: Vec<(String, ReportRequest)>,
+    /// Name typed into the report dialog's "Save as Template" field.
+    pub report_template_name: String,
     pub show_report_dialog: bool,
+    /// Whether the report dialog's live preview window is open.
+    pub show_report_preview: bool,
+    /// Text typed into the report dialog's flow picker to narrow its list.
+    pub report_flow_search: String,
+    /// Whether the flow picker lists only flows the report's filters
+    /// already cover.
+    pub report_flow_search_filtered: bool,
+    pub report_state: ReportDialogState,
+    pub show_import_dialog: bool,
+    pub import_state: ImportDialogState,
+    pub show_verify_dialog: bool,
+    /// Discrepancies found by the last `verify_cached_state` run, if any run.
+    pub verification_results: Option<Vec<String>>,
+    /// Set while a `verify_cached_state` run waits on the database.
+    pub verifying: bool,
     pub dashboard: Dashboard,
+    pub stored_totals: StoredTotals,
+    pub all_time_totals: AllTimeTotals,
     pub category_flows_state: HashMap<String, CategoryFlowsState>,
     pub editing_category: Option<String>,  // Track which category is being edited
+    pub category_editor_tab: CategoryEditorTab,
+    /// The uniqueness rules being edited alongside `new_category`.
+    pub category_uniqueness_draft: Vec<UniquenessRule>,
+    /// The number format being edited alongside `new_category`; `None`
+    /// uses the one in Settings.
+    pub category_number_format_draft: Option<NumberFormat>,
+    /// Select options renamed in `new_category`'s fields, applied to the
+    /// flows when it's saved.
+    pub category_option_renames: OptionRenames,
+    /// The day scheduled flows were last confirmed (see
+    /// `confirm_due_scheduled_flows`), to catch the date changing while
+    /// the app is open.
+    scheduled_flows_confirmed_on: Option<chrono::NaiveDate>,
     // Backup-related fields
     pub show_backup_dialog: bool,
+    pub show_backup_compare_dialog: bool,
+    pub backup_compare_state: BackupCompareState,
+    pub show_bulk_edit_dialog: bool,
+    pub bulk_edit_state: BulkEditState,
+    pub show_highlight_rules_dialog: bool,
+    pub show_settings_dialog: bool,
+    pub show_export_bundle_dialog: bool,
+    pub export_bundle_state: ExportBundleState,
+    /// What this run's migrations changed in the user's data; the "What
+    /// Changed in Your Data" dialog is shown while this is `Some`.
+    pub migration_summary: Option<MigrationSummary>,
+    /// Flow edits waiting for Save All while `UserSettings::deferred_writes`
+    /// is on; always empty otherwise.
+    pub pending_changes: PendingChanges,
+    /// Set while Save All is writing a copy of `pending_changes`.
+    pub saving_pending_changes: bool,
+    pub show_pending_changes_panel: bool,
+    /// Set when a close was held back for pending changes; the review
+    /// panel closes the app once they are saved or discarded.
+    pub quit_requested: bool,
+    pub highlight_rules_state: HighlightRulesState,
+    pub show_kpi_cards_dialog: bool,
+    pub kpi_cards_state: KpiCardsState,
+    pub show_trips_dialog: bool,
+    pub trips_state: TripsState,
+    pub show_sql_views_dialog: bool,
+    pub sql_views_state: SqlViewsState,
+    /// Set when a save was refused by a uniqueness rule; the conflict
+    /// dialog is shown while this is `Some`.
+    pub uniqueness_conflict: Option<UniquenessConflict>,
+    /// Set by the Save shortcut; the open flow editor saves on its next
+    /// frame.
+    pub save_requested: bool,
+    /// Set while the flow editor's Save waits on the category's flows for
+    /// the uniqueness check (see `check_uniqueness`).
+    pub saving_flow: bool,
+    /// Flow and category edits that Undo/Redo can reverse (see `undo`).
+    pub undo_stack: UndoStack,
+    /// Set while an undo or redo waits on the flows of a category it would
+    /// remove (see `step_undo_stack`).
+    pub undo_in_progress: bool,
+    /// Set while a delete waits on the flows it takes along (see
+    /// `delete_category` and `delete_flows`).
+    pub deleting: bool,
+    /// Set while a repro bundle is put together on the database thread.
+    pub saving_repro_bundle: bool,
+    /// Messages shown at the top of the main panel until dismissed (e.g.
+    /// the outcome of each watch-folder import).
+    pub notifications: Vec<String>,
+    /// When the watch folder was last scanned; `None` forces a scan on the
+    /// next frame.
+    last_watch_folder_scan: Option<std::time::Instant>,
+    /// The idle-time check of stored JSON in progress (see `integrity`).
+    integrity_scan: Option<IntegrityScan>,
+    /// The batch of `integrity_scan` the database thread is checking, which
+    /// has the scan until it comes back.
+    integrity_step: Option<Pending<(IntegrityScan, Result<bool>)>>,
+    /// When the last idle-time check finished.
+    last_integrity_scan: Option<std::time::Instant>,
+    /// When input was last seen, so checks only run while the user is away
+    /// and the app can lock itself (see `auto_lock_if_idle`).
+    last_input: std::time::Instant,
+    /// Set while the app is locked (see `lock`), including at startup
+    /// until the password of encrypted data is entered.
+    pub locked: bool,
+    /// Whether `finish_startup` has run.
+    started: bool,
+    /// What the last finished idle-time check found, listed in Verify Data.
+    pub integrity_problems: Option<Vec<String>>,
     pub backup_status: Option<String>,
     pub backup_in_progress: bool,
     /// Set while a manual backup's final move-into-place is running on a
     /// background thread (see `create_backup`); polled once per frame by
     /// `poll_pending_backup`.
     pending_backup: Option<std::sync::mpsc::Receiver<BackupMoveOutcome>>,
+    /// The manual backup or restore before that stage (see `BackupTask`).
+    backup_task: Option<BackupTask>,
     // Encryption-related fields
     pub show_password_dialog: bool,
     pub password_dialog_mode: PasswordDialogMode,
-    pub password_input: String,
-    pub password_confirm: String,
+    /// What's typed into the password dialog, wiped when it's closed or
+    /// reopened (see `secret_buffer`). egui's copies are dropped, not wiped,
+    /// once it's closed (see `ui::password_field`).
+    pub password_input: Zeroizing<String>,
+    pub password_confirm: Zeroizing<String>,
+    /// The current password, when the dialog asks for a new one too.
+    pub current_password_input: Zeroizing<String>,
     pub encryption_status: Option<String>,
+    /// Set while an encryption change runs on the database thread (see
+    /// `encryption_job`); the password dialogs wait for it.
+    pub encryption_busy: bool,
+    /// Held while the password dialog asks for the password in strict
+    /// mode (see `request_guarded_action`).
+    pub pending_guarded_action: Option<GuardedAction>,
+    /// The columns ticked in the password dialog's encrypted fields mode.
+    pub encrypted_columns_draft: Vec<SensitiveColumn>,
+    /// The zoom factor last seen in effect (see `remember_ui_scale`).
+    ui_scale_seen: Option<f32>,
+    /// Where the window is, saved on exit (see `track_window_geometry`).
+    window_geometry: Option<WindowGeometry>,
     // Encryption configuration (loaded from OS keystore)
     pub encryption_config: EncryptionConfig,
+    /// Set at startup when the keystore and the database disagree about
+    /// whether the data is encrypted; the repair wizard is shown while
+    /// this is `Some`.
+    pub encryption_repair: Option<EncryptionRepairState>,
+    /// Viewer mode (launched with `VIEWER_FLAG`): the database is opened
+    /// read-only and nothing that edits, deletes, backs up or changes
+    /// settings is offered.
+    pub read_only: bool,
+    /// Demo mode (launched with `LOAD_REPRO_FLAG`): a repro bundle's data in
+    /// an in-memory database. Everything can be tried out, but nothing is
+    /// written to disk, and backups and encryption aren't offered.
+    pub demo: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -60,6 +251,346 @@ pub enum PasswordDialogMode {
     EnterPassword,    // Entering password to unlock encrypted database
     ChangePassword,   // Changing existing password
     DisableEncryption, // Disabling encryption entirely
+    ConfirmAction,    // Re-entering password before a guarded action in strict mode
+    EncryptFile,      // Encrypting the whole database file, not just the settings
+    EncryptedColumns, // Choosing which flow columns are stored encrypted
+}
+
+/// Actions that, in strict mode, only run once the database password has
+/// been entered again, so one person at the keyboard can't do them alone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardedAction {
+    DeleteCategory(String),
+    /// Deleting several flows at once, from the flows table's selection.
+    DeleteFlows(BTreeSet<String>),
+    RestoreBackup,
+    /// Otherwise unticking the setting would get around it.
+    TurnOffStrictMode,
+}
+
+impl GuardedAction {
+    /// Completes "Enter the database password to ...".
+    pub fn describe(&self, categories: &[Category]) -> String {
+        match self {
+            GuardedAction::DeleteCategory(id) => {
+                let name = categories.iter().find(|c| c.id == *id).map_or(id.as_str(), |c| c.name.as_str());
+                format!("delete the category {} and all its flows", name)
+            }
+            GuardedAction::DeleteFlows(ids) => format!("delete {} flows", ids.len()),
+            GuardedAction::RestoreBackup => "restore a backup over the current data".to_string(),
+            GuardedAction::TurnOffStrictMode => "turn off strict mode".to_string(),
+        }
+    }
+}
+
+/// Applies a `query`'s result to the app if it has come back, returning
+/// whether it had (see `PreftApp::when_done`).
+type ResultHandler = Box<dyn FnMut(&mut PreftApp) -> bool>;
+
+/// Everything the app loads from the database, read in one job on the
+/// database thread and applied with `PreftApp::apply_stored_data`.
+struct StoredData {
+    categories: Result<Vec<Category>>,
+    user_settings: Result<UserSettings>,
+    locked_years: Result<Vec<i32>>,
+    report_templates: Result<Vec<(String, ReportRequest)>>,
+    trips: Result<Vec<Trip>>,
+    sql_views: Result<Vec<SqlView>>,
+    migration_summary: Option<MigrationSummary>,
+}
+
+impl StoredData {
+    fn load(db: &mut Database) -> Self {
+        Self {
+            categories: db.load_categories(),
+            user_settings: db.load_user_settings(),
+            locked_years: db.load_locked_years(),
+            report_templates: db.load_report_templates(),
+            trips: db.load_trips(),
+            sql_views: db.load_sql_views(),
+            migration_summary: db.take_migration_summary(),
+        }
+    }
+}
+
+/// What `StoredTotals` has for a question: the database's answer, nothing
+/// yet (callers keep showing what they had), or nothing to be had, in which
+/// case callers sum the flows themselves.
+pub enum Stored<T> {
+    Ready(T),
+    Loading,
+    Unavailable,
+}
+
+/// Category totals summed by the database rather than over loaded flows
+/// (see `Database::category_summaries`), loaded with one `query` and
+/// kept until the database changes or the day turns over. Unavailable while
+/// there are unsaved changes the database hasn't seen.
+#[derive(Default)]
+pub struct StoredTotals {
+    snapshot: Option<TotalsSnapshot>,
+    /// Whether `snapshot` is still current, as of the last `poll`.
+    fresh: bool,
+    available: bool,
+    /// The change count a load failed at, so it isn't retried every frame.
+    failed_at: Option<u64>,
+    /// Date ranges asked of `between` since the last load, loaded along
+    /// with the summaries.
+    wanted: BTreeSet<(chrono::NaiveDate, chrono::NaiveDate)>,
+    /// Set when a caller got `Loading`.
+    asked: bool,
+    loading: Option<Pending<Result<TotalsSnapshot>>>,
+}
+
+struct TotalsSnapshot {
+    /// The database's change count when the totals were summed.
+    change_count: u64,
+    as_of: chrono::NaiveDate,
+    summaries: HashMap<String, CategorySummary>,
+    ranges: HashMap<(chrono::NaiveDate, chrono::NaiveDate), HashMap<String, f64>>,
+}
+
+impl StoredTotals {
+    /// Every category's summary as of today; categories missing from the
+    /// map have no flows in range (see `CategorySummary::empty`).
+    pub fn summaries(&mut self) -> Stored<&HashMap<String, CategorySummary>> {
+        match self.check() {
+            Stored::Ready(()) => Stored::Ready(&self.snapshot.as_ref().expect("checked").summaries),
+            Stored::Loading => Stored::Loading,
+            Stored::Unavailable => Stored::Unavailable,
+        }
+    }
+
+    /// Each category's net total over flows dated `start..=end`.
+    pub fn between(&mut self, start: chrono::NaiveDate, end: chrono::NaiveDate) -> Stored<&HashMap<String, f64>> {
+        let range = (start, end);
+        self.wanted.insert(range);
+        match self.check() {
+            Stored::Ready(()) if self.snapshot.as_ref().is_some_and(|snapshot| snapshot.ranges.contains_key(&range)) => {
+                Stored::Ready(&self.snapshot.as_ref().expect("checked").ranges[&range])
+            }
+            Stored::Ready(()) => {
+                self.asked = true;
+                Stored::Loading
+            }
+            Stored::Loading => Stored::Loading,
+            Stored::Unavailable => Stored::Unavailable,
+        }
+    }
+
+    /// Whether callers sum the flows themselves for now, i.e. whether
+    /// every question gets `Unavailable`.
+    pub fn needs_fallback(&self) -> bool {
+        !self.available || self.failed_at.is_some()
+    }
+
+    fn check(&mut self) -> Stored<()> {
+        if self.needs_fallback() {
+            Stored::Unavailable
+        } else if self.fresh {
+            Stored::Ready(())
+        } else {
+            self.asked = true;
+            Stored::Loading
+        }
+    }
+
+    /// Once a frame: takes a finished load, and starts one when a caller
+    /// got `Loading` and the database is idle. `available` is false while
+    /// there are changes the database hasn't seen.
+    pub fn poll(&mut self, db: &DbWorker, available: bool, ctx: &egui::Context) {
+        self.available = available;
+        if let Some(loading) = &self.loading
+            && let Some(result) = loading.try_take()
+        {
+            self.loading = None;
+            match result.and_then(|snapshot| snapshot) {
+                Ok(snapshot) => self.snapshot = Some(snapshot),
+                Err(e) => {
+                    log::error!("Failed to total flows in the database: {}", e);
+                    self.failed_at = Some(db.change_count());
+                }
+            }
+        }
+        if self.failed_at.is_some_and(|count| count != db.change_count()) {
+            self.failed_at = None;
+        }
+
+        // A write still queued hasn't counted yet, so nothing is current
+        // while the database is busy.
+        let today = chrono::Local::now().naive_local().date();
+        let change_count = db.change_count();
+        self.fresh = !db.is_busy() && self.snapshot.as_ref()
+            .is_some_and(|snapshot| snapshot.as_of == today && snapshot.change_count == change_count);
+        let missing_range = self.snapshot.as_ref()
+            .is_none_or(|snapshot| self.wanted.iter().any(|range| !snapshot.ranges.contains_key(range)));
+        if self.asked && self.fresh && !missing_range {
+            self.asked = false;
+        }
+        if self.asked && self.loading.is_none() && self.available && self.failed_at.is_none() && !db.is_busy() {
+            self.asked = false;
+            let wanted = std::mem::take(&mut self.wanted);
+            self.loading = Some(db.query(move |db| -> Result<TotalsSnapshot> {
+                let mut ranges = HashMap::new();
+                for (start, end) in wanted {
+                    ranges.insert((start, end), db.category_totals_between(start, end)?);
+                }
+                Ok(TotalsSnapshot {
+                    change_count: db.change_count(),
+                    as_of: today,
+                    summaries: db.category_summaries(today, crate::ui::category_flows::TREND_MONTHS)?,
+                    ranges,
+                })
+            }));
+        }
+        if self.loading.is_some() || self.asked {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+    }
+}
+
+/// Figures over every flow however old, summed by the database so the
+/// whole history is never loaded: what "All time" KPI cards measure and
+/// each trip's totals. Pending changes are counted in (see
+/// `Database::kpi_totals`), so unlike `StoredTotals` there's no falling
+/// back to loaded flows; `Unavailable` only follows a failed load.
+#[derive(Default)]
+pub struct AllTimeTotals {
+    snapshot: Option<AllTimeSnapshot>,
+    /// Whether `snapshot` is still current, as of the last `poll`.
+    fresh: bool,
+    /// The change count a load failed at, so it isn't retried every frame.
+    failed_at: Option<u64>,
+    /// Set when a caller got `Loading`.
+    asked: bool,
+    loading: Option<Pending<Result<AllTimeSnapshot>>>,
+}
+
+struct AllTimeSnapshot {
+    /// The database's change count when the totals were summed.
+    change_count: u64,
+    as_of: chrono::NaiveDate,
+    /// The pending changes counted in.
+    pending: PendingChanges,
+    kpis: KpiTotals,
+    trips: HashMap<String, TripTotals>,
+}
+
+impl AllTimeTotals {
+    /// Each category's `KpiTotals` over every flow up to today.
+    pub fn kpis(&mut self) -> Stored<&KpiTotals> {
+        match self.check() {
+            Stored::Ready(()) => Stored::Ready(&self.snapshot.as_ref().expect("checked").kpis),
+            Stored::Loading => Stored::Loading,
+            Stored::Unavailable => Stored::Unavailable,
+        }
+    }
+
+    /// Totals by trip id; trips with no flows are missing.
+    pub fn trips(&mut self) -> Stored<&HashMap<String, TripTotals>> {
+        match self.check() {
+            Stored::Ready(()) => Stored::Ready(&self.snapshot.as_ref().expect("checked").trips),
+            Stored::Loading => Stored::Loading,
+            Stored::Unavailable => Stored::Unavailable,
+        }
+    }
+
+    fn check(&mut self) -> Stored<()> {
+        if self.failed_at.is_some() {
+            Stored::Unavailable
+        } else if self.fresh {
+            Stored::Ready(())
+        } else {
+            self.asked = true;
+            Stored::Loading
+        }
+    }
+
+    /// Once a frame, like `StoredTotals::poll`; the totals are current
+    /// only while `pending` is what they counted in.
+    pub fn poll(&mut self, db: &DbWorker, pending: &PendingChanges, categories: &[Category], ctx: &egui::Context) {
+        if let Some(loading) = &self.loading
+            && let Some(result) = loading.try_take()
+        {
+            self.loading = None;
+            match result.and_then(|snapshot| snapshot) {
+                Ok(snapshot) => self.snapshot = Some(snapshot),
+                Err(e) => {
+                    log::error!("Failed to total every flow in the database: {}", e);
+                    self.failed_at = Some(db.change_count());
+                }
+            }
+        }
+        if self.failed_at.is_some_and(|count| count != db.change_count()) {
+            self.failed_at = None;
+        }
+
+        let today = chrono::Local::now().naive_local().date();
+        let change_count = db.change_count();
+        self.fresh = !db.is_busy() && self.snapshot.as_ref().is_some_and(|snapshot| {
+            snapshot.as_of == today && snapshot.change_count == change_count && snapshot.pending == *pending
+        });
+        if self.asked && self.fresh {
+            self.asked = false;
+        }
+        if self.asked && self.loading.is_none() && self.failed_at.is_none() && !db.is_busy() {
+            self.asked = false;
+            let (pending, categories) = (pending.clone(), categories.to_vec());
+            self.loading = Some(db.query(move |db| -> Result<AllTimeSnapshot> {
+                Ok(AllTimeSnapshot {
+                    change_count: db.change_count(),
+                    as_of: today,
+                    kpis: db.kpi_totals(today, &pending)?,
+                    trips: db.trip_totals(&categories, &pending)?,
+                    pending,
+                })
+            }));
+        }
+        if self.loading.is_some() || self.asked {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+    }
+}
+
+/// A manual backup, restore or compact under way, moved along once per frame by
+/// `poll_backup_task` so neither the file dialog nor the copy holds up the
+/// UI.
+enum BackupTask {
+    /// The dialog asking where to save the backup is open.
+    ChoosingDestination(std::sync::mpsc::Receiver<Option<std::path::PathBuf>>),
+    /// The database thread is copying itself to `temp_path`. The result
+    /// says whether the copy came out encrypted.
+    Writing {
+        temp_path: std::path::PathBuf,
+        dest_path: std::path::PathBuf,
+        progress: Arc<BackupProgress>,
+        result: Pending<anyhow::Result<bool>>,
+    },
+    /// The dialog asking which backup to restore is open.
+    ChoosingSource(std::sync::mpsc::Receiver<Option<std::path::PathBuf>>),
+    /// The database thread is restoring from the chosen file.
+    Restoring {
+        progress: Arc<BackupProgress>,
+        result: Pending<anyhow::Result<()>>,
+    },
+    /// The database thread is running VACUUM and ANALYZE.
+    Compacting {
+        progress: Arc<BackupProgress>,
+        result: Pending<anyhow::Result<CompactReport>>,
+    },
+}
+
+/// Runs a file dialog on its own thread; the picked path (`None` if the
+/// user cancelled) arrives on the returned channel.
+fn pick_path_in_background(
+    dialog: impl std::future::Future<Output = Option<rfd::FileHandle>> + Send + 'static,
+) -> std::sync::mpsc::Receiver<Option<std::path::PathBuf>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let picked = futures_lite::future::block_on(dialog);
+        let _ = tx.send(picked.map(|handle| handle.path().to_path_buf()));
+    });
+    rx
 }
 
 /// Result of a background thread's attempt to move a completed backup (see
@@ -88,18 +619,54 @@ fn move_backup_file(temp_path: &std::path::Path, dest_path: &std::path::Path) ->
     Ok(())
 }
 
+/// The flows that lose a link when `flow_ids` are deleted, before and
+/// after: refunds of them have nothing left to net against, and expenses
+/// they reimbursed stay reimbursed without the link. Flows in a locked year
+/// can't change, so they keep theirs.
+fn unlinked_by_delete(flows: &[Flow], flow_ids: &BTreeSet<String>, locked_years: &BTreeSet<i32>) -> Vec<(Flow, Flow)> {
+    let mut unlinked = Vec::new();
+    for flow in flows.iter().filter(|f| !flow_ids.contains(&f.id) && !locked_years.contains(&f.date.year())) {
+        let mut after = flow.clone();
+        if after.refund_of.as_ref().is_some_and(|id| flow_ids.contains(id)) {
+            after.refund_of = None;
+        }
+        if after.reimbursed_by.as_ref().is_some_and(|id| flow_ids.contains(id)) {
+            after.reimbursed_by = None;
+        }
+        if after.refund_of != flow.refund_of || after.reimbursed_by != flow.reimbursed_by {
+            unlinked.push((flow.clone(), after));
+        }
+    }
+    unlinked
+}
+
 impl PreftApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, read_only: bool, repro: Option<ReproBundle>) -> Self {
         // Initialize database
-        let db = match Database::new() {
+        let demo = repro.is_some();
+        let opened = match &repro {
+            Some(bundle) => Self::open_repro(bundle),
+            None if read_only => Database::open_read_only(),
+            None => Database::new(),
+        };
+        let mut db = match opened {
             Ok(db) => db,
             Err(e) => {
-                log::error!("Failed to initialize database: {}", e);
+                log::error!("Failed to initialize database: {:#}", e);
                 log::error!("This might happen if the database file is corrupted or inaccessible.");
                 log::error!("The application will start with default settings.");
                 
-                // Try to create a minimal database connection for basic functionality
-                match Database::new_minimal() {
+                // Try to create a minimal database connection for basic
+                // functionality; it writes, so not in viewer mode, and it's
+                // the user's own database, so not in demo mode
+                let minimal = if read_only {
+                    Err(anyhow::anyhow!("viewer mode only opens an existing database"))
+                } else if demo {
+                    Err(anyhow::anyhow!("demo mode never opens the user's database"))
+                } else {
+                    Database::new_minimal()
+                };
+                match minimal {
                     Ok(db) => {
                         log::info!("Successfully created minimal database connection.");
                         db
@@ -116,80 +683,458 @@ impl PreftApp {
             }
         };
         
+        let migration_summary = db.take_migration_summary();
+
+        // Load encryption configuration, and check it against what's
+        // actually stored before anything below saves over the settings
+        let encryption_config = if demo {
+            EncryptionConfig::default()
+        } else {
+            EncryptionConfig::load().unwrap_or_else(|e| {
+                log::error!("Failed to load encryption config: {}", e);
+                EncryptionConfig::default()
+            })
+        };
+        // A locked file can't be read to compare, and its settings are
+        // only checked once it's unlocked
+        let encryption_mismatch = if read_only || demo || db.is_file_locked() {
+            None
+        } else {
+            encryption_config.mismatch(db.detect_encryption_state())
+        };
+        if let Some(mismatch) = encryption_mismatch {
+            log::warn!("Keystore and database disagree about encryption: {:?}", mismatch);
+        }
+        // Encrypted data is neither shown nor written over (the settings
+        // below load as defaults without the key) until the password is
+        // entered; see `lock`
+        let needs_unlock = !demo && (db.is_file_locked() || (encryption_config.is_encryption_ready()
+            && (db.detect_encryption_state() || !encryption_config.encrypted_columns.is_empty())));
+
         // Load categories from database or use defaults if none exist
         let categories = db.load_categories()
             .unwrap_or_else(|e| {
                 log::error!("Failed to load categories: {}", e);
                 get_default_categories()
             });
-            
-        // Load flows from database
-        let flows = db.load_flows().unwrap_or_else(|e| {
-            log::error!("Failed to load flows: {}", e);
-            Vec::new()
-        });
+
 
         // Load user settings
-        let user_settings = db.load_user_settings().unwrap_or_else(|e| {
+        let mut user_settings = db.load_user_settings().unwrap_or_else(|e| {
             log::error!("Failed to load user settings: {}", e);
             UserSettings::new()
         });
+        theme::apply(&cc.egui_ctx, user_settings.theme, cc.integration_info.system_theme);
+        cc.egui_ctx.set_zoom_factor(user_settings.get_ui_scale());
+        let window_geometry = user_settings.window;
+        if user_settings.home_utc_offset.is_none() && !read_only && encryption_mismatch.is_none() && !needs_unlock {
+            user_settings.home_utc_offset = Some(crate::utils::local_utc_offset());
+            if let Err(e) = db.save_user_settings(&user_settings) {
+                log::error!("Failed to save home timezone: {}", e);
+            }
+        }
         
-        // Load encryption configuration
-        let encryption_config = EncryptionConfig::load().unwrap_or_else(|e| {
-            log::error!("Failed to load encryption config: {}", e);
-            EncryptionConfig::default()
+        let locked_years = db.load_locked_years().unwrap_or_else(|e| {
+            log::error!("Failed to load locked years: {}", e);
+            Vec::new()
+        }).into_iter().collect();
+
+        let report_templates = db.load_report_templates().unwrap_or_else(|e| {
+            log::error!("Failed to load report templates: {}", e);
+            Vec::new()
         });
-        
+
+        let trips = db.load_trips().unwrap_or_else(|e| {
+            log::error!("Failed to load trips: {}", e);
+            Vec::new()
+        });
+
+        let sql_views = db.load_sql_views().unwrap_or_else(|e| {
+            log::error!("Failed to load SQL views: {}", e);
+            Vec::new()
+        });
+
         // Initialize category flows state for all categories
         let mut category_flows_state = HashMap::new();
         for category in &categories {
             category_flows_state.insert(category.id.clone(), CategoryFlowsState::new());
         }
         
-        Self {
+        let repaint = cc.egui_ctx.clone();
+        let mut app = Self {
             categories,
-            flows,
+            flow_cache: FlowCache::default(),
+            loading_scopes: HashSet::new(),
+            failed_scopes: HashMap::new(),
+            trips,
+            sql_views,
             selected_category: None,
+            opened_category: None,
             show_category_editor: false,
             show_hidden_categories: false,
             new_flow: None,
             editing_flow: None,
             custom_field_values: HashMap::new(),
             user_settings,
+            locked_years,
             flow_editor_state: FlowEditorState::new(),
-            db,
+            db: DbWorker::start(db, move || repaint.request_repaint()),
+            awaiting: Vec::new(),
             hide_category_confirmation: None,
             delete_category_confirmation: None,
+            delete_category_typed_name: String::new(),
             new_category: None,
             show_field_editor: false,
             editing_field: None,
+            select_option_drafts: Vec::new(),
             report_request: ReportRequest::default(),
+            report_templates,
+            report_template_name: String::new(),
             show_report_dialog: false,
+            show_report_preview: false,
+            report_flow_search: String::new(),
+            report_flow_search_filtered: true,
+            report_state: ReportDialogState::default(),
+            show_import_dialog: false,
+            import_state: ImportDialogState::new(),
+            show_verify_dialog: false,
+            verification_results: None,
+            verifying: false,
             dashboard: Dashboard::new(),
+            stored_totals: StoredTotals::default(),
+            all_time_totals: AllTimeTotals::default(),
             category_flows_state,
             editing_category: None,
+            category_editor_tab: CategoryEditorTab::Basic,
+            category_uniqueness_draft: Vec::new(),
+            category_number_format_draft: None,
+            category_option_renames: OptionRenames::default(),
+            scheduled_flows_confirmed_on: None,
             // Backup-related fields
             show_backup_dialog: false,
+            show_backup_compare_dialog: false,
+            backup_compare_state: BackupCompareState::default(),
+            show_bulk_edit_dialog: false,
+            bulk_edit_state: BulkEditState::default(),
+            show_highlight_rules_dialog: false,
+            show_settings_dialog: false,
+            show_export_bundle_dialog: false,
+            export_bundle_state: ExportBundleState::default(),
+            migration_summary,
+            pending_changes: PendingChanges::default(),
+            saving_pending_changes: false,
+            show_pending_changes_panel: false,
+            quit_requested: false,
+            highlight_rules_state: HighlightRulesState::default(),
+            show_kpi_cards_dialog: false,
+            kpi_cards_state: KpiCardsState::default(),
+            show_trips_dialog: false,
+            trips_state: TripsState::default(),
+            show_sql_views_dialog: false,
+            sql_views_state: SqlViewsState::default(),
+            uniqueness_conflict: None,
+            save_requested: false,
+            saving_flow: false,
+            undo_stack: UndoStack::default(),
+            undo_in_progress: false,
+            deleting: false,
+            saving_repro_bundle: false,
+            notifications: Vec::new(),
+            last_watch_folder_scan: None,
+            integrity_scan: None,
+            integrity_step: None,
+            last_integrity_scan: None,
+            last_input: std::time::Instant::now(),
+            locked: false,
+            started: false,
+            integrity_problems: None,
             backup_status: None,
             backup_in_progress: false,
             pending_backup: None,
+            backup_task: None,
             // Encryption-related fields
             show_password_dialog: false,
             password_dialog_mode: PasswordDialogMode::SetPassword,
-            password_input: String::new(),
-            password_confirm: String::new(),
+            password_input: secret_buffer(),
+            password_confirm: secret_buffer(),
+            current_password_input: secret_buffer(),
             encryption_status: None,
+            encryption_busy: false,
+            pending_guarded_action: None,
+            encrypted_columns_draft: Vec::new(),
+            ui_scale_seen: None,
+            window_geometry,
             // Encryption configuration (loaded from OS keystore)
             encryption_config,
+            encryption_repair: encryption_mismatch.map(EncryptionRepairState::new),
+            read_only,
+            demo,
+        };
+        if needs_unlock {
+            info!("The data is encrypted; waiting for the password");
+            app.lock();
+        } else {
+            app.finish_startup();
+        }
+        app
+    }
+
+    /// What starting up does with the data once it can be read: confirming
+    /// scheduled flows that have come due, metric snapshots, the first
+    /// watch-folder scan and opening the startup view. Encrypted data waits
+    /// for the password first (see `verify_password`).
+    fn finish_startup(&mut self) {
+        self.started = true;
+        if !self.read_only {
+            self.confirm_due_scheduled_flows();
+            self.record_metric_snapshots();
+            self.scan_watch_folder();
+        }
+        self.open_startup_view();
+    }
+
+    /// An in-memory database holding a repro bundle's data, for demo mode.
+    fn open_repro(bundle: &ReproBundle) -> anyhow::Result<Database> {
+        let mut db = Database::new_in_memory()?;
+        for category in &bundle.categories {
+            db.save_category(category)?;
+        }
+        for trip in &bundle.trips {
+            db.save_trip(trip)?;
+        }
+        for flow in &bundle.flows {
+            db.save_flow(flow)?;
+        }
+        db.save_user_settings(&bundle.settings)?;
+        Ok(db)
+    }
+
+    /// Saves an anonymized copy of the data, the settings and the recent
+    /// logs (see `repro`) to attach to a bug report.
+    pub fn save_repro_bundle(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Repro Bundle")
+            .set_file_name(format!("preft_repro_{}.{}", chrono::Local::now().format("%Y%m%d"), REPRO_EXTENSION))
+            .add_filter("Preft Repro Bundle", &[REPRO_EXTENSION])
+            .save_file()
+        else {
+            return;
+        };
+
+        let logs = std::fs::read_dir(crate::logging::get_log_directory())
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().ends_with(".log"))
+                .filter_map(|entry| Some(ReproLog {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    contents: String::from_utf8_lossy(&std::fs::read(entry.path()).ok()?).to_string(),
+                }))
+                .collect())
+            .unwrap_or_else(|e| {
+                log::warn!("Could not read the log directory for a repro bundle: {}", e);
+                Vec::new()
+            });
+        // Every flow goes in, so the bundle is put together on the database
+        // thread rather than loading them all here
+        let (categories, trips, settings) = (self.categories.clone(), self.trips.clone(), self.user_settings.clone());
+        let pending_changes = self.pending_changes.clone();
+        let bundle_path = path.clone();
+        self.saving_repro_bundle = true;
+        let saving = self.db.query(move |db| -> Result<()> {
+            let mut flows = db.load_flows()?;
+            pending_changes.apply_to(&mut flows);
+            let bundle = ReproBundle::new(env!("CARGO_PKG_VERSION"), &categories, &flows, &trips, &settings, logs);
+            Ok(std::fs::write(&bundle_path, bundle.to_bytes()?)?)
+        });
+        self.when_done(saving, move |app, result| {
+            app.saving_repro_bundle = false;
+            app.notifications.push(match result.and_then(|saved| saved) {
+                Ok(()) => format!("Repro bundle saved to {}. Its logs are included as they are; look them over before sharing.", path.display()),
+                Err(e) => {
+                    log::error!("Failed to save repro bundle: {}", e);
+                    format!("Failed to save repro bundle: {}", e)
+                }
+            });
+        });
+    }
+
+    /// Saves the category definitions, without any flows, to a file
+    /// another install can import (see `category_schema`).
+    pub fn export_category_schemas(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export Categories")
+            .set_file_name(format!("preft_categories_{}.{}", chrono::Local::now().format("%Y%m%d"), SCHEMA_EXTENSION))
+            .add_filter("Category Schema", &[SCHEMA_EXTENSION])
+            .save_file()
+        else {
+            return;
+        };
+
+        let file = CategorySchemaFile::new(env!("CARGO_PKG_VERSION"), &self.categories);
+        let result = file.to_bytes().and_then(|bytes| Ok(std::fs::write(&path, bytes)?));
+        self.notifications.push(match result {
+            Ok(()) => format!("Exported {} categories to {}", self.categories.len(), path.display()),
+            Err(e) => {
+                log::error!("Failed to export categories: {}", e);
+                format!("Failed to export categories: {}", e)
+            }
+        });
+    }
+
+    /// Adds the categories in an exported schema file that aren't here yet
+    /// and brings matching ones in line with it (see `SchemaImport::plan`).
+    /// Each change can be undone on its own.
+    pub fn import_category_schemas(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Import Categories")
+            .add_filter("Category Schema", &[SCHEMA_EXTENSION])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let file = match std::fs::read(&path).map_err(anyhow::Error::from).and_then(|data| CategorySchemaFile::from_bytes(&data)) {
+            Ok(file) => file,
+            Err(e) => {
+                log::error!("Failed to import categories from {}: {}", path.display(), e);
+                self.notifications.push(format!("Failed to import categories: {}", e));
+                return;
+            }
+        };
+        let plan = SchemaImport::plan(&self.categories, &file.categories);
+        let (added, updated, unchanged) = (plan.added.len(), plan.updated.len(), plan.unchanged.len());
+        for category in plan.added {
+            self.add_category(category);
+        }
+        for category in plan.updated {
+            self.update_category(category, &OptionRenames::default());
+        }
+        self.notifications.push(format!(
+            "Imported categories from {}: {} added, {} updated, {} already the same",
+            path.display(), added, updated, unchanged
+        ));
+    }
+
+    /// Opens what `UserSettings::startup_view` asks for. The dashboard is
+    /// shown whenever there's no category to open.
+    fn open_startup_view(&mut self) {
+        let categories = &self.categories;
+        let Some(category_id) = self.user_settings
+            .startup_category(|id| categories.iter().any(|c| c.id == id))
+            .map(str::to_string)
+        else {
+            return;
+        };
+        self.selected_category = Some(category_id);
+        if self.user_settings.startup_view == StartupView::NewFlow
+            && !self.read_only
+            && let Some(category) = self.get_selected_category().cloned()
+        {
+            self.create_new_flow(&category);
+        }
+    }
+
+    /// Runs `apply` with the result of `pending` on the first frame it has
+    /// come back, so the UI never waits on the database thread for it.
+    pub fn when_done<T: 'static>(&mut self, pending: Pending<T>, apply: impl FnOnce(&mut Self, anyhow::Result<T>) + 'static) {
+        let mut apply = Some(apply);
+        self.awaiting.push(Box::new(move |app| {
+            let Some(result) = pending.try_take() else { return false };
+            if let Some(apply) = apply.take() {
+                apply(app, result);
+            }
+            true
+        }));
+    }
+
+    /// Applies the results that came back since the last frame (see
+    /// `when_done`).
+    fn apply_db_results(&mut self) {
+        let mut awaiting = std::mem::take(&mut self.awaiting);
+        // In order: nothing is applied before what was queued ahead of it
+        let mut applied = 0;
+        while applied < awaiting.len() && awaiting[applied](self) {
+            applied += 1;
+        }
+        awaiting.drain(..applied);
+        // Applying a result may have queued more work
+        awaiting.append(&mut self.awaiting);
+        self.awaiting = awaiting;
+    }
+
+    /// Reports what the database thread said since the last frame. Writes
+    /// are shown before they reach the database, so after one fails
+    /// everything is loaded again to match what it holds.
+    fn handle_db_events(&mut self) {
+        let mut failed = false;
+        for event in self.db.take_events() {
+            match event {
+                DbEvent::Failed { action, error } => {
+                    log::error!("Failed to {}: {}", action, error);
+                    self.notifications.push(format!("Failed to {}: {}", action, error));
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            self.reload_from_db();
+        }
+    }
+
+    /// Queues a save of the settings on the database thread. A failure is
+    /// logged when it comes back, as "Failed to {action}".
+    pub fn save_settings(&self, action: &str) {
+        // Locked at startup, the settings are defaults standing in for ones
+        // that can't be decrypted yet, and saving would replace those
+        if self.locked {
+            info!("Not saving settings while locked ({})", action);
+            return;
+        }
+        // The viewer's connection can't write
+        if self.read_only {
+            return;
+        }
+        let settings = self.user_settings.clone();
+        self.db.run(action, move |db| db.save_user_settings(&settings));
+    }
+
+    /// Remembers the open category for `StartupView::LastCategory`. Going
+    /// back to the dashboard keeps the last one.
+    fn remember_selected_category(&mut self) {
+        if self.read_only || self.selected_category.is_none() || self.selected_category == self.user_settings.last_category {
+            return;
+        }
+        self.user_settings.last_category = self.selected_category.clone();
+        self.save_settings("save last category");
+    }
+
+    /// Sets up the table of a category just opened as its saved view asks
+    /// (see `UserSettings::category_views`), including the year filter.
+    fn open_category_view(&mut self) {
+        if self.selected_category == self.opened_category {
+            return;
+        }
+        self.opened_category = self.selected_category.clone();
+        let Some(category_id) = self.opened_category.clone() else { return };
+        let Some(view) = self.user_settings.get_category_view(&category_id).cloned() else { return };
+        self.get_category_flows_state(&category_id).apply_view(&view);
+        let year_filter = view.years.map(|years| years.year_filter(chrono::Local::now().year()));
+        if let Some(year_filter) = year_filter
+            && year_filter != self.user_settings.get_year_filter()
+        {
+            self.user_settings.set_year_filter(year_filter);
+            if !self.read_only {
+                self.save_settings("save user settings");
+            }
+            for state in self.category_flows_state.values_mut() {
+                state.mark_for_update();
+            }
         }
     }
 
     pub fn toggle_category_visibility(&mut self, category_id: String) {
         self.user_settings.toggle_category_visibility(category_id);
-        if let Err(e) = self.db.save_user_settings(&self.user_settings) {
-            log::error!("Failed to save user settings: {}", e);
-        }
+        self.save_settings("save user settings");
     }
 
     pub fn is_category_hidden(&self, category_id: &str) -> bool {
@@ -206,6 +1151,13 @@ impl PreftApp {
             linked_flows: Vec::new(),
             custom_fields: HashMap::new(),
             tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: Some(crate::utils::local_utc_offset()),
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
         };
         self.new_flow = Some(new_flow.clone());
         self.flow_editor_state.set_editor(new_flow, true);
@@ -219,20 +1171,45 @@ impl PreftApp {
     }
 
     pub fn save_flow(&mut self, mut flow_data: Flow) {
+        if self.saving_flow {
+            return;
+        }
         // Copy all custom field values to the flow's custom_fields
         for (name, value) in &self.custom_field_values {
             flow_data.custom_fields.insert(name.clone(), value.clone());
         }
 
+        self.saving_flow = true;
+        self.check_uniqueness(flow_data, |app, flow_data, conflict| {
+            app.saving_flow = false;
+            // Cancelled while the category's flows were loading
+            if !app.new_flow.iter().chain(&app.editing_flow).any(|f| f.id == flow_data.id) {
+                return;
+            }
+            match conflict {
+                // Left open in the editor until the conflict is resolved.
+                Ok(Some(conflict)) => app.uniqueness_conflict = Some(conflict),
+                Ok(None) => app.store_flow(flow_data),
+                Err(e) => log::error!("Failed to check the flow against its category's uniqueness rules: {}", e),
+            }
+        });
+    }
+
+    /// The rest of `save_flow`, once the flow has passed its category's
+    /// uniqueness rules.
+    fn store_flow(&mut self, flow_data: Flow) {
         // Save to database
-        if let Err(e) = self.db.save_flow(&flow_data) {
+        if let Err(e) = self.write_flow(&flow_data) {
             log::error!("Failed to save flow: {}", e);
             return;
         }
+        let before = self.editing_flow.clone().filter(|f| f.id == flow_data.id);
+        let label = if before.is_some() { "Edit flow" } else { "Add flow" };
+        self.undo_stack.record(Edit::new(label).flow(before, Some(flow_data.clone())));
 
         if self.new_flow.is_some() {
             if let Some(_) = self.new_flow.take() {
-                self.flows.push(flow_data.clone());
+                self.flow_cache.saved(&flow_data);
                 // Create a new flow for the next entry
                 let category_id = flow_data.category_id.clone();
                 let new_flow = Flow {
@@ -244,6 +1221,13 @@ impl PreftApp {
                     linked_flows: Vec::new(),
                     custom_fields: HashMap::new(),
                     tax_deductible: None,
+                    refund_of: None,
+                    scheduled: false,
+                    created_utc_offset: Some(crate::utils::local_utc_offset()),
+                    location: None,
+                    trip_id: None,
+                    reimbursement: None,
+                    reimbursed_by: None,
                 };
                 self.new_flow = Some(new_flow.clone());
                 // Update the editor with the new flow. FlowEditor::new()
@@ -266,16 +1250,11 @@ impl PreftApp {
                     .expect("Category state should exist");
                 state.mark_for_update();
             }
-        } else if self.editing_flow.is_some() {
-            if let Some(editing_flow) = self.editing_flow.take() {
-                if let Some(existing_flow) = self.flows.iter_mut()
-                    .find(|f| f.id == editing_flow.id) {
-                    *existing_flow = flow_data;
-                    self.dashboard.mark_for_update();
-                    let state = self.category_flows_state.get_mut(&existing_flow.category_id)
-                        .expect("Category state should exist");
-                    state.mark_for_update();
-                }
+        } else if let Some(editing_flow) = self.editing_flow.take() {
+            self.flow_cache.saved(&flow_data);
+            self.dashboard.mark_for_update();
+            for category_id in [&editing_flow.category_id, &flow_data.category_id] {
+                self.get_category_flows_state(category_id).mark_for_update();
             }
         }
     }
@@ -287,6 +1266,12 @@ impl PreftApp {
         self.flow_editor_state.clear_editor();
     }
 
+    /// Whether the flow editor is open, for actions that must wait until
+    /// it's saved or cancelled.
+    pub fn is_editing_flow(&self) -> bool {
+        self.flow_editor_state.has_editor()
+    }
+
     pub fn get_selected_category(&self) -> Option<&Category> {
         self.selected_category.as_ref()
             .and_then(|id| self.categories.iter().find(|c| c.id == *id))
@@ -297,60 +1282,1243 @@ impl PreftApp {
         self.flow_editor_state.set_editor(flow, false);
     }
 
-    pub fn delete_category(&mut self, category_id: String) {
-        // Remove the category from the database
-        if let Err(e) = self.db.delete_category(&category_id) {
-            log::error!("Failed to delete category: {}", e);
-            return;
+    /// Opens `flow` in the flow editor, with its custom field values (or
+    /// the field defaults, for fields it has no value for).
+    pub fn start_editing_flow(&mut self, flow: Flow, category: &Category) {
+        self.custom_field_values.clear();
+        for field in &category.fields {
+            if let Some(value) = flow.custom_fields.get(&field.name) {
+                self.custom_field_values.insert(field.name.clone(), value.clone());
+            } else if let Some(default) = &field.default_value {
+                self.custom_field_values.insert(field.name.clone(), default.clone());
+            }
         }
+        self.set_editing_flow(flow);
+    }
 
-        // Remove the category from memory
-        self.categories.retain(|c| c.id != category_id);
+    /// Runs whichever global shortcut was pressed this frame (see
+    /// `shortcuts`). New Flow, Save, Undo and Redo do nothing in read-only
+    /// mode, and neither the category nor the data can be changed out from
+    /// under an open flow editor. Undo and Redo are left to a focused text
+    /// field, which has its own.
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        let pressed = |action: ShortcutAction| {
+            shortcuts::shortcut(action, self.user_settings.get_shortcut(action))
+                .is_some_and(|shortcut| ctx.input_mut(|i| i.consume_shortcut(&shortcut)))
+        };
+        let [new_flow, save, search, open_report] =
+            [ShortcutAction::NewFlow, ShortcutAction::Save, ShortcutAction::Search, ShortcutAction::OpenReport].map(pressed);
+        let [undo, redo] = if ctx.wants_keyboard_input() {
+            [false; 2]
+        } else {
+            [ShortcutAction::Undo, ShortcutAction::Redo].map(pressed)
+        };
+        let switch_to = match shortcuts::parse_binding(ShortcutAction::SwitchCategory, self.user_settings.get_shortcut(ShortcutAction::SwitchCategory)) {
+            Ok((modifiers, _)) => shortcuts::CATEGORY_KEYS.iter()
+                .position(|key| ctx.input_mut(|i| i.consume_key(modifiers, *key))),
+            Err(_) => None,
+        };
 
-        // Remove all flows associated with this category
-        self.flows.retain(|f| f.category_id != category_id);
-        if let Err(e) = self.db.delete_flows_by_category(&category_id) {
-            log::error!("Failed to delete flows for category: {}", e);
+        let editing = self.flow_editor_state.has_editor();
+        if open_report {
+            self.show_report_dialog = true;
         }
-
-        // Clear selection if the deleted category was selected
-        if self.selected_category.as_ref() == Some(&category_id) {
-            self.selected_category = None;
+        if search && let Some(category_id) = self.selected_category.clone() {
+            self.get_category_flows_state(&category_id).request_search_focus();
+        }
+        if new_flow && !self.read_only && !editing && let Some(category) = self.get_selected_category().cloned() {
+            self.create_new_flow(&category);
+        }
+        if save && !self.read_only && editing {
+            self.save_requested = true;
+        }
+        if undo && !self.read_only && !editing {
+            self.undo();
+        }
+        if redo && !self.read_only && !editing {
+            self.redo();
+        }
+        if let Some(index) = switch_to && !editing {
+            let category_id = self.categories.iter()
+                .filter(|c| !self.is_category_hidden(&c.id))
+                .nth(index)
+                .map(|c| c.id.clone());
+            if category_id.is_some() {
+                self.selected_category = category_id;
+            }
         }
-        self.dashboard.mark_for_update();
-        self.category_flows_state.remove(&category_id);
     }
 
-    pub fn delete_flow(&mut self, flow_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Remove the flow from the database
-        self.db.delete_flow(flow_id)?;
-
-        // Remove the flow from memory
-        if let Some(pos) = self.flows.iter().position(|f| f.id == flow_id) {
-            let flow = self.flows.remove(pos);
-            self.dashboard.mark_for_update();
-            let state = self.category_flows_state.get_mut(&flow.category_id)
-                .expect("Category state should exist");
-            state.mark_for_update();
+    /// Hands `then` the flow back with the first of the category's
+    /// uniqueness rules it would break, if any. The category's flows are
+    /// only loaded if it has any rules (see `with_flows_in`).
+    fn check_uniqueness(&mut self, flow: Flow, then: impl FnOnce(&mut Self, Flow, anyhow::Result<Option<UniquenessConflict>>) + 'static) {
+        if self.user_settings.get_uniqueness_rules(&flow.category_id).is_empty() {
+            return then(self, flow, Ok(None));
         }
-
-        Ok(())
+        self.with_flows_in(FlowScope::category(&flow.category_id, None), move |app, flows| {
+            let conflict = flows.map(|flows| app.user_settings.get_uniqueness_rules(&flow.category_id).iter()
+                .find_map(|rule| rule.conflict(&flow, &flows).map(|existing| UniquenessConflict {
+                    existing: existing.clone(),
+                    fields: rule.fields.clone(),
+                })));
+            then(app, flow, conflict);
+        });
     }
 
-    pub fn add_category(&mut self, category: Category) {
-        self.categories.push(category.clone());
-        self.category_flows_state.insert(category.id.clone(), CategoryFlowsState::new());
-        if let Err(e) = self.db.save_category(&category) {
-            log::error!("Failed to save category: {}", e);
+    /// Deletes the category with all its flows, once they're loaded (see
+    /// `with_flows_in`).
+    pub fn delete_category(&mut self, category_id: String) {
+        if self.deleting {
+            return;
         }
+        self.deleting = true;
+        let scope = FlowScope::category(&category_id, None);
+        self.with_flows_in(scope, move |app, flows| {
+            app.deleting = false;
+            match flows {
+                Ok(flows) => app.delete_category_with(category_id, &flows),
+                Err(e) => log::error!("Failed to load flows for category: {}", e),
+            }
+        });
     }
 
-    pub fn get_category_flows_state(&mut self, category_id: &str) -> &mut CategoryFlowsState {
-        self.category_flows_state
-            .entry(category_id.to_string())
+    /// The rest of `delete_category`, with the category's `flows`.
+    fn delete_category_with(&mut self, category_id: String, flows: &[Flow]) {
+        // Deleted while its flows were loading
+        if !self.categories.iter().any(|c| c.id == category_id) {
+            return;
+        }
+        let mut edit = Edit::new("Delete category")
+            .category(self.categories.iter().find(|c| c.id == category_id).cloned(), None);
+        for flow in flows.iter() {
+            if let Err(e) = self.ensure_flow_unlocked(flow) {
+                log::error!("Failed to delete flows for category: {}", e);
+                return;
+            }
+            edit = edit.flow(Some(flow.clone()), None);
+        }
+
+        // Remove all flows associated with this category first: this fails
+        // if any are in a locked year, and the category must then stay too.
+        let id = category_id.clone();
+        self.db.run("delete category", move |db| {
+            db.delete_flows_by_category(&id).map_err(|e| anyhow::anyhow!("{}", e))?;
+            db.delete_category(&id).map_err(|e| anyhow::anyhow!("{}", e))
+        });
+        for flow in flows.iter() {
+            self.flow_cache.deleted(&flow.id);
+        }
+
+        // Remove the category from memory
+        self.categories.retain(|c| c.id != category_id);
+
+        // Clear selection if the deleted category was selected
+        if self.selected_category.as_ref() == Some(&category_id) {
+            self.selected_category = None;
+        }
+        self.dashboard.mark_for_update();
+        self.category_flows_state.remove(&category_id);
+        self.undo_stack.record(edit);
+    }
+
+    pub fn delete_flow(&mut self, flow_id: &str) -> anyhow::Result<()> {
+        self.delete_flows(&BTreeSet::from([flow_id.to_string()]))
+    }
+
+    /// Deletes every flow in `flow_ids` as one undo step, unlinking refunds
+    /// and reimbursements of them (see `unlinked_by_delete`): in one database
+    /// transaction, or with deferred writes on, as queued changes. Nothing
+    /// is deleted if any of them is in a locked year. The linked flows are
+    /// loaded on the database thread first; a failure to load them is
+    /// reported as a notification.
+    pub fn delete_flows(&mut self, flow_ids: &BTreeSet<String>) -> anyhow::Result<()> {
+        if self.deleting {
+            return Err(anyhow::anyhow!("Another delete is still in progress"));
+        }
+        for flow in flow_ids.iter().filter_map(|id| self.flow_cache.find(id)) {
+            self.ensure_flow_unlocked(flow)?;
+        }
+
+        self.deleting = true;
+        let ids: Vec<String> = flow_ids.iter().cloned().collect();
+        let loading = self.db.query(move |db| db.load_flows_linked_to(&ids));
+        let flow_ids = flow_ids.clone();
+        self.when_done(loading, move |app, result| {
+            app.deleting = false;
+            if app.locked {
+                return;
+            }
+            if let Err(e) = result.and_then(|linked| linked).and_then(|linked| app.delete_flows_with(&flow_ids, linked)) {
+                log::error!("Failed to delete flows: {}", e);
+                app.notifications.push(format!("The flows were not deleted: {}", e));
+            }
+        });
+        Ok(())
+    }
+
+    /// The rest of `delete_flows`, with the flows `linked` to those in
+    /// `flow_ids` as stored. They're looked up again, in case they were
+    /// edited (or a year locked) while `linked` was loading.
+    fn delete_flows_with(&mut self, flow_ids: &BTreeSet<String>, mut linked: Vec<Flow>) -> anyhow::Result<()> {
+        let doomed: Vec<Flow> = flow_ids.iter().filter_map(|id| self.flow_cache.find(id)).cloned().collect();
+        for flow in &doomed {
+            self.ensure_flow_unlocked(flow)?;
+        }
+        self.pending_changes.apply_to(&mut linked);
+        let unlinked = unlinked_by_delete(&linked, flow_ids, &self.locked_years);
+
+        if self.user_settings.deferred_writes {
+            for flow in &doomed {
+                self.pending_changes.delete_flow(flow.clone());
+            }
+            for (_, after) in &unlinked {
+                self.pending_changes.save_flow(after.clone());
+            }
+        } else {
+            let ids: Vec<String> = doomed.iter().map(|f| f.id.clone()).collect();
+            self.db.run(format!("delete {} flows", ids.len()), move |db| db.delete_flows(&ids));
+        }
+
+        let label = if doomed.len() == 1 { "Delete flow".to_string() } else { format!("Delete {} flows", doomed.len()) };
+        let mut edit = Edit::new(&label);
+        for flow in doomed {
+            self.flow_cache.deleted(&flow.id);
+            if let Some(state) = self.category_flows_state.get_mut(&flow.category_id) {
+                state.mark_for_update();
+            }
+            edit = edit.flow(Some(flow), None);
+        }
+        for (before, after) in unlinked {
+            self.flow_cache.saved(&after);
+            self.get_category_flows_state(&after.category_id).mark_for_update();
+            edit = edit.flow(Some(before), Some(after));
+        }
+        self.dashboard.mark_for_update();
+        self.undo_stack.record(edit);
+        Ok(())
+    }
+
+    /// Queues a flow's delete on the database thread, or with deferred
+    /// writes on, for Save All (see `write_flow`). Leaves `flow_cache`
+    /// alone.
+    fn erase_flow(&mut self, flow: &Flow) -> anyhow::Result<()> {
+        self.ensure_flow_unlocked(flow)?;
+        let flow = flow.clone();
+        if self.user_settings.deferred_writes {
+            self.pending_changes.delete_flow(flow);
+        } else {
+            self.db.run("delete flow", move |db| db.delete_flow(&flow.id).map_err(|e| anyhow::anyhow!("{}", e)));
+        }
+        Ok(())
+    }
+
+    /// Reverses the most recent edit on the undo stack, if any.
+    pub fn undo(&mut self) {
+        let Some(edit) = self.undo_stack.next_undo().cloned() else { return };
+        self.step_undo_stack(edit, true);
+    }
+
+    /// Makes the most recently undone edit again, if any.
+    pub fn redo(&mut self) {
+        let Some(edit) = self.undo_stack.next_redo().cloned() else { return };
+        self.step_undo_stack(edit, false);
+    }
+
+    /// Applies one side of `edit` (see `apply_edit`) and moves it across
+    /// the undo stack. A category it would remove mustn't have flows the
+    /// edit doesn't know about, so those categories' flows are loaded
+    /// first; `undo_in_progress` is set until then.
+    fn step_undo_stack(&mut self, edit: Edit, undo: bool) {
+        if self.undo_in_progress {
+            return;
+        }
+        self.undo_in_progress = true;
+        let removed_categories = edit.categories.iter()
+            .filter(|change| change.target(undo).is_none())
+            .filter_map(|change| change.before.clone().or(change.after.clone()))
+            .collect();
+        self.check_removed_categories(edit, undo, removed_categories);
+    }
+
+    fn check_removed_categories(&mut self, edit: Edit, undo: bool, mut unchecked: Vec<Category>) {
+        let Some(category) = unchecked.pop() else {
+            self.undo_in_progress = false;
+            // Another edit made while the flows were loading comes first now
+            let next = if undo { self.undo_stack.next_undo() } else { self.undo_stack.next_redo() };
+            if next != Some(&edit) {
+                return;
+            }
+            let result = self.apply_edit(&edit, undo);
+            return self.finish_undo_step(&edit, undo, result);
+        };
+        self.with_flows_in(FlowScope::category(&category.id, None), move |app, flows| {
+            let removed = |flow: &Flow| edit.flows.iter()
+                .any(|change| change.target(undo).is_none() && change.before.iter().chain(&change.after).any(|f| f.id == flow.id));
+            match flows {
+                Ok(flows) if flows.iter().all(removed) => app.check_removed_categories(edit, undo, unchecked),
+                Ok(_) => {
+                    app.undo_in_progress = false;
+                    app.finish_undo_step(&edit, undo, Err(anyhow::anyhow!("{} has flows added since", category.name)));
+                }
+                Err(e) => {
+                    app.undo_in_progress = false;
+                    app.finish_undo_step(&edit, undo, Err(e));
+                }
+            }
+        });
+    }
+
+    fn finish_undo_step(&mut self, edit: &Edit, undo: bool, result: anyhow::Result<()>) {
+        let verb = if undo { "undo" } else { "redo" };
+        match result {
+            Ok(()) if undo => self.undo_stack.undone(),
+            Ok(()) => self.undo_stack.redone(),
+            Err(e) => {
+                log::error!("Failed to {} {}: {}", verb, edit.label, e);
+                self.notifications.push(format!("Could not {} {}: {}", verb, edit.label.to_lowercase(), e));
+            }
+        }
+    }
+
+    /// Writes one side of `edit` (the "before" side when `undo`) to the
+    /// database and to memory. Refused up front if any flow involved is in
+    /// a locked year, so an edit is never half reversed that way; the
+    /// categories it removes have been checked for other flows already
+    /// (see `step_undo_stack`). Categories that come back are saved before
+    /// their flows, and categories that go away are deleted after them.
+    fn apply_edit(&mut self, edit: &Edit, undo: bool) -> anyhow::Result<()> {
+        for flow in edit.flows.iter().flat_map(|change| change.before.iter().chain(&change.after)) {
+            self.ensure_flow_unlocked(flow)?;
+        }
+
+        for change in &edit.categories {
+            let Some(category) = change.target(undo) else { continue };
+            let saved = category.clone();
+            self.db.run(format!("save category {}", category.name), move |db| db.save_category(&saved));
+            match self.categories.iter_mut().find(|c| c.id == category.id) {
+                Some(existing) => *existing = category.clone(),
+                None => self.categories.push(category.clone()),
+            }
+            self.get_category_flows_state(&category.id).mark_for_update();
+        }
+        // A category brought back takes its old place in the list.
+        self.categories.sort_by_key(|c| c.sort_order);
+
+        for change in &edit.flows {
+            let current = change.before.as_ref().or(change.after.as_ref()).expect("a change has at least one side");
+            match change.target(undo) {
+                Some(flow) => {
+                    self.write_flow(flow)?;
+                    self.flow_cache.saved(flow);
+                }
+                None => {
+                    self.erase_flow(current)?;
+                    self.flow_cache.deleted(&current.id);
+                }
+            }
+            for flow in change.before.iter().chain(&change.after) {
+                self.get_category_flows_state(&flow.category_id).mark_for_update();
+            }
+        }
+
+        for change in &edit.categories {
+            if change.target(undo).is_some() {
+                continue;
+            }
+            let Some(category) = change.before.as_ref().or(change.after.as_ref()) else { continue };
+            let id = category.id.clone();
+            self.db.run(format!("delete category {}", category.name), move |db| db.delete_category(&id).map_err(|e| anyhow::anyhow!("{}", e)));
+            self.categories.retain(|c| c.id != category.id);
+            self.category_flows_state.remove(&category.id);
+            if self.selected_category.as_ref() == Some(&category.id) {
+                self.selected_category = None;
+            }
+        }
+
+        self.dashboard.mark_for_update();
+        Ok(())
+    }
+
+    /// Turns a scheduled flow into a real one, so it starts counting toward
+    /// actual totals.
+    pub fn confirm_flow(&mut self, flow_id: &str) {
+        let Some(flow) = self.flow_cache.find(flow_id).filter(|f| f.scheduled).cloned() else { return };
+        self.confirm_scheduled_flow(flow);
+    }
+
+    fn confirm_scheduled_flow(&mut self, mut flow: Flow) {
+        flow.scheduled = false;
+        if let Err(e) = self.write_flow(&flow) {
+            log::error!("Failed to confirm scheduled flow: {}", e);
+            return;
+        }
+        self.flow_cache.saved(&flow);
+        self.get_category_flows_state(&flow.category_id).mark_for_update();
+        self.dashboard.mark_for_update();
+    }
+
+    /// Confirms every scheduled flow whose date has arrived. Run at startup
+    /// and again whenever the date changes (see `poll_scheduled_flows`), so
+    /// planned flows become real ones without any manual step once the day
+    /// comes.
+    pub fn confirm_due_scheduled_flows(&mut self) {
+        let today = chrono::Local::now().naive_local().date();
+        self.scheduled_flows_confirmed_on = Some(today);
+        let loading = self.db.query(|db| db.load_scheduled_flows());
+        self.when_done(loading, move |app, result| {
+            // Locked in the meantime: tried again once unlocked (see
+            // `poll_scheduled_flows`)
+            if app.locked {
+                app.scheduled_flows_confirmed_on = None;
+                return;
+            }
+            let mut scheduled = match result.and_then(|flows| flows) {
+                Ok(flows) => flows,
+                Err(e) => {
+                    log::error!("Failed to load scheduled flows: {}", e);
+                    return;
+                }
+            };
+            app.pending_changes.apply_to(&mut scheduled);
+            for flow in scheduled.into_iter().filter(|f| f.is_due_as_of(today)) {
+                app.confirm_scheduled_flow(flow);
+            }
+        });
+    }
+
+    /// Loads every unpaid reimbursable expense for the dashboard, whatever
+    /// years it has loaded otherwise.
+    pub fn load_outstanding_reimbursements(&mut self) {
+        let loading = self.db.query(|db| db.load_outstanding_reimbursements());
+        self.when_done(loading, |app, result| {
+            if app.locked {
+                return;
+            }
+            match result.and_then(|flows| flows) {
+                Ok(mut flows) => {
+                    app.pending_changes.apply_to(&mut flows);
+                    app.dashboard.set_outstanding_reimbursements(flows);
+                }
+                Err(e) => log::error!("Failed to load outstanding reimbursements: {}", e),
+            }
+        });
+    }
+
+    /// Makes the system's current timezone the one flow dates are meant in.
+    pub fn use_local_timezone_as_home(&mut self) {
+        self.user_settings.home_utc_offset = Some(crate::utils::local_utc_offset());
+        self.save_settings("save home timezone");
+    }
+
+    /// Confirms scheduled flows again once the date has moved on since
+    /// they were last confirmed.
+    fn poll_scheduled_flows(&mut self) {
+        let today = chrono::Local::now().naive_local().date();
+        if self.scheduled_flows_confirmed_on != Some(today) {
+            self.confirm_due_scheduled_flows();
+        }
+    }
+
+    pub fn is_year_locked(&self, year: i32) -> bool {
+        self.locked_years.contains(&year)
+    }
+
+    /// Whether `flow` is in a locked year, and so can't be edited or
+    /// deleted until that year is unlocked.
+    pub fn is_flow_locked(&self, flow: &Flow) -> bool {
+        self.is_year_locked(flow.date.year())
+    }
+
+    pub fn set_year_locked(&mut self, year: i32, locked: bool) {
+        let action = format!("{} {}", if locked { "lock" } else { "unlock" }, year);
+        self.db.run(action, move |db| if locked { db.lock_year(year) } else { db.unlock_year(year) });
+        if locked {
+            self.locked_years.insert(year);
+        } else {
+            self.locked_years.remove(&year);
+        }
+    }
+
+    /// Saves the current report settings under `name`, replacing any
+    /// template already saved under that name.
+    pub fn save_report_template(&mut self, name: &str) {
+        let (template_name, request) = (name.to_string(), self.report_request.clone());
+        self.db.run(format!("save report template '{}'", name), move |db| db.save_report_template(&template_name, &request));
+        match self.report_templates.binary_search_by(|(existing, _)| existing.as_str().cmp(name)) {
+            Ok(idx) => self.report_templates[idx].1 = self.report_request.clone(),
+            Err(idx) => self.report_templates.insert(idx, (name.to_string(), self.report_request.clone())),
+        }
+    }
+
+    pub fn delete_report_template(&mut self, name: &str) {
+        let template_name = name.to_string();
+        self.db.run(format!("delete report template '{}'", name), move |db| db.delete_report_template(&template_name));
+        self.report_templates.retain(|(existing, _)| existing != name);
+    }
+
+    /// Adds `trip`, or replaces the trip with the same id.
+    pub fn save_trip(&mut self, trip: Trip) {
+        let saved = trip.clone();
+        self.db.run(format!("save trip '{}'", trip.name), move |db| db.save_trip(&saved));
+        self.trips.retain(|t| t.id != trip.id);
+        self.trips.push(trip);
+        self.trips.sort_by(|a, b| (a.start_date, &a.name).cmp(&(b.start_date, &b.name)));
+    }
+
+    /// Deletes the trip and unassigns its flows outside locked years.
+    pub fn delete_trip(&mut self, trip_id: &str) {
+        let id = trip_id.to_string();
+        self.db.run("delete trip", move |db| db.delete_trip(&id));
+        self.trips.retain(|t| t.id != trip_id);
+        let locked_years = &self.locked_years;
+        self.flow_cache.edit_all(|flow| {
+            if flow.trip_id.as_deref() == Some(trip_id) && !locked_years.contains(&flow.date.year()) {
+                flow.trip_id = None;
+            }
+        });
+        if self.report_request.trip_id.as_deref() == Some(trip_id) {
+            self.report_request.trip_id = None;
+        }
+        self.dashboard.mark_for_update();
+    }
+
+    /// Saves `view`, replacing any view already saved under its name, and
+    /// tells `done` whether it was. Fails without saving if the query could
+    /// write or doesn't parse.
+    pub fn save_sql_view(&mut self, view: SqlView, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let saved = view.clone();
+        let pending = self.db.query(move |db| db.save_sql_view(&saved));
+        self.when_done(pending, move |app, result| {
+            let result = result.and_then(|result| result);
+            if result.is_ok() {
+                match app.sql_views.binary_search_by(|existing| existing.name.cmp(&view.name)) {
+                    Ok(idx) => app.sql_views[idx] = view,
+                    Err(idx) => app.sql_views.insert(idx, view),
+                }
+            }
+            done(app, result);
+        });
+    }
+
+    pub fn delete_sql_view(&mut self, name: &str) {
+        let view_name = name.to_string();
+        self.db.run(format!("delete SQL view '{}'", name), move |db| db.delete_sql_view(&view_name));
+        self.sql_views.retain(|view| view.name != name);
+    }
+
+    pub fn add_category(&mut self, mut category: Category) {
+        // New categories go to the end of the list.
+        category.sort_order = self.categories.iter().map(|c| c.sort_order + 1).max().unwrap_or(0);
+        self.categories.push(category.clone());
+        self.category_flows_state.insert(category.id.clone(), CategoryFlowsState::new());
+        let saved = category.clone();
+        self.db.run(format!("save category {}", category.name), move |db| db.save_category(&saved));
+        self.undo_stack.record(Edit::new("Add category").category(None, Some(category)));
+    }
+
+    /// Swaps a category with the nearest shown one above (or below) it and
+    /// saves the new order. Hidden categories are stepped over, so the move
+    /// is always visible in the category selector.
+    pub fn move_category(&mut self, category_id: &str, up: bool) {
+        let Some(pos) = self.categories.iter().position(|c| c.id == category_id) else { return };
+        let shown = |c: &Category| !self.is_category_hidden(&c.id);
+        let target = if up {
+            self.categories[..pos].iter().rposition(shown)
+        } else {
+            self.categories[pos + 1..].iter().position(shown).map(|i| pos + 1 + i)
+        };
+        let Some(target) = target else { return };
+        self.categories.swap(pos, target);
+        for (position, category) in self.categories.iter_mut().enumerate() {
+            category.sort_order = position as i64;
+        }
+        let ids: Vec<String> = self.categories.iter().map(|c| c.id.clone()).collect();
+        self.db.run("save category order", move |db| db.save_category_order(&ids));
+    }
+
+    /// Saves an edited category. Changing its fields migrates the stored
+    /// flows' values (see `Database::save_category_renaming_options`), and
+    /// the loaded flows take the migrated values once they come back,
+    /// apart from flows with changes still pending, which are left as the
+    /// user made them.
+    pub fn update_category(&mut self, category: Category, option_renames: &OptionRenames) {
+        let Some(pos) = self.categories.iter().position(|c| c.id == category.id) else { return };
+        let (saved, renames) = (category.clone(), option_renames.clone());
+        self.db.run(format!("save category {}", category.name), move |db| db.save_category_renaming_options(&saved, &renames));
+        if self.categories[pos].fields != category.fields {
+            let category_id = category.id.clone();
+            let scope = FlowScope { category_id: Some(category_id.clone()), ..FlowScope::default() };
+            let migrated = self.db.query(move |db| db.load_flows_in(&scope));
+            self.when_done(migrated, move |app, migrated| {
+                let migrated = match migrated.and_then(|flows| flows) {
+                    Ok(flows) => flows,
+                    Err(e) => {
+                        log::error!("Failed to reload flows after changing fields: {}", e);
+                        return;
+                    }
+                };
+                let pending: HashSet<&str> = app.pending_changes.changes().iter().map(|change| change.flow().id.as_str()).collect();
+                let mut migrated: HashMap<String, Flow> = migrated.into_iter()
+                    .filter(|flow| !pending.contains(flow.id.as_str()))
+                    .map(|flow| (flow.id.clone(), flow))
+                    .collect();
+                app.flow_cache.edit_all(|flow| {
+                    if let Some(migrated) = migrated.remove(&flow.id) {
+                        flow.custom_fields = migrated.custom_fields;
+                    }
+                });
+                app.get_category_flows_state(&category_id).mark_for_update();
+            });
+        }
+        let before = std::mem::replace(&mut self.categories[pos], category.clone());
+        self.undo_stack.record(Edit::new("Edit category").category(Some(before), Some(category)));
+    }
+
+    /// Saves a batch of flows (e.g. from the CSV import dialog) and adds
+    /// them to memory. Flows that fail to save are logged and skipped rather
+    /// than aborting the rest of the batch. Returns how many were saved.
+    pub fn import_flows(&mut self, flows: Vec<Flow>) -> usize {
+        let mut imported = 0;
+        for flow in flows {
+            if let Err(e) = self.write_flow(&flow) {
+                log::error!("Failed to save imported flow: {}", e);
+                continue;
+            }
+            self.get_category_flows_state(&flow.category_id).mark_for_update();
+            self.flow_cache.saved(&flow);
+            imported += 1;
+        }
+        self.dashboard.mark_for_update();
+        imported
+    }
+
+    /// Imports every settled CSV or OFX file in the watch folder (if one is set)
+    /// and moves it into the folder's `imported/` or `failed/` subfolder,
+    /// adding a notification for each file either way.
+    pub fn scan_watch_folder(&mut self) {
+        self.last_watch_folder_scan = Some(std::time::Instant::now());
+        let Some(folder) = self.user_settings.watch_folder.clone() else { return };
+        let files = match crate::watch_folder::pending_files(std::path::Path::new(&folder), crate::watch_folder::SETTLE_TIME) {
+            Ok(files) => files,
+            Err(e) => {
+                log::warn!("Failed to read watch folder {}: {}", folder, e);
+                return;
+            }
+        };
+
+        let presets = crate::import::ImportPreset::builtin_presets();
+        for path in files {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let prepared = crate::watch_folder::prepare_file(&path, &presets, &self.categories);
+            let (subfolder, message) = match prepared {
+                Ok(import) => {
+                    let found = import.flows.len();
+                    let imported = self.import_flows(import.flows);
+                    let skipped = import.skipped + (found - imported);
+                    (crate::watch_folder::IMPORTED_DIR, format!(
+                        "Imported {} flow(s) from {} ({}){}",
+                        imported,
+                        file_name,
+                        import.preset_name,
+                        if skipped > 0 { format!(", {} row(s) skipped", skipped) } else { String::new() }
+                    ))
+                }
+                Err(e) => (crate::watch_folder::FAILED_DIR, format!("Could not import {}: {}", file_name, e)),
+            };
+            log::info!("{}", message);
+            self.notifications.push(message);
+            if let Err(e) = crate::watch_folder::archive(&path, subfolder) {
+                // Left in place, it would be imported again on the next scan.
+                log::error!("Failed to move {} out of the watch folder: {}", path.display(), e);
+                self.notifications.push(format!(
+                    "Could not move {} into {}/ ({}); move or delete it to avoid importing it again.",
+                    file_name, subfolder, e
+                ));
+            }
+        }
+    }
+
+    /// Rescans the watch folder if `SCAN_INTERVAL` has passed since the
+    /// last scan (or a scan was requested by clearing the timestamp).
+    fn poll_watch_folder(&mut self) {
+        let due = self.last_watch_folder_scan
+            .is_none_or(|last| last.elapsed() >= crate::watch_folder::SCAN_INTERVAL);
+        if due {
+            self.scan_watch_folder();
+        }
+    }
+
+    fn note_input(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| !i.events.is_empty() || i.pointer.is_moving()) {
+            self.last_input = std::time::Instant::now();
+        }
+    }
+
+    /// Locks the app once there's been no input for the user's
+    /// `auto_lock_minutes`, waking up to check when that would be. Only
+    /// with a password set, since there'd be nothing to unlock it with.
+    fn auto_lock_if_idle(&mut self, ctx: &egui::Context) {
+        let Some(minutes) = self.user_settings.auto_lock_minutes else { return };
+        if self.locked || self.demo || !self.encryption_config.is_encryption_ready() {
+            return;
+        }
+        let timeout = std::time::Duration::from_secs(u64::from(minutes) * 60);
+        let idle_for = self.last_input.elapsed();
+        if idle_for >= timeout {
+            info!("Locking after {} minute(s) without input", minutes);
+            self.lock();
+        } else {
+            ctx.request_repaint_after(timeout - idle_for);
+        }
+    }
+
+    /// Drops everything loaded from the database and shows nothing but the
+    /// password prompt until the password is entered again (see
+    /// `verify_password`). Pending changes are kept, out of sight, so no
+    /// saved-for-later work is lost; an open flow editor is closed and the
+    /// undo history cleared.
+    pub fn lock(&mut self) {
+        self.locked = true;
+        self.cancel_flow_edit();
+        // Behind anything already queued, so those writes still seal
+        self.db.run("forget the encryption key", |db| {
+            db.clear_encryption();
+            Ok(())
+        });
+        // The settings can be encrypted too; only how the window looks stays
+        let UserSettings { theme, ui_scale, .. } = std::mem::replace(&mut self.user_settings, UserSettings::new());
+        self.user_settings.theme = theme;
+        self.user_settings.ui_scale = ui_scale;
+        self.stored_totals = StoredTotals::default();
+        self.all_time_totals = AllTimeTotals::default();
+        self.categories.clear();
+        self.forget_flows();
+        self.trips.clear();
+        self.sql_views.clear();
+        self.report_templates.clear();
+        self.category_flows_state.clear();
+        self.undo_stack.clear();
+        // Whatever was waiting on flows is dropped (see `with_flows_in`)
+        self.saving_flow = false;
+        self.undo_in_progress = false;
+        self.deleting = false;
+        self.dashboard.mark_for_update();
+        self.pending_guarded_action = None;
+        self.show_enter_password_dialog();
+    }
+
+    /// Runs one batch of the idle-time integrity check once there's been no
+    /// input for `IDLE_AFTER`, starting a new check every `SCAN_INTERVAL`.
+    /// Any input pauses it until the user is away again.
+    fn poll_integrity_scan(&mut self, ctx: &egui::Context) {
+        use crate::integrity::{IDLE_AFTER, SCAN_INTERVAL};
+
+        if let Some(step) = self.integrity_step.take() {
+            match step.try_take() {
+                Some(stepped) => self.finish_integrity_step(stepped),
+                None => {
+                    self.integrity_step = Some(step);
+                    ctx.request_repaint_after(std::time::Duration::from_millis(50));
+                    return;
+                }
+            }
+        }
+
+        // A backup or restore counts as idle, but the check would only
+        // queue up behind it
+        if self.backup_task.is_some() {
+            return;
+        }
+        let idle_for = self.last_input.elapsed();
+        if idle_for < IDLE_AFTER {
+            ctx.request_repaint_after(IDLE_AFTER - idle_for);
+            return;
+        }
+        if self.integrity_scan.is_none() {
+            match self.last_integrity_scan.map(|last| last.elapsed()) {
+                Some(since) if since < SCAN_INTERVAL => {
+                    ctx.request_repaint_after(SCAN_INTERVAL - since);
+                    return;
+                }
+                _ => self.integrity_scan = Some(IntegrityScan::default()),
+            }
+        }
+
+        let Some(mut scan) = self.integrity_scan.take() else { return };
+        self.integrity_step = Some(self.db.query(move |db| {
+            let stepped = scan.step(db);
+            (scan, stepped)
+        }));
+        // Leave time for input between batches.
+        ctx.request_repaint_after(std::time::Duration::from_millis(50));
+    }
+
+    /// Takes back a batch of the integrity check, reporting what it found
+    /// once the whole check has finished.
+    fn finish_integrity_step(&mut self, stepped: anyhow::Result<(IntegrityScan, Result<bool>)>) {
+        let scan = match stepped.and_then(|(scan, stepped)| stepped.map(|_| scan)) {
+            Ok(scan) => self.integrity_scan.insert(scan),
+            Err(e) => {
+                log::warn!("Integrity check stopped: {}", e);
+                self.integrity_scan = None;
+                self.last_integrity_scan = Some(std::time::Instant::now());
+                return;
+            }
+        };
+        if !scan.is_finished() {
+            return;
+        }
+
+        let problems = std::mem::take(&mut scan.problems);
+        self.integrity_scan = None;
+        self.last_integrity_scan = Some(std::time::Instant::now());
+        if problems.is_empty() {
+            info!("Integrity check found no problems");
+        } else {
+            warn!("Integrity check found {} problem(s)", problems.len());
+            let newly_found = self.integrity_problems.as_ref().is_none_or(|previous| *previous != problems);
+            if newly_found {
+                self.notifications.push(format!(
+                    "Found {} problem(s) in stored data; see Verify Data.",
+                    problems.len()
+                ));
+            }
+        }
+        self.integrity_problems = Some(problems);
+    }
+
+    /// Sets (or with `None`, clears) the watch folder and scans it right away.
+    pub fn set_watch_folder(&mut self, folder: Option<String>) {
+        self.user_settings.watch_folder = folder;
+        self.save_settings("save user settings");
+        self.last_watch_folder_scan = None;
+    }
+
+    /// Saves the edited flows from a reviewed `bulk_edit::preview`,
+    /// returning how many were saved. A flow that fails to save keeps its
+    /// previous contents.
+    pub fn apply_bulk_edit(&mut self, edited: Vec<Flow>) -> usize {
+        let mut saved = 0;
+        for flow in edited {
+            if let Err(e) = self.write_flow(&flow) {
+                log::error!("Failed to save bulk-edited flow: {}", e);
+                continue;
+            }
+            if let Some(old_category_id) = self.flow_cache.find(&flow.id).map(|f| f.category_id.clone()) {
+                self.get_category_flows_state(&old_category_id).mark_for_update();
+            }
+            self.flow_cache.saved(&flow);
+            self.get_category_flows_state(&flow.category_id).mark_for_update();
+            saved += 1;
+        }
+        self.dashboard.mark_for_update();
+        saved
+    }
+
+    /// Saves a flow edited in place in the flows table, with the same
+    /// uniqueness check and undo step as the flow editor's Save, and hands
+    /// `done` the outcome. On error nothing changes, so the cell can stay
+    /// open for another try.
+    pub fn save_inline_edit(&mut self, flow: Flow, done: impl FnOnce(&mut Self, anyhow::Result<()>) + 'static) {
+        self.check_uniqueness(flow, |app, flow, conflict| {
+            let result = conflict.and_then(|conflict| match conflict {
+                Some(conflict) => Err(anyhow::anyhow!(
+                    "{} \"{}\" already has the same {}",
+                    conflict.existing.date,
+                    conflict.existing.description,
+                    conflict.fields.join(", ")
+                )),
+                None => app.store_inline_edit(flow),
+            });
+            done(app, result);
+        });
+    }
+
+    /// The rest of `save_inline_edit`, once the flow has passed its
+    /// category's uniqueness rules.
+    fn store_inline_edit(&mut self, flow: Flow) -> anyhow::Result<()> {
+        self.write_flow(&flow)?;
+        let before = self.flow_cache.find(&flow.id).cloned();
+        self.undo_stack.record(Edit::new("Edit flow").flow(before, Some(flow.clone())));
+        self.get_category_flows_state(&flow.category_id).mark_for_update();
+        self.flow_cache.saved(&flow);
+        self.dashboard.mark_for_update();
+        Ok(())
+    }
+
+    /// Saves `flow` to the database, or with deferred writes on, queues it
+    /// for Save All (see `pending_changes`). Locked years are checked up
+    /// front either way, so a queued change isn't refused only later.
+    fn write_flow(&mut self, flow: &Flow) -> anyhow::Result<()> {
+        self.ensure_flow_unlocked(flow)?;
+        if self.user_settings.deferred_writes {
+            self.pending_changes.save_flow(flow.clone());
+        } else {
+            let flow = flow.clone();
+            self.db.run("save flow", move |db| db.save_flow(&flow));
+        }
+        Ok(())
+    }
+
+    /// The in-memory counterpart of the database's locked-year check: both
+    /// the flow's new date and, for an existing flow, its current one.
+    fn ensure_flow_unlocked(&self, flow: &Flow) -> anyhow::Result<()> {
+        let current_year = self.flow_cache.find(&flow.id).map(|f| f.date.year());
+        for year in std::iter::once(flow.date.year()).chain(current_year) {
+            if self.locked_years.contains(&year) {
+                return Err(crate::db::locked_year_error(year));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes every pending change, reporting the outcome as a
+    /// notification. Whatever the database refuses stays pending, as does
+    /// anything edited while the save was running.
+    pub fn save_pending_changes(&mut self) {
+        if self.saving_pending_changes {
+            return;
+        }
+        self.saving_pending_changes = true;
+        let mut batch = self.pending_changes.clone();
+        let saving = self.db.query(move |db| {
+            let queued = batch.changes().to_vec();
+            let saved = batch.save_all(db);
+            let written = queued[..queued.len() - batch.len()].to_vec();
+            (written, saved)
+        });
+        self.when_done(saving, |app, result| {
+            app.saving_pending_changes = false;
+            let saved = result.and_then(|(written, saved)| {
+                app.pending_changes.forget_written(&written);
+                saved
+            });
+            match saved {
+                Ok(written) => app.notifications.push(format!("Saved {} change(s).", written)),
+                Err(e) => {
+                    log::error!("Failed to save pending changes: {}", e);
+                    app.notifications.push(format!(
+                        "Could not save all changes: {}. {} change(s) are still pending.",
+                        e,
+                        app.pending_changes.len()
+                    ));
+                }
+            }
+        });
+    }
+
+    /// Throws away every pending change and lets go of the loaded flows,
+    /// so the views load them from the database again without the changes.
+    pub fn discard_pending_changes(&mut self) {
+        self.pending_changes.discard();
+        self.undo_stack.clear();
+        self.forget_flows();
+    }
+
+    /// Saves (or refreshes) this week's and this month's metric snapshots.
+    /// Run at startup and on exit, so each period's snapshot ends up
+    /// reflecting the last time the app was open during it.
+    /// The flows are loaded, with pending changes replayed, on the database
+    /// thread.
+    pub fn record_metric_snapshots(&mut self) {
+        let today = chrono::Local::now().naive_local().date();
+        let (categories, pending_changes) = (self.categories.clone(), self.pending_changes.clone());
+        self.db.run("save metric snapshots", move |db| {
+            let mut flows = db.load_flows()?;
+            pending_changes.apply_to(&mut flows);
+            for snapshot in crate::metrics::snapshots_as_of(&flows, &categories, today) {
+                db.save_metric_snapshot(&snapshot)?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Checks every piece of cached state -- each scope in `flow_cache`, the
+    /// categories, each `CategoryFlowsState`, and the dashboard -- against a
+    /// from-scratch recomputation from the database, then rebuilds all of it
+    /// from the database regardless, with any pending changes replayed on
+    /// top (see `PendingChanges::apply_to`). Leaves the discrepancies found
+    /// before the rebuild (empty if everything already agreed) in
+    /// `verification_results` once the database has answered.
+    pub fn verify_cached_state(&mut self) {
+        if self.verifying {
+            return;
+        }
+        self.verifying = true;
+        self.verification_results = None;
+        let loading = self.db.query(|db| (db.load_flows(), db.load_categories()));
+        self.when_done(loading, |app, result| {
+            app.verifying = false;
+            // Locked since, there's nothing loaded to check
+            if app.locked {
+                return;
+            }
+            let (stored_flows, stored_categories) = match result {
+                Ok(stored) => stored,
+                Err(e) => (Err(anyhow::anyhow!("{}", e)), Err(e)),
+            };
+            let found = app.compare_cached_state(stored_flows, stored_categories);
+            app.verification_results = Some(found);
+        });
+    }
+
+    /// The rest of `verify_cached_state`, once the stored flows and
+    /// categories have been loaded.
+    fn compare_cached_state(&mut self, stored_flows: Result<Vec<Flow>>, stored_categories: Result<Vec<Category>>) -> Vec<String> {
+        let today = chrono::Local::now().naive_local().date();
+        let mut found = Vec::new();
+
+        // Pending changes (with deferred writes on) are meant to differ from
+        // the database, so they're replayed over the stored flows first.
+        let stored_flows = match stored_flows {
+            Ok(mut flows) => {
+                self.pending_changes.apply_to(&mut flows);
+                Some(flows)
+            }
+            Err(e) => {
+                found.push(format!("Failed to load flows from the database: {}", e));
+                None
+            }
+        };
+        if let Some(stored_flows) = &stored_flows {
+            for (scope, cached) in self.flow_cache.iter() {
+                // Only a page itself tells which flows are on it
+                let stored: Vec<Flow> = stored_flows.iter()
+                    .filter(|flow| scope.contains(flow) && (!scope.is_paged() || cached.iter().any(|f| f.id == flow.id)))
+                    .cloned()
+                    .collect();
+                // A flow in several scopes is reported once
+                for discrepancy in crate::utils::flow_discrepancies(cached, &stored) {
+                    if !found.contains(&discrepancy) {
+                        found.push(discrepancy);
+                    }
+                }
+            }
+        }
+
+        let stored_categories = match stored_categories {
+            Ok(categories) => Some(categories),
+            Err(e) => {
+                found.push(format!("Failed to load categories from the database: {}", e));
+                None
+            }
+        };
+        if let Some(stored_categories) = &stored_categories {
+            for category in &self.categories {
+                if !stored_categories.iter().any(|c| c.id == category.id) {
+                    found.push(format!("Category {} is in memory but not in the database", category.name));
+                }
+            }
+            for category in stored_categories {
+                if !self.categories.iter().any(|c| c.id == category.id) {
+                    found.push(format!("Category {} is in the database but not in memory", category.name));
+                }
+            }
+        }
+
+        // Derived state is checked against the stored flows, so a bad cache
+        // is reported separately from bad loaded flows.
+        if let Some(stored_flows) = &stored_flows {
+            for category in &self.categories {
+                match self.category_flows_state.get(&category.id) {
+                    Some(state) => found.extend(state.discrepancies_as_of(stored_flows, category, today)),
+                    None => found.push(format!("{}: no cached totals state", category.name)),
+                }
+            }
+            found.extend(self.dashboard.discrepancies_as_of(stored_flows, &self.categories, today));
+        }
+
+        // Rebuild from scratch.
+        self.forget_flows();
+        if let Some(stored_categories) = stored_categories {
+            self.categories = stored_categories;
+        }
+        self.category_flows_state.retain(|id, _| self.categories.iter().any(|c| c.id == *id));
+        for category in &self.categories {
+            self.category_flows_state.entry(category.id.clone())
+                .or_insert_with(CategoryFlowsState::new)
+                .mark_for_update();
+        }
+        self.dashboard.mark_for_update();
+
+        if found.is_empty() {
+            info!("Cache verification found no discrepancies");
+        } else {
+            warn!("Cache verification found {} discrepancies", found.len());
+        }
+        found
+    }
+
+    pub fn get_category_flows_state(&mut self, category_id: &str) -> &mut CategoryFlowsState {
+        self.category_flows_state
+            .entry(category_id.to_string())
             .or_insert_with(CategoryFlowsState::new)
     }
 
+    /// The flows in `scope`, newest first and with pending changes replayed
+    /// over them, or `None` while they're loaded on the database thread. A
+    /// scope that failed to load is tried again once the database changes.
+    pub fn flows_in(&mut self, scope: &FlowScope) -> Option<Arc<Vec<Flow>>> {
+        if let Some(flows) = self.flow_cache.get(scope) {
+            return Some(flows);
+        }
+        if self.locked || self.failed_scopes.get(scope) == Some(&self.db.change_count()) {
+            return None;
+        }
+        if self.loading_scopes.insert(scope.clone()) {
+            self.load_scope(scope.clone());
+        }
+        None
+    }
+
+    /// Loads `scope` into `flow_cache`, starting over if a flow was saved
+    /// or deleted in the meantime, since the load may have missed it.
+    fn load_scope(&mut self, scope: FlowScope) {
+        let generation = self.flow_cache.generation();
+        let query = scope.clone();
+        let loading = self.db.query(move |db| db.load_flows_in(&query));
+        self.when_done(loading, move |app, result| {
+            // Dropped by a lock or a reload since
+            if !app.loading_scopes.contains(&scope) {
+                return;
+            }
+            if app.flow_cache.generation() != generation {
+                return app.load_scope(scope);
+            }
+            app.loading_scopes.remove(&scope);
+            match result.and_then(|flows| flows) {
+                Ok(flows) => {
+                    let flows = app.with_pending_changes(&scope, flows);
+                    match &scope.category_id {
+                        Some(category_id) => app.get_category_flows_state(category_id).mark_for_update(),
+                        None => app.category_flows_state.values_mut().for_each(CategoryFlowsState::mark_for_update),
+                    }
+                    app.dashboard.mark_for_update();
+                    app.flow_cache.insert(scope, flows);
+                }
+                Err(e) => {
+                    log::error!("Failed to load flows: {}", e);
+                    app.failed_scopes.insert(scope, app.db.change_count());
+                }
+            }
+        });
+    }
+
+    /// `flows` as stored for `scope`, with the pending changes replayed
+    /// over them and whatever they moved out of it taken out.
+    fn with_pending_changes(&self, scope: &FlowScope, mut flows: Vec<Flow>) -> Vec<Flow> {
+        self.pending_changes.apply_to(&mut flows);
+        flows.retain(|flow| scope.contains(flow));
+        flows
+    }
+
+    /// Hands the flows in `scope` to `then`, for an action that needs all
+    /// of them (a delete, a uniqueness check): right away if a view has
+    /// loaded them, otherwise on the frame the database thread answers.
+    /// Starts over if a flow was saved or deleted in the meantime, and is
+    /// dropped if the app is locked before then.
+    fn with_flows_in(&mut self, scope: FlowScope, then: impl FnOnce(&mut Self, anyhow::Result<Arc<Vec<Flow>>>) + 'static) {
+        if let Some(flows) = self.flow_cache.get(&scope) {
+            return then(self, Ok(flows));
+        }
+        let generation = self.flow_cache.generation();
+        let query = scope.clone();
+        let loading = self.db.query(move |db| db.load_flows_in(&query));
+        self.when_done(loading, move |app, result| {
+            if app.locked {
+                return;
+            }
+            if app.flow_cache.generation() != generation {
+                return app.with_flows_in(scope, then);
+            }
+            let flows = result.and_then(|flows| flows).map(|flows| {
+                let flows = app.with_pending_changes(&scope, flows);
+                app.flow_cache.insert(scope, flows)
+            });
+            then(app, flows);
+        });
+    }
+
+    /// What the category totals are summed over while `stored_totals` can't
+    /// sum them (see `StoredTotals::needs_fallback`): this year's and last
+    /// year's flows, as far back as the totals and trends go. Nothing is
+    /// loaded otherwise, and the flows are empty.
+    pub fn totals_fallback(&mut self) -> Option<Arc<Vec<Flow>>> {
+        if !self.stored_totals.needs_fallback() {
+            return Some(Arc::default());
+        }
+        let year = chrono::Local::now().year();
+        self.flows_in(&FlowScope::years(year - 1..=year))
+    }
+
+    /// Lets go of every loaded flow, for when the database changed under
+    /// them; the views load theirs again.
+    fn forget_flows(&mut self) {
+        self.flow_cache.clear();
+        self.loading_scopes.clear();
+        self.failed_scopes.clear();
+        self.category_flows_state.values_mut().for_each(CategoryFlowsState::mark_for_update);
+        self.dashboard.mark_for_update();
+    }
+
+    /// Everything needed to move to another computer (or hand over): an
+    /// unencrypted copy of the database, every flow as CSV, the categories,
+    /// and the settings. Meant to be sealed with `export_bundle::seal`, so
+    /// the database copy is deliberately a decrypted, portable one. The
+    /// files are gathered on the database thread and handed to `done`.
+    pub fn export_bundle_files(&mut self, done: impl FnOnce(&mut Self, Result<Vec<crate::export_bundle::BundleFile>>) + 'static) {
+        use crate::export_bundle::BundleFile;
+        use crate::reporting::{ReportCategoryInfo, ReportFormat, ReportGenerator, TimePeriod};
+
+        let temp_path = std::env::temp_dir().join(format!(
+            "preft_export_tmp_{}_{}.db",
+            std::process::id(),
+            chrono::Local::now().format("%Y%m%d%H%M%S"),
+        ));
+        let report_categories = self.categories.iter()
+            .map(|cat| (cat.id.clone(), ReportCategoryInfo::from(cat)
+                .with_number_format(self.user_settings.category_number_formats.get(&cat.id).cloned())))
+            .collect();
+        let category_order = self.categories.iter().map(|cat| cat.id.clone()).collect();
+        let pending_changes = self.pending_changes.clone();
+        let categories = serde_json::to_vec_pretty(&self.categories);
+        let settings = serde_json::to_vec_pretty(&self.user_settings);
+        let views = self.sql_views.clone();
+
+        let gathering = self.db.query(move |db| -> Result<Vec<BundleFile>> {
+            let backup = db.backup_to_file(&temp_path, false)
+                .and_then(|()| Ok(std::fs::read(&temp_path)?));
+            let _ = std::fs::remove_file(&temp_path); // best-effort cleanup
+            let database = backup?;
+
+            let mut flows = db.load_flows()?;
+            pending_changes.apply_to(&mut flows);
+            let report = ReportGenerator::new(flows, report_categories, category_order);
+            let request = ReportRequest {
+                time_period: TimePeriod::Custom(chrono::NaiveDate::MIN, chrono::NaiveDate::MAX),
+                output_format: ReportFormat::Csv,
+                ..ReportRequest::default()
+            };
+            let flows_csv = report.generate_report(&request)
+                .map_err(|e| anyhow::anyhow!("Failed to export flows: {}", e))?;
+
+            let mut files = vec![
+                BundleFile { name: "preft.db".to_string(), contents: database },
+                BundleFile { name: "flows.csv".to_string(), contents: flows_csv },
+                BundleFile { name: "categories.json".to_string(), contents: categories? },
+                BundleFile { name: "settings.json".to_string(), contents: settings? },
+            ];
+            for view in &views {
+                let rows = db.run_sql_view(&view.sql)
+                    .map_err(|e| anyhow::anyhow!("Failed to run SQL view '{}': {}", view.name, e))?;
+                files.push(BundleFile { name: crate::ui::sql_views_dialog::csv_file_name(&view.name), contents: rows.to_csv().into_bytes() });
+            }
+            Ok(files)
+        });
+        self.when_done(gathering, |app, result| done(app, result.and_then(|files| files)));
+    }
+
     pub fn create_backup(&mut self) {
         if self.backup_in_progress {
             return;
@@ -360,41 +2528,56 @@ impl PreftApp {
         self.backup_status = Some("Selecting backup location...".to_string());
 
         // Show file dialog for backup location
-        let Some(dest_path) = rfd::FileDialog::new()
+        let choice = pick_path_in_background(rfd::AsyncFileDialog::new()
             .set_title("Save Backup As")
-            .set_file_name(&format!("preft_backup_{}.db", chrono::Local::now().format("%Y%m%d_%H%M%S")))
+            .set_file_name(format!("preft_backup_{}.db", chrono::Local::now().format("%Y%m%d_%H%M%S")))
             .add_filter("SQLite Database", &["db"])
             .add_filter("All Files", &["*"])
-            .save_file()
-        else {
-            self.backup_status = Some("Backup cancelled".to_string());
-            self.backup_in_progress = false;
-            return;
-        };
+            .save_file());
+        self.backup_task = Some(BackupTask::ChoosingDestination(choice));
+    }
 
+    /// Starts copying the database for a backup to `dest_path`, once the
+    /// user has picked it.
+    fn start_backup(&mut self, dest_path: std::path::PathBuf) {
         self.backup_status = Some("Creating backup...".to_string());
 
-        // Determine if we should create encrypted or unencrypted backup
-        let encrypted_backup = self.db.is_encrypted();
-
-        // The actual SQLite copy needs live access to `self.db` (it reads
-        // the in-memory encryption key for an unencrypted/decrypted backup),
-        // so it can't safely run on another thread without a much bigger
-        // refactor -- but it's local-disk-to-local-disk on a personal-
-        // finance-sized database, so it's fast. Write it to a local temp
-        // file first, then hand that off to a background thread to move
-        // into place. That move is the part that can actually be slow (the
-        // user picked destination could be a network drive or a USB stick),
-        // and it touches nothing but plain files, so it's safe to run off
-        // the UI thread without touching `self.db` at all.
+        // The SQLite copy runs on the database thread (it reads the
+        // in-memory encryption key for an unencrypted/decrypted backup).
+        // Write it to a local temp file first, then hand that off to a
+        // background thread to move into place. That move is the part that
+        // can actually be slow (the user picked destination could be a
+        // network drive or a USB stick), and it touches nothing but plain
+        // files, so it's safe to run without touching `self.db` at all.
         let temp_path = std::env::temp_dir().join(format!(
             "preft_backup_tmp_{}_{}.db",
             std::process::id(),
             chrono::Local::now().format("%Y%m%d%H%M%S"),
         ));
+        let progress = Arc::new(BackupProgress::default());
+        let result = {
+            let temp_path = temp_path.clone();
+            let progress = Arc::clone(&progress);
+            self.db.query(move |db| {
+                // An encrypted database gets an encrypted backup.
+                let encrypted = db.is_encrypted();
+                db.backup_to_file_with_progress(&temp_path, encrypted, &progress).map(|()| encrypted)
+            })
+        };
+        self.backup_task = Some(BackupTask::Writing { temp_path, dest_path, progress, result });
+    }
 
-        match self.db.backup_to_file(&temp_path, encrypted_backup) {
-            Ok(()) => {
+    /// Moves a finished copy into place on a background thread, or records
+    /// why the copy failed.
+    fn finish_backup_copy(
+        &mut self,
+        temp_path: std::path::PathBuf,
+        dest_path: std::path::PathBuf,
+        result: anyhow::Result<bool>,
+        cancelled: bool,
+    ) {
+        match result {
+            Ok(encrypted_backup) => {
                 self.backup_status = Some("Finishing backup...".to_string());
 
                 let (tx, rx) = std::sync::mpsc::channel();
@@ -423,6 +2606,11 @@ impl PreftApp {
                     let _ = tx.send(outcome);
                 });
             }
+            Err(_) if cancelled => {
+                let _ = std::fs::remove_file(&temp_path); // best-effort cleanup
+                self.backup_status = Some("Backup cancelled".to_string());
+                self.backup_in_progress = false;
+            }
             Err(e) => {
                 let _ = std::fs::remove_file(&temp_path); // may not exist; best-effort
 
@@ -434,9 +2622,7 @@ impl PreftApp {
                     error_message: Some(e.to_string()),
                 };
                 self.user_settings.add_backup_entry(entry);
-                if let Err(e) = self.db.save_user_settings(&self.user_settings) {
-                    log::error!("Failed to save backup history: {}", e);
-                }
+                self.save_settings("save backup history");
 
                 self.backup_status = Some(format!("Backup failed: {}", e));
                 self.backup_in_progress = false;
@@ -444,6 +2630,54 @@ impl PreftApp {
         }
     }
 
+    /// Notes where the window is, to be saved on exit (see
+    /// `UserSettings::window`). egui reports it in UI points, so it's scaled
+    /// back up by the zoom factor to the logical pixels the window is made
+    /// with at startup.
+    fn track_window_geometry(&mut self, ctx: &egui::Context) {
+        let zoom = ctx.zoom_factor();
+        let (inner, outer, maximized, minimized) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.inner_rect, viewport.outer_rect, viewport.maximized.unwrap_or(false), viewport.minimized.unwrap_or(false))
+        });
+        if minimized {
+            return;
+        }
+        // Keep the size and position it had before being maximized.
+        if maximized && let Some(window) = &mut self.window_geometry {
+            window.maximized = true;
+            return;
+        }
+        if let (Some(inner), Some(outer)) = (inner, outer) {
+            self.window_geometry = Some(WindowGeometry {
+                x: outer.min.x * zoom,
+                y: outer.min.y * zoom,
+                width: inner.width() * zoom,
+                height: inner.height() * zoom,
+                maximized,
+            });
+        }
+    }
+
+    /// Saves the UI scale once a change takes effect, whether it was made in
+    /// the settings or with egui's Ctrl +/- zoom. Waits for the saved scale
+    /// to be applied at startup before tracking changes.
+    fn remember_ui_scale(&mut self, ctx: &egui::Context) {
+        if self.read_only {
+            return;
+        }
+        let zoom = ctx.zoom_factor();
+        match self.ui_scale_seen {
+            None if zoom == self.user_settings.get_ui_scale() => self.ui_scale_seen = Some(zoom),
+            Some(seen) if seen != zoom => {
+                self.ui_scale_seen = Some(zoom);
+                self.user_settings.ui_scale = (zoom != 1.0).then_some(zoom);
+                self.save_settings("save UI scale");
+            }
+            _ => {}
+        }
+    }
+
     /// Checks whether a manual backup's background move-into-place (started
     /// by `create_backup`) has finished, and if so, finalizes the backup
     /// history entry and clears `backup_in_progress`. Called once per frame
@@ -477,9 +2711,7 @@ impl PreftApp {
         if outcome.error.is_none() {
             self.user_settings.set_last_backup_path(outcome.dest_path.to_string_lossy().to_string());
         }
-        if let Err(e) = self.db.save_user_settings(&self.user_settings) {
-            log::error!("Failed to save backup history: {}", e);
-        }
+        self.save_settings("save backup history");
 
         self.backup_status = Some(match &outcome.error {
             None => format!(
@@ -501,154 +2733,685 @@ impl PreftApp {
         self.backup_status = Some("Selecting backup file...".to_string());
 
         // Show file dialog for backup file
-        if let Some(path) = rfd::FileDialog::new()
+        let choice = pick_path_in_background(rfd::AsyncFileDialog::new()
             .set_title("Select Backup File")
             .add_filter("SQLite Database", &["db"])
             .add_filter("All Files", &["*"])
-            .pick_file()
-        {
-            self.backup_status = Some("Restoring backup...".to_string());
+            .pick_file());
+        self.backup_task = Some(BackupTask::ChoosingSource(choice));
+    }
+
+    /// Starts restoring from `path`, once the user has picked it.
+    fn start_restore(&mut self, path: std::path::PathBuf) {
+        self.backup_status = Some("Restoring backup...".to_string());
 
+        let encryption_ready = self.encryption_config.is_encryption_ready();
+        let progress = Arc::new(BackupProgress::default());
+        let task_progress = Arc::clone(&progress);
+        let result = self.db.query(move |db| {
             // Try to detect if the backup is encrypted
-            let is_encrypted_backup = match self.db.detect_encrypted_backup(&path) {
-                Ok(encrypted) => encrypted,
-                Err(_) => false, // Assume unencrypted if we can't detect
-            };
+            // Assume unencrypted if we can't detect
+            let is_encrypted_backup = db.detect_encrypted_backup(&path).unwrap_or(false);
 
-            let result = if is_encrypted_backup {
+            if is_encrypted_backup {
                 // For encrypted backups, we need the password
-                if !self.encryption_config.is_encryption_ready() {
+                if !encryption_ready {
                     Err(anyhow::anyhow!("Encrypted backup detected but no password is set. Please set a password first."))
                 } else {
                     // For now, we'll use a simple approach - if the backup is encrypted and we have encryption set up,
                     // we'll try to restore it. In a real implementation, you might want to prompt the user for the password.
                     // For now, we'll assume the current password works (this is a simplification)
-                    self.db.restore_from_file(&path, None, true) // Force unencrypted restore for now
+                    db.restore_from_file_with_progress(&path, None, true, &task_progress) // Force unencrypted restore for now
                 }
             } else {
                 // For unencrypted backups, restore as unencrypted
-                self.db.restore_from_file(&path, None, false)
-            };
+                db.restore_from_file_with_progress(&path, None, false, &task_progress)
+            }
+        });
+        self.backup_task = Some(BackupTask::Restoring { progress, result });
+    }
 
-            match result {
-                Ok(_) => {
-                    // Reload all data from the restored database
-                    self.categories = self.db.load_categories()
-                        .unwrap_or_else(|e| { log::error!("Failed to load categories: {}", e); Vec::new() });
-                    self.flows = self.db.load_flows()
-                        .unwrap_or_else(|e| { log::error!("Failed to load flows: {}", e); Vec::new() });
-                    self.user_settings = self.db.load_user_settings()
-                        .unwrap_or_else(|e| { log::error!("Failed to load user settings: {}", e); UserSettings::new() });
-
-                    // Update UI components to reflect the restored data
-                    self.dashboard.mark_for_update();
-                    
-                    // Update category flows states
-                    self.category_flows_state.clear();
-                    for category in &self.categories {
-                        self.category_flows_state.insert(category.id.clone(), crate::ui::category_flows::CategoryFlowsState::new());
-                    }
+    fn finish_restore(&mut self, result: anyhow::Result<()>, cancelled: bool) {
+        match result {
+            Ok(_) => {
+                self.reload_from_db();
+                self.backup_status = Some("Backup restored successfully!".to_string());
+            }
+            Err(_) if cancelled => {
+                self.backup_status = Some("Restore cancelled; nothing was changed".to_string());
+            }
+            Err(e) => {
+                self.backup_status = Some(format!("Restore failed: {}", e));
+            }
+        }
+        self.backup_in_progress = false;
+    }
+
+    /// Rebuilds the database file so the space deleted flows used is given
+    /// back, in the background like a backup.
+    pub fn compact_database(&mut self) {
+        if self.backup_in_progress {
+            return;
+        }
+
+        self.backup_in_progress = true;
+        self.backup_status = Some("Compacting database...".to_string());
+        let progress = Arc::new(BackupProgress::default());
+        let task_progress = Arc::clone(&progress);
+        let result = self.db.query(move |db| db.compact(&task_progress));
+        self.backup_task = Some(BackupTask::Compacting { progress, result });
+    }
+
+    fn finish_compact(&mut self, result: anyhow::Result<CompactReport>, cancelled: bool) {
+        self.backup_status = Some(match result {
+            Ok(report) => format!(
+                "Database compacted: {:.1} KB reclaimed ({:.1} KB now)",
+                report.reclaimed() as f64 / 1024.0,
+                report.size_after as f64 / 1024.0
+            ),
+            Err(_) if cancelled => "Compacting cancelled".to_string(),
+            Err(e) => format!("Compacting failed: {}", e),
+        });
+        self.backup_in_progress = false;
+    }
 
-                    self.backup_status = Some("Backup restored successfully!".to_string());
+    /// Moves the manual backup, restore or compact in `backup_task` along once its
+    /// file dialog or database work is done. Called once per frame from
+    /// `update()`.
+    pub fn poll_backup_task(&mut self) {
+        let Some(task) = self.backup_task.take() else { return };
+        match task {
+            BackupTask::ChoosingDestination(choice) => match choice.try_recv() {
+                Ok(Some(dest_path)) => self.start_backup(dest_path),
+                Ok(None) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.backup_status = Some("Backup cancelled".to_string());
+                    self.backup_in_progress = false;
                 }
-                Err(e) => {
-                    self.backup_status = Some(format!("Restore failed: {}", e));
+                Err(std::sync::mpsc::TryRecvError::Empty) => self.backup_task = Some(BackupTask::ChoosingDestination(choice)),
+            },
+            BackupTask::ChoosingSource(choice) => match choice.try_recv() {
+                Ok(Some(path)) => self.start_restore(path),
+                Ok(None) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    self.backup_status = Some("Restore cancelled".to_string());
+                    self.backup_in_progress = false;
                 }
-            }
-        } else {
-            self.backup_status = Some("Restore cancelled".to_string());
+                Err(std::sync::mpsc::TryRecvError::Empty) => self.backup_task = Some(BackupTask::ChoosingSource(choice)),
+            },
+            BackupTask::Writing { temp_path, dest_path, progress, result } => match result.try_take() {
+                Some(outcome) => {
+                    self.finish_backup_copy(temp_path, dest_path, outcome.and_then(|r| r), progress.is_cancelled());
+                }
+                None => self.backup_task = Some(BackupTask::Writing { temp_path, dest_path, progress, result }),
+            },
+            BackupTask::Restoring { progress, result } => match result.try_take() {
+                Some(outcome) => self.finish_restore(outcome.and_then(|r| r), progress.is_cancelled()),
+                None => self.backup_task = Some(BackupTask::Restoring { progress, result }),
+            },
+            BackupTask::Compacting { progress, result } => match result.try_take() {
+                Some(outcome) => self.finish_compact(outcome.and_then(|r| r), progress.is_cancelled()),
+                None => self.backup_task = Some(BackupTask::Compacting { progress, result }),
+            },
         }
+    }
 
-        self.backup_in_progress = false;
+    /// How far the database has got with a backup, restore or compact,
+    /// while it's working.
+    pub fn backup_progress(&self) -> Option<f32> {
+        self.backup_task_progress().map(|progress| progress.fraction())
+    }
+
+    /// Stops a backup, restore or compact at its next checkpoint. A stopped
+    /// restore leaves the data as it was.
+    pub fn cancel_backup_task(&self) {
+        if let Some(progress) = self.backup_task_progress() {
+            progress.cancel();
+        }
+    }
+
+    fn backup_task_progress(&self) -> Option<&BackupProgress> {
+        match &self.backup_task {
+            Some(
+                BackupTask::Writing { progress, .. }
+                | BackupTask::Restoring { progress, .. }
+                | BackupTask::Compacting { progress, .. },
+            ) => Some(progress),
+            _ => None,
+        }
+    }
+
+    /// Replaces everything loaded from the database with what it holds now,
+    /// after a restore replaced its contents or an unlock made them readable.
+    /// While locked nothing is reloaded: unlocking loads everything anyway.
+    fn reload_from_db(&mut self) {
+        if self.locked {
+            return;
+        }
+        let loading = self.db.query(StoredData::load);
+        self.when_done(loading, |app, result| match result {
+            Ok(_) if app.locked => {}
+            Ok(stored) => app.apply_stored_data(stored),
+            Err(e) => log::error!("Failed to reload from the database: {}", e),
+        });
+    }
+
+    /// Replaces everything loaded from the database with `stored`, and lets
+    /// go of the loaded flows for the views to load again.
+    fn apply_stored_data(&mut self, stored: StoredData) {
+        let StoredData { categories, user_settings, locked_years, report_templates, trips, sql_views, migration_summary } = stored;
+        self.categories = categories
+            .unwrap_or_else(|e| { log::error!("Failed to load categories: {}", e); Vec::new() });
+        self.forget_flows();
+        self.user_settings = user_settings
+            .unwrap_or_else(|e| { log::error!("Failed to load user settings: {}", e); UserSettings::new() });
+        self.locked_years = locked_years
+            .unwrap_or_else(|e| { log::error!("Failed to load locked years: {}", e); Vec::new() })
+            .into_iter().collect();
+        self.report_templates = report_templates
+            .unwrap_or_else(|e| { log::error!("Failed to load report templates: {}", e); Vec::new() });
+        self.trips = trips
+            .unwrap_or_else(|e| { log::error!("Failed to load trips: {}", e); Vec::new() });
+        self.sql_views = sql_views
+            .unwrap_or_else(|e| { log::error!("Failed to load SQL views: {}", e); Vec::new() });
+
+        // Update UI components to reflect the new data
+        self.dashboard.mark_for_update();
+
+        // Update category flows states
+        self.category_flows_state.clear();
+        for category in &self.categories {
+            self.category_flows_state.insert(category.id.clone(), crate::ui::category_flows::CategoryFlowsState::new());
+        }
+
+        self.undo_stack.clear();
+        self.stored_totals = StoredTotals::default();
+        self.all_time_totals = AllTimeTotals::default();
+        self.migration_summary = migration_summary;
     }
 
     pub fn clear_backup_status(&mut self) {
         self.backup_status = None;
     }
 
+    /// Builds the "in case of emergency" PDF and saves it wherever the user
+    /// picks, reporting the outcome in the backup status line.
+    pub fn save_emergency_document(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Emergency Document")
+            .set_file_name("preft_in_case_of_emergency.pdf")
+            .add_filter("PDF", &["pdf"])
+            .save_file()
+        else {
+            return;
+        };
+
+        let database = self.db.query(|db| (
+            db.get_database_path().ok().map(|p| p.to_string_lossy().to_string()),
+            db.is_encrypted(),
+            db.load_flows(),
+        ));
+        self.when_done(database, move |app, database| {
+            let result = database.map_err(|e| e.to_string()).and_then(|(database_path, db_encrypted, flows)| {
+                let mut flows = flows.map_err(|e| e.to_string())?;
+                app.pending_changes.apply_to(&mut flows);
+                let document = crate::emergency::EmergencyDocument::build(
+                    &flows,
+                    &app.categories,
+                    &app.user_settings,
+                    database_path,
+                    app.encryption_config.is_encryption_ready() || db_encrypted,
+                    chrono::Local::now().date_naive(),
+                );
+                document.to_pdf(&app.user_settings.number_format)
+                    .map_err(|e| e.to_string())
+                    .and_then(|pdf| std::fs::write(&path, pdf).map_err(|e| e.to_string()))
+            });
+            app.backup_status = Some(match result {
+                Ok(()) => format!("Emergency document saved to {}", path.display()),
+                Err(e) => {
+                    log::error!("Failed to save emergency document: {}", e);
+                    format!("Failed to save emergency document: {}", e)
+                }
+            });
+        });
+    }
+
     // Password management methods
     pub fn show_set_password_dialog(&mut self) {
         self.password_dialog_mode = PasswordDialogMode::SetPassword;
-        self.password_input.clear();
-        self.password_confirm.clear();
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
+        self.show_password_dialog = true;
+    }
+
+    pub fn show_encrypt_file_dialog(&mut self) {
+        self.password_dialog_mode = PasswordDialogMode::EncryptFile;
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
+        self.clear_encryption_status();
         self.show_password_dialog = true;
     }
 
     pub fn show_enter_password_dialog(&mut self) {
         self.password_dialog_mode = PasswordDialogMode::EnterPassword;
-        self.password_input.clear();
-        self.password_confirm.clear();
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
         self.show_password_dialog = true;
     }
 
     pub fn show_change_password_dialog(&mut self) {
         self.password_dialog_mode = PasswordDialogMode::ChangePassword;
-        self.password_input.clear();
-        self.password_confirm.clear();
+        self.current_password_input.zeroize();
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
+        self.show_password_dialog = true;
+    }
+
+    pub fn show_encrypted_columns_dialog(&mut self) {
+        self.password_dialog_mode = PasswordDialogMode::EncryptedColumns;
+        self.encrypted_columns_draft = self.encryption_config.encrypted_columns.clone();
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
+        self.clear_encryption_status();
         self.show_password_dialog = true;
     }
 
     pub fn show_disable_encryption_dialog(&mut self) {
         self.password_dialog_mode = PasswordDialogMode::DisableEncryption;
-        self.password_input.clear();
-        self.password_confirm.clear();
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
         self.show_password_dialog = true;
     }
 
-    pub fn set_password(&mut self, password: &str) -> Result<(), anyhow::Error> {
-        // Set password in encryption config (this will generate salt and hash)
-        self.encryption_config.set_password(password)?;
-        
-        // Initialize encryption in database
-        self.db.initialize_encryption(password)?;
-        
-        self.encryption_status = Some("Password set successfully".to_string());
-        Ok(())
+    /// Whether guarded actions ask for the password. Strict mode has no
+    /// effect until a password is set.
+    pub fn is_strict_mode_active(&self) -> bool {
+        self.user_settings.strict_mode && self.encryption_config.is_encryption_ready()
     }
 
-    pub fn verify_password(&mut self, password: &str) -> Result<bool, anyhow::Error> {
-        let is_valid = self.encryption_config.verify_password(password);
-        
-        if is_valid {
-            // Initialize encryption with the correct password
-            let salt = self.encryption_config.get_salt()
-                .ok_or_else(|| anyhow::anyhow!("Salt not found"))?;
-            self.db.set_encryption_state(true, Some(password), Some(salt))?;
-            self.encryption_status = Some("Password verified successfully".to_string());
-        } else {
+    /// Runs `action`, or in strict mode holds it and opens the password
+    /// dialog; `confirm_guarded_action` runs it once the password matches.
+    pub fn request_guarded_action(&mut self, action: GuardedAction) {
+        if !self.is_strict_mode_active() {
+            self.run_guarded_action(action);
+            return;
+        }
+        self.password_dialog_mode = PasswordDialogMode::ConfirmAction;
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
+        self.clear_encryption_status();
+        self.pending_guarded_action = Some(action);
+        self.show_password_dialog = true;
+    }
+
+    /// Runs the held action if `password` is the database password.
+    /// Returns whether it matched.
+    pub fn confirm_guarded_action(&mut self, password: &str) -> bool {
+        if !self.encryption_config.verify_password(password) {
             self.encryption_status = Some("Incorrect password".to_string());
+            return false;
         }
-        
-        Ok(is_valid)
+        if let Some(action) = self.pending_guarded_action.take() {
+            self.run_guarded_action(action);
+        }
+        true
     }
 
-    pub fn change_password(&mut self, new_password: &str) -> Result<(), anyhow::Error> {
-        // Set the new password (this will update the hash and salt)
-        self.set_password(new_password)?;
-        self.encryption_status = Some("Password changed successfully".to_string());
-        Ok(())
+    fn run_guarded_action(&mut self, action: GuardedAction) {
+        match action {
+            GuardedAction::DeleteCategory(category_id) => self.delete_category(category_id),
+            GuardedAction::DeleteFlows(flow_ids) => {
+                if let Err(e) = self.delete_flows(&flow_ids) {
+                    log::error!("Failed to delete selected flows: {}", e);
+                    self.notifications.push(format!("The selected flows were not deleted: {}", e));
+                }
+            }
+            GuardedAction::RestoreBackup => self.restore_backup(),
+            GuardedAction::TurnOffStrictMode => {
+                self.user_settings.strict_mode = false;
+                self.save_settings("save settings");
+            }
+        }
     }
 
-    pub fn disable_encryption(&mut self) -> Result<(), anyhow::Error> {
-        // Disable encryption in the config
-        self.encryption_config.disable_encryption()?;
-        
-        // Disable encryption in the database
-        self.db.set_encryption_state(false, None, None)?;
+    /// Runs an encryption change on the database thread, with
+    /// `encryption_busy` set until `done` has its result.
+    fn encryption_job<T: Send + 'static>(
+        &mut self,
+        job: impl FnOnce(&mut Database) -> Result<T> + Send + 'static,
+        done: impl FnOnce(&mut Self, Result<T>) + 'static,
+    ) {
+        self.encryption_busy = true;
+        let pending = self.db.query(job);
+        self.when_done(pending, |app, result| {
+            app.encryption_busy = false;
+            done(app, result.and_then(|result| result));
+        });
+    }
+
+    /// Hands the keystore's current state to the database thread.
+    fn send_encryption_config(&self) {
+        let config = self.encryption_config.clone();
+        self.db.run("update the encryption settings", move |db| {
+            db.set_encryption_config(config);
+            Ok(())
+        });
+    }
+
+    /// The keystore's salt, once `password` checks out against it.
+    fn salt_for(&self, password: &str) -> Result<String> {
+        if !self.encryption_config.verify_password(password) {
+            return Err(anyhow::anyhow!("Incorrect password"));
+        }
+        self.encryption_config.get_salt()
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Salt not found"))
+    }
+
+    pub fn set_password(&mut self, password: &str, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        // Set password in encryption config (this will generate salt and hash)
+        if let Err(e) = self.encryption_config.set_password(password) {
+            return done(self, Err(e));
+        }
         
-        self.encryption_status = Some("Encryption disabled successfully".to_string());
-        Ok(())
+        // Initialize encryption in database, then encrypt the stored
+        // settings now rather than at the next settings change, so the
+        // keystore never says encrypted over plaintext data
+        let config = self.encryption_config.clone();
+        let password = Zeroizing::new(password.to_string());
+        let settings = self.user_settings.clone();
+        self.encryption_job(move |db| {
+            db.set_encryption_config(config);
+            db.initialize_encryption(&password)?;
+            db.save_user_settings(&settings)
+        }, |app, result| {
+            if result.is_ok() {
+                app.encryption_status = Some("Password set successfully".to_string());
+            }
+            done(app, result);
+        });
+    }
+
+    /// Unlocks the data with `password` if it's the database password, and
+    /// tells `done` how that went; a wrong one only sets the status.
+    pub fn verify_password(&mut self, password: &str, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let salt = match self.salt_for(password) {
+            Ok(salt) => salt,
+            Err(e) => {
+                self.encryption_status = Some(e.to_string());
+                return;
+            }
+        };
+        // Encrypted columns were loaded sealed until now, and a locked
+        // app has nothing loaded at all
+        let reload_anyway = self.locked || !self.encryption_config.encrypted_columns.is_empty();
+        let unlock_password = Zeroizing::new(password.to_string());
+        let upgrade_password = Zeroizing::new(password.to_string());
+        self.encryption_job(move |db| {
+            let file_was_locked = db.is_file_locked();
+            db.unlock_file(&unlock_password, &salt)?;
+            db.set_encryption_state(true, Some(unlock_password.as_str()), Some(&salt))?;
+            Ok((file_was_locked || reload_anyway).then(|| StoredData::load(db)))
+        }, move |app, result| {
+            match result {
+                Ok(stored) => {
+                    app.locked = false;
+                    if let Some(stored) = stored {
+                        app.apply_stored_data(stored);
+                    }
+                    if !app.started {
+                        app.finish_startup();
+                    }
+                    app.upgrade_key_derivation(&upgrade_password);
+                    app.encryption_status = Some("Password verified successfully".to_string());
+                    done(app, Ok(()));
+                }
+                Err(e) => done(app, Err(e)),
+            }
+        });
+    }
+
+    /// Re-encrypts the settings, the encrypted flow fields and the file
+    /// under `new_password` (see `rekey`). Nothing changes if
+    /// `current_password` is wrong.
+    pub fn change_password(&mut self, current_password: &str, new_password: &str, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        if !self.encryption_config.verify_password(current_password) {
+            return done(self, Err(anyhow::anyhow!("Incorrect current password")));
+        }
+        self.rekey(current_password, new_password, |app, result| {
+            let result = result.map(|rewritten| {
+                info!("Password changed; re-encrypted {} flows", rewritten);
+                app.encryption_status = Some("Password changed successfully".to_string());
+            });
+            done(app, result);
+        });
+    }
+
+    /// Re-encrypts everything under `new_password` with a fresh salt and
+    /// the recommended key derivation (see `Database::change_key`), then
+    /// records them in the keystore. If the keystore can't be saved, the
+    /// data goes back to the current key, so the two never disagree.
+    fn rekey(&mut self, current_password: &str, new_password: &str, done: impl FnOnce(&mut Self, Result<usize>) + 'static) {
+        self.rekey_with(current_password, new_password, Some(DatabaseEncryption::generate_salt()), done);
+    }
+
+    /// `rekey`, keeping the current salt when `new_salt` is `None`. The
+    /// password is then taken to be unchanged, and the keystore keeps the
+    /// derivation it replaces (see `EncryptionConfig::set_key_derivation`)
+    /// so backups taken before can still be restored.
+    fn rekey_with(&mut self, current_password: &str, new_password: &str, new_salt: Option<String>, done: impl FnOnce(&mut Self, Result<usize>) + 'static) {
+        let Some(current_salt) = self.encryption_config.get_salt().cloned() else {
+            return done(self, Err(anyhow::anyhow!("Salt not found")));
+        };
+        let current_derivation = self.encryption_config.key_derivation;
+        let keep_salt = new_salt.is_none();
+        let new_salt = new_salt.unwrap_or_else(|| current_salt.clone());
+        let derivation = KeyDerivation::RECOMMENDED;
+        let (current, new, salt, unlock_salt) = (Zeroizing::new(current_password.to_string()), Zeroizing::new(new_password.to_string()), new_salt.clone(), current_salt.clone());
+        let (restore_password, new_password) = (current.clone(), new.clone());
+        self.encryption_job(move |db| {
+            db.set_encryption_state(true, Some(current.as_str()), Some(&unlock_salt))?;
+            db.change_key(&new, &salt, derivation)
+        }, move |app, result| {
+            let rewritten = match result {
+                Ok(rewritten) => rewritten,
+                Err(e) => return done(app, Err(e)),
+            };
+            let previous = app.encryption_config.clone();
+            let saved = if keep_salt {
+                app.encryption_config.set_key_derivation(&new_password, derivation)
+            } else {
+                app.encryption_config.set_password_with_salt(&new_password, new_salt, derivation)
+            };
+            if let Err(e) = saved {
+                app.encryption_config = previous;
+                app.db.run("re-encrypt the data under the current password again", move |db| {
+                    db.change_key(&restore_password, &current_salt, current_derivation).map(|_| ())
+                });
+                return done(app, Err(e.context("The keystore couldn't be updated, so the password wasn't changed")));
+            }
+            app.send_encryption_config();
+            done(app, Ok(rewritten));
+        });
+    }
+
+    /// Moves data keyed with an outdated derivation (see
+    /// `KeyDerivation::is_outdated`) onto the recommended one, keeping the
+    /// password and salt. Needs the data unlocked with it first.
+    fn upgrade_key_derivation(&mut self, password: &str) {
+        if self.read_only || self.demo || !self.encryption_config.key_derivation.is_outdated() {
+            return;
+        }
+        self.rekey_with(password, password, None, |_, result| match result {
+            Ok(rewritten) => info!("Upgraded the password's key derivation; re-encrypted {} flows", rewritten),
+            Err(e) => warn!("Could not upgrade the password's key derivation; will try again next time: {:#}", e),
+        });
+    }
+
+    pub fn disable_encryption(&mut self, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        // Nothing could open an encrypted file, or encrypted columns, once
+        // the password is gone
+        let has_encrypted_columns = !self.encryption_config.encrypted_columns.is_empty();
+        self.encryption_job(move |db| {
+            if has_encrypted_columns {
+                db.set_encrypted_columns(Vec::new())?;
+            }
+            db.decrypt_file()
+        }, |app, result| {
+            // Disable encryption in the config
+            if let Err(e) = result.and_then(|()| app.encryption_config.disable_encryption()) {
+                return done(app, Err(e));
+            }
+
+            // Disable encryption in the database, and likewise rewrite the
+            // settings in plaintext straight away; nothing could decrypt them
+            // once the keystore has forgotten the password
+            let settings = app.user_settings.clone();
+            app.encryption_job(move |db| {
+                db.set_encryption_state(false, None, None)?;
+                db.save_user_settings(&settings)
+            }, |app, result| {
+                if result.is_ok() {
+                    app.encryption_status = Some("Encryption disabled successfully".to_string());
+                }
+                done(app, result);
+            });
+        });
     }
 
-    pub fn re_enable_encryption(&mut self) -> Result<(), anyhow::Error> {
+    pub fn re_enable_encryption(&mut self, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
         // Re-enable encryption configuration (without password)
-        self.encryption_config.re_enable_encryption()?;
+        if let Err(e) = self.encryption_config.re_enable_encryption() {
+            return done(self, Err(e));
+        }
         
         // Database remains unencrypted until password is set
-        self.db.set_encryption_state(false, None, None)?;
-        
-        self.encryption_status = Some("Encryption configuration re-enabled. Set a password to encrypt the database.".to_string());
-        Ok(())
+        self.encryption_job(|db| db.set_encryption_state(false, None, None), |app, result| {
+            if result.is_ok() {
+                app.encryption_status = Some("Encryption configuration re-enabled. Set a password to encrypt the database.".to_string());
+            }
+            done(app, result);
+        });
+    }
+
+    /// Encrypts the whole database file with the password (see
+    /// `Database::encrypt_file`), migrating from settings-only encryption.
+    pub fn encrypt_database_file(&mut self, password: &str, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let salt = match self.salt_for(password) {
+            Ok(salt) => salt,
+            Err(e) => return done(self, Err(e)),
+        };
+        let password = Zeroizing::new(password.to_string());
+        self.encryption_job(move |db| {
+            db.set_encryption_state(true, Some(password.as_str()), Some(&salt))?;
+            db.encrypt_file()
+        }, |app, result| {
+            let result = result.and_then(|()| app.encryption_config.set_file_encrypted(true));
+            if result.is_ok() {
+                app.send_encryption_config();
+                app.notifications.push("The database file is now encrypted".to_string());
+            }
+            done(app, result);
+        });
+    }
+
+    /// Stores the flows' `columns` encrypted and the rest in plaintext (see
+    /// `Database::set_encrypted_columns`), once `password` checks out.
+    pub fn set_encrypted_columns(&mut self, password: &str, columns: Vec<SensitiveColumn>, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let salt = match self.salt_for(password) {
+            Ok(salt) => salt,
+            Err(e) => return done(self, Err(e)),
+        };
+        if !self.pending_changes.is_empty() {
+            return done(self, Err(anyhow::anyhow!("Save or discard the pending changes first")));
+        }
+        let password = Zeroizing::new(password.to_string());
+        let chosen = columns.clone();
+        self.encryption_job(move |db| {
+            db.set_encryption_state(true, Some(password.as_str()), Some(&salt))?;
+            db.set_encrypted_columns(chosen)
+        }, move |app, result| {
+            let result = result.and_then(|rewritten| {
+                app.encryption_config.set_encrypted_columns(columns)?;
+                Ok(rewritten)
+            });
+            match result {
+                Ok(rewritten) => {
+                    app.send_encryption_config();
+                    app.reload_from_db();
+                    app.notifications.push(format!("Updated which fields are encrypted in {} flows", rewritten));
+                    done(app, Ok(()));
+                }
+                Err(e) => done(app, Err(e)),
+            }
+        });
+    }
+
+    /// Repairs a keystore that says the data is encrypted when it isn't, by
+    /// encrypting it with the keystore's password.
+    pub fn encrypt_stored_data(&mut self, password: &str, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let salt = match self.salt_for(password) {
+            Ok(salt) => salt,
+            Err(e) => return done(self, Err(e)),
+        };
+        let config = self.encryption_config.clone();
+        let password = Zeroizing::new(password.to_string());
+        let settings = self.user_settings.clone();
+        self.encryption_job(move |db| {
+            db.set_encryption_config(config);
+            db.set_encryption_state(true, Some(password.as_str()), Some(&salt))?;
+            db.save_user_settings(&settings)
+        }, done);
+    }
+
+    /// Repairs a keystore that says the data isn't encrypted when it is, by
+    /// decrypting it with the keystore's password and recording that it's
+    /// encrypted. The settings loaded at startup (defaults, since they
+    /// couldn't be read) are replaced with the decrypted ones.
+    pub fn unlock_stored_data(&mut self, password: &str, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let salt = match self.salt_for(password) {
+            Ok(salt) => salt,
+            Err(e) => return done(self, Err(e)),
+        };
+        let password = Zeroizing::new(password.to_string());
+        self.encryption_job(move |db| {
+            db.set_encryption_state(true, Some(password.as_str()), Some(&salt))?;
+            match db.load_user_settings() {
+                Ok(settings) => Ok(settings),
+                Err(e) => {
+                    db.set_encryption_state(false, None, None)?;
+                    Err(anyhow::anyhow!("The password is right, but the stored settings weren't encrypted with it: {}", e))
+                }
+            }
+        }, |app, result| {
+            let result = result.and_then(|settings| {
+                app.user_settings = settings;
+                app.encryption_config.set_database_encrypted(true)
+            });
+            if result.is_ok() {
+                app.send_encryption_config();
+            }
+            done(app, result);
+        });
+    }
+
+    /// Repairs encrypted data that nothing can decrypt any more by saving
+    /// the current settings over it, unencrypted.
+    pub fn replace_unreadable_settings(&mut self, done: impl FnOnce(&mut Self, Result<()>) + 'static) {
+        let settings = self.user_settings.clone();
+        self.encryption_job(move |db| {
+            db.set_encryption_state(false, None, None)?;
+            db.save_user_settings(&settings)
+        }, done);
+    }
+
+    /// Closes the password dialog, dropping its inputs and any action it
+    /// was holding.
+    pub fn close_password_dialog(&mut self) {
+        self.show_password_dialog = false;
+        self.pending_guarded_action = None;
+        self.password_input.zeroize();
+        self.password_confirm.zeroize();
+        self.current_password_input.zeroize();
     }
 
     pub fn clear_encryption_status(&mut self) {
@@ -666,7 +3429,7 @@ impl PreftApp {
         // recent one, so skip it. Deliberately not affected by UI-only
         // changes like the year filter or a hidden-category toggle, since
         // those are preferences, not records worth backing up.
-        if !self.db.is_dirty() {
+        if self.db.call(|db| db.is_dirty()) != Ok(true) {
             return Ok(());
         }
 
@@ -705,7 +3468,9 @@ impl PreftApp {
         let encrypted_backup = self.user_settings.auto_backup_encrypted.unwrap_or(false);
         
         // Create the backup
-        if let Err(e) = self.db.backup_to_file(&backup_path, encrypted_backup) {
+        let path = backup_path.clone();
+        let backup = self.db.call(move |db| db.backup_to_file(&path, encrypted_backup));
+        if let Err(e) = backup.map_err(anyhow::Error::from).and_then(|backup| backup) {
             log::warn!("Warning: Failed to create automatic backup: {}", e);
             return Ok(()); // Gracefully skip backup if creation fails
         }
@@ -725,9 +3490,7 @@ impl PreftApp {
         self.user_settings.add_backup_entry(entry);
 
         // Save updated settings (don't fail if this doesn't work)
-        if let Err(e) = self.db.save_user_settings(&self.user_settings) {
-            log::warn!("Warning: Failed to save backup history: {}", e);
-        }
+        self.save_settings("save backup history");
 
         // Clean up old automatic backups (keep only the 5 most recent)
         if let Err(e) = self.cleanup_old_automatic_backups(&backup_dir) {
@@ -785,15 +3548,61 @@ impl PreftApp {
 }
 
 impl eframe::App for PreftApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        theme::apply(ctx, self.user_settings.theme, frame.info().system_theme);
+        self.remember_ui_scale(ctx);
+        self.track_window_geometry(ctx);
+        if !self.show_password_dialog {
+            crate::ui::forget_password_fields(ctx, &crate::ui::password_dialog::PASSWORD_FIELDS);
+        }
+        self.handle_db_events();
+        self.apply_db_results();
+        if !self.awaiting.is_empty() {
+            // Nothing else would wake egui up when the results come back.
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+        self.poll_backup_task();
         self.poll_pending_backup();
-        if self.pending_backup.is_some() {
-            // Keep polling at a modest rate while the background move is in
+        if self.backup_task.is_some() || self.pending_backup.is_some() {
+            // Keep polling at a modest rate while a backup or restore is in
             // flight -- egui doesn't repaint on its own between input
-            // events, and nothing else here would otherwise wake it up
-            // until the move finishes.
+            // events, and nothing else here would otherwise wake it up to
+            // move the progress bar or notice the dialog closing.
             ctx.request_repaint_after(std::time::Duration::from_millis(100));
         }
+        self.note_input(ctx);
+        self.auto_lock_if_idle(ctx);
+        if self.user_settings.watch_folder.is_some() && !self.read_only && !self.locked && self.started {
+            self.poll_watch_folder();
+            // Wake up for the next scan even if there's no input.
+            ctx.request_repaint_after(crate::watch_folder::SCAN_INTERVAL);
+        }
+
+        // Closing would silently drop deferred edits; keep the window open
+        // and show them for review instead (once unlocked, if locked; the
+        // lock screen says why the window stays open).
+        if ctx.input(|i| i.viewport().close_requested()) && !self.pending_changes.is_empty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.show_pending_changes_panel = true;
+            self.quit_requested = true;
+        }
+
+        if self.locked {
+            crate::ui::show_lock_screen(ctx, self);
+            return;
+        }
+
+        if self.started {
+            self.handle_shortcuts(ctx);
+            if !self.read_only {
+                self.poll_scheduled_flows();
+            }
+        }
+        self.remember_selected_category();
+        self.open_category_view();
+        self.poll_integrity_scan(ctx);
+        self.stored_totals.poll(&self.db, self.pending_changes.is_empty(), ctx);
+        self.all_time_totals.poll(&self.db, &self.pending_changes, &self.categories, ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
             // First show the main panel
@@ -825,11 +3634,81 @@ impl eframe::App for PreftApp {
                 crate::ui::show_report_dialog(ctx, self);
             }
 
+            // Show import dialog if needed
+            if self.show_import_dialog {
+                crate::ui::show_import_dialog(ctx, self);
+            }
+
+            // Show cache verification results if needed
+            if self.show_verify_dialog {
+                crate::ui::show_verify_dialog(ctx, self);
+            }
+
             // Show backup dialog if needed
             if self.show_backup_dialog {
                 crate::ui::show_backup_dialog(ctx, self);
             }
 
+            // Show backup comparison dialog if needed
+            if self.show_backup_compare_dialog {
+                crate::ui::show_backup_compare_dialog(ctx, self);
+            }
+
+            // Show bulk edit dialog if needed
+            if self.show_bulk_edit_dialog {
+                crate::ui::show_bulk_edit_dialog(ctx, self);
+            }
+
+            // Show highlight rules editor if needed
+            if self.show_highlight_rules_dialog {
+                crate::ui::show_highlight_rules_dialog(ctx, self);
+            }
+
+            // Show KPI card editor if needed
+            if self.show_kpi_cards_dialog {
+                crate::ui::show_kpi_cards_dialog(ctx, self);
+            }
+
+            // Show the encryption repair wizard until it's finished or put off
+            if self.encryption_repair.is_some() {
+                crate::ui::show_encryption_repair_dialog(ctx, self);
+            }
+
+            // Show why a flow couldn't be saved, until resolved
+            if self.uniqueness_conflict.is_some() {
+                crate::ui::show_uniqueness_conflict_dialog(ctx, self);
+            }
+
+            // Show trips list if needed
+            if self.show_trips_dialog {
+                crate::ui::show_trips_dialog(ctx, self);
+            }
+
+            // Show SQL views if needed
+            if self.show_sql_views_dialog {
+                crate::ui::show_sql_views_dialog(ctx, self);
+            }
+
+            // Show settings dialog if needed
+            if self.show_settings_dialog {
+                crate::ui::show_settings_dialog(ctx, self);
+            }
+
+            // Show export bundle dialog if needed
+            if self.show_export_bundle_dialog {
+                crate::ui::show_export_bundle_dialog(ctx, self);
+            }
+
+            // Show what the last migration changed, until acknowledged
+            if self.migration_summary.is_some() {
+                crate::ui::show_migration_summary_dialog(ctx, self);
+            }
+
+            // Show the pending changes review panel if needed
+            if self.show_pending_changes_panel {
+                crate::ui::show_pending_changes_panel(ctx, self);
+            }
+
             // Show password dialog if needed
             if self.show_password_dialog {
                 crate::ui::show_password_dialog(ctx, self);
@@ -843,6 +3722,26 @@ impl eframe::App for PreftApp {
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.read_only || self.demo {
+            return;
+        }
+        // Nothing is loaded while locked, so there's nothing to record
+        if !self.locked {
+            if self.window_geometry.is_some() && self.window_geometry != self.user_settings.window {
+                self.user_settings.window = self.window_geometry;
+                self.save_settings("save window position");
+            }
+            self.record_metric_snapshots();
+        }
+
+        // A backup or restore still copying is stopped rather than waited
+        // for; a restore stopped part way leaves the data as it was.
+        self.cancel_backup_task();
+        if let Some(BackupTask::Writing { temp_path, .. }) = self.backup_task.take() {
+            let _ = self.db.call(|_| ()); // runs once the copy has stopped
+            let _ = std::fs::remove_file(&temp_path); // best-effort cleanup
+        }
+
         // If a manual backup's background move (see `create_backup`) is
         // still in flight, the process exiting would kill that thread
         // mid-copy and could leave a truncated file at the destination the
@@ -898,4 +3797,39 @@ mod tests {
 
         assert!(move_backup_file(&src, &dest).is_err());
     }
+
+    fn flow(id: &str, year: i32) -> Flow {
+        Flow {
+            id: id.to_string(),
+            date: chrono::NaiveDate::from_ymd_opt(year, 3, 1).unwrap(),
+            amount: 10.0,
+            category_id: "cat-1".to_string(),
+            description: String::new(),
+            linked_flows: Vec::new(),
+            custom_fields: HashMap::new(),
+            tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
+        }
+    }
+
+    #[test]
+    fn deleting_a_reimbursement_leaves_a_locked_years_expense_linked() {
+        let flows = vec![
+            Flow { reimbursed_by: Some("payback".to_string()), ..flow("old-expense", 2024) },
+            Flow { reimbursed_by: Some("payback".to_string()), ..flow("new-expense", 2025) },
+            Flow { refund_of: Some("payback".to_string()), ..flow("refund", 2025) },
+            flow("payback", 2025),
+        ];
+        let unlinked = unlinked_by_delete(&flows, &BTreeSet::from(["payback".to_string()]), &BTreeSet::from([2024]));
+
+        let ids: Vec<&str> = unlinked.iter().map(|(before, _)| before.id.as_str()).collect();
+        assert_eq!(ids, vec!["new-expense", "refund"]);
+        assert!(unlinked.iter().all(|(_, after)| after.reimbursed_by.is_none() && after.refund_of.is_none()));
+    }
 }
\ No newline at end of file
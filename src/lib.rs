@@ -2,15 +2,25 @@ use anyhow::Result;
 use eframe::egui;
 
 pub mod app;
-pub mod db;
-pub mod encryption;
-pub mod encryption_config;
 pub mod logging;
-pub mod models;
-pub mod reporting;
-pub mod settings;
+pub mod shortcuts;
+pub mod theme;
 pub mod ui;
-pub mod utils;
+
+// The app's own modules reach these as `crate::models` etc., as they did
+// before they moved into `preft-core`.
+pub use preft_core::{
+    backup_diff, bulk_edit, category_schema, db, db_worker, emergency, encryption, encryption_config, export_bundle,
+    expression, flow_cache, forecast, import, integrity, kpi, locale, metrics, models, pending_changes, reporting, repro,
+    settings, undo, utils, watch_folder, year_grid,
+};
+
+/// Command-line flag that opens the app in read-only viewer mode.
+pub const VIEWER_FLAG: &str = "--viewer";
+
+/// Command-line flag, followed by a path, that opens a repro bundle (see
+/// `repro`) in demo mode.
+pub const LOAD_REPRO_FLAG: &str = "--load-repro";
 
 /// Runs the desktop application. Extracted from `main` so the rest of the
 /// crate is importable (by integration tests, etc.) without pulling in the
@@ -18,16 +28,59 @@ pub mod utils;
 pub fn run() -> Result<(), eframe::Error> {
     logging::init_logging();
     log::info!("Starting Preft application");
+    let read_only = std::env::args().any(|arg| arg == VIEWER_FLAG);
+    if read_only {
+        log::info!("Starting in read-only viewer mode");
+    }
+    let repro = match load_repro_arg() {
+        Ok(repro) => repro,
+        Err(e) => {
+            log::error!("Failed to open repro bundle: {}", e);
+            eprintln!("Failed to open repro bundle: {}", e);
+            std::process::exit(1);
+        }
+    };
 
+    // A repro bundle's settings are the reporter's; the window goes where
+    // this user had it.
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([800.0, 600.0]);
+    if let Some(window) = saved_window_geometry() {
+        viewport = viewport
+            .with_inner_size([window.width, window.height])
+            .with_position([window.x, window.y])
+            .with_maximized(window.maximized);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([800.0, 600.0]),
+        viewport,
         ..Default::default()
     };
 
     eframe::run_native(
         "Preft",
         options,
-        Box::new(|cc| Box::new(app::PreftApp::new(cc))),
+        Box::new(move |cc| Box::new(app::PreftApp::new(cc, read_only, repro))),
     )
 }
+
+/// Where the window was when preft last closed, read before the app (and
+/// its database) starts since the window is made first. `None` on the
+/// first run, or if the settings can't be read.
+fn saved_window_geometry() -> Option<settings::WindowGeometry> {
+    let db = db::Database::open_read_only().ok()?;
+    let window = db.load_user_settings().ok()?.window?;
+    // Too small to find again, or not a real size.
+    (window.width >= 200.0 && window.height >= 150.0 && window.x.is_finite() && window.y.is_finite()).then_some(window)
+}
+
+/// The bundle named after `LOAD_REPRO_FLAG`, if the flag was given.
+fn load_repro_arg() -> Result<Option<repro::ReproBundle>> {
+    let mut args = std::env::args().skip_while(|arg| arg != LOAD_REPRO_FLAG);
+    if args.next().is_none() {
+        return Ok(None);
+    }
+    let path = args.next().ok_or_else(|| anyhow::anyhow!("{} needs the path of a bundle", LOAD_REPRO_FLAG))?;
+    let bundle = repro::ReproBundle::from_bytes(&std::fs::read(&path)?)?;
+    log::info!("Starting in demo mode from repro bundle {} (made by preft {} on {})", path, bundle.app_version, bundle.platform);
+    Ok(Some(bundle))
+}
@@ -0,0 +1,92 @@
+//! Global keyboard shortcuts, handled once per frame by `PreftApp::update`.
+//! Bindings are kept as text like "Ctrl+N" so they can be edited in the
+//! settings dialog; "Ctrl" means Cmd on macOS.
+
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+
+pub use preft_core::shortcuts::ShortcutAction;
+
+/// Keys a binding can name, matched against `Key::name`.
+const BINDABLE_KEYS: [Key; 58] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4,
+    Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+    Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::Enter, Key::Escape, Key::Tab, Key::Space, Key::Insert, Key::Delete,
+    Key::Home, Key::End, Key::PageUp, Key::PageDown,
+];
+
+/// The number keys `ShortcutAction::SwitchCategory` combines with, for the
+/// first through ninth category.
+pub const CATEGORY_KEYS: [Key; 9] = [
+    Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5,
+    Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+];
+
+/// Parses a binding like "Ctrl+Shift+N" (case-insensitive) into its
+/// modifiers and key. `SwitchCategory` takes modifiers alone and every
+/// other action needs exactly one key; at least one modifier is always
+/// required so shortcuts don't fire while typing.
+pub fn parse_binding(action: ShortcutAction, text: &str) -> Result<(Modifiers, Option<Key>), String> {
+    let mut modifiers = Modifiers::NONE;
+    let mut key = None;
+    for part in text.split('+').map(str::trim) {
+        match part.to_lowercase().as_str() {
+            "ctrl" | "cmd" => modifiers.command = true,
+            "shift" => modifiers.shift = true,
+            "alt" => modifiers.alt = true,
+            _ => {
+                let found = BINDABLE_KEYS.into_iter()
+                    .find(|k| k.name().eq_ignore_ascii_case(part))
+                    .ok_or_else(|| format!("\"{}\" isn't a key that can be bound", part))?;
+                if key.replace(found).is_some() {
+                    return Err("Only one key can be bound".to_string());
+                }
+            }
+        }
+    }
+    if modifiers.is_none() {
+        return Err("Include Ctrl, Shift or Alt".to_string());
+    }
+    match (action, key) {
+        (ShortcutAction::SwitchCategory, Some(_)) => Err("Only modifiers; 1-9 are added automatically".to_string()),
+        (ShortcutAction::SwitchCategory, None) => Ok((modifiers, None)),
+        (_, None) => Err("Add a key, e.g. Ctrl+N".to_string()),
+        (_, Some(key)) => Ok((modifiers, Some(key))),
+    }
+}
+
+/// The shortcut for `action`, or `None` if the binding doesn't parse.
+pub fn shortcut(action: ShortcutAction, text: &str) -> Option<KeyboardShortcut> {
+    match parse_binding(action, text) {
+        Ok((modifiers, Some(key))) => Some(KeyboardShortcut::new(modifiers, key)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_all_parse() {
+        for action in ShortcutAction::ALL {
+            assert!(parse_binding(action, action.default_binding()).is_ok(), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn bindings_need_a_modifier_and_exactly_the_right_keys() {
+        let ctrl_shift = Modifiers { command: true, shift: true, ..Modifiers::NONE };
+        assert_eq!(parse_binding(ShortcutAction::NewFlow, "ctrl + shift + n"), Ok((ctrl_shift, Some(Key::N))));
+        assert_eq!(parse_binding(ShortcutAction::OpenReport, "Alt+F5"), Ok((Modifiers::ALT, Some(Key::F5))));
+        assert!(parse_binding(ShortcutAction::NewFlow, "N").is_err(), "no modifier");
+        assert!(parse_binding(ShortcutAction::NewFlow, "Ctrl").is_err(), "no key");
+        assert!(parse_binding(ShortcutAction::NewFlow, "Ctrl+N+M").is_err(), "two keys");
+        assert!(parse_binding(ShortcutAction::NewFlow, "Ctrl+Banana").is_err());
+        assert!(parse_binding(ShortcutAction::SwitchCategory, "Ctrl+1").is_err(), "digits are implied");
+    }
+}
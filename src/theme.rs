@@ -0,0 +1,70 @@
+//! The egui visuals for each `Theme` setting.
+
+use eframe::egui::{self, Color32, Stroke, Visuals};
+
+use crate::settings::Theme;
+
+/// `system_theme` is what eframe reports for the OS; dark is assumed when
+/// it can't tell.
+pub fn visuals(theme: Theme, system_theme: Option<eframe::Theme>) -> Visuals {
+    match theme {
+        Theme::System => match system_theme {
+            Some(eframe::Theme::Light) => Visuals::light(),
+            _ => Visuals::dark(),
+        },
+        Theme::Dark => Visuals::dark(),
+        Theme::Light => Visuals::light(),
+        Theme::SolarizedDark => palette(Visuals::dark(), Palette {
+            background: Color32::from_rgb(0x00, 0x2b, 0x36),
+            surface: Color32::from_rgb(0x07, 0x36, 0x42),
+            text: Color32::from_rgb(0x93, 0xa1, 0xa1),
+            accent: Color32::from_rgb(0x26, 0x8b, 0xd2),
+        }),
+        Theme::SolarizedLight => palette(Visuals::light(), Palette {
+            background: Color32::from_rgb(0xfd, 0xf6, 0xe3),
+            surface: Color32::from_rgb(0xee, 0xe8, 0xd5),
+            text: Color32::from_rgb(0x58, 0x6e, 0x75),
+            accent: Color32::from_rgb(0x26, 0x8b, 0xd2),
+        }),
+        Theme::HighContrast => palette(Visuals::dark(), Palette {
+            background: Color32::BLACK,
+            surface: Color32::from_gray(20),
+            text: Color32::WHITE,
+            accent: Color32::YELLOW,
+        }),
+    }
+}
+
+/// Sets the visuals for `theme`, unless they're already in place. Called
+/// every frame, so a changed setting (or OS theme, for `System`) takes
+/// effect straight away.
+pub fn apply(ctx: &egui::Context, theme: Theme, system_theme: Option<eframe::Theme>) {
+    let visuals = visuals(theme, system_theme);
+    if ctx.style().visuals != visuals {
+        ctx.set_visuals(visuals);
+    }
+}
+
+struct Palette {
+    background: Color32,
+    surface: Color32,
+    text: Color32,
+    accent: Color32,
+}
+
+/// `base` recoloured: `background` behind panels and windows, `surface` for
+/// text fields and striped rows, and `accent` for selections and links.
+fn palette(mut base: Visuals, palette: Palette) -> Visuals {
+    base.override_text_color = Some(palette.text);
+    base.panel_fill = palette.background;
+    base.window_fill = palette.background;
+    base.extreme_bg_color = palette.surface;
+    base.faint_bg_color = palette.surface;
+    base.hyperlink_color = palette.accent;
+    base.selection.bg_fill = palette.accent.gamma_multiply(0.5);
+    base.selection.stroke = Stroke::new(1.0, palette.text);
+    base.widgets.noninteractive.bg_fill = palette.background;
+    base.widgets.inactive.bg_fill = palette.surface;
+    base.widgets.inactive.weak_bg_fill = palette.surface;
+    base
+}
@@ -0,0 +1,143 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+use crate::app::PreftApp;
+use crate::backup_diff::DataDiff;
+use crate::db::Database;
+
+/// State for the backup comparison dialog. Lives on `PreftApp` (like
+/// `import_state`) so the chosen files and the last result survive redraws.
+#[derive(Default)]
+pub struct BackupCompareState {
+    /// The older copy.
+    pub before: Option<PathBuf>,
+    /// The newer copy; `None` compares against the live database.
+    pub after: Option<PathBuf>,
+    /// The last comparison's differences, or why it couldn't be run.
+    pub result: Option<Result<Vec<String>, String>>,
+    /// Set while the comparison runs on the database thread.
+    pub running: bool,
+}
+
+fn pick_backup(title: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title(title)
+        .add_filter("Database Files", &["db"])
+        .add_filter("All Files", &["*"])
+        .pick_file()
+}
+
+/// Runs the comparison on the database thread: every flow on both sides
+/// is read, the current ones (with pending changes replayed) included.
+fn compare(app: &mut PreftApp) {
+    let Some(before_path) = app.backup_compare_state.before.clone() else {
+        app.backup_compare_state.result = Some(Err("Choose a backup to compare first.".to_string()));
+        return;
+    };
+    let after_path = app.backup_compare_state.after.clone();
+    let categories = app.categories.clone();
+    let pending_changes = app.pending_changes.clone();
+    app.backup_compare_state.running = true;
+    let comparing = app.db.query(move |db| -> Result<Vec<String>, String> {
+        let (before_categories, before_flows) = Database::read_backup_contents(&before_path)
+            .map_err(|e| format!("Failed to read {}: {}", before_path.display(), e))?;
+        let diff = match after_path {
+            Some(after_path) => {
+                let (after_categories, after_flows) = Database::read_backup_contents(&after_path)
+                    .map_err(|e| format!("Failed to read {}: {}", after_path.display(), e))?;
+                DataDiff::compare(&before_categories, &before_flows, &after_categories, &after_flows)
+            }
+            None => {
+                let mut flows = db.load_flows()
+                    .map_err(|e| format!("Failed to load the current flows: {}", e))?;
+                pending_changes.apply_to(&mut flows);
+                DataDiff::compare(&before_categories, &before_flows, &categories, &flows)
+            }
+        };
+        Ok(diff.describe())
+    });
+    app.when_done(comparing, |app, result| {
+        app.backup_compare_state.running = false;
+        app.backup_compare_state.result = Some(result.map_err(|e| e.to_string()).and_then(|result| result));
+    });
+}
+
+pub fn show_backup_compare_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_backup_compare_dialog;
+    let mut run = false;
+
+    egui::Window::new("Compare Backups")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_size([600.0, 400.0])
+        .show(ctx, |ui| {
+            let state = &mut app.backup_compare_state;
+            egui::Grid::new("backup_compare_sources").show(ui, |ui| {
+                ui.label("Older:");
+                match &state.before {
+                    Some(path) => ui.label(path.to_string_lossy()),
+                    None => ui.label("No file selected"),
+                };
+                if ui.button("Choose Backup...").clicked()
+                    && let Some(path) = pick_backup("Select Older Backup")
+                {
+                    state.before = Some(path);
+                    state.result = None;
+                }
+                ui.end_row();
+
+                ui.label("Newer:");
+                match &state.after {
+                    Some(path) => ui.label(path.to_string_lossy()),
+                    None => ui.label("Current data"),
+                };
+                ui.horizontal(|ui| {
+                    if ui.button("Choose Backup...").clicked()
+                        && let Some(path) = pick_backup("Select Newer Backup")
+                    {
+                        state.after = Some(path);
+                        state.result = None;
+                    }
+                    if state.after.is_some() && ui.button("Use Current Data").clicked() {
+                        state.after = None;
+                        state.result = None;
+                    }
+                });
+                ui.end_row();
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(state.before.is_some() && !state.running, egui::Button::new("Compare")).clicked() {
+                    run = true;
+                }
+                if state.running {
+                    ui.spinner();
+                }
+            });
+
+            match &state.result {
+                Some(Ok(lines)) if lines.is_empty() => {
+                    ui.label(egui::RichText::new("No differences: both contain the same categories and flows.").color(egui::Color32::GREEN));
+                }
+                Some(Ok(lines)) => {
+                    ui.label(format!("{} difference(s):", lines.len()));
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for line in lines {
+                            ui.label(line);
+                        }
+                    });
+                }
+                Some(Err(e)) => {
+                    ui.label(egui::RichText::new(e).color(egui::Color32::RED));
+                }
+                None => {}
+            }
+        });
+
+    if run {
+        compare(app);
+    }
+
+    app.show_backup_compare_dialog = show_window;
+}
@@ -1,7 +1,7 @@
 use eframe::egui;
 use log::{info, warn, error};
 
-use crate::app::PreftApp;
+use crate::app::{GuardedAction, PreftApp};
 
 pub fn show_backup_dialog(ctx: &egui::Context, app: &mut PreftApp) {
     let mut show_window = app.show_backup_dialog;
@@ -44,9 +44,7 @@ pub fn show_backup_dialog(ctx: &egui::Context, app: &mut PreftApp) {
             if ui.checkbox(&mut auto_backup_enabled, "Enable automatic backups").changed() {
                 app.user_settings.set_auto_backup_enabled(auto_backup_enabled);
                 // Save settings immediately
-                if let Err(e) = app.db.save_user_settings(&app.user_settings) {
-                    log::error!("Failed to save auto backup setting: {}", e);
-                }
+                app.save_settings("save auto backup setting");
             }
             
             if auto_backup_enabled {
@@ -66,9 +64,7 @@ pub fn show_backup_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                             .pick_folder() {
                             app.user_settings.set_auto_backup_directory(Some(path.to_string_lossy().to_string()));
                             // Save settings immediately
-                            if let Err(e) = app.db.save_user_settings(&app.user_settings) {
-                                log::error!("Failed to save auto backup directory: {}", e);
-                            }
+                            app.save_settings("save auto backup directory");
                         }
                     }
                 });
@@ -93,9 +89,7 @@ pub fn show_backup_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                     if current_encrypted != Some(encrypted) {
                         app.user_settings.set_auto_backup_encrypted(Some(encrypted));
                         // Save settings immediately
-                        if let Err(e) = app.db.save_user_settings(&app.user_settings) {
-                            log::error!("Failed to save auto backup encryption setting: {}", e);
-                        }
+                        app.save_settings("save auto backup encryption setting");
                     }
                 });
                 
@@ -115,14 +109,33 @@ pub fn show_backup_dialog(ctx: &egui::Context, app: &mut PreftApp) {
             // Action buttons
             ui.heading("Actions");
             ui.horizontal(|ui| {
-                if ui.button("Create Backup").clicked() && !app.backup_in_progress {
+                if ui.add_enabled(!app.backup_in_progress, egui::Button::new("Create Backup")).clicked() {
                     app.create_backup();
                 }
                 
-                if ui.button("Restore from Backup").clicked() && !app.backup_in_progress {
-                    app.restore_backup();
+                if ui.add_enabled(!app.backup_in_progress, egui::Button::new("Restore from Backup")).clicked() {
+                    app.request_guarded_action(GuardedAction::RestoreBackup);
                 }
                 
+                if ui.add_enabled(!app.backup_in_progress, egui::Button::new("Compact Database"))
+                    .on_hover_text("Give back the space deleted flows left in the database file")
+                    .clicked()
+                {
+                    app.compact_database();
+                }
+
+                if ui.button("Compare Backups").clicked() {
+                    app.show_backup_compare_dialog = true;
+                }
+
+                if ui.button("Export Everything").on_hover_text("Save the database, CSVs and settings as one password-protected file").clicked() {
+                    app.show_export_bundle_dialog = true;
+                }
+
+                if ui.button("Emergency Document").on_hover_text("Save a printable summary of where your records are and what recurs, without any passwords").clicked() {
+                    app.save_emergency_document();
+                }
+
                 if ui.button("Clear Status").clicked() {
                     app.clear_backup_status();
                 }
@@ -131,9 +144,17 @@ pub fn show_backup_dialog(ctx: &egui::Context, app: &mut PreftApp) {
             // Show progress indicator
             if app.backup_in_progress {
                 ui.separator();
-                ui.horizontal(|ui| {
-                    ui.label("Operation in progress...");
-                    ui.spinner();
+                ui.horizontal(|ui| match app.backup_progress() {
+                    Some(fraction) => {
+                        ui.add(egui::ProgressBar::new(fraction).show_percentage().desired_width(300.0));
+                        if ui.button("Cancel").clicked() {
+                            app.cancel_backup_task();
+                        }
+                    }
+                    None => {
+                        ui.label("Operation in progress...");
+                        ui.spinner();
+                    }
                 });
             }
             
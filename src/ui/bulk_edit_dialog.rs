@@ -0,0 +1,299 @@
+use chrono::Datelike;
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::bulk_edit::{self, BulkAction, BulkEdit, BulkEditPreview};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BulkActionKind {
+    #[default]
+    ReplaceInDescription,
+    SetCustomField,
+    MoveToCategory,
+    ScaleAmount,
+}
+
+impl BulkActionKind {
+    fn get_display_name(&self) -> &'static str {
+        match self {
+            BulkActionKind::ReplaceInDescription => "Replace text in description",
+            BulkActionKind::SetCustomField => "Set custom field",
+            BulkActionKind::MoveToCategory => "Move to category",
+            BulkActionKind::ScaleAmount => "Multiply amount",
+        }
+    }
+}
+
+/// State for the bulk edit dialog. Lives on `PreftApp` (like
+/// `import_state`) so the form and the last preview survive redraws. Any
+/// edit to the form discards the preview, so "Apply" only ever saves exactly
+/// what was last shown.
+#[derive(Default)]
+pub struct BulkEditState {
+    pub category_id: Option<String>,
+    pub description_contains: String,
+    pub action_kind: BulkActionKind,
+    pub find: String,
+    pub replace: String,
+    pub field_name: String,
+    pub field_value: String,
+    pub target_category_id: Option<String>,
+    pub factor: String,
+    pub preview: Option<BulkEditPreview>,
+    /// Set while the preview is worked out on the database thread; cleared
+    /// by any edit to the form, so a preview that comes back late is
+    /// dropped.
+    pub previewing: bool,
+    /// Outcome of the last preview attempt or apply.
+    pub status: Option<Result<String, String>>,
+}
+
+impl BulkEditState {
+    fn build(&self) -> Result<BulkEdit, String> {
+        let action = match self.action_kind {
+            BulkActionKind::ReplaceInDescription => {
+                if self.find.is_empty() {
+                    return Err("Enter the text to replace.".to_string());
+                }
+                BulkAction::ReplaceInDescription { find: self.find.clone(), replace: self.replace.clone() }
+            }
+            BulkActionKind::SetCustomField => {
+                let name = self.field_name.trim();
+                if name.is_empty() {
+                    return Err("Enter the name of the field to set.".to_string());
+                }
+                BulkAction::SetCustomField { name: name.to_string(), value: self.field_value.clone() }
+            }
+            BulkActionKind::MoveToCategory => match &self.target_category_id {
+                Some(category_id) => BulkAction::MoveToCategory(category_id.clone()),
+                None => return Err("Choose the category to move flows to.".to_string()),
+            },
+            BulkActionKind::ScaleAmount => match self.factor.trim().parse::<f64>() {
+                Ok(factor) if factor.is_finite() => BulkAction::ScaleAmount(factor),
+                _ => return Err(format!("'{}' is not a valid multiplier.", self.factor)),
+            },
+        };
+        Ok(BulkEdit {
+            category_id: self.category_id.clone(),
+            description_contains: self.description_contains.clone(),
+            action,
+        })
+    }
+}
+
+pub fn show_bulk_edit_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_bulk_edit_dialog;
+    let mut run_preview = false;
+    let mut apply = false;
+    let category_choices: Vec<(String, String)> = app.categories.iter()
+        .map(|cat| (cat.id.clone(), cat.name.clone()))
+        .collect();
+    let category_name = |id: &Option<String>, none: &str| {
+        id.as_ref()
+            .and_then(|id| category_choices.iter().find(|(cat_id, _)| cat_id == id))
+            .map_or(none.to_string(), |(_, name)| name.clone())
+    };
+
+    egui::Window::new("Bulk Edit")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_size([650.0, 450.0])
+        .show(ctx, |ui| {
+            let state = &mut app.bulk_edit_state;
+            let mut form_changed = false;
+
+            ui.heading("Flows to Change");
+            egui::Grid::new("bulk_edit_filter").show(ui, |ui| {
+                ui.label("Category:");
+                egui::ComboBox::from_id_source("bulk_edit_category")
+                    .selected_text(category_name(&state.category_id, "All Categories"))
+                    .show_ui(ui, |ui| {
+                        form_changed |= ui.selectable_value(&mut state.category_id, None, "All Categories").changed();
+                        for (id, name) in &category_choices {
+                            form_changed |= ui.selectable_value(&mut state.category_id, Some(id.clone()), name).changed();
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Description contains:");
+                form_changed |= ui.text_edit_singleline(&mut state.description_contains).changed();
+                ui.end_row();
+            });
+
+            ui.separator();
+            ui.heading("Change");
+            egui::ComboBox::from_id_source("bulk_edit_action")
+                .selected_text(state.action_kind.get_display_name())
+                .show_ui(ui, |ui| {
+                    for kind in [
+                        BulkActionKind::ReplaceInDescription,
+                        BulkActionKind::SetCustomField,
+                        BulkActionKind::MoveToCategory,
+                        BulkActionKind::ScaleAmount,
+                    ] {
+                        form_changed |= ui.selectable_value(&mut state.action_kind, kind, kind.get_display_name()).changed();
+                    }
+                });
+            egui::Grid::new("bulk_edit_action_inputs").show(ui, |ui| {
+                match state.action_kind {
+                    BulkActionKind::ReplaceInDescription => {
+                        ui.label("Find:");
+                        form_changed |= ui.text_edit_singleline(&mut state.find).changed();
+                        ui.end_row();
+                        ui.label("Replace with:");
+                        form_changed |= ui.text_edit_singleline(&mut state.replace).changed();
+                        ui.end_row();
+                    }
+                    BulkActionKind::SetCustomField => {
+                        ui.label("Field:");
+                        form_changed |= ui.text_edit_singleline(&mut state.field_name).changed();
+                        ui.end_row();
+                        ui.label("Value:");
+                        form_changed |= ui.text_edit_singleline(&mut state.field_value).changed();
+                        ui.end_row();
+                    }
+                    BulkActionKind::MoveToCategory => {
+                        ui.label("New category:");
+                        egui::ComboBox::from_id_source("bulk_edit_target_category")
+                            .selected_text(category_name(&state.target_category_id, "Choose..."))
+                            .show_ui(ui, |ui| {
+                                for (id, name) in &category_choices {
+                                    form_changed |= ui.selectable_value(&mut state.target_category_id, Some(id.clone()), name).changed();
+                                }
+                            });
+                        ui.end_row();
+                    }
+                    BulkActionKind::ScaleAmount => {
+                        ui.label("Multiply by:");
+                        form_changed |= ui.text_edit_singleline(&mut state.factor).changed();
+                        ui.end_row();
+                    }
+                }
+            });
+
+            if form_changed {
+                state.preview = None;
+                state.previewing = false;
+                state.status = None;
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!state.previewing, egui::Button::new("Preview")).clicked() {
+                    run_preview = true;
+                }
+                let count = state.preview.as_ref().map_or(0, |p| p.edited.len());
+                if ui.add_enabled(count > 0, egui::Button::new(format!("Apply {} Change(s)", count))).clicked() {
+                    apply = true;
+                }
+                if state.previewing {
+                    ui.spinner();
+                }
+            });
+
+            match &state.status {
+                Some(Ok(message)) => {
+                    ui.label(egui::RichText::new(message).color(egui::Color32::GREEN));
+                }
+                Some(Err(message)) => {
+                    ui.label(egui::RichText::new(message).color(egui::Color32::RED));
+                }
+                None => {}
+            }
+
+            if let Some(preview) = &state.preview {
+                if preview.skipped_locked > 0 {
+                    ui.label(egui::RichText::new(format!(
+                        "{} matching flow(s) are in locked years and will not be changed.",
+                        preview.skipped_locked
+                    )).color(egui::Color32::YELLOW));
+                }
+                if !preview.rejected.is_empty() {
+                    ui.label(egui::RichText::new(format!(
+                        "{} matching flow(s) would fail validation and will not be changed:",
+                        preview.rejected.len()
+                    )).color(egui::Color32::YELLOW));
+                    egui::ScrollArea::vertical().id_source("bulk_edit_rejected").max_height(120.0).show(ui, |ui| {
+                        egui::Grid::new("bulk_edit_rejected_grid").striped(true).show(ui, |ui| {
+                            for (flow, errors) in &preview.rejected {
+                                ui.label(flow.date.to_string());
+                                ui.label(&flow.description);
+                                ui.label(egui::RichText::new(errors.join("\n")).color(egui::Color32::RED));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+                if preview.edited.is_empty() {
+                    ui.label("No flows would change.");
+                } else {
+                    ui.label("Nothing has been saved yet. These flows would change:");
+                    egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                        egui::Grid::new("bulk_edit_preview").striped(true).show(ui, |ui| {
+                            ui.strong("Date");
+                            ui.strong("Description");
+                            ui.strong("Changes");
+                            ui.end_row();
+                            for (before, after) in &preview.diff.flows.changed {
+                                ui.label(before.date.to_string());
+                                ui.label(&before.description);
+                                ui.label(bulk_edit::describe_change(before, after, &app.categories).join("\n"));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            }
+        });
+
+    if run_preview {
+        match app.bulk_edit_state.build() {
+            Ok(edit) => start_preview(app, edit),
+            Err(e) => {
+                app.bulk_edit_state.preview = None;
+                app.bulk_edit_state.status = Some(Err(e));
+            }
+        }
+    }
+
+    if apply && let Some(preview) = app.bulk_edit_state.preview.take() {
+        let total = preview.edited.len();
+        let saved = app.apply_bulk_edit(preview.edited);
+        app.bulk_edit_state.status = Some(if saved == total {
+            Ok(format!("Saved {} flow(s).", saved))
+        } else {
+            Err(format!("Saved {} of {} flows; see the log for the rest.", saved, total))
+        });
+    }
+
+    app.show_bulk_edit_dialog = show_window;
+}
+
+/// Works out the preview of `edit` on the database thread. Edited flows are
+/// checked against every flow, in any category, so they're loaded there
+/// (with the pending changes replayed) rather than into `flow_cache`.
+fn start_preview(app: &mut PreftApp, edit: BulkEdit) {
+    let categories = app.categories.clone();
+    let uniqueness_rules = app.user_settings.uniqueness_rules.clone();
+    let locked_years = app.locked_years.clone();
+    let pending_changes = app.pending_changes.clone();
+    app.bulk_edit_state.preview = None;
+    app.bulk_edit_state.status = None;
+    app.bulk_edit_state.previewing = true;
+    let previewing = app.db.query(move |db| -> anyhow::Result<BulkEditPreview> {
+        let mut flows = db.load_flows()?;
+        pending_changes.apply_to(&mut flows);
+        Ok(bulk_edit::preview(&edit, &flows, &categories, &uniqueness_rules, |f| locked_years.contains(&f.date.year())))
+    });
+    app.when_done(previewing, |app, result| {
+        let state = &mut app.bulk_edit_state;
+        // The form changed since
+        if !std::mem::take(&mut state.previewing) {
+            return;
+        }
+        match result.and_then(|preview| preview) {
+            Ok(preview) => state.preview = Some(preview),
+            Err(e) => state.status = Some(Err(format!("Failed to load the flows: {}", e))),
+        }
+    });
+}
@@ -1,8 +1,27 @@
 use eframe::egui;
 use log::{info, warn, error};
 
-use crate::models::{Category, CategoryField, FieldType};
+use crate::models::{Category, CategoryField, FieldType, JurisdictionTreatment, OptionRenames, UniquenessRule};
 use crate::app::PreftApp;
+use crate::db::FlowScope;
+use crate::locale::NumberFormat;
+
+/// One option of the Select field being edited. `original` is the name it
+/// was saved with, so renaming it can carry flows' values over; options
+/// added in this edit have none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectOptionDraft {
+    pub original: Option<String>,
+    pub name: String,
+}
+
+/// The category editor's tabs: everyday settings up front, tax options
+/// (which most categories never need) behind "Advanced".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CategoryEditorTab {
+    Basic,
+    Advanced,
+}
 
 pub fn show_category_editor(ui: &mut egui::Ui, app: &mut PreftApp) {
     if app.show_category_editor {
@@ -16,6 +35,13 @@ pub fn show_category_editor(ui: &mut egui::Ui, app: &mut PreftApp) {
             } else {
                 app.new_category = Some(Category::new("New Category".to_string()));
             }
+            app.category_editor_tab = CategoryEditorTab::Basic;
+            app.category_uniqueness_draft = app.new_category.as_ref()
+                .map(|category| app.user_settings.get_uniqueness_rules(&category.id).to_vec())
+                .unwrap_or_default();
+            app.category_number_format_draft = app.new_category.as_ref()
+                .and_then(|category| app.user_settings.category_number_formats.get(&category.id).cloned());
+            app.category_option_renames = OptionRenames::default();
         }
 
         // Take the category out of the Option to avoid borrowing issues
@@ -29,105 +55,23 @@ pub fn show_category_editor(ui: &mut egui::Ui, app: &mut PreftApp) {
                 .show(ui.ctx(), |ui| {
                     ui.vertical(|ui| {
                         ui.heading(if app.editing_category.is_some() { "Edit Category" } else { "New Category" });
-                        
-                        // Category name
-                        ui.horizontal(|ui| {
-                            ui.label("Name:");
-                            if ui.text_edit_singleline(&mut category.name).changed() {
-                                // Name is updated directly in the category
-                            }
-                        });
 
-                        // Flow type
                         ui.horizontal(|ui| {
-                            ui.label("Type:");
-                            let mut flow_type = category.flow_type.clone();
-                            egui::ComboBox::from_label("")
-                                .selected_text(format!("{:?}", flow_type))
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(&mut flow_type, crate::models::FlowType::Income, "Income");
-                                    ui.selectable_value(&mut flow_type, crate::models::FlowType::Expense, "Expense");
-                                });
-                            category.flow_type = flow_type;
+                            ui.selectable_value(&mut app.category_editor_tab, CategoryEditorTab::Basic, "Basic");
+                            ui.selectable_value(&mut app.category_editor_tab, CategoryEditorTab::Advanced, "Advanced");
                         });
-
-                        // Tax deduction settings
-                        ui.horizontal(|ui| {
-                            ui.label("Allow Tax Deduction:");
-                            if ui.checkbox(&mut category.tax_deduction.deduction_allowed, "").changed() {
-                                // Tax deduction setting is updated directly
-                            }
-                        });
-
-                        // Default tax deduction value
-                        ui.horizontal(|ui| {
-                            ui.label("Default Tax Deductible:");
-                            if ui.checkbox(&mut category.tax_deduction.default_value, "").changed() {
-                                // Default deductible value is updated directly
-                            }
-                        });
-
                         ui.separator();
 
-                        // Show existing fields
-                        if !category.fields.is_empty() {
-                            ui.heading("Fields");
-                            let mut indices_to_remove = Vec::new();
-                            egui::Grid::new("fields_grid")
-                                .striped(true)
-                                .show(ui, |ui| {
-                                    for (index, field) in category.fields.iter().enumerate() {
-                                        ui.label(&field.name);
-                                        ui.label(match field.field_type {
-                                            FieldType::Text => "Text",
-                                            FieldType::Integer => "Whole Number",
-                                            FieldType::Float => "Decimal Number",
-                                            FieldType::Currency => "Currency",
-                                            FieldType::Boolean => "Boolean",
-                                            FieldType::Date => "Date",
-                                            #[allow(deprecated)]
-                                            FieldType::Number => "Decimal Number",
-                                            FieldType::Select(_) => "Select",
-                                        });
-                                        if let Some(default) = &field.default_value {
-                                            ui.label(default);
-                                        } else {
-                                            ui.label("No default");
-                                        }
-                                        if ui.button("Edit").clicked() {
-                                            app.editing_field = Some(field.clone());
-                                            app.show_field_editor = true;
-                                        }
-                                        if ui.button("Remove").clicked() && !indices_to_remove.contains(&index) {
-                                            indices_to_remove.push(index);
-                                        }
-                                        ui.end_row();
-                                    }
-                                });
-                            
-                            // Remove fields in reverse order to avoid index shifting
-                            if !indices_to_remove.is_empty() {
-                                indices_to_remove.sort_unstable();
-                                indices_to_remove.dedup();
-                                for &index in indices_to_remove.iter().rev() {
-                                    if index < category.fields.len() {
-                                        category.fields.remove(index);
-                                    }
-                                }
+                        match app.category_editor_tab {
+                            CategoryEditorTab::Basic => show_basic_tab(ui, app, &mut category),
+                            CategoryEditorTab::Advanced => {
+                                show_currency_selector(ui, &mut app.category_number_format_draft, &app.user_settings.number_format);
+                                show_advanced_tab(ui, &mut category);
+                                ui.separator();
+                                show_uniqueness_rules_editor(ui, &category, &mut app.category_uniqueness_draft);
                             }
                         }
 
-                        // Add field button
-                        if ui.button("Add Field").clicked() {
-                            app.editing_field = Some(CategoryField {
-                                name: String::new(),
-                                field_type: FieldType::Text,
-                                required: false,
-                                default_value: None,
-                            });
-                            app.show_field_editor = true;
-                        }
-
                         ui.separator();
 
                         // Show field editor if needed
@@ -135,11 +79,22 @@ pub fn show_category_editor(ui: &mut egui::Ui, app: &mut PreftApp) {
                             show_field_editor(ui, app, &mut category);
                         }
 
+                        ui.collapsing("Flow Editor Preview", |ui| {
+                            show_flow_editor_preview(ui, &category);
+                        });
+
+                        // Checked every frame, so problems show up as they're
+                        // typed rather than after a failed save.
+                        let errors = category.validation_errors();
+                        for error in &errors {
+                            ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+                        }
+
                         ui.separator();
 
                         // Save/Cancel buttons
                         ui.horizontal(|ui| {
-                            if ui.button("Save").clicked() {
+                            if ui.add_enabled(errors.is_empty(), egui::Button::new("Save")).clicked() {
                                 should_save = true;
                             }
                             if ui.button("Cancel").clicked() {
@@ -151,14 +106,29 @@ pub fn show_category_editor(ui: &mut egui::Ui, app: &mut PreftApp) {
 
             // Handle save/cancel after the window is closed
             if should_save {
+                // A jurisdiction row left unnamed doesn't identify anything.
+                category.tax_deduction.jurisdictions.retain(|t| !t.jurisdiction.trim().is_empty());
+                // Fields can be renamed or removed while editing, so rules
+                // only keep the fields that still exist.
+                let mut rules = std::mem::take(&mut app.category_uniqueness_draft);
+                for rule in &mut rules {
+                    rule.fields.retain(|name| category.fields.iter().any(|f| &f.name == name));
+                }
+                rules.retain(|rule| !rule.fields.is_empty());
+                if app.user_settings.get_uniqueness_rules(&category.id) != rules.as_slice() {
+                    app.user_settings.set_uniqueness_rules(&category.id, rules);
+                    app.save_settings("save uniqueness rules");
+                }
+                let number_format = app.category_number_format_draft.take();
+                if app.user_settings.category_number_formats.get(&category.id) != number_format.as_ref() {
+                    app.user_settings.set_category_number_format(&category.id, number_format);
+                    app.save_settings("save category currency");
+                    app.dashboard.mark_for_update();
+                }
                 if app.editing_category.is_some() {
                     // Update existing category
-                    if let Some(pos) = app.categories.iter().position(|c| c.id == category.id) {
-                        app.categories[pos] = category.clone();
-                        if let Err(e) = app.db.save_category(&category) {
-                            log::error!("Failed to save category: {}", e);
-                        }
-                    }
+                    let option_renames = std::mem::take(&mut app.category_option_renames);
+                    app.update_category(category, &option_renames);
                     app.editing_category = None;
                 } else {
                     // Add new category
@@ -176,6 +146,257 @@ pub fn show_category_editor(ui: &mut egui::Ui, app: &mut PreftApp) {
     }
 }
 
+/// Name, type and custom fields.
+fn show_basic_tab(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Category) {
+    ui.horizontal(|ui| {
+        ui.label("Name:");
+        ui.text_edit_singleline(&mut category.name);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Icon:");
+        let mut icon = category.icon.clone().unwrap_or_default();
+        if ui.add(egui::TextEdit::singleline(&mut icon).desired_width(40.0).hint_text("🛒")).changed() {
+            category.icon = Some(icon.trim().to_string()).filter(|icon| !icon.is_empty());
+        }
+
+        let mut has_color = category.color.is_some();
+        if ui.checkbox(&mut has_color, "Color").changed() {
+            category.color = has_color.then_some([66, 133, 244]);
+        }
+        if let Some(color) = &mut category.color {
+            ui.color_edit_button_srgb(color);
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Type:");
+        let mut flow_type = category.flow_type.clone();
+        egui::ComboBox::from_label("")
+            .selected_text(format!("{:?}", flow_type))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut flow_type, crate::models::FlowType::Income, "Income");
+                ui.selectable_value(&mut flow_type, crate::models::FlowType::Expense, "Expense");
+            });
+        category.flow_type = flow_type;
+    });
+
+    ui.separator();
+
+    // Show existing fields
+    if !category.fields.is_empty() {
+        ui.heading("Fields");
+        let mut indices_to_remove = Vec::new();
+        egui::Grid::new("fields_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, field) in category.fields.iter().enumerate() {
+                    ui.label(&field.name);
+                    ui.label(field.field_type.get_display_name());
+                    if let Some(default) = &field.default_value {
+                        ui.label(default);
+                    } else {
+                        ui.label("No default");
+                    }
+                    if ui.button("Edit").clicked() {
+                        start_field_edit(app, field.clone());
+                    }
+                    if ui.button("Remove").clicked() && !indices_to_remove.contains(&index) {
+                        indices_to_remove.push(index);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        // Remove fields in reverse order to avoid index shifting
+        if !indices_to_remove.is_empty() {
+            indices_to_remove.sort_unstable();
+            indices_to_remove.dedup();
+            for &index in indices_to_remove.iter().rev() {
+                if index < category.fields.len() {
+                    category.fields.remove(index);
+                }
+            }
+        }
+    }
+
+    // Add field button
+    if ui.button("Add Field").clicked() {
+        start_field_edit(app, CategoryField {
+            name: String::new(),
+            field_type: FieldType::Text,
+            required: false,
+            default_value: None,
+        });
+    }
+}
+
+/// Opens the field editor on `field`, with its options if it's a Select.
+fn start_field_edit(app: &mut PreftApp, field: CategoryField) {
+    app.select_option_drafts = match &field.field_type {
+        FieldType::Select(options) => options.iter()
+            .map(|option| SelectOptionDraft { original: Some(option.clone()), name: option.clone() })
+            .collect(),
+        _ => Vec::new(),
+    };
+    app.editing_field = Some(field);
+    app.show_field_editor = true;
+}
+
+/// The currency this category's amounts are shown in, for e.g. a rental
+/// property abroad. Only the symbol and separators change; amounts aren't
+/// converted.
+fn show_currency_selector(ui: &mut egui::Ui, draft: &mut Option<NumberFormat>, app_format: &NumberFormat) {
+    let presets = NumberFormat::presets();
+    let selected_text = match draft {
+        None => "Same as Settings".to_string(),
+        Some(format) => presets.iter()
+            .find(|(_, preset)| preset == format)
+            .map_or_else(|| format.format_currency(1234.56), |(name, _)| name.to_string()),
+    };
+    ui.horizontal(|ui| {
+        ui.label("Currency:")
+            .on_hover_text("How this category's amounts are shown. Amounts aren't converted: totals in another currency are kept apart from the rest.");
+        egui::ComboBox::from_id_source("category_currency")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(draft, None, format!("Same as Settings ({})", app_format.format_currency(1234.56)));
+                for (name, preset) in presets {
+                    ui.selectable_value(draft, Some(preset), name);
+                }
+            });
+    });
+}
+
+/// Tax deduction settings.
+fn show_advanced_tab(ui: &mut egui::Ui, category: &mut Category) {
+    ui.horizontal(|ui| {
+        ui.label("Allow Tax Deduction:");
+        ui.checkbox(&mut category.tax_deduction.deduction_allowed, "");
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Default Tax Deductible:");
+        ui.checkbox(&mut category.tax_deduction.default_value, "");
+    });
+
+    if category.tax_deduction.deduction_allowed {
+        show_jurisdiction_editor(ui, &mut category.tax_deduction.jurisdictions);
+    }
+}
+
+/// Sets of custom fields whose values must be unique together across the
+/// category's flows, each edited as a row of field checkboxes.
+fn show_uniqueness_rules_editor(ui: &mut egui::Ui, category: &Category, rules: &mut Vec<UniquenessRule>) {
+    ui.label("Unique Together:")
+        .on_hover_text("Saving a flow whose values for every checked field match another flow's is refused");
+    if category.fields.is_empty() {
+        ui.label("Add custom fields to set up uniqueness rules.");
+        return;
+    }
+
+    let mut remove = None;
+    for (i, rule) in rules.iter_mut().enumerate() {
+        ui.horizontal_wrapped(|ui| {
+            for field in &category.fields {
+                let mut included = rule.fields.contains(&field.name);
+                if ui.checkbox(&mut included, field.display_name()).changed() {
+                    if included {
+                        rule.fields.push(field.name.clone());
+                    } else {
+                        rule.fields.retain(|name| name != &field.name);
+                    }
+                }
+            }
+            if ui.button("Remove").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        rules.remove(i);
+    }
+    if ui.button("Add Rule").clicked() {
+        rules.push(UniquenessRule::default());
+    }
+}
+
+/// A disabled mock-up of the flow editor for `category`: the same rows in
+/// the same order, prefilled with each field's default.
+fn show_flow_editor_preview(ui: &mut egui::Ui, category: &Category) {
+    ui.add_enabled_ui(false, |ui| {
+        egui::Grid::new("flow_editor_preview").show(ui, |ui| {
+            ui.label("Date:");
+            ui.label(chrono::Local::now().date_naive().format("%Y-%m-%d").to_string());
+            ui.end_row();
+            ui.label("Amount:");
+            ui.text_edit_singleline(&mut String::new());
+            ui.end_row();
+            ui.label("Description:");
+            ui.text_edit_singleline(&mut String::new());
+            ui.end_row();
+            if category.tax_deduction.deduction_allowed {
+                ui.label("Tax Deductible:");
+                ui.checkbox(&mut category.tax_deduction.default_value.clone(), "");
+                ui.end_row();
+            }
+
+            for (index, field) in category.fields.iter().enumerate() {
+                ui.label(format!("{}:", field.display_name()));
+                let mut default = field.default_value.clone().unwrap_or_default();
+                match &field.field_type {
+                    FieldType::Boolean => {
+                        ui.checkbox(&mut default.eq_ignore_ascii_case("true"), "");
+                    }
+                    FieldType::Select(options) => {
+                        let selected = if default.is_empty() { options.first().cloned().unwrap_or_default() } else { default };
+                        egui::ComboBox::from_id_source(("flow_editor_preview_select", index))
+                            .selected_text(selected)
+                            .show_ui(ui, |_| {});
+                    }
+                    FieldType::Date => {
+                        let _ = ui.button(if default.is_empty() { "Today".to_string() } else { default });
+                    }
+                    _ => {
+                        ui.text_edit_singleline(&mut default);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Per-jurisdiction overrides of a category's deductibility. Jurisdictions
+/// left out here follow each flow's own deductible flag.
+fn show_jurisdiction_editor(ui: &mut egui::Ui, jurisdictions: &mut Vec<JurisdictionTreatment>) {
+    ui.collapsing(format!("Tax Jurisdictions ({})", jurisdictions.len()), |ui| {
+        let mut index_to_remove = None;
+        egui::Grid::new("jurisdictions_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, treatment) in jurisdictions.iter_mut().enumerate() {
+                    ui.text_edit_singleline(&mut treatment.jurisdiction);
+                    ui.checkbox(&mut treatment.deductible, "Deductible");
+                    if ui.button("Remove").clicked() {
+                        index_to_remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = index_to_remove {
+            jurisdictions.remove(index);
+        }
+
+        if ui.button("Add Jurisdiction").clicked() {
+            jurisdictions.push(JurisdictionTreatment {
+                jurisdiction: String::new(),
+                deductible: true,
+            });
+        }
+    });
+}
+
 fn show_field_editor(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Category) {
     if let Some(mut field) = app.editing_field.take() {
         let mut should_save = false;
@@ -202,17 +423,7 @@ fn show_field_editor(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Categ
                         let mut field_type = field.field_type.clone();
                         let old_type = field_type.clone();
                         egui::ComboBox::from_label("")
-                            .selected_text(match field_type {
-                                FieldType::Text => "Text",
-                                FieldType::Integer => "Whole Number",
-                                FieldType::Float => "Decimal Number",
-                                FieldType::Currency => "Currency",
-                                FieldType::Boolean => "Boolean",
-                                FieldType::Date => "Date",
-                                #[allow(deprecated)]
-                                FieldType::Number => "Decimal Number",
-                                FieldType::Select(_) => "Select",
-                            })
+                            .selected_text(field_type.get_display_name())
                             .show_ui(ui, |ui| {
                                 ui.selectable_value(&mut field_type, FieldType::Text, "Text");
                                 ui.selectable_value(&mut field_type, FieldType::Integer, "Whole Number");
@@ -220,6 +431,12 @@ fn show_field_editor(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Categ
                                 ui.selectable_value(&mut field_type, FieldType::Currency, "Currency");
                                 ui.selectable_value(&mut field_type, FieldType::Boolean, "Boolean");
                                 ui.selectable_value(&mut field_type, FieldType::Date, "Date");
+                                // Options are kept while it stays a Select.
+                                if ui.selectable_label(matches!(field_type, FieldType::Select(_)), "Select").clicked()
+                                    && !matches!(field_type, FieldType::Select(_))
+                                {
+                                    field_type = FieldType::Select(Vec::new());
+                                }
                             });
                         
                         // Handle default value conversion when type changes
@@ -274,23 +491,39 @@ fn show_field_editor(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Categ
                                 None
                             };
                         }
+                        if !matches!(field_type, FieldType::Select(_)) {
+                            app.select_option_drafts.clear();
+                        }
                         field.field_type = field_type;
                     });
 
-                    // Default value
-                    ui.horizontal(|ui| {
-                        ui.label("Default Value:");
-                        let mut default_value = field.default_value.clone().unwrap_or_default();
-                        if ui.text_edit_singleline(&mut default_value).changed() {
-                            field.default_value = Some(default_value);
-                        }
-                    });
+                    ui.checkbox(&mut field.required, "Required")
+                        .on_hover_text("Flows can't be saved with this field left blank");
+
+                    if matches!(field.field_type, FieldType::Select(_)) {
+                        let existing = existing_values(app, category, &field.name);
+                        show_select_options_editor(ui, &mut app.select_option_drafts, &mut field, &existing);
+                    } else {
+                        // Default value
+                        ui.horizontal(|ui| {
+                            ui.label("Default Value:");
+                            let mut default_value = field.default_value.clone().unwrap_or_default();
+                            if ui.text_edit_singleline(&mut default_value).changed() {
+                                field.default_value = Some(default_value);
+                            }
+                        });
+                    }
+
+                    let errors = field.validation_errors();
+                    for error in &errors {
+                        ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+                    }
 
                     ui.separator();
 
                     // Save/Cancel buttons
                     ui.horizontal(|ui| {
-                        if ui.button("Save").clicked() {
+                        if ui.add_enabled(errors.is_empty(), egui::Button::new("Save")).clicked() {
                             should_save = true;
                         }
                         if ui.button("Cancel").clicked() {
@@ -302,6 +535,11 @@ fn show_field_editor(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Categ
 
         // Handle save/cancel after the window is closed
         if should_save {
+            for draft in std::mem::take(&mut app.select_option_drafts) {
+                if let Some(original) = draft.original.filter(|original| original != draft.name.trim()) {
+                    app.category_option_renames.rename(&field.name, &original, draft.name.trim());
+                }
+            }
             // If this is a new field, add it to the category
             if !field.name.is_empty() {
                 // Check if we're editing an existing field
@@ -322,4 +560,92 @@ fn show_field_editor(ui: &mut egui::Ui, app: &mut PreftApp, category: &mut Categ
             app.editing_field = Some(field);
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Values the category's flows already have for the field named `name`,
+/// sorted, for turning a Text field into a Select without losing them.
+/// None are offered while the flows are still loading.
+fn existing_values(app: &mut PreftApp, category: &Category, name: &str) -> Vec<String> {
+    let flows = app.flows_in(&FlowScope::category(&category.id, None)).unwrap_or_default();
+    let values: std::collections::BTreeSet<&str> = flows.iter()
+        .filter_map(|f| f.custom_fields.get(name))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect();
+    values.into_iter().map(str::to_string).collect()
+}
+
+/// The options of a Select field: renamed in place, added, removed and
+/// moved up or down, with one of them (or none) as the default. The field's
+/// `field_type` is kept in step with `drafts` so validation and the flow
+/// editor preview see the options as they're typed.
+fn show_select_options_editor(ui: &mut egui::Ui, drafts: &mut Vec<SelectOptionDraft>, field: &mut CategoryField, existing: &[String]) {
+    ui.label("Options:");
+    let mut remove = None;
+    let mut move_up = None;
+    egui::Grid::new("select_options_grid").show(ui, |ui| {
+        let count = drafts.len();
+        for (index, draft) in drafts.iter_mut().enumerate() {
+            let before = draft.name.trim().to_string();
+            if ui.text_edit_singleline(&mut draft.name).changed()
+                && field.default_value.as_deref() == Some(before.as_str())
+            {
+                field.default_value = Some(draft.name.trim().to_string());
+            }
+            if ui.add_enabled(index > 0, egui::Button::new("⬆")).on_hover_text("Move up").clicked() {
+                move_up = Some(index);
+            }
+            if ui.add_enabled(index + 1 < count, egui::Button::new("⬇")).on_hover_text("Move down").clicked() {
+                move_up = Some(index + 1);
+            }
+            let remove_button = ui.button("Remove");
+            let remove_button = match &draft.original {
+                Some(original) => remove_button.on_hover_text(format!("Flows set to \"{}\" lose their value when the category is saved", original)),
+                None => remove_button,
+            };
+            if remove_button.clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = move_up {
+        drafts.swap(index - 1, index);
+    }
+    if let Some(index) = remove {
+        let removed = drafts.remove(index);
+        if field.default_value.as_deref() == Some(removed.name.trim()) {
+            field.default_value = None;
+        }
+    }
+
+    let missing: Vec<&String> = existing.iter()
+        .filter(|value| !drafts.iter().any(|draft| draft.name.trim() == value.as_str()))
+        .collect();
+    ui.horizontal(|ui| {
+        if ui.button("Add Option").clicked() {
+            drafts.push(SelectOptionDraft { original: None, name: String::new() });
+        }
+        if !missing.is_empty()
+            && ui.button(format!("Add Values in Use ({})", missing.len()))
+                .on_hover_text("Add the values flows already have for this field, so they're kept")
+                .clicked()
+        {
+            drafts.extend(missing.iter().map(|value| SelectOptionDraft { original: None, name: value.to_string() }));
+        }
+    });
+
+    let options: Vec<String> = drafts.iter().map(|draft| draft.name.trim().to_string()).collect();
+    ui.horizontal(|ui| {
+        ui.label("Default Value:");
+        egui::ComboBox::from_id_source("select_default_value")
+            .selected_text(field.default_value.clone().unwrap_or_else(|| "No default".to_string()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut field.default_value, None, "No default");
+                for option in options.iter().filter(|option| !option.is_empty()) {
+                    ui.selectable_value(&mut field.default_value, Some(option.clone()), option);
+                }
+            });
+    });
+    field.field_type = FieldType::Select(options);
+}
@@ -1,33 +1,28 @@
 use eframe::egui;
-use chrono::{Local, NaiveDate, Datelike};
+use chrono::{Datelike, Local, NaiveDate};
 use log::warn;
+use std::borrow::Borrow;
+use std::collections::BTreeSet;
+use egui_extras::{Column, TableBuilder};
 
-use crate::models::{Flow, Category};
-use crate::app::PreftApp;
+use crate::db::{CategorySummary, FlowScope};
+use crate::locale::NumberFormat;
+use crate::models::{Flow, Category, FieldType};
+use crate::settings::{CategoryView, SortColumn, YearView};
+use crate::app::{GuardedAction, PreftApp, Stored, StoredTotals};
 use crate::utils;
+use crate::ui::sparkline::sparkline;
+use crate::year_grid::{MONTH_LABELS, YearGrid};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum SortColumn {
-    Date,
-    Amount,
-    Description,
-}
-
-impl SortColumn {
-    /// Direction a column starts in the first time it's selected.
-    fn default_ascending(self) -> bool {
-        match self {
-            SortColumn::Date => false,        // newest first
-            SortColumn::Amount => false,      // largest first
-            SortColumn::Description => true,  // A-Z
-        }
-    }
-}
+/// How many months the header's trend sparkline covers.
+pub(crate) const TREND_MONTHS: usize = 12;
 
 /// Sorts flows in place by the given column/direction. `Description` sorts
-/// case-insensitively so e.g. "apple" comes before "Banana".
-fn sort_flows(flows: &mut [Flow], column: SortColumn, ascending: bool) {
+/// case-insensitively so e.g. "apple" comes before "Banana". Works on
+/// borrowed flows too, so the table can sort without cloning any.
+fn sort_flows<F: Borrow<Flow>>(flows: &mut [F], column: SortColumn, ascending: bool) {
     flows.sort_by(|a, b| {
+        let (a, b) = (a.borrow(), b.borrow());
         let ordering = match column {
             SortColumn::Date => a.date.cmp(&b.date),
             SortColumn::Amount => a.amount.partial_cmp(&b.amount).unwrap_or(std::cmp::Ordering::Equal),
@@ -41,10 +36,33 @@ pub struct CategoryFlowsState {
     last_year_total: f64,
     this_year_total: f64,
     current_month_total: f64,
+    /// Net total per month for the last `TREND_MONTHS` months, oldest first.
+    monthly_trend: Vec<f64>,
     tracking_ratio: Option<f64>,
     needs_update: bool,
     sort_column: SortColumn,
     sort_ascending: bool,
+    /// Only flows at this location (compared ignoring case) are listed.
+    location_filter: Option<String>,
+    search: FlowSearch,
+    /// Set by the Search shortcut; the search bar takes focus next frame.
+    focus_search: bool,
+    /// Ids of the flows ticked for "Delete Selected". Flows the table stops
+    /// showing (e.g. after a search) drop out, so nothing unseen is deleted.
+    selected: BTreeSet<String>,
+    /// The flow last ticked or unticked, where a shift-click range starts.
+    selection_anchor: Option<String>,
+    /// Set while the "Delete Selected" confirmation is open.
+    confirm_delete_selected: bool,
+    /// The flow whose Delete button was clicked, while its confirmation is
+    /// open (see `ConfirmationSettings::delete_flow`).
+    confirm_delete_flow: Option<String>,
+    /// The cell open for editing in the table, if any.
+    inline_edit: Option<InlineEdit>,
+    /// The row the arrow keys move and Enter, Delete and Space act on, by
+    /// flow id and its position when last shown. If the flow goes away
+    /// (e.g. is deleted) the focus stays at that position.
+    focused_row: Option<(String, usize)>,
 }
 
 impl CategoryFlowsState {
@@ -53,10 +71,20 @@ impl CategoryFlowsState {
             last_year_total: 0.0,
             this_year_total: 0.0,
             current_month_total: 0.0,
+            monthly_trend: Vec::new(),
             tracking_ratio: None,
             needs_update: true,
             sort_column: SortColumn::Date,
             sort_ascending: false, // newest first, matching the table's prior hardcoded behavior
+            location_filter: None,
+            search: FlowSearch::default(),
+            focus_search: false,
+            selected: BTreeSet::new(),
+            selection_anchor: None,
+            confirm_delete_selected: false,
+            confirm_delete_flow: None,
+            inline_edit: None,
+            focused_row: None,
         }
     }
 
@@ -64,6 +92,28 @@ impl CategoryFlowsState {
         self.needs_update = true;
     }
 
+    pub fn request_search_focus(&mut self) {
+        self.focus_search = true;
+    }
+
+    /// This month's total and the trend sparkline, for listing beside the
+    /// category's name in the selector. Uses the same cached totals as the
+    /// category's own header, recomputed only once marked for update.
+    pub fn show_compact_trend(&mut self, ui: &mut egui::Ui, flows: Option<&[Flow]>, category: &Category, number_format: &NumberFormat, stored: &mut StoredTotals) {
+        self.update_totals(flows, category, stored);
+        self.show_trend(ui, number_format);
+        ui.label(egui::RichText::new(number_format.format_currency(self.current_month_total)).weak())
+            .on_hover_text("This month's total");
+    }
+
+    fn show_trend(&self, ui: &mut egui::Ui, number_format: &NumberFormat) {
+        sparkline(ui, &self.monthly_trend).on_hover_text(format!(
+            "Monthly totals over the last {} months (average {})",
+            TREND_MONTHS,
+            number_format.format_currency(self.monthly_trend.iter().sum::<f64>() / TREND_MONTHS as f64)
+        ));
+    }
+
     /// Clicking the active column's header flips its direction; clicking a
     /// different column switches to it at that column's default direction.
     fn toggle_sort(&mut self, column: SortColumn) {
@@ -75,8 +125,51 @@ impl CategoryFlowsState {
         }
     }
 
-    pub fn update_totals(&mut self, flows: &[Flow], category: &Category) {
-        self.update_totals_as_of(flows, category, Local::now().naive_local().date());
+    /// Sets the sort and location filter to the category's saved view (the
+    /// app sets the year filter, which isn't per category).
+    pub fn apply_view(&mut self, view: &CategoryView) {
+        self.sort_column = view.sort_column;
+        self.sort_ascending = view.sort_ascending;
+        self.location_filter = view.location.clone();
+    }
+
+    /// The table's sort and location filter as a view to save, opening on
+    /// `years`.
+    fn view(&self, years: Option<YearView>) -> CategoryView {
+        CategoryView {
+            sort_column: self.sort_column,
+            sort_ascending: self.sort_ascending,
+            years,
+            location: self.location_filter.clone(),
+        }
+    }
+
+    /// Recomputes the totals once marked for update, from the database's
+    /// summary when `stored` has one and over `flows` (see
+    /// `PreftApp::totals_fallback`) when it can't. While either loads the
+    /// old figures stay up, still marked.
+    pub fn update_totals(&mut self, flows: Option<&[Flow]>, category: &Category, stored: &mut StoredTotals) {
+        if !self.needs_update {
+            return;
+        }
+
+        match stored.summaries() {
+            Stored::Ready(summaries) => {
+                let summary = summaries.get(&category.id).cloned().unwrap_or_else(|| CategorySummary::empty(TREND_MONTHS));
+                self.last_year_total = summary.last_year_total;
+                self.this_year_total = summary.this_year_total;
+                self.current_month_total = summary.current_month_total;
+                self.monthly_trend = summary.monthly_trend;
+                self.tracking_ratio = summary.tracking_ratio;
+                self.needs_update = false;
+            }
+            Stored::Loading => {}
+            Stored::Unavailable => {
+                if let Some(flows) = flows {
+                    self.update_totals_as_of(flows, category, Local::now().naive_local().date());
+                }
+            }
+        }
     }
 
     /// Core of `update_totals`, parameterized on "today" so it's testable
@@ -91,53 +184,272 @@ impl CategoryFlowsState {
 
         self.last_year_total = flows.iter()
             .filter(|f| f.category_id == category.id && f.date.year() == current_year - 1)
-            .map(|f| f.amount)
+            .map(|f| f.net_amount())
             .sum();
 
         self.this_year_total = flows.iter()
             .filter(|f| f.category_id == category.id && f.date.year() == current_year)
-            .map(|f| f.amount)
+            .map(|f| f.net_amount())
             .sum();
 
         self.current_month_total = flows.iter()
             .filter(|f| f.category_id == category.id &&
                     f.date.year() == current_year &&
                     f.date.month() == current_month)
-            .map(|f| f.amount)
+            .map(|f| f.net_amount())
             .sum();
 
+        self.monthly_trend = utils::trailing_monthly_totals(flows, &category.id, as_of, TREND_MONTHS);
         self.tracking_ratio = utils::calculate_tracking_ratio_as_of(flows, category, as_of);
         self.needs_update = false;
     }
+
+    /// Recomputes this category's totals from scratch and describes each
+    /// cached one that disagrees. A state already marked for update is
+    /// expected to be stale, so it never reports anything.
+    pub(crate) fn discrepancies_as_of(&self, flows: &[Flow], category: &Category, as_of: NaiveDate) -> Vec<String> {
+        if self.needs_update {
+            return Vec::new();
+        }
+
+        let mut fresh = CategoryFlowsState::new();
+        fresh.update_totals_as_of(flows, category, as_of);
+
+        let mut found = Vec::new();
+        for (label, cached, expected) in [
+            ("last year total", self.last_year_total, fresh.last_year_total),
+            ("this year total", self.this_year_total, fresh.this_year_total),
+            ("current month total", self.current_month_total, fresh.current_month_total),
+        ] {
+            if utils::totals_differ(cached, expected) {
+                found.push(format!("{}: cached {} ${:.2}, expected ${:.2}", category.name, label, cached, expected));
+            }
+        }
+        let ratio_differs = match (self.tracking_ratio, fresh.tracking_ratio) {
+            (Some(cached), Some(expected)) => utils::totals_differ(cached, expected),
+            (cached, expected) => cached.is_some() != expected.is_some(),
+        };
+        if ratio_differs {
+            found.push(format!("{}: cached tracking ratio {:?}, expected {:?}", category.name, self.tracking_ratio, fresh.tracking_ratio));
+        }
+        found
+    }
 }
 
-pub fn show_category_flows(ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
-    // Get all data we need first
-    let flows = app.flows.clone();
-    let state = app.get_category_flows_state(&category.id);
-    
-    if state.needs_update {
-        state.update_totals(&flows, category);
-        state.tracking_ratio = utils::calculate_tracking_ratio(&flows, category);
-        state.needs_update = false;
+/// The search bar's criteria. Every set criterion must match.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FlowSearch {
+    /// Matched, ignoring case, against the description, the amount and
+    /// every custom field value.
+    text: String,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+impl FlowSearch {
+    fn is_active(&self) -> bool {
+        !self.text.trim().is_empty() || self.from.is_some() || self.to.is_some()
     }
 
-    ui.heading(format!("{} Flows", category.name));
+    /// Text without letters is also tried as an amount: it matches when the
+    /// amount's two-decimal form contains it with any currency symbol and
+    /// thousands separators removed, so "12.5" finds 12.50 and "$1,200"
+    /// finds 1200.00.
+    fn matches(&self, flow: &Flow) -> bool {
+        if self.from.is_some_and(|from| flow.date < from) || self.to.is_some_and(|to| flow.date > to) {
+            return false;
+        }
+        let text = self.text.trim().to_lowercase();
+        if text.is_empty() {
+            return true;
+        }
+        let amount_text: String = if text.chars().any(char::is_alphabetic) {
+            String::new()
+        } else {
+            text.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect()
+        };
+        flow.description.to_lowercase().contains(&text)
+            || (!amount_text.is_empty() && format!("{:.2}", flow.amount).contains(&amount_text))
+            || flow.custom_fields.values().any(|value| value.to_lowercase().contains(&text))
+    }
+}
+
+/// A cell that can be edited in place in the flows table.
+#[derive(Debug, Clone, PartialEq)]
+enum InlineCell {
+    Amount,
+    Description,
+    /// A custom field, by name.
+    Field(String),
+}
+
+/// A cell opened by double-clicking it in the flows table. Enter saves it,
+/// Escape or clicking elsewhere puts it back as it was.
+#[derive(Debug, Clone)]
+struct InlineEdit {
+    flow_id: String,
+    cell: InlineCell,
+    text: String,
+    /// Why the last save was refused, shown on the cell until it's fixed.
+    error: Option<String>,
+    /// Set when the cell (re)opens, so its text field takes focus.
+    focus: bool,
+    /// Set while the save waits on the uniqueness check (see
+    /// `PreftApp::save_inline_edit`).
+    saving: bool,
+}
+
+enum InlineOutcome {
+    Save,
+    Cancel,
+}
+
+impl InlineEdit {
+    fn start(flow: &Flow, cell: InlineCell) -> Self {
+        let text = match &cell {
+            InlineCell::Amount => flow.amount.to_string(),
+            InlineCell::Description => flow.description.clone(),
+            InlineCell::Field(name) => flow.custom_fields.get(name).cloned().unwrap_or_default(),
+        };
+        Self { flow_id: flow.id.clone(), cell, text, error: None, focus: true, saving: false }
+    }
+
+    fn is_on(&self, flow_id: &str, cell: &InlineCell) -> bool {
+        self.flow_id == flow_id && self.cell == *cell
+    }
+
+    /// `flow` with the edited value in place, or why it can't be saved.
+    /// Field values are stored the way the flow editor stores them, and
+    /// clearing one removes it. Amounts may also be typed in the category's
+    /// number format.
+    fn apply(&self, flow: &Flow, category: &Category, number_format: &NumberFormat) -> Result<Flow, String> {
+        let mut flow = flow.clone();
+        let text = self.text.trim();
+        let name = match &self.cell {
+            InlineCell::Amount => {
+                flow.amount = text.parse().ok().or_else(|| number_format.parse_amount(text))
+                    .ok_or_else(|| format!("\"{}\" isn't an amount", text))?;
+                return Ok(flow);
+            }
+            InlineCell::Description => {
+                flow.description = text.to_string();
+                return Ok(flow);
+            }
+            InlineCell::Field(name) => name,
+        };
+        let field = category.fields.iter().find(|f| f.name == *name)
+            .ok_or_else(|| format!("{} no longer has a {} field", category.name, name))?;
+        if text.is_empty() {
+            if let Some(error) = field.value_error(None) {
+                return Err(error);
+            }
+            flow.custom_fields.remove(name);
+            return Ok(flow);
+        }
+        let label = field.display_name();
+        #[allow(deprecated)]
+        let value = match &field.field_type {
+            FieldType::Text => text.to_string(),
+            FieldType::Integer => text.parse::<i64>().map(|n| n.to_string())
+                .map_err(|_| format!("{} must be a whole number", label))?,
+            FieldType::Float | FieldType::Number => text.parse::<f64>().map(|n| format!("{:.2}", n))
+                .map_err(|_| format!("{} must be a number", label))?,
+            FieldType::Currency => utils::stored_currency(text, number_format)
+                .ok_or_else(|| format!("{} must be an amount", label))?,
+            FieldType::Date => NaiveDate::parse_from_str(text, "%Y-%m-%d").map(|d| d.format("%Y-%m-%d").to_string())
+                .map_err(|_| format!("{} must be a date like 2024-01-31", label))?,
+            FieldType::Boolean => text.parse::<bool>().map(|b| b.to_string())
+                .map_err(|_| format!("{} must be true or false", label))?,
+            FieldType::Select(options) => options.iter().find(|option| option.eq_ignore_ascii_case(text)).cloned()
+                .ok_or_else(|| format!("{} must be one of: {}", label, options.join(", ")))?,
+        };
+        flow.custom_fields.insert(name.clone(), value);
+        Ok(flow)
+    }
+
+    /// Draws the open cell: a checkbox or a dropdown for Boolean and Select
+    /// fields, which save as soon as they change, otherwise a text field.
+    fn show(&mut self, ui: &mut egui::Ui, field_type: Option<&FieldType>) -> Option<InlineOutcome> {
+        if self.saving {
+            ui.spinner();
+            return None;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            return Some(InlineOutcome::Cancel);
+        }
+        match field_type {
+            Some(FieldType::Boolean) => {
+                let mut value = self.text.parse().unwrap_or(false);
+                if ui.checkbox(&mut value, "").changed() {
+                    self.text = value.to_string();
+                    return Some(InlineOutcome::Save);
+                }
+                None
+            }
+            Some(FieldType::Select(options)) => {
+                let mut selected = self.text.clone();
+                egui::ComboBox::from_id_source(format!("inline_select_{}", self.flow_id))
+                    .selected_text(&selected)
+                    .show_ui(ui, |ui| {
+                        for option in options {
+                            ui.selectable_value(&mut selected, option.clone(), option);
+                        }
+                    });
+                if selected != self.text {
+                    self.text = selected;
+                    return Some(InlineOutcome::Save);
+                }
+                None
+            }
+            _ => {
+                let mut text_edit = egui::TextEdit::singleline(&mut self.text).desired_width(f32::INFINITY);
+                if self.error.is_some() {
+                    text_edit = text_edit.text_color(egui::Color32::RED);
+                }
+                let mut response = ui.add(text_edit);
+                if std::mem::take(&mut self.focus) {
+                    response.request_focus();
+                }
+                if let Some(error) = &self.error {
+                    response = response.on_hover_text(error);
+                }
+                response.lost_focus().then(|| if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    InlineOutcome::Save
+                } else {
+                    InlineOutcome::Cancel
+                })
+            }
+        }
+    }
+}
+
+pub fn show_category_flows(ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
+    let number_format = app.user_settings.number_format_for(&category.id).clone();
+    let fallback = app.totals_fallback();
+    let state = app.category_flows_state.entry(category.id.clone()).or_insert_with(CategoryFlowsState::new);
+    state.update_totals(fallback.as_deref().map(Vec::as_slice), category, &mut app.stored_totals);
+
+    ui.horizontal(|ui| {
+        ui.heading(super::category_label(category));
+        ui.heading("Flows");
+    });
     ui.separator();
 
     // Display category totals
     ui.horizontal(|ui| {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
             ui.label("Last Year:");
-            ui.label(format!("${:.2}", state.last_year_total));
+            ui.label(number_format.format_currency(state.last_year_total));
             ui.add_space(20.0);
             
             ui.label("This Year:");
-            ui.label(format!("${:.2}", state.this_year_total));
+            ui.label(number_format.format_currency(state.this_year_total));
             ui.add_space(20.0);
 
             ui.label("Current Month:");
-            ui.label(format!("${:.2}", state.current_month_total));
+            ui.label(number_format.format_currency(state.current_month_total));
+            ui.add_space(10.0);
+            state.show_trend(ui, &number_format);
             ui.add_space(20.0);
 
             if let Some(ratio) = state.tracking_ratio {
@@ -153,12 +465,164 @@ pub fn show_category_flows(ui: &mut egui::Ui, app: &mut PreftApp, category: &Cat
         });
     });
 
-    if ui.button("Add Flow").clicked() {
+    if !app.read_only && ui.button("Add Flow").clicked() {
         app.create_new_flow(category);
     }
 
+    // Only the year filter's flows are loaded, not the category's whole
+    // history
+    let scope = FlowScope::category(&category.id, app.user_settings.get_year_filter());
+    let Some(flows) = app.flows_in(&scope) else {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Loading flows...");
+        });
+        return;
+    };
+
+    show_year_grid(ui, app, &flows, category);
+
+    show_locations(ui, app, &flows, category);
+
+    show_search_bar(ui, app, category);
+
+    if !app.read_only {
+        show_view_buttons(ui, app, category);
+    }
+
     // Show flows table
-    show_flows_table(ui, app, category);
+    show_flows_table(ui, app, &flows, category);
+}
+
+/// Saves the table's sort, location filter and year filter as the view the
+/// category opens with, or forgets it. A year filter on a past year isn't
+/// kept, since it would go stale; the current year or all years are.
+fn show_view_buttons(ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
+    let current_year = Local::now().year();
+    let years = match app.user_settings.get_year_filter() {
+        None => Some(YearView::AllYears),
+        Some(year) if year == current_year => Some(YearView::CurrentYear),
+        Some(_) => None,
+    };
+    let view = app.get_category_flows_state(&category.id).view(years);
+    let saved = app.user_settings.get_category_view(&category.id);
+    let mut changed = None;
+    ui.horizontal(|ui| {
+        if saved != Some(&view)
+            && ui.button("Remember View")
+                .on_hover_text("Open this category with the current sort, location and year filter")
+                .clicked()
+        {
+            changed = Some(Some(view.clone()));
+        }
+        if saved.is_some() && ui.button("Forget View").on_hover_text("Open this category however it was last left").clicked() {
+            changed = Some(None);
+        }
+    });
+    if let Some(view) = changed {
+        app.user_settings.set_category_view(&category.id, view);
+        app.save_settings("save category view");
+    }
+}
+
+/// The category's `YearGrid` for the year being viewed (the year filter, or
+/// the current year when showing all years), with a button to copy it as
+/// spreadsheet-ready text.
+fn show_year_grid(ui: &mut egui::Ui, app: &PreftApp, flows: &[Flow], category: &Category) {
+    let year = app.user_settings.get_year_filter().unwrap_or_else(|| Local::now().year());
+    egui::CollapsingHeader::new(format!("Year Grid ({})", year))
+        .id_source(format!("year_grid_{}", category.id))
+        .show(ui, |ui| {
+            let grid = YearGrid::for_category(flows, &category.id, year);
+            egui::Grid::new(format!("year_grid_table_{}", category.id))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    for month in MONTH_LABELS {
+                        ui.strong(month);
+                    }
+                    ui.strong("Year");
+                    ui.end_row();
+
+                    for (metric, values) in grid.rows(app.user_settings.number_format_for(&category.id)) {
+                        ui.label(metric);
+                        for value in values {
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.label(value);
+                            });
+                        }
+                        ui.end_row();
+                    }
+                });
+            if ui.button("Copy to Clipboard").on_hover_text("Tab-separated, for pasting into a spreadsheet").clicked() {
+                ui.output_mut(|o| o.copied_text = grid.to_tsv());
+            }
+        });
+}
+
+/// How many flows are selected and what they add up to, with "Copy" (the
+/// rows as TSV, in table order) and "Delete Selected..." asking once
+/// (unless turned off in the settings) before deleting them all together.
+fn show_selection_bar(ui: &mut egui::Ui, app: &mut PreftApp, flows: &[Flow], category: &Category) {
+    let number_format = app.user_settings.number_format_for(&category.id).clone();
+    let confirm = app.user_settings.confirmations.delete_selected_flows;
+    let state = app.category_flows_state.entry(category.id.clone()).or_insert_with(CategoryFlowsState::new);
+    if state.selected.is_empty() {
+        state.confirm_delete_selected = false;
+        return;
+    }
+    let count = state.selected.len();
+    let total: f64 = flows.iter().filter(|f| state.selected.contains(&f.id)).map(|f| f.amount).sum();
+
+    let mut delete = false;
+    ui.horizontal(|ui| {
+        ui.label(format!("{} selected, totaling {}", count, number_format.format_currency(total)));
+        if ui.button("Copy").on_hover_text("Copy the selected rows, with headers, for pasting into a spreadsheet").clicked() {
+            let mut selected: Vec<&Flow> = flows.iter().filter(|f| state.selected.contains(&f.id)).collect();
+            sort_flows(&mut selected, state.sort_column, state.sort_ascending);
+            ui.output_mut(|o| o.copied_text = utils::flows_to_tsv(&selected, category));
+        }
+        if ui.button(if confirm { "Delete Selected..." } else { "Delete Selected" }).clicked() {
+            if confirm {
+                state.confirm_delete_selected = true;
+            } else {
+                delete = true;
+            }
+        }
+        if ui.button("Clear Selection").clicked() {
+            state.selected.clear();
+            state.selection_anchor = None;
+        }
+    });
+    if state.confirm_delete_selected {
+        egui::Window::new("Confirm Delete Flows")
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.label(format!(
+                    "Delete {} flows from {}, totaling {}?",
+                    count,
+                    category.name,
+                    number_format.format_currency(total)
+                ));
+                ui.label("Refunds and reimbursements linked to them are kept but lose the link. Undo brings everything back.");
+                ui.horizontal(|ui| {
+                    if ui.button(format!("Yes, Delete {} Flows", count)).clicked() {
+                        delete = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        state.confirm_delete_selected = false;
+                    }
+                });
+            });
+    }
+
+    if delete {
+        state.confirm_delete_selected = false;
+        state.selection_anchor = None;
+        let flow_ids = std::mem::take(&mut state.selected);
+        app.request_guarded_action(GuardedAction::DeleteFlows(flow_ids));
+    }
 }
 
 /// Renders a clickable column header, with a ▲/▼ indicator when it's the
@@ -173,161 +637,602 @@ fn sortable_header(ui: &mut egui::Ui, label: &str, column: SortColumn, active_co
     ui.button(text)
 }
 
-fn show_flows_table(ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
-    let (sort_column, sort_ascending) = {
-        let state = app.get_category_flows_state(&category.id);
-        (state.sort_column, state.sort_ascending)
-    };
+/// Totals per location for the flows the table can show (i.e. within the
+/// year filter), and a filter narrowing the table to one of them. Hidden
+/// while no flow in the category has a location.
+fn show_locations(ui: &mut egui::Ui, app: &mut PreftApp, flows: &[Flow], category: &Category) {
+    let totals = utils::totals_by_location(flows.iter());
+    let state = app.get_category_flows_state(&category.id);
+    if totals.is_empty() {
+        state.location_filter = None;
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Location:");
+        egui::ComboBox::from_id_source(format!("location_filter_{}", category.id))
+            .selected_text(state.location_filter.clone().unwrap_or_else(|| "All locations".to_string()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.location_filter, None, "All locations");
+                for (location, _) in &totals {
+                    ui.selectable_value(&mut state.location_filter, Some(location.clone()), location);
+                }
+            });
+    });
 
-    egui::ScrollArea::vertical()
-        .id_source(format!("flows_scroll_{}", category.id))
-        .auto_shrink([false, false])
+    let number_format = app.user_settings.number_format_for(&category.id);
+    egui::CollapsingHeader::new("Totals by Location")
+        .id_source(format!("location_totals_{}", category.id))
         .show(ui, |ui| {
-            egui::Grid::new(format!("flows_grid_{}", category.id))
+            egui::Grid::new(format!("location_totals_grid_{}", category.id))
                 .striped(true)
                 .show(ui, |ui| {
-                    // Header row -- Date/Amount/Description are sortable by
-                    // clicking; custom fields aren't (they're typed per-field
-                    // and would need type-aware comparisons, unlike these three).
-                    if sortable_header(ui, "Date", SortColumn::Date, sort_column, sort_ascending).clicked() {
-                        app.get_category_flows_state(&category.id).toggle_sort(SortColumn::Date);
-                    }
-                    if sortable_header(ui, "Amount", SortColumn::Amount, sort_column, sort_ascending).clicked() {
-                        app.get_category_flows_state(&category.id).toggle_sort(SortColumn::Amount);
-                    }
-                    if sortable_header(ui, "Description", SortColumn::Description, sort_column, sort_ascending).clicked() {
-                        app.get_category_flows_state(&category.id).toggle_sort(SortColumn::Description);
-                    }
-                    if category.tax_deduction.deduction_allowed {
-                        ui.label("Tax Deductible");
-                    }
-                    for field in &category.fields {
-                        ui.label(field.display_name());
-                    }
-                    ui.label(""); // Empty header for edit button column
-                    ui.label(""); // Spacer
-                    ui.label(""); // Empty header for delete button column
-                    ui.end_row();
-
-                    // Data rows
-                    let mut flows: Vec<_> = app.flows.iter()
-                        .filter(|f| f.category_id == category.id)
-                        .filter(|f| {
-                            if let Some(year) = app.user_settings.get_year_filter() {
-                                f.date.year() == year
-                            } else {
-                                true
-                            }
-                        })
-                        .cloned()
-                        .collect();
-                    
-                    sort_flows(&mut flows, sort_column, sort_ascending);
-
-                    for flow in flows {
-                        // Date cell
-                        ui.label(flow.date.to_string());
-                        
-                        // Amount cell
+                    for (location, total) in &totals {
+                        ui.label(location);
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            ui.label(format!("${:.2}", flow.amount));
+                            ui.label(number_format.format_currency(*total));
                         });
-                        
-                        // Description cell
-                        ui.label(&flow.description);
-                        
-                        // Tax deductible cell
-                        if category.tax_deduction.deduction_allowed {
-                            let symbol = match flow.tax_deductible {
-                                Some(true) => "[X]",
-                                Some(false) => "[ ]",
-                                None => "[ ]",
-                            };
-                            ui.label(symbol);
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// Text search plus an optional date range, narrowing the table below.
+fn show_search_bar(ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
+    let today = Local::now().date_naive();
+    let state = app.get_category_flows_state(&category.id);
+    let focus = std::mem::take(&mut state.focus_search);
+    let search = &mut state.search;
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        let response = ui.add(egui::TextEdit::singleline(&mut search.text)
+            .hint_text("Description, amount or field value")
+            .desired_width(220.0));
+        if focus {
+            response.request_focus();
+        }
+
+        let mut has_from = search.from.is_some();
+        if ui.checkbox(&mut has_from, "From").changed() {
+            search.from = has_from.then(|| search.to.unwrap_or(today));
+        }
+        if let Some(from) = &mut search.from {
+            ui.add(egui_extras::DatePickerButton::new(from).id_source(&format!("search_from_{}", category.id)));
+        }
+        let mut has_to = search.to.is_some();
+        if ui.checkbox(&mut has_to, "To").changed() {
+            search.to = has_to.then_some(today);
+        }
+        if let Some(to) = &mut search.to {
+            ui.add(egui_extras::DatePickerButton::new(to).id_source(&format!("search_to_{}", category.id)));
+        }
+
+        if search.is_active() && ui.button("Clear").clicked() {
+            *search = FlowSearch::default();
+        }
+    });
+}
+
+/// A button clicked in a row of the flows table, applied once the table is
+/// drawn (the table only borrows the app while it draws).
+enum RowAction {
+    Edit(String),
+    Confirm(String),
+    Delete(String),
+    /// Save the cell open for editing.
+    SaveInline,
+}
+
+/// The category's flows as a `TableBuilder` table. Only the rows scrolled
+/// into view are laid out, and the table works on borrowed flows, so long
+/// histories stay responsive. Double-clicking an amount, description or
+/// field value edits it in place. Without a mouse, the arrow keys (and Home
+/// and End) move between rows, Enter edits the row, Delete deletes it and
+/// Space ticks it for "Delete Selected".
+fn show_flows_table(ui: &mut egui::Ui, app: &mut PreftApp, all_flows: &[Flow], category: &Category) {
+    let number_format = app.user_settings.number_format_for(&category.id).clone();
+    let (sort_column, sort_ascending, location_filter, search) = {
+        let state = app.get_category_flows_state(&category.id);
+        (state.sort_column, state.sort_ascending, state.location_filter.clone(), state.search.clone())
+    };
+    // `all_flows` are already the category's within the year filter
+    let is_shown = |f: &Flow| {
+        location_filter.as_ref().is_none_or(|wanted| {
+            f.location.as_deref().is_some_and(|location| location.trim().eq_ignore_ascii_case(wanted))
+        }) && search.matches(f)
+    };
+
+    // Only flows that could be deleted one by one can be selected.
+    if !app.get_category_flows_state(&category.id).selected.is_empty() {
+        let selectable: BTreeSet<String> = if app.read_only {
+            BTreeSet::new()
+        } else {
+            all_flows.iter().filter(|f| is_shown(f) && !app.is_flow_locked(f)).map(|f| f.id.clone()).collect()
+        };
+        app.get_category_flows_state(&category.id).selected.retain(|id| selectable.contains(id));
+    }
+    show_selection_bar(ui, app, all_flows, category);
+
+    // Taken out of the state while the table borrows the app, put back after.
+    let state = app.get_category_flows_state(&category.id);
+    let mut selected = std::mem::take(&mut state.selected);
+    let mut selection_anchor = state.selection_anchor.take();
+    let mut inline_edit = state.inline_edit.take();
+    let focused_row = state.focused_row.take();
+    let confirming = state.confirm_delete_selected || state.confirm_delete_flow.is_some();
+    let mut sort_clicked = None;
+    let mut action = None;
+
+    let app_ref: &PreftApp = app;
+    let mut flows: Vec<&Flow> = all_flows.iter().filter(|f| is_shown(f)).collect();
+    sort_flows(&mut flows, sort_column, sort_ascending);
+    // In table order, for shift-click ranges.
+    let selectable: Vec<&str> = if app_ref.read_only {
+        Vec::new()
+    } else {
+        flows.iter().filter(|f| !app_ref.is_flow_locked(f)).map(|f| f.id.as_str()).collect()
+    };
+
+    // Refund pairing: how much has been refunded against each original.
+    let mut refunded: std::collections::HashMap<&str, f64> = std::collections::HashMap::new();
+    for refund in all_flows {
+        if let Some(original_id) = &refund.refund_of {
+            *refunded.entry(original_id.as_str()).or_default() += refund.amount;
+        }
+    }
+
+    let mut focused = focused_row.and_then(|(id, index)| {
+        flows.iter().position(|f| f.id == id)
+            .or_else(|| flows.len().checked_sub(1).map(|last| index.min(last)))
+    });
+    let mut scroll_to = None;
+    // The keys are left alone while a widget has keyboard focus (Tab still
+    // moves through the buttons and checkboxes), a cell is being edited or
+    // a confirmation or the flow editor is open.
+    let keys_free = inline_edit.is_none()
+        && !confirming
+        && !app_ref.is_editing_flow()
+        && ui.memory(|m| m.focus().is_none());
+    if keys_free && let Some(last) = flows.len().checked_sub(1) {
+        let pressed = |key| ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+        let moved_to = if pressed(egui::Key::ArrowDown) {
+            Some(focused.map_or(0, |i| (i + 1).min(last)))
+        } else if pressed(egui::Key::ArrowUp) {
+            Some(focused.map_or(0, |i| i.saturating_sub(1)))
+        } else if pressed(egui::Key::Home) {
+            Some(0)
+        } else if pressed(egui::Key::End) {
+            Some(last)
+        } else {
+            None
+        };
+        if let Some(index) = moved_to {
+            focused = Some(index);
+            scroll_to = Some(index);
+            // Read out by screen readers, like a widget taking focus.
+            let flow = flows[index];
+            let label = format!("{}, {}, {}", flow.date, number_format.format_currency(flow.amount), flow.description);
+            ui.ctx().output_mut(|o| o.events.push(egui::output::OutputEvent::FocusGained(
+                egui::WidgetInfo::labeled(egui::WidgetType::Other, label),
+            )));
+        }
+        if let Some(flow) = focused.map(|i| flows[i]) {
+            let editable = !app_ref.read_only && !app_ref.is_flow_locked(flow);
+            if pressed(egui::Key::Enter) && editable {
+                action = Some(RowAction::Edit(flow.id.clone()));
+            } else if pressed(egui::Key::Delete) && editable {
+                action = Some(RowAction::Delete(flow.id.clone()));
+            } else if pressed(egui::Key::Space) && editable {
+                if !selected.remove(&flow.id) {
+                    selected.insert(flow.id.clone());
+                }
+                selection_anchor = Some(flow.id.clone());
+            } else if pressed(egui::Key::Escape) {
+                focused = None;
+            }
+        }
+    }
+
+    show_status_bar(ui, &flows, &selected, search.is_active() || location_filter.is_some(), &number_format, &category.id);
+
+    let shift = ui.input(|i| i.modifiers.shift);
+    let row_height = ui.spacing().interact_size.y;
+    let focus_stroke = ui.visuals().selection.stroke;
+    let show_tax = category.tax_deduction.deduction_allowed;
+
+    ui.push_id(format!("flows_table_{}", category.id), |ui| {
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .auto_shrink([false, false])
+            .max_scroll_height(f32::INFINITY)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center));
+        if !app_ref.read_only {
+            table = table.column(Column::auto());
+        }
+        if let Some(row) = scroll_to {
+            table = table.scroll_to_row(row, None);
+        }
+        table = table
+            .column(Column::auto().at_least(80.0))   // Date
+            .column(Column::auto().at_least(80.0))   // Amount
+            .column(Column::initial(240.0).at_least(80.0).clip(true)) // Description
+            .column(Column::auto());                 // Location
+        if show_tax {
+            table = table.column(Column::auto());
+        }
+        for _ in &category.fields {
+            table = table.column(Column::auto());
+        }
+        // Edit, Confirm (scheduled flows only) and Delete
+        table = table.columns(Column::auto(), 3);
+
+        table
+            .header(20.0, |mut header| {
+                // Date/Amount/Description are sortable by clicking; custom
+                // fields aren't (they're typed per-field and would need
+                // type-aware comparisons, unlike these three).
+                if !app_ref.read_only {
+                    header.col(|ui| {
+                        let mut all = !selectable.is_empty() && selected.len() == selectable.len();
+                        if ui.add_enabled(!selectable.is_empty(), egui::Checkbox::without_text(&mut all))
+                            .on_hover_text("Select every flow shown")
+                            .changed()
+                        {
+                            selected = if all { selectable.iter().map(|id| id.to_string()).collect() } else { BTreeSet::new() };
+                            selection_anchor = None;
+                        }
+                    });
+                }
+                for (label, column) in [
+                    ("Date", SortColumn::Date),
+                    ("Amount", SortColumn::Amount),
+                    ("Description", SortColumn::Description),
+                ] {
+                    header.col(|ui| {
+                        if sortable_header(ui, label, column, sort_column, sort_ascending).clicked() {
+                            sort_clicked = Some(column);
+                        }
+                    });
+                }
+                header.col(|ui| { ui.strong("Location"); });
+                if show_tax {
+                    header.col(|ui| { ui.strong("Tax Deductible"); });
+                }
+                for field in &category.fields {
+                    header.col(|ui| { ui.strong(field.display_name()); });
+                }
+                for _ in 0..3 {
+                    header.col(|_| {});
+                }
+            })
+            .body(|body| {
+                body.rows(row_height, flows.len(), |row_index, mut row| {
+                    let flow = flows[row_index];
+                    // Flows in a locked year are read-only.
+                    let locked = app_ref.is_flow_locked(flow);
+                    let editable = !locked && !app_ref.read_only;
+                    // Draws the open editor if `cell` is the one being
+                    // edited, or returns false to draw the value instead.
+                    let mut show_editor = |ui: &mut egui::Ui, cell: &InlineCell, field_type: Option<&FieldType>| {
+                        let Some(edit) = inline_edit.as_mut().filter(|edit| edit.is_on(&flow.id, cell)) else {
+                            return false;
+                        };
+                        match edit.show(ui, field_type) {
+                            Some(InlineOutcome::Save) => action = Some(RowAction::SaveInline),
+                            Some(InlineOutcome::Cancel) => inline_edit = None,
+                            None => {}
                         }
+                        true
+                    };
 
-                        // Custom fields cells
-                        for field in &category.fields {
-                            if let Some(value) = flow.custom_fields.get(&field.name) {
-                                match field.field_type {
-                                    crate::models::FieldType::Boolean => {
-                                        if value.parse::<bool>().unwrap_or(false) {
-                                            ui.label("[X]");
-                                        } else {
-                                            ui.label("[ ]");
-                                        }
-                                    },
-                                    crate::models::FieldType::Currency => {
-                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                            if let Ok(num) = value.replace(['$', ','], "").parse::<f64>() {
-                                                ui.label(format!("${:.2}", num));
-                                            } else {
-                                                ui.label(value);
-                                            }
-                                        });
-                                    },
-                                    crate::models::FieldType::Integer => {
-                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                            if let Ok(num) = value.parse::<i64>() {
-                                                ui.label(num.to_string());
-                                            } else {
-                                                ui.label(value);
-                                            }
-                                        });
-                                    },
-                                    crate::models::FieldType::Float => {
-                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                            if let Ok(num) = value.parse::<f64>() {
-                                                ui.label(format!("{:.2}", num));
-                                            } else {
-                                                ui.label(value);
-                                            }
-                                        });
-                                    },
-                                    _ => {
-                                        let mut display_value = value.clone();
-                                        if !display_value.is_empty() {
-                                            let mut chars: Vec<char> = display_value.chars().collect();
-                                            if let Some(first) = chars.first_mut() {
-                                                *first = first.to_uppercase().next().unwrap_or(*first);
-                                            }
-                                            display_value = chars.into_iter().collect();
-                                        }
-                                        ui.label(&display_value);
+                    // Outlined afterwards if it has the keyboard focus.
+                    let mut row_rect = egui::Rect::NOTHING;
+                    let mut clip_rect = egui::Rect::EVERYTHING;
+
+                    // Selection cell; shift-click sets every flow between
+                    // this one and the last one clicked to match it.
+                    if !app_ref.read_only {
+                        let (rect, _) = row.col(|ui| {
+                            let mut checked = selected.contains(&flow.id);
+                            let clicked = selectable.iter().position(|id| *id == flow.id.as_str());
+                            if ui.add_enabled(clicked.is_some(), egui::Checkbox::without_text(&mut checked)).changed()
+                                && let Some(clicked) = clicked
+                            {
+                                let anchor = selection_anchor.as_ref()
+                                    .filter(|_| shift)
+                                    .and_then(|anchor| selectable.iter().position(|id| *id == anchor.as_str()));
+                                let (from, to) = anchor.map_or((clicked, clicked), |anchor| (anchor.min(clicked), anchor.max(clicked)));
+                                for id in &selectable[from..=to] {
+                                    if checked {
+                                        selected.insert(id.to_string());
+                                    } else {
+                                        selected.remove(*id);
                                     }
                                 }
-                            } else {
-                                ui.label("");
+                                selection_anchor = Some(flow.id.clone());
+                                focused = Some(row_index);
                             }
-                        }
+                        });
+                        row_rect = row_rect.union(rect);
+                    }
 
-                        // Edit button cell
-                        if ui.button("Edit").clicked() {
-                            app.set_editing_flow(flow.clone());
-                            app.custom_field_values.clear();
-                            for field in &category.fields {
-                                if let Some(value) = flow.custom_fields.get(&field.name) {
-                                    app.custom_field_values.insert(field.name.clone(), value.clone());
-                                } else if let Some(default) = &field.default_value {
-                                    app.custom_field_values.insert(field.name.clone(), default.clone());
-                                }
+                    let (rect, _) = row.col(|ui| {
+                        clip_rect = ui.clip_rect();
+                        ui.label(flow.date.to_string());
+                    });
+                    row_rect = row_rect.union(rect);
+
+                    let (_, amount_cell) = row.col(|ui| {
+                        if show_editor(ui, &InlineCell::Amount, None) {
+                            return;
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if flow.scheduled {
+                                ui.label(egui::RichText::new(number_format.format_currency(flow.projected_amount())).italics().weak());
+                            } else if flow.is_refund() {
+                                ui.label(egui::RichText::new(number_format.format_currency(-flow.amount)).color(egui::Color32::GREEN));
+                            } else if let Some(rule) = app_ref.user_settings.highlight_for(flow, &category.flow_type) {
+                                let [r, g, b] = rule.color;
+                                let text = egui::RichText::new(number_format.format_currency(flow.amount)).color(egui::Color32::from_rgb(r, g, b));
+                                ui.label(if rule.bold { text.strong() } else { text });
+                            } else {
+                                ui.label(number_format.format_currency(flow.amount));
                             }
+                        });
+                    });
+
+                    let (_, description_cell) = row.col(|ui| {
+                        if show_editor(ui, &InlineCell::Description, None) {
+                            return;
                         }
+                        // The original may be from a year that isn't loaded
+                        let original = flow.refund_of.as_ref()
+                            .and_then(|id| all_flows.iter().find(|f| f.id == *id).or_else(|| app_ref.flow_cache.find(id)));
+                        let text = if flow.scheduled {
+                            egui::RichText::new(format!("{} (scheduled)", flow.description)).italics().weak()
+                        } else if let Some(original) = original {
+                            egui::RichText::new(format!("\u{21A9} {} (refund of {} {})", flow.description, original.date, original.description))
+                        } else if flow.is_refund() {
+                            egui::RichText::new(format!("\u{21A9} {} (refund)", flow.description))
+                        } else if let Some(total) = refunded.get(flow.id.as_str()) {
+                            egui::RichText::new(format!("{} (refunded {})", flow.description, number_format.format_currency(*total)))
+                        } else {
+                            egui::RichText::new(&flow.description)
+                        };
+                        ui.add(egui::Label::new(text).truncate(true));
+                    });
 
-                        ui.label("");
+                    row.col(|ui| { ui.label(flow.location.as_deref().unwrap_or("")); });
+
+                    if show_tax {
+                        row.col(|ui| {
+                            ui.label(if flow.tax_deductible == Some(true) { "[X]" } else { "[ ]" });
+                        });
+                    }
 
-                        // Delete button
-                        if ui.button("Delete").clicked() {
-                            if let Err(e) = app.delete_flow(&flow.id) {
-                                ui.label(egui::RichText::new(format!("Error deleting flow: {}", e))
-                                    .color(egui::Color32::RED));
+                    let mut field_cells = Vec::with_capacity(category.fields.len());
+                    for field in &category.fields {
+                        let cell = InlineCell::Field(field.name.clone());
+                        let (_, response) = row.col(|ui| {
+                            if show_editor(ui, &cell, Some(&field.field_type)) {
+                                return;
                             }
+                            if let Some(value) = flow.custom_fields.get(&field.name) {
+                                show_field_value(ui, &field.field_type, value, &number_format);
+                            }
+                        });
+                        field_cells.push((cell, response));
+                    }
+                    if editable {
+                        let cells = [(InlineCell::Amount, amount_cell), (InlineCell::Description, description_cell)];
+                        if let Some((cell, _)) = cells.into_iter().chain(field_cells)
+                            .find(|(_, response)| response.interact(egui::Sense::click()).double_clicked())
+                        {
+                            inline_edit = Some(InlineEdit::start(flow, cell));
+                            focused = Some(row_index);
                         }
+                    }
 
-                        ui.end_row();
+                    let disabled_reason = if app_ref.read_only {
+                        "Read-only viewer mode".to_string()
+                    } else {
+                        format!("{} is locked", flow.date.year())
+                    };
+                    row.col(|ui| {
+                        if ui.add_enabled(editable, egui::Button::new("Edit"))
+                            .on_disabled_hover_text(&disabled_reason)
+                            .clicked()
+                        {
+                            action = Some(RowAction::Edit(flow.id.clone()));
+                        }
+                    });
+                    row.col(|ui| {
+                        if flow.scheduled && !app_ref.read_only && ui.button("Confirm").clicked() {
+                            action = Some(RowAction::Confirm(flow.id.clone()));
+                        }
+                    });
+                    let (rect, response) = row.col(|ui| {
+                        if ui.add_enabled(editable, egui::Button::new("Delete"))
+                            .on_disabled_hover_text(&disabled_reason)
+                            .clicked()
+                        {
+                            action = Some(RowAction::Delete(flow.id.clone()));
+                        }
+                    });
+                    if focused == Some(row_index) {
+                        response.ctx.layer_painter(response.layer_id)
+                            .with_clip_rect(clip_rect)
+                            .rect_stroke(row_rect.union(rect), 2.0, focus_stroke);
                     }
                 });
+            });
+    });
+
+    let focused_row = focused.map(|i| (flows[i].id.clone(), i));
+    let state = app.get_category_flows_state(&category.id);
+    state.selected = selected;
+    state.selection_anchor = selection_anchor;
+    state.inline_edit = inline_edit;
+    state.focused_row = focused_row;
+    if let Some(column) = sort_clicked {
+        state.toggle_sort(column);
+    }
+    match action {
+        Some(RowAction::Edit(flow_id)) => {
+            if let Some(flow) = all_flows.iter().find(|f| f.id == flow_id).cloned() {
+                app.start_editing_flow(flow, category);
+            }
+        }
+        Some(RowAction::Confirm(flow_id)) => app.confirm_flow(&flow_id),
+        Some(RowAction::Delete(flow_id)) => {
+            if app.user_settings.confirmations.delete_flow {
+                app.get_category_flows_state(&category.id).confirm_delete_flow = Some(flow_id);
+            } else {
+                delete_flow(app, &flow_id);
+            }
+        }
+        Some(RowAction::SaveInline) => save_inline_edit(app, category),
+        None => {}
+    }
+    show_delete_flow_confirmation(ui, app, all_flows, category);
+}
+
+/// Count, sum and average of the selected flows, or while the search or
+/// location filter narrows the table, of the flows shown, refunds counting
+/// against the rest as in the table. Drawn before the table, at the bottom
+/// of the space it would otherwise fill.
+fn show_status_bar(ui: &mut egui::Ui, flows: &[&Flow], selected: &BTreeSet<String>, filtered: bool, number_format: &NumberFormat, category_id: &str) {
+    let (label, amounts): (&str, Vec<f64>) = if !selected.is_empty() {
+        ("selected", flows.iter().filter(|f| selected.contains(&f.id)).map(|f| f.projected_amount()).collect())
+    } else if filtered {
+        ("shown", flows.iter().map(|f| f.projected_amount()).collect())
+    } else {
+        return;
+    };
+    let sum: f64 = amounts.iter().sum();
+    let average = if amounts.is_empty() { 0.0 } else { sum / amounts.len() as f64 };
+    egui::TopBottomPanel::bottom(format!("flows_status_{}", category_id)).show_inside(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} {}", amounts.len(), label));
+            ui.separator();
+            ui.label(format!("Sum: {}", number_format.format_currency(sum)));
+            ui.separator();
+            ui.label(format!("Average: {}", number_format.format_currency(average)));
         });
+    });
+}
+
+fn delete_flow(app: &mut PreftApp, flow_id: &str) {
+    if let Err(e) = app.delete_flow(flow_id) {
+        log::error!("Failed to delete flow: {}", e);
+        app.notifications.push(format!("The flow was not deleted: {}", e));
+    }
+}
+
+/// Asks before deleting the flow whose Delete button was clicked, when
+/// `ConfirmationSettings::delete_flow` is on.
+fn show_delete_flow_confirmation(ui: &mut egui::Ui, app: &mut PreftApp, flows: &[Flow], category: &Category) {
+    let Some(flow_id) = app.get_category_flows_state(&category.id).confirm_delete_flow.clone() else { return };
+    let Some(flow) = flows.iter().find(|f| f.id == flow_id) else {
+        app.get_category_flows_state(&category.id).confirm_delete_flow = None;
+        return;
+    };
+    let number_format = app.user_settings.number_format_for(&category.id);
+    let mut delete = false;
+    let mut cancel = false;
+    egui::Window::new("Confirm Delete Flow")
+        .collapsible(false)
+        .resizable(false)
+        .show(ui.ctx(), |ui| {
+            let description = if flow.description.is_empty() { String::new() } else { format!(" ({})", flow.description) };
+            ui.label(format!("Delete the flow of {} on {}{}?", number_format.format_currency(flow.amount), flow.date, description));
+            ui.label("Undo brings it back.");
+            ui.horizontal(|ui| {
+                delete = ui.button("Yes, Delete Flow").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+        });
+
+    if delete || cancel {
+        app.get_category_flows_state(&category.id).confirm_delete_flow = None;
+    }
+    if delete {
+        delete_flow(app, &flow_id);
+    }
+}
+
+/// Saves the cell open for editing, closing it, or if the value can't be
+/// saved, leaves it open showing why.
+fn save_inline_edit(app: &mut PreftApp, category: &Category) {
+    let Some(edit) = app.get_category_flows_state(&category.id).inline_edit.take() else { return };
+    let flow = app.flow_cache.find(&edit.flow_id)
+        .ok_or_else(|| "The flow no longer exists".to_string())
+        .and_then(|flow| edit.apply(flow, category, app.user_settings.number_format_for(&category.id)));
+    let flow = match flow {
+        Ok(flow) => flow,
+        Err(e) => {
+            app.get_category_flows_state(&category.id).inline_edit = Some(InlineEdit { error: Some(e), focus: true, ..edit });
+            return;
+        }
+    };
+    app.get_category_flows_state(&category.id).inline_edit = Some(InlineEdit { saving: true, ..edit.clone() });
+    let category_id = category.id.clone();
+    app.save_inline_edit(flow, move |app, result| {
+        let state = app.get_category_flows_state(&category_id);
+        // Another cell was opened in the meantime
+        if !state.inline_edit.as_ref().is_some_and(|open| open.saving && open.is_on(&edit.flow_id, &edit.cell)) {
+            return;
+        }
+        state.inline_edit = match result {
+            Ok(()) => None,
+            Err(e) => Some(InlineEdit { error: Some(e.to_string()), focus: true, ..edit }),
+        };
+    });
+}
+
+/// A custom field's value, shown according to the field's type.
+fn show_field_value(ui: &mut egui::Ui, field_type: &FieldType, value: &str, number_format: &NumberFormat) {
+    match field_type {
+        FieldType::Boolean => {
+            ui.label(if value.parse::<bool>().unwrap_or(false) { "[X]" } else { "[ ]" });
+        },
+        FieldType::Currency => {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if let Some(num) = utils::parse_stored_currency(value) {
+                    ui.label(number_format.format_currency(num));
+                } else {
+                    ui.label(value);
+                }
+            });
+        },
+        FieldType::Integer => {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if let Ok(num) = value.parse::<i64>() {
+                    ui.label(num.to_string());
+                } else {
+                    ui.label(value);
+                }
+            });
+        },
+        FieldType::Float => {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if let Ok(num) = value.parse::<f64>() {
+                    ui.label(format!("{:.2}", num));
+                } else {
+                    ui.label(value);
+                }
+            });
+        },
+        _ => {
+            let mut chars = value.chars();
+            let display_value: String = chars.next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default();
+            ui.label(display_value);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -342,8 +1247,11 @@ mod tests {
             name: format!("Category {}", id),
             flow_type: FlowType::Expense,
             parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
             fields: Vec::new(),
-            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false },
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
         }
     }
 
@@ -357,6 +1265,13 @@ mod tests {
             linked_flows: Vec::new(),
             custom_fields: HashMap::new(),
             tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
         }
     }
 
@@ -377,6 +1292,9 @@ mod tests {
         assert_eq!(state.last_year_total, 100.0);
         assert_eq!(state.this_year_total, 70.0);
         assert_eq!(state.current_month_total, 20.0);
+        assert_eq!(state.monthly_trend.len(), TREND_MONTHS);
+        assert_eq!(state.monthly_trend[TREND_MONTHS - 1], 20.0);
+        assert_eq!(state.monthly_trend[TREND_MONTHS - 6], 50.0, "January is five months before June");
     }
 
     #[test]
@@ -400,6 +1318,25 @@ mod tests {
         assert_eq!(state.this_year_total, 500.0, "should recompute after mark_for_update");
     }
 
+    #[test]
+    fn discrepancies_reports_totals_that_went_stale_without_being_marked() {
+        let cat = category("cat-1");
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let mut flows = vec![flow("cat-1", as_of, 100.0)];
+
+        let mut state = CategoryFlowsState::new();
+        state.update_totals_as_of(&flows, &cat, as_of);
+        assert!(state.discrepancies_as_of(&flows, &cat, as_of).is_empty());
+
+        // A flow added without marking the state for update.
+        flows.push(flow("cat-1", as_of, 50.0));
+        let found = state.discrepancies_as_of(&flows, &cat, as_of);
+        assert_eq!(found.len(), 2, "this year and current month totals: {:?}", found);
+
+        state.mark_for_update();
+        assert!(state.discrepancies_as_of(&flows, &cat, as_of).is_empty(), "a state awaiting update is expected to be stale");
+    }
+
     #[test]
     fn new_state_defaults_to_zero_and_needs_update() {
         let state = CategoryFlowsState::new();
@@ -495,4 +1432,93 @@ mod tests {
             ..flow(category_id, date, 0.0)
         }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn search_matches_description_amount_and_field_values_within_the_dates() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let mut flow = flow_with_description("cat-1", date(10), "Dinner at Luigi's");
+        flow.amount = 1200.5;
+        flow.custom_fields.insert("Vendor".to_string(), "Trattoria".to_string());
+        let search = |text: &str| FlowSearch { text: text.to_string(), ..FlowSearch::default() };
+
+        assert!(search("luigi").matches(&flow));
+        assert!(search("$1,200.5").matches(&flow));
+        assert!(search("TRATT").matches(&flow));
+        assert!(!search("lunch").matches(&flow));
+        assert!(!search("99").matches(&flow));
+        assert!(!search("room 12").matches(&flow), "text with letters isn't an amount");
+
+        let in_range = FlowSearch { from: Some(date(1)), to: Some(date(10)), ..search("dinner") };
+        assert!(in_range.matches(&flow));
+        let too_late = FlowSearch { to: Some(date(9)), ..search("") };
+        assert!(!too_late.matches(&flow));
+    }
+
+    fn field(name: &str, field_type: FieldType) -> crate::models::CategoryField {
+        crate::models::CategoryField { name: name.to_string(), field_type, required: false, default_value: None }
+    }
+
+    #[test]
+    fn inline_edit_applies_amount_and_description() {
+        let category = category("cat-1");
+        let flow = flow("cat-1", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 10.0);
+
+        let mut edit = InlineEdit::start(&flow, InlineCell::Amount);
+        assert_eq!(edit.text, "10");
+        edit.text = " 42.5 ".to_string();
+        assert_eq!(edit.apply(&flow, &category, &NumberFormat::default()).unwrap().amount, 42.5);
+        edit.text = "forty".to_string();
+        assert_eq!(edit.apply(&flow, &category, &NumberFormat::default()).unwrap_err(), "\"forty\" isn't an amount");
+
+        let mut edit = InlineEdit::start(&flow, InlineCell::Description);
+        edit.text = "Groceries ".to_string();
+        assert_eq!(edit.apply(&flow, &category, &NumberFormat::default()).unwrap().description, "Groceries");
+    }
+
+    #[test]
+    fn inline_edit_stores_field_values_like_the_flow_editor() {
+        let mut category = category("cat-1");
+        category.fields = vec![
+            field("count", FieldType::Integer),
+            field("fee", FieldType::Currency),
+            field("size", FieldType::Select(vec!["Small".to_string(), "Large".to_string()])),
+        ];
+        let mut flow = flow("cat-1", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 10.0);
+        flow.custom_fields.insert("count".to_string(), "3".to_string());
+        let edit = |name: &str, text: &str| InlineEdit {
+            text: text.to_string(),
+            ..InlineEdit::start(&flow, InlineCell::Field(name.to_string()))
+        };
+
+        assert_eq!(edit("count", "3").text, "3", "opens with the stored value");
+        assert_eq!(edit("fee", "$1,250.5").apply(&flow, &category, &NumberFormat::default()).unwrap().custom_fields["fee"], "$1250.50");
+        assert_eq!(edit("size", "large").apply(&flow, &category, &NumberFormat::default()).unwrap().custom_fields["size"], "Large");
+        assert!(!edit("count", "").apply(&flow, &category, &NumberFormat::default()).unwrap().custom_fields.contains_key("count"));
+        assert_eq!(edit("count", "2.5").apply(&flow, &category, &NumberFormat::default()).unwrap_err(), "Count must be a whole number");
+        assert_eq!(edit("size", "Medium").apply(&flow, &category, &NumberFormat::default()).unwrap_err(), "Size must be one of: Small, Large");
+    }
+
+    #[test]
+    fn apply_view_sets_sort_and_location() {
+        let mut state = CategoryFlowsState::new();
+        let view = CategoryView {
+            sort_column: SortColumn::Description,
+            sort_ascending: true,
+            years: None,
+            location: Some("Springfield".to_string()),
+        };
+        state.apply_view(&view);
+        assert_eq!(state.view(None), view);
+    }
+
+    #[test]
+    fn inline_edit_refuses_to_clear_a_required_field() {
+        let mut category = category("cat-1");
+        category.fields = vec![crate::models::CategoryField { required: true, ..field("payee", FieldType::Text) }];
+        let mut flow = flow("cat-1", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 10.0);
+        flow.custom_fields.insert("payee".to_string(), "Grocer".to_string());
+        let edit = InlineEdit { text: " ".to_string(), ..InlineEdit::start(&flow, InlineCell::Field("payee".to_string())) };
+
+        assert_eq!(edit.apply(&flow, &category, &NumberFormat::default()).unwrap_err(), "Payee is required.");
+    }
+}
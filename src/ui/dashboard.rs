@@ -1,14 +1,181 @@
 use eframe::egui;
-use chrono::{Local, NaiveDate, Datelike};
+use chrono::{Local, Months, NaiveDate, Datelike};
+use std::collections::{HashMap, HashSet};
 use log::{info, warn, error};
 
-use crate::models::{Flow, Category};
+use crate::app::{AllTimeTotals, Stored, StoredTotals};
+use crate::db::FlowScope;
+use crate::forecast::{self, Forecast};
+use crate::kpi::{KpiCard, KpiPeriod};
+use crate::locale::NumberFormat;
+use crate::settings::UserSettings;
+use crate::models::{Flow, Category, FlowType, ReimbursementStatus};
 use crate::utils;
+use crate::year_grid::MONTH_LABELS;
+use egui_plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+
+/// How the spending-over-time chart draws its monthly totals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartStyle {
+    Bars,
+    Lines,
+}
+
+/// The span of time the dashboard's summary, charts and breakdown cover.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DashboardPeriod {
+    ThisMonth,
+    ThisQuarter,
+    /// The whole calendar year, including any flows dated later this year.
+    ThisYear,
+    YearToDate,
+    /// The current month and the eleven before it.
+    Last12Months,
+    Custom(NaiveDate, NaiveDate),
+}
+
+impl DashboardPeriod {
+    /// Every period but `Custom`, in the order the selector lists them.
+    pub const PRESETS: [DashboardPeriod; 5] = [
+        DashboardPeriod::ThisMonth,
+        DashboardPeriod::ThisQuarter,
+        DashboardPeriod::ThisYear,
+        DashboardPeriod::YearToDate,
+        DashboardPeriod::Last12Months,
+    ];
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            DashboardPeriod::ThisMonth => "This Month",
+            DashboardPeriod::ThisQuarter => "This Quarter",
+            DashboardPeriod::ThisYear => "This Year",
+            DashboardPeriod::YearToDate => "Year to Date",
+            DashboardPeriod::Last12Months => "Last 12 Months",
+            DashboardPeriod::Custom(_, _) => "Custom",
+        }
+    }
+
+    /// First and last day of the period as of `today`, both inclusive.
+    pub fn bounds(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let month_start = today.with_day(1).unwrap();
+        let year_start = NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap();
+        let months_later = |start: NaiveDate, months: u32| start + Months::new(months) - chrono::Duration::days(1);
+        match *self {
+            DashboardPeriod::ThisMonth => (month_start, months_later(month_start, 1)),
+            DashboardPeriod::ThisQuarter => {
+                let quarter_start = NaiveDate::from_ymd_opt(today.year(), today.month0() / 3 * 3 + 1, 1).unwrap();
+                (quarter_start, months_later(quarter_start, 3))
+            }
+            DashboardPeriod::ThisYear => (year_start, months_later(year_start, 12)),
+            DashboardPeriod::YearToDate => (year_start, today),
+            DashboardPeriod::Last12Months => (month_start - Months::new(11), today),
+            DashboardPeriod::Custom(start, end) => (start, end),
+        }
+    }
+}
+
+/// Income and expense totals for each calendar month the dashboard period
+/// touches, oldest first.
+#[derive(Debug, Clone, PartialEq)]
+struct MonthlyTotals {
+    /// (year, zero-based month) of each entry.
+    months: Vec<(i32, u32)>,
+    income: Vec<f64>,
+    expenses: Vec<f64>,
+}
+
+/// This year to date against the same stretch of last year, so a partly
+/// finished year isn't compared with a whole one.
+#[derive(Debug, Clone, PartialEq)]
+struct YearComparison {
+    /// Last year's start and end of the compared stretch.
+    last_year: (NaiveDate, NaiveDate),
+    this_year: (NaiveDate, NaiveDate),
+    /// (last year, this year).
+    income: (f64, f64),
+    expenses: (f64, f64),
+    /// Each category with flows in either year: (name, flow type, last
+    /// year, this year), in category order.
+    categories: Vec<(String, FlowType, f64, f64)>,
+}
+
+/// Totals for the categories shown in one other currency (see
+/// `UserSettings::category_number_formats`), kept apart from the app
+/// currency's since there's no conversion between them.
+#[derive(Debug, Clone, PartialEq)]
+struct CurrencySubtotal {
+    format: NumberFormat,
+    income: f64,
+    expenses: f64,
+    /// Each expense category's total, largest first.
+    breakdown: Vec<(String, f64)>,
+}
+
+/// The breakdown chart shows at most this many slices; smaller categories
+/// are folded into a final "Other" slice.
+const MAX_BREAKDOWN_SLICES: usize = 8;
+
+/// How many recipients and providers the top counterparties list shows.
+const MAX_TOP_COUNTERPARTIES: usize = 10;
+
+const SLICE_COLORS: [egui::Color32; MAX_BREAKDOWN_SLICES] = [
+    egui::Color32::from_rgb(66, 133, 244),
+    egui::Color32::from_rgb(219, 68, 55),
+    egui::Color32::from_rgb(244, 180, 0),
+    egui::Color32::from_rgb(15, 157, 88),
+    egui::Color32::from_rgb(171, 71, 188),
+    egui::Color32::from_rgb(0, 172, 193),
+    egui::Color32::from_rgb(255, 112, 67),
+    egui::Color32::from_rgb(158, 158, 158),
+];
 
 pub struct Dashboard {
     tracking_ratios: Vec<(String, f64)>,
     needs_update: bool,
+    /// Set by the figures summed in the database when its totals were
+    /// still loading, so they're worked out again next frame.
+    awaiting_stored: bool,
+    /// Last frame's `awaiting_stored`.
+    retry_stored: bool,
     financial_summary: Option<(f64, f64, f64)>, // (income, expenses, net)
+    monthly_totals: Option<MonthlyTotals>,
+    /// Each expense category's total over the period, largest first.
+    expense_breakdown: Option<Vec<(String, f64)>>,
+    /// The colors of the categories in `expense_breakdown` that have one,
+    /// by label; the rest are colored from `SLICE_COLORS`.
+    breakdown_colors: HashMap<String, egui::Color32>,
+    /// The largest recipients and providers over the period (see
+    /// `Flow::counterparty`) with their flow type and total, largest first.
+    top_counterparties: Option<Vec<(String, FlowType, f64)>>,
+    /// Projected net at the end of this month and of this year.
+    forecasts: Option<[Forecast; 2]>,
+    /// The value of each of the user's KPI cards, in the same order.
+    kpi_values: Option<Vec<Option<f64>>>,
+    year_comparison: Option<YearComparison>,
+    /// Flows in the period up to today, newest first.
+    recent_flows: Option<Vec<Flow>>,
+    /// Every reimbursable expense not yet paid back, oldest first. Unlike
+    /// most widgets this ignores the period: a claim stays owed until paid,
+    /// so these are loaded on their own (see `wants_outstanding_reimbursements`)
+    /// rather than picked out of the flows in `scope`.
+    outstanding_reimbursements: Option<Vec<Flow>>,
+    /// Whether `outstanding_reimbursements` needs loading again.
+    reload_outstanding: bool,
+    /// Categories shown in a currency other than the app's. The summary,
+    /// chart, breakdown and counterparties leave them out rather than add
+    /// across currencies; `currency_subtotals` totals them instead.
+    other_currency_categories: HashSet<String>,
+    /// Period totals per other currency, ordered by symbol.
+    currency_subtotals: Option<Vec<CurrencySubtotal>>,
+    /// List every recent flow in the period rather than just the first
+    /// `UserSettings::get_recent_flows_count`.
+    pub show_all_recent: bool,
+    /// Show this year against last year in place of the financial summary.
+    pub compare_years: bool,
+    pub chart_style: ChartStyle,
+    /// Scopes the financial summary, spending chart and breakdown. Tracking
+    /// ratios are always this year's.
+    pub period: DashboardPeriod,
 }
 
 impl Dashboard {
@@ -16,16 +183,75 @@ impl Dashboard {
         Self {
             tracking_ratios: Vec::new(),
             needs_update: true,
+            awaiting_stored: false,
+            retry_stored: false,
             financial_summary: None,
+            monthly_totals: None,
+            expense_breakdown: None,
+            breakdown_colors: HashMap::new(),
+            top_counterparties: None,
+            forecasts: None,
+            kpi_values: None,
+            year_comparison: None,
+            recent_flows: None,
+            outstanding_reimbursements: None,
+            reload_outstanding: true,
+            other_currency_categories: HashSet::new(),
+            currency_subtotals: None,
+            show_all_recent: false,
+            compare_years: false,
+            chart_style: ChartStyle::Bars,
+            period: DashboardPeriod::ThisYear,
         }
     }
 
     pub fn mark_for_update(&mut self) {
         self.needs_update = true;
+        self.reload_outstanding = true;
+    }
+
+    /// Whether the outstanding reimbursements should be loaded again, for
+    /// `set_outstanding_reimbursements`. Only says so once per update.
+    pub fn wants_outstanding_reimbursements(&mut self) -> bool {
+        std::mem::take(&mut self.reload_outstanding)
+    }
+
+    /// The flows the dashboard is worked out over as of `as_of`: the years
+    /// the period touches, plus last year for the comparison and the
+    /// recurring flows behind the forecasts. "All time" KPI cards are
+    /// summed in the database instead (see `AllTimeTotals`).
+    pub fn scope(&self, as_of: NaiveDate) -> FlowScope {
+        let (start, end) = self.period.bounds(as_of);
+        FlowScope::years(start.year().min(as_of.year() - 1)..=end.year().max(as_of.year()))
     }
 
-    fn update_financial_summary(&mut self, flows: &[Flow], categories: &[Category]) {
-        self.update_financial_summary_as_of(flows, categories, Local::now().naive_local().date());
+    /// Sums the period in the database when `stored` allows, and over
+    /// `flows` when it can't.
+    fn update_financial_summary(&mut self, flows: &[Flow], categories: &[Category], stored: &mut StoredTotals) {
+        let as_of = Local::now().naive_local().date();
+        if !self.needs_update && !self.retry_stored && self.financial_summary.is_some() {
+            return;
+        }
+
+        let (start, end) = self.period.bounds(as_of);
+        let totals = match stored.between(start, end) {
+            Stored::Ready(totals) => totals,
+            Stored::Loading => {
+                self.awaiting_stored = true;
+                return;
+            }
+            Stored::Unavailable => return self.update_financial_summary_as_of(flows, categories, as_of),
+        };
+        let mut total_income = 0.0;
+        let mut total_expenses = 0.0;
+        for category in categories.iter().filter(|c| !self.other_currency_categories.contains(&c.id)) {
+            let total = totals.get(&category.id).copied().unwrap_or(0.0);
+            match category.flow_type {
+                FlowType::Income => total_income += total,
+                FlowType::Expense => total_expenses += total,
+            }
+        }
+        self.financial_summary = Some((total_income, total_expenses, total_income - total_expenses));
     }
 
     /// Core of `update_financial_summary`, parameterized on "today" so it's
@@ -35,16 +261,16 @@ impl Dashboard {
             return;
         }
 
-        let current_year = as_of.year();
+        let (start, end) = self.period.bounds(as_of);
         let mut total_income = 0.0;
         let mut total_expenses = 0.0;
 
-        for flow in flows {
-            if flow.date.year() == current_year {
+        for flow in flows.iter().filter(|f| !self.other_currency_categories.contains(&f.category_id)) {
+            if (start..=end).contains(&flow.date) {
                 if let Some(category) = categories.iter().find(|c| c.id == flow.category_id) {
                     match category.flow_type {
-                        crate::models::FlowType::Income => total_income += flow.amount,
-                        crate::models::FlowType::Expense => total_expenses += flow.amount,
+                        crate::models::FlowType::Income => total_income += flow.net_amount(),
+                        crate::models::FlowType::Expense => total_expenses += flow.net_amount(),
                     }
                 } else {
                     log::warn!("Flow {} (date: {}) has no matching category (category_id: {})",
@@ -57,8 +283,340 @@ impl Dashboard {
         self.financial_summary = Some((total_income, total_expenses, net_total));
     }
 
-    fn update_tracking_ratios(&mut self, flows: &[Flow], categories: &[Category]) {
-        self.update_tracking_ratios_as_of(flows, categories, Local::now().naive_local().date());
+    fn update_monthly_totals(&mut self, flows: &[Flow], categories: &[Category]) {
+        self.update_monthly_totals_as_of(flows, categories, Local::now().naive_local().date());
+    }
+
+    /// Core of `update_monthly_totals`, parameterized on "today" so it's
+    /// testable without depending on the wall clock.
+    fn update_monthly_totals_as_of(&mut self, flows: &[Flow], categories: &[Category], as_of: NaiveDate) {
+        if !self.needs_update && self.monthly_totals.is_some() {
+            return;
+        }
+
+        let (start, end) = self.period.bounds(as_of);
+        let month_index = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+        let first = month_index(start);
+        let months: Vec<(i32, u32)> = (first..=month_index(end).max(first))
+            .map(|index| (index.div_euclid(12), index.rem_euclid(12) as u32))
+            .collect();
+
+        let mut totals = MonthlyTotals { income: vec![0.0; months.len()], expenses: vec![0.0; months.len()], months };
+        for flow in flows.iter().filter(|f| (start..=end).contains(&f.date) && !self.other_currency_categories.contains(&f.category_id)) {
+            let Some(category) = categories.iter().find(|c| c.id == flow.category_id) else { continue };
+            let month = (month_index(flow.date) - first) as usize;
+            match category.flow_type {
+                crate::models::FlowType::Income => totals.income[month] += flow.net_amount(),
+                crate::models::FlowType::Expense => totals.expenses[month] += flow.net_amount(),
+            }
+        }
+        self.monthly_totals = Some(totals);
+    }
+
+    fn update_expense_breakdown(&mut self, flows: &[Flow], categories: &[Category]) {
+        self.update_expense_breakdown_as_of(flows, categories, Local::now().naive_local().date());
+    }
+
+    /// Core of `update_expense_breakdown`, parameterized on "today" so it's
+    /// testable without depending on the wall clock. Categories whose
+    /// refunds outweigh their spending have no share to show and are left
+    /// out.
+    fn update_expense_breakdown_as_of(&mut self, flows: &[Flow], categories: &[Category], as_of: NaiveDate) {
+        if !self.needs_update && self.expense_breakdown.is_some() {
+            return;
+        }
+
+        let (start, end) = self.period.bounds(as_of);
+        let mut breakdown: Vec<(String, f64)> = categories.iter()
+            .filter(|c| c.flow_type == crate::models::FlowType::Expense && !self.other_currency_categories.contains(&c.id))
+            .map(|c| {
+                let total = flows.iter()
+                    .filter(|f| f.category_id == c.id && (start..=end).contains(&f.date))
+                    .map(|f| f.net_amount())
+                    .sum();
+                (c.label(), total)
+            })
+            .filter(|(_, total)| *total > 0.0)
+            .collect();
+        self.breakdown_colors = categories.iter()
+            .filter_map(|c| c.color.map(|[r, g, b]| (c.label(), egui::Color32::from_rgb(r, g, b))))
+            .collect();
+        breakdown.sort_by(|a, b| b.1.total_cmp(&a.1));
+        if breakdown.len() > MAX_BREAKDOWN_SLICES {
+            let other: f64 = breakdown.drain(MAX_BREAKDOWN_SLICES - 1..).map(|(_, total)| total).sum();
+            breakdown.push(("Other".to_string(), other));
+        }
+        self.expense_breakdown = Some(breakdown);
+    }
+
+    fn update_top_counterparties(&mut self, flows: &[Flow], categories: &[Category]) {
+        self.update_top_counterparties_as_of(flows, categories, Local::now().naive_local().date());
+    }
+
+    /// Core of `update_top_counterparties`, parameterized on "today" so
+    /// it's testable without depending on the wall clock. Names differing
+    /// only in case or surrounding spaces are the same counterparty, shown
+    /// as first seen.
+    fn update_top_counterparties_as_of(&mut self, flows: &[Flow], categories: &[Category], as_of: NaiveDate) {
+        if !self.needs_update && self.top_counterparties.is_some() {
+            return;
+        }
+
+        let (start, end) = self.period.bounds(as_of);
+        let mut totals: Vec<(String, FlowType, f64)> = Vec::new();
+        for flow in flows.iter().filter(|f| (start..=end).contains(&f.date) && !self.other_currency_categories.contains(&f.category_id)) {
+            let Some(name) = flow.counterparty() else { continue };
+            let Some(category) = categories.iter().find(|c| c.id == flow.category_id) else { continue };
+            match totals.iter_mut().find(|(seen, flow_type, _)| seen.eq_ignore_ascii_case(name) && *flow_type == category.flow_type) {
+                Some((_, _, total)) => *total += flow.net_amount(),
+                None => totals.push((name.to_string(), category.flow_type.clone(), flow.net_amount())),
+            }
+        }
+        totals.retain(|(_, _, total)| *total > 0.0);
+        totals.sort_by(|a, b| b.2.total_cmp(&a.2));
+        totals.truncate(MAX_TOP_COUNTERPARTIES);
+        self.top_counterparties = Some(totals);
+    }
+
+    fn update_currency_subtotals(&mut self, flows: &[Flow], categories: &[Category], settings: &UserSettings) {
+        self.update_currency_subtotals_as_of(flows, categories, settings, Local::now().naive_local().date());
+    }
+
+    /// Core of `update_currency_subtotals`, parameterized on "today" so
+    /// it's testable without depending on the wall clock. Also works out
+    /// which categories are in another currency, for the other widgets.
+    fn update_currency_subtotals_as_of(&mut self, flows: &[Flow], categories: &[Category], settings: &UserSettings, as_of: NaiveDate) {
+        if !self.needs_update && self.currency_subtotals.is_some() {
+            return;
+        }
+
+        let (start, end) = self.period.bounds(as_of);
+        let mut subtotals: Vec<CurrencySubtotal> = Vec::new();
+        self.other_currency_categories.clear();
+        for category in categories {
+            let format = settings.number_format_for(&category.id);
+            if format.same_currency(&settings.number_format) {
+                continue;
+            }
+            self.other_currency_categories.insert(category.id.clone());
+            let total: f64 = flows.iter()
+                .filter(|f| f.category_id == category.id && (start..=end).contains(&f.date))
+                .map(|f| f.net_amount())
+                .sum();
+            let index = match subtotals.iter().position(|s| s.format.same_currency(format)) {
+                Some(index) => index,
+                None => {
+                    subtotals.push(CurrencySubtotal { format: format.clone(), income: 0.0, expenses: 0.0, breakdown: Vec::new() });
+                    subtotals.len() - 1
+                }
+            };
+            let subtotal = &mut subtotals[index];
+            match category.flow_type {
+                FlowType::Income => subtotal.income += total,
+                FlowType::Expense => {
+                    subtotal.expenses += total;
+                    if total > 0.0 {
+                        subtotal.breakdown.push((category.label(), total));
+                    }
+                }
+            }
+        }
+        for subtotal in &mut subtotals {
+            subtotal.breakdown.sort_by(|a, b| b.1.total_cmp(&a.1));
+        }
+        subtotals.sort_by(|a, b| a.format.currency_symbol.cmp(&b.format.currency_symbol));
+        self.currency_subtotals = Some(subtotals);
+    }
+
+    fn update_forecasts(&mut self, flows: &[Flow], categories: &[Category]) {
+        self.update_forecasts_as_of(flows, categories, Local::now().naive_local().date());
+    }
+
+    /// Core of `update_forecasts`, parameterized on "today" so it's testable
+    /// without depending on the wall clock. Unlike the summary, these
+    /// always cover the calendar month and year, whatever the period.
+    fn update_forecasts_as_of(&mut self, flows: &[Flow], categories: &[Category], as_of: NaiveDate) {
+        if !self.needs_update && self.forecasts.is_some() {
+            return;
+        }
+        let (month_start, month_end) = DashboardPeriod::ThisMonth.bounds(as_of);
+        let (year_start, year_end) = DashboardPeriod::ThisYear.bounds(as_of);
+        self.forecasts = Some([
+            forecast::forecast(flows, categories, month_start, month_end, as_of),
+            forecast::forecast(flows, categories, year_start, year_end, as_of),
+        ]);
+    }
+
+    /// Each card has its own period, so the dashboard period doesn't
+    /// apply. "All time" cards go by `all_time`, since `flows` only cover
+    /// the years in `scope`.
+    fn update_kpi_values(&mut self, flows: &[Flow], categories: &[Category], kpi_cards: &[KpiCard], all_time: &mut AllTimeTotals) {
+        if !self.needs_update && !self.retry_stored && self.kpi_values.as_ref().is_some_and(|values| values.len() == kpi_cards.len()) {
+            return;
+        }
+
+        let as_of = Local::now().naive_local().date();
+        let all_time_totals = if kpi_cards.iter().any(|card| card.period == KpiPeriod::AllTime) {
+            match all_time.kpis() {
+                Stored::Ready(totals) => Some(totals),
+                Stored::Loading => {
+                    self.awaiting_stored = true;
+                    return;
+                }
+                Stored::Unavailable => None,
+            }
+        } else {
+            None
+        };
+        self.kpi_values = Some(kpi_cards.iter()
+            .map(|card| match card.period {
+                KpiPeriod::AllTime => all_time_totals.and_then(|totals| card.evaluate_totals(totals, categories, as_of)),
+                _ => card.evaluate(flows, categories, as_of),
+            })
+            .collect());
+    }
+
+    /// Sums both years in the database when `stored` allows, and over
+    /// `flows` when it can't.
+    fn update_year_comparison(&mut self, flows: &[Flow], categories: &[Category], stored: &mut StoredTotals) {
+        let as_of = Local::now().naive_local().date();
+        if !self.needs_update && !self.retry_stored && self.year_comparison.is_some() {
+            return;
+        }
+
+        let (last_year, this_year) = Self::compared_years(as_of);
+        let last_totals = match stored.between(last_year.0, last_year.1) {
+            Stored::Ready(totals) => totals.clone(),
+            Stored::Loading => {
+                self.awaiting_stored = true;
+                return;
+            }
+            Stored::Unavailable => return self.update_year_comparison_as_of(flows, categories, as_of),
+        };
+        let this_totals = match stored.between(this_year.0, this_year.1) {
+            Stored::Ready(totals) => totals,
+            Stored::Loading => {
+                self.awaiting_stored = true;
+                return;
+            }
+            Stored::Unavailable => return self.update_year_comparison_as_of(flows, categories, as_of),
+        };
+        self.build_year_comparison(categories, last_year, this_year, |category, (start, _)| {
+            let totals = if start == this_year.0 { this_totals } else { &last_totals };
+            totals.get(&category.id).copied().unwrap_or(0.0)
+        });
+    }
+
+    /// Last year's and this year's stretches up to today's date.
+    fn compared_years(as_of: NaiveDate) -> ((NaiveDate, NaiveDate), (NaiveDate, NaiveDate)) {
+        let this_year = DashboardPeriod::YearToDate.bounds(as_of);
+        // Feb 29 has no counterpart, so last year's stretch ends Feb 28.
+        let last_year = (this_year.0 - Months::new(12), as_of - Months::new(12));
+        (last_year, this_year)
+    }
+
+    /// Core of `update_year_comparison`, parameterized on "today" so it's
+    /// testable without depending on the wall clock. Like
+    /// `CategoryFlowsState::update_totals`, but for every category at once
+    /// and cut off at today's date in both years.
+    fn update_year_comparison_as_of(&mut self, flows: &[Flow], categories: &[Category], as_of: NaiveDate) {
+        if !self.needs_update && self.year_comparison.is_some() {
+            return;
+        }
+
+        let (last_year, this_year) = Self::compared_years(as_of);
+        self.build_year_comparison(categories, last_year, this_year, |category, (start, end)| {
+            flows.iter()
+                .filter(|f| f.category_id == category.id && (start..=end).contains(&f.date))
+                .map(|f| f.net_amount())
+                .sum()
+        });
+    }
+
+    /// Builds the comparison from `total`, each category's total over a
+    /// stretch of days.
+    fn build_year_comparison(
+        &mut self,
+        categories: &[Category],
+        last_year: (NaiveDate, NaiveDate),
+        this_year: (NaiveDate, NaiveDate),
+        total: impl Fn(&Category, (NaiveDate, NaiveDate)) -> f64,
+    ) {
+
+        let mut comparison = YearComparison {
+            last_year,
+            this_year,
+            income: (0.0, 0.0),
+            expenses: (0.0, 0.0),
+            categories: Vec::new(),
+        };
+        for category in categories {
+            let totals = (total(category, last_year), total(category, this_year));
+            if totals == (0.0, 0.0) {
+                continue;
+            }
+            let side = match category.flow_type {
+                FlowType::Income => &mut comparison.income,
+                FlowType::Expense => &mut comparison.expenses,
+            };
+            side.0 += totals.0;
+            side.1 += totals.1;
+            comparison.categories.push((category.name.clone(), category.flow_type.clone(), totals.0, totals.1));
+        }
+        self.year_comparison = Some(comparison);
+    }
+
+    fn update_recent_flows(&mut self, flows: &[Flow]) {
+        self.update_recent_flows_as_of(flows, Local::now().naive_local().date());
+    }
+
+    /// Core of `update_recent_flows`, parameterized on "today" so it's
+    /// testable without depending on the wall clock. Scheduled flows
+    /// haven't happened yet, so they aren't recent.
+    fn update_recent_flows_as_of(&mut self, flows: &[Flow], as_of: NaiveDate) {
+        if !self.needs_update && self.recent_flows.is_some() {
+            return;
+        }
+
+        let (start, end) = self.period.bounds(as_of);
+        let mut recent: Vec<Flow> = flows.iter()
+            .filter(|f| !f.scheduled && f.date >= start && f.date <= end.min(as_of))
+            .cloned()
+            .collect();
+        recent.sort_by_key(|f| std::cmp::Reverse(f.date));
+        self.recent_flows = Some(recent);
+    }
+
+    /// Takes the freshly loaded outstanding reimbursements. `flows` may
+    /// include others, e.g. claims a pending change has since paid back.
+    pub fn set_outstanding_reimbursements(&mut self, mut flows: Vec<Flow>) {
+        flows.retain(|f| f.is_outstanding_reimbursement());
+        flows.sort_by_key(|f| f.date);
+        self.outstanding_reimbursements = Some(flows);
+    }
+
+    /// Takes the ratios from the database's summaries when `stored` allows,
+    /// and works them out over `flows` when it can't.
+    fn update_tracking_ratios(&mut self, flows: &[Flow], categories: &[Category], stored: &mut StoredTotals) {
+        if !self.needs_update && !self.retry_stored && !self.tracking_ratios.is_empty() {
+            return;
+        }
+
+        let summaries = match stored.summaries() {
+            Stored::Ready(summaries) => summaries,
+            Stored::Loading => {
+                self.awaiting_stored = true;
+                return;
+            }
+            Stored::Unavailable => return self.update_tracking_ratios_as_of(flows, categories, Local::now().naive_local().date()),
+        };
+        self.tracking_ratios = categories.iter()
+            .filter_map(|category| {
+                let ratio = summaries.get(&category.id)?.tracking_ratio?;
+                Some((category.name.clone(), ratio))
+            })
+            .collect();
+        self.tracking_ratios.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
     }
 
     /// Core of `update_tracking_ratios`, parameterized on "today" so it's
@@ -78,18 +636,136 @@ impl Dashboard {
         self.tracking_ratios.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui, flows: &[Flow], categories: &[Category]) {
-        // Update financial summary and tracking ratios if needed
-        self.update_financial_summary(flows, categories);
-        self.update_tracking_ratios(flows, categories);
-        
-        // Reset the update flag after both functions have run
-        self.needs_update = false;
+    /// Recomputes the dashboard's aggregates from scratch and describes each
+    /// cached one that disagrees. Like `CategoryFlowsState`, a dashboard
+    /// already marked for update is expected to be stale.
+    pub(crate) fn discrepancies_as_of(&self, flows: &[Flow], categories: &[Category], as_of: NaiveDate) -> Vec<String> {
+        if self.needs_update {
+            return Vec::new();
+        }
+
+        let mut fresh = Dashboard::new();
+        fresh.period = self.period;
+        fresh.other_currency_categories = self.other_currency_categories.clone();
+        fresh.update_financial_summary_as_of(flows, categories, as_of);
+        fresh.update_tracking_ratios_as_of(flows, categories, as_of);
+
+        let mut found = Vec::new();
+        if let (Some(cached), Some(expected)) = (self.financial_summary, fresh.financial_summary) {
+            for (label, cached, expected) in [
+                ("total income", cached.0, expected.0),
+                ("total expenses", cached.1, expected.1),
+                ("net total", cached.2, expected.2),
+            ] {
+                if utils::totals_differ(cached, expected) {
+                    found.push(format!("Dashboard: cached {} ${:.2}, expected ${:.2}", label, cached, expected));
+                }
+            }
+        }
+
+        let ratios_match = self.tracking_ratios.len() == fresh.tracking_ratios.len()
+            && self.tracking_ratios.iter().zip(&fresh.tracking_ratios)
+                .all(|(cached, expected)| cached.0 == expected.0 && !utils::totals_differ(cached.1, expected.1));
+        if !ratios_match {
+            found.push("Dashboard: cached tracking ratios don't match a fresh calculation".to_string());
+        }
+        found
+    }
+
+    /// Returns the id of a category the user asked to open, by clicking a
+    /// recent flow. `flows` are the ones in `scope`; while they're still
+    /// loading (`None`) the last figures stay up, still marked for update.
+    pub fn show(&mut self, ui: &mut egui::Ui, flows: Option<&[Flow]>, categories: &[Category], settings: &UserSettings, stored: &mut StoredTotals, all_time: &mut AllTimeTotals) -> Option<String> {
+        let kpi_cards = &settings.kpi_cards;
+        let number_format = &settings.number_format;
+
+        // Update financial summary and tracking ratios if needed. Which
+        // categories are in another currency comes first, since the
+        // summary, chart and breakdown leave those out.
+        if let Some(flows) = flows {
+            self.retry_stored = std::mem::take(&mut self.awaiting_stored);
+            self.update_currency_subtotals(flows, categories, settings);
+            self.update_financial_summary(flows, categories, stored);
+            self.update_tracking_ratios(flows, categories, stored);
+            self.update_monthly_totals(flows, categories);
+            self.update_expense_breakdown(flows, categories);
+            self.update_top_counterparties(flows, categories);
+            self.update_forecasts(flows, categories);
+            self.update_kpi_values(flows, categories, kpi_cards, all_time);
+            self.update_year_comparison(flows, categories, stored);
+            self.update_recent_flows(flows);
+
+            // Reset the update flag after all of them have run
+            self.needs_update = false;
+        }
 
         ui.heading("Financial Dashboard");
+        if flows.is_none() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Loading flows...");
+            });
+        }
+        self.show_kpi_cards(ui, kpi_cards, number_format);
+        self.show_period_selector(ui);
+        ui.separator();
+
+        if self.compare_years {
+            self.show_year_comparison(ui, number_format);
+        } else {
+            self.show_financial_summary(ui, number_format);
+        }
+
         ui.separator();
 
-        // Financial Summary
+        let mut opened = self.show_recent_flows(ui, categories, settings.get_recent_flows_count(), settings);
+
+        ui.separator();
+
+        if self.outstanding_reimbursements.as_ref().is_some_and(|flows| !flows.is_empty()) {
+            opened = opened.or(self.show_outstanding_reimbursements(ui, categories, settings));
+            ui.separator();
+        }
+
+        self.show_forecasts(ui, number_format);
+
+        ui.separator();
+
+        self.show_spending_chart(ui, number_format);
+
+        ui.separator();
+
+        self.show_expense_breakdown(ui, number_format);
+
+        ui.separator();
+
+        self.show_top_counterparties(ui, number_format);
+
+        ui.separator();
+
+        // Category Tracking Ratios
+        ui.heading("Category Tracking Ratios");
+        egui::Grid::new("tracking_ratios_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (category_name, ratio) in &self.tracking_ratios {
+                    ui.label(category_name);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let color = if *ratio >= 1.0 {
+                            egui::Color32::GREEN
+                        } else {
+                            egui::Color32::RED
+                        };
+                        ui.label(egui::RichText::new(format!("{:.2}", ratio)).color(color));
+                    });
+                    ui.end_row();
+                }
+            });
+
+        opened
+    }
+
+    fn show_financial_summary(&self, ui: &mut egui::Ui, number_format: &NumberFormat) {
         ui.heading("Financial Summary");
         if let Some((income, expenses, net)) = self.financial_summary {
             egui::Grid::new("financial_summary_grid")
@@ -97,13 +773,13 @@ impl Dashboard {
                 .show(ui, |ui| {
                     ui.label("Total Income:");
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.label(format!("${:.2}", income));
+                        ui.label(number_format.format_currency(income));
                     });
                     ui.end_row();
 
                     ui.label("Total Expenses:");
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.label(format!("${:.2}", expenses));
+                        ui.label(number_format.format_currency(expenses));
                     });
                     ui.end_row();
 
@@ -114,33 +790,453 @@ impl Dashboard {
                         } else {
                             egui::Color32::RED
                         };
-                        ui.label(egui::RichText::new(format!("${:.2}", net)).color(color));
+                        ui.label(egui::RichText::new(number_format.format_currency(net)).color(color));
                     });
                     ui.end_row();
                 });
         }
+        self.show_currency_subtotals(ui);
+    }
 
-        ui.separator();
+    /// The period's income, expenses and net in each other currency, below
+    /// (never added into) the app currency's totals.
+    fn show_currency_subtotals(&self, ui: &mut egui::Ui) {
+        let Some(subtotals) = &self.currency_subtotals else { return };
+        for subtotal in subtotals {
+            let symbol = subtotal.format.currency_symbol.trim();
+            ui.add_space(4.0);
+            ui.strong(format!("In {}", symbol))
+                .on_hover_text("Categories shown in this currency aren't included in the totals above, since amounts aren't converted between currencies.");
+            egui::Grid::new(format!("currency_subtotal_grid_{}", symbol))
+                .striped(true)
+                .show(ui, |ui| {
+                    let net = subtotal.income - subtotal.expenses;
+                    for (label, amount) in [("Income:", subtotal.income), ("Expenses:", subtotal.expenses), ("Net:", net)] {
+                        ui.label(label);
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(subtotal.format.format_currency(amount));
+                        });
+                        ui.end_row();
+                    }
+                });
+        }
+    }
 
-        // Category Tracking Ratios
-        ui.heading("Category Tracking Ratios");
-        egui::Grid::new("tracking_ratios_grid")
+    /// Income, expenses and each category side by side for both years, with
+    /// an arrow showing which way each moved. More income is good news and
+    /// more spending bad, so the arrows are colored accordingly.
+    fn show_year_comparison(&self, ui: &mut egui::Ui, number_format: &NumberFormat) {
+        ui.heading("This Year vs. Last Year");
+        let Some(comparison) = &self.year_comparison else { return };
+        let stretch = |(start, end): (NaiveDate, NaiveDate)| format!("{} to {}", start.format("%b %d, %Y"), end.format("%b %d, %Y"));
+        ui.label(format!("{} compared with {}", stretch(comparison.this_year), stretch(comparison.last_year)));
+
+        let delta = |ui: &mut egui::Ui, flow_type: &FlowType, last: f64, this: f64| {
+            let change = this - last;
+            if utils::totals_differ(change, 0.0) {
+                let rising = change > 0.0;
+                let good = rising == (*flow_type == FlowType::Income);
+                let arrow = if rising { "\u{25B2}" } else { "\u{25BC}" };
+                let percent = if last != 0.0 { format!(" ({:+.0}%)", change / last.abs() * 100.0) } else { String::new() };
+                let color = if good { egui::Color32::GREEN } else { egui::Color32::RED };
+                ui.label(egui::RichText::new(format!("{} {}{}", arrow, number_format.format_currency(change.abs()), percent)).color(color));
+            } else {
+                ui.label("\u{2014}");
+            }
+        };
+
+        egui::Grid::new("year_comparison_grid")
             .striped(true)
             .show(ui, |ui| {
-                for (category_name, ratio) in &self.tracking_ratios {
-                    ui.label(category_name);
+                ui.label("");
+                ui.strong(comparison.last_year.0.year().to_string());
+                ui.strong(comparison.this_year.0.year().to_string());
+                ui.strong("Change");
+                ui.end_row();
+
+                let row = |ui: &mut egui::Ui, label: egui::RichText, flow_type: &FlowType, (last, this): (f64, f64)| {
+                    ui.label(label);
+                    for value in [last, this] {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(number_format.format_currency(value));
+                        });
+                    }
+                    delta(ui, flow_type, last, this);
+                    ui.end_row();
+                };
+
+                row(ui, egui::RichText::new("Total Income").strong(), &FlowType::Income, comparison.income);
+                row(ui, egui::RichText::new("Total Expenses").strong(), &FlowType::Expense, comparison.expenses);
+                for (name, flow_type, last, this) in &comparison.categories {
+                    row(ui, egui::RichText::new(name), flow_type, (*last, *this));
+                }
+            });
+    }
+
+    /// The newest flows in the period, each linking to its category.
+    /// Returns the category clicked, if any.
+    fn show_recent_flows(&mut self, ui: &mut egui::Ui, categories: &[Category], count: usize, settings: &UserSettings) -> Option<String> {
+        ui.heading("Recent Flows");
+        let recent = self.recent_flows.as_ref()?;
+        if recent.is_empty() {
+            ui.label("No flows recorded in this period yet.");
+            return None;
+        }
+
+        let shown = if self.show_all_recent { recent.len() } else { count.min(recent.len()) };
+        let mut opened = None;
+        egui::ScrollArea::vertical()
+            .id_source("recent_flows_scroll")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("recent_flows_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for flow in &recent[..shown] {
+                            ui.label(flow.date.to_string());
+                            let category_name = categories.iter()
+                                .find(|c| c.id == flow.category_id)
+                                .map_or(flow.category_id.as_str(), |c| c.name.as_str());
+                            if ui.link(category_name).on_hover_text("Open this category").clicked() {
+                                opened = Some(flow.category_id.clone());
+                            }
+                            ui.label(&flow.description);
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.label(settings.number_format_for(&flow.category_id).format_currency(flow.net_amount()));
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if recent.len() > count {
+            let label = if self.show_all_recent {
+                "Show Fewer".to_string()
+            } else {
+                format!("View All {} in Period", recent.len())
+            };
+            if ui.button(label).clicked() {
+                self.show_all_recent = !self.show_all_recent;
+            }
+        }
+        opened
+    }
+
+    /// Only shown while something is owed. Returns the id of a category the
+    /// user asked to open.
+    fn show_outstanding_reimbursements(&self, ui: &mut egui::Ui, categories: &[Category], settings: &UserSettings) -> Option<String> {
+        let outstanding = self.outstanding_reimbursements.as_ref()?;
+        let number_format = &settings.number_format;
+        let total = |submitted: bool| -> f64 {
+            outstanding.iter()
+                .filter(|f| !self.other_currency_categories.contains(&f.category_id))
+                .filter(|f| (f.reimbursement == Some(ReimbursementStatus::Submitted)) == submitted)
+                .map(|f| f.net_amount())
+                .sum()
+        };
+
+        ui.heading("Outstanding Reimbursements");
+        ui.label(format!(
+            "{} owed: {} not yet submitted, {} submitted and awaiting payment.",
+            number_format.format_currency(total(false) + total(true)),
+            number_format.format_currency(total(false)),
+            number_format.format_currency(total(true)),
+        ));
+        if outstanding.iter().any(|f| self.other_currency_categories.contains(&f.category_id)) {
+            ui.label(egui::RichText::new("Claims in other currencies are listed below but not included in these totals.").weak());
+        }
+
+        let mut opened = None;
+        egui::ScrollArea::vertical()
+            .id_source("outstanding_reimbursements_scroll")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("outstanding_reimbursements_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for flow in outstanding {
+                            ui.label(flow.date.to_string());
+                            let category_name = categories.iter()
+                                .find(|c| c.id == flow.category_id)
+                                .map_or(flow.category_id.as_str(), |c| c.name.as_str());
+                            if ui.link(category_name).on_hover_text("Open this category").clicked() {
+                                opened = Some(flow.category_id.clone());
+                            }
+                            ui.label(&flow.description);
+                            ui.label(flow.reimbursement.map_or("", |status| status.get_display_name()));
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.label(settings.number_format_for(&flow.category_id).format_currency(flow.net_amount()));
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        opened
+    }
+
+    /// Changing the period recomputes everything it scopes on the next
+    /// frame.
+    fn show_period_selector(&mut self, ui: &mut egui::Ui) {
+        let before = self.period;
+        ui.horizontal(|ui| {
+            ui.label("Period:");
+            egui::ComboBox::from_id_source("dashboard_period")
+                .selected_text(self.period.get_display_name())
+                .show_ui(ui, |ui| {
+                    for period in DashboardPeriod::PRESETS {
+                        ui.selectable_value(&mut self.period, period, period.get_display_name());
+                    }
+                    let is_custom = matches!(self.period, DashboardPeriod::Custom(_, _));
+                    if ui.selectable_label(is_custom, "Custom").clicked() && !is_custom {
+                        let (start, end) = self.period.bounds(Local::now().naive_local().date());
+                        self.period = DashboardPeriod::Custom(start, end);
+                    }
+                });
+
+            if let DashboardPeriod::Custom(start, end) = &mut self.period {
+                ui.label("From:");
+                ui.add(egui_extras::DatePickerButton::new(start).id_source("dashboard_custom_start"));
+                ui.label("To:");
+                ui.add(egui_extras::DatePickerButton::new(end).id_source("dashboard_custom_end"));
+            } else {
+                let (start, end) = self.period.bounds(Local::now().naive_local().date());
+                ui.label(format!("{} to {}", start.format("%b %d, %Y"), end.format("%b %d, %Y")));
+            }
+            ui.checkbox(&mut self.compare_years, "Compare with last year")
+                .on_hover_text("Show this year to date against the same stretch of last year instead of the period's summary");
+        });
+        if self.period != before {
+            self.mark_for_update();
+        }
+    }
+
+    /// The user's KPI cards (see `kpi`), wrapping onto more rows as needed.
+    fn show_kpi_cards(&self, ui: &mut egui::Ui, kpi_cards: &[KpiCard], number_format: &NumberFormat) {
+        let Some(values) = &self.kpi_values else { return };
+        if kpi_cards.is_empty() {
+            return;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for (card, value) in kpi_cards.iter().zip(values) {
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(140.0);
+                    ui.vertical(|ui| {
+                        ui.label(egui::RichText::new(&card.label).small());
+                        ui.label(egui::RichText::new(card.format_value(*value, number_format)).heading().strong());
+                    });
+                });
+            }
+        });
+    }
+
+    /// End-of-month and end-of-year projected net, with what each is made
+    /// of so the estimate can be judged.
+    fn show_forecasts(&self, ui: &mut egui::Ui, number_format: &NumberFormat) {
+        ui.heading("Cash-Flow Forecast");
+        let Some([month, year]) = &self.forecasts else { return };
+
+        egui::Grid::new("forecast_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.strong(format!("End of {}", month.period_end.format("%B")));
+                ui.strong(format!("End of {}", year.period_end.year()));
+                ui.end_row();
+
+                for (label, values) in [
+                    ("So far", [month.actual, year.actual]),
+                    ("Scheduled", [month.scheduled, year.scheduled]),
+                    ("Recurring", [month.recurring, year.recurring]),
+                    ("Estimated from past spending", [month.estimated, year.estimated]),
+                ] {
+                    ui.label(label);
+                    for value in values {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(number_format.format_currency(value));
+                        });
+                    }
+                    ui.end_row();
+                }
+
+                ui.strong("Projected net");
+                for forecast in [month, year] {
+                    let net = forecast.projected_net();
+                    let color = if net >= 0.0 { egui::Color32::GREEN } else { egui::Color32::RED };
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        let color = if *ratio >= 1.0 {
-                            egui::Color32::GREEN
-                        } else {
-                            egui::Color32::RED
-                        };
-                        ui.label(egui::RichText::new(format!("{:.2}", ratio)).color(color));
+                        ui.label(egui::RichText::new(number_format.format_currency(net)).color(color).strong());
+                    });
+                }
+                ui.end_row();
+            });
+    }
+
+    /// A donut chart of each expense category's share of the period's
+    /// spending, with a legend giving the amounts.
+    fn show_expense_breakdown(&self, ui: &mut egui::Ui, number_format: &NumberFormat) {
+        ui.heading("Expense Breakdown");
+        let Some(breakdown) = &self.expense_breakdown else { return };
+        if breakdown.is_empty() {
+            ui.label("No expenses recorded in this period.");
+        } else {
+            self.show_breakdown_chart(ui, breakdown, number_format);
+        }
+
+        // Other currencies get a list each rather than slices of the chart,
+        // whose shares would otherwise mix amounts in different currencies.
+        for subtotal in self.currency_subtotals.iter().flatten().filter(|s| !s.breakdown.is_empty()) {
+            let symbol = subtotal.format.currency_symbol.trim();
+            ui.add_space(4.0);
+            ui.strong(format!("In {}", symbol));
+            egui::Grid::new(format!("currency_breakdown_grid_{}", symbol)).show(ui, |ui| {
+                for (name, amount) in &subtotal.breakdown {
+                    ui.label(name);
+                    ui.label(subtotal.format.format_currency(*amount));
+                    ui.label(format!("{:.1}%", amount / subtotal.expenses * 100.0));
+                    ui.end_row();
+                }
+            });
+        }
+    }
+
+    fn show_breakdown_chart(&self, ui: &mut egui::Ui, breakdown: &[(String, f64)], number_format: &NumberFormat) {
+        let total: f64 = breakdown.iter().map(|(_, amount)| amount).sum();
+
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 160.0), egui::Sense::hover());
+            let colors: Vec<egui::Color32> = breakdown.iter().enumerate()
+                .map(|(i, (name, _))| self.breakdown_colors.get(name).copied().unwrap_or(SLICE_COLORS[i % SLICE_COLORS.len()]))
+                .collect();
+            let slices: Vec<(f64, egui::Color32)> = breakdown.iter().zip(&colors).map(|((_, amount), color)| (amount / total, *color)).collect();
+            paint_donut(ui.painter(), rect.center(), 75.0, 40.0, &slices);
+
+            egui::Grid::new("expense_breakdown_grid").show(ui, |ui| {
+                for ((name, amount), color) in breakdown.iter().zip(&colors) {
+                    let (swatch, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                    ui.painter().rect_filled(swatch, 2.0, *color);
+                    ui.label(name);
+                    ui.label(number_format.format_currency(*amount));
+                    ui.label(format!("{:.1}%", amount / total * 100.0));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    fn show_top_counterparties(&self, ui: &mut egui::Ui, number_format: &NumberFormat) {
+        ui.heading("Top Recipients and Providers");
+        let Some(top) = &self.top_counterparties else { return };
+        if top.is_empty() {
+            ui.label("No flows in this period name a payee, recipient, provider, employer, payer or source.");
+            return;
+        }
+
+        egui::Grid::new("top_counterparties_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (name, flow_type, total) in top {
+                    ui.label(name);
+                    ui.label(match flow_type {
+                        FlowType::Income => "Received from",
+                        FlowType::Expense => "Paid to",
+                    });
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(number_format.format_currency(*total));
                     });
                     ui.end_row();
                 }
             });
     }
+
+    fn show_spending_chart(&mut self, ui: &mut egui::Ui, number_format: &NumberFormat) {
+        ui.horizontal(|ui| {
+            ui.heading("Spending Over Time");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.selectable_value(&mut self.chart_style, ChartStyle::Lines, "Lines");
+                ui.selectable_value(&mut self.chart_style, ChartStyle::Bars, "Bars");
+            });
+        });
+        let Some(totals) = &self.monthly_totals else { return };
+
+        let income_color = egui::Color32::from_rgb(80, 170, 80);
+        let expense_color = egui::Color32::from_rgb(210, 80, 70);
+        let currency_symbol = number_format.currency_symbol.clone();
+        // Only name the year when the period spans more than one.
+        let spans_years = totals.months.first().map(|m| m.0) != totals.months.last().map(|m| m.0);
+        let labels: Vec<String> = totals.months.iter()
+            .map(|(year, month)| if spans_years {
+                format!("{} '{:02}", MONTH_LABELS[*month as usize], year.rem_euclid(100))
+            } else {
+                MONTH_LABELS[*month as usize].to_string()
+            })
+            .collect();
+        let month_count = labels.len();
+        Plot::new("spending_over_time")
+            .height(200.0)
+            .legend(Legend::default())
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .include_x(-0.5)
+            .include_x(month_count as f64 - 0.5)
+            .include_y(0.0)
+            // Months are plotted at x = 0, 1, ...; there's no label between them.
+            .x_axis_formatter(move |x, _, _| {
+                let month = x.round();
+                if (x - month).abs() < 0.01 && (0.0..month_count as f64).contains(&month) {
+                    labels[month as usize].clone()
+                } else {
+                    String::new()
+                }
+            })
+            .y_axis_formatter(move |y, _, _| format!("{}{:.0}", currency_symbol, y))
+            .show(ui, |plot_ui| match self.chart_style {
+                ChartStyle::Bars => {
+                    let bars = |values: &[f64], offset: f64| -> Vec<Bar> {
+                        values.iter().zip(&totals.months).enumerate()
+                            .map(|(i, (value, (_, month)))| Bar::new(i as f64 + offset, *value).width(0.4).name(MONTH_LABELS[*month as usize]))
+                            .collect()
+                    };
+                    plot_ui.bar_chart(BarChart::new(bars(&totals.income, -0.2)).name("Income").color(income_color));
+                    plot_ui.bar_chart(BarChart::new(bars(&totals.expenses, 0.2)).name("Expenses").color(expense_color));
+                }
+                ChartStyle::Lines => {
+                    let points = |values: &[f64]| -> PlotPoints {
+                        values.iter().enumerate().map(|(i, value)| [i as f64, *value]).collect()
+                    };
+                    plot_ui.line(Line::new(points(&totals.income)).name("Income").color(income_color));
+                    plot_ui.line(Line::new(points(&totals.expenses)).name("Expenses").color(expense_color));
+                }
+            });
+    }
+}
+
+/// Paints a ring of slices clockwise from 12 o'clock, one per (share,
+/// color), each share a fraction of the whole.
+fn paint_donut(painter: &egui::Painter, center: egui::Pos2, outer_radius: f32, inner_radius: f32, slices: &[(f64, egui::Color32)]) {
+    use std::f32::consts::TAU;
+    let point = |angle: f32, radius: f32| center + radius * egui::vec2(angle.sin(), -angle.cos());
+
+    let mut mesh = egui::Mesh::default();
+    let mut start = 0.0_f32;
+    for &(share, color) in slices {
+        let sweep = share as f32 * TAU;
+        // About one segment every 3 degrees keeps the edge looking round.
+        let segments = ((sweep / TAU * 120.0).ceil() as usize).max(1);
+        for step in 0..segments {
+            let a0 = start + sweep * step as f32 / segments as f32;
+            let a1 = start + sweep * (step + 1) as f32 / segments as f32;
+            let base = mesh.vertices.len() as u32;
+            mesh.colored_vertex(point(a0, inner_radius), color);
+            mesh.colored_vertex(point(a0, outer_radius), color);
+            mesh.colored_vertex(point(a1, outer_radius), color);
+            mesh.colored_vertex(point(a1, inner_radius), color);
+            mesh.add_triangle(base, base + 1, base + 2);
+            mesh.add_triangle(base, base + 2, base + 3);
+        }
+        start += sweep;
+    }
+    painter.add(egui::Shape::mesh(mesh));
 }
 
 #[cfg(test)]
@@ -155,8 +1251,11 @@ mod tests {
             name: format!("Category {}", id),
             flow_type,
             parent_id: None,
+            sort_order: 0,
+            color: None,
+            icon: None,
             fields: Vec::new(),
-            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false },
+            tax_deduction: TaxDeductionInfo { deduction_allowed: false, default_value: false, jurisdictions: Vec::new() },
         }
     }
 
@@ -170,9 +1269,56 @@ mod tests {
             linked_flows: Vec::new(),
             custom_fields: HashMap::new(),
             tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
         }
     }
 
+    #[test]
+    fn financial_summary_summed_in_the_database_matches_the_flows() {
+        let categories = vec![
+            category("income-cat", FlowType::Income),
+            category("expense-cat", FlowType::Expense),
+        ];
+        let today = Local::now().naive_local().date();
+        let flows = vec![
+            flow("income-cat", today, 1000.0),
+            flow("expense-cat", today, 300.0),
+            Flow { refund_of: Some("x".to_string()), ..flow("expense-cat", today, 50.0) },
+        ];
+        let mut db = crate::db::Database::new_for_test(rusqlite::Connection::open_in_memory().unwrap()).unwrap();
+        for category in &categories {
+            db.save_category(category).unwrap();
+        }
+        for f in &flows {
+            db.save_flow(f).unwrap();
+        }
+        let worker = crate::db_worker::DbWorker::start(db, || {});
+
+        let ctx = egui::Context::default();
+        let mut stored = StoredTotals::default();
+        let mut from_db = Dashboard::new();
+        for _ in 0..200 {
+            stored.poll(&worker, true, &ctx);
+            from_db.update_financial_summary(&flows, &categories, &mut stored);
+            if from_db.financial_summary.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(matches!(stored.summaries(), Stored::Ready(_)), "summed in the database, not over the flows");
+        let mut from_flows = Dashboard::new();
+        from_flows.update_financial_summary_as_of(&flows, &categories, today);
+
+        assert_eq!(from_db.financial_summary, Some((1000.0, 250.0, 750.0)));
+        assert_eq!(from_db.financial_summary, from_flows.financial_summary);
+    }
+
     #[test]
     fn financial_summary_separates_income_and_expenses_by_flow_type() {
         let categories = vec![
@@ -191,6 +1337,38 @@ mod tests {
         assert_eq!(dashboard.financial_summary, Some((1000.0, 300.0, 700.0)));
     }
 
+    #[test]
+    fn categories_in_another_currency_are_totaled_apart_from_the_summary() {
+        let categories = vec![
+            category("income-cat", FlowType::Income),
+            category("expense-cat", FlowType::Expense),
+            category("rental-upkeep", FlowType::Expense),
+        ];
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let flows = vec![
+            flow("income-cat", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 1000.0),
+            flow("expense-cat", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 300.0),
+            flow("rental-upkeep", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 450.0),
+        ];
+        let mut settings = UserSettings::new();
+        let euros = NumberFormat { currency_symbol: "€".to_string(), ..NumberFormat::default() };
+        settings.set_category_number_format("rental-upkeep", Some(euros.clone()));
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_currency_subtotals_as_of(&flows, &categories, &settings, as_of);
+        dashboard.update_financial_summary_as_of(&flows, &categories, as_of);
+        dashboard.update_expense_breakdown_as_of(&flows, &categories, as_of);
+
+        assert_eq!(dashboard.financial_summary, Some((1000.0, 300.0, 700.0)));
+        assert_eq!(dashboard.expense_breakdown, Some(vec![("Category expense-cat".to_string(), 300.0)]));
+        assert_eq!(dashboard.currency_subtotals, Some(vec![CurrencySubtotal {
+            format: euros,
+            income: 0.0,
+            expenses: 450.0,
+            breakdown: vec![("Category rental-upkeep".to_string(), 450.0)],
+        }]));
+    }
+
     #[test]
     fn financial_summary_excludes_flows_from_other_years() {
         let categories = vec![category("income-cat", FlowType::Income)];
@@ -271,6 +1449,207 @@ mod tests {
         assert_eq!(dashboard.tracking_ratios[1].0, "Category ahead");
     }
 
+    #[test]
+    fn discrepancies_reports_a_stale_financial_summary() {
+        let categories = vec![category("income-cat", FlowType::Income)];
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let mut flows = vec![flow("income-cat", as_of, 100.0)];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_financial_summary_as_of(&flows, &categories, as_of);
+        dashboard.update_tracking_ratios_as_of(&flows, &categories, as_of);
+        dashboard.needs_update = false;
+        assert!(dashboard.discrepancies_as_of(&flows, &categories, as_of).is_empty());
+
+        flows.push(flow("income-cat", as_of, 50.0));
+        let found = dashboard.discrepancies_as_of(&flows, &categories, as_of);
+        assert!(found.iter().any(|d| d.contains("total income")), "{:?}", found);
+    }
+
+    #[test]
+    fn monthly_totals_split_this_years_income_and_expenses_by_month() {
+        let categories = vec![
+            category("income-cat", FlowType::Income),
+            category("expense-cat", FlowType::Expense),
+        ];
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let mut planned = flow("expense-cat", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 999.0);
+        planned.scheduled = true;
+        let flows = vec![
+            flow("income-cat", NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), 1000.0),
+            flow("expense-cat", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 200.0),
+            flow("expense-cat", NaiveDate::from_ymd_opt(2024, 3, 20).unwrap(), 50.0),
+            flow("expense-cat", NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(), 75.0),
+            planned,
+        ];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_monthly_totals_as_of(&flows, &categories, as_of);
+
+        let totals = dashboard.monthly_totals.unwrap();
+        assert_eq!(totals.income[0], 1000.0);
+        assert_eq!(totals.expenses[2], 250.0, "other years and scheduled flows are left out");
+        assert_eq!(totals.income.iter().sum::<f64>() + totals.expenses.iter().sum::<f64>(), 1250.0);
+    }
+
+    #[test]
+    fn expense_breakdown_is_largest_first_and_folds_small_categories_into_other() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let mut categories = vec![category("salary", FlowType::Income)];
+        let mut flows = vec![flow("salary", as_of, 5000.0)];
+        for i in 0..10 {
+            let id = format!("expense-{}", i);
+            categories.push(category(&id, FlowType::Expense));
+            flows.push(flow(&id, as_of, 100.0 * (i + 1) as f64));
+        }
+        flows.push(flow("expense-9", NaiveDate::from_ymd_opt(2023, 6, 15).unwrap(), 99999.0));
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_expense_breakdown_as_of(&flows, &categories, as_of);
+
+        let breakdown = dashboard.expense_breakdown.unwrap();
+        assert_eq!(breakdown.len(), MAX_BREAKDOWN_SLICES);
+        assert_eq!(breakdown[0], ("Category expense-9".to_string(), 1000.0), "last year's flows are left out");
+        assert_eq!(breakdown[MAX_BREAKDOWN_SLICES - 1], ("Other".to_string(), 100.0 + 200.0 + 300.0));
+        assert!(breakdown.iter().all(|(name, _)| name != "Category salary"));
+    }
+
+    #[test]
+    fn top_counterparties_merge_names_by_case_and_rank_by_total() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let categories = vec![category("medical", FlowType::Expense), category("salary", FlowType::Income)];
+        let named = |category_id: &str, field: &str, name: &str, amount: f64| {
+            let mut flow = flow(category_id, as_of, amount);
+            flow.custom_fields.insert(field.to_string(), name.to_string());
+            flow
+        };
+        let flows = vec![
+            named("medical", "provider", "Dr. Smith", 100.0),
+            named("medical", "provider", " dr. smith", 50.0),
+            named("medical", "provider", "City Clinic", 120.0),
+            named("salary", "employer", "Acme", 3000.0),
+            flow("medical", as_of, 999.0),
+        ];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_top_counterparties_as_of(&flows, &categories, as_of);
+
+        assert_eq!(dashboard.top_counterparties.unwrap(), vec![
+            ("Acme".to_string(), FlowType::Income, 3000.0),
+            ("Dr. Smith".to_string(), FlowType::Expense, 150.0),
+            ("City Clinic".to_string(), FlowType::Expense, 120.0),
+        ]);
+    }
+
+    #[test]
+    fn year_comparison_matches_this_year_to_date_with_the_same_stretch_last_year() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let categories = vec![
+            category("salary", FlowType::Income),
+            category("food", FlowType::Expense),
+            category("unused", FlowType::Expense),
+        ];
+        let flows = vec![
+            flow("salary", NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(), 3000.0),
+            flow("salary", NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 3500.0),
+            flow("food", NaiveDate::from_ymd_opt(2023, 6, 15).unwrap(), 200.0),
+            // After this date last year, so not part of the comparison.
+            flow("food", NaiveDate::from_ymd_opt(2023, 6, 16).unwrap(), 999.0),
+            flow("food", NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(), 150.0),
+        ];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_year_comparison_as_of(&flows, &categories, as_of);
+
+        let comparison = dashboard.year_comparison.unwrap();
+        assert_eq!(comparison.last_year, (NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2023, 6, 15).unwrap()));
+        assert_eq!(comparison.income, (3000.0, 3500.0));
+        assert_eq!(comparison.expenses, (200.0, 150.0));
+        assert_eq!(comparison.categories, vec![
+            ("Category salary".to_string(), FlowType::Income, 3000.0, 3500.0),
+            ("Category food".to_string(), FlowType::Expense, 200.0, 150.0),
+        ]);
+    }
+
+    #[test]
+    fn recent_flows_are_newest_first_and_stop_at_today() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 15).unwrap();
+        let mut planned = flow("food", NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(), 10.0);
+        planned.scheduled = true;
+        let flows = vec![
+            flow("food", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 1.0),
+            flow("food", NaiveDate::from_ymd_opt(2024, 6, 10).unwrap(), 2.0),
+            flow("food", NaiveDate::from_ymd_opt(2024, 6, 20).unwrap(), 3.0),
+            flow("food", NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(), 4.0),
+            planned,
+        ];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_recent_flows_as_of(&flows, as_of);
+
+        let amounts: Vec<f64> = dashboard.recent_flows.unwrap().iter().map(|f| f.amount).collect();
+        assert_eq!(amounts, vec![2.0, 1.0]);
+    }
+
+    #[test]
+    fn outstanding_reimbursements_keep_only_unpaid_claims_oldest_first() {
+        let with_status = |date: NaiveDate, amount: f64, status: ReimbursementStatus| Flow {
+            reimbursement: Some(status),
+            ..flow("food", date, amount)
+        };
+        let flows = vec![
+            with_status(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), 1.0, ReimbursementStatus::Submitted),
+            with_status(NaiveDate::from_ymd_opt(2022, 7, 1).unwrap(), 2.0, ReimbursementStatus::Outstanding),
+            with_status(NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(), 3.0, ReimbursementStatus::Reimbursed),
+            flow("food", NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(), 4.0),
+        ];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.set_outstanding_reimbursements(flows);
+
+        let amounts: Vec<f64> = dashboard.outstanding_reimbursements.unwrap().iter().map(|f| f.amount).collect();
+        assert_eq!(amounts, vec![2.0, 1.0]);
+    }
+
+    #[test]
+    fn period_bounds_are_inclusive_and_calendar_aligned() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 15).unwrap();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(DashboardPeriod::ThisMonth.bounds(today), (date(2024, 5, 1), date(2024, 5, 31)));
+        assert_eq!(DashboardPeriod::ThisQuarter.bounds(today), (date(2024, 4, 1), date(2024, 6, 30)));
+        assert_eq!(DashboardPeriod::ThisYear.bounds(today), (date(2024, 1, 1), date(2024, 12, 31)));
+        assert_eq!(DashboardPeriod::YearToDate.bounds(today), (date(2024, 1, 1), today));
+        assert_eq!(DashboardPeriod::Last12Months.bounds(today), (date(2023, 6, 1), today));
+        let custom = DashboardPeriod::Custom(date(2020, 2, 2), date(2020, 3, 3));
+        assert_eq!(custom.bounds(today), (date(2020, 2, 2), date(2020, 3, 3)));
+    }
+
+    #[test]
+    fn period_scopes_the_summary_and_the_chart_months() {
+        let categories = vec![
+            category("income-cat", FlowType::Income),
+            category("expense-cat", FlowType::Expense),
+        ];
+        let as_of = NaiveDate::from_ymd_opt(2024, 2, 10).unwrap();
+        let flows = vec![
+            flow("income-cat", NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(), 1000.0),
+            flow("income-cat", NaiveDate::from_ymd_opt(2023, 2, 28).unwrap(), 5000.0), // 13 months back
+            flow("expense-cat", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 300.0),
+        ];
+
+        let mut dashboard = Dashboard::new();
+        dashboard.period = DashboardPeriod::Last12Months;
+        dashboard.update_financial_summary_as_of(&flows, &categories, as_of);
+        dashboard.update_monthly_totals_as_of(&flows, &categories, as_of);
+
+        assert_eq!(dashboard.financial_summary, Some((1000.0, 300.0, 700.0)));
+        let totals = dashboard.monthly_totals.unwrap();
+        assert_eq!(totals.months.len(), 12);
+        assert_eq!(totals.months[0], (2023, 2), "March 2023");
+        assert_eq!(totals.income[0], 1000.0);
+        assert_eq!(totals.expenses[11], 300.0);
+    }
+
     #[test]
     fn new_dashboard_defaults_to_needs_update_with_no_summary() {
         let dashboard = Dashboard::new();
@@ -0,0 +1,192 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::encryption::secret_buffer;
+use crate::encryption_config::EncryptionMismatch;
+use super::{forget_password_fields, password_field};
+use zeroize::Zeroizing;
+
+/// The ways out of an `EncryptionMismatch`; which are offered depends on
+/// the mismatch and on whether the keystore still has a password.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repair {
+    /// Encrypt the plaintext data with the keystore's password.
+    EncryptData,
+    /// Forget the password so the keystore matches the plaintext data.
+    DisableEncryption,
+    /// Decrypt with the keystore's password and record that it's encrypted.
+    UnlockData,
+    /// Save the current settings over data that can't be decrypted.
+    ReplaceSettings,
+}
+
+impl Repair {
+    fn options(mismatch: EncryptionMismatch) -> [Repair; 2] {
+        match mismatch {
+            EncryptionMismatch::DataNotEncrypted => [Repair::EncryptData, Repair::DisableEncryption],
+            EncryptionMismatch::DataEncrypted => [Repair::UnlockData, Repair::ReplaceSettings],
+        }
+    }
+
+    fn get_display_name(&self) -> &'static str {
+        match self {
+            Repair::EncryptData => "Encrypt the data now with my password",
+            Repair::DisableEncryption => "Turn encryption off and keep the data unencrypted",
+            Repair::UnlockData => "Unlock the data with my password and keep it encrypted",
+            Repair::ReplaceSettings => "Replace the unreadable settings with defaults",
+        }
+    }
+
+    fn needs_password(&self) -> bool {
+        matches!(self, Repair::EncryptData | Repair::UnlockData)
+    }
+}
+
+/// Progress through the repair wizard shown at startup when the keystore
+/// and the database disagree about encryption.
+pub struct EncryptionRepairState {
+    pub mismatch: EncryptionMismatch,
+    repair: Option<Repair>,
+    password: Zeroizing<String>,
+    error: Option<String>,
+    /// Set once a repair has been applied, to the message shown on the
+    /// wizard's last step.
+    done: Option<String>,
+}
+
+impl EncryptionRepairState {
+    pub fn new(mismatch: EncryptionMismatch) -> Self {
+        Self {
+            mismatch,
+            repair: None,
+            password: secret_buffer(),
+            error: None,
+            done: None,
+        }
+    }
+}
+
+/// Explains what's out of step, lets the user pick a repair, and applies
+/// it. "Not Now" leaves everything as it is; the check runs again at the
+/// next startup.
+pub fn show_encryption_repair_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let Some(state) = app.encryption_repair.as_mut() else {
+        return;
+    };
+    let has_password = app.encryption_config.has_password();
+    let busy = app.encryption_busy;
+
+    let mut close = false;
+    let mut apply = None;
+
+    egui::Window::new("Repair Encryption")
+        .collapsible(false)
+        .resizable(false)
+        .default_width(460.0)
+        .show(ctx, |ui| {
+            if let Some(message) = &state.done {
+                ui.label(message);
+                ui.separator();
+                if ui.button("Close").clicked() {
+                    close = true;
+                }
+                return;
+            }
+
+            ui.heading("1. What's wrong");
+            ui.label(match state.mismatch {
+                EncryptionMismatch::DataNotEncrypted => {
+                    "Your system keystore says your data is encrypted, but it's stored unencrypted. \
+                     This happens when settings are saved before the password is entered."
+                }
+                EncryptionMismatch::DataEncrypted => {
+                    "Your data is stored encrypted, but your system keystore says it isn't. Until \
+                     this is fixed your settings (hidden categories, backup options and the \
+                     like) can't be read and defaults are shown. Flows and categories are \
+                     not affected."
+                }
+            });
+
+            ui.separator();
+            ui.heading("2. Choose a fix");
+            for repair in Repair::options(state.mismatch) {
+                let available = has_password || !repair.needs_password();
+                ui.add_enabled_ui(available, |ui| {
+                    ui.radio_value(&mut state.repair, Some(repair), repair.get_display_name())
+                        .on_disabled_hover_text("The keystore no longer has a password for this database");
+                });
+            }
+            if state.repair == Some(Repair::ReplaceSettings) {
+                ui.colored_label(egui::Color32::from_rgb(255, 140, 0), "The encrypted settings will be lost for good.");
+            }
+
+            let Some(repair) = state.repair else {
+                ui.separator();
+                if ui.button("Not Now").on_hover_text("Ask again next time Preft starts").clicked() {
+                    close = true;
+                }
+                return;
+            };
+
+            ui.separator();
+            ui.heading("3. Apply");
+            if repair.needs_password() {
+                ui.horizontal(|ui| {
+                    ui.label("Password:");
+                    ui.add(password_field(&mut state.password, "repair_password").desired_width(200.0));
+                });
+            }
+            if let Some(error) = &state.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.horizontal(|ui| {
+                let ready = !repair.needs_password() || !state.password.is_empty();
+                if ui.add_enabled(ready && !busy, egui::Button::new("Apply")).clicked() {
+                    apply = Some(repair);
+                }
+                if ui.button("Not Now").on_hover_text("Ask again next time Preft starts").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if apply.is_some() || close {
+        forget_password_fields(ctx, &["repair_password"]);
+    }
+    if let Some(repair) = apply {
+        let password = app.encryption_repair.as_mut()
+            .map(|s| std::mem::replace(&mut s.password, secret_buffer()))
+            .unwrap_or_else(secret_buffer);
+        let done = move |app: &mut PreftApp, result| record_repair(app, repair, result);
+        match repair {
+            Repair::EncryptData => app.encrypt_stored_data(&password, done),
+            Repair::DisableEncryption => app.disable_encryption(done),
+            Repair::UnlockData => app.unlock_stored_data(&password, done),
+            Repair::ReplaceSettings => app.replace_unreadable_settings(done),
+        }
+    }
+    if close {
+        app.encryption_repair = None;
+    }
+}
+
+/// Shows how a repair went, once the database thread has applied it.
+fn record_repair(app: &mut PreftApp, repair: Repair, result: anyhow::Result<()>) {
+    if let Some(state) = app.encryption_repair.as_mut() {
+        match result {
+            Ok(()) => {
+                state.error = None;
+                state.done = Some(match repair {
+                    Repair::EncryptData => "Your data is encrypted again.",
+                    Repair::DisableEncryption => "Encryption is off; your data stays unencrypted.",
+                    Repair::UnlockData => "Your data is unlocked and your settings are back.",
+                    Repair::ReplaceSettings => "Your settings have been reset and saved unencrypted.",
+                }.to_string());
+            }
+            Err(e) => {
+                log::error!("Encryption repair failed: {}", e);
+                state.error = Some(e.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::encryption::secret_buffer;
+use crate::export_bundle;
+use super::{forget_password_fields, password_field};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Extension for sealed export bundles.
+const BUNDLE_EXTENSION: &str = "preftbundle";
+
+/// The ids of the dialog's `password_field`s.
+const PASSWORD_FIELDS: [&str; 3] = ["bundle_password", "bundle_password_confirm", "bundle_open_password"];
+
+/// Form state for the export bundle dialog. Passwords are wiped as soon
+/// as an export or extraction finishes, successfully or not; egui's own
+/// copies are dropped then too, but not wiped (see `password_field`).
+pub struct ExportBundleState {
+    pub password: Zeroizing<String>,
+    pub password_confirm: Zeroizing<String>,
+    pub open_password: Zeroizing<String>,
+    /// Outcome of the last export or extraction.
+    pub status: Option<Result<String, String>>,
+}
+
+impl Default for ExportBundleState {
+    fn default() -> Self {
+        Self {
+            password: secret_buffer(),
+            password_confirm: secret_buffer(),
+            open_password: secret_buffer(),
+            status: None,
+        }
+    }
+}
+
+pub fn show_export_bundle_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_export_bundle_dialog;
+    let mut export = false;
+    let mut extract = false;
+
+    egui::Window::new("Export Everything")
+        .open(&mut show_window)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let state = &mut app.export_bundle_state;
+
+            ui.heading("Create Bundle");
+            ui.label("Saves a copy of the database, every flow as CSV, each of your SQL views as CSV, your categories and your settings as a single file, encrypted with the password below.");
+            ui.label(egui::RichText::new("Without this password the bundle cannot be opened; it is not your database password and is not stored anywhere.")
+                .color(egui::Color32::from_rgb(255, 140, 0)));
+            egui::Grid::new("export_bundle_passwords").show(ui, |ui| {
+                ui.label("Password:");
+                ui.add(password_field(&mut state.password, "bundle_password"));
+                ui.end_row();
+                ui.label("Confirm:");
+                ui.add(password_field(&mut state.password_confirm, "bundle_password_confirm"));
+                ui.end_row();
+            });
+            let mismatch = !state.password_confirm.is_empty() && state.password != state.password_confirm;
+            if mismatch {
+                ui.label(egui::RichText::new("Passwords do not match.").color(egui::Color32::RED));
+            }
+            let ready = !state.password.is_empty() && state.password == state.password_confirm;
+            if ui.add_enabled(ready, egui::Button::new("Export...")).clicked() {
+                export = true;
+            }
+
+            ui.separator();
+            ui.heading("Open Bundle");
+            ui.label("Extracts a bundle's files into a folder of your choice. To use the database, restore preft.db from the Backup & Restore window.");
+            ui.horizontal(|ui| {
+                ui.label("Password:");
+                ui.add(password_field(&mut state.open_password, "bundle_open_password"));
+                if ui.add_enabled(!state.open_password.is_empty(), egui::Button::new("Open and Extract...")).clicked() {
+                    extract = true;
+                }
+            });
+
+            match &state.status {
+                Some(Ok(message)) => {
+                    ui.label(egui::RichText::new(message).color(egui::Color32::GREEN));
+                }
+                Some(Err(message)) => {
+                    ui.label(egui::RichText::new(message).color(egui::Color32::RED));
+                }
+                None => {}
+            }
+        });
+
+    if export && let Some(path) = rfd::FileDialog::new()
+        .set_title("Save Export Bundle")
+        .set_file_name(format!("preft_export_{}.{}", chrono::Local::now().format("%Y%m%d"), BUNDLE_EXTENSION))
+        .add_filter("Preft Export Bundle", &[BUNDLE_EXTENSION])
+        .save_file()
+    {
+        let password = std::mem::replace(&mut app.export_bundle_state.password, secret_buffer());
+        app.export_bundle_state.password_confirm.zeroize();
+        forget_password_fields(ctx, &PASSWORD_FIELDS[..2]);
+        app.export_bundle_state.status = Some(Ok("Exporting...".to_string()));
+        app.export_bundle_files(move |app, files| {
+            let result = files
+                .and_then(|files| export_bundle::seal(&files, &password))
+                .and_then(|sealed| Ok(std::fs::write(&path, sealed)?));
+            app.export_bundle_state.status = Some(match result {
+                Ok(()) => Ok(format!("Exported to {}", path.display())),
+                Err(e) => {
+                    log::error!("Failed to export bundle: {}", e);
+                    Err(format!("Export failed: {}", e))
+                }
+            });
+        });
+    }
+
+    if extract
+        && let Some(bundle_path) = rfd::FileDialog::new()
+            .set_title("Open Export Bundle")
+            .add_filter("Preft Export Bundle", &[BUNDLE_EXTENSION])
+            .add_filter("All Files", &["*"])
+            .pick_file()
+        && let Some(dir) = rfd::FileDialog::new()
+            .set_title("Extract Into Folder")
+            .pick_folder()
+    {
+        let password = std::mem::replace(&mut app.export_bundle_state.open_password, secret_buffer());
+        forget_password_fields(ctx, &PASSWORD_FIELDS[2..]);
+        let result = std::fs::read(&bundle_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| export_bundle::open(&data, &password))
+            .and_then(|files| export_bundle::extract_to(&files, &dir));
+        app.export_bundle_state.status = Some(match result {
+            Ok(written) => Ok(format!("Extracted {} file(s) into {}", written.len(), dir.display())),
+            Err(e) => Err(format!("Could not open bundle: {}", e)),
+        });
+    }
+
+    if !show_window {
+        forget_password_fields(ctx, &PASSWORD_FIELDS);
+    }
+    app.show_export_bundle_dialog = show_window;
+}
@@ -1,8 +1,12 @@
 use eframe::egui;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
 
-use crate::models::{Flow, Category};
+use crate::models::{Flow, Category, FlowType, ReimbursementStatus};
 use crate::app::PreftApp;
+use crate::db::FlowScope;
+use crate::expression;
+use crate::utils;
 
 pub struct FlowEditorState {
     pub editor: Option<FlowEditor>,
@@ -50,15 +54,24 @@ pub struct FlowEditor {
     flow_data: Flow,
     is_new_flow: bool,
     has_set_focus: bool,
+    /// The date as typed; `flow_data.date` only follows it while it parses.
+    date_input: String,
     amount_input: String,
     description_input: String,
+    location_input: String,
+    /// Currency fields as typed, by field name, while
+    /// `PreftApp::custom_field_values` holds the stored form.
+    currency_inputs: HashMap<String, String>,
 }
 
 impl FlowEditor {
     pub fn new(flow: Flow, is_new_flow: bool) -> Self {
         Self {
+            date_input: flow.date.format("%Y-%m-%d").to_string(),
             amount_input: flow.amount.to_string(),
             description_input: flow.description.clone(),
+            location_input: flow.location.clone().unwrap_or_default(),
+            currency_inputs: HashMap::new(),
             flow_data: flow,
             is_new_flow,
             has_set_focus: false,
@@ -73,6 +86,248 @@ impl FlowEditor {
         self.flow_data
     }
 
+    /// Lets this flow be marked as a refund of another flow in the same
+    /// category, so the pair nets out in category totals. Only flows that
+    /// aren't refunds themselves are offered, and a flow that already has
+    /// refunds against it can't become one, so links never chain.
+    fn show_refund_picker(&mut self, ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
+        let Some(flows) = app.flows_in(&FlowScope::category(&category.id, None)) else {
+            ui.horizontal(|ui| {
+                ui.label("Refund Of:");
+                ui.spinner();
+            });
+            return;
+        };
+        if flows.iter().any(|f| f.refund_of.as_deref() == Some(self.flow_data.id.as_str())) {
+            return;
+        }
+
+        let mut candidates: Vec<&Flow> = flows.iter()
+            .filter(|f| f.id != self.flow_data.id && !f.is_refund())
+            .collect();
+        candidates.sort_by_key(|f| std::cmp::Reverse(f.date));
+        let label = |f: &Flow| format!("{} - {} ({})", f.date, f.description, app.user_settings.number_format.format_currency(f.amount));
+
+        ui.horizontal(|ui| {
+            ui.label("Refund Of:");
+            let mut selected = self.flow_data.refund_of.clone();
+            egui::ComboBox::from_id_source("refund_of")
+                .selected_text(
+                    selected.as_ref()
+                        .and_then(|id| flows.iter().find(|f| f.id == *id))
+                        .map(label)
+                        .unwrap_or_else(|| "Not a refund".to_string())
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "Not a refund");
+                    for flow in &candidates {
+                        ui.selectable_value(&mut selected, Some(flow.id.clone()), label(flow));
+                    }
+                });
+
+            if selected != self.flow_data.refund_of {
+                // A refund of a deductible expense reduces the deductible
+                // total, so it inherits the original's deductibility.
+                if let Some(original) = selected.as_ref().and_then(|id| flows.iter().find(|f| f.id == *id)) {
+                    self.flow_data.tax_deductible = original.tax_deductible;
+                }
+                self.flow_data.refund_of = selected;
+            }
+        });
+    }
+
+    /// Marks an expense as reimbursable and tracks it until it's paid back,
+    /// when it can be linked to the income flow that paid it. The income
+    /// categories' flows are only loaded once it's marked reimbursed.
+    fn show_reimbursement_input(&mut self, ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
+        if category.flow_type != FlowType::Expense {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            let mut reimbursable = self.flow_data.reimbursement.is_some();
+            if ui.checkbox(&mut reimbursable, "Reimbursable").changed() {
+                self.flow_data.reimbursement = reimbursable.then_some(ReimbursementStatus::Outstanding);
+            }
+            let Some(status) = &mut self.flow_data.reimbursement else {
+                return;
+            };
+            egui::ComboBox::from_id_source("reimbursement_status")
+                .selected_text(status.get_display_name())
+                .show_ui(ui, |ui| {
+                    for variant in ReimbursementStatus::ALL {
+                        ui.selectable_value(status, variant, variant.get_display_name());
+                    }
+                });
+        });
+
+        if self.flow_data.reimbursement != Some(ReimbursementStatus::Reimbursed) {
+            self.flow_data.reimbursed_by = None;
+            return;
+        }
+        let income_categories: Vec<String> = app.categories.iter()
+            .filter(|c| c.flow_type == FlowType::Income)
+            .map(|c| c.id.clone())
+            .collect();
+        let loaded: Option<Vec<_>> = income_categories.iter()
+            .map(|id| app.flows_in(&FlowScope::category(id, None)))
+            .collect();
+        let Some(loaded) = loaded else {
+            ui.horizontal(|ui| {
+                ui.label("Reimbursed By:");
+                ui.spinner();
+            });
+            return;
+        };
+        let mut income_flows: Vec<&Flow> = loaded.iter()
+            .flat_map(|flows| flows.iter())
+            .filter(|f| !f.is_refund())
+            .collect();
+        income_flows.sort_by_key(|f| std::cmp::Reverse(f.date));
+        let label = |f: &Flow| format!("{} - {} ({})", f.date, f.description, app.user_settings.number_format.format_currency(f.amount));
+
+        ui.horizontal(|ui| {
+            ui.label("Reimbursed By:");
+            egui::ComboBox::from_id_source("reimbursed_by")
+                .selected_text(
+                    self.flow_data.reimbursed_by.as_ref()
+                        .and_then(|id| income_flows.iter().find(|f| f.id == *id))
+                        .map(|f| label(f))
+                        .unwrap_or_else(|| "No linked income".to_string())
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.flow_data.reimbursed_by, None, "No linked income");
+                    for flow in &income_flows {
+                        ui.selectable_value(&mut self.flow_data.reimbursed_by, Some(flow.id.clone()), label(flow));
+                    }
+                });
+        });
+    }
+
+    /// A typed date alongside a calendar picker. Typing only moves the date
+    /// once the text is a valid `YYYY-MM-DD` date, and leaving the field
+    /// restores the text to the date actually set, so a half-typed or
+    /// mistyped date never reaches the flow.
+    fn show_date_input(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Date:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.date_input)
+                .desired_width(90.0)
+                .hint_text("YYYY-MM-DD"));
+            let typed = NaiveDate::parse_from_str(self.date_input.trim(), "%Y-%m-%d");
+            if response.changed() && let Ok(date) = typed {
+                self.flow_data.date = date;
+            }
+            if ui.add(egui_extras::DatePickerButton::new(&mut self.flow_data.date)).changed()
+                || (response.lost_focus() && typed.is_err())
+            {
+                self.date_input = self.flow_data.date.format("%Y-%m-%d").to_string();
+            } else if response.has_focus() && typed.is_err() {
+                ui.colored_label(egui::Color32::RED, "Use YYYY-MM-DD");
+            }
+        });
+    }
+
+    /// The custom fields the flow can't be saved with yet (see
+    /// `Category::field_errors`), checking the values as `save_flow` will
+    /// store them: the editor's over the flow's own.
+    fn field_errors(&self, app: &PreftApp, category: &Category) -> HashMap<String, String> {
+        let mut values = self.flow_data.custom_fields.clone();
+        values.extend(app.custom_field_values.iter().map(|(name, value)| (name.clone(), value.clone())));
+        category.field_errors(&values)
+    }
+
+    /// Free-text location or venue, suggesting matching places used before
+    /// in the category so the same city is spelled the same way each time.
+    fn show_location_input(&mut self, ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
+        ui.horizontal(|ui| {
+            ui.label("Location:");
+            ui.add(egui::TextEdit::singleline(&mut self.location_input).hint_text("City or venue (optional)"));
+        });
+
+        if !self.location_input.trim().is_empty() {
+            let flows = app.flows_in(&FlowScope::category(&category.id, None)).unwrap_or_default();
+            let suggestions = utils::location_suggestions(&flows, &self.location_input, 5);
+            if !suggestions.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.add_space(60.0);
+                    for suggestion in suggestions {
+                        if ui.small_button(&suggestion).clicked() {
+                            self.location_input = suggestion;
+                        }
+                    }
+                });
+            }
+        }
+
+        let location = self.location_input.trim();
+        self.flow_data.location = (!location.is_empty()).then(|| location.to_string());
+    }
+
+    /// Trip the flow belongs to. Only shown once a trip exists; trips
+    /// covering the flow's date are listed first.
+    fn show_trip_input(&mut self, ui: &mut egui::Ui, app: &PreftApp) {
+        if app.trips.is_empty() {
+            return;
+        }
+        let date = self.flow_data.date;
+        let mut trips: Vec<&crate::models::Trip> = app.trips.iter().collect();
+        trips.sort_by_key(|t| !t.contains(date));
+
+        ui.horizontal(|ui| {
+            ui.label("Trip:");
+            egui::ComboBox::from_id_source("flow_trip")
+                .selected_text(
+                    self.flow_data.trip_id.as_ref()
+                        .and_then(|id| app.trips.iter().find(|t| t.id == *id))
+                        .map_or_else(|| "No trip".to_string(), |t| t.name.clone())
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.flow_data.trip_id, None, "No trip");
+                    for trip in trips {
+                        let label = format!("{} ({} to {})", trip.name, trip.start_date, trip.end_date);
+                        ui.selectable_value(&mut self.flow_data.trip_id, Some(trip.id.clone()), label);
+                    }
+                });
+        });
+    }
+
+    /// Dates are local, so a flow entered away from the home timezone (see
+    /// `UserSettings::home_utc_offset`) can be dated a day off from what it
+    /// would be at home -- enough to move it into another month or year.
+    fn show_timezone_notes(&mut self, ui: &mut egui::Ui, app: &PreftApp) {
+        let Some(home_offset) = app.user_settings.home_utc_offset else {
+            return;
+        };
+        let home = crate::utils::format_utc_offset(home_offset);
+
+        if self.is_new_flow {
+            let local_offset = crate::utils::local_utc_offset();
+            let now = chrono::Utc::now();
+            if let Some((local_today, home_today)) = crate::utils::ambiguous_today(now, local_offset, home_offset)
+                && self.flow_data.date == local_today
+            {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!(
+                        "Today is {} here ({}) but {} in your home timezone ({}).",
+                        local_today, crate::utils::format_utc_offset(local_offset), home_today, home
+                    )).color(egui::Color32::YELLOW));
+                    if ui.button(format!("Use {}", home_today)).clicked() {
+                        self.flow_data.date = home_today;
+                        self.date_input = home_today.format("%Y-%m-%d").to_string();
+                    }
+                });
+            }
+        } else if let Some(created_offset) = self.flow_data.created_utc_offset
+            && created_offset != home_offset
+        {
+            ui.label(egui::RichText::new(format!(
+                "Entered in {} (home timezone is {}).",
+                crate::utils::format_utc_offset(created_offset), home
+            )).weak());
+        }
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui, app: &mut PreftApp, category: &Category) {
         let window_id = egui::Id::new("flow_editor_window");
         egui::Window::new("Edit Flow")
@@ -82,19 +337,35 @@ impl FlowEditor {
             .show(ui.ctx(), |ui| {                
                 ui.vertical(|ui| {
                     // Basic flow information
-                    ui.horizontal(|ui| {
-                        ui.label("Date:");
-                        // A picker guarantees a valid date by construction --
-                        // no free-text parsing/validation needed here at all.
-                        ui.add(egui_extras::DatePickerButton::new(&mut self.flow_data.date));
-                    });
+                    self.show_date_input(ui);
+
+                    self.show_timezone_notes(ui, app);
+
+                    // Only future-dated flows can be planned ahead; one
+                    // moved back to today or earlier has already happened.
+                    let today = chrono::Local::now().naive_local().date();
+                    if self.flow_data.date > today {
+                        ui.checkbox(&mut self.flow_data.scheduled, "Scheduled (not counted in totals until confirmed)");
+                    } else {
+                        self.flow_data.scheduled = false;
+                    }
 
                     ui.horizontal(|ui| {
                         ui.label("Amount:");
+                        // Arithmetic like "12.50*3+4" is worked out as it's
+                        // typed and replaced by its result on leaving the field.
+                        let number_format = app.user_settings.number_format_for(&category.id);
                         let amount_response = ui.text_edit_singleline(&mut self.amount_input);
-                        if amount_response.changed() {
-                            if let Ok(amount) = self.amount_input.parse::<f64>() {
-                                self.flow_data.amount = amount;
+                        if amount_response.changed()
+                            && let Some(amount) = expression::evaluate_amount(&self.amount_input, number_format)
+                        {
+                            self.flow_data.amount = amount;
+                        }
+                        if expression::is_expression(&self.amount_input, number_format) {
+                            match expression::evaluate_amount(&self.amount_input, number_format) {
+                                Some(amount) if amount_response.lost_focus() => self.amount_input = amount.to_string(),
+                                Some(amount) => { ui.weak(format!("= {}", number_format.format_currency(amount))); }
+                                None => { ui.weak("?"); }
                             }
                         }
                         if !self.has_set_focus {
@@ -110,6 +381,9 @@ impl FlowEditor {
                         }
                     });
 
+                    self.show_location_input(ui, app, category);
+                    self.show_trip_input(ui, app);
+
                     // Show tax_deductible checkbox for relevant categories
                     if category.tax_deduction.deduction_allowed {
                         ui.horizontal(|ui| {
@@ -125,12 +399,30 @@ impl FlowEditor {
                         });
                     }
 
+                    self.show_refund_picker(ui, app, category);
+                    self.show_reimbursement_input(ui, app, category);
+
                     ui.separator();
 
-                    // Category-specific fields
+                    // Category-specific fields. Required fields left blank
+                    // and values that don't fit their field's type are
+                    // outlined and say so, and the flow can't be saved until
+                    // they're fixed.
+                    let field_errors = self.field_errors(app, category);
                     for field in &category.fields {
+                        let error = field_errors.get(&field.name);
                         ui.horizontal(|ui| {
-                            ui.label(format!("{}:", field.display_name()));
+                            if error.is_some() {
+                                let stroke = egui::Stroke::new(1.0, ui.visuals().error_fg_color);
+                                ui.visuals_mut().widgets.inactive.bg_stroke = stroke;
+                                ui.visuals_mut().widgets.hovered.bg_stroke = stroke;
+                            }
+                            if field.required {
+                                ui.label(format!("{}:", field.display_name())).on_hover_text("Required");
+                                ui.colored_label(ui.visuals().error_fg_color, "*");
+                            } else {
+                                ui.label(format!("{}:", field.display_name()));
+                            }
                             match field.field_type {
                                 crate::models::FieldType::Text => {
                                     let value = app.custom_field_values
@@ -141,17 +433,27 @@ impl FlowEditor {
                                     }
                                 },
                                 crate::models::FieldType::Currency => {
-                                    let value = app.custom_field_values
+                                    // Typed in the user's number format, stored US-style.
+                                    let number_format = app.user_settings.number_format_for(&category.id);
+                                    let stored = app.custom_field_values
                                         .entry(field.name.clone())
                                         .or_insert_with(String::new);
-                                    // Remove any currency symbols and commas before parsing
-                                    let clean_value = value.replace(['$', ','], "");
-                                    if ui.text_edit_singleline(&mut value.clone()).changed() {
-                                        // Format the value as currency when displaying
-                                        if let Ok(num) = clean_value.parse::<f64>() {
-                                            *value = format!("${:.2}", num);
-                                        }
-                                        self.flow_data.custom_fields.insert(field.name.clone(), value.clone());
+                                    let typed = self.currency_inputs.entry(field.name.clone()).or_insert_with(|| {
+                                        utils::parse_stored_currency(stored)
+                                            .map_or_else(|| stored.clone(), |amount| number_format.format_currency(amount))
+                                    });
+                                    if ui.text_edit_singleline(typed).changed() {
+                                        // Text that isn't an amount is kept as typed, so
+                                        // the field says so and the flow can't be saved.
+                                        *stored = if typed.trim().is_empty() {
+                                            String::new()
+                                        } else {
+                                            utils::stored_currency(typed, number_format).unwrap_or_else(|| typed.clone())
+                                        };
+                                        self.flow_data.custom_fields.insert(field.name.clone(), stored.clone());
+                                    }
+                                    if let Some(amount) = utils::parse_stored_currency(stored) {
+                                        ui.weak(number_format.format_currency(amount));
                                     }
                                 },
                                 crate::models::FieldType::Boolean => {
@@ -197,54 +499,51 @@ impl FlowEditor {
                                         self.flow_data.custom_fields.insert(field.name.clone(), value.clone());
                                     }
                                 },
-                                crate::models::FieldType::Integer => {
-                                    let value = app.custom_field_values
-                                        .entry(field.name.clone())
-                                        .or_insert_with(String::new);
-                                    if ui.text_edit_singleline(value).changed() {
-                                        // Validate that the input is a valid integer
-                                        if let Ok(num) = value.parse::<i64>() {
-                                            *value = num.to_string();
-                                            self.flow_data.custom_fields.insert(field.name.clone(), value.clone());
-                                        }
-                                    }
-                                },
-                                crate::models::FieldType::Float => {
-                                    let value = app.custom_field_values
-                                        .entry(field.name.clone())
-                                        .or_insert_with(String::new);
-                                    if ui.text_edit_singleline(value).changed() {
-                                        // Validate that the input is a valid float
-                                        if let Ok(num) = value.parse::<f64>() {
-                                            *value = format!("{:.2}", num);
-                                            self.flow_data.custom_fields.insert(field.name.clone(), value.clone());
-                                        }
-                                    }
-                                },
+                                // Number is handled the same way as Float since
+                                // we're migrating to Float.
                                 #[allow(deprecated)]
-                                crate::models::FieldType::Number => {
+                                crate::models::FieldType::Integer | crate::models::FieldType::Float | crate::models::FieldType::Number => {
                                     let value = app.custom_field_values
                                         .entry(field.name.clone())
                                         .or_insert_with(String::new);
-                                    if ui.text_edit_singleline(value).changed() {
-                                        // Handle Number type the same way as Float since we're migrating to Float
-                                        if let Ok(num) = value.parse::<f64>() {
-                                            *value = format!("{:.2}", num);
-                                            self.flow_data.custom_fields.insert(field.name.clone(), value.clone());
-                                        }
+                                    let whole = field.field_type == crate::models::FieldType::Integer;
+                                    if show_number_input(ui, value, whole, field.required) {
+                                        self.flow_data.custom_fields.insert(field.name.clone(), value.clone());
                                     }
                                 },
                             }
+                            if let Some(error) = error {
+                                ui.colored_label(ui.visuals().error_fg_color, error);
+                            }
                         });
                     }
 
                     ui.separator();
 
+                    // The database would refuse the save anyway; say why up front.
+                    let locked_year = Some(self.flow_data.date.year()).filter(|year| app.is_year_locked(*year));
+                    if let Some(year) = locked_year {
+                        ui.label(egui::RichText::new(format!("{} is locked. Unlock it to save flows dated in it.", year))
+                            .color(egui::Color32::RED));
+                    }
+
                     // Save/Cancel buttons
+                    let save_requested = std::mem::take(&mut app.save_requested);
+                    let can_save = locked_year.is_none() && !app.saving_flow && self.field_errors(app, category).is_empty();
                     ui.horizontal(|ui| {
-                        if ui.button("Save").clicked() || ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        let save_button = ui.add_enabled(can_save, egui::Button::new("Save"));
+                        let save_clicked = if locked_year.is_none() && !app.saving_flow {
+                            save_button.on_disabled_hover_text("Fix the fields marked above first").clicked()
+                        } else {
+                            save_button.clicked()
+                        };
+                        if can_save && (save_clicked || save_requested || ui.input(|i| i.key_pressed(egui::Key::Enter))) {
                             app.save_flow(self.flow_data.clone());
                         }
+                        if app.saving_flow {
+                            // Waiting on the category's flows for its uniqueness rules
+                            ui.spinner();
+                        }
                         if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
                             app.cancel_flow_edit();
                         }
@@ -254,6 +553,32 @@ impl FlowEditor {
     }
 }
 
+/// A whole or decimal number custom field as a `DragValue` (drag it, or
+/// click to type), with a button to clear it if it's optional. Blank or
+/// unreadable values show a Set button instead. Returns whether `value`
+/// changed; it's stored like the old text field stored it.
+fn show_number_input(ui: &mut egui::Ui, value: &mut String, whole: bool, required: bool) -> bool {
+    let Ok(mut number) = value.trim().parse::<f64>() else {
+        if ui.button("Set").clicked() {
+            *value = if whole { "0".to_string() } else { "0.00".to_string() };
+            return true;
+        }
+        return false;
+    };
+    let drag = egui::DragValue::new(&mut number);
+    let drag = if whole { drag.speed(1.0).fixed_decimals(0) } else { drag.speed(0.1).fixed_decimals(2) };
+    let mut changed = false;
+    if ui.add(drag).changed() {
+        *value = if whole { (number.round() as i64).to_string() } else { format!("{:.2}", number) };
+        changed = true;
+    }
+    if !required && ui.small_button("✖").on_hover_text("Clear").clicked() {
+        value.clear();
+        changed = true;
+    }
+    changed
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -269,6 +594,13 @@ mod tests {
             linked_flows: Vec::new(),
             custom_fields: HashMap::new(),
             tax_deductible: None,
+            refund_of: None,
+            scheduled: false,
+            created_utc_offset: None,
+            location: None,
+            trip_id: None,
+            reimbursement: None,
+            reimbursed_by: None,
         }
     }
 
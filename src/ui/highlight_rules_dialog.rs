@@ -0,0 +1,100 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::models::FlowType;
+use crate::settings::{AmountComparison, AmountHighlightRule};
+
+/// The rules being edited. Taken from `UserSettings` when the dialog opens
+/// and only written back on "Save", so "Cancel" discards every edit.
+#[derive(Default)]
+pub struct HighlightRulesState {
+    pub draft: Option<Vec<AmountHighlightRule>>,
+}
+
+fn flow_type_label(flow_type: &Option<FlowType>) -> &'static str {
+    match flow_type {
+        None => "Any flow",
+        Some(FlowType::Income) => "Income",
+        Some(FlowType::Expense) => "Expense",
+    }
+}
+
+pub fn show_highlight_rules_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_highlight_rules_dialog;
+    let mut save = false;
+    let mut cancel = false;
+    let currency_symbol = app.user_settings.number_format.currency_symbol.clone();
+    let draft = app.highlight_rules_state.draft
+        .get_or_insert_with(|| app.user_settings.highlight_rules.clone());
+
+    egui::Window::new("Highlight Rules")
+        .open(&mut show_window)
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.label("Amounts matching a rule are shown in its color in category tables. The first matching rule wins.");
+            ui.separator();
+
+            let mut remove = None;
+            let mut move_up = None;
+            egui::Grid::new("highlight_rules").striped(true).show(ui, |ui| {
+                for (i, rule) in draft.iter_mut().enumerate() {
+                    egui::ComboBox::from_id_source(("highlight_flow_type", i))
+                        .selected_text(flow_type_label(&rule.flow_type))
+                        .show_ui(ui, |ui| {
+                            for flow_type in [None, Some(FlowType::Income), Some(FlowType::Expense)] {
+                                let label = flow_type_label(&flow_type);
+                                ui.selectable_value(&mut rule.flow_type, flow_type, label);
+                            }
+                        });
+                    egui::ComboBox::from_id_source(("highlight_comparison", i))
+                        .selected_text(rule.comparison.get_display_name())
+                        .show_ui(ui, |ui| {
+                            for comparison in [AmountComparison::Above, AmountComparison::Below] {
+                                ui.selectable_value(&mut rule.comparison, comparison, comparison.get_display_name());
+                            }
+                        });
+                    ui.add(egui::DragValue::new(&mut rule.threshold).prefix(currency_symbol.as_str()).speed(1.0).clamp_range(0.0..=f64::MAX));
+                    ui.color_edit_button_srgb(&mut rule.color);
+                    ui.checkbox(&mut rule.bold, "Bold");
+                    if ui.add_enabled(i > 0, egui::Button::new("\u{2191}")).on_hover_text("Check this rule earlier").clicked() {
+                        move_up = Some(i);
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = move_up {
+                draft.swap(i - 1, i);
+            }
+            if let Some(i) = remove {
+                draft.remove(i);
+            }
+
+            if ui.button("Add Rule").clicked() {
+                draft.push(AmountHighlightRule::default());
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    save = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if save && let Some(rules) = app.highlight_rules_state.draft.take() {
+        app.user_settings.highlight_rules = rules;
+        app.save_settings("save highlight rules");
+    }
+    if save || cancel || !show_window {
+        app.highlight_rules_state.draft = None;
+        show_window = false;
+    }
+
+    app.show_highlight_rules_dialog = show_window;
+}
@@ -0,0 +1,207 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::import::{import_csv, ImportPreset, ImportedRow};
+use crate::models::Flow;
+
+/// State for the CSV import dialog. Lives on `PreftApp` (like
+/// `report_request`) so a half-reviewed import survives the dialog being
+/// redrawn every frame; it's reset once the import is committed.
+pub struct ImportDialogState {
+    pub presets: Vec<ImportPreset>,
+    pub preset_index: usize,
+    pub file_path: Option<std::path::PathBuf>,
+    pub rows: Vec<ImportedRow>,
+    /// Per-row target category id, seeded from the preset's suggestions.
+    pub assignments: Vec<Option<String>>,
+    pub errors: Vec<String>,
+    pub status: Option<String>,
+}
+
+impl Default for ImportDialogState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImportDialogState {
+    pub fn new() -> Self {
+        Self {
+            presets: ImportPreset::builtin_presets(),
+            preset_index: 0,
+            file_path: None,
+            rows: Vec::new(),
+            assignments: Vec::new(),
+            errors: Vec::new(),
+            status: None,
+        }
+    }
+
+    fn preset(&self) -> &ImportPreset {
+        &self.presets[self.preset_index]
+    }
+
+    fn clear_rows(&mut self) {
+        self.rows.clear();
+        self.assignments.clear();
+        self.errors.clear();
+    }
+}
+
+/// (Re)parses the chosen file with the currently selected preset.
+fn load_file(app: &mut PreftApp) {
+    let state = &mut app.import_state;
+    state.clear_rows();
+    let Some(path) = state.file_path.clone() else { return };
+
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            state.status = Some(format!("Failed to read file: {}", e));
+            return;
+        }
+    };
+
+    match import_csv(&text, state.preset()) {
+        Ok(result) => {
+            state.assignments = result.rows.iter()
+                .map(|row| row.suggested_category_id(state.preset(), &app.categories))
+                .collect();
+            state.status = Some(format!(
+                "{} row(s) ready to import, {} skipped",
+                result.rows.len(),
+                result.errors.len()
+            ));
+            state.rows = result.rows;
+            state.errors = result.errors;
+        }
+        Err(e) => {
+            state.status = Some(format!("Import failed: {}", e));
+        }
+    }
+}
+
+pub fn show_import_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let number_format = app.user_settings.number_format.clone();
+    let mut show_window = app.show_import_dialog;
+    let mut reload = false;
+    let mut commit = false;
+
+    egui::Window::new("Import CSV")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_size([700.0, 450.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Format:");
+                let state = &mut app.import_state;
+                let previous = state.preset_index;
+                egui::ComboBox::from_id_source("import_preset")
+                    .selected_text(state.preset().name.clone())
+                    .show_ui(ui, |ui| {
+                        for (i, preset) in state.presets.iter().enumerate() {
+                            ui.selectable_value(&mut state.preset_index, i, &preset.name);
+                        }
+                    });
+                if state.preset_index != previous {
+                    reload = true;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Choose File...").clicked()
+                    && let Some(path) = rfd::FileDialog::new()
+                        .set_title("Select CSV File")
+                        .add_filter("CSV", &["csv"])
+                        .add_filter("All Files", &["*"])
+                        .pick_file()
+                {
+                    app.import_state.file_path = Some(path);
+                    reload = true;
+                }
+                match &app.import_state.file_path {
+                    Some(path) => ui.label(path.to_string_lossy()),
+                    None => ui.label("No file selected"),
+                };
+            });
+
+            if let Some(status) = &app.import_state.status {
+                ui.label(status);
+            }
+
+            if !app.import_state.errors.is_empty() {
+                ui.collapsing(format!("Skipped rows ({})", app.import_state.errors.len()), |ui| {
+                    for error in &app.import_state.errors {
+                        ui.label(error);
+                    }
+                });
+            }
+
+            if app.import_state.rows.is_empty() {
+                return;
+            }
+
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("import_preview_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Date");
+                        ui.label("Amount");
+                        ui.label("Description");
+                        ui.label("Source Category");
+                        ui.label("Import Into");
+                        ui.end_row();
+
+                        let state = &mut app.import_state;
+                        for (i, row) in state.rows.iter().enumerate() {
+                            ui.label(row.date.format("%Y-%m-%d").to_string());
+                            let signed = if row.flow_type == crate::models::FlowType::Expense { -row.amount } else { row.amount };
+                            ui.label(number_format.format_currency(signed));
+                            ui.label(&row.description);
+                            ui.label(row.source_category.as_deref().unwrap_or(""));
+
+                            let assignment = &mut state.assignments[i];
+                            egui::ComboBox::from_id_source(("import_category", i))
+                                .selected_text(
+                                    assignment.as_ref()
+                                        .and_then(|id| app.categories.iter().find(|c| c.id == *id))
+                                        .map(|c| c.name.clone())
+                                        .unwrap_or_else(|| "Skip".to_string())
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(assignment, None, "Skip");
+                                    for category in app.categories.iter().filter(|c| c.flow_type == row.flow_type) {
+                                        ui.selectable_value(assignment, Some(category.id.clone()), &category.name);
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.separator();
+            let count = app.import_state.assignments.iter().filter(|a| a.is_some()).count();
+            if ui.button(format!("Import {} Flow(s)", count)).clicked() {
+                commit = true;
+            }
+        });
+
+    if reload {
+        load_file(app);
+    }
+
+    if commit {
+        let state = std::mem::take(&mut app.import_state);
+        let flows: Vec<Flow> = state.rows.into_iter()
+            .zip(state.assignments)
+            .filter_map(|(row, category_id)| category_id.map(|id| row.into_flow(id)))
+            .collect();
+        let imported = app.import_flows(flows);
+        app.import_state.presets = state.presets;
+        app.import_state.preset_index = state.preset_index;
+        app.import_state.status = Some(format!("Imported {} flow(s)", imported));
+    }
+
+    app.show_import_dialog = show_window;
+}
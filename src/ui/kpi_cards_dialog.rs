@@ -0,0 +1,111 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::kpi::{KpiCard, KpiMeasure, KpiPeriod, KpiScope};
+
+/// The cards being edited. Taken from `UserSettings` when the dialog opens
+/// and only written back on "Save", so "Cancel" discards every edit.
+#[derive(Default)]
+pub struct KpiCardsState {
+    pub draft: Option<Vec<KpiCard>>,
+}
+
+pub fn show_kpi_cards_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_kpi_cards_dialog;
+    let mut save = false;
+    let mut cancel = false;
+    let categories = &app.categories;
+    let draft = app.kpi_cards_state.draft
+        .get_or_insert_with(|| app.user_settings.kpi_cards.clone());
+
+    egui::Window::new("KPI Cards")
+        .open(&mut show_window)
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.label("Each card shows one measure of the chosen flows over its own period, at the top of the dashboard.");
+            ui.separator();
+
+            let mut scopes = vec![KpiScope::AllFlows, KpiScope::Income, KpiScope::Expenses, KpiScope::Deductible];
+            scopes.extend(categories.iter().map(|c| KpiScope::Category(c.id.clone())));
+
+            let mut remove = None;
+            let mut move_up = None;
+            egui::Grid::new("kpi_cards").striped(true).show(ui, |ui| {
+                for (i, card) in draft.iter_mut().enumerate() {
+                    ui.add(egui::TextEdit::singleline(&mut card.label).desired_width(180.0).hint_text("Label"));
+                    egui::ComboBox::from_id_source(("kpi_measure", i))
+                        .selected_text(card.measure.get_display_name())
+                        .show_ui(ui, |ui| {
+                            for measure in KpiMeasure::ALL {
+                                ui.selectable_value(&mut card.measure, measure, measure.get_display_name());
+                            }
+                        });
+                    ui.label("of");
+                    egui::ComboBox::from_id_source(("kpi_scope", i))
+                        .selected_text(card.scope.get_display_name(categories))
+                        .show_ui(ui, |ui| {
+                            for scope in &scopes {
+                                let label = scope.get_display_name(categories);
+                                ui.selectable_value(&mut card.scope, scope.clone(), label);
+                            }
+                        });
+                    egui::ComboBox::from_id_source(("kpi_period", i))
+                        .selected_text(card.period.get_display_name())
+                        .show_ui(ui, |ui| {
+                            for period in KpiPeriod::ALL {
+                                ui.selectable_value(&mut card.period, period, period.get_display_name());
+                            }
+                        });
+                    if ui.add_enabled(i > 0, egui::Button::new("\u{2191}")).on_hover_text("Show this card earlier").clicked() {
+                        move_up = Some(i);
+                    }
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = move_up {
+                draft.swap(i - 1, i);
+            }
+            if let Some(i) = remove {
+                draft.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Add Card").clicked() {
+                    draft.push(KpiCard::default());
+                }
+                ui.menu_button("Add Example", |ui| {
+                    for example in KpiCard::examples() {
+                        if ui.button(&example.label).clicked() {
+                            draft.push(example);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    save = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if save && let Some(cards) = app.kpi_cards_state.draft.take() {
+        app.user_settings.kpi_cards = cards;
+        app.save_settings("save KPI cards");
+        app.dashboard.mark_for_update();
+    }
+    if save || cancel || !show_window {
+        app.kpi_cards_state.draft = None;
+        show_window = false;
+    }
+
+    app.show_kpi_cards_dialog = show_window;
+}
@@ -0,0 +1,32 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+
+/// Shown instead of everything else while the app is locked (see
+/// `PreftApp::lock`): no data, only the way back in through the password
+/// dialog. Closing with changes pending is held up here too, but the
+/// pending changes panel would show data, so it waits for the unlock.
+pub fn show_lock_screen(ctx: &egui::Context, app: &mut PreftApp) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 3.0);
+            ui.heading("🔒 Preft is locked");
+            ui.label("Enter the database password to continue.");
+            if app.quit_requested && !app.pending_changes.is_empty() {
+                ui.label(egui::RichText::new(format!(
+                    "{} unsaved change(s) are pending. Unlock to save or discard them, then Preft will close.",
+                    app.pending_changes.len()
+                ))
+                    .color(egui::Color32::from_rgb(255, 140, 0))
+                    .strong());
+            }
+            if ui.button("Unlock").clicked() {
+                app.show_enter_password_dialog();
+            }
+        });
+    });
+
+    if app.show_password_dialog {
+        crate::ui::show_password_dialog(ctx, app);
+    }
+}
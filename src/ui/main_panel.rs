@@ -2,59 +2,194 @@ use eframe::egui;
 use chrono::Datelike;
 use log::{info, warn, error};
 
-use crate::app::PreftApp;
-use crate::ui::category_flows::show_category_flows;
+use crate::app::{GuardedAction, PreftApp};
+use crate::ui::category_flows::{show_category_flows, CategoryFlowsState};
 use crate::ui::category_editor::show_category_editor;
+use crate::shortcuts::ShortcutAction;
 
 pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
     ui.horizontal(|ui| {
         ui.heading("Personal Finance Tracker");
     });
 
-    // Row for backup and encryption controls
-    ui.horizontal(|ui| {
-        if ui.button("Backup & Restore").clicked() {
-            app.show_backup_dialog = true;
-        }
+    // Row for backup and encryption controls; viewer mode offers neither
+    if app.read_only {
+        ui.label(egui::RichText::new("👁 Viewer mode: read-only, nothing can be changed")
+            .color(egui::Color32::LIGHT_BLUE)
+            .strong());
+    } else {
+        ui.horizontal(|ui| {
+            if !app.demo && ui.button("Backup & Restore").clicked() {
+                app.show_backup_dialog = true;
+            }
+
+            let editing = app.is_editing_flow() || app.undo_in_progress;
+            let undo_label = app.undo_stack.next_undo().map(|edit| edit.label.clone());
+            if ui.add_enabled(undo_label.is_some() && !editing, egui::Button::new("Undo"))
+                .on_hover_text(format!("Undo {} ({})", undo_label.unwrap_or_default().to_lowercase(), app.user_settings.get_shortcut(ShortcutAction::Undo)))
+                .on_disabled_hover_text("Nothing to undo, or a flow is being edited")
+                .clicked()
+            {
+                app.undo();
+            }
+            let redo_label = app.undo_stack.next_redo().map(|edit| edit.label.clone());
+            if ui.add_enabled(redo_label.is_some() && !editing, egui::Button::new("Redo"))
+                .on_hover_text(format!("Redo {} ({})", redo_label.unwrap_or_default().to_lowercase(), app.user_settings.get_shortcut(ShortcutAction::Redo)))
+                .on_disabled_hover_text("Nothing to redo, or a flow is being edited")
+                .clicked()
+            {
+                app.redo();
+            }
+            if app.undo_in_progress {
+                ui.spinner();
+            }
         
-        // Show encryption status and password management
-        if app.encryption_config.enabled {
-            if app.encryption_config.is_encryption_ready() {
-                ui.label(egui::RichText::new("🔒 Encrypted").color(egui::Color32::GREEN));
-                if ui.button("Change Password").clicked() {
-                    app.show_change_password_dialog();
-                }
-                if ui.button("Disable Encryption").clicked() {
-                    app.show_disable_encryption_dialog();
+            // Show encryption status and password management
+            if app.demo {
+                ui.label(egui::RichText::new("🧪 Demo mode: a repro bundle's data, kept in memory; nothing is saved")
+                    .color(egui::Color32::LIGHT_BLUE)
+                    .strong());
+            } else if app.encryption_config.enabled {
+                if app.encryption_config.is_encryption_ready() {
+                    ui.label(egui::RichText::new("🔒 Encrypted").color(egui::Color32::GREEN));
+                    if app.encryption_config.file_encrypted {
+                        ui.label("(whole file)");
+                    } else if crate::db::FILE_ENCRYPTION_SUPPORTED
+                        && ui.button("Encrypt Database File")
+                            .on_hover_text("Only the settings are encrypted now; this encrypts flows and categories too")
+                            .clicked()
+                    {
+                        app.show_encrypt_file_dialog();
+                    }
+                    if ui.button("Encrypted Fields")
+                        .on_hover_text("Choose which flow fields are stored encrypted, trading searchability for privacy")
+                        .clicked()
+                    {
+                        app.show_encrypted_columns_dialog();
+                    }
+                    if ui.button("Change Password").clicked() {
+                        app.show_change_password_dialog();
+                    }
+                    if ui.button("Disable Encryption").clicked() {
+                        app.show_disable_encryption_dialog();
+                    }
+                } else {
+                    ui.label(egui::RichText::new("🔓 Encryption Configured (No Password)").color(egui::Color32::from_rgb(255, 140, 0))); // Dark orange/amber
+                    ui.label("Database is currently unencrypted. Set a password to enable encryption.");
+                    if ui.button("Set Password").clicked() {
+                        app.show_set_password_dialog();
+                    }
                 }
             } else {
-                ui.label(egui::RichText::new("🔓 Encryption Configured (No Password)").color(egui::Color32::from_rgb(255, 140, 0))); // Dark orange/amber
-                ui.label("Database is currently unencrypted. Set a password to enable encryption.");
-                if ui.button("Set Password").clicked() {
+                ui.label(egui::RichText::new("🔓 Unencrypted").color(egui::Color32::from_rgb(255, 140, 0))); // Dark orange/amber
+                ui.label("Database is not encrypted. Enable encryption for better security.");
+                if ui.button("Enable Encryption").clicked() {
                     app.show_set_password_dialog();
                 }
             }
-        } else {
-            ui.label(egui::RichText::new("🔓 Unencrypted").color(egui::Color32::from_rgb(255, 140, 0))); // Dark orange/amber
-            ui.label("Database is not encrypted. Enable encryption for better security.");
-            if ui.button("Enable Encryption").clicked() {
-                app.show_set_password_dialog();
+        });
+    }
+
+    // The system timezone changed since the home timezone was recorded
+    // (travel, or a changed system setting), so "today" for new flows may
+    // not be the date it is at home.
+    let local_offset = crate::utils::local_utc_offset();
+    if let Some(home_offset) = app.user_settings.home_utc_offset
+        && home_offset != local_offset
+    {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!(
+                "System timezone is {}, but your home timezone is {}; today's date may differ there.",
+                crate::utils::format_utc_offset(local_offset),
+                crate::utils::format_utc_offset(home_offset)
+            )).color(egui::Color32::YELLOW));
+            if !app.read_only && ui.button("Make This My Home Timezone").clicked() {
+                app.use_local_timezone_as_home();
+            }
+        });
+    }
+
+    if !app.pending_changes.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("{} unsaved change(s)", app.pending_changes.len()))
+                .color(egui::Color32::from_rgb(255, 140, 0))
+                .strong());
+            if ui.button("Review").clicked() {
+                app.show_pending_changes_panel = true;
+            }
+            if ui.button("Save All").clicked() {
+                app.save_pending_changes();
             }
-        }
-    });
+        });
+    }
+
+    if app.deleting || app.saving_repro_bundle {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(if app.deleting { "Deleting..." } else { "Saving the repro bundle..." });
+        });
+    }
 
+    let mut dismissed = None;
+    for (i, message) in app.notifications.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(message).color(egui::Color32::LIGHT_BLUE));
+            if ui.small_button("Dismiss").clicked() {
+                dismissed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = dismissed {
+        app.notifications.remove(i);
+    }
 
     // Row for main controls
     ui.horizontal(|ui| {
         if ui.button("Show Dashboard").clicked() {
             app.selected_category = None;
         }
-        if ui.button("Add Category").clicked() {
+        if !app.read_only && ui.button("Add Category").clicked() {
             app.show_category_editor = true;
         }
         if ui.button("Generate Report").clicked() {
             app.show_report_dialog = true;
         }
+        if !app.read_only {
+            if ui.button("Import CSV").clicked() {
+                app.show_import_dialog = true;
+            }
+            if ui.button("Import Categories").on_hover_text("Add or update categories from an exported category file; no flows are changed").clicked() {
+                app.import_category_schemas();
+            }
+            if ui.button("Bulk Edit").on_hover_text("Change many flows at once, previewing the result before saving").clicked() {
+                app.show_bulk_edit_dialog = true;
+            }
+            if ui.button("Settings").clicked() {
+                app.show_settings_dialog = true;
+            }
+            if ui.button("Highlight Rules").on_hover_text("Color amounts in category tables by size").clicked() {
+                app.show_highlight_rules_dialog = true;
+            }
+            if ui.button("KPI Cards").on_hover_text("Choose the metrics shown at the top of the dashboard").clicked() {
+                app.show_kpi_cards_dialog = true;
+            }
+        }
+        if ui.button("Export Categories").on_hover_text("Save the categories with their fields and tax settings, but no flows, to import elsewhere").clicked() {
+            app.export_category_schemas();
+        }
+        if ui.button("Trips").on_hover_text("Trips and what each one cost").clicked() {
+            app.show_trips_dialog = true;
+        }
+        if ui.button("SQL Views").on_hover_text("Named read-only queries, previewed here and exported as CSV").clicked() {
+            app.show_sql_views_dialog = true;
+        }
+        if ui.button("Verify Data").on_hover_text("Recompute all cached totals and report any discrepancies").clicked() {
+            app.verify_cached_state();
+            app.show_verify_dialog = true;
+        }
+        if ui.add_enabled(!app.saving_repro_bundle, egui::Button::new("Create Repro Bundle")).on_hover_text("Save anonymized data, settings and recent logs as one file to attach to a bug report").clicked() {
+            app.save_repro_bundle();
+        }
     });
 
     // Show category editor if needed
@@ -67,32 +202,61 @@ pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
                 app.selected_category
                     .as_ref()
                     .and_then(|id| app.categories.iter().find(|c| c.id == *id))
-                    .map(|c| c.name.clone())
-                    .unwrap_or_else(|| "Select a category".to_string())
+                    .map(super::category_label)
+                    .unwrap_or_else(|| "Select a category".into())
             )
             .show_ui(ui, |ui| {
-                for category in &app.categories {
-                    if !app.is_category_hidden(&category.id) {
+                let fallback = app.totals_fallback();
+                egui::Grid::new("category_selector_grid").show(ui, |ui| {
+                    for category in app.categories.iter().filter(|c| !app.user_settings.is_category_hidden(&c.id)) {
                         ui.selectable_value(
                             &mut app.selected_category,
                             Some(category.id.clone()),
-                            &category.name,
+                            super::category_label(category),
                         );
+                        app.category_flows_state
+                            .entry(category.id.clone())
+                            .or_insert_with(CategoryFlowsState::new)
+                            .show_compact_trend(
+                                ui,
+                                fallback.as_deref().map(Vec::as_slice),
+                                category,
+                                app.user_settings.number_format_for(&category.id),
+                                &mut app.stored_totals,
+                            );
+                        ui.end_row();
                     }
-                }
+                });
             });
 
         // Hide category button (only shown when a category is selected)
-        if let Some(category_id) = &app.selected_category {
+        if let Some(category_id) = app.selected_category.clone()
+            && !app.read_only
+        {
+            if ui.small_button("\u{25B2}").on_hover_text("Move up in the category list").clicked() {
+                app.move_category(&category_id, true);
+            }
+            if ui.small_button("\u{25BC}").on_hover_text("Move down in the category list").clicked() {
+                app.move_category(&category_id, false);
+            }
             if ui.button("Edit Category").clicked() {
                 app.editing_category = Some(category_id.clone());
                 app.show_category_editor = true;
             }
             if ui.button("Hide Category").clicked() {
-                app.hide_category_confirmation = Some(category_id.clone());
+                if app.user_settings.confirmations.hide_category {
+                    app.hide_category_confirmation = Some(category_id.clone());
+                } else {
+                    app.toggle_category_visibility(category_id.clone());
+                }
             }
             if ui.button("Delete Category").clicked() {
-                app.delete_category_confirmation = Some(category_id.clone());
+                if app.user_settings.confirmations.delete_category {
+                    app.delete_category_typed_name.clear();
+                    app.delete_category_confirmation = Some(category_id);
+                } else {
+                    app.request_guarded_action(GuardedAction::DeleteCategory(category_id));
+                }
             }
         }
 
@@ -120,6 +284,8 @@ pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
 
         // Show delete confirmation dialog if needed
         if let Some(category_id) = app.delete_category_confirmation.clone() {
+            let name = app.categories.iter().find(|c| c.id == category_id).map(|c| c.name.clone()).unwrap_or_default();
+            let type_name = app.user_settings.confirmations.type_category_name_to_delete;
             egui::Window::new("Confirm Delete Category")
                 .collapsible(false)
                 .resizable(false)
@@ -127,11 +293,16 @@ pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
                     ui.label("Are you sure you want to delete this category?");
                     ui.label("This will permanently delete the category and all its flows.");
                     ui.label("This action cannot be undone!");
+                    if type_name {
+                        ui.label(format!("Type \"{}\" to confirm:", name));
+                        ui.text_edit_singleline(&mut app.delete_category_typed_name);
+                    }
+                    let confirmed = !type_name || app.delete_category_typed_name.trim() == name.trim();
                     
                     ui.horizontal(|ui| {
-                        if ui.button("Yes, Delete Category").clicked() {
-                            app.delete_category(category_id);
+                        if ui.add_enabled(confirmed, egui::Button::new("Yes, Delete Category")).clicked() {
                             app.delete_category_confirmation = None;
+                            app.request_guarded_action(GuardedAction::DeleteCategory(category_id));
                         }
                         if ui.button("Cancel").clicked() {
                             app.delete_category_confirmation = None;
@@ -141,7 +312,7 @@ pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
         }
 
         // Show hidden categories button
-        if ui.button("Show Hidden Categories").clicked() {
+        if !app.read_only && ui.button("Show Hidden Categories").clicked() {
             app.show_hidden_categories = !app.show_hidden_categories;
         }
 
@@ -166,14 +337,35 @@ pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
 
             if year_filter != app.user_settings.get_year_filter() {
                 app.user_settings.set_year_filter(year_filter);
-                if let Err(e) = app.db.save_user_settings(&app.user_settings) {
-                    log::error!("Failed to save user settings: {}", e);
+                // Viewers can still filter; the choice just isn't remembered.
+                if !app.read_only {
+                    app.save_settings("save user settings");
                 }
                 // Mark all category flows states for update
                 for state in app.category_flows_state.values_mut() {
                     state.mark_for_update();
                 }
             }
+
+            // Only past years can be locked, e.g. once taxes are filed.
+            if let Some(year) = year_filter
+                && year < current_year
+                && !app.read_only
+            {
+                if app.is_year_locked(year) {
+                    if ui.button(format!("Unlock {}", year))
+                        .on_hover_text("Allow flows in this year to be edited and deleted again")
+                        .clicked()
+                    {
+                        app.set_year_locked(year, false);
+                    }
+                } else if ui.button(format!("Lock {}", year))
+                    .on_hover_text("Prevent flows in this year from being edited or deleted")
+                    .clicked()
+                {
+                    app.set_year_locked(year, true);
+                }
+            }
         });
     });
 
@@ -208,6 +400,14 @@ pub fn show_main_panel(ui: &mut egui::Ui, app: &mut PreftApp) {
     if let Some(category) = app.get_selected_category().cloned() {
         show_category_flows(ui, app, &category);
     } else {
-        app.dashboard.show(ui, &app.flows, &app.categories);
+        if app.dashboard.wants_outstanding_reimbursements() {
+            app.load_outstanding_reimbursements();
+        }
+        let scope = app.dashboard.scope(chrono::Local::now().date_naive());
+        let flows = app.flows_in(&scope);
+        let opened = app.dashboard.show(ui, flows.as_deref().map(Vec::as_slice), &app.categories, &app.user_settings, &mut app.stored_totals, &mut app.all_time_totals);
+        if opened.is_some() {
+            app.selected_category = opened;
+        }
     }
 } 
\ No newline at end of file
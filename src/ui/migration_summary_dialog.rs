@@ -0,0 +1,101 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+
+/// Shown once after an upgrade (or a restore of an older backup) whose
+/// migrations changed the user's data, so those changes aren't only in the
+/// log. Closing it discards the summary.
+pub fn show_migration_summary_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let Some(summary) = app.migration_summary.as_ref() else {
+        return;
+    };
+
+    let mut show_window = true;
+    let mut should_close = false;
+    let mut added: Vec<usize> = Vec::new();
+
+    egui::Window::new("What Changed in Your Data")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_size([500.0, 350.0])
+        .show(ctx, |ui| {
+            ui.label("This version of Preft updated your database. Nothing was deleted; here is what changed.");
+            if let Some(backup_path) = &summary.backup_path {
+                ui.label(format!("A copy of your database from before these changes was saved to {}", backup_path.display()));
+            }
+            ui.separator();
+
+            if !summary.schema_changes.is_empty() {
+                ui.heading("New Fields");
+                for change in &summary.schema_changes {
+                    ui.label(format!("• {}", change));
+                }
+                ui.separator();
+            }
+
+            if !summary.converted_fields.is_empty() {
+                ui.heading("Converted Fields");
+                ui.label("These Number fields are now Decimal Number fields. Existing values were kept as they were.");
+                egui::Grid::new("migration_converted_fields")
+                    .striped(true)
+                    .spacing([10.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.strong("Category");
+                        ui.strong("Field");
+                        ui.end_row();
+                        for (category, field) in &summary.converted_fields {
+                            ui.label(category);
+                            ui.label(field);
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+            }
+
+            if !summary.offered_categories.is_empty() {
+                ui.heading("Default Categories You Don't Have");
+                ui.label("Add any you'd like to use; the rest can be ignored.");
+                for (i, category) in summary.offered_categories.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} ({})", category.name, category.flow_type));
+                        if ui.button("Add").clicked() {
+                            added.push(i);
+                        }
+                    });
+                }
+                if ui.button("Add All").clicked() {
+                    added = (0..summary.offered_categories.len()).collect();
+                }
+                ui.separator();
+            }
+
+            ui.horizontal(|ui| {
+                ui.add_space(ui.available_width() - 60.0);
+                if ui.button("Got It").clicked() {
+                    should_close = true;
+                }
+            });
+        });
+
+    if !added.is_empty() {
+        let offered = app.migration_summary.as_mut()
+            .map(|summary| std::mem::take(&mut summary.offered_categories))
+            .unwrap_or_default();
+        let mut remaining = Vec::new();
+        for (i, category) in offered.into_iter().enumerate() {
+            if added.contains(&i) {
+                app.add_category(category);
+            } else {
+                remaining.push(category);
+            }
+        }
+        if let Some(summary) = app.migration_summary.as_mut() {
+            summary.offered_categories = remaining;
+        }
+        app.dashboard.mark_for_update();
+    }
+
+    if should_close || !show_window {
+        app.migration_summary = None;
+    }
+}
@@ -6,10 +6,93 @@ pub mod main_panel;
 pub mod backup_dialog;
 pub mod password_dialog;
 pub mod report_dialog;
+pub mod import_dialog;
+pub mod verify_dialog;
+pub mod backup_compare_dialog;
+pub mod bulk_edit_dialog;
+pub mod highlight_rules_dialog;
+pub mod settings_dialog;
+pub mod export_bundle_dialog;
+pub mod migration_summary_dialog;
+pub mod pending_changes_panel;
+pub mod sparkline;
+pub mod kpi_cards_dialog;
+pub mod trips_dialog;
+pub mod sql_views_dialog;
+pub mod uniqueness_conflict_dialog;
+pub mod encryption_repair_dialog;
+pub mod lock_screen;
+
+use eframe::egui;
 
 pub use dashboard::Dashboard;
 pub use flow_editor::{FlowEditor, FlowEditorState};
 pub use main_panel::show_main_panel;
 pub use backup_dialog::show_backup_dialog;
 pub use password_dialog::show_password_dialog;
-pub use report_dialog::show_report_dialog; 
\ No newline at end of file
+pub use report_dialog::show_report_dialog;
+pub use import_dialog::show_import_dialog;
+pub use verify_dialog::show_verify_dialog;
+pub use backup_compare_dialog::show_backup_compare_dialog;
+pub use bulk_edit_dialog::show_bulk_edit_dialog;
+pub use highlight_rules_dialog::show_highlight_rules_dialog;
+pub use settings_dialog::show_settings_dialog;
+pub use export_bundle_dialog::show_export_bundle_dialog;
+pub use migration_summary_dialog::show_migration_summary_dialog;
+pub use pending_changes_panel::show_pending_changes_panel;
+pub use kpi_cards_dialog::show_kpi_cards_dialog;
+pub use trips_dialog::show_trips_dialog;
+pub use sql_views_dialog::show_sql_views_dialog;
+pub use uniqueness_conflict_dialog::show_uniqueness_conflict_dialog;
+pub use encryption_repair_dialog::show_encryption_repair_dialog;
+pub use lock_screen::show_lock_screen;
+
+/// A masked single-line field for a password, under an id that
+/// `forget_password_fields` can find again. egui keeps copies of what's
+/// typed that a `Zeroizing` buffer can't reach: undo snapshots in the
+/// field's state, until `forget_password_fields` drops them, and a copy
+/// of the text it takes every frame. Both are freed without being wiped.
+pub fn password_field<'t>(text: &'t mut String, id: &str) -> egui::TextEdit<'t> {
+    egui::TextEdit::singleline(text).password(true).id(egui::Id::new(("password_field", id)))
+}
+
+/// Drops the state egui keeps for the `password_field`s named `ids`,
+/// undo history included, once their passwords have been used or their
+/// dialog has closed.
+pub fn forget_password_fields(ctx: &egui::Context, ids: &[&str]) {
+    ctx.memory_mut(|memory| {
+        for id in ids {
+            memory.data.remove::<egui::text_edit::TextEditState>(egui::Id::new(("password_field", *id)));
+        }
+    });
+}
+
+/// A category's name with its icon in front, in its color if it has one.
+pub fn category_label(category: &crate::models::Category) -> egui::RichText {
+    let text = egui::RichText::new(category.label());
+    match category.color {
+        Some([r, g, b]) => text.color(egui::Color32::from_rgb(r, g, b)),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forgetting_a_password_field_drops_its_undo_history() {
+        let ctx = egui::Context::default();
+        let mut password = String::from("s3cret");
+        let id = egui::Id::new(("password_field", "password"));
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.add(password_field(&mut password, "password"));
+            });
+        });
+        assert!(egui::text_edit::TextEditState::load(&ctx, id).is_some());
+
+        forget_password_fields(&ctx, &["password"]);
+        assert!(egui::text_edit::TextEditState::load(&ctx, id).is_none());
+    }
+}
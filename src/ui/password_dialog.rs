@@ -1,6 +1,12 @@
 use eframe::egui;
 
 use crate::app::{PreftApp, PasswordDialogMode};
+use crate::encryption::secret_buffer;
+use crate::encryption_config::SensitiveColumn;
+use super::password_field;
+
+/// The ids of the dialog's `password_field`s, for `forget_password_fields`.
+pub const PASSWORD_FIELDS: [&str; 3] = ["password", "password_confirm", "current_password"];
 
 pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
     let mut show_window = app.show_password_dialog;
@@ -11,6 +17,15 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
         .collapsible(false)
         .default_size([400.0, 300.0])
         .show(ctx, |ui| {
+            // Changes run on the database thread; hold the dialog until
+            // the one in flight comes back.
+            if app.encryption_busy {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Working...");
+                });
+            }
+            ui.set_enabled(!app.encryption_busy);
             match app.password_dialog_mode {
                 PasswordDialogMode::SetPassword => {
                     ui.heading("Set Database Password");
@@ -19,13 +34,11 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                     ui.separator();
                     
                     ui.label("Password:");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_input)
-                        .password(true)
+                    ui.add(password_field(&mut app.password_input, "password")
                         .desired_width(300.0));
                     
                     ui.label("Confirm Password:");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_confirm)
-                        .password(true)
+                    ui.add(password_field(&mut app.password_confirm, "password_confirm")
                         .desired_width(300.0));
                     
                     // Show status if any
@@ -46,24 +59,22 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                                 app.encryption_status = Some("Password must be at least 8 characters".to_string());
                             } else {
                                 let password = app.password_input.clone();
-                                if let Err(e) = app.set_password(&password) {
-                                    app.encryption_status = Some(format!("Failed to set password: {}", e));
-                                } else {
-                                    app.show_password_dialog = false;
-                                }
+                                app.set_password(&password, |app, result| match result {
+                                    Ok(()) => app.close_password_dialog(),
+                                    Err(e) => app.encryption_status = Some(format!("Failed to set password: {}", e)),
+                                });
                             }
                         }
                         
                         if ui.button("Configure Encryption (No Password)").clicked() {
-                            if let Err(e) = app.re_enable_encryption() {
-                                app.encryption_status = Some(format!("Failed to configure encryption: {}", e));
-                            } else {
-                                app.show_password_dialog = false;
-                            }
+                            app.re_enable_encryption(|app, result| match result {
+                                Ok(()) => app.close_password_dialog(),
+                                Err(e) => app.encryption_status = Some(format!("Failed to configure encryption: {}", e)),
+                            });
                         }
                         
                         if ui.button("Cancel").clicked() {
-                            app.show_password_dialog = false;
+                            app.close_password_dialog();
                             app.clear_encryption_status();
                         }
                     });
@@ -75,8 +86,7 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                     ui.separator();
                     
                     ui.label("Password:");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_input)
-                        .password(true)
+                    ui.add(password_field(&mut app.password_input, "password")
                         .desired_width(300.0));
                     
                     // Show status if any
@@ -93,23 +103,18 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                                 app.encryption_status = Some("Password cannot be empty".to_string());
                             } else {
                                 let password = app.password_input.clone();
-                                match app.verify_password(&password) {
-                                    Ok(true) => {
-                                        app.show_password_dialog = false;
+                                app.verify_password(&password, |app, result| match result {
+                                    Ok(()) => {
+                                        app.close_password_dialog();
                                         app.clear_encryption_status();
                                     }
-                                    Ok(false) => {
-                                        // Status already set in verify_password
-                                    }
-                                    Err(e) => {
-                                        app.encryption_status = Some(format!("Error: {}", e));
-                                    }
-                                }
+                                    Err(e) => app.encryption_status = Some(format!("Error: {}", e)),
+                                });
                             }
                         }
                         
                         if ui.button("Cancel").clicked() {
-                            app.show_password_dialog = false;
+                            app.close_password_dialog();
                             app.clear_encryption_status();
                         }
                     });
@@ -117,17 +122,19 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                 
                 PasswordDialogMode::ChangePassword => {
                     ui.heading("Change Database Password");
-                    ui.label("Enter your new password below.");
+                    ui.label("Everything encrypted with the current password is re-encrypted with the new one.");
                     ui.separator();
                     
+                    ui.label("Current Password:");
+                    ui.add(password_field(&mut app.current_password_input, "current_password")
+                        .desired_width(300.0));
+                    
                     ui.label("New Password:");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_input)
-                        .password(true)
+                    ui.add(password_field(&mut app.password_input, "password")
                         .desired_width(300.0));
                     
                     ui.label("Confirm New Password:");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_confirm)
-                        .password(true)
+                    ui.add(password_field(&mut app.password_confirm, "password_confirm")
                         .desired_width(300.0));
                     
                     // Show status if any
@@ -140,24 +147,26 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                     
                     ui.horizontal(|ui| {
                         if ui.button("Change Password").clicked() {
-                            if app.password_input.is_empty() {
+                            if app.current_password_input.is_empty() {
+                                app.encryption_status = Some("Enter the current password".to_string());
+                            } else if app.password_input.is_empty() {
                                 app.encryption_status = Some("Password cannot be empty".to_string());
                             } else if app.password_input != app.password_confirm {
                                 app.encryption_status = Some("Passwords do not match".to_string());
                             } else if app.password_input.len() < 8 {
                                 app.encryption_status = Some("Password must be at least 8 characters".to_string());
                             } else {
+                                let current = app.current_password_input.clone();
                                 let password = app.password_input.clone();
-                                if let Err(e) = app.change_password(&password) {
-                                    app.encryption_status = Some(format!("Failed to change password: {}", e));
-                                } else {
-                                    app.show_password_dialog = false;
-                                }
+                                app.change_password(&current, &password, |app, result| match result {
+                                    Ok(()) => app.close_password_dialog(),
+                                    Err(e) => app.encryption_status = Some(format!("Failed to change password: {:#}", e)),
+                                });
                             }
                         }
                         
                         if ui.button("Cancel").clicked() {
-                            app.show_password_dialog = false;
+                            app.close_password_dialog();
                             app.clear_encryption_status();
                         }
                     });
@@ -171,12 +180,11 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                     ui.separator();
                     
                     ui.label("Current Password (for verification):");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_input)
-                        .password(true)
+                    ui.add(password_field(&mut app.password_input, "password")
                         .desired_width(300.0));
                     
                     ui.label("Type 'DISABLE' to confirm:");
-                    ui.add(egui::TextEdit::singleline(&mut app.password_confirm)
+                    ui.add(egui::TextEdit::singleline(&mut *app.password_confirm)
                         .desired_width(300.0));
                     
                     // Show status if any
@@ -191,32 +199,146 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                         if ui.button("Disable Encryption").clicked() {
                             if app.password_input.is_empty() {
                                 app.encryption_status = Some("Password cannot be empty".to_string());
-                            } else if app.password_confirm != "DISABLE" {
+                            } else if app.password_confirm.as_str() != "DISABLE" {
                                 app.encryption_status = Some("Please type 'DISABLE' to confirm".to_string());
                             } else {
                                 // Verify the current password first
                                 let password = app.password_input.clone();
-                                match app.verify_password(&password) {
-                                    Ok(true) => {
-                                        // Password verified, now disable encryption
-                                        if let Err(e) = app.disable_encryption() {
-                                            app.encryption_status = Some(format!("Failed to disable encryption: {}", e));
-                                        } else {
-                                            app.show_password_dialog = false;
-                                        }
-                                    }
-                                    Ok(false) => {
-                                        app.encryption_status = Some("Incorrect password".to_string());
-                                    }
-                                    Err(e) => {
-                                        app.encryption_status = Some(format!("Error: {}", e));
-                                    }
-                                }
+                                app.verify_password(&password, |app, result| match result {
+                                    // Password verified, now disable encryption
+                                    Ok(()) => app.disable_encryption(|app, result| match result {
+                                        Ok(()) => app.close_password_dialog(),
+                                        Err(e) => app.encryption_status = Some(format!("Failed to disable encryption: {}", e)),
+                                    }),
+                                    Err(e) => app.encryption_status = Some(format!("Error: {}", e)),
+                                });
                             }
                         }
                         
                         if ui.button("Cancel").clicked() {
-                            app.show_password_dialog = false;
+                            app.close_password_dialog();
+                            app.clear_encryption_status();
+                        }
+                    });
+                }
+
+                PasswordDialogMode::EncryptFile => {
+                    ui.heading("Encrypt Database File");
+                    ui.label("Your settings are encrypted, but your flows and categories are stored in plain text.");
+                    ui.label("This encrypts the whole database file with your password. Backups made afterwards need it too.");
+                    ui.separator();
+
+                    ui.label("Password:");
+                    ui.add(password_field(&mut app.password_input, "password")
+                        .desired_width(300.0));
+
+                    // Show status if any
+                    if let Some(status) = &app.encryption_status {
+                        ui.label(egui::RichText::new(status)
+                            .color(egui::Color32::from_rgb(255, 140, 0)));
+                    }
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Encrypt File").clicked() {
+                            let password = std::mem::replace(&mut app.password_input, secret_buffer());
+                            app.encrypt_database_file(&password, |app, result| match result {
+                                Ok(()) => {
+                                    app.close_password_dialog();
+                                    app.clear_encryption_status();
+                                }
+                                Err(e) => app.encryption_status = Some(format!("Failed to encrypt the database file: {}", e)),
+                            });
+                        }
+
+                        if ui.button("Cancel").clicked() {
+                            app.close_password_dialog();
+                            app.clear_encryption_status();
+                        }
+                    });
+                }
+
+                PasswordDialogMode::EncryptedColumns => {
+                    ui.heading("Encrypted Fields");
+                    ui.label("Ticked fields are stored encrypted, so they can't be searched or read in SQL views.");
+                    ui.label("Dates, amounts and categories always stay readable, so totals keep working.");
+                    ui.separator();
+
+                    for column in SensitiveColumn::ALL {
+                        let mut encrypted = app.encrypted_columns_draft.contains(&column);
+                        if ui.checkbox(&mut encrypted, column.label()).changed() {
+                            if encrypted {
+                                app.encrypted_columns_draft.push(column);
+                            } else {
+                                app.encrypted_columns_draft.retain(|c| *c != column);
+                            }
+                        }
+                    }
+
+                    ui.label("Password:");
+                    ui.add(password_field(&mut app.password_input, "password")
+                        .desired_width(300.0));
+
+                    // Show status if any
+                    if let Some(status) = &app.encryption_status {
+                        ui.label(egui::RichText::new(status)
+                            .color(egui::Color32::from_rgb(255, 140, 0)));
+                    }
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply").clicked() {
+                            let password = std::mem::replace(&mut app.password_input, secret_buffer());
+                            let columns = app.encrypted_columns_draft.clone();
+                            app.set_encrypted_columns(&password, columns, |app, result| match result {
+                                Ok(()) => {
+                                    app.close_password_dialog();
+                                    app.clear_encryption_status();
+                                }
+                                Err(e) => app.encryption_status = Some(format!("Failed to change encrypted fields: {}", e)),
+                            });
+                        }
+
+                        if ui.button("Cancel").clicked() {
+                            app.close_password_dialog();
+                            app.clear_encryption_status();
+                        }
+                    });
+                }
+
+                PasswordDialogMode::ConfirmAction => {
+                    ui.heading("Confirm with Password");
+                    let action = app.pending_guarded_action.as_ref()
+                        .map(|action| action.describe(&app.categories))
+                        .unwrap_or_default();
+                    ui.label(format!("Strict mode is on. Enter the database password to {}.", action));
+                    ui.separator();
+
+                    ui.label("Password:");
+                    ui.add(password_field(&mut app.password_input, "password")
+                        .desired_width(300.0));
+
+                    // Show status if any
+                    if let Some(status) = &app.encryption_status {
+                        ui.label(egui::RichText::new(status)
+                            .color(egui::Color32::from_rgb(255, 140, 0)));
+                    }
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Confirm").clicked() {
+                            let password = std::mem::replace(&mut app.password_input, secret_buffer());
+                            if app.confirm_guarded_action(&password) {
+                                app.close_password_dialog();
+                                app.clear_encryption_status();
+                            }
+                        }
+
+                        if ui.button("Cancel").clicked() {
+                            app.close_password_dialog();
                             app.clear_encryption_status();
                         }
                     });
@@ -224,5 +346,9 @@ pub fn show_password_dialog(ctx: &egui::Context, app: &mut PreftApp) {
             }
         });
     
-    app.show_password_dialog = show_window;
+    // Buttons close the dialog with `close_password_dialog`; the title
+    // bar's close button by clearing `show_window`.
+    if !show_window {
+        app.close_password_dialog();
+    }
 }
\ No newline at end of file
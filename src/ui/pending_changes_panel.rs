@@ -0,0 +1,90 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::pending_changes::PendingChange;
+
+/// Lists the flow edits queued while deferred writes are on, with Save All
+/// and Discard. Also shown when the app is closed with changes pending, in
+/// which case it finishes closing once they are saved or discarded.
+pub fn show_pending_changes_panel(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_pending_changes_panel;
+    let mut save = false;
+    let mut discard = false;
+
+    egui::Window::new("Pending Changes")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_size([550.0, 350.0])
+        .show(ctx, |ui| {
+            if app.quit_requested {
+                ui.label(egui::RichText::new("Save or discard these changes to finish closing Preft.")
+                    .color(egui::Color32::from_rgb(255, 140, 0))
+                    .strong());
+                ui.separator();
+            }
+
+            if app.pending_changes.is_empty() {
+                ui.label("No unsaved changes.");
+                return;
+            }
+
+            egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                egui::Grid::new("pending_changes_grid")
+                    .striped(true)
+                    .spacing([10.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.strong("Change");
+                        ui.strong("Category");
+                        ui.strong("Date");
+                        ui.strong("Description");
+                        ui.strong("Amount");
+                        ui.end_row();
+
+                        for change in app.pending_changes.changes() {
+                            let flow = change.flow();
+                            let action = egui::RichText::new(change.get_display_name());
+                            ui.label(match change {
+                                PendingChange::SaveFlow(_) => action,
+                                PendingChange::DeleteFlow(_) => action.color(egui::Color32::RED),
+                            });
+                            ui.label(app.categories.iter()
+                                .find(|c| c.id == flow.category_id)
+                                .map_or(flow.category_id.as_str(), |c| c.name.as_str()));
+                            ui.label(flow.date.to_string());
+                            ui.label(&flow.description);
+                            ui.label(app.user_settings.number_format_for(&flow.category_id).format_currency(flow.amount));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!app.saving_pending_changes, egui::Button::new("Save All")).clicked() {
+                    save = true;
+                }
+                if app.saving_pending_changes {
+                    ui.spinner();
+                }
+                if ui.add_enabled(!app.saving_pending_changes, egui::Button::new("Discard All")).on_hover_text("Undo every change listed here").clicked() {
+                    discard = true;
+                }
+            });
+        });
+
+    if save {
+        app.save_pending_changes();
+    }
+    if discard {
+        app.discard_pending_changes();
+    }
+
+    if app.quit_requested && app.pending_changes.is_empty() {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+    if !show_window {
+        // Closing the panel means the user wants to keep working.
+        app.quit_requested = false;
+    }
+    app.show_pending_changes_panel = show_window;
+}
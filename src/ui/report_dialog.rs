@@ -4,15 +4,40 @@ use std::fs::File;
 use std::io::Write;
 
 use crate::app::PreftApp;
-use crate::models::Flow;
-use crate::reporting::{FontVariant, ReportCategoryInfo, ReportGenerator, TimePeriod};
+use crate::db::FlowScope;
+use crate::db_worker::Pending;
+use crate::models::{Flow, Trip};
+use crate::reporting::{FontVariant, GroupAggregate, PageOrientation, PaperSize, ReportCategoryInfo, ReportFlowTypes, ReportFormat, ReportGenerator, ReportKind, ReportPreview, ReportRequest, RoundingPrecision, RoundingRule, RoundingStage, TimePeriod};
 use std::collections::HashMap;
 
+/// How many flows the flow picker lists at a time.
+const PICKER_PAGE_SIZE: usize = 200;
+
+/// What a preview was made from: the request, the flow cache's generation
+/// (see `FlowCache::generation`) and the day.
+type PreviewKey = (ReportRequest, u64, chrono::NaiveDate);
+
+/// The report dialog's work on the database thread, which loads only the
+/// flows a report covers (see `Database::load_report_flows`), and the
+/// flow picker's page.
+#[derive(Default)]
+pub struct ReportDialogState {
+    /// Set while "Generate Report" waits on the database thread; cleared
+    /// when the dialog closes, so a report that comes back late is dropped.
+    generating: bool,
+    /// The last preview made, kept up while a newer one is made.
+    preview: Option<(PreviewKey, ReportPreview)>,
+    /// Set while a preview is made.
+    previewing: bool,
+    /// Where the flow picker's page starts, newest flow first.
+    picker_offset: usize,
+}
+
 /// The "Custom" range is seeded with Jan 1 -> today the first time it's
 /// selected; the date pickers shown below the combo box let it be narrowed
 /// from there. This request (including any custom range) lives only on
 /// `PreftApp` in memory -- it's never written to `UserSettings`/the database,
-/// so it does not persist across app restarts.
+/// so it does not persist across app restarts unless saved as a template.
 ///
 /// Every widget below reads/writes `app.report_request` directly rather than
 /// a local clone. This function runs every frame the dialog is open (not
@@ -33,21 +58,28 @@ pub fn show_report_dialog(ctx: &egui::Context, app: &mut PreftApp) {
         .collect();
     field_names.sort();
     field_names.dedup();
+    let field_choices: Vec<(String, String)> = field_names.iter()
+        .map(|name| {
+            let label = app.categories.iter()
+                .flat_map(|c| c.fields.iter())
+                .find(|f| &f.name == name)
+                .map_or_else(|| name.clone(), |f| f.display_name());
+            (name.clone(), label)
+        })
+        .collect();
 
-    let flows: Vec<Flow> = app.flows.clone();
     let categories: HashMap<String, ReportCategoryInfo> = app.categories.iter()
-        .map(|cat| (cat.id.clone(), ReportCategoryInfo {
-            name: cat.name.clone(),
-            flow_type: cat.flow_type.clone(),
-            fields: cat.fields.clone(),
-        }))
+        .map(|cat| (cat.id.clone(), ReportCategoryInfo::from(cat)
+            .with_number_format(app.user_settings.category_number_formats.get(&cat.id).cloned())))
         .collect();
     // Same order as the category selection dropdown, so the report's
     // category order is deterministic instead of following `categories`'
     // arbitrary `HashMap` iteration order.
     let category_order: Vec<String> = app.categories.iter().map(|cat| cat.id.clone()).collect();
-    let mut should_close = false;
-    let mut pdf_data = None;
+    let category_choices: Vec<(String, String)> = app.categories.iter()
+        .map(|cat| (cat.id.clone(), cat.name.clone()))
+        .collect();
+    let mut generate = false;
     let mut show_window = true;
 
     egui::Window::new("Generate Report")
@@ -55,10 +87,32 @@ pub fn show_report_dialog(ctx: &egui::Context, app: &mut PreftApp) {
         .show(ctx, |ui| {
             ui.heading("Report Settings");
 
+            show_template_selection(ui, app);
+            ui.separator();
+
+            show_kind_selection(ui, &mut app.report_request.kind, &mut app.report_request.title);
+
             show_time_period_selection(ui, &mut app.report_request.time_period);
 
+            show_flow_types_selection(ui, &mut app.report_request.flow_types);
+
+            if !app.trips.is_empty() {
+                show_trip_selection(ui, &mut app.report_request.trip_id, &app.trips);
+            }
+
+            show_multi_selection(ui, "report_categories", "Categories", &mut app.report_request.selected_categories, &category_choices);
+
+            show_flow_picker(ui, app);
+
             // Group by selection
             show_group_by_selection(ui, &mut app.report_request.group_by, &field_names);
+            if app.report_request.group_by.is_some() {
+                show_group_aggregates_selection(ui, &mut app.report_request.group_aggregates);
+            }
+
+            if !field_choices.is_empty() {
+                show_multi_selection(ui, "report_fields", "Field Columns", &mut app.report_request.selected_fields, &field_choices);
+            }
 
             // Title and subtitle
             ui.horizontal(|ui| {
@@ -70,40 +124,249 @@ pub fn show_report_dialog(ctx: &egui::Context, app: &mut PreftApp) {
                 ui.text_edit_singleline(&mut app.report_request.subtitle);
             });
 
-            // Font settings
-            ui.separator();
-            ui.heading("Font Settings");
+            show_format_selection(ui, &mut app.report_request.output_format);
+            show_rounding_selection(ui, &mut app.report_request.rounding);
 
-            show_font_selection(ui, "title_font", "Title Font:", &mut app.report_request.font_settings.title_font);
-            show_font_selection(ui, "subtitle_font", "Subtitle Font:", &mut app.report_request.font_settings.subtitle_font);
-            show_font_selection(ui, "header_font", "Header Font:", &mut app.report_request.font_settings.header_font);
-            show_font_selection(ui, "body_font", "Body Font:", &mut app.report_request.font_settings.body_font);
+            // Page and font settings (only PDF output has pages or fonts)
+            if app.report_request.output_format == ReportFormat::Pdf {
+                show_page_setup(ui, &mut app.report_request.paper_size, &mut app.report_request.orientation);
+
+                ui.separator();
+                ui.heading("Font Settings");
+
+                show_font_selection(ui, "title_font", "Title Font:", &mut app.report_request.font_settings.title_font);
+                show_font_selection(ui, "subtitle_font", "Subtitle Font:", &mut app.report_request.font_settings.subtitle_font);
+                show_font_selection(ui, "header_font", "Header Font:", &mut app.report_request.font_settings.header_font);
+                show_font_selection(ui, "body_font", "Body Font:", &mut app.report_request.font_settings.body_font);
+
+                ui.separator();
+                ui.checkbox(&mut app.report_request.include_monthly_breakdown, "Include monthly breakdown by category");
+                ui.checkbox(&mut app.report_request.include_year_grid, "Include year grid (monthly total, count, and average) per category");
+            }
 
             // Generate button
-            if ui.button("Generate Report").clicked() {
-                let generator = ReportGenerator::new(flows.clone(), categories.clone(), category_order.clone());
-                if let Ok(data) = generator.generate_report(&app.report_request) {
-                    pdf_data = Some(data);
-                    should_close = true;
+            ui.horizontal(|ui| {
+                let generating = app.report_state.generating;
+                if ui.add_enabled(!generating, egui::Button::new("Generate Report")).clicked() {
+                    generate = true;
                 }
-            }
+                if generating {
+                    ui.spinner();
+                }
+                let preview_label = if app.show_report_preview { "Hide Preview" } else { "Preview" };
+                if ui.button(preview_label).on_hover_text("Show the report's tables as they stand, updating as settings change").clicked() {
+                    app.show_report_preview = !app.show_report_preview;
+                }
+            });
         });
 
-    if should_close || !show_window {
-        if let Some(data) = pdf_data {
-            // Save the PDF file
-            if let Some(path) = rfd::FileDialog::new()
-                .set_title("Save Report")
-                .set_file_name("financial_report.pdf")
-                .save_file() {
-                if let Ok(mut file) = File::create(path) {
-                    if let Err(e) = file.write_all(&data) {
-                        log::error!("Failed to save PDF: {}", e);
-                    }
+    if generate {
+        start_generating(app, categories.clone(), category_order.clone());
+    }
+
+    if app.show_report_preview && show_window {
+        let key = (app.report_request.clone(), app.flow_cache.generation(), chrono::Local::now().date_naive());
+        let state = &app.report_state;
+        if !state.previewing && state.preview.as_ref().is_none_or(|(made_for, _)| *made_for != key) {
+            start_preview(app, key, categories, category_order);
+        }
+        let preview = app.report_state.preview.as_ref().map(|(_, preview)| preview);
+        show_report_preview(ctx, preview, &mut app.show_report_preview);
+    }
+
+    if !show_window {
+        app.show_report_dialog = false;
+        app.show_report_preview = false;
+        app.report_state = ReportDialogState::default();
+    }
+}
+
+/// Loads the flows `app.report_request` covers and hands `make` a generator
+/// over them, all on the database thread.
+fn query_report<T: Send + 'static>(
+    app: &PreftApp,
+    categories: HashMap<String, ReportCategoryInfo>,
+    category_order: Vec<String>,
+    make: impl FnOnce(ReportGenerator, &ReportRequest) -> anyhow::Result<T> + Send + 'static,
+) -> Pending<anyhow::Result<T>> {
+    let request = app.report_request.clone();
+    let number_format = app.user_settings.number_format.clone();
+    let pending_changes = app.pending_changes.clone();
+    app.db.query(move |db| {
+        let flows = db.load_report_flows(&request, chrono::Local::now().date_naive(), &pending_changes)?;
+        make(ReportGenerator::new(flows, categories, category_order).with_number_format(number_format), &request)
+    })
+}
+
+/// Writes the report on the database thread, then asks where to save it
+/// and closes the dialog.
+fn start_generating(app: &mut PreftApp, categories: HashMap<String, ReportCategoryInfo>, category_order: Vec<String>) {
+    let format = app.report_request.output_format;
+    app.report_state.generating = true;
+    let generating = query_report(app, categories, category_order, |generator, request| {
+        generator.generate_report(request).map_err(|e| anyhow::anyhow!("{}", e))
+    });
+    app.when_done(generating, move |app, result| {
+        // The dialog was closed since
+        if !std::mem::take(&mut app.report_state.generating) || app.locked {
+            return;
+        }
+        let data = match result.and_then(|data| data) {
+            Ok(data) => data,
+            Err(e) => {
+                log::error!("Failed to generate the report: {}", e);
+                return;
+            }
+        };
+        if let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Report")
+            .set_file_name(format!("financial_report.{}", format.extension()))
+            .add_filter(format.get_display_name(), &[format.extension()])
+            .save_file() {
+            if let Ok(mut file) = File::create(path) {
+                if let Err(e) = file.write_all(&data) {
+                    log::error!("Failed to save {}: {}", format.get_display_name(), e);
                 }
             }
         }
         app.show_report_dialog = false;
+        app.show_report_preview = false;
+        app.report_state = ReportDialogState::default();
+    });
+}
+
+fn start_preview(app: &mut PreftApp, key: PreviewKey, categories: HashMap<String, ReportCategoryInfo>, category_order: Vec<String>) {
+    app.report_state.previewing = true;
+    let previewing = query_report(app, categories, category_order, |generator, request| Ok(generator.preview(request)));
+    app.when_done(previewing, move |app, result| {
+        // The dialog was closed since
+        if !std::mem::take(&mut app.report_state.previewing) || app.locked {
+            return;
+        }
+        match result.and_then(|preview| preview) {
+            Ok(preview) => app.report_state.preview = Some((key, preview)),
+            Err(e) => log::error!("Failed to preview the report: {}", e),
+        }
+    });
+}
+
+/// Remade on the database thread whenever the settings or the flows
+/// change, so it soon matches what "Generate Report" would produce; the
+/// last one stays up meanwhile.
+fn show_report_preview(ctx: &egui::Context, preview: Option<&ReportPreview>, open: &mut bool) {
+    egui::Window::new("Report Preview")
+        .open(open)
+        .resizable(true)
+        .default_size([700.0, 500.0])
+        .show(ctx, |ui| {
+            let Some(preview) = preview else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Loading flows...");
+                });
+                return;
+            };
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.heading(&preview.title);
+                if !preview.subtitle.is_empty() {
+                    ui.label(egui::RichText::new(&preview.subtitle).size(16.0));
+                }
+                ui.label(&preview.time_period_text);
+
+                if preview.categories.is_empty() {
+                    ui.separator();
+                    ui.label("No flows match these settings.");
+                }
+
+                for (i, category) in preview.categories.iter().enumerate() {
+                    ui.separator();
+                    let heading = egui::RichText::new(format!("Category: {}", category.name)).strong();
+                    match category.color {
+                        Some([r, g, b]) => ui.label(heading.color(egui::Color32::from_rgb(r, g, b))),
+                        None => ui.label(heading),
+                    };
+                    for (j, group) in category.groups.iter().enumerate() {
+                        if let Some(heading) = &group.heading {
+                            ui.label(egui::RichText::new(heading).strong());
+                        }
+                        egui::Grid::new(("report_preview_table", i, j)).striped(true).show(ui, |ui| {
+                            for column in &category.columns {
+                                ui.strong(column);
+                            }
+                            ui.end_row();
+                            for row in &group.rows {
+                                for cell in row {
+                                    ui.label(cell);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(total) = &group.total {
+                            ui.label(format!("Group Total: {}", total));
+                        }
+                        for (label, value) in &group.aggregates {
+                            ui.label(format!("{}: {}", label, value));
+                        }
+                    }
+                    ui.strong(format!("Category Total: {}", category.total));
+                }
+
+                ui.separator();
+                ui.heading("Summary");
+                egui::Grid::new("report_preview_summary").show(ui, |ui| {
+                    for (label, amount) in &preview.summary {
+                        ui.label(label);
+                        ui.label(amount);
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+}
+
+/// Loading a template replaces every setting below it; saving stores the
+/// settings as they are now under the typed name (replacing a template of
+/// the same name).
+fn show_template_selection(ui: &mut egui::Ui, app: &mut PreftApp) {
+    let mut load = None;
+    let mut delete = None;
+    ui.horizontal(|ui| {
+        ui.label("Template:");
+        egui::ComboBox::from_id_source("report_template")
+            .selected_text(if app.report_templates.is_empty() { "No saved templates" } else { "Load..." })
+            .show_ui(ui, |ui| {
+                for (name, _) in &app.report_templates {
+                    if ui.selectable_label(false, name).clicked() {
+                        load = Some(name.clone());
+                    }
+                }
+            });
+        let name = app.report_template_name.trim().to_string();
+        let exists = app.report_templates.iter().any(|(existing, _)| *existing == name);
+        if !app.read_only && ui.add_enabled(exists, egui::Button::new("Delete")).clicked() {
+            delete = Some(name);
+        }
+    });
+    // The viewer can load templates but not change them
+    if !app.read_only {
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut app.report_template_name);
+            let name = app.report_template_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save as Template")).clicked() {
+                app.save_report_template(&name);
+            }
+        });
+    }
+
+    if let Some(name) = load
+        && let Some((_, request)) = app.report_templates.iter().find(|(existing, _)| *existing == name)
+    {
+        app.report_request = request.clone();
+        app.report_template_name = name;
+    }
+    if let Some(name) = delete {
+        app.delete_report_template(&name);
     }
 }
 
@@ -152,6 +415,162 @@ fn time_period_label(time_period: &TimePeriod) -> String {
     }
 }
 
+/// Checklist of (id, label) `choices` -- the categories or custom-field
+/// columns to include. `selected` stays `None` while every choice is
+/// checked (see `ReportRequest::selected_categories`), and is only filled
+/// in once something is actually unchecked.
+fn show_multi_selection(ui: &mut egui::Ui, id_source: &str, heading: &str, selected: &mut Option<Vec<String>>, choices: &[(String, String)]) {
+    let summary = match selected {
+        None => format!("{}: All", heading),
+        Some(selected) => {
+            let count = choices.iter().filter(|(id, _)| selected.contains(id)).count();
+            format!("{}: {} of {}", heading, count, choices.len())
+        }
+    };
+
+    egui::CollapsingHeader::new(summary)
+        .id_source(id_source)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("All").clicked() {
+                    *selected = None;
+                }
+                if ui.button("None").clicked() {
+                    *selected = Some(Vec::new());
+                }
+            });
+
+            for (id, name) in choices {
+                let mut checked = selected.as_ref().is_none_or(|selected| selected.contains(id));
+                if ui.checkbox(&mut checked, name).changed() {
+                    set_selected(selected, choices, id, checked);
+                }
+            }
+        });
+}
+
+/// Checks or unchecks `id` in a `show_multi_selection` list, going back
+/// to `None` once every choice is checked again.
+fn set_selected(selected: &mut Option<Vec<String>>, choices: &[(String, String)], id: &str, checked: bool) {
+    let list = selected.get_or_insert_with(|| choices.iter().map(|(id, _)| id.clone()).collect());
+    if checked {
+        list.push(id.to_string());
+    } else {
+        list.retain(|selected_id| selected_id != id);
+    }
+    if choices.iter().all(|(id, _)| list.contains(id)) {
+        *selected = None;
+    }
+}
+
+/// Picks individual flows for the report (`ReportRequest::selected_flows`)
+/// from a page of flows, newest first, narrowed by a search and,
+/// optionally, the filters above. Only loads once opened.
+fn show_flow_picker(ui: &mut egui::Ui, app: &mut PreftApp) {
+    let picked = app.report_request.selected_flows.len();
+    let summary = if picked == 0 {
+        "Flows: All matching".to_string()
+    } else {
+        format!("Flows: {} picked", picked)
+    };
+
+    egui::CollapsingHeader::new(summary)
+        .id_source("report_flows")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Search:");
+                ui.add(egui::TextEdit::singleline(&mut app.report_flow_search)
+                    .hint_text("Description or amount")
+                    .desired_width(180.0));
+                if ui.checkbox(&mut app.report_flow_search_filtered, "Only flows matching the filters above").changed() {
+                    app.report_state.picker_offset = 0;
+                }
+            });
+
+            let today = chrono::Local::now().date_naive();
+            let mut scope = FlowScope { limit: Some(PICKER_PAGE_SIZE), offset: app.report_state.picker_offset, ..FlowScope::default() };
+            if app.report_flow_search_filtered {
+                let (start, end) = app.report_request.time_period.bounds(today);
+                scope.years = Some(start.year()..=end.year());
+            }
+            let Some(flows) = app.flows_in(&scope) else {
+                ui.spinner();
+                return;
+            };
+            let search = app.report_flow_search.trim().to_lowercase();
+            let categories = &app.categories;
+            let request = &app.report_request;
+            let mut shown: Vec<&Flow> = flows.iter()
+                .filter(|f| !f.scheduled)
+                .filter(|f| !app.report_flow_search_filtered || request.matches_filters(
+                    f,
+                    categories.iter().find(|c| c.id == f.category_id).map(|c| &c.flow_type),
+                    today,
+                ))
+                .filter(|f| search.is_empty()
+                    || f.description.to_lowercase().contains(&search)
+                    || format!("{:.2}", f.amount).contains(&search))
+                .collect();
+            shown.sort_by_key(|f| std::cmp::Reverse(f.date));
+
+            let selected = &mut app.report_request.selected_flows;
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!shown.is_empty(), egui::Button::new(format!("Pick All {} Shown", shown.len()))).clicked() {
+                    for flow in &shown {
+                        if !selected.contains(&flow.id) {
+                            selected.push(flow.id.clone());
+                        }
+                    }
+                }
+                if ui.add_enabled(!selected.is_empty(), egui::Button::new("Clear")).clicked() {
+                    selected.clear();
+                }
+            });
+            if !selected.is_empty() {
+                ui.label(egui::RichText::new("Only the picked flows are reported; the period, category, trip and type filters are ignored.").weak());
+            }
+
+            egui::ScrollArea::vertical()
+                .id_source("report_flow_list")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for flow in shown {
+                        let category = categories.iter().find(|c| c.id == flow.category_id).map_or("", |c| c.name.as_str());
+                        let mut checked = selected.contains(&flow.id);
+                        let label = format!(
+                            "{}  {}  {}  {}",
+                            flow.date,
+                            category,
+                            flow.description,
+                            app.user_settings.number_format.format_currency(flow.amount)
+                        );
+                        if ui.checkbox(&mut checked, label).changed() {
+                            if checked {
+                                selected.push(flow.id.clone());
+                            } else {
+                                selected.retain(|id| id != &flow.id);
+                            }
+                        }
+                    }
+                });
+
+            let offset = &mut app.report_state.picker_offset;
+            ui.horizontal(|ui| {
+                if ui.add_enabled(*offset > 0, egui::Button::new("Newer")).clicked() {
+                    *offset = offset.saturating_sub(PICKER_PAGE_SIZE);
+                }
+                if flows.is_empty() {
+                    ui.label("No more flows");
+                } else {
+                    ui.label(format!("Flows {} to {}", *offset + 1, *offset + flows.len()));
+                }
+                if ui.add_enabled(flows.len() == PICKER_PAGE_SIZE, egui::Button::new("Older")).clicked() {
+                    *offset += PICKER_PAGE_SIZE;
+                }
+            });
+        });
+}
+
 fn show_group_by_selection(ui: &mut egui::Ui, group_by: &mut Option<String>, field_names: &[String]) {
     ui.horizontal(|ui| {
         ui.label("Group By:");
@@ -166,6 +585,127 @@ fn show_group_by_selection(ui: &mut egui::Ui, group_by: &mut Option<String>, fie
     });
 }
 
+/// Statistics to list under each group's total, kept in `ALL`'s order
+/// however they're ticked.
+fn show_group_aggregates_selection(ui: &mut egui::Ui, aggregates: &mut Vec<GroupAggregate>) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Per Group:");
+        for aggregate in GroupAggregate::ALL {
+            let mut checked = aggregates.contains(&aggregate);
+            if ui.checkbox(&mut checked, aggregate.get_display_name()).changed() {
+                if checked {
+                    aggregates.push(aggregate);
+                } else {
+                    aggregates.retain(|a| *a != aggregate);
+                }
+                aggregates.sort_by_key(|a| GroupAggregate::ALL.iter().position(|b| b == a));
+            }
+        }
+    });
+}
+
+/// Switching kinds also switches the title, unless it's been customized.
+fn show_kind_selection(ui: &mut egui::Ui, kind: &mut ReportKind, title: &mut String) {
+    let previous = *kind;
+    ui.horizontal(|ui| {
+        ui.label("Report Type:");
+        egui::ComboBox::from_id_source("report_kind")
+            .selected_text(kind.get_display_name())
+            .show_ui(ui, |ui| {
+                for variant in [ReportKind::Flows, ReportKind::TaxDeductions, ReportKind::OutstandingReimbursements] {
+                    ui.selectable_value(kind, variant, variant.get_display_name());
+                }
+            });
+    });
+    if *kind != previous && title == previous.default_title() {
+        *title = kind.default_title().to_string();
+    }
+}
+
+fn show_flow_types_selection(ui: &mut egui::Ui, flow_types: &mut ReportFlowTypes) {
+    ui.horizontal(|ui| {
+        ui.label("Flow Types:");
+        egui::ComboBox::from_id_source("report_flow_types")
+            .selected_text(flow_types.get_display_name())
+            .show_ui(ui, |ui| {
+                for variant in [ReportFlowTypes::Both, ReportFlowTypes::IncomeOnly, ReportFlowTypes::ExpenseOnly] {
+                    ui.selectable_value(flow_types, variant, variant.get_display_name());
+                }
+            });
+    });
+}
+
+fn show_trip_selection(ui: &mut egui::Ui, trip_id: &mut Option<String>, trips: &[Trip]) {
+    ui.horizontal(|ui| {
+        ui.label("Trip:");
+        egui::ComboBox::from_id_source("report_trip")
+            .selected_text(
+                trip_id.as_ref()
+                    .and_then(|id| trips.iter().find(|t| t.id == *id))
+                    .map_or("Any (no trip filter)", |t| t.name.as_str())
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(trip_id, None, "Any (no trip filter)");
+                for trip in trips {
+                    ui.selectable_value(trip_id, Some(trip.id.clone()), &trip.name);
+                }
+            });
+    });
+}
+
+fn show_format_selection(ui: &mut egui::Ui, format: &mut ReportFormat) {
+    ui.horizontal(|ui| {
+        ui.label("Output Format:");
+        egui::ComboBox::from_id_source("output_format")
+            .selected_text(format.get_display_name())
+            .show_ui(ui, |ui| {
+                for variant in [ReportFormat::Pdf, ReportFormat::Csv, ReportFormat::Xlsx, ReportFormat::Markdown] {
+                    ui.selectable_value(format, variant, variant.get_display_name());
+                }
+            });
+    });
+}
+
+fn show_page_setup(ui: &mut egui::Ui, paper_size: &mut PaperSize, orientation: &mut PageOrientation) {
+    ui.horizontal(|ui| {
+        ui.label("Paper:");
+        egui::ComboBox::from_id_source("paper_size")
+            .selected_text(paper_size.get_display_name())
+            .show_ui(ui, |ui| {
+                for variant in [PaperSize::A4, PaperSize::Letter] {
+                    ui.selectable_value(paper_size, variant, variant.get_display_name());
+                }
+            });
+        for variant in [PageOrientation::Portrait, PageOrientation::Landscape] {
+            ui.radio_value(orientation, variant, variant.get_display_name());
+        }
+    });
+}
+
+fn show_rounding_selection(ui: &mut egui::Ui, rounding: &mut RoundingRule) {
+    ui.horizontal(|ui| {
+        ui.label("Rounding:");
+        egui::ComboBox::from_id_source("rounding_precision")
+            .selected_text(match rounding.precision {
+                RoundingPrecision::Cent => "To the cent",
+                RoundingPrecision::Dollar => "To the dollar",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rounding.precision, RoundingPrecision::Cent, "To the cent");
+                ui.selectable_value(&mut rounding.precision, RoundingPrecision::Dollar, "To the dollar");
+            });
+        egui::ComboBox::from_id_source("rounding_stage")
+            .selected_text(match rounding.stage {
+                RoundingStage::EachItem => "Round each item",
+                RoundingStage::Totals => "Round totals only",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rounding.stage, RoundingStage::EachItem, "Round each item");
+                ui.selectable_value(&mut rounding.stage, RoundingStage::Totals, "Round totals only");
+            });
+    });
+}
+
 fn show_font_selection(ui: &mut egui::Ui, id_source: &str, label: &str, selected: &mut FontVariant) {
     ui.horizontal(|ui| {
         ui.label(label);
@@ -187,3 +727,34 @@ fn show_font_selection(ui: &mut egui::Ui, id_source: &str, label: &str, selected
             });
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choices(ids: &[&str]) -> Vec<(String, String)> {
+        ids.iter().map(|id| (id.to_string(), id.to_uppercase())).collect()
+    }
+
+    #[test]
+    fn unchecking_the_last_checked_choice_selects_none() {
+        let choices = choices(&["rent", "food"]);
+        let mut selected = None;
+        set_selected(&mut selected, &choices, "rent", false);
+        assert_eq!(selected, Some(vec!["food".to_string()]));
+
+        set_selected(&mut selected, &choices, "food", false);
+        assert_eq!(selected, Some(Vec::new()), "not every category");
+    }
+
+    #[test]
+    fn checking_every_choice_again_selects_all() {
+        let choices = choices(&["rent", "food"]);
+        let mut selected = Some(Vec::new());
+        set_selected(&mut selected, &choices, "rent", true);
+        assert_eq!(selected, Some(vec!["rent".to_string()]));
+
+        set_selected(&mut selected, &choices, "food", true);
+        assert_eq!(selected, None);
+    }
+}
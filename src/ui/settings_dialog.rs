@@ -0,0 +1,287 @@
+use eframe::egui;
+
+use crate::app::{GuardedAction, PreftApp};
+use crate::locale::NumberFormat;
+use crate::settings::{ConfirmationSettings, StartupView, Theme, UserSettings, DEFAULT_AUTO_LOCK_MINUTES, UI_SCALE_RANGE};
+use crate::shortcuts::{self, ShortcutAction};
+
+/// Display preferences. Changes apply (and are saved) immediately.
+pub fn show_settings_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_settings_dialog;
+    let mut changed = false;
+
+    egui::Window::new("Settings")
+        .open(&mut show_window)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.heading("Appearance");
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                egui::ComboBox::from_id_source("theme")
+                    .selected_text(app.user_settings.theme.get_display_name())
+                    .show_ui(ui, |ui| {
+                        for theme in Theme::ALL {
+                            changed |= ui.selectable_value(&mut app.user_settings.theme, theme, theme.get_display_name()).changed();
+                        }
+                    });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Open to:");
+                egui::ComboBox::from_id_source("startup_view")
+                    .selected_text(app.user_settings.startup_view.get_display_name())
+                    .show_ui(ui, |ui| {
+                        for view in StartupView::ALL {
+                            changed |= ui.selectable_value(&mut app.user_settings.startup_view, view, view.get_display_name()).changed();
+                        }
+                    });
+            }).response.on_hover_text("What preft shows when it starts. Without a last-used category, it opens to the dashboard.");
+
+            ui.horizontal(|ui| {
+                ui.label("UI scale:");
+                // Applied once the slider is let go, so it doesn't slide out
+                // from under the pointer while it rescales. Saved by
+                // `PreftApp::remember_ui_scale` once it takes effect.
+                let mut scale = app.user_settings.get_ui_scale();
+                let response = ui.add(egui::Slider::new(&mut scale, UI_SCALE_RANGE).step_by(0.05).custom_formatter(|scale, _| format!("{:.0}%", scale * 100.0)));
+                if response.changed() {
+                    app.user_settings.ui_scale = Some(scale);
+                }
+                if response.drag_released() || (response.changed() && !response.dragged()) {
+                    ctx.set_zoom_factor(scale);
+                }
+                if ui.button("Reset").clicked() {
+                    app.user_settings.ui_scale = None;
+                    ctx.set_zoom_factor(1.0);
+                }
+            }).response.on_hover_text("Also changed with Ctrl + and Ctrl -.");
+
+            ui.separator();
+            ui.heading("Number Format");
+            changed |= show_number_format_settings(ui, &mut app.user_settings.number_format);
+
+            ui.separator();
+            ui.heading("Dashboard");
+            ui.horizontal(|ui| {
+                ui.label("Recent flows shown:");
+                let mut count = app.user_settings.get_recent_flows_count();
+                if ui.add(egui::DragValue::new(&mut count).clamp_range(1..=100)).changed() {
+                    app.user_settings.recent_flows_count = Some(count);
+                    changed = true;
+                }
+            });
+
+            ui.separator();
+            ui.heading("Watch Folder");
+            show_watch_folder_settings(ui, app);
+
+            ui.separator();
+            ui.heading("Saving");
+            show_deferred_writes_setting(ui, app);
+
+            ui.separator();
+            ui.heading("Security");
+            show_strict_mode_setting(ui, app);
+            changed |= show_auto_lock_setting(ui, app);
+
+            ui.separator();
+            ui.heading("Confirmations");
+            changed |= show_confirmation_settings(ui, &mut app.user_settings.confirmations);
+
+            ui.separator();
+            ui.heading("Keyboard Shortcuts");
+            changed |= show_shortcut_settings(ui, &mut app.user_settings);
+        });
+
+    if changed {
+        app.save_settings("save settings");
+    }
+
+    app.show_settings_dialog = show_window;
+}
+
+/// The watch folder is saved through `PreftApp::set_watch_folder` rather
+/// than the `changed` flag, since it also triggers an immediate scan.
+fn show_watch_folder_settings(ui: &mut egui::Ui, app: &mut PreftApp) {
+    ui.label(format!(
+        "CSV and OFX/QFX files saved here are imported automatically, then moved into its \"{}\" or \"{}\" subfolder.",
+        crate::watch_folder::IMPORTED_DIR,
+        crate::watch_folder::FAILED_DIR
+    ));
+    ui.horizontal(|ui| {
+        match &app.user_settings.watch_folder {
+            Some(folder) => ui.label(folder),
+            None => ui.label("Not watching a folder"),
+        };
+        if ui.button("Choose Folder...").clicked()
+            && let Some(folder) = rfd::FileDialog::new()
+                .set_title("Select Watch Folder")
+                .pick_folder()
+        {
+            app.set_watch_folder(Some(folder.to_string_lossy().to_string()));
+        }
+        if app.user_settings.watch_folder.is_some() && ui.button("Stop Watching").clicked() {
+            app.set_watch_folder(None);
+        }
+    });
+}
+
+/// Turning deferred writes off with changes still pending would leave them
+/// in limbo, so the checkbox is locked until they are saved or discarded.
+fn show_deferred_writes_setting(ui: &mut egui::Ui, app: &mut PreftApp) {
+    let has_pending = !app.pending_changes.is_empty();
+    let mut deferred = app.user_settings.deferred_writes;
+    let checkbox = ui.add_enabled(
+        !(deferred && has_pending),
+        egui::Checkbox::new(&mut deferred, "Review changes before saving"),
+    ).on_hover_text("Flow edits wait in a pending list until you choose Save All");
+    if checkbox.changed() {
+        app.user_settings.deferred_writes = deferred;
+        app.save_settings("save settings");
+    }
+    if app.user_settings.deferred_writes && has_pending {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} change(s) pending.", app.pending_changes.len()));
+            if ui.button("Review").clicked() {
+                app.show_pending_changes_panel = true;
+            }
+        });
+    }
+}
+
+/// Turning strict mode off goes through the password itself, so it can't be
+/// used to get around strict mode.
+fn show_strict_mode_setting(ui: &mut egui::Ui, app: &mut PreftApp) {
+    let mut strict = app.user_settings.strict_mode;
+    let checkbox = ui.add_enabled(
+        app.encryption_config.is_encryption_ready(),
+        egui::Checkbox::new(&mut strict, "Strict mode"),
+    )
+        .on_hover_text("Ask for the database password again before deleting a category, deleting several flows at once or restoring a backup")
+        .on_disabled_hover_text("Set a database password first");
+    if !checkbox.changed() {
+        return;
+    }
+    if strict {
+        app.user_settings.strict_mode = true;
+        app.save_settings("save settings");
+    } else {
+        app.request_guarded_action(GuardedAction::TurnOffStrictMode);
+    }
+}
+
+/// Returns whether it was changed.
+fn show_auto_lock_setting(ui: &mut egui::Ui, app: &mut PreftApp) -> bool {
+    let mut changed = false;
+    ui.add_enabled_ui(app.encryption_config.is_encryption_ready(), |ui| {
+        ui.horizontal(|ui| {
+            let mut enabled = app.user_settings.auto_lock_minutes.is_some();
+            if ui.checkbox(&mut enabled, "Lock after").changed() {
+                app.user_settings.auto_lock_minutes = enabled.then_some(DEFAULT_AUTO_LOCK_MINUTES);
+                changed = true;
+            }
+            let mut minutes = app.user_settings.auto_lock_minutes.unwrap_or(DEFAULT_AUTO_LOCK_MINUTES);
+            if ui.add_enabled(enabled, egui::DragValue::new(&mut minutes).clamp_range(1..=240).suffix(" min")).changed() {
+                app.user_settings.auto_lock_minutes = Some(minutes);
+                changed = true;
+            }
+            ui.label("without input");
+        });
+    }).response
+        .on_hover_text("Hide everything until the database password is entered again")
+        .on_disabled_hover_text("Set a database password first");
+    changed
+}
+
+/// Returns whether any were changed. Strict mode asks for the password
+/// either way.
+fn show_confirmation_settings(ui: &mut egui::Ui, confirmations: &mut ConfirmationSettings) -> bool {
+    let mut changed = false;
+    ui.label("Ask before:");
+    changed |= ui.checkbox(&mut confirmations.delete_flow, "Deleting a flow").changed();
+    changed |= ui.checkbox(&mut confirmations.delete_selected_flows, "Deleting selected flows").changed();
+    changed |= ui.checkbox(&mut confirmations.hide_category, "Hiding a category").changed();
+    changed |= ui.checkbox(&mut confirmations.delete_category, "Deleting a category").changed();
+    ui.indent("type_category_name_to_delete", |ui| {
+        ui.add_enabled_ui(confirmations.delete_category, |ui| {
+            changed |= ui.checkbox(&mut confirmations.type_category_name_to_delete, "By typing its name").changed();
+        });
+    });
+    changed
+}
+
+/// Returns whether `format` was changed.
+fn show_number_format_settings(ui: &mut egui::Ui, format: &mut NumberFormat) -> bool {
+    let mut changed = false;
+    let presets = NumberFormat::presets();
+    let current = presets.iter()
+        .find(|(_, preset)| preset == format)
+        .map_or("Custom", |(name, _)| name);
+
+    egui::Grid::new("number_format_settings").show(ui, |ui| {
+        ui.label("Preset:");
+        egui::ComboBox::from_id_source("number_format_preset")
+            .selected_text(current)
+            .show_ui(ui, |ui| {
+                for (name, preset) in &presets {
+                    if ui.selectable_label(preset == format, *name).clicked() {
+                        *format = preset.clone();
+                        changed = true;
+                    }
+                }
+            });
+        ui.end_row();
+
+        ui.label("Currency symbol:");
+        changed |= ui.text_edit_singleline(&mut format.currency_symbol).changed();
+        ui.end_row();
+
+        ui.label("");
+        changed |= ui.checkbox(&mut format.symbol_after, "Symbol after the amount").changed();
+        ui.end_row();
+
+        ui.label("Thousands separator:");
+        changed |= ui.text_edit_singleline(&mut format.thousands_separator).changed();
+        ui.end_row();
+
+        ui.label("Decimal separator:");
+        ui.horizontal(|ui| {
+            changed |= ui.radio_value(&mut format.decimal_separator, '.', "Point (.)").changed();
+            changed |= ui.radio_value(&mut format.decimal_separator, ',', "Comma (,)").changed();
+        });
+        ui.end_row();
+
+        ui.label("Example:");
+        ui.label(format!("{}   {}", format.format_currency(1234567.89), format.format_accounting(-42.5)));
+        ui.end_row();
+    });
+
+    changed
+}
+
+/// A binding that doesn't parse is kept (so it can be finished) but does
+/// nothing until it's fixed. Returns whether any binding was changed.
+fn show_shortcut_settings(ui: &mut egui::Ui, settings: &mut UserSettings) -> bool {
+    let mut changed = false;
+    egui::Grid::new("shortcut_settings").show(ui, |ui| {
+        for action in ShortcutAction::ALL {
+            ui.label(format!("{}:", action.get_display_name()));
+            let mut binding = settings.get_shortcut(action).to_string();
+            if ui.add(egui::TextEdit::singleline(&mut binding).desired_width(120.0)).changed() {
+                settings.set_shortcut(action, binding.clone());
+                changed = true;
+            }
+            if binding != action.default_binding()
+                && ui.small_button("Reset").on_hover_text(action.default_binding()).clicked()
+            {
+                settings.set_shortcut(action, action.default_binding().to_string());
+                changed = true;
+            }
+            if let Err(e) = shortcuts::parse_binding(action, &binding) {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            ui.end_row();
+        }
+    });
+    changed
+}
@@ -0,0 +1,35 @@
+use eframe::egui;
+
+/// Size of a sparkline, roughly one line of text tall.
+const SPARKLINE_SIZE: egui::Vec2 = egui::vec2(80.0, 18.0);
+
+/// Draws `values` as a small line with no axes, marking the last point, and
+/// returns its response so callers can attach hover text. Nothing is drawn
+/// for fewer than two values.
+pub fn sparkline(ui: &mut egui::Ui, values: &[f64]) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(SPARKLINE_SIZE, egui::Sense::hover());
+    if values.len() < 2 || !ui.is_rect_visible(rect) {
+        return response;
+    }
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    // A flat series sits in the middle rather than along an edge.
+    let span = if max > min { max - min } else { 1.0 };
+    let offset = if max > min { 0.0 } else { 0.5 };
+    let rect = rect.shrink(2.0);
+    let points: Vec<egui::Pos2> = values.iter().enumerate()
+        .map(|(i, value)| {
+            let x = i as f32 / (values.len() - 1) as f32;
+            let y = ((value - min) / span + offset) as f32;
+            egui::pos2(rect.left() + x * rect.width(), rect.bottom() - y * rect.height())
+        })
+        .collect();
+
+    let color = ui.visuals().hyperlink_color;
+    let painter = ui.painter();
+    let last = *points.last().expect("at least two points");
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+    painter.circle_filled(last, 2.0, color);
+    response
+}
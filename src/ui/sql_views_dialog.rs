@@ -0,0 +1,250 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+use crate::db::SqlViewRows;
+use crate::db_worker::Pending;
+use crate::models::SqlView;
+
+/// How many rows the preview shows; exports always include every row.
+const PREVIEW_ROWS: usize = 200;
+
+/// The view being added or edited and the last preview, kept until the
+/// window closes.
+#[derive(Default)]
+pub struct SqlViewsState {
+    /// Only written to the database on "Save".
+    pub draft: Option<SqlView>,
+    /// The name the draft was opened under, so renaming replaces that view
+    /// rather than adding a second one.
+    editing: Option<String>,
+    /// View name awaiting confirmation before it's deleted.
+    confirm_delete: Option<String>,
+    /// Why the draft couldn't be saved.
+    error: Option<String>,
+    /// The title and outcome of the last run.
+    preview: Option<(String, Result<SqlViewRows, String>)>,
+    /// A run still on the database thread, shown in place of the preview
+    /// until it comes back.
+    running: Option<(String, Pending<anyhow::Result<SqlViewRows>>)>,
+    /// Outcome of the last CSV export.
+    status: Option<Result<String, String>>,
+}
+
+impl SqlViewsState {
+    fn close_editor(&mut self) {
+        self.draft = None;
+        self.editing = None;
+        self.error = None;
+    }
+}
+
+/// The file a view's rows are exported to, e.g. "view-Fuel_by_month.csv"
+/// for "Fuel by month".
+pub fn csv_file_name(view_name: &str) -> String {
+    let stem: String = view_name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("view-{}.csv", stem)
+}
+
+pub fn show_sql_views_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_sql_views_dialog;
+    let mut run = None;
+    let mut export = None;
+    let mut save = None;
+    let mut close_editor = false;
+    let mut delete = None;
+    let read_only = app.read_only;
+    let state = &mut app.sql_views_state;
+
+    if let Some((_, pending)) = &state.running
+        && let Some(result) = pending.try_take()
+    {
+        let (title, _) = state.running.take().expect("checked above");
+        state.preview = Some((title, result.and_then(|rows| rows).map_err(|e| e.to_string())));
+    }
+
+    egui::Window::new("SQL Views")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_width(560.0)
+        .show(ctx, |ui| {
+            ui.label("A view is a named SELECT query over preft's database, for slices the reports don't offer. Views can only read data, and Export Everything includes each one as a CSV file.");
+            ui.label(egui::RichText::new("Tables include flows, categories, trips and locked_years. Custom field values are JSON in flows.custom_fields; read them with json_extract(custom_fields, '$.Vendor').").weak());
+            ui.separator();
+
+            if app.sql_views.is_empty() {
+                ui.label("No views yet.");
+            } else {
+                egui::Grid::new("sql_views").striped(true).show(ui, |ui| {
+                    for view in &app.sql_views {
+                        ui.label(&view.name);
+                        if ui.button("Run").clicked() {
+                            run = Some(view.clone());
+                        }
+                        if ui.button("Export CSV...").clicked() {
+                            export = Some(view.clone());
+                        }
+                        if !read_only {
+                            if ui.button("Edit").clicked() {
+                                state.draft = Some(view.clone());
+                                state.editing = Some(view.name.clone());
+                                state.error = None;
+                            }
+                            if state.confirm_delete.as_ref() == Some(&view.name) {
+                                if ui.button("Confirm Delete").clicked() {
+                                    delete = Some(view.name.clone());
+                                }
+                            } else if ui.button("Delete").clicked() {
+                                state.confirm_delete = Some(view.name.clone());
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            }
+
+            if !read_only {
+                ui.separator();
+                if let Some(draft) = &mut state.draft {
+                    egui::Grid::new("sql_view_draft").show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut draft.name);
+                        ui.end_row();
+                        ui.label("Query:");
+                        ui.add(egui::TextEdit::multiline(&mut draft.sql)
+                            .code_editor()
+                            .desired_rows(6)
+                            .desired_width(f32::INFINITY)
+                            .hint_text("SELECT date, amount, description FROM flows WHERE amount > 100"));
+                        ui.end_row();
+                    });
+                    let problem = if draft.name.trim().is_empty() {
+                        Some("Enter a name for the view")
+                    } else if draft.sql.trim().is_empty() {
+                        Some("Enter a query")
+                    } else {
+                        None
+                    };
+                    if let Some(message) = problem.or(state.error.as_deref()) {
+                        ui.colored_label(egui::Color32::RED, message);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!draft.sql.trim().is_empty(), egui::Button::new("Run")).clicked() {
+                            run = Some(draft.clone());
+                        }
+                        if ui.add_enabled(problem.is_none(), egui::Button::new("Save")).clicked() {
+                            save = Some(SqlView { name: draft.name.trim().to_string(), sql: draft.sql.trim().to_string() });
+                        }
+                        if ui.button("Cancel").clicked() {
+                            close_editor = true;
+                        }
+                    });
+                } else if ui.button("Add View").clicked() {
+                    state.draft = Some(SqlView { name: String::new(), sql: String::new() });
+                    state.editing = None;
+                    state.error = None;
+                }
+            }
+
+            match &state.status {
+                Some(Ok(message)) => {
+                    ui.colored_label(egui::Color32::GREEN, message);
+                }
+                Some(Err(message)) => {
+                    ui.colored_label(egui::Color32::RED, message);
+                }
+                None => {}
+            }
+
+            if let Some((title, _)) = &state.running {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Running {}...", title));
+                });
+            } else if let Some((title, result)) = &state.preview {
+                ui.separator();
+                ui.strong(title);
+                match result {
+                    Ok(rows) => show_preview(ui, rows),
+                    Err(message) => {
+                        ui.colored_label(egui::Color32::RED, message);
+                    }
+                }
+            }
+        });
+
+    if let Some(view) = run {
+        let title = if view.name.trim().is_empty() { "Preview".to_string() } else { view.name.clone() };
+        let pending = app.db.query(move |db| db.run_sql_view(&view.sql));
+        app.sql_views_state.running = Some((title, pending));
+    }
+    if let Some(view) = export
+        && let Some(path) = rfd::FileDialog::new()
+            .set_title("Export SQL View")
+            .set_file_name(csv_file_name(&view.name))
+            .add_filter("CSV", &["csv"])
+            .save_file()
+    {
+        let (sql, file) = (view.sql.clone(), path.clone());
+        let exported = app.db.query(move |db| db.run_sql_view(&sql).and_then(|rows| Ok(std::fs::write(&file, rows.to_csv())?)));
+        app.when_done(exported, move |app, result| {
+            app.sql_views_state.status = Some(match result.and_then(|result| result) {
+                Ok(()) => Ok(format!("Exported {} to {}", view.name, path.display())),
+                Err(e) => {
+                    log::error!("Failed to export SQL view '{}': {}", view.name, e);
+                    Err(format!("Export failed: {}", e))
+                }
+            });
+        });
+    }
+    if let Some(view) = save {
+        let previous = app.sql_views_state.editing.clone();
+        let name = view.name.clone();
+        app.save_sql_view(view, move |app, result| match result {
+            Ok(()) => {
+                if let Some(previous) = previous.filter(|previous| *previous != name) {
+                    app.delete_sql_view(&previous);
+                }
+                app.sql_views_state.close_editor();
+            }
+            Err(e) => app.sql_views_state.error = Some(e.to_string()),
+        });
+    }
+    if close_editor {
+        app.sql_views_state.close_editor();
+    }
+    if let Some(name) = delete {
+        app.delete_sql_view(&name);
+        app.sql_views_state.confirm_delete = None;
+    }
+    if !show_window {
+        app.sql_views_state = SqlViewsState::default();
+    }
+
+    app.show_sql_views_dialog = show_window;
+}
+
+/// A row count and the first `PREVIEW_ROWS` rows in a scrollable grid.
+fn show_preview(ui: &mut egui::Ui, rows: &SqlViewRows) {
+    if rows.rows.len() > PREVIEW_ROWS {
+        ui.label(format!("{} rows; showing the first {}.", rows.rows.len(), PREVIEW_ROWS));
+    } else {
+        ui.label(format!("{} row(s).", rows.rows.len()));
+    }
+    egui::ScrollArea::both().max_height(300.0).show(ui, |ui| {
+        egui::Grid::new("sql_view_preview").striped(true).show(ui, |ui| {
+            for column in &rows.columns {
+                ui.strong(column);
+            }
+            ui.end_row();
+            for row in rows.rows.iter().take(PREVIEW_ROWS) {
+                for value in row {
+                    ui.label(value);
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
@@ -0,0 +1,173 @@
+use eframe::egui;
+
+use crate::app::{PreftApp, Stored};
+use crate::models::Trip;
+use crate::reporting::{ReportKind, TimePeriod};
+
+/// The trip being added or edited, only written to the database on "Save".
+#[derive(Default)]
+pub struct TripsState {
+    pub draft: Option<Trip>,
+    /// Trip id awaiting confirmation before it's deleted.
+    pub confirm_delete: Option<String>,
+}
+
+pub fn show_trips_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_trips_dialog;
+    let mut save = None;
+    let mut close_editor = false;
+    let mut delete = None;
+    let mut report = None;
+    // A trip's flows can be in any category and year, so the database
+    // totals them
+    let totals = match app.all_time_totals.trips() {
+        Stored::Ready(totals) => Some(Some(totals.clone())),
+        Stored::Loading => None,
+        Stored::Unavailable => Some(None),
+    };
+    let number_format = app.user_settings.number_format.clone();
+    let read_only = app.read_only;
+    let state = &mut app.trips_state;
+
+    egui::Window::new("Trips")
+        .open(&mut show_window)
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.label("Assign flows to a trip in the flow editor to see what each trip cost.");
+            ui.separator();
+
+            if app.trips.is_empty() {
+                ui.label("No trips yet.");
+            } else {
+                egui::Grid::new("trips").striped(true).show(ui, |ui| {
+                    ui.strong("Trip");
+                    ui.strong("Dates");
+                    ui.strong("Flows");
+                    ui.strong("Income");
+                    ui.strong("Expenses");
+                    ui.end_row();
+
+                    for trip in &app.trips {
+                        ui.label(&trip.name);
+                        ui.label(format!("{} to {}", trip.start_date, trip.end_date));
+                        match &totals {
+                            Some(Some(totals)) => {
+                                let trip_totals = totals.get(&trip.id).copied().unwrap_or_default();
+                                ui.label(trip_totals.flow_count.to_string());
+                                ui.label(number_format.format_currency(trip_totals.income));
+                                ui.label(number_format.format_currency(trip_totals.expenses));
+                            }
+                            Some(None) => {
+                                ui.label("—");
+                                ui.label("—");
+                                ui.label("—");
+                            }
+                            None => {
+                                ui.spinner();
+                                ui.spinner();
+                                ui.spinner();
+                            }
+                        }
+                        if ui.button("Report...").on_hover_text("Open the report dialog for this trip's flows").clicked() {
+                            report = Some(trip.clone());
+                        }
+                        if !read_only {
+                            if ui.button("Edit").clicked() {
+                                state.draft = Some(trip.clone());
+                            }
+                            if state.confirm_delete.as_ref() == Some(&trip.id) {
+                                if ui.button("Confirm Delete").on_hover_text("Its flows are kept but no longer on a trip").clicked() {
+                                    delete = Some(trip.id.clone());
+                                }
+                            } else if ui.button("Delete").clicked() {
+                                state.confirm_delete = Some(trip.id.clone());
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            }
+
+            if read_only {
+                return;
+            }
+            ui.separator();
+
+            let Some(draft) = &mut state.draft else {
+                if ui.button("Add Trip").clicked() {
+                    let today = chrono::Local::now().date_naive();
+                    state.draft = Some(Trip {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        name: String::new(),
+                        start_date: today,
+                        end_date: today,
+                    });
+                }
+                return;
+            };
+
+            egui::Grid::new("trip_draft").show(ui, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut draft.name);
+                ui.end_row();
+                ui.label("From:");
+                ui.add(egui_extras::DatePickerButton::new(&mut draft.start_date).id_source("trip_start"));
+                ui.end_row();
+                ui.label("To:");
+                ui.add(egui_extras::DatePickerButton::new(&mut draft.end_date).id_source("trip_end"));
+                ui.end_row();
+            });
+
+            let problem = if draft.name.trim().is_empty() {
+                Some("Enter a name for the trip")
+            } else if draft.end_date < draft.start_date {
+                Some("The trip can't end before it starts")
+            } else {
+                None
+            };
+            if let Some(problem) = problem {
+                ui.colored_label(egui::Color32::RED, problem);
+            }
+            ui.horizontal(|ui| {
+                if ui.add_enabled(problem.is_none(), egui::Button::new("Save")).clicked() {
+                    save = Some(Trip { name: draft.name.trim().to_string(), ..draft.clone() });
+                }
+                if ui.button("Cancel").clicked() {
+                    close_editor = true;
+                }
+            });
+        });
+
+    if let Some(trip) = save {
+        app.save_trip(trip);
+        close_editor = true;
+    }
+    if close_editor {
+        app.trips_state.draft = None;
+    }
+    if let Some(trip_id) = delete {
+        app.delete_trip(&trip_id);
+        app.trips_state.confirm_delete = None;
+        if app.trips_state.draft.as_ref().is_some_and(|t| t.id == trip_id) {
+            app.trips_state.draft = None;
+        }
+    }
+    if let Some(trip) = report {
+        // Flows on a trip are often booked before it starts, so the period
+        // stretches to cover every flow assigned to it (once they're totaled).
+        let trip_totals = totals.flatten().and_then(|totals| totals.get(&trip.id).copied()).unwrap_or_default();
+        let start = trip_totals.first.map_or(trip.start_date, |d| d.min(trip.start_date));
+        let end = trip_totals.last.map_or(trip.end_date, |d| d.max(trip.end_date));
+        app.report_request.kind = ReportKind::Flows;
+        app.report_request.time_period = TimePeriod::Custom(start, end);
+        app.report_request.trip_id = Some(trip.id.clone());
+        app.report_request.title = format!("{} Expenses", trip.name);
+        app.report_request.subtitle = format!("{} to {}", trip.start_date, trip.end_date);
+        app.show_report_dialog = true;
+    }
+    if !show_window {
+        app.trips_state = TripsState::default();
+    }
+
+    app.show_trips_dialog = show_window;
+}
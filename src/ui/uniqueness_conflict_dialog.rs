@@ -0,0 +1,80 @@
+use eframe::egui;
+use chrono::Datelike;
+
+use crate::app::PreftApp;
+use crate::models::Flow;
+
+/// A save refused because another flow already has the same values for
+/// every field in one of the category's uniqueness rules.
+#[derive(Debug, Clone)]
+pub struct UniquenessConflict {
+    pub existing: Flow,
+    pub fields: Vec<String>,
+}
+
+/// Explains the conflict and offers to switch to editing the existing
+/// flow. The flow being saved stays open in the editor either way, until
+/// it's changed or cancelled.
+pub fn show_uniqueness_conflict_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let Some(conflict) = app.uniqueness_conflict.as_ref() else {
+        return;
+    };
+    let category = app.categories.iter().find(|c| c.id == conflict.existing.category_id).cloned();
+    let field_label = |name: &str| category.as_ref()
+        .and_then(|c| c.fields.iter().find(|f| f.name == name))
+        .map_or_else(|| name.to_string(), |f| f.display_name());
+    let can_edit = !app.read_only && !app.is_year_locked(conflict.existing.date.year());
+
+    let mut show_window = true;
+    let mut edit_existing = false;
+    let mut keep_editing = false;
+
+    egui::Window::new("Duplicate Flow")
+        .open(&mut show_window)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let names: Vec<String> = conflict.fields.iter().map(|name| field_label(name)).collect();
+            ui.label(format!(
+                "{} must be unique together in {}, and another flow already has these values:",
+                names.join(" + "),
+                category.as_ref().map_or("this category", |c| c.name.as_str())
+            ));
+            egui::Grid::new("uniqueness_conflict_values").show(ui, |ui| {
+                for (name, label) in conflict.fields.iter().zip(&names) {
+                    ui.strong(label);
+                    ui.label(conflict.existing.custom_fields.get(name).map_or("", |value| value.as_str()));
+                    ui.end_row();
+                }
+            });
+            ui.label(format!(
+                "Existing flow: {} - {} ({})",
+                conflict.existing.date,
+                conflict.existing.description,
+                app.user_settings.number_format.format_currency(conflict.existing.amount)
+            ));
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_edit, egui::Button::new("Edit Existing Flow"))
+                    .on_hover_text("Discard this entry and open the existing flow instead")
+                    .on_disabled_hover_text("The existing flow is in a locked year")
+                    .clicked()
+                {
+                    edit_existing = true;
+                }
+                if ui.button("Keep Editing").clicked() {
+                    keep_editing = true;
+                }
+            });
+        });
+
+    if edit_existing && let Some(conflict) = app.uniqueness_conflict.take() && let Some(category) = category {
+        app.cancel_flow_edit();
+        app.selected_category = Some(category.id.clone());
+        app.start_editing_flow(conflict.existing, &category);
+    }
+    if keep_editing || !show_window {
+        app.uniqueness_conflict = None;
+    }
+}
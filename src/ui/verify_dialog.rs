@@ -0,0 +1,72 @@
+use eframe::egui;
+
+use crate::app::PreftApp;
+
+/// Shows the results of the last `PreftApp::verify_cached_state` run. The
+/// check itself (and the rebuild that follows it) runs when the "Verify Data"
+/// button is clicked; this just reports it, with a way to run it again.
+/// Problems the idle-time integrity check found are listed below it.
+pub fn show_verify_dialog(ctx: &egui::Context, app: &mut PreftApp) {
+    let mut show_window = app.show_verify_dialog;
+    let mut run_again = false;
+
+    egui::Window::new("Verify Data")
+        .open(&mut show_window)
+        .resizable(true)
+        .default_size([500.0, 300.0])
+        .show(ctx, |ui| {
+            match &app.verification_results {
+                _ if app.verifying => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Checking...");
+                    });
+                }
+                Some(found) if found.is_empty() => {
+                    ui.label(egui::RichText::new("All cached totals match the database.").color(egui::Color32::GREEN));
+                }
+                Some(found) => {
+                    ui.label(egui::RichText::new(format!("Found {} discrepancies:", found.len())).color(egui::Color32::RED));
+                    egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                        for discrepancy in found {
+                            ui.label(discrepancy);
+                        }
+                    });
+                    ui.label("All cached state has been rebuilt from the database.");
+                }
+                None => {
+                    ui.label("Not run yet.");
+                }
+            }
+
+            ui.separator();
+            ui.strong("Stored Data");
+            match &app.integrity_problems {
+                Some(problems) if problems.is_empty() => {
+                    ui.label(egui::RichText::new("All stored custom fields, links and category fields parse.").color(egui::Color32::GREEN));
+                }
+                Some(problems) => {
+                    ui.label(egui::RichText::new(format!("Found {} problem(s) that would stop these rows loading:", problems.len())).color(egui::Color32::RED));
+                    egui::ScrollArea::vertical().id_source("integrity_problems").max_height(150.0).show(ui, |ui| {
+                        for problem in problems {
+                            ui.label(problem);
+                        }
+                    });
+                }
+                None => {
+                    ui.label("Checked in the background while preft is idle; not run yet.");
+                }
+            }
+
+            ui.separator();
+            if ui.add_enabled(!app.verifying, egui::Button::new("Run Again")).clicked() {
+                run_again = true;
+            }
+        });
+
+    if run_again {
+        app.verify_cached_state();
+    }
+
+    app.show_verify_dialog = show_window;
+}